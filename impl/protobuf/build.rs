@@ -1,4 +1,30 @@
+use std::path::PathBuf;
+use std::process::Command;
+
+const PROTOS: &[&str] = &[
+    "proto/stip.proto",
+    "proto/grpc/health/v1/health.proto",
+    "proto/grpc/reflection/v1alpha/reflection.proto",
+];
+
 fn main() -> Result<(), Box<dyn std::error::Error>> {
-    tonic_build::compile_protos("proto/stip.proto")?;
+    for proto in PROTOS {
+        tonic_build::compile_protos(proto)?;
+    }
+
+    // write a descriptor set of every service for server reflection
+    let out_dir = PathBuf::from(std::env::var("OUT_DIR")?);
+    let status = Command::new(prost_build::protoc())
+        .arg("--include_imports")
+        .arg("--proto_path=proto")
+        .arg(format!("--descriptor_set_out={}",
+            out_dir.join("descriptor.bin").display()))
+        .args(PROTOS)
+        .status()?;
+
+    if !status.success() {
+        return Err(format!("protoc failed with {}", status).into());
+    }
+
     Ok(())
 }
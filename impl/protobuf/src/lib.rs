@@ -2,13 +2,38 @@ mod stip {
     tonic::include_proto!("stip");
 }
 
+pub mod health {
+    tonic::include_proto!("grpc.health.v1");
+
+    pub use health_client::HealthClient;
+    pub use health_server::{Health, HealthServer};
+}
+
+pub mod reflection {
+    tonic::include_proto!("grpc.reflection.v1alpha");
+
+    pub use server_reflection_server::{ServerReflection,
+        ServerReflectionServer};
+}
+
+// encoded FileDescriptorSet of every service for server reflection
+pub const FILE_DESCRIPTOR_SET: &[u8] =
+    include_bytes!(concat!(env!("OUT_DIR"), "/descriptor.bin"));
+
+mod token;
+pub use token::{set_header_fn, TokenChannel};
+
 pub use stip::*;
 pub use stip::album_management_client::AlbumManagementClient;
 pub use stip::album_management_server::{AlbumManagement, AlbumManagementServer};
+pub use stip::filter_management_client::FilterManagementClient;
+pub use stip::filter_management_server::{FilterManagement, FilterManagementServer};
 pub use stip::image_management_client::ImageManagementClient;
 pub use stip::image_management_server::{ImageManagement, ImageManagementServer};
 pub use stip::node_management_client::NodeManagementClient;
 pub use stip::node_management_server::{NodeManagement, NodeManagementServer};
+pub use stip::task_executor_client::TaskExecutorClient;
+pub use stip::task_executor_server::{TaskExecutor, TaskExecutorServer};
 pub use stip::task_management_client::TaskManagementClient;
 pub use stip::task_management_server::{TaskManagement, TaskManagementServer};
 
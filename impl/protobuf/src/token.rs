@@ -0,0 +1,63 @@
+use tonic::body::BoxBody;
+use tonic::codegen::{http, Context, Poll, Service};
+use tonic::transport::Channel;
+
+use std::error::Error;
+use std::sync::RwLock;
+
+type ChannelRequest = http::Request<BoxBody>;
+
+// hook adding headers, such as trace context, to every request sent
+static HEADER_FN: RwLock<Option<fn(&mut http::HeaderMap)>> =
+    RwLock::new(None);
+
+pub fn set_header_fn(header_fn: fn(&mut http::HeaderMap)) {
+    *HEADER_FN.write().unwrap() = Some(header_fn);
+}
+
+// channel attaching a bearer token to every request it sends
+#[derive(Clone, Debug)]
+pub struct TokenChannel {
+    channel: Channel,
+    token: Option<http::HeaderValue>,
+}
+
+impl TokenChannel {
+    pub fn new(channel: Channel, token: Option<&str>)
+            -> Result<TokenChannel, Box<dyn Error>> {
+        let token = match token {
+            Some(token) => Some(http::HeaderValue::from_str(
+                &format!("Bearer {}", token))?),
+            None => None,
+        };
+
+        Ok(TokenChannel {
+            channel: channel,
+            token: token,
+        })
+    }
+}
+
+impl Service<ChannelRequest> for TokenChannel {
+    type Response = <Channel as Service<ChannelRequest>>::Response;
+    type Error = <Channel as Service<ChannelRequest>>::Error;
+    type Future = <Channel as Service<ChannelRequest>>::Future;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>)
+            -> Poll<Result<(), Self::Error>> {
+        self.channel.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: ChannelRequest) -> Self::Future {
+        if let Some(token) = &self.token {
+            request.headers_mut().insert(http::header::AUTHORIZATION,
+                token.clone());
+        }
+
+        if let Some(header_fn) = *HEADER_FN.read().unwrap() {
+            header_fn(request.headers_mut());
+        }
+
+        self.channel.call(request)
+    }
+}
@@ -1,22 +1,35 @@
 use clap::ArgMatches;
-use protobuf::{AlbumBroadcastRequest, AlbumBroadcastType, AlbumCloseRequest, AlbumCreateRequest, AlbumDeleteRequest, AlbumListRequest, AlbumManagementClient, AlbumOpenRequest, AlbumStatus, Geocode};
+use protobuf::{AlbumBroadcastRequest, AlbumBroadcastType, AlbumCloseRequest, AlbumCollectRequest, AlbumCreateRequest, AlbumDeleteRequest, AlbumExportRequest, AlbumHoldRequest, AlbumImportRequest, AlbumListRequest, AlbumManagementClient, AlbumOpenRequest, AlbumReindexRequest, AlbumRetentionRequest, AlbumStatus, Filter, Geocode};
 use tonic::Request;
 
 use std::{error, io};
+use std::time::Duration;
 
 pub fn process(matches: &ArgMatches, album_matches: &ArgMatches) {
     let result: Result<(), Box<dyn error::Error>> 
             = match album_matches.subcommand() {
         ("close", Some(close_matches)) =>
             close(&matches, &album_matches, &close_matches),
+        ("collect", Some(collect_matches)) =>
+            collect(&matches, &album_matches, &collect_matches),
         ("create", Some(create_matches)) =>
             create(&matches, &album_matches, &create_matches),
         ("delete", Some(delete_matches)) =>
             delete(&matches, &album_matches, &delete_matches),
+        ("export", Some(export_matches)) =>
+            export(&matches, &album_matches, &export_matches),
+        ("hold", Some(hold_matches)) =>
+            hold(&matches, &album_matches, &hold_matches),
+        ("import", Some(import_matches)) =>
+            import(&matches, &album_matches, &import_matches),
         ("list", Some(list_matches)) =>
             list(&matches, &album_matches, &list_matches),
         ("open", Some(open_matches)) =>
             open(&matches, &album_matches, &open_matches),
+        ("reindex", Some(reindex_matches)) =>
+            reindex(&matches, &album_matches, &reindex_matches),
+        ("retention", Some(retention_matches)) =>
+            retention(&matches, &album_matches, &retention_matches),
         (cmd, _) => Err(Box::new(io::Error::new(io::ErrorKind::Other,
             format!("unknown subcommand '{}'", cmd)))),
     };
@@ -32,8 +45,8 @@ async fn close(matches: &ArgMatches, _: &ArgMatches,
     // initialize grpc client
     let ip_address = matches.value_of("ip_address").unwrap();
     let port = matches.value_of("port").unwrap().parse::<u16>()?;
-    let mut client = AlbumManagementClient::connect(
-        format!("http://{}:{}", ip_address, port)).await?;
+    let mut client = AlbumManagementClient::new(crate::channel(matches,
+        &format!("{}:{}", ip_address, port)).await?);
 
     // initialize request
     let close_request = AlbumCloseRequest {
@@ -46,6 +59,10 @@ async fn close(matches: &ArgMatches, _: &ArgMatches,
         close_request: Some(close_request),
         delete_request: None,
         open_request: None,
+        reindex_request: None,
+        hold_request: None,
+        retention_request: None,
+        collect_request: None,
     });
 
     // retrieve reply
@@ -55,35 +72,81 @@ async fn close(matches: &ArgMatches, _: &ArgMatches,
 }
 
 #[tokio::main]
-async fn create(matches: &ArgMatches, _: &ArgMatches,
-        create_matches: &ArgMatches) -> Result<(), Box<dyn error::Error>> {
+async fn collect(matches: &ArgMatches, _: &ArgMatches,
+        collect_matches: &ArgMatches) -> Result<(), Box<dyn error::Error>> {
     // initialize grpc client
     let ip_address = matches.value_of("ip_address").unwrap();
     let port = matches.value_of("port").unwrap().parse::<u16>()?;
-    let mut client = AlbumManagementClient::connect(
-        format!("http://{}:{}", ip_address, port)).await?;
+    let mut client = AlbumManagementClient::new(crate::channel(matches,
+        &format!("{}:{}", ip_address, port)).await?);
 
-    // parse arguments
-    let geocode = match create_matches.value_of("GEOCODE") {
-        Some("geohash") => Geocode::Geohash as i32,
-        Some("quadtile") => Geocode::Quadtile as i32,
-        _ => unimplemented!(),
+    // initialize request
+    let dry_run = match collect_matches.is_present("dry_run") {
+        true => Some(true),
+        false => None,
     };
 
-    // initialize request
-    let create_request = AlbumCreateRequest {
-        dht_key_length: create_matches.value_of("dht_key_length")
-            .unwrap().parse::<i32>()?,
-        geocode: geocode,
-        id: create_matches.value_of("ID").unwrap().to_string(),
+    let collect_request = AlbumCollectRequest {
+        dry_run: dry_run,
+        id: collect_matches.value_of("ID").unwrap().to_string(),
+        priority: crate::u32_opt(collect_matches.value_of("priority"))?,
+        task_id: crate::u64_opt(collect_matches.value_of("task_id"))?,
+        thread_count: collect_matches.value_of("thread_count")
+            .unwrap().parse::<u32>()?,
     };
 
+    let request = Request::new(AlbumBroadcastRequest {
+        message_type: AlbumBroadcastType::AlbumCollect as i32,
+        create_request: None,
+        close_request: None,
+        delete_request: None,
+        open_request: None,
+        reindex_request: None,
+        hold_request: None,
+        retention_request: None,
+        collect_request: Some(collect_request),
+    });
+
+    // retrieve reply
+    let reply = client.broadcast(request).await?;
+    let reply = reply.get_ref();
+
+    // print information
+    for (node_id, collect_reply) in reply.collect_replies.iter() {
+        match (collect_reply.file_count, collect_reply.byte_count) {
+            (Some(file_count), Some(byte_count)) => println!(
+                "dry run on node '{}': {} file(s), {} byte(s)",
+                node_id, file_count, byte_count),
+            _ => println!("task starting on node '{}' with id '{}'",
+                node_id, collect_reply.task_id),
+        }
+    }
+
+    Ok(())
+}
+
+#[tokio::main]
+async fn create(matches: &ArgMatches, _: &ArgMatches,
+        create_matches: &ArgMatches) -> Result<(), Box<dyn error::Error>> {
+    // initialize grpc client
+    let ip_address = matches.value_of("ip_address").unwrap();
+    let port = matches.value_of("port").unwrap().parse::<u16>()?;
+    let mut client = AlbumManagementClient::new(crate::channel(matches,
+        &format!("{}:{}", ip_address, port)).await?);
+
+    // initialize request
+    let create_request = parse_create_request(create_matches)?;
+
     let request = Request::new(AlbumBroadcastRequest {
         message_type: AlbumBroadcastType::AlbumCreate as i32,
         create_request: Some(create_request),
         close_request: None,
         delete_request: None,
         open_request: None,
+        reindex_request: None,
+        hold_request: None,
+        retention_request: None,
+        collect_request: None,
     });
 
     // retrieve reply
@@ -98,8 +161,8 @@ async fn delete(matches: &ArgMatches, _: &ArgMatches,
     // initialize grpc client
     let ip_address = matches.value_of("ip_address").unwrap();
     let port = matches.value_of("port").unwrap().parse::<u16>()?;
-    let mut client = AlbumManagementClient::connect(
-        format!("http://{}:{}", ip_address, port)).await?;
+    let mut client = AlbumManagementClient::new(crate::channel(matches,
+        &format!("{}:{}", ip_address, port)).await?);
 
     // initialize request
     let delete_request = AlbumDeleteRequest {
@@ -112,6 +175,10 @@ async fn delete(matches: &ArgMatches, _: &ArgMatches,
         close_request: None,
         delete_request: Some(delete_request),
         open_request: None,
+        reindex_request: None,
+        hold_request: None,
+        retention_request: None,
+        collect_request: None,
     });
 
     // retrieve reply
@@ -120,14 +187,135 @@ async fn delete(matches: &ArgMatches, _: &ArgMatches,
     Ok(())
 }
 
+#[tokio::main]
+async fn export(matches: &ArgMatches, _: &ArgMatches,
+        export_matches: &ArgMatches) -> Result<(), Box<dyn error::Error>> {
+    // initialize grpc client
+    let ip_address = matches.value_of("ip_address").unwrap();
+    let port = matches.value_of("port").unwrap().parse::<u16>()?;
+    let mut client = AlbumManagementClient::new(crate::channel(matches,
+        &format!("{}:{}", ip_address, port)).await?);
+
+    // initialize Filter
+    let filter = Filter {
+        end_timestamp: crate::i64_opt(
+            export_matches.value_of("end_timestamp"))?,
+        geocode: crate::string_opt(export_matches.value_of("geocode")),
+        max_cloud_coverage: crate::f64_opt(
+            export_matches.value_of("max_cloud_coverage"))?,
+        min_pixel_coverage: crate::f64_opt(
+            export_matches.value_of("min_pixel_coverage"))?,
+        name: None,
+        platform: crate::string_opt(export_matches.value_of("platform")),
+        recurse: export_matches.is_present("recurse"),
+        source: crate::string_opt(export_matches.value_of("source")),
+        start_timestamp: crate::i64_opt(
+            export_matches.value_of("start_timestamp"))?,
+    };
+
+    // initialize request
+    let request = Request::new(AlbumExportRequest {
+        destination: export_matches.value_of("DESTINATION")
+            .unwrap().to_string(),
+        filter: filter,
+        id: export_matches.value_of("ID").unwrap().to_string(),
+        priority: crate::u32_opt(export_matches.value_of("priority"))?,
+        task_id: crate::u64_opt(export_matches.value_of("task_id"))?,
+        tenant: crate::string_opt(matches.value_of("tenant")),
+        thread_count: export_matches.value_of("thread_count")
+            .unwrap().parse::<u32>()?,
+    });
+
+    // retrieve reply
+    let reply = client.export(request).await?;
+    let reply = reply.get_ref();
+
+    // print information
+    println!("task starting with id '{}'", reply.task_id);
+
+    Ok(())
+}
+
+#[tokio::main]
+async fn hold(matches: &ArgMatches, _: &ArgMatches,
+        hold_matches: &ArgMatches) -> Result<(), Box<dyn error::Error>> {
+    // initialize grpc client
+    let ip_address = matches.value_of("ip_address").unwrap();
+    let port = matches.value_of("port").unwrap().parse::<u16>()?;
+    let mut client = AlbumManagementClient::new(crate::channel(matches,
+        &format!("{}:{}", ip_address, port)).await?);
+
+    // initialize request
+    let hold_request = AlbumHoldRequest {
+        admin_token: crate::string_opt(hold_matches.value_of("admin_token")),
+        frozen: crate::bool_opt(hold_matches.value_of("frozen"))?,
+        id: hold_matches.value_of("ID").unwrap().to_string(),
+        legal_hold: crate::bool_opt(hold_matches.value_of("legal_hold"))?,
+    };
+
+    let request = Request::new(AlbumBroadcastRequest {
+        message_type: AlbumBroadcastType::AlbumHold as i32,
+        create_request: None,
+        close_request: None,
+        delete_request: None,
+        open_request: None,
+        reindex_request: None,
+        hold_request: Some(hold_request),
+        retention_request: None,
+        collect_request: None,
+    });
+
+    // retrieve reply
+    let reply = client.broadcast(request).await?;
+    let reply = reply.get_ref();
+
+    // print information
+    for (node_id, hold_reply) in reply.hold_replies.iter() {
+        println!("album holds on node '{}': frozen={} legal_hold={}",
+            node_id, hold_reply.frozen, hold_reply.legal_hold);
+    }
+
+    Ok(())
+}
+
+#[tokio::main]
+async fn import(matches: &ArgMatches, _: &ArgMatches,
+        import_matches: &ArgMatches) -> Result<(), Box<dyn error::Error>> {
+    // initialize grpc client
+    let ip_address = matches.value_of("ip_address").unwrap();
+    let port = matches.value_of("port").unwrap().parse::<u16>()?;
+    let mut client = AlbumManagementClient::new(crate::channel(matches,
+        &format!("{}:{}", ip_address, port)).await?);
+
+    // initialize request
+    let request = Request::new(AlbumImportRequest {
+        directory: import_matches.value_of("DIRECTORY")
+            .unwrap().to_string(),
+        id: import_matches.value_of("ID").unwrap().to_string(),
+        priority: crate::u32_opt(import_matches.value_of("priority"))?,
+        task_id: crate::u64_opt(import_matches.value_of("task_id"))?,
+        thread_count: import_matches.value_of("thread_count")
+            .unwrap().parse::<u32>()?,
+    });
+
+    // retrieve reply
+    let reply = client.import(request).await?;
+    let reply = reply.get_ref();
+
+    // print information
+    println!("task starting with id '{}'", reply.task_id);
+
+    Ok(())
+}
+
 #[tokio::main]
 async fn list(matches: &ArgMatches, _: &ArgMatches,
         _list_matches: &ArgMatches) -> Result<(), Box<dyn error::Error>> {
     // initialize grpc client
     let ip_address = matches.value_of("ip_address").unwrap();
     let port = matches.value_of("port").unwrap().parse::<u16>()?;
-    let mut client = AlbumManagementClient::connect(
-        format!("http://{}:{}", ip_address, port)).await?;
+    let mut client = AlbumManagementClient::new(crate::channel(matches,
+        &format!("{}:{}", ip_address, port)).await?);
 
     // initialize request
     let request = Request::new(AlbumListRequest {});
@@ -137,9 +325,10 @@ async fn list(matches: &ArgMatches, _: &ArgMatches,
     let reply = reply.get_ref();
 
     // print information
-    println!("{:<24}{:<12}{:<16}{:<8}", "id",
-        "geocode", "dht_key_length", "status");
-    println!("------------------------------------------------------------");
+    println!("{:<24}{:<12}{:<16}{:<12}{:<8}{:<20}{:<20}", "id",
+        "geocode", "dht_key_length", "precision", "status", "holds",
+        "retention");
+    println!("----------------------------------------------------------------------------------------------------");
     for album in reply.albums.iter() {
         let geocode = match Geocode::from_i32(album.geocode).unwrap() {
             Geocode::Geohash => "geohash",
@@ -151,8 +340,28 @@ async fn list(matches: &ArgMatches, _: &ArgMatches,
             AlbumStatus::Open => "open",
         };
 
-        println!("{:<24}{:<12}{:<16}{:<8}", album.id, geocode,
-            album.dht_key_length, status);
+        let holds = match (album.frozen, album.legal_hold) {
+            (true, true) => "frozen,legal_hold",
+            (true, false) => "frozen",
+            (false, true) => "legal_hold",
+            (false, false) => "-",
+        };
+
+        let precision = match album.precision {
+            Some(precision) => precision.to_string(),
+            None => "-".to_string(),
+        };
+
+        let retention = match (album.raw_ttl_days, album.max_bytes) {
+            (Some(days), Some(bytes)) => format!("{}d,{}B", days, bytes),
+            (Some(days), None) => format!("{}d", days),
+            (None, Some(bytes)) => format!("{}B", bytes),
+            (None, None) => "-".to_string(),
+        };
+
+        println!("{:<24}{:<12}{:<16}{:<12}{:<8}{:<20}{:<20}", album.id,
+            geocode, album.dht_key_length, precision, status, holds,
+            retention);
     }
 
     Ok(())
@@ -164,16 +373,11 @@ async fn open(matches: &ArgMatches, _: &ArgMatches,
     // initialize grpc client
     let ip_address = matches.value_of("ip_address").unwrap();
     let port = matches.value_of("port").unwrap().parse::<u16>()?;
-    let mut client = AlbumManagementClient::connect(
-        format!("http://{}:{}", ip_address, port)).await?;
+    let mut client = AlbumManagementClient::new(crate::channel(matches,
+        &format!("{}:{}", ip_address, port)).await?);
 
     // initialize request
-    let open_request = AlbumOpenRequest {
-        id: open_matches.value_of("ID").unwrap().to_string(),
-        task_id: crate::u64_opt(open_matches.value_of("task_id"))?,
-        thread_count: open_matches.value_of("thread_count")
-            .unwrap().parse::<u32>()?,
-    };
+    let open_request = parse_open_request(open_matches)?;
 
     let request = Request::new(AlbumBroadcastRequest {
         message_type: AlbumBroadcastType::AlbumOpen as i32,
@@ -181,6 +385,10 @@ async fn open(matches: &ArgMatches, _: &ArgMatches,
         close_request: None,
         delete_request: None,
         open_request: Some(open_request),
+        reindex_request: None,
+        hold_request: None,
+        retention_request: None,
+        collect_request: None,
     });
 
     // retrieve reply
@@ -195,3 +403,145 @@ async fn open(matches: &ArgMatches, _: &ArgMatches,
 
     Ok(())
 }
+
+#[tokio::main]
+async fn reindex(matches: &ArgMatches, _: &ArgMatches,
+        reindex_matches: &ArgMatches) -> Result<(), Box<dyn error::Error>> {
+    // initialize grpc client
+    let ip_address = matches.value_of("ip_address").unwrap();
+    let port = matches.value_of("port").unwrap().parse::<u16>()?;
+    let mut client = AlbumManagementClient::new(crate::channel(matches,
+        &format!("{}:{}", ip_address, port)).await?);
+
+    // initialize request
+    let reindex_request = AlbumReindexRequest {
+        id: reindex_matches.value_of("ID").unwrap().to_string(),
+        priority: crate::u32_opt(reindex_matches.value_of("priority"))?,
+        task_id: crate::u64_opt(reindex_matches.value_of("task_id"))?,
+        thread_count: reindex_matches.value_of("thread_count")
+            .unwrap().parse::<u32>()?,
+    };
+
+    let request = Request::new(AlbumBroadcastRequest {
+        message_type: AlbumBroadcastType::AlbumReindex as i32,
+        create_request: None,
+        close_request: None,
+        delete_request: None,
+        open_request: None,
+        reindex_request: Some(reindex_request),
+        hold_request: None,
+        retention_request: None,
+        collect_request: None,
+    });
+
+    // retrieve reply, nodes reply once their reindex finishes
+    let reply = match crate::u64_opt(reindex_matches.value_of("timeout"))? {
+        Some(timeout) => match tokio::time::timeout(
+                Duration::from_secs(timeout),
+                client.broadcast(request)).await {
+            Ok(reply) => reply?,
+            // nodes continue reindexing, reported by 'task list'
+            Err(_) => return Err(format!(
+                "reindex did not finish within {} seconds", timeout).into()),
+        },
+        None => client.broadcast(request).await?,
+    };
+    let reply = reply.get_ref();
+
+    // print information
+    for (node_id, reindex_reply) in reply.reindex_replies.iter() {
+        println!("task completed on node '{}' with id '{}'",
+            node_id, reindex_reply.task_id);
+
+        // print index discrepancies, nodes return a sample of each
+        for (label, paths, count) in &[
+                ("missing", &reindex_reply.missing,
+                    reindex_reply.missing_count),
+                ("corrupt", &reindex_reply.corrupt,
+                    reindex_reply.corrupt_count),
+                ("orphaned", &reindex_reply.orphaned,
+                    reindex_reply.orphaned_count)] {
+            for path in paths.iter() {
+                println!("  {:<10}{}", label, path);
+            }
+
+            let count = count.unwrap_or(paths.len() as u64);
+            if count > paths.len() as u64 {
+                println!("  {:<10}{} further paths omitted",
+                    label, count - paths.len() as u64);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[tokio::main]
+async fn retention(matches: &ArgMatches, _: &ArgMatches,
+        retention_matches: &ArgMatches) -> Result<(), Box<dyn error::Error>> {
+    // initialize grpc client
+    let ip_address = matches.value_of("ip_address").unwrap();
+    let port = matches.value_of("port").unwrap().parse::<u16>()?;
+    let mut client = AlbumManagementClient::new(crate::channel(matches,
+        &format!("{}:{}", ip_address, port)).await?);
+
+    // initialize request
+    let retention_request = AlbumRetentionRequest {
+        id: retention_matches.value_of("ID").unwrap().to_string(),
+        max_bytes: crate::u64_opt(retention_matches.value_of("max_bytes"))?,
+        raw_ttl_days: crate::u32_opt(
+            retention_matches.value_of("raw_ttl_days"))?,
+    };
+
+    let request = Request::new(AlbumBroadcastRequest {
+        message_type: AlbumBroadcastType::AlbumRetention as i32,
+        create_request: None,
+        close_request: None,
+        delete_request: None,
+        open_request: None,
+        reindex_request: None,
+        hold_request: None,
+        retention_request: Some(retention_request),
+        collect_request: None,
+    });
+
+    // retrieve reply
+    let _ = client.broadcast(request).await?;
+
+    Ok(())
+}
+
+pub fn parse_create_request(create_matches: &ArgMatches)
+        -> Result<AlbumCreateRequest, Box<dyn error::Error>> {
+    // parse arguments
+    let geocode = match create_matches.value_of("GEOCODE") {
+        Some("geohash") => Geocode::Geohash as i32,
+        Some("quadtile") => Geocode::Quadtile as i32,
+        _ => unimplemented!(),
+    };
+
+    let mut creation_options = Vec::new();
+    if let Some(values) = create_matches.values_of("creation_option") {
+        creation_options.extend(values.map(|x| x.to_string()));
+    }
+
+    Ok(AlbumCreateRequest {
+        creation_options: creation_options,
+        dht_key_length: create_matches.value_of("dht_key_length")
+            .unwrap().parse::<i32>()?,
+        geocode: geocode,
+        id: create_matches.value_of("ID").unwrap().to_string(),
+        precision: crate::u32_opt(create_matches.value_of("precision"))?,
+    })
+}
+
+pub fn parse_open_request(open_matches: &ArgMatches)
+        -> Result<AlbumOpenRequest, Box<dyn error::Error>> {
+    Ok(AlbumOpenRequest {
+        id: open_matches.value_of("ID").unwrap().to_string(),
+        priority: crate::u32_opt(open_matches.value_of("priority"))?,
+        task_id: crate::u64_opt(open_matches.value_of("task_id"))?,
+        thread_count: open_matches.value_of("thread_count")
+            .unwrap().parse::<u32>()?,
+    })
+}
@@ -0,0 +1,374 @@
+use clap::{App, ArgMatches};
+use protobuf::{AlbumBroadcastRequest, AlbumBroadcastType, AlbumManagementClient, ImageBroadcastRequest, ImageBroadcastType, ImageManagementClient, TaskBroadcastRequest, TaskBroadcastType, TaskListRequest, TaskManagementClient, TokenChannel};
+use tonic::Request;
+use yaml_rust::{Yaml, YamlLoader};
+
+use std::collections::{BTreeMap, HashSet};
+use std::error::Error;
+use std::time::Duration;
+
+// commands which may be submitted as job steps
+const COMMANDS: &[&str] = &["album create", "album open", "image coalesce",
+    "image fill", "image split", "image store"];
+
+// interval between task progress checks when waiting on a step
+const WAIT_INTERVAL: Duration = Duration::from_secs(5);
+
+pub fn process(matches: &ArgMatches, apply_matches: &ArgMatches) {
+    if let Err(e) = apply(matches, apply_matches) {
+        println!("{}", e);
+    }
+}
+
+#[tokio::main]
+async fn apply(matches: &ArgMatches, apply_matches: &ArgMatches)
+        -> Result<(), Box<dyn Error>> {
+    // parse every step before submitting any of them
+    let path = apply_matches.value_of("file").unwrap();
+    let spec = std::fs::read_to_string(path)
+        .map_err(|e| format!("failed to read job spec '{}': {}", path, e))?;
+    let yaml = crate::config::load(include_str!("clap.yaml"))?;
+    let steps = parse(&spec, &yaml)?;
+
+    // initialize grpc channel
+    let ip_address = matches.value_of("ip_address").unwrap();
+    let port = matches.value_of("port").unwrap().parse::<u16>()?;
+    let channel = crate::channel(matches,
+        &format!("{}:{}", ip_address, port)).await?;
+
+    // submit steps in order
+    let wait = apply_matches.is_present("wait");
+    for (i, (command, step_matches)) in steps.iter().enumerate() {
+        println!("step {} '{}'", i + 1, command);
+        let task_ids = submit(matches, command,
+            step_matches, channel.clone()).await?;
+
+        if wait && !task_ids.is_empty() {
+            wait_for(channel.clone(), &task_ids).await?;
+        }
+    }
+
+    Ok(())
+}
+
+// parse each step of a job spec into the matches of its command
+fn parse<'a>(spec: &str, yaml: &'a Yaml)
+        -> Result<Vec<(String, ArgMatches<'a>)>, Box<dyn Error>> {
+    let docs = YamlLoader::load_from_str(spec)
+        .map_err(|e| format!("failed to parse job spec: {}", e))?;
+    let steps = match docs.first().map(|x| &x["steps"]) {
+        Some(Yaml::Array(steps)) => steps,
+        _ => return Err("job spec requires a 'steps' list".into()),
+    };
+
+    let mut commands = Vec::new();
+    for (i, step) in steps.iter().enumerate() {
+        // steps are formatted as '<command>: { <argument>: <value> }'
+        let (command, args) = match step.as_hash() {
+            Some(hash) if hash.len() == 1 => hash.iter().next().unwrap(),
+            _ => return Err(format!(
+                "step {} must contain exactly one command", i + 1).into()),
+        };
+
+        let command = command.as_str()
+            .ok_or(format!("step {} command is not a string", i + 1))?
+            .split_whitespace().collect::<Vec<&str>>().join(" ");
+        if !COMMANDS.contains(&command.as_str()) {
+            return Err(format!("step {} command '{}' is not supported, expected one of {}",
+                i + 1, command, COMMANDS.join(", ")).into());
+        }
+
+        let argv = argv(yaml, &command, args)
+            .map_err(|e| format!("step {} '{}': {}", i + 1, command, e))?;
+
+        let matches = App::from_yaml(yaml).get_matches_from_safe(argv)
+            .map_err(|e| format!("step {} '{}': {}", i + 1, command, e))?;
+        commands.push((command, matches));
+    }
+
+    Ok(commands)
+}
+
+// convert step arguments into command line arguments
+fn argv(yaml: &Yaml, command: &str, args: &Yaml)
+        -> Result<Vec<String>, Box<dyn Error>> {
+    let mut argv = vec!["stip".to_string()];
+    let mut subcommand = yaml;
+    for name in command.split_whitespace() {
+        subcommand = match &subcommand["subcommands"] {
+            Yaml::Array(subcommands) => subcommands.iter()
+                .filter_map(|x| x.as_hash())
+                .filter_map(|x| x.get(&Yaml::String(name.to_string())))
+                .next(),
+            _ => None,
+        }.ok_or(format!("unknown command '{}'", command))?;
+
+        argv.push(name.to_string());
+    }
+
+    // arguments are referenced by name or long flag
+    let mut definitions = BTreeMap::new();
+    if let Yaml::Array(args) = &subcommand["args"] {
+        for (name, definition) in args.iter()
+                .filter_map(|x| x.as_hash()).flat_map(|x| x.iter()) {
+            if let Some(name) = name.as_str() {
+                definitions.insert(name.to_lowercase(), definition);
+            }
+
+            if let Some(long) = definition["long"].as_str() {
+                definitions.insert(long.to_lowercase(), definition);
+            }
+        }
+    }
+
+    let args = match args {
+        Yaml::Hash(args) => args.clone(),
+        Yaml::Null => BTreeMap::new(),
+        _ => return Err("arguments must be a mapping".into()),
+    };
+
+    let mut positional = BTreeMap::new();
+    for (name, value) in args.iter() {
+        // argument names match case insensitively, so 'album' sets ALBUM
+        let name = name.as_str().ok_or("argument name is not a string")?;
+        let definition = definitions.get(&name.to_lowercase())
+            .ok_or(format!("unknown argument '{}'", name))?;
+
+        let values = match value {
+            Yaml::Array(values) => values.iter().map(|x| string(x, name))
+                .collect::<Result<Vec<String>, _>>()?,
+            x => vec![string(x, name)?],
+        };
+
+        // positional arguments are ordered by their index
+        if let Some(index) = definition["index"].as_i64() {
+            positional.insert(index, values);
+            continue;
+        }
+
+        let flag = match (definition["long"].as_str(),
+                definition["short"].as_str()) {
+            (Some(long), _) => format!("--{}", long),
+            (None, Some(short)) => format!("-{}", short),
+            (None, None) => return Err(format!(
+                "argument '{}' may not be set", name).into()),
+        };
+
+        // values are joined to their flag so they may begin with '-'
+        if definition["takes_value"].as_bool().unwrap_or(false) {
+            for value in values {
+                match flag.starts_with("--") {
+                    true => argv.push(format!("{}={}", flag, value)),
+                    false => argv.push(format!("{}{}", flag, value)),
+                }
+            }
+        } else if value.as_bool()
+                .ok_or(format!("argument '{}' must be a boolean", name))? {
+            argv.push(flag);
+        }
+    }
+
+    for (_, values) in positional.into_iter() {
+        argv.extend(values);
+    }
+
+    Ok(argv)
+}
+
+fn string(value: &Yaml, name: &str) -> Result<String, Box<dyn Error>> {
+    match value {
+        Yaml::Boolean(x) => Ok(x.to_string()),
+        Yaml::Integer(x) => Ok(x.to_string()),
+        Yaml::Real(x) | Yaml::String(x) => Ok(x.clone()),
+        _ => Err(format!("argument '{}' has an unsupported value", name).into()),
+    }
+}
+
+// submit a step, returning the identifiers of started tasks
+async fn submit(matches: &ArgMatches<'_>, command: &str,
+        step_matches: &ArgMatches<'_>, channel: TokenChannel)
+        -> Result<Vec<u64>, Box<dyn Error>> {
+    let tenant = matches.value_of("tenant");
+    let album_request = AlbumBroadcastRequest {
+        message_type: AlbumBroadcastType::AlbumCreate as i32,
+        create_request: None,
+        close_request: None,
+        delete_request: None,
+        open_request: None,
+        reindex_request: None,
+        hold_request: None,
+        retention_request: None,
+        collect_request: None,
+    };
+
+    let mut image_request = ImageBroadcastRequest {
+        message_type: ImageBroadcastType::Fill as i32,
+        band_math_request: None,
+        calc_request: None,
+        change_request: None,
+        coalesce_request: None,
+        composite_request: None,
+        copy_request: None,
+        delete_request: None,
+        execute_request: None,
+        fill_request: None,
+        pipeline_request: None,
+        preload_request: None,
+        reproject_request: None,
+        split_request: None,
+        statistics_request: None,
+        update_request: None,
+    };
+
+    match subcommand_path(step_matches) {
+        ("album", "create", x) => {
+            // album creation starts no tasks
+            let request = AlbumBroadcastRequest {
+                create_request: Some(crate::album::parse_create_request(x)?),
+                ..album_request
+            };
+
+            AlbumManagementClient::new(channel)
+                .broadcast(Request::new(request)).await?;
+            return Ok(Vec::new());
+        },
+        ("album", "open", x) => {
+            let request = AlbumBroadcastRequest {
+                message_type: AlbumBroadcastType::AlbumOpen as i32,
+                open_request: Some(crate::album::parse_open_request(x)?),
+                ..album_request
+            };
+
+            let reply = AlbumManagementClient::new(channel)
+                .broadcast(Request::new(request)).await?;
+
+            let mut task_ids = Vec::new();
+            for (node_id, open_reply) in reply.get_ref().open_replies.iter() {
+                println!("task starting on node '{}' with id '{}'",
+                    node_id, open_reply.task_id);
+                task_ids.push(open_reply.task_id);
+            }
+
+            return Ok(task_ids);
+        },
+        ("image", "coalesce", x) => {
+            image_request.message_type = ImageBroadcastType::Coalesce as i32;
+            image_request.coalesce_request =
+                Some(crate::image::parse_coalesce_request(x, tenant)?);
+        },
+        ("image", "fill", x) => {
+            image_request.fill_request =
+                Some(crate::image::parse_fill_request(x, tenant)?);
+        },
+        ("image", "split", x) => {
+            image_request.message_type = ImageBroadcastType::Split as i32;
+            image_request.split_request =
+                Some(crate::image::parse_split_request(x, tenant)?);
+        },
+        ("image", "store", x) => {
+            // loads run on the contacted node
+            let request = crate::image::parse_store_request(x, tenant)?;
+            let reply = ImageManagementClient::new(channel)
+                .store(Request::new(request)).await?;
+            let reply = reply.get_ref();
+
+            return match &reply.dry_run {
+                Some(report) => {
+                    crate::image::print_dry_run(
+                        matches.value_of("ip_address").unwrap(), report);
+                    Ok(Vec::new())
+                },
+                None => {
+                    println!("task starting with id '{}'", reply.task_id);
+                    Ok(vec![reply.task_id])
+                },
+            };
+        },
+        _ => return Err(format!("command '{}' is not supported in job specs",
+            command).into()),
+    }
+
+    let reply = ImageManagementClient::new(channel)
+        .broadcast(Request::new(image_request)).await?;
+    let reply = reply.get_ref();
+
+    // print information
+    let mut task_ids = Vec::new();
+    let mut started = |node_id: &u32, task_id: u64| {
+        println!("task starting on node '{}' with id '{}'", node_id, task_id);
+        task_ids.push(task_id);
+    };
+
+    for (node_id, x) in reply.coalesce_replies.iter() {
+        started(node_id, x.task_id);
+    }
+
+    for (node_id, x) in reply.fill_replies.iter() {
+        match &x.dry_run {
+            Some(report) => crate::image::print_dry_run(
+                &node_id.to_string(), report),
+            None => started(node_id, x.task_id),
+        }
+    }
+
+    for (node_id, x) in reply.split_replies.iter() {
+        match &x.dry_run {
+            Some(report) => crate::image::print_dry_run(
+                &node_id.to_string(), report),
+            None => started(node_id, x.task_id),
+        }
+    }
+
+    Ok(task_ids)
+}
+
+fn subcommand_path<'a>(matches: &'a ArgMatches<'a>)
+        -> (&'a str, &'a str, &'a ArgMatches<'a>) {
+    match matches.subcommand() {
+        (group, Some(group_matches)) => match group_matches.subcommand() {
+            (command, Some(command_matches)) =>
+                (group, command, command_matches),
+            (command, None) => (group, command, group_matches),
+        },
+        (group, None) => (group, "", matches),
+    }
+}
+
+// block until no node reports the tasks as running
+async fn wait_for(channel: TokenChannel, task_ids: &[u64])
+        -> Result<(), Box<dyn Error>> {
+    let task_ids: HashSet<u64> = task_ids.iter().cloned().collect();
+    let mut client = TaskManagementClient::new(channel);
+    loop {
+        let request = Request::new(TaskBroadcastRequest {
+            message_type: TaskBroadcastType::TaskList as i32,
+            clear_request: None,
+            list_request: Some(TaskListRequest {}),
+            cancel_request: None,
+            pause_request: None,
+            resume_request: None,
+            retry_request: None,
+            errors_request: None,
+        });
+
+        let reply = client.broadcast(request).await?;
+        let tasks: Vec<_> = reply.get_ref().list_replies.values()
+            .flat_map(|x| x.tasks.iter())
+            .filter(|x| task_ids.contains(&x.id)).collect();
+
+        if tasks.iter().all(|x| !x.running) {
+            // tasks completing fewer records than expected failed
+            let failed: HashSet<u64> = tasks.iter()
+                .filter(|x| x.completed_count < x.total_count)
+                .map(|x| x.id).collect();
+
+            return match failed.is_empty() {
+                true => Ok(()),
+                false => Err(format!("task(s) {:?} failed, see 'task errors'",
+                    failed).into()),
+            };
+        }
+
+        tokio::time::delay_for(WAIT_INTERVAL).await;
+    }
+}
@@ -0,0 +1,313 @@
+use clap::{ArgMatches, YamlLoader};
+use protobuf::{AlbumCreateRequest, AlbumListRequest, AlbumManagementClient, Geocode, NodeListRequest, NodeManagementClient, NodeStatusRequest};
+use serde_json::json;
+use tonic::Request;
+
+use crate::output::Output;
+
+use std::{error, io};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::time::Instant;
+
+pub fn process(matches: &ArgMatches, cluster_matches: &ArgMatches) {
+    let result: Result<(), Box<dyn error::Error>>
+            = match cluster_matches.subcommand() {
+        ("add", Some(add_matches)) =>
+            add(&matches, &cluster_matches, &add_matches),
+        ("init", Some(init_matches)) =>
+            init(&matches, &cluster_matches, &init_matches),
+        ("list", Some(list_matches)) =>
+            list(&matches, &cluster_matches, &list_matches),
+        ("remove", Some(remove_matches)) =>
+            remove(&matches, &cluster_matches, &remove_matches),
+        ("status", Some(status_matches)) =>
+            status(&matches, &cluster_matches, &status_matches),
+        (cmd, _) => Err(Box::new(io::Error::new(io::ErrorKind::Other,
+            format!("unknown subcommand '{}'", cmd)))),
+    };
+
+    if let Err(e) = result {
+        println!("{}", e);
+    }
+}
+
+fn add(_: &ArgMatches, _: &ArgMatches,
+        add_matches: &ArgMatches) -> Result<(), Box<dyn error::Error>> {
+    let name = add_matches.value_of("NAME").unwrap();
+    let address = add_matches.value_of("ADDRESS").unwrap();
+    if !address.contains(':') {
+        return Err(format!("cluster address '{}' must be formatted as ip_address:port", address).into());
+    }
+
+    // add cluster to registry
+    let mut clusters = load()?;
+    clusters.insert(name.to_string(), address.to_string());
+    save(&clusters)?;
+
+    println!("registered cluster '{}' at {}", name, address);
+    Ok(())
+}
+
+#[tokio::main]
+async fn init(matches: &ArgMatches, _: &ArgMatches,
+        init_matches: &ArgMatches) -> Result<(), Box<dyn error::Error>> {
+    let path = init_matches.value_of("FILE").unwrap();
+    let docs = YamlLoader::load_from_str(&std::fs::read_to_string(path)?)?;
+    let doc = match docs.get(0) {
+        Some(doc) => doc,
+        None => return Err(format!("cluster config '{}' is empty",
+            path).into()),
+    };
+
+    // parse album definitions
+    let mut requests = Vec::new();
+    for album in doc["albums"].as_vec().unwrap_or(&Vec::new()) {
+        let geocode = match album["geocode"].as_str() {
+            Some("geohash") => Geocode::Geohash as i32,
+            Some("quadtile") => Geocode::Quadtile as i32,
+            _ => return Err("album geocode must be 'geohash' or 'quadtile'".into()),
+        };
+
+        let id = match album["id"].as_str() {
+            Some(id) => id.to_string(),
+            None => return Err("album id is required".into()),
+        };
+
+        let mut creation_options = Vec::new();
+        for option in album["creation_options"].as_vec()
+                .unwrap_or(&Vec::new()) {
+            match option.as_str() {
+                Some(x) => creation_options.push(x.to_string()),
+                None => return Err(format!(
+                    "invalid creation option for album '{}'", id).into()),
+            }
+        }
+
+        requests.push(AlbumCreateRequest {
+            creation_options: creation_options,
+            dht_key_length: album["dht_key_length"].as_i64()
+                .unwrap_or(0) as i32,
+            geocode: geocode,
+            id: id,
+            precision: album["precision"].as_i64().map(|x| x as u32),
+        });
+    }
+
+    // create missing albums on each defined node
+    for node in doc["nodes"].as_vec().unwrap_or(&Vec::new()) {
+        let address = match (node["ip_address"].as_str(),
+                node["rpc_port"].as_i64()) {
+            (Some(ip_address), Some(port)) =>
+                format!("{}:{}", ip_address, port),
+            _ => return Err("node ip_address and rpc_port are required".into()),
+        };
+
+        let mut client = AlbumManagementClient::new(crate::channel(matches,
+            &address).await?);
+        let reply = client.list(Request::new(AlbumListRequest {})).await?;
+        let existing: Vec<String> = reply.into_inner().albums.into_iter()
+            .map(|x| x.id).collect();
+
+        for request in requests.iter() {
+            if existing.contains(&request.id) {
+                continue;
+            }
+
+            client.create(Request::new(request.clone())).await?;
+            println!("created album '{}' on node {}", request.id, address);
+        }
+    }
+
+    Ok(())
+}
+
+fn list(_: &ArgMatches, _: &ArgMatches,
+        _list_matches: &ArgMatches) -> Result<(), Box<dyn error::Error>> {
+    // print information
+    println!("{:<24}{:<24}", "name", "address");
+    println!("------------------------------------------------");
+    for (name, address) in load()?.iter() {
+        println!("{:<24}{:<24}", name, address);
+    }
+
+    Ok(())
+}
+
+fn remove(_: &ArgMatches, _: &ArgMatches,
+        remove_matches: &ArgMatches) -> Result<(), Box<dyn error::Error>> {
+    let name = remove_matches.value_of("NAME").unwrap();
+
+    // remove cluster from registry
+    let mut clusters = load()?;
+    if clusters.remove(name).is_none() {
+        return Err(format!("cluster '{}' is not registered", name).into());
+    }
+
+    save(&clusters)?;
+    Ok(())
+}
+
+#[tokio::main]
+async fn status(matches: &ArgMatches, _: &ArgMatches,
+        status_matches: &ArgMatches) -> Result<(), Box<dyn error::Error>> {
+    let output = Output::new(matches, vec![("cluster", 12), ("id", 6),
+        ("rpc_addr", 22), ("state", 10), ("latency_ms", 12),
+        ("uptime", 10), ("ring", 6), ("tasks", 7), ("queued", 8),
+        ("disk_used", 11),
+        ("disk_free", 11), ("images", 10), ("albums", 32)])?;
+    output.header();
+
+    for (name, address) in targets(matches, status_matches)?.iter() {
+        let cluster = json!(name.as_ref().map(|x| x.as_str())
+            .unwrap_or("-"));
+
+        // retrieve cluster nodes
+        let mut client = NodeManagementClient::new(crate::channel(matches,
+            address).await?);
+        let reply = client.list(Request::new(NodeListRequest {})).await?;
+        let nodes = reply.into_inner().nodes;
+
+        for node in nodes.iter() {
+            // retrieve node status, tolerating unreachable nodes
+            let instant = Instant::now();
+            let reply = match crate::channel(matches, &node.rpc_addr).await
+                    .map(NodeManagementClient::new) {
+                Ok(mut client) => client.status(Request::new(
+                    NodeStatusRequest {})).await.ok()
+                    .map(|x| x.into_inner()),
+                Err(_) => None,
+            };
+            let latency = instant.elapsed().as_millis() as u64;
+
+            // nodes seeing fewer peers than the coordinator lag in gossip
+            let state = match (&reply, node.decommissioned) {
+                (None, _) => "down",
+                (Some(_), Some(true)) => "draining",
+                (Some(x), _) if (x.ring_node_count as usize) < nodes.len()
+                    => "partial",
+                (Some(_), _) => "up",
+            };
+
+            let mut row = vec![cluster.clone(), json!(node.id),
+                json!(node.rpc_addr), json!(state)];
+            match reply {
+                Some(x) => {
+                    let images: u64 = x.albums.iter()
+                        .filter_map(|x| x.image_count).sum();
+                    let albums: Vec<String> = x.albums.iter()
+                        .map(|x| match x.image_count {
+                            Some(count) => format!("{}:{}", x.album, count),
+                            None => format!("{}:closed", x.album),
+                        }).collect();
+
+                    row.extend(vec![json!(latency),
+                        json!(format_duration(x.uptime_seconds)),
+                        json!(x.ring_node_count),
+                        json!(x.running_task_count),
+                        json!(x.queued_task_count),
+                        json!(crate::node::format_bytes(
+                            x.disk_total_bytes - x.disk_free_bytes)),
+                        json!(crate::node::format_bytes(x.disk_free_bytes)),
+                        json!(images), json!(albums.join(","))]);
+                },
+                None => row.extend(vec![json!(null); 9]),
+            }
+
+            output.row(row);
+        }
+    }
+
+    Ok(())
+}
+
+pub fn targets(matches: &ArgMatches, sub_matches: &ArgMatches)
+        -> Result<Vec<(Option<String>, String)>, Box<dyn error::Error>> {
+    let clusters = load()?;
+
+    // select registered clusters for federated queries
+    if sub_matches.is_present("federated") {
+        if clusters.is_empty() {
+            return Err("no clusters are registered".into());
+        }
+
+        return Ok(clusters.into_iter()
+            .map(|(name, address)| (Some(name), address)).collect());
+    }
+
+    if let Some(names) = sub_matches.values_of("cluster") {
+        let mut targets = Vec::new();
+        for name in names {
+            match clusters.get(name) {
+                Some(address) => targets.push((Some(name.to_string()),
+                    address.clone())),
+                None => return Err(format!(
+                    "cluster '{}' is not registered", name).into()),
+            }
+        }
+
+        return Ok(targets);
+    }
+
+    // default to the cluster of the provided node
+    Ok(vec![(None, format!("{}:{}", matches.value_of("ip_address").unwrap(),
+        matches.value_of("port").unwrap()))])
+}
+
+fn load() -> Result<BTreeMap<String, String>, Box<dyn error::Error>> {
+    let path = registry_path()?;
+    let mut clusters = BTreeMap::new();
+    if !path.exists() {
+        return Ok(clusters);
+    }
+
+    // parse 'name address' lines
+    for line in std::fs::read_to_string(&path)?.lines() {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        match fields.len() {
+            0 => continue,
+            2 => clusters.insert(fields[0].to_string(),
+                fields[1].to_string()),
+            _ => return Err(format!("invalid cluster registry line '{}'",
+                line).into()),
+        };
+    }
+
+    Ok(clusters)
+}
+
+fn format_duration(seconds: u64) -> String {
+    match seconds {
+        x if x >= 86400 => format!("{}d{}h", x / 86400, (x % 86400) / 3600),
+        x if x >= 3600 => format!("{}h{}m", x / 3600, (x % 3600) / 60),
+        x if x >= 60 => format!("{}m{}s", x / 60, x % 60),
+        x => format!("{}s", x),
+    }
+}
+
+fn registry_path() -> Result<PathBuf, Box<dyn error::Error>> {
+    if let Ok(path) = std::env::var("STIP_CLUSTERS") {
+        return Ok(PathBuf::from(path));
+    }
+
+    match std::env::var("HOME") {
+        Ok(home) => Ok(PathBuf::from(home).join(".stip").join("clusters")),
+        Err(_) => Err("unable to locate cluster registry, set STIP_CLUSTERS".into()),
+    }
+}
+
+fn save(clusters: &BTreeMap<String, String>)
+        -> Result<(), Box<dyn error::Error>> {
+    let path = registry_path()?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    let mut buf = String::new();
+    for (name, address) in clusters.iter() {
+        buf.push_str(&format!("{} {}\n", name, address));
+    }
+
+    std::fs::write(&path, buf)?;
+    Ok(())
+}
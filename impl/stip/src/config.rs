@@ -0,0 +1,160 @@
+use toml::Value;
+use yaml_rust::{Yaml, YamlLoader};
+
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::path::PathBuf;
+
+// global arguments which may be set by the config file or environment
+const GLOBAL_ARGS: &[&str] = &["ip_address", "output", "port", "tenant",
+    "tls_ca", "tls_cert", "tls_domain", "tls_key", "token"];
+
+// parse the clap yaml, defaulting arguments from the config file and
+//   STIP_* environment variables, which take precedence
+pub fn load(yaml: &str) -> Result<Yaml, Box<dyn Error>> {
+    let mut doc = YamlLoader::load_from_str(yaml)?.remove(0);
+
+    let mut settings = read()?;
+    for key in GLOBAL_ARGS.iter().chain(["album"].iter()) {
+        let variable = format!("STIP_{}", key.to_uppercase());
+        if let Ok(value) = std::env::var(&variable) {
+            settings.insert(key.to_string(), value);
+        }
+    }
+
+    // set global argument defaults
+    for (key, value) in settings.iter() {
+        if !GLOBAL_ARGS.contains(&key.as_str()) {
+            continue;
+        }
+
+        if let Some(arg) = find_arg(&mut doc, key) {
+            set_default(arg, value);
+        }
+    }
+
+    // default albums of subcommands taking no other positional argument
+    if let Some(album) = settings.get("album") {
+        set_album_default(&mut doc, album);
+    }
+
+    Ok(doc)
+}
+
+fn find_arg<'a>(doc: &'a mut Yaml, name: &str) -> Option<&'a mut Yaml> {
+    let args = match doc {
+        Yaml::Hash(hash) => hash.get_mut(&Yaml::String("args".to_string())),
+        _ => None,
+    };
+
+    match args {
+        Some(Yaml::Array(args)) => args.iter_mut().filter_map(|x| match x {
+                Yaml::Hash(hash) => hash.get_mut(
+                    &Yaml::String(name.to_string())),
+                _ => None,
+            }).next(),
+        _ => None,
+    }
+}
+
+fn profile() -> Option<String> {
+    // the profile flag is read before clap parses arguments
+    let args: Vec<String> = std::env::args().collect();
+    for (i, arg) in args.iter().enumerate() {
+        if arg == "--profile" {
+            return args.get(i + 1).cloned();
+        } else if arg.starts_with("--profile=") {
+            return Some(arg["--profile=".len()..].to_string());
+        }
+    }
+
+    std::env::var("STIP_PROFILE").ok()
+}
+
+fn read() -> Result<BTreeMap<String, String>, Box<dyn Error>> {
+    let mut settings = BTreeMap::new();
+    let path = match std::env::var("STIP_CONFIG") {
+        Ok(path) => PathBuf::from(path),
+        Err(_) => match std::env::var("HOME") {
+            Ok(home) => PathBuf::from(home).join(".stip").join("config.toml"),
+            Err(_) => return Ok(settings),
+        },
+    };
+
+    let profile = profile();
+    if !path.exists() {
+        return match profile {
+            Some(profile) => Err(format!(
+                "profile '{}' requires config file {:?}", profile, path).into()),
+            None => Ok(settings),
+        };
+    }
+
+    let config = std::fs::read_to_string(&path)?.parse::<Value>()
+        .map_err(|e| format!("failed to parse config {:?}: {}", path, e))?;
+
+    // top level values apply to every profile
+    let mut tables = vec![&config];
+    if let Some(profile) = profile {
+        match config.get("profile").and_then(|x| x.get(&profile)) {
+            Some(table) => tables.push(table),
+            None => return Err(format!("profile '{}' is not defined in {:?}",
+                profile, path).into()),
+        }
+    }
+
+    for table in tables {
+        let table = match table.as_table() {
+            Some(table) => table,
+            None => continue,
+        };
+
+        for (key, value) in table.iter() {
+            match value {
+                Value::String(x) =>
+                    settings.insert(key.clone(), x.clone()),
+                Value::Table(_) => continue,
+                x => settings.insert(key.clone(), x.to_string()),
+            };
+        }
+    }
+
+    Ok(settings)
+}
+
+fn set_album_default(command: &mut Yaml, album: &str) {
+    // count positional arguments
+    let positional = match &command["args"] {
+        Yaml::Array(args) => args.iter().filter_map(|x| x.as_hash())
+            .flat_map(|x| x.values())
+            .filter(|x| !x["index"].is_badvalue()).count(),
+        _ => 0,
+    };
+
+    if positional == 1 {
+        if let Some(arg) = find_arg(command, "ALBUM") {
+            set_default(arg, album);
+        }
+    }
+
+    // recurse into subcommands
+    if let Yaml::Hash(hash) = command {
+        if let Some(Yaml::Array(subcommands)) =
+                hash.get_mut(&Yaml::String("subcommands".to_string())) {
+            for subcommand in subcommands.iter_mut() {
+                if let Yaml::Hash(subcommand) = subcommand {
+                    for (_, command) in subcommand.iter_mut() {
+                        set_album_default(command, album);
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn set_default(arg: &mut Yaml, value: &str) {
+    if let Yaml::Hash(hash) = arg {
+        hash.insert(Yaml::String("default_value".to_string()),
+            Yaml::String(value.to_string()));
+    }
+}
@@ -0,0 +1,125 @@
+use clap::ArgMatches;
+use protobuf::{Filter, FilterDeleteRequest, FilterListRequest, FilterManagementClient, FilterSaveRequest};
+use tonic::Request;
+
+use std::{error, io};
+
+pub fn process(matches: &ArgMatches, filter_matches: &ArgMatches) {
+    let result: Result<(), Box<dyn error::Error>>
+            = match filter_matches.subcommand() {
+        ("delete", Some(delete_matches)) =>
+            delete(&matches, &filter_matches, &delete_matches),
+        ("list", Some(list_matches)) =>
+            list(&matches, &filter_matches, &list_matches),
+        ("save", Some(save_matches)) =>
+            save(&matches, &filter_matches, &save_matches),
+        (cmd, _) => Err(Box::new(io::Error::new(io::ErrorKind::Other,
+            format!("unknown subcommand '{}'", cmd)))),
+    };
+
+    if let Err(e) = result {
+        println!("{}", e);
+    }
+}
+
+#[tokio::main]
+async fn delete(matches: &ArgMatches, _: &ArgMatches,
+        delete_matches: &ArgMatches) -> Result<(), Box<dyn error::Error>> {
+    // initialize grpc client
+    let ip_address = matches.value_of("ip_address").unwrap();
+    let port = matches.value_of("port").unwrap().parse::<u16>()?;
+    let mut client = FilterManagementClient::new(crate::channel(matches,
+        &format!("{}:{}", ip_address, port)).await?);
+
+    // initialize request
+    let request = Request::new(FilterDeleteRequest {
+        local: None,
+        name: delete_matches.value_of("NAME").unwrap().to_string(),
+    });
+
+    // retrieve reply
+    let _ = client.delete(request).await?;
+
+    Ok(())
+}
+
+#[tokio::main]
+async fn list(matches: &ArgMatches, _: &ArgMatches,
+        _list_matches: &ArgMatches) -> Result<(), Box<dyn error::Error>> {
+    // initialize grpc client
+    let ip_address = matches.value_of("ip_address").unwrap();
+    let port = matches.value_of("port").unwrap().parse::<u16>()?;
+    let mut client = FilterManagementClient::new(crate::channel(matches,
+        &format!("{}:{}", ip_address, port)).await?);
+
+    // initialize request
+    let request = Request::new(FilterListRequest {});
+
+    // retrieve reply
+    let reply = client.list(request).await?;
+    let reply = reply.get_ref();
+
+    // print information
+    println!("{:<16}{:<14}{:<10}{:<14}{:<12}{:<12}{:<12}{:<12}{:<8}",
+        "name", "platform", "geocode", "source", "start", "end",
+        "cloud_cov", "pixel_cov", "recurse");
+    println!("--------------------------------------------------------------------------------------------------------------");
+    for saved_filter in reply.filters.iter() {
+        let filter = &saved_filter.filter;
+        println!("{:<16}{:<14}{:<10}{:<14}{:<12}{:<12}{:<12}{:<12}{:<8}",
+            saved_filter.name, display(&filter.platform),
+            display(&filter.geocode), display(&filter.source),
+            display(&filter.start_timestamp),
+            display(&filter.end_timestamp),
+            display(&filter.max_cloud_coverage),
+            display(&filter.min_pixel_coverage), filter.recurse);
+    }
+
+    Ok(())
+}
+
+#[tokio::main]
+async fn save(matches: &ArgMatches, _: &ArgMatches,
+        save_matches: &ArgMatches) -> Result<(), Box<dyn error::Error>> {
+    // initialize grpc client
+    let ip_address = matches.value_of("ip_address").unwrap();
+    let port = matches.value_of("port").unwrap().parse::<u16>()?;
+    let mut client = FilterManagementClient::new(crate::channel(matches,
+        &format!("{}:{}", ip_address, port)).await?);
+
+    // initialize Filter
+    let filter = Filter {
+        end_timestamp: crate::i64_opt(
+            save_matches.value_of("end_timestamp"))?,
+        geocode: crate::string_opt(save_matches.value_of("geocode")),
+        max_cloud_coverage: crate::f64_opt(
+            save_matches.value_of("max_cloud_coverage"))?,
+        min_pixel_coverage: crate::f64_opt(
+            save_matches.value_of("min_pixel_coverage"))?,
+        name: crate::string_opt(save_matches.value_of("filter")),
+        platform: crate::string_opt(save_matches.value_of("platform")),
+        recurse: save_matches.is_present("recurse"),
+        source: crate::string_opt(save_matches.value_of("source")),
+        start_timestamp: crate::i64_opt(
+            save_matches.value_of("start_timestamp"))?,
+    };
+
+    // initialize request
+    let request = Request::new(FilterSaveRequest {
+        filter: filter,
+        local: None,
+        name: save_matches.value_of("NAME").unwrap().to_string(),
+    });
+
+    // retrieve reply
+    let _ = client.save(request).await?;
+
+    Ok(())
+}
+
+fn display<T: ToString>(value: &Option<T>) -> String {
+    match value {
+        Some(value) => value.to_string(),
+        None => "-".to_string(),
+    }
+}
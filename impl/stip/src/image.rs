@@ -1,25 +1,78 @@
-use clap::ArgMatches;
-use protobuf::{NodeManagementClient, ImageBroadcastRequest, ImageBroadcastType, ImageCoalesceRequest, ImageFillRequest, ImageListRequest, Extent, Filter, ImageFormat, ImageStoreRequest, ImageManagementClient, ImageSearchRequest, ImageSplitRequest, NodeListRequest};
+use clap::{App, ArgMatches};
+use futures::stream::{self, StreamExt, TryStreamExt};
+use protobuf::{DryRunReport, NodeManagementClient, ImageBandMathRequest, ImageBroadcastRequest, ImageBroadcastType, ImageCalcRequest, ImageChangeRequest, ImageCoalesceRequest, ImageCompositeRequest, ImageCopyRequest, ImageCoverageRequest, ImageDeleteRequest, ImageDistinctRequest, ImageDownloadRequest, ImageExecuteRequest, ImageExportRequest, ImageFillRequest, ImageListRequest, Extent, Filter, Image, ImageFormat, ImagePipelineRequest, ImagePipelineStage, ImagePreloadRequest, ImagePreviewRequest, ImageReadTransform, ImageReprojectRequest, ImageSampleRequest, ImageStorePair, ImageStoreRequest, ImageManagementClient, ImageMosaicPreview, ImageMosaicRequest, ImageOverviewRequest, ImageSearchRequest, ImageSplitRequest, ImageStatisticsRequest, ImageUpdateRequest, ImageUploadChunk, ImageUploadHeader, ImageZonalRequest, Node, NodeListRequest, TokenChannel};
+use serde_json::{json, Value};
 use tonic::Request;
 
+use crate::output::{Output, OutputFormat};
+
 use std::{error, io};
-use std::collections::BTreeMap;
+use std::io::Write;
+use std::collections::{BTreeMap, BTreeSet};
+
+// maximum number of nodes queried concurrently
+const FAN_OUT_LIMIT: usize = 16;
+const UPLOAD_CHUNK_SIZE: usize = 1024 * 1024;
 
 pub fn process(matches: &ArgMatches, data_matches: &ArgMatches) {
     let result: Result<(), Box<dyn error::Error>> 
             = match data_matches.subcommand() {
+        ("bandmath", Some(band_math_matches)) =>
+            band_math(&matches, &data_matches, &band_math_matches),
+        ("calc", Some(calc_matches)) =>
+            calc(&matches, &data_matches, &calc_matches),
+        ("change", Some(change_matches)) =>
+            change(&matches, &data_matches, &change_matches),
         ("coalesce", Some(coalesce_matches)) =>
             coalesce(&matches, &data_matches, &coalesce_matches),
+        ("composite", Some(composite_matches)) =>
+            composite(&matches, &data_matches, &composite_matches),
+        ("copy", Some(copy_matches)) =>
+            copy(&matches, &data_matches, &copy_matches),
+        ("coverage", Some(coverage_matches)) =>
+            coverage(&matches, &data_matches, &coverage_matches),
+        ("delete", Some(delete_matches)) =>
+            delete(&matches, &data_matches, &delete_matches),
+        ("distinct", Some(distinct_matches)) =>
+            distinct(&matches, &data_matches, &distinct_matches),
+        ("download", Some(download_matches)) =>
+            download(&matches, &data_matches, &download_matches),
+        ("execute", Some(execute_matches)) =>
+            execute(&matches, &data_matches, &execute_matches),
+        ("export", Some(export_matches)) =>
+            export(&matches, &data_matches, &export_matches),
         ("fill", Some(fill_matches)) =>
             fill(&matches, &data_matches, &fill_matches),
         ("list", Some(list_matches)) =>
             list(&matches, &data_matches, &list_matches),
+        ("mosaic", Some(mosaic_matches)) =>
+            mosaic(&matches, &data_matches, &mosaic_matches),
+        ("overview", Some(overview_matches)) =>
+            overview(&matches, &data_matches, &overview_matches),
+        ("pipeline", Some(pipeline_matches)) =>
+            pipeline(&matches, &data_matches, &pipeline_matches),
+        ("preload", Some(preload_matches)) =>
+            preload(&matches, &data_matches, &preload_matches),
+        ("preview", Some(preview_matches)) =>
+            preview(&matches, &data_matches, &preview_matches),
+        ("reproject", Some(reproject_matches)) =>
+            reproject(&matches, &data_matches, &reproject_matches),
+        ("sample", Some(sample_matches)) =>
+            sample(&matches, &data_matches, &sample_matches),
         ("search", Some(search_matches)) =>
             search(&matches, &data_matches, &search_matches),
         ("split", Some(split_matches)) =>
             split(&matches, &data_matches, &split_matches),
+        ("statistics", Some(statistics_matches)) =>
+            statistics(&matches, &data_matches, &statistics_matches),
         ("store", Some(store_matches)) =>
             store(&matches, &data_matches, &store_matches),
+        ("update", Some(update_matches)) =>
+            update(&matches, &data_matches, &update_matches),
+        ("upload", Some(upload_matches)) =>
+            upload(&matches, &data_matches, &upload_matches),
+        ("zonal", Some(zonal_matches)) =>
+            zonal(&matches, &data_matches, &zonal_matches),
         (cmd, _) => Err(Box::new(io::Error::new(io::ErrorKind::Other,
             format!("unknown subcommand '{}'", cmd)))),
     };
@@ -30,50 +83,76 @@ pub fn process(matches: &ArgMatches, data_matches: &ArgMatches) {
 }
 
 #[tokio::main]
-async fn coalesce(matches: &ArgMatches, _: &ArgMatches,
-        coalesce_matches: &ArgMatches) -> Result<(), Box<dyn error::Error>> {
+async fn band_math(matches: &ArgMatches, _: &ArgMatches,
+        band_math_matches: &ArgMatches)
+        -> Result<(), Box<dyn error::Error>> {
     // initialize grpc client
     let ip_address = matches.value_of("ip_address").unwrap();
     let port = matches.value_of("port").unwrap().parse::<u16>()?;
-    let mut client = ImageManagementClient::connect(
-        format!("http://{}:{}", ip_address, port)).await?;
+    let mut client = ImageManagementClient::new(crate::channel(matches,
+        &format!("{}:{}", ip_address, port)).await?);
+
+    // parse band numbers
+    let bands = match band_math_matches.value_of("bands") {
+        Some(bands) => bands.split(',').map(|x| x.trim().parse::<u32>())
+            .collect::<Result<Vec<u32>, _>>()?,
+        None => Vec::new(),
+    };
 
     // initialize Filter
     let filter = Filter {
         end_timestamp: crate::i64_opt(
-            coalesce_matches.value_of("end_timestamp"))?,
-        geocode: crate::string_opt(
-            coalesce_matches.value_of("geocode")),
+            band_math_matches.value_of("end_timestamp"))?,
+        geocode: crate::string_opt(band_math_matches.value_of("geocode")),
         max_cloud_coverage: crate::f64_opt(
-            coalesce_matches.value_of("max_cloud_coverage"))?,
+            band_math_matches.value_of("max_cloud_coverage"))?,
         min_pixel_coverage: crate::f64_opt(
-            coalesce_matches.value_of("min_pixel_coverage"))?,
-        platform: crate::string_opt(
-            coalesce_matches.value_of("platform")),
-        recurse: coalesce_matches.is_present("recurse"),
-        source: crate::string_opt(coalesce_matches.value_of("source")),
+            band_math_matches.value_of("min_pixel_coverage"))?,
+        name: None,
+        platform: crate::string_opt(band_math_matches.value_of("platform")),
+        recurse: band_math_matches.is_present("recurse"),
+        source: crate::string_opt(band_math_matches.value_of("source")),
         start_timestamp: crate::i64_opt(
-            coalesce_matches.value_of("start_timestamp"))?,
+            band_math_matches.value_of("start_timestamp"))?,
     };
 
-    // initialize ImageCoalesceRequest
-    let coalesce_request = ImageCoalesceRequest {
-        album: coalesce_matches.value_of("ALBUM").unwrap().to_string(),
+    // initialize ImageBandMathRequest
+    let band_math_request = ImageBandMathRequest {
+        album: band_math_matches.value_of("ALBUM").unwrap().to_string(),
+        bands: bands,
         filter: filter,
-        platform: coalesce_matches.value_of("PLATFORM").unwrap().to_string(),
-        task_id: crate::u64_opt(coalesce_matches.value_of("task_id"))?,
-        thread_count: coalesce_matches.value_of("thread_count")
+        index: band_math_matches.value_of("INDEX").unwrap().to_string(),
+        priority: crate::u32_opt(band_math_matches.value_of("priority"))?,
+        retry_policy: crate::retry_policy_opt(
+            band_math_matches.value_of("max_attempts"),
+            band_math_matches.value_of("retry_backoff"))?,
+        scale: crate::f64_opt(band_math_matches.value_of("scale"))?,
+        subdataset: band_math_matches.value_of("subdataset")
+            .unwrap().parse::<u32>()?,
+        task_id: crate::u64_opt(band_math_matches.value_of("task_id"))?,
+        tenant: crate::string_opt(matches.value_of("tenant")),
+        thread_count: band_math_matches.value_of("thread_count")
             .unwrap().parse::<u32>()?,
-        window_seconds: coalesce_matches.value_of("window_seconds")
-            .unwrap().parse::<i64>()?,
     };
 
     // initialize request
     let request = Request::new(ImageBroadcastRequest {
-        message_type: ImageBroadcastType::Coalesce as i32,
-        coalesce_request: Some(coalesce_request),
+        message_type: ImageBroadcastType::BandMath as i32,
+        band_math_request: Some(band_math_request),
+        calc_request: None,
+        change_request: None,
+        coalesce_request: None,
+        composite_request: None,
+        copy_request: None,
+        delete_request: None,
+        execute_request: None,
         fill_request: None,
+        pipeline_request: None,
+        preload_request: None,
+        reproject_request: None,
         split_request: None,
+        statistics_request: None,
+        update_request: None,
     });
 
     // retrieve reply
@@ -81,54 +160,76 @@ async fn coalesce(matches: &ArgMatches, _: &ArgMatches,
     let reply = reply.get_ref();
 
     // print information
-    for (node_id, coalesce_reply) in reply.coalesce_replies.iter() {
+    for (node_id, band_math_reply) in reply.band_math_replies.iter() {
         println!("task starting on node '{}' with id '{}'",
-            node_id, coalesce_reply.task_id);
+            node_id, band_math_reply.task_id);
     }
 
     Ok(())
 }
 
 #[tokio::main]
-async fn fill(matches: &ArgMatches, _: &ArgMatches,
-        fill_matches: &ArgMatches) -> Result<(), Box<dyn error::Error>> {
+async fn calc(matches: &ArgMatches, _: &ArgMatches,
+        calc_matches: &ArgMatches) -> Result<(), Box<dyn error::Error>> {
     // initialize grpc client
     let ip_address = matches.value_of("ip_address").unwrap();
     let port = matches.value_of("port").unwrap().parse::<u16>()?;
-    let mut client = ImageManagementClient::connect(
-        format!("http://{}:{}", ip_address, port)).await?;
+    let mut client = ImageManagementClient::new(crate::channel(matches,
+        &format!("{}:{}", ip_address, port)).await?);
 
     // initialize Filter
     let filter = Filter {
         end_timestamp: crate::i64_opt(
-            fill_matches.value_of("end_timestamp"))?,
-        geocode: crate::string_opt(fill_matches.value_of("geocode")),
-        max_cloud_coverage: None,
-        min_pixel_coverage: None,
-        platform: crate::string_opt(fill_matches.value_of("platform")),
-        recurse: fill_matches.is_present("recurse"),
-        source: None,
+            calc_matches.value_of("end_timestamp"))?,
+        geocode: crate::string_opt(calc_matches.value_of("geocode")),
+        max_cloud_coverage: crate::f64_opt(
+            calc_matches.value_of("max_cloud_coverage"))?,
+        min_pixel_coverage: crate::f64_opt(
+            calc_matches.value_of("min_pixel_coverage"))?,
+        name: None,
+        platform: crate::string_opt(calc_matches.value_of("platform")),
+        recurse: calc_matches.is_present("recurse"),
+        source: crate::string_opt(calc_matches.value_of("source")),
         start_timestamp: crate::i64_opt(
-            fill_matches.value_of("start_timestamp"))?,
+            calc_matches.value_of("start_timestamp"))?,
     };
 
-    // initialize ImageFillRequest
-    let fill_request = ImageFillRequest {
-        album: fill_matches.value_of("ALBUM").unwrap().to_string(),
+    // initialize ImageCalcRequest
+    let calc_request = ImageCalcRequest {
+        album: calc_matches.value_of("ALBUM").unwrap().to_string(),
+        expression: calc_matches.value_of("EXPRESSION").unwrap().to_string(),
         filter: filter,
-        task_id: crate::u64_opt(fill_matches.value_of("task_id"))?,
-        thread_count: fill_matches.value_of("thread_count")
+        name: calc_matches.value_of("NAME").unwrap().to_string(),
+        priority: crate::u32_opt(calc_matches.value_of("priority"))?,
+        retry_policy: crate::retry_policy_opt(
+            calc_matches.value_of("max_attempts"),
+            calc_matches.value_of("retry_backoff"))?,
+        subdataset: calc_matches.value_of("subdataset")
+            .unwrap().parse::<u32>()?,
+        task_id: crate::u64_opt(calc_matches.value_of("task_id"))?,
+        tenant: crate::string_opt(matches.value_of("tenant")),
+        thread_count: calc_matches.value_of("thread_count")
             .unwrap().parse::<u32>()?,
-        window_seconds: fill_matches.value_of("window_seconds")
-            .unwrap().parse::<i64>()?,
     };
 
     // initialize request
     let request = Request::new(ImageBroadcastRequest {
-        message_type: ImageBroadcastType::Fill as i32,
+        message_type: ImageBroadcastType::Calc as i32,
+        band_math_request: None,
+        calc_request: Some(calc_request),
+        change_request: None,
         coalesce_request: None,
-        fill_request: Some(fill_request),
+        composite_request: None,
+        copy_request: None,
+        delete_request: None,
+        execute_request: None,
+        fill_request: None,
+        pipeline_request: None,
+        preload_request: None,
+        reproject_request: None,
         split_request: None,
+        statistics_request: None,
+        update_request: None,
     });
 
     // retrieve reply
@@ -136,267 +237,427 @@ async fn fill(matches: &ArgMatches, _: &ArgMatches,
     let reply = reply.get_ref();
 
     // print information
-    for (node_id, fill_reply) in reply.fill_replies.iter() {
+    for (node_id, calc_reply) in reply.calc_replies.iter() {
         println!("task starting on node '{}' with id '{}'",
-            node_id, fill_reply.task_id);
+            node_id, calc_reply.task_id);
     }
 
     Ok(())
 }
 
 #[tokio::main]
-async fn list(matches: &ArgMatches, _: &ArgMatches,
-        list_matches: &ArgMatches) -> Result<(), Box<dyn error::Error>> {
-    // initialize NodeManagement grpc client
+async fn change(matches: &ArgMatches, _: &ArgMatches,
+        change_matches: &ArgMatches) -> Result<(), Box<dyn error::Error>> {
+    // initialize grpc client
     let ip_address = matches.value_of("ip_address").unwrap();
     let port = matches.value_of("port").unwrap().parse::<u16>()?;
-    let mut client = NodeManagementClient::connect(
-        format!("http://{}:{}", ip_address, port)).await?;
+    let mut client = ImageManagementClient::new(crate::channel(matches,
+        &format!("{}:{}", ip_address, port)).await?);
 
-    // initialize NodeListRequest
-    let node_list_request = Request::new(NodeListRequest {});
-
-    // retrieve NodeListReply
-    let node_list_reply = client.list(node_list_request).await?;
-    let node_list_reply = node_list_reply.get_ref();
+    // parse band numbers
+    let bands = match change_matches.value_of("bands") {
+        Some(bands) => bands.split(',').map(|x| x.trim().parse::<u32>())
+            .collect::<Result<Vec<u32>, _>>()?,
+        None => Vec::new(),
+    };
 
     // initialize Filter
     let filter = Filter {
-        end_timestamp: crate::i64_opt(
-            list_matches.value_of("end_timestamp"))?,
-        geocode: crate::string_opt(list_matches.value_of("geocode")),
+        end_timestamp: None,
+        geocode: crate::string_opt(change_matches.value_of("geocode")),
         max_cloud_coverage: crate::f64_opt(
-            list_matches.value_of("max_cloud_coverage"))?,
-        min_pixel_coverage: crate::f64_opt(
-            list_matches.value_of("min_pixel_coverage"))?,
-        platform: crate::string_opt(list_matches.value_of("platform")),
-        recurse: list_matches.is_present("recurse"),
-        source: crate::string_opt(list_matches.value_of("source")),
-        start_timestamp: crate::i64_opt(
-            list_matches.value_of("start_timestamp"))?,
+            change_matches.value_of("max_cloud_coverage"))?,
+        min_pixel_coverage: None,
+        name: None,
+        platform: crate::string_opt(change_matches.value_of("platform")),
+        recurse: change_matches.is_present("recurse"),
+        source: crate::string_opt(change_matches.value_of("source")),
+        start_timestamp: None,
     };
 
-    // initialize ImageListRequest
-    let request = ImageListRequest {
-        album: list_matches.value_of("ALBUM").unwrap().to_string(),
+    // initialize ImageChangeRequest
+    let change_request = ImageChangeRequest {
+        after_end: change_matches.value_of("AFTER_END")
+            .unwrap().parse::<i64>()?,
+        after_start: change_matches.value_of("AFTER_START")
+            .unwrap().parse::<i64>()?,
+        album: change_matches.value_of("ALBUM").unwrap().to_string(),
+        bands: bands,
+        before_end: change_matches.value_of("BEFORE_END")
+            .unwrap().parse::<i64>()?,
+        before_start: change_matches.value_of("BEFORE_START")
+            .unwrap().parse::<i64>()?,
         filter: filter,
+        method: crate::string_opt(change_matches.value_of("method")),
+        priority: crate::u32_opt(change_matches.value_of("priority"))?,
+        retry_policy: crate::retry_policy_opt(
+            change_matches.value_of("max_attempts"),
+            change_matches.value_of("retry_backoff"))?,
+        task_id: crate::u64_opt(change_matches.value_of("task_id"))?,
+        tenant: crate::string_opt(matches.value_of("tenant")),
+        thread_count: change_matches.value_of("thread_count")
+            .unwrap().parse::<u32>()?,
     };
 
-    // iterate over each available node
-    println!("{:<8}{:<12}{:<10}{:<8}{:<12}{:<16}{:<16}{:<12}{:<80}",
-        "node", "platform", "geocode", "source", "timestamp",
-        "pixel_coverage", "cloud_coverage", "subdataset", "path");
-    println!("------------------------------------------------------------------------------------------------------------------------------------------------------------------------------");
-    for node in node_list_reply.nodes.iter() {
-        // initialize ImageManagement grpc client
-        let mut client = ImageManagementClient::connect(
-            format!("http://{}", node.rpc_addr)).await?;
+    // initialize request
+    let request = Request::new(ImageBroadcastRequest {
+        message_type: ImageBroadcastType::Change as i32,
+        band_math_request: None,
+        calc_request: None,
+        change_request: Some(change_request),
+        coalesce_request: None,
+        composite_request: None,
+        copy_request: None,
+        delete_request: None,
+        execute_request: None,
+        fill_request: None,
+        pipeline_request: None,
+        preload_request: None,
+        reproject_request: None,
+        split_request: None,
+        statistics_request: None,
+        update_request: None,
+    });
+
+    // retrieve reply
+    let reply = client.broadcast(request).await?;
+    let reply = reply.get_ref();
 
-        // iterate over image stream
-        let mut stream = client.list(Request::new(request.clone()))
-            .await?.into_inner();
-        while let Some(image) = stream.message().await? {
-            for file in image.files.iter() {
-                println!("{:<8}{:<12}{:<10}{:<8}{:<12}{:<16.5}{:<16.5}{:<12}{:<80}",
-                    node.id, image.platform, image.geocode,
-                    image.source, image.timestamp, file.pixel_coverage,
-                    image.cloud_coverage.unwrap_or(-1.0),
-                    file.subdataset, file.path);
-            }
-        }
+    // print information
+    for (node_id, change_reply) in reply.change_replies.iter() {
+        println!("task starting on node '{}' with id '{}'",
+            node_id, change_reply.task_id);
     }
 
     Ok(())
 }
 
 #[tokio::main]
-async fn store(matches: &ArgMatches, _: &ArgMatches,
-        store_matches: &ArgMatches) -> Result<(), Box<dyn error::Error>> {
+async fn coalesce(matches: &ArgMatches, _: &ArgMatches,
+        coalesce_matches: &ArgMatches) -> Result<(), Box<dyn error::Error>> {
     // initialize grpc client
     let ip_address = matches.value_of("ip_address").unwrap();
     let port = matches.value_of("port").unwrap().parse::<u16>()?;
-    let mut client = ImageManagementClient::connect(
-        format!("http://{}:{}", ip_address, port)).await?;
+    let mut client = ImageManagementClient::new(crate::channel(matches,
+        &format!("{}:{}", ip_address, port)).await?);
 
-    // parse load format
-    let format = match store_matches.value_of("FORMAT") {
-        Some("generic") => ImageFormat::Generic as i32,
-        Some("gridmet") => ImageFormat::Gridmet as i32,
-        Some("mcd43a4") => ImageFormat::Mcd43a4 as i32,
-        Some("mod11a1") => ImageFormat::Mod11a1 as i32,
-        Some("mod11a2") => ImageFormat::Mod11a2 as i32,
-        Some("naip") => ImageFormat::Naip as i32,
-        Some("nlcd") => ImageFormat::Nlcd as i32,
-        Some("sentinel2") => ImageFormat::Sentinel2 as i32,
-        Some("vnp21v001") => ImageFormat::Vnp21v001 as i32,
-        _ => unimplemented!(),
-    };
+    // initialize ImageCoalesceRequest
+    let coalesce_request = parse_coalesce_request(coalesce_matches,
+        matches.value_of("tenant"))?;
 
-    // initialize ImageStoreRequest
-    let request = Request::new(ImageStoreRequest {
-        album: store_matches.value_of("ALBUM").unwrap().to_string(),
-        format: format,
-        glob: store_matches.value_of("GLOB").unwrap().to_string(),
-        precision: store_matches.value_of("precision")
-            .unwrap().parse::<u32>()?,
-        task_id: crate::u64_opt(store_matches.value_of("task_id"))?,
-        thread_count: store_matches.value_of("thread_count")
-            .unwrap().parse::<u32>()?,
+    // initialize request
+    let request = Request::new(ImageBroadcastRequest {
+        message_type: ImageBroadcastType::Coalesce as i32,
+        band_math_request: None,
+        calc_request: None,
+        change_request: None,
+        coalesce_request: Some(coalesce_request),
+        composite_request: None,
+        copy_request: None,
+        delete_request: None,
+        execute_request: None,
+        fill_request: None,
+        pipeline_request: None,
+        preload_request: None,
+        reproject_request: None,
+        split_request: None,
+        statistics_request: None,
+        update_request: None,
     });
 
     // retrieve reply
-    let reply = client.store(request).await?;
+    let reply = client.broadcast(request).await?;
     let reply = reply.get_ref();
 
     // print information
-    println!("task starting with id '{}'", reply.task_id);
+    for (node_id, coalesce_reply) in reply.coalesce_replies.iter() {
+        println!("task starting on node '{}' with id '{}'",
+            node_id, coalesce_reply.task_id);
+    }
 
     Ok(())
 }
 
 #[tokio::main]
-async fn search(matches: &ArgMatches, _: &ArgMatches,
-        search_matches: &ArgMatches) -> Result<(), Box<dyn error::Error>> {
-    // initialize NodeManagement grpc client
+async fn composite(matches: &ArgMatches, _: &ArgMatches,
+        composite_matches: &ArgMatches)
+        -> Result<(), Box<dyn error::Error>> {
+    // initialize grpc client
     let ip_address = matches.value_of("ip_address").unwrap();
     let port = matches.value_of("port").unwrap().parse::<u16>()?;
-    let mut client = NodeManagementClient::connect(
-        format!("http://{}:{}", ip_address, port)).await?;
+    let mut client = ImageManagementClient::new(crate::channel(matches,
+        &format!("{}:{}", ip_address, port)).await?);
 
-    // initialize NodeListRequest
-    let node_list_request = Request::new(NodeListRequest {});
+    // parse band numbers
+    let bands = match composite_matches.value_of("bands") {
+        Some(bands) => bands.split(',').map(|x| x.trim().parse::<u32>())
+            .collect::<Result<Vec<u32>, _>>()?,
+        None => Vec::new(),
+    };
 
-    // retrieve NodeListReply
-    let node_list_reply = client.list(node_list_request).await?;
-    let node_list_reply = node_list_reply.get_ref();
+    // initialize Filter
+    let filter = Filter {
+        end_timestamp: crate::i64_opt(
+            composite_matches.value_of("end_timestamp"))?,
+        geocode: crate::string_opt(composite_matches.value_of("geocode")),
+        max_cloud_coverage: crate::f64_opt(
+            composite_matches.value_of("max_cloud_coverage"))?,
+        min_pixel_coverage: None,
+        name: None,
+        platform: crate::string_opt(composite_matches.value_of("PLATFORM")),
+        recurse: composite_matches.is_present("recurse"),
+        source: crate::string_opt(composite_matches.value_of("source")),
+        start_timestamp: crate::i64_opt(
+            composite_matches.value_of("start_timestamp"))?,
+    };
+
+    // initialize ImageCompositeRequest
+    let composite_request = ImageCompositeRequest {
+        album: composite_matches.value_of("ALBUM").unwrap().to_string(),
+        bands: bands,
+        filter: filter,
+        method: composite_matches.value_of("method").unwrap().to_string(),
+        priority: crate::u32_opt(composite_matches.value_of("priority"))?,
+        retry_policy: crate::retry_policy_opt(
+            composite_matches.value_of("max_attempts"),
+            composite_matches.value_of("retry_backoff"))?,
+        task_id: crate::u64_opt(composite_matches.value_of("task_id"))?,
+        tenant: crate::string_opt(matches.value_of("tenant")),
+        thread_count: composite_matches.value_of("thread_count")
+            .unwrap().parse::<u32>()?,
+        window_seconds: crate::i64_opt(
+            composite_matches.value_of("window_seconds"))?,
+    };
+
+    // initialize request
+    let request = Request::new(ImageBroadcastRequest {
+        message_type: ImageBroadcastType::Composite as i32,
+        band_math_request: None,
+        calc_request: None,
+        change_request: None,
+        coalesce_request: None,
+        composite_request: Some(composite_request),
+        copy_request: None,
+        delete_request: None,
+        execute_request: None,
+        fill_request: None,
+        pipeline_request: None,
+        preload_request: None,
+        reproject_request: None,
+        split_request: None,
+        statistics_request: None,
+        update_request: None,
+    });
+
+    // retrieve reply
+    let reply = client.broadcast(request).await?;
+    let reply = reply.get_ref();
+
+    // print information
+    for (node_id, composite_reply) in reply.composite_replies.iter() {
+        println!("task starting on node '{}' with id '{}'",
+            node_id, composite_reply.task_id);
+    }
+
+    Ok(())
+}
+
+#[tokio::main]
+async fn copy(matches: &ArgMatches, _: &ArgMatches,
+        copy_matches: &ArgMatches) -> Result<(), Box<dyn error::Error>> {
+    // initialize grpc client
+    let ip_address = matches.value_of("ip_address").unwrap();
+    let port = matches.value_of("port").unwrap().parse::<u16>()?;
+    let mut client = ImageManagementClient::new(crate::channel(matches,
+        &format!("{}:{}", ip_address, port)).await?);
 
     // initialize Filter
     let filter = Filter {
         end_timestamp: crate::i64_opt(
-            search_matches.value_of("end_timestamp"))?,
-        geocode: crate::string_opt(search_matches.value_of("geocode")),
+            copy_matches.value_of("end_timestamp"))?,
+        geocode: crate::string_opt(copy_matches.value_of("geocode")),
         max_cloud_coverage: crate::f64_opt(
-            search_matches.value_of("max_cloud_coverage"))?,
+            copy_matches.value_of("max_cloud_coverage"))?,
         min_pixel_coverage: crate::f64_opt(
-            search_matches.value_of("min_pixel_coverage"))?,
-        platform: crate::string_opt(search_matches.value_of("platform")),
-        recurse: search_matches.is_present("recurse"),
-        source: crate::string_opt(search_matches.value_of("source")),
+            copy_matches.value_of("min_pixel_coverage"))?,
+        name: crate::string_opt(copy_matches.value_of("filter")),
+        platform: crate::string_opt(copy_matches.value_of("platform")),
+        recurse: copy_matches.is_present("recurse"),
+        source: crate::string_opt(copy_matches.value_of("source")),
         start_timestamp: crate::i64_opt(
-            search_matches.value_of("start_timestamp"))?,
+            copy_matches.value_of("start_timestamp"))?,
     };
 
-    // initialize ImageSearchRequest
-    let request = ImageSearchRequest {
-        album: search_matches.value_of("ALBUM").unwrap().to_string(),
+    // initialize ImageCopyRequest
+    let copy_request = ImageCopyRequest {
+        album: copy_matches.value_of("ALBUM").unwrap().to_string(),
+        destination: copy_matches.value_of("DESTINATION")
+            .unwrap().to_string(),
         filter: filter,
+        precision: crate::u32_opt(copy_matches.value_of("precision"))?,
+        priority: crate::u32_opt(copy_matches.value_of("priority"))?,
+        task_id: crate::u64_opt(copy_matches.value_of("task_id"))?,
+        thread_count: copy_matches.value_of("thread_count")
+            .unwrap().parse::<u32>()?,
     };
 
-    // maintain streams vector
-    let mut clients = Vec::new();
-    for node in node_list_reply.nodes.iter() {
-        // initialize ImageManagement grpc client
-        let client = ImageManagementClient::connect(
-            format!("http://{}", node.rpc_addr)).await?;
+    // initialize request
+    let request = Request::new(ImageBroadcastRequest {
+        message_type: ImageBroadcastType::Copy as i32,
+        band_math_request: None,
+        calc_request: None,
+        change_request: None,
+        coalesce_request: None,
+        composite_request: None,
+        copy_request: Some(copy_request),
+        delete_request: None,
+        execute_request: None,
+        fill_request: None,
+        pipeline_request: None,
+        preload_request: None,
+        reproject_request: None,
+        split_request: None,
+        statistics_request: None,
+        update_request: None,
+    });
+
+    // retrieve reply
+    let reply = client.broadcast(request).await?;
+    let reply = reply.get_ref();
 
-        clients.push(client);
+    // print information
+    for (node_id, copy_reply) in reply.copy_replies.iter() {
+        println!("task starting on node '{}' with id '{}'",
+            node_id, copy_reply.task_id);
     }
 
-    let mut replies = Vec::new();
-    for client in clients.iter_mut() {
-        // iterate over image stream
-        let reply = client.search(Request::new(request.clone()));
-        replies.push(reply);
-    }
+    Ok(())
+}
 
-    let mut streams: Vec<tonic::codec::Streaming<Extent>> = Vec::new();
-    for reply in replies {
-        let stream = reply.await?.into_inner();
-        streams.push(stream);
-    }
+#[tokio::main]
+async fn coverage(matches: &ArgMatches, _: &ArgMatches,
+        coverage_matches: &ArgMatches) -> Result<(), Box<dyn error::Error>> {
+    // initialize grpc client
+    let ip_address = matches.value_of("ip_address").unwrap();
+    let port = matches.value_of("port").unwrap().parse::<u16>()?;
+    let mut client = ImageManagementClient::new(crate::channel(matches,
+        &format!("{}:{}", ip_address, port)).await?);
 
-    let mut stream_index = streams.len();
-    let mut platform_map = BTreeMap::new();
-    while streams.len() != 0 {
-        stream_index = (stream_index + 1) % streams.len();
-        if let Some(extent) = streams[stream_index].message().await? {
-            let geocode_map = platform_map.entry(
-                extent.platform.clone()).or_insert(BTreeMap::new());
+    // read optional wkt or geojson geometry and bounding box
+    let geometry = match coverage_matches.value_of("geometry") {
+        Some(path) => Some(std::fs::read_to_string(path)?),
+        None => None,
+    };
 
-            let source_map = geocode_map.entry(
-                extent.geocode.clone()).or_insert(BTreeMap::new());
+    let bounds = crate::bounds_opt(coverage_matches.value_of("bounds"))?;
 
-            let count_map = source_map.entry(
-                extent.source.clone()).or_insert(BTreeMap::new());
+    // initialize Filter
+    let filter = Filter {
+        end_timestamp: Some(coverage_matches.value_of("END_TIMESTAMP")
+            .unwrap().parse::<i64>()?),
+        geocode: crate::string_opt(coverage_matches.value_of("geocode")),
+        max_cloud_coverage: crate::f64_opt(
+            coverage_matches.value_of("max_cloud_coverage"))?,
+        min_pixel_coverage: crate::f64_opt(
+            coverage_matches.value_of("min_pixel_coverage"))?,
+        name: None,
+        platform: crate::string_opt(coverage_matches.value_of("platform")),
+        recurse: coverage_matches.is_present("recurse"),
+        source: crate::string_opt(coverage_matches.value_of("source")),
+        start_timestamp: Some(coverage_matches.value_of("START_TIMESTAMP")
+            .unwrap().parse::<i64>()?),
+    };
 
-            let count = count_map.entry(extent.precision)
-                .or_insert(0);
-            *count += extent.count;
-        } else {
-            let _ = streams.remove(stream_index);
-        }
-    }
+    // initialize ImageCoverageRequest
+    let request = Request::new(ImageCoverageRequest {
+        album: coverage_matches.value_of("ALBUM").unwrap().to_string(),
+        filter: filter,
+        geometry: geometry,
+        interval_seconds: coverage_matches.value_of("interval_seconds")
+            .unwrap().parse::<i64>()?,
+        local: None,
+        max_latitude: bounds.as_ref().map(|x| x[3]),
+        max_longitude: bounds.as_ref().map(|x| x[1]),
+        min_latitude: bounds.as_ref().map(|x| x[2]),
+        min_longitude: bounds.as_ref().map(|x| x[0]),
+        precision: coverage_matches.value_of("PRECISION")
+            .unwrap().parse::<u32>()?,
+    });
 
-    // print summarized data
-    println!("{:<16}{:<10}{:<12}{:<12}{:<12}", "platform",
-        "geocode", "source", "precision", "count");
-    println!("--------------------------------------------------------------");
-    for (platform, geocode_map) in platform_map.iter() {
-        for (geocode, source_map) in geocode_map.iter() {
-            for (source, count_map) in source_map.iter() {
-                for (precision, count) in count_map.iter() {
-                    println!("{:<16}{:<10}{:<12}{:<12}{:<12}",
-                        platform, geocode, source, precision, count);
-                }
-            }
-        }
+    // retrieve reply
+    let reply = client.coverage(request).await?;
+    let reply = reply.get_ref();
+
+    // print information
+    println!("{:<16}{:<16}{:<16}", "geocode", "start_timestamp",
+        "end_timestamp");
+    println!("------------------------------------------------");
+    for gap in reply.gaps.iter() {
+        println!("{:<16}{:<16}{:<16}", gap.geocode,
+            gap.start_timestamp, gap.end_timestamp);
     }
 
+    println!("{} of {} geocode interval(s) lack imagery ({} geocode(s), {} interval(s))",
+        reply.gaps.len(), reply.geocode_count * reply.interval_count,
+        reply.geocode_count, reply.interval_count);
+
     Ok(())
 }
 
 #[tokio::main]
-async fn split(matches: &ArgMatches, _: &ArgMatches,
-        split_matches: &ArgMatches) -> Result<(), Box<dyn error::Error>> {
+async fn delete(matches: &ArgMatches, _: &ArgMatches,
+        delete_matches: &ArgMatches) -> Result<(), Box<dyn error::Error>> {
     // initialize grpc client
     let ip_address = matches.value_of("ip_address").unwrap();
     let port = matches.value_of("port").unwrap().parse::<u16>()?;
-    let mut client = ImageManagementClient::connect(
-        format!("http://{}:{}", ip_address, port)).await?;
+    let mut client = ImageManagementClient::new(crate::channel(matches,
+        &format!("{}:{}", ip_address, port)).await?);
 
     // initialize Filter
     let filter = Filter {
         end_timestamp: crate::i64_opt(
-            split_matches.value_of("end_timestamp"))?,
-        geocode: crate::string_opt(split_matches.value_of("geocode")),
-        max_cloud_coverage: None,
-        min_pixel_coverage: None,
-        platform: crate::string_opt(split_matches.value_of("platform")),
-        recurse: split_matches.is_present("recurse"),
-        source: None,
+            delete_matches.value_of("end_timestamp"))?,
+        geocode: crate::string_opt(delete_matches.value_of("geocode")),
+        max_cloud_coverage: crate::f64_opt(
+            delete_matches.value_of("max_cloud_coverage"))?,
+        min_pixel_coverage: crate::f64_opt(
+            delete_matches.value_of("min_pixel_coverage"))?,
+        name: crate::string_opt(delete_matches.value_of("filter")),
+        platform: crate::string_opt(delete_matches.value_of("platform")),
+        recurse: delete_matches.is_present("recurse"),
+        source: crate::string_opt(delete_matches.value_of("source")),
         start_timestamp: crate::i64_opt(
-            split_matches.value_of("start_timestamp"))?,
+            delete_matches.value_of("start_timestamp"))?,
     };
 
-    // initialize ImageSplitRequest
-    let split_request = ImageSplitRequest {
-        album: split_matches.value_of("ALBUM").unwrap().to_string(),
+    // initialize ImageDeleteRequest
+    let delete_request = ImageDeleteRequest {
+        album: delete_matches.value_of("ALBUM").unwrap().to_string(),
         filter: filter,
-        geocode_bound: crate::string_opt(
-            split_matches.value_of("geocode_bound")),
-        precision: split_matches.value_of("precision")
-            .unwrap().parse::<u32>()?,
-        task_id: crate::u64_opt(split_matches.value_of("task_id"))?,
-        thread_count: split_matches.value_of("thread_count")
+        priority: crate::u32_opt(delete_matches.value_of("priority"))?,
+        task_id: crate::u64_opt(delete_matches.value_of("task_id"))?,
+        thread_count: delete_matches.value_of("thread_count")
             .unwrap().parse::<u32>()?,
     };
 
     // initialize request
     let request = Request::new(ImageBroadcastRequest {
-        message_type: ImageBroadcastType::Split as i32,
+        message_type: ImageBroadcastType::Delete as i32,
+        band_math_request: None,
+        calc_request: None,
+        change_request: None,
         coalesce_request: None,
+        composite_request: None,
+        copy_request: None,
+        delete_request: Some(delete_request),
+        execute_request: None,
         fill_request: None,
-        split_request: Some(split_request),
+        pipeline_request: None,
+        preload_request: None,
+        reproject_request: None,
+        split_request: None,
+        statistics_request: None,
+        update_request: None,
     });
 
     // retrieve reply
@@ -404,10 +665,1698 @@ async fn split(matches: &ArgMatches, _: &ArgMatches,
     let reply = reply.get_ref();
 
     // print information
-    for (node_id, split_reply) in reply.split_replies.iter() {
+    for (node_id, delete_reply) in reply.delete_replies.iter() {
         println!("task starting on node '{}' with id '{}'",
-            node_id, split_reply.task_id);
+            node_id, delete_reply.task_id);
+    }
+
+    Ok(())
+}
+
+#[tokio::main]
+async fn distinct(matches: &ArgMatches, _: &ArgMatches,
+        distinct_matches: &ArgMatches)
+        -> Result<(), Box<dyn error::Error>> {
+    // initialize ImageDistinctRequest
+    let request = ImageDistinctRequest {
+        album: distinct_matches.value_of("ALBUM").unwrap().to_string(),
+        precision: distinct_matches.value_of("precision")
+            .unwrap().parse::<u32>()?,
+    };
+
+    // merge distinct values of each node
+    let mut geocodes = BTreeSet::new();
+    let mut platforms = BTreeSet::new();
+    let mut sources = BTreeSet::new();
+    let mut subdatasets = BTreeSet::new();
+
+    let ip_address = matches.value_of("ip_address").unwrap();
+    let port = matches.value_of("port").unwrap().parse::<u16>()?;
+    for node in list_nodes(matches,
+            &format!("{}:{}", ip_address, port)).await? {
+        let mut client = ImageManagementClient::new(crate::channel(matches,
+            &node.rpc_addr).await?);
+
+        let reply = client.distinct(Request::new(request.clone()))
+            .await?.into_inner();
+        geocodes.extend(reply.geocodes.into_iter());
+        platforms.extend(reply.platforms.into_iter());
+        sources.extend(reply.sources.into_iter());
+        subdatasets.extend(reply.subdatasets.into_iter());
+    }
+
+    // print information
+    for (name, values) in vec![
+            ("platforms", platforms.into_iter().collect::<Vec<String>>()),
+            ("sources", sources.into_iter().collect()),
+            ("subdatasets", subdatasets.iter()
+                .map(|x: &u32| x.to_string()).collect()),
+            ("geocodes", geocodes.into_iter().collect())] {
+        println!("{} ({}):", name, values.len());
+        for value in values.iter() {
+            println!("    {}", value);
+        }
     }
 
     Ok(())
 }
+
+#[tokio::main]
+async fn download(matches: &ArgMatches, _: &ArgMatches,
+        download_matches: &ArgMatches)
+        -> Result<(), Box<dyn error::Error>> {
+    // initialize grpc client
+    let ip_address = matches.value_of("ip_address").unwrap();
+    let port = matches.value_of("port").unwrap().parse::<u16>()?;
+    let mut client = ImageManagementClient::new(crate::channel(matches,
+        &format!("{}:{}", ip_address, port)).await?);
+
+    // initialize Filter
+    let filter = Filter {
+        end_timestamp: crate::i64_opt(
+            download_matches.value_of("end_timestamp"))?,
+        geocode: crate::string_opt(download_matches.value_of("geocode")),
+        max_cloud_coverage: crate::f64_opt(
+            download_matches.value_of("max_cloud_coverage"))?,
+        min_pixel_coverage: crate::f64_opt(
+            download_matches.value_of("min_pixel_coverage"))?,
+        name: crate::string_opt(download_matches.value_of("filter")),
+        platform: crate::string_opt(download_matches.value_of("platform")),
+        recurse: download_matches.is_present("recurse"),
+        source: crate::string_opt(download_matches.value_of("source")),
+        start_timestamp: crate::i64_opt(
+            download_matches.value_of("start_timestamp"))?,
+    };
+
+    // initialize request
+    let request = Request::new(ImageDownloadRequest {
+        album: download_matches.value_of("ALBUM").unwrap().to_string(),
+        filter: filter,
+        mosaic: Some(download_matches.is_present("mosaic")),
+        subdataset: crate::u32_opt(download_matches.value_of("subdataset"))?,
+        tenant: crate::string_opt(matches.value_of("tenant")),
+        transform: parse_read_transform(download_matches)?,
+    });
+
+    // write streamed geotiffs beneath the output directory
+    let directory = std::path::PathBuf::from(
+        download_matches.value_of("DIRECTORY").unwrap());
+    let mut stream = client.download(request).await?.into_inner();
+    let (mut count, mut len) = (0, 0);
+    let mut current: Option<(String, std::fs::File)> = None;
+    while let Some(chunk) = stream.message().await? {
+        if current.as_ref().map(|x| x.0 != chunk.path).unwrap_or(true) {
+            let path = directory.join(&chunk.path);
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+
+            current = Some((chunk.path.clone(),
+                std::fs::File::create(&path)?));
+            count += 1;
+        }
+
+        if let Some((_, file)) = current.as_mut() {
+            file.write_all(&chunk.data)?;
+        }
+
+        len += chunk.data.len();
+    }
+
+    println!("wrote {} files ({} bytes) to '{}'",
+        count, len, directory.display());
+    Ok(())
+}
+
+#[tokio::main]
+async fn execute(matches: &ArgMatches, _: &ArgMatches,
+        execute_matches: &ArgMatches) -> Result<(), Box<dyn error::Error>> {
+    // initialize grpc client
+    let ip_address = matches.value_of("ip_address").unwrap();
+    let port = matches.value_of("port").unwrap().parse::<u16>()?;
+    let mut client = ImageManagementClient::new(crate::channel(matches,
+        &format!("{}:{}", ip_address, port)).await?);
+
+    // initialize Filter
+    let filter = Filter {
+        end_timestamp: crate::i64_opt(
+            execute_matches.value_of("end_timestamp"))?,
+        geocode: crate::string_opt(execute_matches.value_of("geocode")),
+        max_cloud_coverage: crate::f64_opt(
+            execute_matches.value_of("max_cloud_coverage"))?,
+        min_pixel_coverage: crate::f64_opt(
+            execute_matches.value_of("min_pixel_coverage"))?,
+        name: None,
+        platform: crate::string_opt(execute_matches.value_of("platform")),
+        recurse: execute_matches.is_present("recurse"),
+        source: crate::string_opt(execute_matches.value_of("source")),
+        start_timestamp: crate::i64_opt(
+            execute_matches.value_of("start_timestamp"))?,
+    };
+
+    // initialize ImageExecuteRequest
+    let execute_request = ImageExecuteRequest {
+        album: execute_matches.value_of("ALBUM").unwrap().to_string(),
+        executor: execute_matches.value_of("EXECUTOR").unwrap().to_string(),
+        filter: filter,
+        parameters: crate::string_opt(execute_matches.value_of("parameters")),
+        priority: crate::u32_opt(execute_matches.value_of("priority"))?,
+        retry_policy: crate::retry_policy_opt(
+            execute_matches.value_of("max_attempts"),
+            execute_matches.value_of("retry_backoff"))?,
+        task_id: crate::u64_opt(execute_matches.value_of("task_id"))?,
+        tenant: crate::string_opt(matches.value_of("tenant")),
+        thread_count: execute_matches.value_of("thread_count")
+            .unwrap().parse::<u32>()?,
+    };
+
+    // initialize request
+    let request = Request::new(ImageBroadcastRequest {
+        message_type: ImageBroadcastType::Execute as i32,
+        band_math_request: None,
+        calc_request: None,
+        change_request: None,
+        coalesce_request: None,
+        composite_request: None,
+        copy_request: None,
+        delete_request: None,
+        execute_request: Some(execute_request),
+        fill_request: None,
+        pipeline_request: None,
+        preload_request: None,
+        reproject_request: None,
+        split_request: None,
+        statistics_request: None,
+        update_request: None,
+    });
+
+    // retrieve reply
+    let reply = client.broadcast(request).await?;
+    let reply = reply.get_ref();
+
+    // print information
+    for (node_id, execute_reply) in reply.execute_replies.iter() {
+        println!("task starting on node '{}' with id '{}'",
+            node_id, execute_reply.task_id);
+    }
+
+    Ok(())
+}
+
+#[tokio::main]
+async fn export(matches: &ArgMatches, _: &ArgMatches,
+        export_matches: &ArgMatches) -> Result<(), Box<dyn error::Error>> {
+    // initialize grpc client
+    let ip_address = matches.value_of("ip_address").unwrap();
+    let port = matches.value_of("port").unwrap().parse::<u16>()?;
+    let mut client = ImageManagementClient::new(crate::channel(matches,
+        &format!("{}:{}", ip_address, port)).await?);
+
+    // initialize Filter
+    let filter = Filter {
+        end_timestamp: crate::i64_opt(
+            export_matches.value_of("end_timestamp"))?,
+        geocode: crate::string_opt(export_matches.value_of("geocode")),
+        max_cloud_coverage: crate::f64_opt(
+            export_matches.value_of("max_cloud_coverage"))?,
+        min_pixel_coverage: crate::f64_opt(
+            export_matches.value_of("min_pixel_coverage"))?,
+        name: None,
+        platform: crate::string_opt(export_matches.value_of("platform")),
+        recurse: export_matches.is_present("recurse"),
+        source: crate::string_opt(export_matches.value_of("source")),
+        start_timestamp: crate::i64_opt(
+            export_matches.value_of("start_timestamp"))?,
+    };
+
+    // initialize ImageExportRequest
+    let request = Request::new(ImageExportRequest {
+        album: export_matches.value_of("ALBUM").unwrap().to_string(),
+        assets: crate::string_opt(export_matches.value_of("assets")),
+        destination: export_matches.value_of("DESTINATION")
+            .unwrap().to_string(),
+        filter: filter,
+        priority: crate::u32_opt(export_matches.value_of("priority"))?,
+        retry_policy: crate::retry_policy_opt(
+            export_matches.value_of("max_attempts"),
+            export_matches.value_of("retry_backoff"))?,
+        task_id: crate::u64_opt(export_matches.value_of("task_id"))?,
+        tenant: crate::string_opt(matches.value_of("tenant")),
+        thread_count: export_matches.value_of("thread_count")
+            .unwrap().parse::<u32>()?,
+    });
+
+    // retrieve reply
+    let reply = client.export(request).await?;
+    let reply = reply.get_ref();
+
+    // print information
+    println!("task starting with id '{}'", reply.task_id);
+
+    Ok(())
+}
+
+#[tokio::main]
+async fn fill(matches: &ArgMatches, _: &ArgMatches,
+        fill_matches: &ArgMatches) -> Result<(), Box<dyn error::Error>> {
+    // initialize grpc client
+    let ip_address = matches.value_of("ip_address").unwrap();
+    let port = matches.value_of("port").unwrap().parse::<u16>()?;
+    let mut client = ImageManagementClient::new(crate::channel(matches,
+        &format!("{}:{}", ip_address, port)).await?);
+
+    // initialize ImageFillRequest
+    let fill_request = parse_fill_request(fill_matches,
+        matches.value_of("tenant"))?;
+
+    // initialize request
+    let request = Request::new(ImageBroadcastRequest {
+        message_type: ImageBroadcastType::Fill as i32,
+        band_math_request: None,
+        calc_request: None,
+        change_request: None,
+        coalesce_request: None,
+        composite_request: None,
+        copy_request: None,
+        delete_request: None,
+        execute_request: None,
+        fill_request: Some(fill_request),
+        pipeline_request: None,
+        preload_request: None,
+        reproject_request: None,
+        split_request: None,
+        statistics_request: None,
+        update_request: None,
+    });
+
+    // retrieve reply
+    let reply = client.broadcast(request).await?;
+    let reply = reply.get_ref();
+
+    // print information
+    for (node_id, fill_reply) in reply.fill_replies.iter() {
+        match &fill_reply.dry_run {
+            Some(report) => print_dry_run(&node_id.to_string(), report),
+            None => println!("task starting on node '{}' with id '{}'",
+                node_id, fill_reply.task_id),
+        }
+    }
+
+    Ok(())
+}
+
+#[tokio::main]
+async fn list(matches: &ArgMatches, _: &ArgMatches,
+        list_matches: &ArgMatches) -> Result<(), Box<dyn error::Error>> {
+    // compile clusters to query
+    let targets = crate::cluster::targets(matches, list_matches)?;
+    let federated = targets.iter().any(|(name, _)| name.is_some());
+
+    // initialize Filter
+    let filter = Filter {
+        end_timestamp: crate::i64_opt(
+            list_matches.value_of("end_timestamp"))?,
+        geocode: crate::string_opt(list_matches.value_of("geocode")),
+        max_cloud_coverage: crate::f64_opt(
+            list_matches.value_of("max_cloud_coverage"))?,
+        min_pixel_coverage: crate::f64_opt(
+            list_matches.value_of("min_pixel_coverage"))?,
+        name: crate::string_opt(list_matches.value_of("filter")),
+        platform: crate::string_opt(list_matches.value_of("platform")),
+        recurse: list_matches.is_present("recurse"),
+        source: crate::string_opt(list_matches.value_of("source")),
+        start_timestamp: crate::i64_opt(
+            list_matches.value_of("start_timestamp"))?,
+    };
+
+    // read optional wkt or geojson geometry and bounding box
+    let geometry = match list_matches.value_of("geometry") {
+        Some(path) => Some(std::fs::read_to_string(path)?),
+        None => None,
+    };
+
+    let bounds = crate::bounds_opt(list_matches.value_of("bounds"))?;
+
+    // parse requested albums, the first is sent as the primary album
+    let all_albums = list_matches.is_present("all_albums");
+    let mut albums: Vec<String> = list_matches.value_of("ALBUM")
+        .unwrap_or("").split(',').map(|x| x.trim().to_string())
+        .filter(|x| !x.is_empty()).collect();
+    let album = match albums.is_empty() {
+        true => String::new(),
+        false => albums.remove(0),
+    };
+
+    let multiple = all_albums || !albums.is_empty();
+
+    // initialize ImageListRequest
+    let statistics = list_matches.is_present("statistics");
+    let request = ImageListRequest {
+        album: album,
+        albums: albums,
+        all_albums: Some(all_albums),
+        filter: filter,
+        geometry: geometry,
+        max_latitude: bounds.as_ref().map(|x| x[3]),
+        max_longitude: bounds.as_ref().map(|x| x[1]),
+        min_latitude: bounds.as_ref().map(|x| x[2]),
+        min_longitude: bounds.as_ref().map(|x| x[0]),
+        statistics: Some(statistics),
+    };
+
+    // compile output columns
+    let mut columns = Vec::new();
+    if federated {
+        columns.push(("cluster", 16));
+    }
+    if multiple {
+        columns.push(("album", 16));
+    }
+    columns.extend(vec![("node", 8), ("platform", 12), ("geocode", 10),
+        ("source", 8), ("timestamp", 12), ("pixel_coverage", 16),
+        ("cloud_coverage", 16), ("subdataset", 12), ("path", 80),
+        ("misaligned", 0)]);
+
+    // machine readable formats embed statistics within each row
+    let format = matches.value_of("output").unwrap()
+        .parse::<OutputFormat>()?;
+    let inline_statistics = statistics && format != OutputFormat::Table;
+    if inline_statistics {
+        columns.push(("statistics", 0));
+    }
+
+    let output = Output::new(matches, columns)?.select(list_matches)?;
+
+    // query every node of each cluster concurrently
+    let mut queries = Vec::new();
+    for (name, address) in targets.iter() {
+        for (node, channel) in node_channels(matches, address).await? {
+            queries.push((name, node.id, channel));
+        }
+    }
+
+    let mut images: Vec<(&Option<String>, u32, Image)> =
+            stream::iter(queries).map(|(name, node_id, channel)| {
+        let request = request.clone();
+        async move {
+            let mut client = ImageManagementClient::new(channel);
+            let mut stream = client.list(Request::new(request))
+                .await?.into_inner();
+
+            let mut images = Vec::new();
+            while let Some(image) = stream.message().await? {
+                images.push((name, node_id, image));
+            }
+
+            Ok::<_, Box<dyn error::Error>>(images)
+        }
+    }).buffer_unordered(FAN_OUT_LIMIT).try_concat().await?;
+
+    // merge node results into a deterministic order
+    images.sort_by(|(a_name, a_node, a), (b_name, b_node, b)|
+        (a_name, &a.album, a.timestamp, &a.geocode, &a.platform,
+            &a.source, a_node)
+        .cmp(&(b_name, &b.album, b.timestamp, &b.geocode, &b.platform,
+            &b.source, b_node)));
+
+    output.header();
+    for (name, node_id, image) in images.iter() {
+        for file in image.files.iter() {
+            let mut values = Vec::new();
+            if let Some(name) = name {
+                values.push(json!(name));
+            }
+            if multiple {
+                values.push(json!(image.album));
+            }
+            values.extend(vec![json!(node_id), json!(image.platform),
+                json!(image.geocode), json!(image.source),
+                json!(image.timestamp), json!(file.pixel_coverage),
+                json!(image.cloud_coverage), json!(file.subdataset),
+                json!(file.path), json!(file.misaligned)]);
+            if inline_statistics {
+                values.push(Value::Array(file.statistics.iter()
+                    .map(|x| json!({
+                        "band": x.band,
+                        "max": x.max,
+                        "mean": x.mean,
+                        "min": x.min,
+                        "stddev": x.stddev,
+                    })).collect()));
+            }
+
+            let lines = match statistics && !inline_statistics {
+                true => file.statistics.iter().map(|band| format!(
+                    "    band {:<4} min {:<14.5} max {:<14.5} mean {:<14.5} stddev {:<14.5}",
+                    band.band, band.min, band.max,
+                    band.mean, band.stddev)).collect(),
+                false => Vec::new(),
+            };
+
+            output.row_with_lines(values, lines);
+        }
+    }
+
+    output.flush();
+    Ok(())
+}
+
+// connect to every node of the cluster at address concurrently, reusing
+//   the channel of the contacted node
+async fn node_channels(matches: &ArgMatches, address: &str)
+        -> Result<Vec<(Node, TokenChannel)>, Box<dyn error::Error>> {
+    let coordinator = crate::channel(matches, address).await?;
+    let mut client = NodeManagementClient::new(coordinator.clone());
+    let reply = client.list(Request::new(NodeListRequest {})).await?;
+
+    let mut channels: Vec<(Node, TokenChannel)> =
+            stream::iter(reply.into_inner().nodes).map(|node| {
+        let coordinator = coordinator.clone();
+        async move {
+            let channel = match node.rpc_addr == address {
+                true => coordinator,
+                false => crate::channel(matches, &node.rpc_addr).await?,
+            };
+
+            Ok::<_, Box<dyn error::Error>>((node, channel))
+        }
+    }).buffer_unordered(FAN_OUT_LIMIT).try_collect().await?;
+
+    channels.sort_by_key(|(node, _)| node.id);
+    Ok(channels)
+}
+
+async fn list_nodes(matches: &ArgMatches, address: &str)
+        -> Result<Vec<Node>, Box<dyn error::Error>> {
+    // initialize NodeManagement grpc client
+    let mut client = NodeManagementClient::new(crate::channel(matches,
+        &address).await?);
+
+    // retrieve NodeListReply
+    let reply = client.list(Request::new(NodeListRequest {})).await?;
+    Ok(reply.into_inner().nodes)
+}
+
+#[tokio::main]
+async fn mosaic(matches: &ArgMatches, _: &ArgMatches,
+        mosaic_matches: &ArgMatches) -> Result<(), Box<dyn error::Error>> {
+    // initialize grpc client
+    let ip_address = matches.value_of("ip_address").unwrap();
+    let port = matches.value_of("port").unwrap().parse::<u16>()?;
+    let mut client = ImageManagementClient::new(crate::channel(matches,
+        &format!("{}:{}", ip_address, port)).await?);
+
+    // parse clip bounds
+    let bounds = crate::bounds_opt(mosaic_matches.value_of("bounds"))?;
+
+    // initialize Filter
+    let filter = Filter {
+        end_timestamp: crate::i64_opt(
+            mosaic_matches.value_of("end_timestamp"))?,
+        geocode: crate::string_opt(mosaic_matches.value_of("geocode")),
+        max_cloud_coverage: crate::f64_opt(
+            mosaic_matches.value_of("max_cloud_coverage"))?,
+        min_pixel_coverage: crate::f64_opt(
+            mosaic_matches.value_of("min_pixel_coverage"))?,
+        name: None,
+        platform: crate::string_opt(mosaic_matches.value_of("platform")),
+        recurse: mosaic_matches.is_present("recurse"),
+        source: crate::string_opt(mosaic_matches.value_of("source")),
+        start_timestamp: crate::i64_opt(
+            mosaic_matches.value_of("start_timestamp"))?,
+    };
+
+    // rendering arguments imply a preview
+    let preview = match ["bands", "format", "preview", "size"].iter()
+            .any(|x| mosaic_matches.is_present(x)) {
+        true => Some(ImageMosaicPreview {
+            bands: match mosaic_matches.value_of("bands") {
+                Some(bands) => bands.split(',')
+                    .map(|x| x.trim().parse::<u32>())
+                    .collect::<Result<Vec<u32>, _>>()?,
+                None => Vec::new(),
+            },
+            format: crate::string_opt(mosaic_matches.value_of("format")),
+            size: crate::u32_opt(mosaic_matches.value_of("size"))?,
+        }),
+        false => None,
+    };
+
+    let kind = match preview.is_some() {
+        true => "preview",
+        false => "mosaic",
+    };
+
+    // initialize ImageMosaicRequest
+    let request = Request::new(ImageMosaicRequest {
+        album: mosaic_matches.value_of("ALBUM").unwrap().to_string(),
+        filter: filter,
+        min_longitude: bounds.as_ref().map(|x| x[0]),
+        max_longitude: bounds.as_ref().map(|x| x[1]),
+        min_latitude: bounds.as_ref().map(|x| x[2]),
+        max_latitude: bounds.as_ref().map(|x| x[3]),
+        preview: preview,
+        subdataset: mosaic_matches.value_of("subdataset")
+            .unwrap().parse::<u32>()?,
+        tenant: crate::string_opt(matches.value_of("tenant")),
+        transform: parse_read_transform(mosaic_matches)?,
+    });
+
+    // write streamed geotiff or preview to output file
+    let output = mosaic_matches.value_of("OUTPUT").unwrap();
+    let mut file = std::fs::File::create(output)?;
+    let mut stream = client.mosaic(request).await?.into_inner();
+    let mut len = 0;
+    while let Some(chunk) = stream.message().await? {
+        file.write_all(&chunk.data)?;
+        len += chunk.data.len();
+    }
+
+    println!("wrote {} byte {} to '{}'", len, kind, output);
+    Ok(())
+}
+
+#[tokio::main]
+async fn overview(matches: &ArgMatches, _: &ArgMatches,
+        overview_matches: &ArgMatches)
+        -> Result<(), Box<dyn error::Error>> {
+    // initialize grpc client
+    let ip_address = matches.value_of("ip_address").unwrap();
+    let port = matches.value_of("port").unwrap().parse::<u16>()?;
+    let mut client = ImageManagementClient::new(crate::channel(matches,
+        &format!("{}:{}", ip_address, port)).await?);
+
+    // initialize Filter
+    let filter = Filter {
+        end_timestamp: crate::i64_opt(
+            overview_matches.value_of("end_timestamp"))?,
+        geocode: crate::string_opt(overview_matches.value_of("geocode")),
+        max_cloud_coverage: crate::f64_opt(
+            overview_matches.value_of("max_cloud_coverage"))?,
+        min_pixel_coverage: None,
+        name: None,
+        platform: crate::string_opt(overview_matches.value_of("platform")),
+        recurse: true,
+        source: crate::string_opt(overview_matches.value_of("source")),
+        start_timestamp: crate::i64_opt(
+            overview_matches.value_of("start_timestamp"))?,
+    };
+
+    // initialize ImageOverviewRequest
+    let request = Request::new(ImageOverviewRequest {
+        album: overview_matches.value_of("ALBUM").unwrap().to_string(),
+        filter: filter,
+        precision: overview_matches.value_of("PRECISION")
+            .unwrap().parse::<u32>()?,
+        priority: crate::u32_opt(overview_matches.value_of("priority"))?,
+        resampling: crate::string_opt(
+            overview_matches.value_of("resampling")),
+        retry_policy: crate::retry_policy_opt(
+            overview_matches.value_of("max_attempts"),
+            overview_matches.value_of("retry_backoff"))?,
+        task_id: crate::u64_opt(overview_matches.value_of("task_id"))?,
+        tenant: crate::string_opt(matches.value_of("tenant")),
+        thread_count: overview_matches.value_of("thread_count")
+            .unwrap().parse::<u32>()?,
+    });
+
+    // retrieve reply
+    let reply = client.overview(request).await?;
+    let reply = reply.get_ref();
+
+    // print information
+    println!("task starting with id '{}'", reply.task_id);
+
+    Ok(())
+}
+
+#[tokio::main]
+async fn pipeline(matches: &ArgMatches, _: &ArgMatches,
+        pipeline_matches: &ArgMatches) -> Result<(), Box<dyn error::Error>> {
+    // initialize grpc client
+    let ip_address = matches.value_of("ip_address").unwrap();
+    let port = matches.value_of("port").unwrap().parse::<u16>()?;
+    let mut client = ImageManagementClient::new(crate::channel(matches,
+        &format!("{}:{}", ip_address, port)).await?);
+
+    // parse pipeline stages
+    let mut stages = Vec::new();
+    for stage in pipeline_matches.values_of("stage").unwrap() {
+        stages.push(parse_pipeline_stage(stage,
+            matches.value_of("tenant"))?);
+    }
+
+    // initialize ImagePipelineRequest
+    let pipeline_request = ImagePipelineRequest {
+        priority: crate::u32_opt(pipeline_matches.value_of("priority"))?,
+        stages: stages,
+        task_id: crate::u64_opt(pipeline_matches.value_of("task_id"))?,
+    };
+
+    // initialize request
+    let request = Request::new(ImageBroadcastRequest {
+        message_type: ImageBroadcastType::Pipeline as i32,
+        band_math_request: None,
+        calc_request: None,
+        change_request: None,
+        coalesce_request: None,
+        composite_request: None,
+        copy_request: None,
+        delete_request: None,
+        execute_request: None,
+        fill_request: None,
+        pipeline_request: Some(pipeline_request),
+        preload_request: None,
+        reproject_request: None,
+        split_request: None,
+        statistics_request: None,
+        update_request: None,
+    });
+
+    // retrieve reply
+    let reply = client.broadcast(request).await?;
+    let reply = reply.get_ref();
+
+    // print information
+    for (node_id, pipeline_reply) in reply.pipeline_replies.iter() {
+        println!("pipeline starting on node '{}' with id '{}'",
+            node_id, pipeline_reply.task_id);
+    }
+
+    Ok(())
+}
+
+#[tokio::main]
+async fn statistics(matches: &ArgMatches, _: &ArgMatches,
+        statistics_matches: &ArgMatches)
+        -> Result<(), Box<dyn error::Error>> {
+    // initialize grpc client
+    let ip_address = matches.value_of("ip_address").unwrap();
+    let port = matches.value_of("port").unwrap().parse::<u16>()?;
+    let mut client = ImageManagementClient::new(crate::channel(matches,
+        &format!("{}:{}", ip_address, port)).await?);
+
+    // initialize Filter
+    let filter = Filter {
+        end_timestamp: crate::i64_opt(
+            statistics_matches.value_of("end_timestamp"))?,
+        geocode: crate::string_opt(statistics_matches.value_of("geocode")),
+        max_cloud_coverage: crate::f64_opt(
+            statistics_matches.value_of("max_cloud_coverage"))?,
+        min_pixel_coverage: crate::f64_opt(
+            statistics_matches.value_of("min_pixel_coverage"))?,
+        name: None,
+        platform: crate::string_opt(statistics_matches.value_of("platform")),
+        recurse: statistics_matches.is_present("recurse"),
+        source: crate::string_opt(statistics_matches.value_of("source")),
+        start_timestamp: crate::i64_opt(
+            statistics_matches.value_of("start_timestamp"))?,
+    };
+
+    // initialize ImageStatisticsRequest
+    let statistics_request = ImageStatisticsRequest {
+        album: statistics_matches.value_of("ALBUM").unwrap().to_string(),
+        bins: crate::u32_opt(statistics_matches.value_of("bins"))?,
+        filter: filter,
+        priority: crate::u32_opt(statistics_matches.value_of("priority"))?,
+        recompute: Some(statistics_matches.is_present("recompute")),
+        retry_policy: crate::retry_policy_opt(
+            statistics_matches.value_of("max_attempts"),
+            statistics_matches.value_of("retry_backoff"))?,
+        task_id: crate::u64_opt(statistics_matches.value_of("task_id"))?,
+        tenant: crate::string_opt(matches.value_of("tenant")),
+        thread_count: statistics_matches.value_of("thread_count")
+            .unwrap().parse::<u32>()?,
+    };
+
+    // initialize request
+    let request = Request::new(ImageBroadcastRequest {
+        message_type: ImageBroadcastType::Statistics as i32,
+        band_math_request: None,
+        calc_request: None,
+        change_request: None,
+        coalesce_request: None,
+        composite_request: None,
+        copy_request: None,
+        delete_request: None,
+        execute_request: None,
+        fill_request: None,
+        pipeline_request: None,
+        preload_request: None,
+        reproject_request: None,
+        split_request: None,
+        statistics_request: Some(statistics_request),
+        update_request: None,
+    });
+
+    // retrieve reply
+    let reply = client.broadcast(request).await?;
+    let reply = reply.get_ref();
+
+    // print information
+    for (node_id, statistics_reply) in reply.statistics_replies.iter() {
+        println!("task starting on node '{}' with id '{}'",
+            node_id, statistics_reply.task_id);
+    }
+
+    Ok(())
+}
+
+#[tokio::main]
+async fn store(matches: &ArgMatches, _: &ArgMatches,
+        store_matches: &ArgMatches) -> Result<(), Box<dyn error::Error>> {
+    // initialize grpc client
+    let ip_address = matches.value_of("ip_address").unwrap();
+    let port = matches.value_of("port").unwrap().parse::<u16>()?;
+    let mut client = ImageManagementClient::new(crate::channel(matches,
+        &format!("{}:{}", ip_address, port)).await?);
+
+    // initialize ImageStoreRequest
+    let request = Request::new(parse_store_request(store_matches,
+        matches.value_of("tenant"))?);
+
+    // retrieve reply
+    let reply = client.store(request).await?;
+    let reply = reply.get_ref();
+
+    // print information
+    match &reply.dry_run {
+        Some(report) => print_dry_run(ip_address, report),
+        None => println!("task starting with id '{}'", reply.task_id),
+    }
+
+    Ok(())
+}
+
+#[tokio::main]
+async fn preload(matches: &ArgMatches, _: &ArgMatches,
+        preload_matches: &ArgMatches) -> Result<(), Box<dyn error::Error>> {
+    // initialize grpc client
+    let ip_address = matches.value_of("ip_address").unwrap();
+    let port = matches.value_of("port").unwrap().parse::<u16>()?;
+    let mut client = ImageManagementClient::new(crate::channel(matches,
+        &format!("{}:{}", ip_address, port)).await?);
+
+    // initialize Filter
+    let filter = Filter {
+        end_timestamp: crate::i64_opt(
+            preload_matches.value_of("end_timestamp"))?,
+        geocode: crate::string_opt(preload_matches.value_of("geocode")),
+        max_cloud_coverage: crate::f64_opt(
+            preload_matches.value_of("max_cloud_coverage"))?,
+        min_pixel_coverage: crate::f64_opt(
+            preload_matches.value_of("min_pixel_coverage"))?,
+        name: None,
+        platform: crate::string_opt(preload_matches.value_of("platform")),
+        recurse: preload_matches.is_present("recurse"),
+        source: crate::string_opt(preload_matches.value_of("source")),
+        start_timestamp: crate::i64_opt(
+            preload_matches.value_of("start_timestamp"))?,
+    };
+
+    // initialize ImagePreloadRequest
+    let preload_request = ImagePreloadRequest {
+        album: preload_matches.value_of("ALBUM").unwrap().to_string(),
+        filter: filter,
+        priority: crate::u32_opt(preload_matches.value_of("priority"))?,
+        task_id: crate::u64_opt(preload_matches.value_of("task_id"))?,
+        thread_count: preload_matches.value_of("thread_count")
+            .unwrap().parse::<u32>()?,
+    };
+
+    // initialize request
+    let request = Request::new(ImageBroadcastRequest {
+        message_type: ImageBroadcastType::Preload as i32,
+        band_math_request: None,
+        calc_request: None,
+        change_request: None,
+        coalesce_request: None,
+        composite_request: None,
+        copy_request: None,
+        delete_request: None,
+        execute_request: None,
+        fill_request: None,
+        pipeline_request: None,
+        preload_request: Some(preload_request),
+        reproject_request: None,
+        split_request: None,
+        statistics_request: None,
+        update_request: None,
+    });
+
+    // retrieve reply
+    let reply = client.broadcast(request).await?;
+    let reply = reply.get_ref();
+
+    // print information
+    for (node_id, preload_reply) in reply.preload_replies.iter() {
+        println!("task starting on node '{}' with id '{}'",
+            node_id, preload_reply.task_id);
+    }
+
+    Ok(())
+}
+
+#[tokio::main]
+async fn preview(matches: &ArgMatches, _: &ArgMatches,
+        preview_matches: &ArgMatches) -> Result<(), Box<dyn error::Error>> {
+    // initialize grpc client
+    let ip_address = matches.value_of("ip_address").unwrap();
+    let port = matches.value_of("port").unwrap().parse::<u16>()?;
+    let mut client = ImageManagementClient::new(crate::channel(matches,
+        &format!("{}:{}", ip_address, port)).await?);
+
+    // parse band numbers
+    let bands = match preview_matches.value_of("bands") {
+        Some(bands) => bands.split(',').map(|x| x.trim().parse::<u32>())
+            .collect::<Result<Vec<u32>, _>>()?,
+        None => Vec::new(),
+    };
+
+    // initialize request
+    let request = Request::new(ImagePreviewRequest {
+        album: preview_matches.value_of("ALBUM").unwrap().to_string(),
+        bands: bands,
+        format: crate::string_opt(preview_matches.value_of("format")),
+        geocode: preview_matches.value_of("GEOCODE").unwrap().to_string(),
+        local: None,
+        platform: preview_matches.value_of("PLATFORM").unwrap().to_string(),
+        size: crate::u32_opt(preview_matches.value_of("size"))?,
+        source: preview_matches.value_of("SOURCE").unwrap().to_string(),
+        subdataset: preview_matches.value_of("subdataset")
+            .unwrap().parse::<u32>()?,
+        tile: preview_matches.value_of("TILE").unwrap().to_string(),
+    });
+
+    // retrieve reply
+    let reply = client.preview(request).await?;
+    let reply = reply.get_ref();
+
+    // write preview to output file
+    let output = preview_matches.value_of("OUTPUT").unwrap();
+    std::fs::write(output, &reply.data)?;
+    println!("wrote {} preview to '{}'", reply.format, output);
+
+    Ok(())
+}
+
+#[tokio::main]
+async fn reproject(matches: &ArgMatches, _: &ArgMatches,
+        reproject_matches: &ArgMatches)
+        -> Result<(), Box<dyn error::Error>> {
+    // initialize grpc client
+    let ip_address = matches.value_of("ip_address").unwrap();
+    let port = matches.value_of("port").unwrap().parse::<u16>()?;
+    let mut client = ImageManagementClient::new(crate::channel(matches,
+        &format!("{}:{}", ip_address, port)).await?);
+
+    // initialize Filter
+    let filter = Filter {
+        end_timestamp: crate::i64_opt(
+            reproject_matches.value_of("end_timestamp"))?,
+        geocode: crate::string_opt(reproject_matches.value_of("geocode")),
+        max_cloud_coverage: crate::f64_opt(
+            reproject_matches.value_of("max_cloud_coverage"))?,
+        min_pixel_coverage: crate::f64_opt(
+            reproject_matches.value_of("min_pixel_coverage"))?,
+        name: None,
+        platform: crate::string_opt(reproject_matches.value_of("platform")),
+        recurse: reproject_matches.is_present("recurse"),
+        source: crate::string_opt(reproject_matches.value_of("source")),
+        start_timestamp: crate::i64_opt(
+            reproject_matches.value_of("start_timestamp"))?,
+    };
+
+    // initialize ImageReprojectRequest
+    let reproject_request = ImageReprojectRequest {
+        album: reproject_matches.value_of("ALBUM").unwrap().to_string(),
+        epsg_code: reproject_matches.value_of("EPSG")
+            .unwrap().parse::<u32>()?,
+        filter: filter,
+        priority: crate::u32_opt(reproject_matches.value_of("priority"))?,
+        resampling: crate::string_opt(
+            reproject_matches.value_of("resampling")),
+        resolution: crate::f64_opt(
+            reproject_matches.value_of("resolution"))?,
+        retry_policy: crate::retry_policy_opt(
+            reproject_matches.value_of("max_attempts"),
+            reproject_matches.value_of("retry_backoff"))?,
+        task_id: crate::u64_opt(reproject_matches.value_of("task_id"))?,
+        tenant: crate::string_opt(matches.value_of("tenant")),
+        thread_count: reproject_matches.value_of("thread_count")
+            .unwrap().parse::<u32>()?,
+    };
+
+    // initialize request
+    let request = Request::new(ImageBroadcastRequest {
+        message_type: ImageBroadcastType::Reproject as i32,
+        band_math_request: None,
+        calc_request: None,
+        change_request: None,
+        coalesce_request: None,
+        composite_request: None,
+        copy_request: None,
+        delete_request: None,
+        execute_request: None,
+        fill_request: None,
+        pipeline_request: None,
+        preload_request: None,
+        reproject_request: Some(reproject_request),
+        split_request: None,
+        statistics_request: None,
+        update_request: None,
+    });
+
+    // retrieve reply
+    let reply = client.broadcast(request).await?;
+    let reply = reply.get_ref();
+
+    // print information
+    for (node_id, reproject_reply) in reply.reproject_replies.iter() {
+        println!("task starting on node '{}' with id '{}'",
+            node_id, reproject_reply.task_id);
+    }
+
+    Ok(())
+}
+
+#[tokio::main]
+async fn sample(matches: &ArgMatches, _: &ArgMatches,
+        sample_matches: &ArgMatches) -> Result<(), Box<dyn error::Error>> {
+    let ip_address = matches.value_of("ip_address").unwrap();
+    let port = matches.value_of("port").unwrap().parse::<u16>()?;
+
+    // initialize Filter
+    let filter = Filter {
+        end_timestamp: crate::i64_opt(
+            sample_matches.value_of("end_timestamp"))?,
+        geocode: None,
+        max_cloud_coverage: crate::f64_opt(
+            sample_matches.value_of("max_cloud_coverage"))?,
+        min_pixel_coverage: None,
+        name: None,
+        platform: crate::string_opt(sample_matches.value_of("platform")),
+        recurse: true,
+        source: crate::string_opt(sample_matches.value_of("source")),
+        start_timestamp: crate::i64_opt(
+            sample_matches.value_of("start_timestamp"))?,
+    };
+
+    // initialize ImageSampleRequest
+    let request = ImageSampleRequest {
+        album: sample_matches.value_of("ALBUM").unwrap().to_string(),
+        band: sample_matches.value_of("band").unwrap().parse::<u32>()?,
+        buffer: crate::f64_opt(sample_matches.value_of("buffer"))?,
+        filter: filter,
+        latitude: sample_matches.value_of("LATITUDE")
+            .unwrap().parse::<f64>()?,
+        longitude: sample_matches.value_of("LONGITUDE")
+            .unwrap().parse::<f64>()?,
+        subdataset: crate::u32_opt(sample_matches.value_of("subdataset"))?,
+    };
+
+    // collect samples from each node, ignoring replicas
+    let mut samples = BTreeMap::new();
+    for node in list_nodes(matches,
+            &format!("{}:{}", ip_address, port)).await? {
+        let mut client = ImageManagementClient::new(crate::channel(matches,
+            &node.rpc_addr).await?);
+
+        let mut stream = client.sample(Request::new(request.clone()))
+            .await?.into_inner();
+        while let Some(sample) = stream.message().await? {
+            samples.entry((sample.timestamp, sample.platform.clone(),
+                    sample.geocode.clone(), sample.source.clone(),
+                    sample.subdataset))
+                .or_insert(sample);
+        }
+    }
+
+    // print information
+    println!("{:<12}{:<12}{:<10}{:<8}{:<12}{:<16}{:<8}{:<16}",
+        "timestamp", "platform", "geocode", "source", "subdataset",
+        "value", "count", "cloud_coverage");
+    println!("----------------------------------------------------------------------------------------------");
+    for (_, sample) in samples.iter() {
+        println!("{:<12}{:<12}{:<10}{:<8}{:<12}{:<16.5}{:<8}{:<16.5}",
+            sample.timestamp, sample.platform, sample.geocode,
+            sample.source, sample.subdataset, sample.value, sample.count,
+            sample.cloud_coverage.unwrap_or(-1.0));
+    }
+
+    Ok(())
+}
+
+#[tokio::main]
+async fn search(matches: &ArgMatches, _: &ArgMatches,
+        search_matches: &ArgMatches) -> Result<(), Box<dyn error::Error>> {
+    // compile clusters to query
+    let targets = crate::cluster::targets(matches, search_matches)?;
+    let federated = targets.iter().any(|(name, _)| name.is_some());
+
+    // initialize Filter
+    let filter = Filter {
+        end_timestamp: crate::i64_opt(
+            search_matches.value_of("end_timestamp"))?,
+        geocode: crate::string_opt(search_matches.value_of("geocode")),
+        max_cloud_coverage: crate::f64_opt(
+            search_matches.value_of("max_cloud_coverage"))?,
+        min_pixel_coverage: crate::f64_opt(
+            search_matches.value_of("min_pixel_coverage"))?,
+        name: crate::string_opt(search_matches.value_of("filter")),
+        platform: crate::string_opt(search_matches.value_of("platform")),
+        recurse: search_matches.is_present("recurse"),
+        source: crate::string_opt(search_matches.value_of("source")),
+        start_timestamp: crate::i64_opt(
+            search_matches.value_of("start_timestamp"))?,
+    };
+
+    // read optional wkt or geojson geometry and bounding box
+    let geometry = match search_matches.value_of("geometry") {
+        Some(path) => Some(std::fs::read_to_string(path)?),
+        None => None,
+    };
+
+    let bounds = crate::bounds_opt(search_matches.value_of("bounds"))?;
+
+    // initialize ImageSearchRequest
+    let request = ImageSearchRequest {
+        album: search_matches.value_of("ALBUM").unwrap().to_string(),
+        filter: filter,
+        full_geocode: Some(true),
+        geometry: geometry,
+        group_by_interval: crate::string_opt(
+            search_matches.value_of("interval")),
+        max_latitude: bounds.as_ref().map(|x| x[3]),
+        max_longitude: bounds.as_ref().map(|x| x[1]),
+        min_latitude: bounds.as_ref().map(|x| x[2]),
+        min_longitude: bounds.as_ref().map(|x| x[0]),
+    };
+
+    // query every node of each cluster concurrently
+    let mut queries = Vec::new();
+    for (name, address) in targets.iter() {
+        for (_, channel) in node_channels(matches, address).await? {
+            queries.push((name.clone().unwrap_or(String::new()), channel));
+        }
+    }
+
+    let extents: Vec<(String, Extent)> =
+            stream::iter(queries).map(|(name, channel)| {
+        let request = request.clone();
+        async move {
+            let mut client = ImageManagementClient::new(channel);
+            let mut stream = client.search(Request::new(request))
+                .await?.into_inner();
+
+            let mut extents = Vec::new();
+            while let Some(extent) = stream.message().await? {
+                extents.push((name.clone(), extent));
+            }
+
+            Ok::<_, Box<dyn error::Error>>(extents)
+        }
+    }).buffer_unordered(FAN_OUT_LIMIT).try_concat().await?;
+
+    // replicas of a geocode hold the same images, so merge node counts
+    //   of each full geocode before summing them by geocode prefix
+    let mut replica_map = BTreeMap::new();
+    for (name, extent) in extents.into_iter() {
+        let count = replica_map.entry((name, extent.platform.clone(),
+                extent.geocode.clone(), extent.source.clone(),
+                extent.precision, extent.timestamp))
+            .or_insert(extent.clone());
+        count.count = std::cmp::max(count.count, extent.count);
+    }
+
+    let length = request.filter.geocode.as_ref()
+        .map(|x| x.len() + 1).unwrap_or(1);
+
+    let mut cluster_map = BTreeMap::new();
+    for ((name, _, _, _, _, _), extent) in replica_map.into_iter() {
+        let platform_map = cluster_map.entry(name)
+            .or_insert(BTreeMap::new());
+
+        let geocode_map = platform_map.entry(
+            extent.platform.clone()).or_insert(BTreeMap::new());
+
+        let geocode: String = extent.geocode.chars().take(length).collect();
+        let source_map = geocode_map.entry(geocode)
+            .or_insert(BTreeMap::new());
+
+        let count_map = source_map.entry(
+            extent.source.clone()).or_insert(BTreeMap::new());
+
+        let count = count_map.entry((extent.precision,
+            extent.timestamp)).or_insert(0);
+        *count += extent.count;
+    }
+
+    // print summarized data
+    let interval = search_matches.is_present("interval");
+    let mut columns = Vec::new();
+    if federated {
+        columns.push(("cluster", 16));
+    }
+    columns.extend(vec![("platform", 16), ("geocode", 10),
+        ("source", 12), ("precision", 12)]);
+    if interval {
+        columns.push(("interval", 12));
+    }
+    columns.push(("count", 12));
+
+    let output = Output::new(matches, columns)?;
+    output.header();
+    for (name, platform_map) in cluster_map.iter() {
+        for (platform, geocode_map) in platform_map.iter() {
+            for (geocode, source_map) in geocode_map.iter() {
+                for (source, count_map) in source_map.iter() {
+                    for ((precision, timestamp), count) in count_map.iter() {
+                        let mut values = Vec::new();
+                        if federated {
+                            values.push(json!(name));
+                        }
+                        values.extend(vec![json!(platform), json!(geocode),
+                            json!(source), json!(precision)]);
+                        if interval {
+                            values.push(json!(timestamp.unwrap_or(0)));
+                        }
+                        values.push(json!(count));
+
+                        output.row(values);
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[tokio::main]
+async fn split(matches: &ArgMatches, _: &ArgMatches,
+        split_matches: &ArgMatches) -> Result<(), Box<dyn error::Error>> {
+    // initialize grpc client
+    let ip_address = matches.value_of("ip_address").unwrap();
+    let port = matches.value_of("port").unwrap().parse::<u16>()?;
+    let mut client = ImageManagementClient::new(crate::channel(matches,
+        &format!("{}:{}", ip_address, port)).await?);
+
+    // initialize ImageSplitRequest
+    let split_request = parse_split_request(split_matches,
+        matches.value_of("tenant"))?;
+
+    // initialize request
+    let request = Request::new(ImageBroadcastRequest {
+        message_type: ImageBroadcastType::Split as i32,
+        band_math_request: None,
+        calc_request: None,
+        change_request: None,
+        coalesce_request: None,
+        composite_request: None,
+        copy_request: None,
+        delete_request: None,
+        execute_request: None,
+        fill_request: None,
+        pipeline_request: None,
+        preload_request: None,
+        reproject_request: None,
+        split_request: Some(split_request),
+        statistics_request: None,
+        update_request: None,
+    });
+
+    // retrieve reply
+    let reply = client.broadcast(request).await?;
+    let reply = reply.get_ref();
+
+    // print information
+    for (node_id, split_reply) in reply.split_replies.iter() {
+        match &split_reply.dry_run {
+            Some(report) => print_dry_run(&node_id.to_string(), report),
+            None => println!("task starting on node '{}' with id '{}'",
+                node_id, split_reply.task_id),
+        }
+    }
+
+    Ok(())
+}
+
+#[tokio::main]
+async fn update(matches: &ArgMatches, _: &ArgMatches,
+        update_matches: &ArgMatches) -> Result<(), Box<dyn error::Error>> {
+    // initialize grpc client
+    let ip_address = matches.value_of("ip_address").unwrap();
+    let port = matches.value_of("port").unwrap().parse::<u16>()?;
+    let mut client = ImageManagementClient::new(crate::channel(matches,
+        &format!("{}:{}", ip_address, port)).await?);
+
+    // initialize Filter
+    let filter = Filter {
+        end_timestamp: crate::i64_opt(
+            update_matches.value_of("end_timestamp"))?,
+        geocode: crate::string_opt(update_matches.value_of("geocode")),
+        max_cloud_coverage: crate::f64_opt(
+            update_matches.value_of("max_cloud_coverage"))?,
+        min_pixel_coverage: crate::f64_opt(
+            update_matches.value_of("min_pixel_coverage"))?,
+        name: crate::string_opt(update_matches.value_of("filter")),
+        platform: crate::string_opt(update_matches.value_of("platform")),
+        recurse: update_matches.is_present("recurse"),
+        source: crate::string_opt(update_matches.value_of("source")),
+        start_timestamp: crate::i64_opt(
+            update_matches.value_of("start_timestamp"))?,
+    };
+
+    // initialize ImageUpdateRequest
+    let update_request = ImageUpdateRequest {
+        album: update_matches.value_of("ALBUM").unwrap().to_string(),
+        cloud_coverage: crate::f64_opt(
+            update_matches.value_of("set_cloud_coverage"))?,
+        filter: filter,
+        priority: crate::u32_opt(update_matches.value_of("priority"))?,
+        task_id: crate::u64_opt(update_matches.value_of("task_id"))?,
+        thread_count: update_matches.value_of("thread_count")
+            .unwrap().parse::<u32>()?,
+        timestamp: crate::i64_opt(update_matches.value_of("set_timestamp"))?,
+        timestamp_offset: crate::i64_opt(
+            update_matches.value_of("timestamp_offset"))?,
+    };
+
+    // initialize request
+    let request = Request::new(ImageBroadcastRequest {
+        message_type: ImageBroadcastType::Update as i32,
+        band_math_request: None,
+        calc_request: None,
+        change_request: None,
+        coalesce_request: None,
+        composite_request: None,
+        copy_request: None,
+        delete_request: None,
+        execute_request: None,
+        fill_request: None,
+        pipeline_request: None,
+        preload_request: None,
+        reproject_request: None,
+        split_request: None,
+        statistics_request: None,
+        update_request: Some(update_request),
+    });
+
+    // retrieve reply
+    let reply = client.broadcast(request).await?;
+    let reply = reply.get_ref();
+
+    // print information
+    for (node_id, update_reply) in reply.update_replies.iter() {
+        println!("task starting on node '{}' with id '{}'",
+            node_id, update_reply.task_id);
+    }
+
+    Ok(())
+}
+
+#[tokio::main]
+async fn upload(matches: &ArgMatches, _: &ArgMatches,
+        upload_matches: &ArgMatches) -> Result<(), Box<dyn error::Error>> {
+    // initialize grpc client
+    let ip_address = matches.value_of("ip_address").unwrap();
+    let port = matches.value_of("port").unwrap().parse::<u16>()?;
+    let mut client = ImageManagementClient::new(crate::channel(matches,
+        &format!("{}:{}", ip_address, port)).await?);
+
+    // initialize ImageUploadHeader
+    let filename = upload_matches.value_of("FILE").unwrap();
+    let header = ImageUploadHeader {
+        album: upload_matches.value_of("ALBUM").unwrap().to_string(),
+        cloud_mask: crate::string_opt(upload_matches.value_of("cloud_mask")),
+        filename: filename.to_string(),
+        platform: crate::string_opt(upload_matches.value_of("platform")),
+        precision: crate::u32_opt(upload_matches.value_of("precision"))?,
+        subdataset: crate::u32_opt(upload_matches.value_of("subdataset"))?,
+        tile: crate::string_opt(upload_matches.value_of("tile")),
+        timestamp: crate::i64_opt(upload_matches.value_of("timestamp"))?,
+    };
+
+    // stream file in chunks, the first carrying the header
+    let buf = std::fs::read(filename)?;
+    let mut chunks: Vec<ImageUploadChunk> = buf.chunks(UPLOAD_CHUNK_SIZE)
+        .map(|x| ImageUploadChunk { data: x.to_vec(), header: None })
+        .collect();
+    match chunks.first_mut() {
+        Some(chunk) => chunk.header = Some(header),
+        None => return Err(format!("file '{}' is empty", filename).into()),
+    }
+
+    let reply = client.upload(Request::new(
+        tokio::stream::iter(chunks))).await?;
+    let reply = reply.get_ref();
+
+    // print information
+    println!("stored '{}' in {} geocodes", filename, reply.geocodes.len());
+    for geocode in reply.geocodes.iter() {
+        println!("    {}", geocode);
+    }
+
+    Ok(())
+}
+
+#[tokio::main]
+async fn zonal(matches: &ArgMatches, _: &ArgMatches,
+        zonal_matches: &ArgMatches) -> Result<(), Box<dyn error::Error>> {
+    // initialize grpc client
+    let ip_address = matches.value_of("ip_address").unwrap();
+    let port = matches.value_of("port").unwrap().parse::<u16>()?;
+    let mut client = ImageManagementClient::new(crate::channel(matches,
+        &format!("{}:{}", ip_address, port)).await?);
+
+    // read zone polygons
+    let geojson = std::fs::read_to_string(
+        zonal_matches.value_of("GEOJSON").unwrap())?;
+
+    // parse band numbers
+    let bands = match zonal_matches.value_of("bands") {
+        Some(bands) => bands.split(',').map(|x| x.trim().parse::<u32>())
+            .collect::<Result<Vec<u32>, _>>()?,
+        None => Vec::new(),
+    };
+
+    // initialize Filter
+    let filter = Filter {
+        end_timestamp: crate::i64_opt(
+            zonal_matches.value_of("end_timestamp"))?,
+        geocode: crate::string_opt(zonal_matches.value_of("geocode")),
+        max_cloud_coverage: crate::f64_opt(
+            zonal_matches.value_of("max_cloud_coverage"))?,
+        min_pixel_coverage: crate::f64_opt(
+            zonal_matches.value_of("min_pixel_coverage"))?,
+        name: None,
+        platform: crate::string_opt(zonal_matches.value_of("platform")),
+        recurse: zonal_matches.is_present("recurse"),
+        source: crate::string_opt(zonal_matches.value_of("source")),
+        start_timestamp: crate::i64_opt(
+            zonal_matches.value_of("start_timestamp"))?,
+    };
+
+    // initialize ImageZonalRequest
+    let request = Request::new(ImageZonalRequest {
+        album: zonal_matches.value_of("ALBUM").unwrap().to_string(),
+        bands: bands,
+        filter: filter,
+        geojson: geojson,
+        local: None,
+        subdataset: crate::u32_opt(zonal_matches.value_of("subdataset"))?,
+    });
+
+    // retrieve reply
+    let reply = client.zonal(request).await?;
+    let reply = reply.get_ref();
+
+    // print information
+    println!("{:<8}{:<12}{:<12}{:<8}{:<12}{:<16}{:<16}",
+        "zone", "platform", "timestamp", "band", "count", "sum", "mean");
+    println!("------------------------------------------------------------------------------------");
+    for statistics in reply.statistics.iter() {
+        println!("{:<8}{:<12}{:<12}{:<8}{:<12}{:<16.5}{:<16.5}",
+            statistics.zone, statistics.platform, statistics.timestamp,
+            statistics.band, statistics.count, statistics.sum,
+            statistics.mean.unwrap_or(std::f64::NAN));
+    }
+
+    Ok(())
+}
+
+fn dry_run_opt(matches: &ArgMatches) -> Option<bool> {
+    match matches.is_present("dry_run") {
+        true => Some(true),
+        false => None,
+    }
+}
+
+pub fn print_dry_run(node: &str, report: &DryRunReport) {
+    let tile_count = match report.tile_count {
+        Some(tile_count) => tile_count.to_string(),
+        None => "unknown".to_string(),
+    };
+
+    println!("dry run on node '{}': {} record(s), {} tile(s), {} estimate error(s)",
+        node, report.record_count, tile_count, report.error_count);
+    for (destination, count) in report.destinations.iter() {
+        println!("    {:<24}{}", destination, count);
+    }
+}
+
+pub fn parse_coalesce_request(coalesce_matches: &ArgMatches,
+        tenant: Option<&str>) -> Result<ImageCoalesceRequest, Box<dyn error::Error>> {
+    // initialize Filter
+    let filter = Filter {
+        end_timestamp: crate::i64_opt(
+            coalesce_matches.value_of("end_timestamp"))?,
+        geocode: crate::string_opt(
+            coalesce_matches.value_of("geocode")),
+        max_cloud_coverage: crate::f64_opt(
+            coalesce_matches.value_of("max_cloud_coverage"))?,
+        min_pixel_coverage: crate::f64_opt(
+            coalesce_matches.value_of("min_pixel_coverage"))?,
+        name: None,
+        platform: crate::string_opt(
+            coalesce_matches.value_of("platform")),
+        recurse: coalesce_matches.is_present("recurse"),
+        source: crate::string_opt(coalesce_matches.value_of("source")),
+        start_timestamp: crate::i64_opt(
+            coalesce_matches.value_of("start_timestamp"))?,
+    };
+
+    Ok(ImageCoalesceRequest {
+        album: coalesce_matches.value_of("ALBUM").unwrap().to_string(),
+        filter: filter,
+        platform: coalesce_matches.value_of("PLATFORM").unwrap().to_string(),
+        priority: crate::u32_opt(coalesce_matches.value_of("priority"))?,
+        task_id: crate::u64_opt(coalesce_matches.value_of("task_id"))?,
+        tenant: crate::string_opt(tenant),
+        thread_count: coalesce_matches.value_of("thread_count")
+            .unwrap().parse::<u32>()?,
+        retry_policy: crate::retry_policy_opt(
+            coalesce_matches.value_of("max_attempts"),
+            coalesce_matches.value_of("retry_backoff"))?,
+        window_seconds: coalesce_matches.value_of("window_seconds")
+            .unwrap().parse::<i64>()?,
+    })
+}
+
+pub fn parse_fill_request(fill_matches: &ArgMatches,
+        tenant: Option<&str>) -> Result<ImageFillRequest, Box<dyn error::Error>> {
+    // initialize Filter
+    let filter = Filter {
+        end_timestamp: crate::i64_opt(
+            fill_matches.value_of("end_timestamp"))?,
+        geocode: crate::string_opt(fill_matches.value_of("geocode")),
+        max_cloud_coverage: None,
+        min_pixel_coverage: None,
+        name: crate::string_opt(fill_matches.value_of("filter")),
+        platform: crate::string_opt(fill_matches.value_of("platform")),
+        recurse: fill_matches.is_present("recurse"),
+        source: None,
+        start_timestamp: crate::i64_opt(
+            fill_matches.value_of("start_timestamp"))?,
+    };
+
+    // parse donor band mapping
+    let band_map = match fill_matches.value_of("band_map") {
+        Some(bands) => bands.split(',').map(|x| x.trim().parse::<u32>())
+            .collect::<Result<Vec<u32>, _>>()?,
+        None => Vec::new(),
+    };
+
+    Ok(ImageFillRequest {
+        album: fill_matches.value_of("ALBUM").unwrap().to_string(),
+        band_map: band_map,
+        cloud_mask: crate::string_opt(fill_matches.value_of("cloud_mask")),
+        donor_cloud_mask: crate::string_opt(
+            fill_matches.value_of("donor_cloud_mask")),
+        donor_platform: crate::string_opt(
+            fill_matches.value_of("donor_platform")),
+        dry_run: dry_run_opt(fill_matches),
+        filter: filter,
+        mask_band: crate::u32_opt(fill_matches.value_of("mask_band"))?,
+        mask_subdataset: crate::u32_opt(
+            fill_matches.value_of("mask_subdataset"))?,
+        priority: crate::u32_opt(fill_matches.value_of("priority"))?,
+        resampling: crate::string_opt(fill_matches.value_of("resampling")),
+        task_id: crate::u64_opt(fill_matches.value_of("task_id"))?,
+        tenant: crate::string_opt(tenant),
+        thread_count: fill_matches.value_of("thread_count")
+            .unwrap().parse::<u32>()?,
+        retry_policy: crate::retry_policy_opt(
+            fill_matches.value_of("max_attempts"),
+            fill_matches.value_of("retry_backoff"))?,
+        schedule: crate::string_opt(fill_matches.value_of("schedule")),
+        strategy: crate::string_opt(fill_matches.value_of("strategy")),
+        window_seconds: fill_matches.value_of("window_seconds")
+            .unwrap().parse::<i64>()?,
+    })
+}
+
+pub fn parse_split_request(split_matches: &ArgMatches,
+        tenant: Option<&str>) -> Result<ImageSplitRequest, Box<dyn error::Error>> {
+    // initialize Filter
+    let filter = Filter {
+        end_timestamp: crate::i64_opt(
+            split_matches.value_of("end_timestamp"))?,
+        geocode: crate::string_opt(split_matches.value_of("geocode")),
+        max_cloud_coverage: None,
+        min_pixel_coverage: None,
+        name: crate::string_opt(split_matches.value_of("filter")),
+        platform: crate::string_opt(split_matches.value_of("platform")),
+        recurse: split_matches.is_present("recurse"),
+        source: None,
+        start_timestamp: crate::i64_opt(
+            split_matches.value_of("start_timestamp"))?,
+    };
+
+    Ok(ImageSplitRequest {
+        album: split_matches.value_of("ALBUM").unwrap().to_string(),
+        dry_run: dry_run_opt(split_matches),
+        filter: filter,
+        geocode_bound: crate::string_opt(
+            split_matches.value_of("geocode_bound")),
+        precision: crate::u32_opt(split_matches.value_of("precision"))?,
+        priority: crate::u32_opt(split_matches.value_of("priority"))?,
+        task_id: crate::u64_opt(split_matches.value_of("task_id"))?,
+        tenant: crate::string_opt(tenant),
+        thread_count: split_matches.value_of("thread_count")
+            .unwrap().parse::<u32>()?,
+        retry_policy: crate::retry_policy_opt(
+            split_matches.value_of("max_attempts"),
+            split_matches.value_of("retry_backoff"))?,
+    })
+}
+
+fn parse_pipeline_stage(stage: &str, tenant: Option<&str>)
+        -> Result<ImagePipelineStage, Box<dyn error::Error>> {
+    // parse stage as an image subcommand
+    let yaml = crate::config::load(include_str!("clap.yaml"))?;
+    let mut args = vec!["stip", "image"];
+    args.extend(stage.split_whitespace());
+
+    let matches = App::from_yaml(&yaml).get_matches_from_safe(args)?;
+    let image_matches = matches.subcommand_matches("image").unwrap();
+
+    let mut pipeline_stage = ImagePipelineStage {
+        coalesce_request: None,
+        fill_request: None,
+        split_request: None,
+        store_request: None,
+    };
+
+    match image_matches.subcommand() {
+        ("coalesce", Some(coalesce_matches)) =>
+            pipeline_stage.coalesce_request =
+                Some(parse_coalesce_request(coalesce_matches, tenant)?),
+        ("fill", Some(fill_matches)) =>
+            pipeline_stage.fill_request =
+                Some(parse_fill_request(fill_matches, tenant)?),
+        ("split", Some(split_matches)) =>
+            pipeline_stage.split_request =
+                Some(parse_split_request(split_matches, tenant)?),
+        ("store", Some(store_matches)) =>
+            pipeline_stage.store_request =
+                Some(parse_store_request(store_matches, tenant)?),
+        (cmd, _) => return Err(format!(
+            "unsupported pipeline stage '{}'", cmd).into()),
+    }
+
+    Ok(pipeline_stage)
+}
+
+pub fn parse_store_request(store_matches: &ArgMatches,
+        tenant: Option<&str>) -> Result<ImageStoreRequest, Box<dyn error::Error>> {
+    // parse load format
+    let format = parse_format(store_matches.value_of("FORMAT").unwrap());
+
+    // parse paired album ingest
+    let pair = match store_matches.value_of("pair_album") {
+        Some(album) => Some(ImageStorePair {
+            album: album.to_string(),
+            format: parse_format(store_matches
+                .value_of("pair_format").unwrap()),
+            pattern: store_matches.value_of("pair_pattern")
+                .unwrap().to_string(),
+            replacement: store_matches.value_of("pair_replacement")
+                .unwrap().to_string(),
+        }),
+        None => None,
+    };
+
+    Ok(ImageStoreRequest {
+        album: store_matches.value_of("ALBUM").unwrap().to_string(),
+        cloud_mask: crate::string_opt(store_matches.value_of("cloud_mask")),
+        dry_run: dry_run_opt(store_matches),
+        format: format,
+        glob: store_matches.value_of("GLOB").unwrap().to_string(),
+        pair: pair,
+        precision: crate::u32_opt(store_matches.value_of("precision"))?,
+        priority: crate::u32_opt(store_matches.value_of("priority"))?,
+        task_id: crate::u64_opt(store_matches.value_of("task_id"))?,
+        tenant: crate::string_opt(tenant),
+        thread_count: store_matches.value_of("thread_count")
+            .unwrap().parse::<u32>()?,
+        retry_policy: crate::retry_policy_opt(
+            store_matches.value_of("max_attempts"),
+            store_matches.value_of("retry_backoff"))?,
+        schedule: crate::string_opt(store_matches.value_of("schedule")),
+    })
+}
+
+// band subset, data type, scale, and clip geometry applied to reads
+fn parse_read_transform(matches: &ArgMatches)
+        -> Result<Option<ImageReadTransform>, Box<dyn error::Error>> {
+    if !["band_subset", "clip", "data_type", "scale"].iter()
+            .any(|x| matches.is_present(x)) {
+        return Ok(None);
+    }
+
+    let scale = match matches.value_of("scale") {
+        Some(scale) => {
+            let values = scale.split(',').map(|x| x.trim().parse::<f64>())
+                .collect::<Result<Vec<f64>, _>>()?;
+            if values.len() != 2 {
+                return Err("scale must be formatted as scale,offset".into());
+            }
+
+            Some((values[0], values[1]))
+        },
+        None => None,
+    };
+
+    Ok(Some(ImageReadTransform {
+        bands: match matches.value_of("band_subset") {
+            Some(bands) => bands.split(',')
+                .map(|x| x.trim().parse::<u32>())
+                .collect::<Result<Vec<u32>, _>>()?,
+            None => Vec::new(),
+        },
+        data_type: crate::string_opt(matches.value_of("data_type")),
+        geometry: match matches.value_of("clip") {
+            Some(path) => Some(std::fs::read_to_string(path)?),
+            None => None,
+        },
+        offset: scale.map(|x| x.1),
+        scale: scale.map(|x| x.0),
+    }))
+}
+
+fn parse_format(format: &str) -> i32 {
+    match format {
+        "generic" => ImageFormat::Generic as i32,
+        "gridmet" => ImageFormat::Gridmet as i32,
+        "mcd43a4" => ImageFormat::Mcd43a4 as i32,
+        "mod11a1" => ImageFormat::Mod11a1 as i32,
+        "mod11a2" => ImageFormat::Mod11a2 as i32,
+        "naip" => ImageFormat::Naip as i32,
+        "nlcd" => ImageFormat::Nlcd as i32,
+        "sentinel2" => ImageFormat::Sentinel2 as i32,
+        "vnp21v001" => ImageFormat::Vnp21v001 as i32,
+        _ => unimplemented!(),
+    }
+}
@@ -1,32 +1,111 @@
-#[macro_use]
-extern crate clap;
-use clap::App;
+use clap::{App, ArgMatches};
 
 mod album;
+mod apply;
+mod cluster;
+mod config;
+mod filter;
 mod image;
 mod node;
+mod output;
+mod shell;
 mod task;
 
+use protobuf::{RetryPolicy, TokenChannel};
+use tonic::transport::{Certificate, Channel, ClientTlsConfig, Identity};
+
 use std::error::Error;
 
 fn main() {
-    let yaml = load_yaml!("clap.yaml");
-    let matches = App::from_yaml(yaml).get_matches();
+    // default arguments from the config file and environment
+    let yaml = match config::load(include_str!("clap.yaml")) {
+        Ok(yaml) => yaml,
+        Err(e) => {
+            println!("{}", e);
+            std::process::exit(1);
+        },
+    };
+
+    let matches = App::from_yaml(&yaml).get_matches();
+    dispatch(&matches);
+}
 
+fn dispatch(matches: &ArgMatches) {
     // parse subcommands
     match matches.subcommand() {
         ("album", Some(album_matches)) =>
             album::process(&matches, &album_matches),
+        ("apply", Some(apply_matches)) =>
+            apply::process(&matches, &apply_matches),
+        ("cluster", Some(cluster_matches)) =>
+            cluster::process(&matches, &cluster_matches),
+        ("filter", Some(filter_matches)) =>
+            filter::process(&matches, &filter_matches),
         ("image", Some(image_matches)) =>
             image::process(&matches, &image_matches),
         ("node", Some(node_matches)) =>
             node::process(&matches, &node_matches),
+        ("shell", Some(shell_matches)) =>
+            shell::process(&matches, &shell_matches),
         ("task", Some(task_matches)) =>
             task::process(&matches, &task_matches),
         (cmd, _) => println!("unknown subcommand '{}'", cmd),
     }
 }
 
+async fn channel(matches: &ArgMatches, addr: &str)
+        -> Result<TokenChannel, Box<dyn Error>> {
+    let endpoint = match matches.value_of("tls_ca") {
+        Some(ca) => {
+            // verify nodes against the cluster ca
+            let mut config = ClientTlsConfig::new()
+                .ca_certificate(Certificate::from_pem(std::fs::read(ca)?))
+                .domain_name(matches.value_of("tls_domain").unwrap());
+
+            if let (Some(cert), Some(key)) = (matches.value_of("tls_cert"),
+                    matches.value_of("tls_key")) {
+                config = config.identity(Identity::from_pem(
+                    std::fs::read(cert)?, std::fs::read(key)?));
+            }
+
+            Channel::from_shared(format!("https://{}", addr))?
+                .tls_config(config)
+        },
+        None => Channel::from_shared(format!("http://{}", addr))?,
+    };
+
+    // reuse open connections within a shell session
+    let channel = match shell::is_active() {
+        true => shell::channel(addr, endpoint).await?,
+        false => endpoint.connect().await?,
+    };
+
+    TokenChannel::new(channel, matches.value_of("token"))
+}
+
+fn bool_opt(value: Option<&str>) -> Result<Option<bool>, Box<dyn Error>> {
+    match value {
+        Some(value) => Ok(Some(value.parse::<bool>()?)),
+        None => Ok(None),
+    }
+}
+
+fn bounds_opt(value: Option<&str>)
+        -> Result<Option<Vec<f64>>, Box<dyn Error>> {
+    match value {
+        Some(value) => {
+            let values = value.split(',').map(|x| x.trim().parse::<f64>())
+                .collect::<Result<Vec<f64>, _>>()?;
+            if values.len() != 4 {
+                return Err("bounds must be formatted as min_long,max_long,min_lat,max_lat".into());
+            }
+
+            Ok(Some(values))
+        },
+        None => Ok(None),
+    }
+}
+
 fn f64_opt(value: Option<&str>)
         -> Result<Option<f64>, Box<dyn Error>> {
     match value {
@@ -49,9 +128,27 @@ fn string_opt(value: Option<&str>) -> Option<String> {
     }
 }
 
+fn u32_opt(value: Option<&str>) -> Result<Option<u32>, Box<dyn Error>> {
+    match value {
+        Some(value) => Ok(Some(value.parse::<u32>()?)),
+        None => Ok(None),
+    }
+}
+
 fn u64_opt(value: Option<&str>) -> Result<Option<u64>, Box<dyn Error>> {
     match value {
         Some(value) => Ok(Some(value.parse::<u64>()?)),
         None => Ok(None),
     }
 }
+
+fn retry_policy_opt(max_attempts: Option<&str>, retry_backoff: Option<&str>)
+        -> Result<Option<RetryPolicy>, Box<dyn Error>> {
+    match max_attempts {
+        Some(max_attempts) => Ok(Some(RetryPolicy {
+            backoff_ms: retry_backoff.unwrap_or("0").parse::<u64>()?,
+            max_attempts: max_attempts.parse::<u32>()?,
+        })),
+        None => Ok(None),
+    }
+}
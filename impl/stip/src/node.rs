@@ -1,16 +1,32 @@
 use clap::ArgMatches;
-use protobuf::{NodeListRequest, NodeLocateRequest, NodeManagementClient};
+use protobuf::{EventType, NodeAuditRequest, NodeCapacityRequest, NodeDecommissionRequest, NodeEventsRequest, NodeListRequest, NodeLocateRequest, NodeManagementClient, NodeStatsRequest, NodeUsageRequest};
+use serde_json::json;
 use tonic::Request;
 
+use crate::output::Output;
+
 use std::{error, io};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::Write;
 
 pub fn process(matches: &ArgMatches, cluster_matches: &ArgMatches) {
     let result: Result<(), Box<dyn error::Error>> 
             = match cluster_matches.subcommand() {
+        ("audit", Some(audit_matches)) =>
+            audit(&matches, &cluster_matches, &audit_matches),
+        ("decommission", Some(decommission_matches)) =>
+            decommission(&matches, &cluster_matches, &decommission_matches),
+        ("events", Some(events_matches)) =>
+            events(&matches, &cluster_matches, &events_matches),
         ("list", Some(list_matches)) =>
             list(&matches, &cluster_matches, &list_matches),
         ("locate", Some(locate_matches)) =>
             locate(&matches, &cluster_matches, &locate_matches),
+        ("stats", Some(stats_matches)) =>
+            stats(&matches, &cluster_matches, &stats_matches),
+        ("usage", Some(usage_matches)) =>
+            usage(&matches, &cluster_matches, &usage_matches),
         (cmd, _) => Err(Box::new(io::Error::new(io::ErrorKind::Other,
             format!("unknown subcommand '{}'", cmd)))),
     };
@@ -20,14 +36,158 @@ pub fn process(matches: &ArgMatches, cluster_matches: &ArgMatches) {
     }
 }
 
+#[tokio::main]
+async fn audit(matches: &ArgMatches, _: &ArgMatches,
+        audit_matches: &ArgMatches) -> Result<(), Box<dyn error::Error>> {
+    // initialize grpc client
+    let ip_address = matches.value_of("ip_address").unwrap();
+    let port = matches.value_of("port").unwrap().parse::<u16>()?;
+    let mut client = NodeManagementClient::new(crate::channel(matches,
+        &format!("{}:{}", ip_address, port)).await?);
+
+    // retrieve cluster nodes
+    let reply = client.list(Request::new(NodeListRequest {})).await?;
+    let nodes = reply.into_inner().nodes;
+
+    // initialize NodeAuditRequest
+    let request = NodeAuditRequest {
+        end_timestamp: crate::u64_opt(
+            audit_matches.value_of("end_timestamp"))?,
+        identity: crate::string_opt(audit_matches.value_of("identity")),
+        limit: crate::u32_opt(audit_matches.value_of("limit"))?,
+        operation: crate::string_opt(audit_matches.value_of("operation")),
+        start_timestamp: crate::u64_opt(
+            audit_matches.value_of("start_timestamp"))?,
+    };
+
+    // merge entries of every node by timestamp
+    let mut entries = Vec::new();
+    for node in nodes.iter() {
+        let mut client = NodeManagementClient::new(crate::channel(matches,
+            &node.rpc_addr).await?);
+        let reply = client.audit(Request::new(request.clone())).await?;
+
+        for entry in reply.into_inner().entries {
+            entries.push((node.id, entry));
+        }
+    }
+
+    entries.sort_by_key(|(node_id, entry)|
+        (entry.timestamp, *node_id, entry.sequence));
+
+    // print information
+    let output = Output::new(matches, vec![("timestamp", 12),
+        ("node", 8), ("sequence", 10), ("identity", 16),
+        ("operation", 24), ("parameters", 12)])?;
+    output.header();
+    for (node_id, entry) in entries {
+        output.row(vec![json!(entry.timestamp), json!(node_id),
+            json!(entry.sequence), json!(entry.identity),
+            json!(entry.operation), json!(entry.parameters)]);
+    }
+
+    Ok(())
+}
+
+#[tokio::main]
+async fn decommission(matches: &ArgMatches, _: &ArgMatches,
+        decommission_matches: &ArgMatches)
+        -> Result<(), Box<dyn error::Error>> {
+    // initialize grpc client
+    let ip_address = matches.value_of("ip_address").unwrap();
+    let port = matches.value_of("port").unwrap().parse::<u16>()?;
+    let mut client = NodeManagementClient::new(crate::channel(matches,
+        &format!("{}:{}", ip_address, port)).await?);
+
+    // retrieve cluster nodes
+    let reply = client.list(Request::new(NodeListRequest {})).await?;
+    let nodes = reply.into_inner().nodes;
+
+    let id = decommission_matches.value_of("ID").unwrap().parse::<u32>()?;
+    let target = match nodes.iter().find(|x| x.id == id) {
+        Some(node) => node,
+        None => return Err(format!("node '{}' not found", id).into()),
+    };
+
+    // initialize NodeDecommissionRequest
+    let request = NodeDecommissionRequest {
+        id: id,
+        task_id: crate::u64_opt(decommission_matches.value_of("task_id"))?,
+        thread_count: decommission_matches.value_of("thread_count")
+            .unwrap().parse::<u32>()?,
+    };
+
+    // exclude node from replica sets on remaining nodes first
+    for node in nodes.iter().filter(|x| x.id != id) {
+        let mut client = NodeManagementClient::new(crate::channel(matches,
+            &node.rpc_addr).await?);
+        client.decommission(Request::new(request.clone())).await?;
+    }
+
+    // start drain on decommissioned node
+    let mut client = NodeManagementClient::new(crate::channel(matches,
+        &target.rpc_addr).await?);
+    let reply = client.decommission(Request::new(request)).await?;
+
+    match reply.get_ref().task_id {
+        Some(task_id) => println!("task starting on node '{}' with id '{}'",
+            id, task_id),
+        None => println!("node '{}' decommissioned", id),
+    }
+
+    Ok(())
+}
+
+#[tokio::main]
+async fn events(matches: &ArgMatches, _: &ArgMatches,
+        events_matches: &ArgMatches) -> Result<(), Box<dyn error::Error>> {
+    // initialize grpc client
+    let ip_address = matches.value_of("ip_address").unwrap();
+    let port = matches.value_of("port").unwrap().parse::<u16>()?;
+    let mut client = NodeManagementClient::new(crate::channel(matches,
+        &format!("{}:{}", ip_address, port)).await?);
+
+    // parse event types
+    let mut event_types = Vec::new();
+    if let Some(values) = events_matches.values_of("type") {
+        for value in values {
+            event_types.push(parse_event_type(value)? as i32);
+        }
+    }
+
+    // initialize request
+    let request = Request::new(NodeEventsRequest {
+        event_types: event_types,
+        local: Some(events_matches.is_present("local")),
+        sequence: crate::u64_opt(events_matches.value_of("sequence"))?,
+    });
+
+    // print events as they arrive
+    let mut stream = client.events(request).await?.into_inner();
+    let output = Output::new(matches, vec![("timestamp", 12),
+        ("node", 8), ("sequence", 10), ("type", 16), ("attributes", 8)])?;
+    output.header();
+    while let Some(event) = stream.message().await? {
+        let attributes: BTreeMap<String, String> =
+            event.attributes.into_iter().collect();
+        let event_type = EventType::from_i32(event.event_type)
+            .map(event_type_name).unwrap_or("unknown");
+
+        output.row(vec![json!(event.timestamp), json!(event.node_id),
+            json!(event.sequence), json!(event_type), json!(attributes)]);
+    }
+
+    Ok(())
+}
+
 #[tokio::main]
 async fn list(matches: &ArgMatches, _: &ArgMatches,
         _list_matches: &ArgMatches) -> Result<(), Box<dyn error::Error>> {
     // initialize grpc client
     let ip_address = matches.value_of("ip_address").unwrap();
     let port = matches.value_of("port").unwrap().parse::<u16>()?;
-    let mut client = NodeManagementClient::connect(
-        format!("http://{}:{}", ip_address, port)).await?;
+    let mut client = NodeManagementClient::new(crate::channel(matches,
+        &format!("{}:{}", ip_address, port)).await?);
 
     // initialize request
     let request = Request::new(NodeListRequest {});
@@ -37,11 +197,32 @@ async fn list(matches: &ArgMatches, _: &ArgMatches,
     let reply = reply.get_ref();
 
     // print information
-    println!("{:<8}{:<24}{:<24}", "id", "rpc_addr", "xfer_addr");
-    println!("------------------------------------------------");
+    let output = Output::new(matches, vec![("id", 8), ("rpc_addr", 24),
+        ("xfer_addr", 24), ("tokens", 8), ("cpus", 6), ("tasks", 8),
+        ("disk_used", 12), ("disk_free", 12), ("decommissioned", 14)])?;
+    output.header();
     for node in reply.nodes.iter() {
-        println!("{:<8}{:<24}{:<24}", node.id,
-            node.rpc_addr, node.xfer_addr);
+        // retrieve node capacity, tolerating unreachable nodes
+        let capacity = match crate::channel(matches,
+                &node.rpc_addr).await
+                .map(NodeManagementClient::new) {
+            Ok(mut client) => client.capacity(Request::new(
+                NodeCapacityRequest {})).await.ok()
+                .map(|x| x.into_inner()),
+            Err(_) => None,
+        };
+
+        let (cpus, tasks, disk_used, disk_free) = match capacity {
+            Some(x) => (json!(x.cpu_count), json!(x.running_task_count),
+                json!(format_bytes(x.disk_total_bytes - x.disk_free_bytes)),
+                json!(format_bytes(x.disk_free_bytes))),
+            None => (json!(null), json!(null), json!(null), json!(null)),
+        };
+
+        output.row(vec![json!(node.id), json!(node.rpc_addr),
+            json!(node.xfer_addr), json!(node.tokens.len()), cpus, tasks,
+            disk_used, disk_free,
+            json!(node.decommissioned.unwrap_or(false))]);
     }
 
     Ok(())
@@ -53,8 +234,8 @@ async fn locate(matches: &ArgMatches, _: &ArgMatches,
     // initialize grpc client
     let ip_address = matches.value_of("ip_address").unwrap();
     let port = matches.value_of("port").unwrap().parse::<u16>()?;
-    let mut client = NodeManagementClient::connect(
-        format!("http://{}:{}", ip_address, port)).await?;
+    let mut client = NodeManagementClient::new(crate::channel(matches,
+        &format!("{}:{}", ip_address, port)).await?);
 
     // initialize request
     let request = Request::new(NodeLocateRequest {
@@ -67,10 +248,184 @@ async fn locate(matches: &ArgMatches, _: &ArgMatches,
 
     // print information
     match &reply.node {
-        Some(node) => println!("node: {}\nrpcAddr: {}\nxferAddr: {}",
-            node.id, node.rpc_addr, node.xfer_addr),
+        Some(node) => {
+            let tokens: Vec<String> = node.tokens.iter()
+                .map(|x| x.to_string()).collect();
+            println!("node: {}\nrpcAddr: {}\nxferAddr: {}\ntokens: {}",
+                node.id, node.rpc_addr, node.xfer_addr, tokens.join(","))
+        },
         None => println!("node not found"),
     }
 
     Ok(())
 }
+
+#[tokio::main]
+async fn stats(matches: &ArgMatches, _: &ArgMatches,
+        stats_matches: &ArgMatches) -> Result<(), Box<dyn error::Error>> {
+    // initialize grpc client
+    let ip_address = matches.value_of("ip_address").unwrap();
+    let port = matches.value_of("port").unwrap().parse::<u16>()?;
+    let mut client = NodeManagementClient::new(crate::channel(matches,
+        &format!("{}:{}", ip_address, port)).await?);
+
+    // retrieve cluster nodes
+    let reply = client.list(Request::new(NodeListRequest {})).await?;
+    let nodes = reply.into_inner().nodes;
+
+    // print per method rpc counts if requested
+    let methods = stats_matches.is_present("methods");
+    let output = match methods {
+        true => Output::new(matches, vec![("id", 8), ("method", 56),
+            ("calls", 12), ("errors", 12)])?,
+        false => Output::new(matches, vec![("id", 8), ("rpc_addr", 24),
+            ("sent", 10), ("received", 10), ("failed_sends", 14),
+            ("retransmits", 13), ("dht_failures", 14), ("tiles_read", 12),
+            ("tiles_written", 15), ("rpc_calls", 11), ("rpc_errors", 11)])?,
+    };
+
+    output.header();
+    for node in nodes.iter() {
+        // retrieve node stats, tolerating unreachable nodes
+        let stats = match crate::channel(matches, &node.rpc_addr).await
+                .map(NodeManagementClient::new) {
+            Ok(mut client) => client.stats(Request::new(
+                NodeStatsRequest {})).await.ok().map(|x| x.into_inner()),
+            Err(_) => None,
+        };
+
+        let stats = match (stats, methods) {
+            (Some(stats), _) => stats,
+            (None, true) => continue,
+            (None, false) => {
+                let mut row = vec![json!(node.id), json!(node.rpc_addr)];
+                row.extend(vec![json!(null); 9]);
+                output.row(row);
+                continue;
+            },
+        };
+
+        if methods {
+            for rpc_call in stats.rpc_calls.iter() {
+                output.row(vec![json!(node.id), json!(rpc_call.method),
+                    json!(rpc_call.call_count), json!(rpc_call.error_count)]);
+            }
+
+            continue;
+        }
+
+        let rpc_calls: u64 = stats.rpc_calls.iter()
+            .map(|x| x.call_count).sum();
+        let rpc_errors: u64 = stats.rpc_calls.iter()
+            .map(|x| x.error_count).sum();
+
+        output.row(vec![json!(node.id), json!(node.rpc_addr),
+            json!(format_bytes(stats.transfer_sent_bytes)),
+            json!(format_bytes(stats.transfer_received_bytes)),
+            json!(stats.transfer_failed_sends),
+            json!(stats.transfer_retransmits),
+            json!(stats.dht_lookup_failures), json!(stats.tiles_read),
+            json!(stats.tiles_written), json!(rpc_calls),
+            json!(rpc_errors)]);
+    }
+
+    Ok(())
+}
+
+#[tokio::main]
+async fn usage(matches: &ArgMatches, _: &ArgMatches,
+        usage_matches: &ArgMatches) -> Result<(), Box<dyn error::Error>> {
+    // initialize grpc client
+    let ip_address = matches.value_of("ip_address").unwrap();
+    let port = matches.value_of("port").unwrap().parse::<u16>()?;
+    let mut client = NodeManagementClient::new(crate::channel(matches,
+        &format!("{}:{}", ip_address, port)).await?);
+
+    // retrieve cluster nodes
+    let reply = client.list(Request::new(NodeListRequest {})).await?;
+    let nodes = reply.into_inner().nodes;
+
+    // initialize NodeUsageRequest
+    let request = NodeUsageRequest {
+        month: crate::string_opt(usage_matches.value_of("month")),
+        tenant: crate::string_opt(usage_matches.value_of("tenant")),
+    };
+
+    // aggregate usage across nodes by month and tenant
+    let mut usage = BTreeMap::new();
+    for node in nodes.iter() {
+        let mut client = NodeManagementClient::new(crate::channel(matches,
+            &node.rpc_addr).await?);
+        let reply = client.usage(Request::new(request.clone())).await?;
+
+        for tenant_usage in reply.get_ref().usage.iter() {
+            let entry = usage.entry((tenant_usage.month.clone(),
+                tenant_usage.tenant.clone())).or_insert((0u64, 0u64, 0u64));
+            entry.0 += tenant_usage.bytes_ingested;
+            entry.1 += tenant_usage.bytes_served;
+            entry.2 += tenant_usage.compute_ms;
+        }
+    }
+
+    // export report as csv if requested
+    if let Some(path) = usage_matches.value_of("export") {
+        let mut file = File::create(path)?;
+        writeln!(file, "month,tenant,bytes_ingested,bytes_served,compute_seconds")?;
+        for ((month, tenant), (ingested, served, compute_ms))
+                in usage.iter() {
+            writeln!(file, "{},{},{},{},{:.3}", month, tenant,
+                ingested, served, *compute_ms as f64 / 1000.0)?;
+        }
+
+        println!("exported {} usage record(s) to '{}'", usage.len(), path);
+        return Ok(());
+    }
+
+    // print information
+    println!("{:<10}{:<24}{:<18}{:<18}{:<18}", "month", "tenant",
+        "bytes_ingested", "bytes_served", "compute_seconds");
+    println!("------------------------------------------------------------------------------------------");
+    for ((month, tenant), (ingested, served, compute_ms)) in usage.iter() {
+        println!("{:<10}{:<24}{:<18}{:<18}{:<18.3}", month, tenant,
+            ingested, served, *compute_ms as f64 / 1000.0);
+    }
+
+    Ok(())
+}
+
+pub fn format_bytes(bytes: u64) -> String {
+    let units = ["B", "K", "M", "G", "T", "P"];
+    let mut value = bytes as f64;
+    let mut index = 0;
+    while value >= 1024.0 && index < units.len() - 1 {
+        value /= 1024.0;
+        index += 1;
+    }
+
+    format!("{:.1}{}", value, units[index])
+}
+
+fn event_type_name(event_type: EventType) -> &'static str {
+    match event_type {
+        EventType::AlbumCreated => "album_created",
+        EventType::AlbumDeleted => "album_deleted",
+        EventType::NodeJoined => "node_joined",
+        EventType::NodeLeft => "node_left",
+        EventType::TaskCompleted => "task_completed",
+        EventType::TaskStarted => "task_started",
+        EventType::TileWritten => "tile_written",
+    }
+}
+
+fn parse_event_type(value: &str) -> Result<EventType, Box<dyn error::Error>> {
+    match value.to_lowercase().as_str() {
+        "album_created" => Ok(EventType::AlbumCreated),
+        "album_deleted" => Ok(EventType::AlbumDeleted),
+        "node_joined" => Ok(EventType::NodeJoined),
+        "node_left" => Ok(EventType::NodeLeft),
+        "task_completed" => Ok(EventType::TaskCompleted),
+        "task_started" => Ok(EventType::TaskStarted),
+        "tile_written" => Ok(EventType::TileWritten),
+        x => Err(format!("unknown event type '{}'", x).into()),
+    }
+}
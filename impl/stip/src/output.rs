@@ -0,0 +1,208 @@
+use clap::ArgMatches;
+use serde_json::{Map, Value};
+
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::error::Error;
+use std::str::FromStr;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OutputFormat {
+    Csv,
+    Json,
+    Table,
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "csv" => Ok(OutputFormat::Csv),
+            "json" => Ok(OutputFormat::Json),
+            "table" => Ok(OutputFormat::Table),
+            x => Err(format!("unsupported output format '{}'", x)),
+        }
+    }
+}
+
+// prints rows as fixed width columns, csv records, or json lines
+pub struct Output {
+    columns: Vec<(String, usize)>,
+    format: OutputFormat,
+    rows: RefCell<Vec<(Vec<Value>, Vec<String>)>>,
+    selected: Vec<usize>,
+    sort_by: Vec<(usize, bool)>,
+}
+
+impl Output {
+    pub fn new(matches: &ArgMatches, columns: Vec<(&str, usize)>)
+            -> Result<Output, Box<dyn Error>> {
+        let format = matches.value_of("output").unwrap()
+            .parse::<OutputFormat>()?;
+
+        Ok(Output {
+            selected: (0..columns.len()).collect(),
+            columns: columns.into_iter()
+                .map(|(name, width)| (name.to_string(), width)).collect(),
+            format: format,
+            rows: RefCell::new(Vec::new()),
+            sort_by: Vec::new(),
+        })
+    }
+
+    // apply the '--columns' and '--sort_by' flags of a subcommand
+    pub fn select(mut self, sub_matches: &ArgMatches)
+            -> Result<Output, Box<dyn Error>> {
+        if let Some(columns) = sub_matches.value_of("columns") {
+            self.selected = Vec::new();
+            for name in columns.split(',').map(|x| x.trim())
+                    .filter(|x| !x.is_empty()) {
+                let index = self.index(name)?;
+                self.selected.push(index);
+            }
+
+            if self.selected.is_empty() {
+                return Err("no output columns selected".into());
+            }
+        }
+
+        // sort keys are formatted as 'column[:asc|:desc]'
+        if let Some(sort_by) = sub_matches.value_of("sort_by") {
+            for key in sort_by.split(',').map(|x| x.trim())
+                    .filter(|x| !x.is_empty()) {
+                let (name, descending) = match key.split_once(':') {
+                    Some((name, "asc")) => (name, false),
+                    Some((name, "desc")) => (name, true),
+                    Some((_, order)) => return Err(format!(
+                        "unsupported sort order '{}'", order).into()),
+                    None => (key, false),
+                };
+
+                let index = self.index(name)?;
+                self.sort_by.push((index, descending));
+            }
+        }
+
+        Ok(self)
+    }
+
+    // print rows buffered for sorting
+    pub fn flush(&self) {
+        let mut rows = self.rows.borrow_mut();
+        rows.sort_by(|(a, _), (b, _)| {
+            for (index, descending) in self.sort_by.iter() {
+                let ordering = compare(&a[*index], &b[*index]);
+                if ordering != Ordering::Equal {
+                    return match descending {
+                        true => ordering.reverse(),
+                        false => ordering,
+                    };
+                }
+            }
+
+            Ordering::Equal
+        });
+
+        for (values, lines) in rows.drain(..) {
+            self.print(values, &lines);
+        }
+    }
+
+    pub fn header(&self) {
+        match self.format {
+            OutputFormat::Csv => println!("{}", self.selected.iter()
+                .map(|x| csv_field(&self.columns[*x].0))
+                .collect::<Vec<String>>().join(",")),
+            OutputFormat::Json => {},
+            OutputFormat::Table => {
+                for index in self.selected.iter() {
+                    let (name, width) = &self.columns[*index];
+                    print!("{:<1$}", name, width);
+                }
+
+                println!();
+                println!("{}", "-".repeat(self.selected.iter()
+                    .map(|x| self.columns[*x].1).sum()));
+            },
+        }
+    }
+
+    pub fn row(&self, values: Vec<Value>) {
+        self.row_with_lines(values, Vec::new());
+    }
+
+    // lines are printed beneath the row in table output only
+    pub fn row_with_lines(&self, values: Vec<Value>, lines: Vec<String>) {
+        match self.sort_by.is_empty() {
+            true => self.print(values, &lines),
+            false => self.rows.borrow_mut().push((values, lines)),
+        }
+    }
+
+    fn index(&self, name: &str) -> Result<usize, Box<dyn Error>> {
+        match self.columns.iter().position(|(x, _)| x == name) {
+            Some(index) => Ok(index),
+            None => Err(format!("unknown column '{}', expected one of {}",
+                name, self.columns.iter().map(|(x, _)| x.as_str())
+                    .collect::<Vec<&str>>().join(", ")).into()),
+        }
+    }
+
+    fn print(&self, mut values: Vec<Value>, lines: &[String]) {
+        match self.format {
+            OutputFormat::Csv => println!("{}", self.selected.iter()
+                .map(|x| csv_field(&text(&values[*x])))
+                .collect::<Vec<String>>().join(",")),
+            OutputFormat::Json => {
+                let mut object = Map::new();
+                for index in self.selected.iter() {
+                    object.insert(self.columns[*index].0.clone(),
+                        values[*index].take());
+                }
+
+                println!("{}", Value::Object(object));
+            },
+            OutputFormat::Table => {
+                for index in self.selected.iter() {
+                    print!("{:<1$}", text(&values[*index]),
+                        self.columns[*index].1);
+                }
+
+                println!();
+                for line in lines.iter() {
+                    println!("{}", line);
+                }
+            },
+        }
+    }
+}
+
+// order numbers numerically and other values by their text
+fn compare(a: &Value, b: &Value) -> Ordering {
+    match (a, b) {
+        (Value::Null, Value::Null) => Ordering::Equal,
+        (Value::Null, _) => Ordering::Greater,
+        (_, Value::Null) => Ordering::Less,
+        (Value::Number(a), Value::Number(b)) => a.as_f64()
+            .partial_cmp(&b.as_f64()).unwrap_or(Ordering::Equal),
+        (a, b) => text(a).cmp(&text(b)),
+    }
+}
+
+fn csv_field(value: &str) -> String {
+    match value.contains(|c| c == ',' || c == '"' || c == '\n') {
+        true => format!("\"{}\"", value.replace("\"", "\"\"")),
+        false => value.to_string(),
+    }
+}
+
+fn text(value: &Value) -> String {
+    match value {
+        Value::Null => "-".to_string(),
+        Value::Number(x) if x.is_f64() =>
+            format!("{:.5}", x.as_f64().unwrap()),
+        Value::String(x) => x.clone(),
+        x => x.to_string(),
+    }
+}
@@ -0,0 +1,303 @@
+use clap::{App, ArgMatches};
+use protobuf::{AlbumListRequest, AlbumManagementClient, ImageDistinctRequest, ImageManagementClient, NodeListRequest, NodeManagementClient};
+use rustyline::completion::Completer;
+use rustyline::error::ReadlineError;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::validate::Validator;
+use rustyline::{Context, Editor, Helper};
+use tokio::runtime::Handle;
+use tonic::Request;
+use tonic::transport::{Channel, Endpoint};
+use yaml_rust::Yaml;
+
+use std::collections::{BTreeSet, HashMap};
+use std::error::Error;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+static SESSION: Mutex<Option<Session>> = Mutex::new(None);
+
+// connections held open on a runtime which outlives each command
+struct Session {
+    channels: HashMap<String, Channel>,
+    handle: Handle,
+}
+
+pub fn process(matches: &ArgMatches, _shell_matches: &ArgMatches) {
+    if let Err(e) = run(matches) {
+        println!("{}", e);
+    }
+}
+
+pub fn is_active() -> bool {
+    SESSION.lock().unwrap().is_some()
+}
+
+pub async fn channel(addr: &str, endpoint: Endpoint)
+        -> Result<Channel, Box<dyn Error>> {
+    let handle = {
+        let session = SESSION.lock().unwrap();
+        let session = session.as_ref().ok_or("shell session is not active")?;
+        if let Some(channel) = session.channels.get(addr) {
+            return Ok(channel.clone());
+        }
+
+        session.handle.clone()
+    };
+
+    // connect on the session runtime so the connection outlives the command
+    let (tx, rx) = tokio::sync::oneshot::channel();
+    handle.spawn(async move {
+        let _ = tx.send(endpoint.connect().await.map_err(|e| e.to_string()));
+    });
+
+    let channel = rx.await??;
+    if let Some(session) = SESSION.lock().unwrap().as_mut() {
+        session.channels.insert(addr.to_string(), channel.clone());
+    }
+
+    Ok(channel)
+}
+
+fn run(matches: &ArgMatches) -> Result<(), Box<dyn Error>> {
+    start()?;
+
+    // global arguments preceding the shell subcommand apply to each command
+    let globals: Vec<String> = std::env::args()
+        .take_while(|x| x != "shell").collect();
+    let yaml = crate::config::load(include_str!("clap.yaml"))?;
+
+    let mut editor = Editor::<Completion>::new();
+    editor.set_helper(Some(Completion {
+        commands: command_words(&yaml),
+        values: discover_values(matches),
+    }));
+
+    let history_path = history_path();
+    if let Some(path) = &history_path {
+        let _ = editor.load_history(path);
+    }
+
+    loop {
+        let line = match editor.readline("stip> ") {
+            Ok(line) => line,
+            Err(ReadlineError::Interrupted) => continue,
+            Err(ReadlineError::Eof) => break,
+            Err(e) => return Err(e.into()),
+        };
+
+        let args = match split(&line) {
+            Ok(args) => args,
+            Err(e) => {
+                println!("{}", e);
+                continue;
+            },
+        };
+
+        editor.add_history_entry(line.as_str());
+        match args.first().map(|x| x.as_str()) {
+            None => continue,
+            Some("exit") | Some("quit") => break,
+            Some("refresh") => {
+                if let Some(helper) = editor.helper_mut() {
+                    helper.values = discover_values(matches);
+                }
+
+                continue;
+            },
+            Some("shell") => {
+                println!("already running a shell");
+                continue;
+            },
+            _ => {},
+        }
+
+        // parse and execute command
+        let argv = globals.iter().cloned().chain(args.into_iter());
+        match App::from_yaml(&yaml).get_matches_from_safe(argv) {
+            Ok(matches) => crate::dispatch(&matches),
+            Err(e) => println!("{}", e),
+        }
+    }
+
+    if let Some(path) = &history_path {
+        if let Some(parent) = path.parent() {
+            let _ = std::fs::create_dir_all(parent);
+        }
+
+        let _ = editor.save_history(path);
+    }
+
+    Ok(())
+}
+
+fn start() -> Result<(), Box<dyn Error>> {
+    // drive connections on a dedicated runtime thread
+    let (tx, rx) = std::sync::mpsc::channel();
+    std::thread::spawn(move || {
+        let mut runtime = match tokio::runtime::Builder::new()
+                .basic_scheduler().enable_all().build() {
+            Ok(runtime) => runtime,
+            Err(e) => {
+                let _ = tx.send(Err(e.to_string()));
+                return;
+            },
+        };
+
+        let _ = tx.send(Ok(runtime.handle().clone()));
+        runtime.block_on(futures::future::pending::<()>());
+    });
+
+    let handle = rx.recv()??;
+    *SESSION.lock().unwrap() = Some(Session {
+        channels: HashMap::new(),
+        handle: handle,
+    });
+
+    Ok(())
+}
+
+struct Completion {
+    commands: BTreeSet<String>,
+    values: BTreeSet<String>,
+}
+
+impl Completer for Completion {
+    type Candidate = String;
+
+    fn complete(&self, line: &str, pos: usize, _ctx: &Context<'_>)
+            -> rustyline::Result<(usize, Vec<String>)> {
+        // complete the word preceding the cursor
+        let start = line[..pos].rfind(char::is_whitespace)
+            .map(|x| x + 1).unwrap_or(0);
+        let prefix = &line[start..pos];
+
+        let candidates = self.commands.iter().chain(self.values.iter())
+            .filter(|x| x.starts_with(prefix)).cloned().collect();
+        Ok((start, candidates))
+    }
+}
+
+impl Helper for Completion {}
+impl Highlighter for Completion {}
+impl Hinter for Completion {}
+impl Validator for Completion {}
+
+// subcommand names and long arguments of every command
+fn command_words(yaml: &Yaml) -> BTreeSet<String> {
+    let mut words = BTreeSet::new();
+    for arg in yaml["args"].as_vec().unwrap_or(&Vec::new()) {
+        // positional arguments are not completed
+        for (_, properties) in arg.as_hash().into_iter().flatten() {
+            if let Some(long) = properties["long"].as_str() {
+                words.insert(format!("--{}", long));
+            }
+        }
+    }
+
+    for subcommand in yaml["subcommands"].as_vec().unwrap_or(&Vec::new()) {
+        for (name, command) in subcommand.as_hash().into_iter().flatten() {
+            if let Some(name) = name.as_str() {
+                words.insert(name.to_string());
+            }
+
+            words.extend(command_words(command));
+        }
+    }
+
+    words.extend(vec!["exit".to_string(), "quit".to_string(),
+        "refresh".to_string()]);
+    words
+}
+
+// album and platform names stored within the cluster
+fn discover_values(matches: &ArgMatches) -> BTreeSet<String> {
+    match discover(matches) {
+        Ok(values) => values,
+        Err(e) => {
+            println!("failed to discover albums and platforms: {}", e);
+            BTreeSet::new()
+        },
+    }
+}
+
+#[tokio::main]
+async fn discover(matches: &ArgMatches)
+        -> Result<BTreeSet<String>, Box<dyn Error>> {
+    let ip_address = matches.value_of("ip_address").unwrap();
+    let port = matches.value_of("port").unwrap().parse::<u16>()?;
+    let address = format!("{}:{}", ip_address, port);
+
+    // retrieve albums and cluster nodes
+    let channel = crate::channel(matches, &address).await?;
+    let reply = AlbumManagementClient::new(channel.clone())
+        .list(Request::new(AlbumListRequest {})).await?;
+    let albums: Vec<String> = reply.into_inner().albums.into_iter()
+        .map(|x| x.id).collect();
+
+    let reply = NodeManagementClient::new(channel)
+        .list(Request::new(NodeListRequest {})).await?;
+    let nodes = reply.into_inner().nodes;
+
+    // retrieve platforms of each album from every node
+    let mut values: BTreeSet<String> = albums.iter().cloned().collect();
+    for node in nodes.iter() {
+        let mut client = ImageManagementClient::new(
+            crate::channel(matches, &node.rpc_addr).await?);
+        for album in albums.iter() {
+            let request = ImageDistinctRequest {
+                album: album.clone(),
+                precision: 1,
+            };
+
+            // closed albums fail to report distinct values
+            if let Ok(reply) = client.distinct(Request::new(request)).await {
+                values.extend(reply.into_inner().platforms.into_iter());
+            }
+        }
+    }
+
+    Ok(values)
+}
+
+fn history_path() -> Option<PathBuf> {
+    std::env::var("HOME").ok()
+        .map(|home| PathBuf::from(home).join(".stip").join("history"))
+}
+
+// split a line into whitespace separated words, respecting quotes
+fn split(line: &str) -> Result<Vec<String>, Box<dyn Error>> {
+    let mut words = Vec::new();
+    let (mut word, mut quote, mut in_word) = (String::new(), None, false);
+    for c in line.chars() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), c) => word.push(c),
+            (None, '\'') | (None, '"') => {
+                quote = Some(c);
+                in_word = true;
+            },
+            (None, c) if c.is_whitespace() => {
+                if in_word {
+                    words.push(std::mem::replace(&mut word, String::new()));
+                    in_word = false;
+                }
+            },
+            (None, c) => {
+                word.push(c);
+                in_word = true;
+            },
+        }
+    }
+
+    if quote.is_some() {
+        return Err("unterminated quote".into());
+    }
+
+    if in_word {
+        words.push(word);
+    }
+
+    Ok(words)
+}
@@ -1,17 +1,35 @@
 use clap::ArgMatches;
-use protobuf::{TaskBroadcastRequest, TaskBroadcastType, TaskClearRequest, TaskManagementClient, TaskListRequest};
+use futures::StreamExt;
+use protobuf::{NodeListRequest, NodeManagementClient, TaskBroadcastRequest, TaskBroadcastType, TaskCancelRequest, TaskClearRequest, TaskErrorsRequest, TaskLogEntry, TaskLogsRequest, TaskManagementClient, TaskListRequest, TaskPauseRequest, TaskRegisterExecutorRequest, TaskResumeRequest, TaskRetryRequest};
+use serde_json::json;
 use tonic::Request;
 
+use crate::output::Output;
+
 use std::{error, io};
 use std::collections::HashMap;
 
 pub fn process(matches: &ArgMatches, task_matches: &ArgMatches) {
     let result: Result<(), Box<dyn error::Error>> 
             = match task_matches.subcommand() {
+        ("cancel", Some(cancel_matches)) =>
+            cancel(&matches, &task_matches, &cancel_matches),
         ("clear", Some(clear_matches)) =>
             clear(&matches, &task_matches, &clear_matches),
+        ("errors", Some(errors_matches)) =>
+            errors(&matches, &task_matches, &errors_matches),
+        ("executor", Some(executor_matches)) =>
+            executor(&matches, &task_matches, &executor_matches),
         ("list", Some(list_matches)) =>
             list(&matches, &task_matches, &list_matches),
+        ("logs", Some(logs_matches)) =>
+            logs(&matches, &task_matches, &logs_matches),
+        ("pause", Some(pause_matches)) =>
+            pause(&matches, &task_matches, &pause_matches),
+        ("resume", Some(resume_matches)) =>
+            resume(&matches, &task_matches, &resume_matches),
+        ("retry", Some(retry_matches)) =>
+            retry(&matches, &task_matches, &retry_matches),
         (cmd, _) => Err(Box::new(io::Error::new(io::ErrorKind::Other,
             format!("unknown subcommand '{}'", cmd)))),
     };
@@ -21,20 +39,64 @@ pub fn process(matches: &ArgMatches, task_matches: &ArgMatches) {
     }
 }
 
+#[tokio::main]
+async fn cancel(matches: &ArgMatches, _: &ArgMatches,
+        cancel_matches: &ArgMatches) -> Result<(), Box<dyn error::Error>> {
+    // initialize grpc client
+    let ip_address = matches.value_of("ip_address").unwrap();
+    let port = matches.value_of("port").unwrap().parse::<u16>()?;
+    let mut client = TaskManagementClient::new(crate::channel(matches,
+        &format!("{}:{}", ip_address, port)).await?);
+
+    // initialize request
+    let cancel_request = TaskCancelRequest {
+        id: cancel_matches.value_of("ID").unwrap().parse::<u64>()?,
+    };
+
+    let request = Request::new(TaskBroadcastRequest {
+        message_type: TaskBroadcastType::TaskCancel as i32,
+        clear_request: None,
+        list_request: None,
+        cancel_request: Some(cancel_request),
+        pause_request: None,
+        resume_request: None,
+        retry_request: None,
+        errors_request: None,
+    });
+
+    // retrieve reply
+    let reply = client.broadcast(request).await?;
+    let reply = reply.get_ref();
+
+    // print information
+    for (node_id, cancel_reply) in reply.cancel_replies.iter() {
+        if cancel_reply.cancelled {
+            println!("task cancelled on node '{}'", node_id);
+        }
+    }
+
+    Ok(())
+}
+
 #[tokio::main]
 async fn clear(matches: &ArgMatches, _: &ArgMatches,
         _clear_matches: &ArgMatches) -> Result<(), Box<dyn error::Error>> {
     // initialize grpc client
     let ip_address = matches.value_of("ip_address").unwrap();
     let port = matches.value_of("port").unwrap().parse::<u16>()?;
-    let mut client = TaskManagementClient::connect(
-        format!("http://{}:{}", ip_address, port)).await?;
+    let mut client = TaskManagementClient::new(crate::channel(matches,
+        &format!("{}:{}", ip_address, port)).await?);
 
     // initialize request
     let request = Request::new(TaskBroadcastRequest {
         message_type: TaskBroadcastType::TaskClear as i32,
         clear_request: Some(TaskClearRequest {}),
         list_request: None,
+        cancel_request: None,
+        pause_request: None,
+        resume_request: None,
+        retry_request: None,
+        errors_request: None,
     });
 
     // retrieve reply
@@ -43,20 +105,99 @@ async fn clear(matches: &ArgMatches, _: &ArgMatches,
     Ok(())
 }
 
+#[tokio::main]
+async fn errors(matches: &ArgMatches, _: &ArgMatches,
+        errors_matches: &ArgMatches) -> Result<(), Box<dyn error::Error>> {
+    // initialize grpc client
+    let ip_address = matches.value_of("ip_address").unwrap();
+    let port = matches.value_of("port").unwrap().parse::<u16>()?;
+    let mut client = TaskManagementClient::new(crate::channel(matches,
+        &format!("{}:{}", ip_address, port)).await?);
+
+    // initialize request
+    let errors_request = TaskErrorsRequest {
+        id: errors_matches.value_of("ID").unwrap().parse::<u64>()?,
+    };
+
+    let request = Request::new(TaskBroadcastRequest {
+        message_type: TaskBroadcastType::TaskErrors as i32,
+        clear_request: None,
+        list_request: None,
+        cancel_request: None,
+        pause_request: None,
+        resume_request: None,
+        retry_request: None,
+        errors_request: Some(errors_request),
+    });
+
+    // retrieve reply
+    let reply = client.broadcast(request).await?;
+    let reply = reply.get_ref();
+
+    // print information
+    println!("{:<8}{:<64}{}", "node", "record", "error");
+    println!("------------------------------------------------------------------------------------------------");
+    for (node_id, errors_reply) in reply.errors_replies.iter() {
+        for error in errors_reply.errors.iter() {
+            println!("{:<8}{:<64}{}", node_id, error.record, error.message);
+        }
+
+        // nodes retain only the first errors of each task
+        if let Some(omitted) = errors_reply.omitted.filter(|x| *x != 0) {
+            println!("{:<8}{:<64}{} further errors omitted",
+                node_id, "", omitted);
+        }
+    }
+
+    Ok(())
+}
+
+#[tokio::main]
+async fn executor(matches: &ArgMatches, _: &ArgMatches,
+        executor_matches: &ArgMatches) -> Result<(), Box<dyn error::Error>> {
+    // initialize grpc client
+    let ip_address = matches.value_of("ip_address").unwrap();
+    let port = matches.value_of("port").unwrap().parse::<u16>()?;
+    let mut client = TaskManagementClient::new(crate::channel(matches,
+        &format!("{}:{}", ip_address, port)).await?);
+
+    // initialize request
+    let request = Request::new(TaskRegisterExecutorRequest {
+        name: executor_matches.value_of("NAME").unwrap().to_string(),
+        address: executor_matches.value_of("ADDRESS").unwrap().to_string(),
+    });
+
+    // retrieve reply
+    let reply = client.register_executor(request).await?;
+
+    // print information
+    match &reply.get_ref().replaced {
+        Some(address) => println!("replaced executor at {}", address),
+        None => println!("registered executor"),
+    }
+
+    Ok(())
+}
+
 #[tokio::main]
 async fn list(matches: &ArgMatches, _: &ArgMatches,
-        _list_matches: &ArgMatches) -> Result<(), Box<dyn error::Error>> {
+        list_matches: &ArgMatches) -> Result<(), Box<dyn error::Error>> {
     // initialize grpc client
     let ip_address = matches.value_of("ip_address").unwrap();
     let port = matches.value_of("port").unwrap().parse::<u16>()?;
-    let mut client = TaskManagementClient::connect(
-        format!("http://{}:{}", ip_address, port)).await?;
+    let mut client = TaskManagementClient::new(crate::channel(matches,
+        &format!("{}:{}", ip_address, port)).await?);
 
     // initialize request
     let request = Request::new(TaskBroadcastRequest {
         message_type: TaskBroadcastType::TaskList as i32,
         clear_request: None,
         list_request: Some(TaskListRequest {}),
+        cancel_request: None,
+        pause_request: None,
+        resume_request: None,
+        retry_request: None,
+        errors_request: None,
     });
 
     // retrieve reply
@@ -68,30 +209,244 @@ async fn list(matches: &ArgMatches, _: &ArgMatches,
     for (_node_id, task_list_reply) in reply.list_replies.iter() {
         for task in task_list_reply.tasks.iter() {
             let mut task_tuple = tasks.entry(task.id).or_insert(
-                (0u16, 0u16, 0u16, 0u16, 0u32, 0u32, 0u32));
+                (0u16, 0u16, 0u16, 0u16, 0u16, 0u16, 0u32, 0u32, 0u32));
 
             // compile task status
-            match (task.running, task.completed_count, task.total_count) {
-                (true, _, 0) => task_tuple.0 += 1,
-                (true, _, _) => task_tuple.1 += 1,
-                (false, x, y) if x < y => task_tuple.2 += 1,
-                (false, _, _) => task_tuple.3 += 1,
+            match (task.running, task.queued, task.paused,
+                    task.completed_count, task.total_count) {
+                (true, _, true, _, _) => task_tuple.3 += 1,
+                (true, true, false, _, _) => task_tuple.1 += 1,
+                (true, false, false, _, 0) => task_tuple.0 += 1,
+                (true, false, false, _, _) => task_tuple.2 += 1,
+                (false, _, _, x, y) if x < y => task_tuple.4 += 1,
+                (false, _, _, _, _) => task_tuple.5 += 1,
             };
 
-            task_tuple.4 += task.completed_count;
-            task_tuple.5 += task.skipped_count;
-            task_tuple.6 += task.total_count;
+            task_tuple.6 += task.completed_count;
+            task_tuple.7 += task.skipped_count;
+            task_tuple.8 += task.total_count;
         }
     }
 
     // print information
-    println!("{:<24}{:<16}{:<12}{:<12}{:<12}{:<24}", "task_id",
-        "initializing", "running", "failed", "completed", "progress");
-    println!("----------------------------------------------------------------------------------------------------");
+    let output = Output::new(matches, vec![("task_id", 24),
+        ("initializing", 16), ("queued", 12), ("running", 12),
+        ("paused", 12), ("failed", 12), ("completed", 12),
+        ("progress", 24)])?.select(list_matches)?;
+    output.header();
     for (task_id, task_tuple) in tasks.iter() {
-        println!("{:<24}{:<16}{:<12}{:<12}{:<12}{:<24}", task_id,
-            task_tuple.0, task_tuple.1, task_tuple.2, task_tuple.3,
-            compute_progress(task_tuple.4, task_tuple.5, task_tuple.6));
+        output.row(vec![json!(task_id), json!(task_tuple.0),
+            json!(task_tuple.1), json!(task_tuple.2), json!(task_tuple.3),
+            json!(task_tuple.4), json!(task_tuple.5),
+            json!(compute_progress(task_tuple.6,
+                task_tuple.7, task_tuple.8))]);
+    }
+
+    output.flush();
+
+    // compute an agglomerated view of recurring tasks
+    let mut recurring_tasks = HashMap::new();
+    for (_node_id, task_list_reply) in reply.list_replies.iter() {
+        for recurring_task in task_list_reply.recurring_tasks.iter() {
+            let recurring_tuple = recurring_tasks
+                .entry(recurring_task.id).or_insert(
+                    (recurring_task.task_type.clone(),
+                    recurring_task.album.clone(),
+                    recurring_task.schedule.clone(),
+                    0u16, std::i64::MAX));
+
+            recurring_tuple.3 += 1;
+            recurring_tuple.4 = std::cmp::min(recurring_tuple.4,
+                recurring_task.next_timestamp);
+        }
+    }
+
+    if recurring_tasks.is_empty() {
+        return Ok(());
+    }
+
+    // print recurring information
+    let output = Output::new(matches, vec![("recurring_id", 24),
+        ("type", 12), ("album", 16), ("schedule", 20), ("nodes", 8),
+        ("next", 16)])?;
+    println!();
+    output.header();
+    for (recurring_id, recurring_tuple) in recurring_tasks.iter() {
+        output.row(vec![json!(recurring_id), json!(recurring_tuple.0),
+            json!(recurring_tuple.1), json!(recurring_tuple.2),
+            json!(recurring_tuple.3), json!(recurring_tuple.4)]);
+    }
+
+    Ok(())
+}
+
+#[tokio::main]
+async fn logs(matches: &ArgMatches, _: &ArgMatches,
+        logs_matches: &ArgMatches) -> Result<(), Box<dyn error::Error>> {
+    // initialize grpc client
+    let ip_address = matches.value_of("ip_address").unwrap();
+    let port = matches.value_of("port").unwrap().parse::<u16>()?;
+    let mut client = NodeManagementClient::new(crate::channel(matches,
+        &format!("{}:{}", ip_address, port)).await?);
+
+    // retrieve cluster nodes
+    let reply = client.list(Request::new(NodeListRequest {})).await?;
+
+    // initialize request
+    let follow = logs_matches.is_present("follow");
+    let request = TaskLogsRequest {
+        follow: Some(follow),
+        id: logs_matches.value_of("ID").unwrap().parse::<u64>()?,
+    };
+
+    // open a log stream on each node
+    let mut streams = Vec::new();
+    for node in reply.into_inner().nodes {
+        let mut client = TaskManagementClient::new(
+            crate::channel(matches, &node.rpc_addr).await?);
+        let stream = client.logs(Request::new(request.clone()))
+            .await?.into_inner();
+
+        streams.push(Box::pin(stream.map(move |x| x.map(|x| (node.id, x)))));
+    }
+
+    // print entries as they arrive when following, otherwise in order
+    println!("{:<12}{:<8}{:<8}{}", "timestamp", "node", "level", "message");
+    println!("------------------------------------------------------------------------------------------------");
+
+    let mut entries = Vec::new();
+    let mut stream = futures::stream::select_all(streams);
+    while let Some(result) = stream.next().await {
+        let (node_id, entry) = result?;
+        match follow {
+            true => print_log_entry(node_id, &entry),
+            false => entries.push((node_id, entry)),
+        }
+    }
+
+    entries.sort_by_key(|(node_id, entry)| (entry.timestamp, *node_id));
+    for (node_id, entry) in entries.iter() {
+        print_log_entry(*node_id, entry);
+    }
+
+    Ok(())
+}
+
+#[tokio::main]
+async fn pause(matches: &ArgMatches, _: &ArgMatches,
+        pause_matches: &ArgMatches) -> Result<(), Box<dyn error::Error>> {
+    // initialize grpc client
+    let ip_address = matches.value_of("ip_address").unwrap();
+    let port = matches.value_of("port").unwrap().parse::<u16>()?;
+    let mut client = TaskManagementClient::new(crate::channel(matches,
+        &format!("{}:{}", ip_address, port)).await?);
+
+    // initialize request
+    let pause_request = TaskPauseRequest {
+        id: pause_matches.value_of("ID").unwrap().parse::<u64>()?,
+    };
+
+    let request = Request::new(TaskBroadcastRequest {
+        message_type: TaskBroadcastType::TaskPause as i32,
+        clear_request: None,
+        list_request: None,
+        cancel_request: None,
+        pause_request: Some(pause_request),
+        resume_request: None,
+        retry_request: None,
+        errors_request: None,
+    });
+
+    // retrieve reply
+    let reply = client.broadcast(request).await?;
+    let reply = reply.get_ref();
+
+    // print information
+    for (node_id, pause_reply) in reply.pause_replies.iter() {
+        if pause_reply.paused {
+            println!("task paused on node '{}'", node_id);
+        }
+    }
+
+    Ok(())
+}
+
+#[tokio::main]
+async fn resume(matches: &ArgMatches, _: &ArgMatches,
+        resume_matches: &ArgMatches) -> Result<(), Box<dyn error::Error>> {
+    // initialize grpc client
+    let ip_address = matches.value_of("ip_address").unwrap();
+    let port = matches.value_of("port").unwrap().parse::<u16>()?;
+    let mut client = TaskManagementClient::new(crate::channel(matches,
+        &format!("{}:{}", ip_address, port)).await?);
+
+    // initialize request
+    let resume_request = TaskResumeRequest {
+        id: resume_matches.value_of("ID").unwrap().parse::<u64>()?,
+    };
+
+    let request = Request::new(TaskBroadcastRequest {
+        message_type: TaskBroadcastType::TaskResume as i32,
+        clear_request: None,
+        list_request: None,
+        cancel_request: None,
+        pause_request: None,
+        resume_request: Some(resume_request),
+        retry_request: None,
+        errors_request: None,
+    });
+
+    // retrieve reply
+    let reply = client.broadcast(request).await?;
+    let reply = reply.get_ref();
+
+    // print information
+    for (node_id, resume_reply) in reply.resume_replies.iter() {
+        if resume_reply.resumed {
+            println!("task resumed on node '{}'", node_id);
+        }
+    }
+
+    Ok(())
+}
+
+#[tokio::main]
+async fn retry(matches: &ArgMatches, _: &ArgMatches,
+        retry_matches: &ArgMatches) -> Result<(), Box<dyn error::Error>> {
+    // initialize grpc client
+    let ip_address = matches.value_of("ip_address").unwrap();
+    let port = matches.value_of("port").unwrap().parse::<u16>()?;
+    let mut client = TaskManagementClient::new(crate::channel(matches,
+        &format!("{}:{}", ip_address, port)).await?);
+
+    // initialize request
+    let retry_request = TaskRetryRequest {
+        id: retry_matches.value_of("ID").unwrap().parse::<u64>()?,
+        thread_count: retry_matches.value_of("thread_count")
+            .unwrap().parse::<u32>()?,
+    };
+
+    let request = Request::new(TaskBroadcastRequest {
+        message_type: TaskBroadcastType::TaskRetry as i32,
+        clear_request: None,
+        list_request: None,
+        cancel_request: None,
+        pause_request: None,
+        resume_request: None,
+        retry_request: Some(retry_request),
+        errors_request: None,
+    });
+
+    // retrieve reply
+    let reply = client.broadcast(request).await?;
+    let reply = reply.get_ref();
+
+    // print information
+    for (node_id, retry_reply) in reply.retry_replies.iter() {
+        if retry_reply.retry_count != 0 {
+            println!("retrying {} record(s) on node '{}'",
+                retry_reply.retry_count, node_id);
+        }
     }
 
     Ok(())
@@ -104,3 +459,8 @@ fn compute_progress(completed_count: u32,
         _ => (completed_count + skipped_count) as f32 / total_count as f32,
     }
 }
+
+fn print_log_entry(node_id: u32, entry: &TaskLogEntry) {
+    println!("{:<12}{:<8}{:<8}{}", entry.timestamp,
+        node_id, entry.level, entry.message);
+}
@@ -0,0 +1,107 @@
+use chrono::Utc;
+use serde_json::{json, Value};
+
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+pub const DEFAULT_TENANT: &'static str = "default";
+
+#[derive(Clone, Debug, Default)]
+pub struct Usage {
+    pub bytes_ingested: u64,
+    pub bytes_served: u64,
+    pub compute_ms: u64,
+}
+
+pub struct UsageLedger {
+    dirty: AtomicBool,
+    path: PathBuf,
+    usage: Mutex<BTreeMap<(String, String), Usage>>,
+}
+
+impl UsageLedger {
+    pub fn new(path: PathBuf) -> Result<UsageLedger, Box<dyn Error>> {
+        // load persisted usage
+        let mut usage = BTreeMap::new();
+        if path.exists() {
+            let value: Value =
+                serde_json::from_slice(&std::fs::read(&path)?)?;
+            for entry in value.as_array().ok_or("invalid usage ledger")? {
+                let (month, tenant) = match (entry["month"].as_str(),
+                        entry["tenant"].as_str()) {
+                    (Some(month), Some(tenant)) => (month, tenant),
+                    _ => return Err("invalid usage ledger entry".into()),
+                };
+
+                usage.insert((month.to_string(), tenant.to_string()), Usage {
+                    bytes_ingested: entry["bytes_ingested"]
+                        .as_u64().unwrap_or(0),
+                    bytes_served: entry["bytes_served"].as_u64().unwrap_or(0),
+                    compute_ms: entry["compute_ms"].as_u64().unwrap_or(0),
+                });
+            }
+        }
+
+        Ok(UsageLedger {
+            dirty: AtomicBool::new(false),
+            path: path,
+            usage: Mutex::new(usage),
+        })
+    }
+
+    pub fn charge(&self, tenant: &Option<String>, bytes_ingested: u64,
+            bytes_served: u64, compute_ms: u64) {
+        if bytes_ingested == 0 && bytes_served == 0 && compute_ms == 0 {
+            return;
+        }
+
+        // charge usage to tenant within the current month
+        let month = Utc::now().format("%Y-%m").to_string();
+        let tenant = tenant.clone()
+            .unwrap_or(DEFAULT_TENANT.to_string());
+
+        let mut usage = self.usage.lock().unwrap();
+        let entry = usage.entry((month, tenant)).or_insert(Usage::default());
+        entry.bytes_ingested += bytes_ingested;
+        entry.bytes_served += bytes_served;
+        entry.compute_ms += compute_ms;
+
+        self.dirty.store(true, Ordering::SeqCst);
+    }
+
+    pub fn flush(&self) -> Result<(), Box<dyn Error>> {
+        if !self.dirty.swap(false, Ordering::SeqCst) {
+            return Ok(());
+        }
+
+        let entries: Vec<Value> = self.usage.lock().unwrap().iter()
+            .map(|((month, tenant), usage)| json!({
+                "month": month,
+                "tenant": tenant,
+                "bytes_ingested": usage.bytes_ingested,
+                "bytes_served": usage.bytes_served,
+                "compute_ms": usage.compute_ms,
+            })).collect();
+
+        // write to a temporary file and rename to replace atomically
+        let tmp_path = self.path.with_extension("tmp");
+        std::fs::write(&tmp_path, Value::Array(entries).to_string())?;
+        std::fs::rename(&tmp_path, &self.path)?;
+        Ok(())
+    }
+
+    pub fn report(&self, month: &Option<String>, tenant: &Option<String>)
+            -> Vec<(String, String, Usage)> {
+        self.usage.lock().unwrap().iter()
+            .filter(|((x, _), _)| month.is_none()
+                || month.as_ref() == Some(x))
+            .filter(|((_, x), _)| tenant.is_none()
+                || tenant.as_ref() == Some(x))
+            .map(|((month, tenant), usage)|
+                (month.clone(), tenant.clone(), usage.clone()))
+            .collect()
+    }
+}
@@ -0,0 +1,188 @@
+use serde_json::{json, Value};
+use swarm::prelude::Dht;
+
+use crate::album::AlbumManager;
+use crate::task::TaskManager;
+
+use std::error::Error;
+use std::ffi::CString;
+use std::io::{Read, Write};
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+
+pub struct AdminServer {
+    album_manager: Arc<RwLock<AlbumManager>>,
+    dht: Arc<Dht>,
+    directory: PathBuf,
+    node_id: u32,
+    task_manager: Arc<RwLock<TaskManager>>,
+}
+
+impl AdminServer {
+    pub fn new(album_manager: Arc<RwLock<AlbumManager>>, dht: Arc<Dht>,
+            directory: PathBuf, node_id: u32,
+            task_manager: Arc<RwLock<TaskManager>>) -> AdminServer {
+        AdminServer {
+            album_manager: album_manager,
+            dht: dht,
+            directory: directory,
+            node_id: node_id,
+            task_manager: task_manager,
+        }
+    }
+
+    pub fn start(self, path: &PathBuf) -> Result<(), Box<dyn Error>> {
+        // remove stale socket from a previous run
+        if path.exists() {
+            std::fs::remove_file(path)?;
+        }
+
+        info!("starting admin server [path={:?}]", path);
+        let listener = UnixListener::bind(path)?;
+
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = match stream {
+                    Ok(stream) => stream,
+                    Err(e) => {
+                        warn!("failed to accept admin connection: {}", e);
+                        continue;
+                    },
+                };
+
+                // write status and close connection
+                let status = self.status().to_string();
+                if let Err(e) = stream.write_all(status.as_bytes()) {
+                    warn!("failed to write admin status: {}", e);
+                }
+            }
+        });
+
+        Ok(())
+    }
+
+    fn status(&self) -> Value {
+        // compile ring membership
+        let mut in_ring = false;
+        let mut nodes = Vec::new();
+        for node in self.dht.nodes() {
+            in_ring |= node.get_id() == self.node_id;
+            nodes.push(json!({
+                "id": node.get_id(),
+                "ip_address": node.get_ip_address().to_string(),
+                "rpc_port": node.get_metadata("rpc_port"),
+                "xfer_port": node.get_metadata("xfer_port"),
+            }));
+        }
+
+        // compile album index load state
+        let albums: Vec<Value> = {
+            let album_manager = self.album_manager.read().unwrap();
+            album_manager.iter().map(|(id, album)| {
+                let album = album.read().unwrap();
+                json!({
+                    "id": id,
+                    "open": album.get_index().is_some(),
+                })
+            }).collect()
+        };
+
+        // compile task counts
+        let (mut queued, mut running, mut paused,
+            mut cancelled, mut failed, mut completed) = (0, 0, 0, 0, 0, 0);
+        {
+            let task_manager = self.task_manager.read().unwrap();
+            for (_, task_handle) in task_manager.iter() {
+                match (task_handle.running(), task_handle.queued(),
+                        task_handle.paused()) {
+                    (true, _, true) => paused += 1,
+                    (true, true, false) => queued += 1,
+                    (true, false, false) => running += 1,
+                    (false, _, _) if task_handle.cancelled() =>
+                        cancelled += 1,
+                    (false, _, _) if !task_handle.errors().is_empty() =>
+                        failed += 1,
+                    (false, _, _) => completed += 1,
+                }
+            }
+        }
+
+        // compute storage usage from the filesystem rather than walking
+        //   the storage directory
+        let (free_bytes, total_bytes, storage_error) =
+                match disk_usage(&self.directory) {
+            Ok((free_bytes, total_bytes)) =>
+                (Some(free_bytes), Some(total_bytes), None),
+            Err(e) => (None, None, Some(e.to_string())),
+        };
+
+        let storage_bytes = match (free_bytes, total_bytes) {
+            (Some(free_bytes), Some(total_bytes)) =>
+                Some(total_bytes.saturating_sub(free_bytes)),
+            _ => None,
+        };
+
+        json!({
+            "healthy": in_ring && storage_error.is_none(),
+            "node_id": self.node_id,
+            "ring": {
+                "member": in_ring,
+                "nodes": nodes,
+            },
+            "albums": albums,
+            "tasks": {
+                "cancelled": cancelled,
+                "completed": completed,
+                "failed": failed,
+                "paused": paused,
+                "queued": queued,
+                "running": running,
+            },
+            "storage": {
+                "directory": self.directory.to_string_lossy(),
+                "error": storage_error,
+                "free_bytes": free_bytes,
+                "total_bytes": total_bytes,
+                "used_bytes": storage_bytes,
+            },
+        })
+    }
+}
+
+pub fn status(path: &PathBuf) -> i32 {
+    // retrieve status from admin socket
+    let mut buf = String::new();
+    let result = UnixStream::connect(path)
+        .and_then(|mut stream| stream.read_to_string(&mut buf));
+
+    if let Err(e) = result {
+        println!("{}", json!({
+            "healthy": false,
+            "error": format!("failed to connect to admin socket {:?}: {}",
+                path, e),
+        }));
+        return 1;
+    }
+
+    println!("{}", buf);
+
+    // set exit code from reported health
+    match serde_json::from_str::<Value>(&buf) {
+        Ok(status) if status["healthy"] == Value::Bool(true) => 0,
+        _ => 1,
+    }
+}
+
+// free and total bytes of the filesystem containing path
+pub fn disk_usage(path: &PathBuf) -> Result<(u64, u64), Box<dyn Error>> {
+    let c_path = CString::new(path.as_os_str().as_bytes())?;
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statvfs(c_path.as_ptr(), &mut stat) } != 0 {
+        return Err(std::io::Error::last_os_error().into());
+    }
+
+    Ok((stat.f_bavail as u64 * stat.f_frsize as u64,
+        stat.f_blocks as u64 * stat.f_frsize as u64))
+}
@@ -1,37 +1,152 @@
-use byteorder::{ReadBytesExt, WriteBytesExt};
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
 use gdal::{Dataset, Driver, Metadata};
 use geocode::Geocode;
+use protobuf::EventType;
+use serde_json::json;
 
 use crate::{Extent, Image, StFile};
-use crate::index::AlbumIndex;
+use crate::index::{AlbumIndex, ListCursor, TimeInterval};
+use crate::metrics::Counter;
+use crate::raster::Statistics;
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::collections::hash_map::Iter;
 use std::error::Error;
 use std::ffi::{CStr, CString};
 use std::fs::File;
-use std::path::PathBuf;
+use std::io::{Read, Write};
+use std::os::raw::c_int;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
 use std::sync::{Arc, RwLock};
 use std::os::unix::fs::PermissionsExt;
+use std::time::{Instant, SystemTime};
+
+// sources checked for alignment with images of other sources
+static COREGISTRATION_SOURCES: RwLock<Vec<String>> = RwLock::new(Vec::new());
+
+const FROZEN_FLAG: u8 = 0x01;
+const GEO_TRANSFORM_EPSILON: f64 = 0.000001;
+const LEGAL_HOLD_FLAG: u8 = 0x02;
+
+#[derive(Clone, Copy, Debug)]
+pub enum Coregistration {
+    Flag,
+    Reject,
+    Resample,
+}
+
+impl FromStr for Coregistration {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "flag" => Ok(Coregistration::Flag),
+            "reject" => Ok(Coregistration::Reject),
+            "resample" => Ok(Coregistration::Resample),
+            _ => Err(format!("unknown coregistration policy '{}'", s)),
+        }
+    }
+}
+
+// album retention rules, where 0 values are persisted as unset
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Retention {
+    pub max_bytes: Option<u64>,
+    pub raw_ttl_days: Option<u32>,
+}
+
+impl Retention {
+    pub fn is_empty(&self) -> bool {
+        self.max_bytes.is_none() && self.raw_ttl_days.is_none()
+    }
+}
+
+// image staged by a transaction until it commits
+pub struct StagedImage {
+    pub album: String,
+    pub cloud_coverage: Option<f64>,
+    pub geocode: String,
+    pub path: PathBuf,
+    pub pixel_coverage: f64,
+    pub platform: String,
+    pub source: String,
+    pub subdataset: u8,
+    pub tile: String,
+    pub timestamp: i64,
+}
+
+impl StagedImage {
+    fn read<T: Read>(reader: &mut T) -> Result<StagedImage, Box<dyn Error>> {
+        let album = crate::transfer::read_string(reader)?;
+        let cloud_coverage = match reader.read_u8()? {
+            0 => None,
+            _ => Some(reader.read_f64::<BigEndian>()?),
+        };
+
+        Ok(StagedImage {
+            album: album,
+            cloud_coverage: cloud_coverage,
+            geocode: crate::transfer::read_string(reader)?,
+            path: PathBuf::from(crate::transfer::read_string(reader)?),
+            pixel_coverage: reader.read_f64::<BigEndian>()?,
+            platform: crate::transfer::read_string(reader)?,
+            source: crate::transfer::read_string(reader)?,
+            subdataset: reader.read_u8()?,
+            tile: crate::transfer::read_string(reader)?,
+            timestamp: reader.read_i64::<BigEndian>()?,
+        })
+    }
+
+    fn write<T: Write>(&self, writer: &mut T) -> Result<(), Box<dyn Error>> {
+        crate::transfer::write_string(&self.album, writer)?;
+        match self.cloud_coverage {
+            Some(cloud_coverage) => {
+                writer.write_u8(1)?;
+                writer.write_f64::<BigEndian>(cloud_coverage)?;
+            },
+            None => writer.write_u8(0)?,
+        }
+
+        crate::transfer::write_string(&self.geocode, writer)?;
+        crate::transfer::write_string(&self.path.to_string_lossy(), writer)?;
+        writer.write_f64::<BigEndian>(self.pixel_coverage)?;
+        crate::transfer::write_string(&self.platform, writer)?;
+        crate::transfer::write_string(&self.source, writer)?;
+        writer.write_u8(self.subdataset)?;
+        crate::transfer::write_string(&self.tile, writer)?;
+        writer.write_i64::<BigEndian>(self.timestamp)?;
+        Ok(())
+    }
+}
 
 pub struct AlbumManager {
+    coregistration: Coregistration,
     directory: PathBuf,
     albums: HashMap<String, Arc<RwLock<Album>>>,
 }
 
 impl AlbumManager {
-    pub fn new(directory: PathBuf)
+    pub fn new(directory: PathBuf, coregistration: Coregistration)
             -> Result<AlbumManager, Box<dyn Error>> {
         // parse existing albums
         let mut albums = HashMap::new();
         for entry in std::fs::read_dir(&directory)? {
             let mut path = entry?.path();
+            if !path.is_dir() {
+                continue;
+            }
+
             let id = path.file_name().unwrap()
                 .to_string_lossy().to_string();
 
             // parse metadata file
             path.push("album");
             path.set_extension("meta");
+            if !path.exists() {
+                continue;
+            }
+
             let mut file = File::open(&path)?;
 
             let dht_key_length = file.read_i8()?;
@@ -43,30 +158,88 @@ impl AlbumManager {
                     geocode_value).into()),
             };
 
+            // albums created before hold flags have none set
+            let flags = file.read_u8().unwrap_or(0);
+
+            // albums created before manifest settings have none set
+            let precision = match file.read_u8().unwrap_or(0) {
+                0 => None,
+                x => Some(x as usize),
+            };
+
+            let mut creation_options = Vec::new();
+            for _ in 0..file.read_u8().unwrap_or(0) {
+                creation_options.push(
+                    crate::transfer::read_string(&mut file)?);
+            }
+
+            // albums created before retention rules have none set
+            let raw_ttl_days = file.read_u32::<BigEndian>().unwrap_or(0);
+            let max_bytes = file.read_u64::<BigEndian>().unwrap_or(0);
+            let retention = Retention {
+                max_bytes: Some(max_bytes).filter(|x| *x != 0),
+                raw_ttl_days: Some(raw_ttl_days).filter(|x| *x != 0),
+            };
+
             path.pop();
 
+            let mut album = Album {
+                coregistration: coregistration,
+                creation_options: creation_options,
+                dht_key_length: dht_key_length,
+                directory: path,
+                frozen: flags & FROZEN_FLAG != 0,
+                geocode: geocode,
+                id: id.clone(),
+                index: None,
+                legal_hold: flags & LEGAL_HOLD_FLAG != 0,
+                precision: precision,
+                retention: retention,
+                snapshot_time: None,
+            };
+
+            // complete commits interrupted by a restart, then remove
+            //   images staged by uncommitted transactions and files of
+            //   writes interrupted before their rename
+            album.recover_commits()?;
+            album.clear_staging()?;
+            album.clear_temporary()?;
+
             // add album to map
-            albums.insert(id.clone(),
-                Arc::new(RwLock::new(Album {
-                    dht_key_length: dht_key_length,
-                    directory: path,
-                    geocode: geocode,
-                    id: id,
-                    index: None,
-                })));
+            albums.insert(id, Arc::new(RwLock::new(album)));
         }
 
         Ok(AlbumManager {
+            coregistration: coregistration,
             directory: directory,
             albums: albums,
         })
     }
 
-    pub fn create(&mut self, dht_key_length: i8, geocode: Geocode,
-            id: &str) -> Result<(), Box<dyn Error>> {
-        info!("creating album [id:{}, geocode={:?}, dht_key_length={}]",
-            id, geocode, dht_key_length);
-            
+    pub fn create(&mut self, creation_options: Vec<String>,
+            dht_key_length: i8, geocode: Geocode, id: &str,
+            precision: Option<usize>) -> Result<(), Box<dyn Error>> {
+        info!("creating album [id:{}, geocode={:?}, dht_key_length={}, precision={:?}]",
+            id, geocode, dht_key_length, precision);
+
+        // validate manifest settings before creating the album
+        match precision {
+            Some(x) if x == 0 || x > u8::MAX as usize => return Err(
+                format!("album precision {} is out of range", x).into()),
+            _ => {},
+        }
+
+        if creation_options.len() > u8::MAX as usize {
+            return Err("too many GeoTIFF creation options".into());
+        }
+
+        for option in creation_options.iter() {
+            if !option.contains('=') || option.len() > u8::MAX as usize {
+                return Err(format!("invalid GeoTIFF creation option '{}'",
+                    option).into());
+            }
+        }
+
         // create album directory
         let mut path = self.directory.clone();
         path.push(id);
@@ -77,28 +250,27 @@ impl AlbumManager {
         std::fs::set_permissions(&path, permissions)?;
 
         // write metadata file
-        path.push("album");
-        path.set_extension("meta");
-        let mut file = File::create(&path)?;
+        let album = Album {
+            coregistration: self.coregistration,
+            creation_options: creation_options,
+            dht_key_length: dht_key_length,
+            directory: path,
+            frozen: false,
+            geocode: geocode,
+            id: id.to_string(),
+            index: None,
+            legal_hold: false,
+            precision: precision,
+            retention: Retention::default(),
+            snapshot_time: None,
+        };
 
-        file.write_i8(dht_key_length)?;
-        match geocode {
-            Geocode::Geohash => file.write_u8(0)?,
-            Geocode::QuadTile => file.write_u8(1)?,
-            _ => return Err(format!(
-                "unsupported geocode: {:?}", geocode).into()),
-        }
-        path.pop();
+        album.write_metadata()?;
 
         // add album to map
-        self.albums.insert(id.to_string(),
-            Arc::new(RwLock::new(Album {
-                dht_key_length: dht_key_length,
-                directory: path,
-                geocode: geocode,
-                id: id.to_string(),
-                index: None,
-            })));
+        self.albums.insert(id.to_string(), Arc::new(RwLock::new(album)));
+        crate::event::publish(EventType::AlbumCreated,
+            &[("album", id.to_string())]);
 
         Ok(())
     }
@@ -106,14 +278,22 @@ impl AlbumManager {
     pub fn delete(&mut self, id: &str) -> Result<(), Box<dyn Error>> {
         info!("deleting album [id:{}]", id);
 
+        // ensure album is not under a hold
+        if let Some(album) = self.albums.get(id) {
+            album.read().unwrap().assert_modifiable()?;
+        }
+
         // delete album directory
         let mut path = self.directory.clone();
         path.push(id);
 
         std::fs::remove_dir_all(&path)?;
+        crate::compaction::evict(&path);
 
         // remove from map
         self.albums.remove(id);
+        crate::event::publish(EventType::AlbumDeleted,
+            &[("album", id.to_string())]);
 
         Ok(())
     }
@@ -128,16 +308,185 @@ impl AlbumManager {
 }
 
 pub struct Album {
+    coregistration: Coregistration,
+    creation_options: Vec<String>,
     dht_key_length: i8,
     directory: PathBuf,
+    frozen: bool,
     geocode: Geocode,
     id: String,
-    index: Option<AlbumIndex>,
+    index: Option<Arc<AlbumIndex>>,
+    legal_hold: bool,
+    precision: Option<usize>,
+    retention: Retention,
+    snapshot_time: Option<SystemTime>,
 }
 
 impl Album {
+    // holds protect existing images from deletion and rewrites
+    pub fn assert_modifiable(&self) -> Result<(), Box<dyn Error>> {
+        match (self.frozen, self.legal_hold) {
+            (true, _) => Err(format!("album '{}' is frozen", self.id).into()),
+            (_, true) => Err(format!("album '{}' is under legal hold",
+                self.id).into()),
+            _ => Ok(()),
+        }
+    }
+
+    fn assert_writable(&self) -> Result<(), Box<dyn Error>> {
+        match self.frozen {
+            true => Err(format!("album '{}' is frozen", self.id).into()),
+            false => Ok(()),
+        }
+    }
+
     pub fn close(&mut self) {
+        if let Err(e) = self.snapshot() {
+            warn!("failed to snapshot album '{}' index: {}", self.id, e);
+        }
+
         self.index = None;
+        self.snapshot_time = None;
+    }
+
+    fn coregister(&self, dataset: &Dataset, geocode: &str,
+            platform: &str, source: &str, timestamp: i64,
+            transaction_id: Option<u64>)
+            -> Result<(Option<Dataset>, Option<PathBuf>), Box<dyn Error>> {
+        // reprojected images intentionally use a different grid
+        if source.starts_with(crate::REPROJECTED_SOURCE_PREFIX)
+                || COREGISTRATION_SOURCES.read().unwrap().is_empty() {
+            return Ok((None, None));
+        }
+
+        // find image of another source for the same geocode and timestamp,
+        //   including images staged earlier in the same transaction
+        let reference = match self.find_reference(
+                geocode, platform, source, timestamp)? {
+            Some(reference) => Some(reference),
+            None => match transaction_id {
+                Some(transaction_id) => self.find_staged_reference(
+                    transaction_id, geocode, platform, source, timestamp)?,
+                None => None,
+            },
+        };
+
+        let (reference_path, reference) = match reference {
+            Some(reference) => reference,
+            None => return Ok((None, None)),
+        };
+
+        if is_aligned(dataset, &reference)? {
+            return Ok((None, None));
+        }
+
+        match self.coregistration {
+            Coregistration::Flag => {
+                warn!("image misaligned with {:?} [album={}, geocode={}, source={}, timestamp={}]",
+                    reference_path, self.id, geocode, source, timestamp);
+                Ok((None, Some(reference_path)))
+            },
+            Coregistration::Reject => Err(format!(
+                "image misaligned with {:?} [geocode={}, source={}, timestamp={}]",
+                reference_path, geocode, source, timestamp).into()),
+            Coregistration::Resample => {
+                debug!("resampling image onto {:?} [album={}, geocode={}, source={}, timestamp={}]",
+                    reference_path, self.id, geocode, source, timestamp);
+                Ok((Some(resample(dataset, &reference)?), None))
+            },
+        }
+    }
+
+    fn find_reference(&self, geocode: &str, platform: &str,
+            source: &str, timestamp: i64)
+            -> Result<Option<(PathBuf, Dataset)>, Box<dyn Error>> {
+        // open albums find references in the index without a scan
+        if let Some(index) = &self.index {
+            for (reference_source, tile, subdataset) in index.references(
+                    geocode, platform, source, timestamp)? {
+                if reference_source
+                        .starts_with(crate::REPROJECTED_SOURCE_PREFIX)
+                        || !is_coregistered(source, &reference_source) {
+                    continue;
+                }
+
+                let path = self.get_image_path(false, geocode,
+                    platform, &reference_source, subdataset, &tile)?;
+                let dataset = crate::compaction::open(&path)?;
+                return Ok(Some((path, dataset)));
+            }
+
+            return Ok(None);
+        }
+
+        let glob_expression = format!("{}/{}/{}/*/*.tif",
+            self.directory.to_string_lossy(), platform, geocode);
+        let timestamp = timestamp.to_string();
+
+        for entry in glob::glob(&glob_expression)? {
+            let path = entry?;
+
+            // skip images of the ingested source and reprojected images
+            let directory = path.parent().and_then(|x| x.file_name())
+                .and_then(|x| x.to_str()).unwrap_or("");
+            if directory == source || directory
+                    .starts_with(crate::REPROJECTED_SOURCE_PREFIX)
+                    || !is_coregistered(source, directory) {
+                continue;
+            }
+
+            let dataset = Dataset::open(&path)?;
+            if dataset.metadata_item("TIMESTAMP", "STIP").as_ref()
+                    == Some(&timestamp) {
+                return Ok(Some((path, dataset)));
+            }
+        }
+
+        Ok(None)
+    }
+
+    fn find_staged_reference(&self, transaction_id: u64, geocode: &str,
+            platform: &str, source: &str, timestamp: i64)
+            -> Result<Option<(PathBuf, Dataset)>, Box<dyn Error>> {
+        let glob_expression = format!("{}/{}-*.tif",
+            self.get_staging_path().to_string_lossy(), transaction_id);
+        let timestamp = timestamp.to_string();
+
+        for entry in glob::glob(&glob_expression)? {
+            let dataset = Dataset::open(&entry?)?;
+            let item = |name| dataset.metadata_item(name, "STIP")
+                .unwrap_or(String::new());
+
+            let reference_source = item("SOURCE");
+            if item("GEOCODE") != geocode || item("PLATFORM") != platform
+                    || item("TIMESTAMP") != timestamp
+                    || reference_source == source || reference_source
+                        .starts_with(crate::REPROJECTED_SOURCE_PREFIX)
+                    || !is_coregistered(source, &reference_source) {
+                continue;
+            }
+
+            // name the reference by the path it is committed to
+            let subdataset = item("SUBDATASET").parse::<u8>()?;
+            let path = self.get_image_path(false, geocode, platform,
+                &reference_source, subdataset, &item("TILE"))?;
+            return Ok(Some((path, dataset)));
+        }
+
+        Ok(None)
+    }
+
+    pub fn distinct(&self, precision: usize) -> Result<(Vec<String>,
+            Vec<String>, Vec<String>, Vec<u8>), Box<dyn Error>> {
+        match &self.index {
+            Some(index) => Ok(index.distinct(precision)?),
+            None => Err("unable to query distinct values on closed album"
+                .into()),
+        }
+    }
+
+    pub fn get_creation_options(&self) -> &Vec<String> {
+        &self.creation_options
     }
 
     pub fn get_dht_key_length(&self) -> i8 {
@@ -173,146 +522,892 @@ impl Album {
         Ok(path)
     }
 
-    pub fn get_index(&self) -> &Option<AlbumIndex> {
+    pub fn get_index(&self) -> &Option<Arc<AlbumIndex>> {
         &self.index
     }
 
-    pub fn get_paths(&self) -> Result<Vec<PathBuf>, Box<dyn Error>> {
-        let glob_expression = format!("{}/*/*/*/*tif",
+    pub fn get_precision(&self) -> Option<usize> {
+        self.precision
+    }
+
+    pub fn get_retention(&self) -> &Retention {
+        &self.retention
+    }
+
+    fn get_metadata_path(&self) -> PathBuf {
+        let mut path = self.directory.clone();
+        path.push("album");
+        path.set_extension("meta");
+        path
+    }
+
+    // 'platform/geocode' directories, which partition the album images
+    //   so they may be scanned concurrently
+    pub fn get_shards(&self) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+        let glob_expression = format!("{}/*/*",
             self.directory.to_string_lossy());
 
-        // iterate over existing images
-        let mut paths = Vec::new();
+        let mut shards = Vec::new();
         for entry in glob::glob(&glob_expression)? {
-            paths.push(entry?);
+            let directory = entry?;
+            if directory.is_dir() {
+                shards.push(directory);
+            }
+        }
+
+        Ok(shards)
+    }
+
+    // paths of indexed images, None if the album is closed
+    pub fn indexed_paths(&self)
+            -> Result<Option<HashSet<PathBuf>>, Box<dyn Error>> {
+        if self.index.is_none() {
+            return Ok(None);
+        }
+
+        let images = self.list(&None, &None, &None,
+            &None, &None, true, &None, &None)?;
+        Ok(Some(images.into_iter()
+            .flat_map(|(_, files)| files.into_iter())
+            .map(|file| PathBuf::from(file.0)).collect()))
+    }
+
+    // paths of images stored within the album directory
+    pub fn stored_paths(&self) -> Result<HashSet<PathBuf>, Box<dyn Error>> {
+        let mut paths = HashSet::new();
+        for shard in self.get_shards()? {
+            paths.extend(get_shard_paths(&shard, None)?);
         }
 
         Ok(paths)
     }
 
+    pub fn get_snapshot_time(&self) -> Option<SystemTime> {
+        self.snapshot_time
+    }
+
+    fn get_snapshot_path(&self) -> PathBuf {
+        let mut path = self.directory.clone();
+        path.push("album");
+        path.set_extension("snapshot");
+        path
+    }
+
+    // number of indexed images, None if the album is closed
+    pub fn image_count(&self) -> Result<Option<u64>, Box<dyn Error>> {
+        match &self.index {
+            Some(index) => Ok(Some(index.image_count()?)),
+            None => Ok(None),
+        }
+    }
+
     pub fn list(&self, end_timestamp: &Option<i64>,
             geocode: &Option<String>, max_cloud_coverage: &Option<f64>,
             min_pixel_coverage: &Option<f64>, platform: &Option<String>,
             recurse: bool, source: &Option<String>,
             start_timestamp: &Option<i64>)
             -> Result<Vec<(Image, Vec<StFile>)>, Box<dyn Error>> {
-        match &self.index {
-            Some(index) => Ok(index.list(&self, end_timestamp, geocode,
+        let start = Instant::now();
+        let images = match &self.index {
+            Some(index) => index.list(&self, end_timestamp, geocode,
                 max_cloud_coverage, min_pixel_coverage, platform,
-                recurse, source, start_timestamp)?),
-            None => Err("unable to list on closed album".into()),
-        }
+                recurse, source, start_timestamp)?,
+            None => return Err("unable to list on closed album".into()),
+        };
+
+        crate::slowlog::record("list", &self.id, start, || json!({
+            "end_timestamp": end_timestamp,
+            "geocode": geocode,
+            "max_cloud_coverage": max_cloud_coverage,
+            "min_pixel_coverage": min_pixel_coverage,
+            "platform": platform,
+            "recurse": recurse,
+            "source": source,
+            "start_timestamp": start_timestamp,
+            "results": images.len(),
+        }));
+
+        Ok(images)
+    }
+
+    pub fn list_page(&self, end_timestamp: &Option<i64>,
+            geocode: &Option<String>, max_cloud_coverage: &Option<f64>,
+            min_pixel_coverage: &Option<f64>, platform: &Option<String>,
+            recurse: bool, source: &Option<String>,
+            start_timestamp: &Option<i64>, cursor: &Option<ListCursor>,
+            limit: usize) -> Result<(Vec<(Image, Vec<StFile>)>,
+                Option<ListCursor>), Box<dyn Error>> {
+        let start = Instant::now();
+        let (images, next_cursor) = match &self.index {
+            Some(index) => index.list_page(&self, end_timestamp, geocode,
+                max_cloud_coverage, min_pixel_coverage, platform,
+                recurse, source, start_timestamp, cursor, Some(limit))?,
+            None => return Err("unable to list on closed album".into()),
+        };
+
+        crate::slowlog::record("list_page", &self.id, start, || json!({
+            "end_timestamp": end_timestamp,
+            "geocode": geocode,
+            "max_cloud_coverage": max_cloud_coverage,
+            "min_pixel_coverage": min_pixel_coverage,
+            "platform": platform,
+            "recurse": recurse,
+            "source": source,
+            "start_timestamp": start_timestamp,
+            "cursor": cursor,
+            "results": images.len(),
+        }));
+
+        Ok((images, next_cursor))
     }
 
     pub fn load(&mut self, cloud_coverage: Option<f64>, geocode: &str,
-            pixel_coverage: f64, platform: &str, source: &str,
-            subdataset: u8, tile: &str, timestamp: i64) 
+            misaligned: Option<&str>, pixel_coverage: f64, platform: &str,
+            source: &str, subdataset: u8, tile: &str, timestamp: i64)
             -> Result<(), Box<dyn Error>> {
-        match &mut self.index {
+        match &self.index {
             Some(index) => Ok(index.load(cloud_coverage,
-                geocode, pixel_coverage, platform, source,
+                geocode, misaligned, pixel_coverage, platform, source,
                 subdataset, tile, timestamp)?),
             None => Err("unable to load on closed album".into()),
         }
     }
 
-    pub fn open(&mut self) -> Result<(), Box<dyn Error>> {
-        self.index = Some(AlbumIndex::new()?);
+    pub fn open(&mut self, snapshot: bool) -> Result<(), Box<dyn Error>> {
+        let path = self.get_snapshot_path();
+        match snapshot && path.exists() {
+            true => {
+                // initialize index from existing snapshot
+                info!("loading album index snapshot [id={}]", self.id);
+                self.snapshot_time =
+                    Some(std::fs::metadata(&path)?.modified()?);
+                self.index = Some(Arc::new(AlbumIndex::from_snapshot(&path)?));
+            },
+            false => {
+                self.snapshot_time = None;
+                self.index = Some(Arc::new(AlbumIndex::new()?));
+            },
+        }
+
         Ok(())
     }
 
-    pub fn search(&self, end_timestamp: &Option<i64>,
-            geocode: &Option<String>, max_cloud_coverage: &Option<f64>,
+    pub fn is_frozen(&self) -> bool {
+        self.frozen
+    }
+
+    pub fn is_legal_hold(&self) -> bool {
+        self.legal_hold
+    }
+
+    // requests without a precision use the album target precision
+    pub fn resolve_precision(&self, precision: Option<u32>)
+            -> Result<usize, Box<dyn Error>> {
+        match precision.map(|x| x as usize).or(self.precision) {
+            Some(precision) => Ok(precision),
+            None => Err(format!("album '{}' has no target precision",
+                self.id).into()),
+        }
+    }
+
+    pub fn remove(&mut self, geocode: &str, platform: &str, source: &str,
+            subdataset: u8, tile: &str) -> Result<(), Box<dyn Error>> {
+        self.assert_modifiable()?;
+
+        // remove image and its quicklooks
+        let path = self.get_image_path(false, geocode,
+            platform, source, subdataset, tile)?;
+        if path.exists() {
+            std::fs::remove_file(&path)?;
+        }
+
+        crate::compaction::remove(&path)?;
+        crate::quicklook::remove(&path)?;
+
+        // if album is open -> remove from index
+        match &self.index {
+            Some(index) => index.remove(geocode, source, subdataset, tile),
+            None => Ok(()),
+        }
+    }
+
+    pub fn search(&self, end_timestamp: &Option<i64>, full_geocode: bool,
+            geocode: &Option<String>, interval: &Option<TimeInterval>,
+            max_cloud_coverage: &Option<f64>,
             min_pixel_coverage: &Option<f64>, platform: &Option<String>,
             recurse: bool, source: &Option<String>,
             start_timestamp: &Option<i64>)
             -> Result<Vec<Extent>, Box<dyn Error>> {
+        let start = Instant::now();
+        let extents = match &self.index {
+            Some(index) => index.search(end_timestamp, full_geocode,
+                geocode, interval, max_cloud_coverage, min_pixel_coverage,
+                platform, recurse, source, start_timestamp)?,
+            None => return Err("unable to search on closed album".into()),
+        };
+
+        crate::slowlog::record("search", &self.id, start, || json!({
+            "end_timestamp": end_timestamp,
+            "geocode": geocode,
+            "max_cloud_coverage": max_cloud_coverage,
+            "min_pixel_coverage": min_pixel_coverage,
+            "platform": platform,
+            "recurse": recurse,
+            "source": source,
+            "start_timestamp": start_timestamp,
+            "interval": interval.as_ref().map(|x| format!("{:?}", x)),
+            "results": extents.len(),
+        }));
+
+        Ok(extents)
+    }
+
+    pub fn set_holds(&mut self, frozen: bool, legal_hold: bool)
+            -> Result<(), Box<dyn Error>> {
+        info!("setting album holds [id={}, frozen={}, legal_hold={}]",
+            self.id, frozen, legal_hold);
+        self.frozen = frozen;
+        self.legal_hold = legal_hold;
+        self.write_metadata()
+    }
+
+    pub fn set_retention(&mut self, retention: Retention)
+            -> Result<(), Box<dyn Error>> {
+        info!("setting album retention [id={}, max_bytes={:?}, raw_ttl_days={:?}]",
+            self.id, retention.max_bytes, retention.raw_ttl_days);
+        self.retention = retention;
+        self.write_metadata()
+    }
+
+    pub fn set_statistics(&mut self, geocode: &str, source: &str,
+            tile: &str, subdataset: u8, statistics: &[Statistics])
+            -> Result<(), Box<dyn Error>> {
+        self.assert_modifiable()?;
         match &self.index {
-            Some(index) => Ok(index.search(end_timestamp, geocode,
-                max_cloud_coverage, min_pixel_coverage, platform,
-                recurse, source, start_timestamp)?),
-            None => Err("unable to search on closed album".into()),
+            Some(index) => index.set_statistics(geocode,
+                source, tile, subdataset, statistics),
+            None => Err("unable to set statistics on closed album".into()),
         }
     }
 
-    pub fn write(&mut self, dataset: &mut Dataset, geocode: &str,
+    pub fn snapshot(&self) -> Result<(), Box<dyn Error>> {
+        if let Some((index, path)) = self.get_snapshot() {
+            debug!("writing album index snapshot [id={}]", self.id);
+            index.snapshot(&path)?;
+        }
+
+        Ok(())
+    }
+
+    // index and snapshot path of an open album, so snapshots may be
+    //   written without holding the album
+    pub fn get_snapshot(&self) -> Option<(Arc<AlbumIndex>, PathBuf)> {
+        self.index.as_ref().map(|x| (x.clone(), self.get_snapshot_path()))
+    }
+
+    pub fn misalignment(&self, geocode: &str, source: &str, tile: &str,
+            subdataset: u8) -> Result<Option<String>, Box<dyn Error>> {
+        match &self.index {
+            Some(index) => index.misalignment(geocode,
+                source, tile, subdataset),
+            None => Err("unable to read misalignment on closed album".into()),
+        }
+    }
+
+    pub fn statistics(&self, geocode: &str, source: &str, tile: &str,
+            subdataset: u8) -> Result<Vec<Statistics>, Box<dyn Error>> {
+        match &self.index {
+            Some(index) => index.statistics(geocode,
+                source, tile, subdataset),
+            None => Err("unable to read statistics on closed album".into()),
+        }
+    }
+
+    pub fn update(&mut self, geocode: &str, platform: &str, source: &str,
+            subdataset: u8, tile: &str, cloud_coverage: Option<f64>,
+            timestamp: Option<i64>) -> Result<(), Box<dyn Error>> {
+        self.assert_modifiable()?;
+
+        // rewrite metadata within the image itself so reindexing retains it
+        let path = self.get_image_path(false, geocode,
+            platform, source, subdataset, tile)?;
+        {
+            // packed images are unpacked to be modified
+            crate::compaction::unpack(&path)?;
+
+            // update a copy which replaces the image once closed, so an
+            //   interruption never leaves a partially rewritten image
+            let tmp_path = temporary_path(&path);
+            std::fs::copy(&path, &tmp_path)?;
+            {
+                let mut dataset = open_update(&tmp_path)?;
+                if let Some(cloud_coverage) = cloud_coverage {
+                    dataset.set_metadata_item(
+                        crate::mask::CLOUD_COVERAGE_ITEM,
+                        &cloud_coverage.to_string(), "STIP")?;
+                }
+
+                if let Some(timestamp) = timestamp {
+                    dataset.set_metadata_item("TIMESTAMP",
+                        &timestamp.to_string(), "STIP")?;
+                }
+            }
+
+            sync_path(&tmp_path)?;
+            std::fs::rename(&tmp_path, &path)?;
+            if let Some(directory) = path.parent() {
+                sync_path(directory)?;
+            }
+        }
+
+        // if album is open -> update index
+        match &self.index {
+            Some(index) => index.update(geocode,
+                source, tile, cloud_coverage, timestamp),
+            None => Ok(()),
+        }
+    }
+
+    pub fn clear_staging(&self) -> Result<(), Box<dyn Error>> {
+        let path = self.get_staging_path();
+        if path.exists() {
+            std::fs::remove_dir_all(&path)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn clear_temporary(&self) -> Result<(), Box<dyn Error>> {
+        // temporary files sit beside the album metadata and images
+        let mut count = 0;
+        for glob_expression in &[
+                format!("{}/*.tmp", self.directory.to_string_lossy()),
+                format!("{}/*/*/*/*.tmp", self.directory.to_string_lossy())] {
+            for entry in glob::glob(glob_expression)? {
+                std::fs::remove_file(entry?)?;
+                count += 1;
+            }
+        }
+
+        if count != 0 {
+            info!("removed stale temporary files [album={}, count={}]",
+                self.id, count);
+        }
+
+        Ok(())
+    }
+
+    pub fn commit_staged(&mut self, staged_path: &PathBuf,
+            cloud_coverage: Option<f64>, geocode: &str,
             pixel_coverage: f64, platform: &str, source: &str,
             subdataset: u8, tile: &str, timestamp: i64)
             -> Result<(), Box<dyn Error>> {
+        self.assert_writable()?;
+        let start = Instant::now();
+
         // get image path
         let path = self.get_image_path(true, geocode,
             platform, source, subdataset, tile)?;
 
-        if path.exists() { // attempting to rewrite existing file
+        // attempting to rewrite existing file
+        if crate::compaction::exists(&path) {
+            std::fs::remove_file(staged_path)?;
             return Ok(());
         }
 
-        // open GeoTiff driver
-        let driver = Driver::get("GTiff")?;
+        // staged images record coregistration failures in their metadata
+        let misaligned = match self.index {
+            Some(_) => Dataset::open(staged_path)?
+                .metadata_item("MISALIGNED", "STIP"),
+            None => None,
+        };
 
-        // intialize copy arguments
-        let path_str = path.to_string_lossy().to_string();
-        let c_filename = CString::new(path_str)?;
+        // move staged image into place
+        std::fs::rename(staged_path, &path)?;
+        if let Some(directory) = path.parent() {
+            sync_path(directory)?;
+        }
 
-        let c_compress_str = CString::new("COMPRESS=LZW")?;
-        let c_compress_ptr = c_compress_str.into_raw();
-        let mut c_options = vec![
-            c_compress_ptr,
-            std::ptr::null_mut()
-        ];
+        write_quicklook(&path);
 
-        // copy dataset using driver
-        let c_dataset = unsafe {
-            gdal_sys::GDALCreateCopy(driver.c_driver(),
-                c_filename.as_ptr(), dataset.c_dataset(), 0,
-                c_options.as_mut_ptr(), None, std::ptr::null_mut())
-        };
+        // if album is open -> load data
+        if let Some(_) = self.index {
+            self.load(cloud_coverage, geocode, misaligned.as_deref(),
+                pixel_coverage, platform, source, subdataset, tile,
+                timestamp)?;
+        }
 
-        // check for error
-        if c_dataset.is_null() {
-            let err_msg = unsafe {
-                let c_ptr = gdal_sys::CPLGetLastErrorMsg();
-                let c_str = CStr::from_ptr(c_ptr);
-                c_str.to_string_lossy().into_owned()
-            };
+        crate::metrics::add(Counter::TilesWritten, 1);
+        self.record_written(start, geocode, platform, source,
+            subdataset, tile, timestamp);
+        Ok(())
+    }
+
+    pub fn commit_transaction(&mut self, transaction_id: u64,
+            staged_images: &[StagedImage]) -> Result<(), Box<dyn Error>> {
+        self.assert_writable()?;
+
+        // persist the commit decision so a restart completes the commit
+        //   rather than discarding staged images
+        let record_path = self.get_staging_path()
+            .join(format!("{}.commit", transaction_id));
+        let tmp_path = temporary_path(&record_path);
+        {
+            let mut file = File::create(&tmp_path)?;
+            file.write_u32::<BigEndian>(staged_images.len() as u32)?;
+            for staged_image in staged_images.iter() {
+                staged_image.write(&mut file)?;
+            }
+
+            file.sync_all()?;
+        }
+
+        std::fs::rename(&tmp_path, &record_path)?;
+
+        for staged_image in staged_images.iter() {
+            self.commit_staged(&staged_image.path,
+                staged_image.cloud_coverage, &staged_image.geocode,
+                staged_image.pixel_coverage, &staged_image.platform,
+                &staged_image.source, staged_image.subdataset,
+                &staged_image.tile, staged_image.timestamp)?;
+        }
+
+        std::fs::remove_file(&record_path)?;
+        Ok(())
+    }
+
+    fn recover_commits(&mut self) -> Result<(), Box<dyn Error>> {
+        let glob_expression = format!("{}/*.commit",
+            self.get_staging_path().to_string_lossy());
+        for entry in glob::glob(&glob_expression)? {
+            let record_path = entry?;
+            let mut file = File::open(&record_path)?;
 
-            unsafe { gdal_sys::CPLErrorReset() };
-            return Err(format!(
-                "failed to copy dataset: {}", err_msg).into())
+            // images moved before the restart no longer exist
+            let mut count = 0;
+            for _ in 0..file.read_u32::<BigEndian>()? {
+                let staged_image = StagedImage::read(&mut file)?;
+                if !staged_image.path.exists() {
+                    continue;
+                }
+
+                self.commit_staged(&staged_image.path,
+                    staged_image.cloud_coverage, &staged_image.geocode,
+                    staged_image.pixel_coverage, &staged_image.platform,
+                    &staged_image.source, staged_image.subdataset,
+                    &staged_image.tile, staged_image.timestamp)?;
+                count += 1;
+            }
+
+            std::fs::remove_file(&record_path)?;
+            info!("recovered interrupted commit [album={}, record={:?}, count={}]",
+                self.id, record_path, count);
         }
 
-        let mut dataset_copy = unsafe {
-            Dataset::from_c_dataset(c_dataset)
+        Ok(())
+    }
+
+    fn get_staging_path(&self) -> PathBuf {
+        let mut path = self.directory.clone();
+        path.push(".staging");
+        path
+    }
+
+    pub fn stage(&self, dataset: &mut Dataset, transaction_id: u64,
+            sequence: u32, geocode: &str, pixel_coverage: f64,
+            platform: &str, source: &str, subdataset: u8, tile: &str,
+            timestamp: i64) -> Result<PathBuf, Box<dyn Error>> {
+        self.assert_writable()?;
+        let cloud_coverage = crate::mask::cloud_coverage(dataset);
+
+        // check alignment with other sources
+        let (mut resampled, misaligned) = self.coregister(dataset,
+            geocode, platform, source, timestamp, Some(transaction_id))?;
+        let dataset = match resampled.as_mut() {
+            Some(resampled) => resampled,
+            None => dataset,
         };
 
-        // clean up c memory to mitigate leaks
-        unsafe {
-            let _ = CString::from_raw(c_compress_ptr);
+        // get staged image path
+        let mut path = self.get_staging_path();
+        std::fs::create_dir_all(&path)?;
+        path.push(format!("{}-{}.tif", transaction_id, sequence));
+
+        write_dataset(&path, dataset, cloud_coverage,
+            &self.creation_options, geocode, misaligned, pixel_coverage,
+            platform, source, subdataset, tile, timestamp)?;
+        Ok(path)
+    }
+
+    pub fn write(&mut self, dataset: &mut Dataset, geocode: &str,
+            pixel_coverage: f64, platform: &str, source: &str,
+            subdataset: u8, tile: &str, timestamp: i64)
+            -> Result<(), Box<dyn Error>> {
+        self.assert_writable()?;
+        let start = Instant::now();
+
+        // get image path
+        let path = self.get_image_path(true, geocode,
+            platform, source, subdataset, tile)?;
+
+        // attempting to rewrite existing file
+        if crate::compaction::exists(&path) {
+            return Ok(());
         }
 
-        // set image permissions
-        let mut permissions = std::fs::metadata(&path)?.permissions();
-        permissions.set_mode(0o644);
-        std::fs::set_permissions(&path, permissions)?;
+        // resampling drops dataset metadata
+        let cloud_coverage = crate::mask::cloud_coverage(dataset);
 
-        // set dataset metadata attributes
-        dataset_copy.set_metadata_item("GEOCODE", geocode, "STIP")?;
-        dataset_copy.set_metadata_item("PIXEL_COVERAGE",
-            &pixel_coverage.to_string(), "STIP")?;
-        dataset_copy.set_metadata_item("PLATFORM", platform, "STIP")?;
-        dataset_copy.set_metadata_item("SOURCE", source, "STIP")?;
-        dataset_copy.set_metadata_item("SUBDATASET",
-            &subdataset.to_string(), "STIP")?;
-        dataset_copy.set_metadata_item("TILE", tile, "STIP")?;
-        dataset_copy.set_metadata_item("TIMESTAMP",
-            &timestamp.to_string(), "STIP")?;
+        // check alignment with other sources
+        let (mut resampled, misaligned) = self.coregister(dataset,
+            geocode, platform, source, timestamp, None)?;
+        let dataset = match resampled.as_mut() {
+            Some(resampled) => resampled,
+            None => dataset,
+        };
+
+        let reference = misaligned.as_ref()
+            .map(|x| x.to_string_lossy().to_string());
+        write_dataset(&path, dataset, cloud_coverage,
+            &self.creation_options, geocode, misaligned, pixel_coverage,
+            platform, source, subdataset, tile, timestamp)?;
+        write_quicklook(&path);
 
         // if album is open -> load data
         if let Some(_) = self.index {
-            self.load(None, geocode, pixel_coverage,
-                platform, source, subdataset, tile, timestamp)?;
+            self.load(cloud_coverage, geocode, reference.as_deref(),
+                pixel_coverage, platform, source, subdataset, tile,
+                timestamp)?;
         }
 
+        crate::metrics::add(Counter::TilesWritten, 1);
+        self.record_written(start, geocode, platform, source,
+            subdataset, tile, timestamp);
         Ok(())
     }
+
+    fn record_written(&self, start: Instant, geocode: &str,
+            platform: &str, source: &str, subdataset: u8, tile: &str,
+            timestamp: i64) {
+        crate::slowlog::record("write", &self.id, start, || json!({
+            "geocode": geocode,
+            "platform": platform,
+            "source": source,
+            "subdataset": subdataset,
+            "tile": tile,
+            "timestamp": timestamp,
+        }));
+
+        self.publish_written(geocode, platform, source,
+            subdataset, tile, timestamp);
+    }
+
+    fn publish_written(&self, geocode: &str, platform: &str, source: &str,
+            subdataset: u8, tile: &str, timestamp: i64) {
+        crate::event::publish(EventType::TileWritten, &[
+            ("album", self.id.clone()),
+            ("geocode", geocode.to_string()),
+            ("platform", platform.to_string()),
+            ("source", source.to_string()),
+            ("subdataset", subdataset.to_string()),
+            ("tile", tile.to_string()),
+            ("timestamp", timestamp.to_string()),
+        ]);
+    }
+
+    fn write_metadata(&self) -> Result<(), Box<dyn Error>> {
+        // write to a temporary file which replaces the existing metadata
+        let path = self.get_metadata_path();
+        let tmp_path = temporary_path(&path);
+        let mut file = File::create(&tmp_path)?;
+
+        file.write_i8(self.dht_key_length)?;
+        match self.geocode {
+            Geocode::Geohash => file.write_u8(0)?,
+            Geocode::QuadTile => file.write_u8(1)?,
+            _ => return Err(format!(
+                "unsupported geocode: {:?}", self.geocode).into()),
+        }
+
+        let mut flags = 0;
+        if self.frozen {
+            flags |= FROZEN_FLAG;
+        }
+
+        if self.legal_hold {
+            flags |= LEGAL_HOLD_FLAG;
+        }
+
+        file.write_u8(flags)?;
+
+        // write manifest settings
+        file.write_u8(self.precision.unwrap_or(0) as u8)?;
+        file.write_u8(self.creation_options.len() as u8)?;
+        for option in self.creation_options.iter() {
+            crate::transfer::write_string(option, &mut file)?;
+        }
+
+        file.write_u32::<BigEndian>(self.retention.raw_ttl_days.unwrap_or(0))?;
+        file.write_u64::<BigEndian>(self.retention.max_bytes.unwrap_or(0))?;
+        file.sync_all()?;
+
+        std::fs::rename(&tmp_path, &path)?;
+        Ok(())
+    }
+}
+
+pub fn configure_coregistration(sources: Vec<String>) {
+    *COREGISTRATION_SOURCES.write().unwrap() = sources;
+}
+
+// image paths within a shard, skipping source directories unmodified
+//   since the index snapshot
+pub fn get_shard_paths(shard: &Path, snapshot_time: Option<SystemTime>)
+        -> Result<Vec<PathBuf>, Box<dyn Error>> {
+    let glob_expression = format!("{}/*", shard.to_string_lossy());
+
+    // iterate over existing image directories
+    let mut paths = Vec::new();
+    for entry in glob::glob(&glob_expression)? {
+        let directory = entry?;
+        if !directory.is_dir() {
+            continue;
+        }
+
+        if let Some(snapshot_time) = snapshot_time {
+            let modified = std::fs::metadata(&directory)?.modified()?;
+            if modified < snapshot_time {
+                continue;
+            }
+        }
+
+        // iterate over existing images
+        let image_glob_expression = format!("{}/*tif",
+            directory.to_string_lossy());
+        for entry in glob::glob(&image_glob_expression)? {
+            paths.push(entry?);
+        }
+
+        // include images packed into the directory container
+        paths.extend(crate::compaction::packed_paths(&directory)?);
+    }
+
+    Ok(paths)
+}
+
+pub fn is_aligned(dataset: &Dataset, reference: &Dataset)
+        -> Result<bool, Box<dyn Error>> {
+    if dataset.raster_size() != reference.raster_size() {
+        return Ok(false);
+    }
+
+    let geo_transform = dataset.geo_transform()?;
+    let reference_geo_transform = reference.geo_transform()?;
+    Ok(geo_transform.iter().zip(reference_geo_transform.iter())
+        .all(|(a, b)| (a - b).abs() <= GEO_TRANSFORM_EPSILON))
+}
+
+// images are checked against references when either source is configured
+fn is_coregistered(source: &str, reference_source: &str) -> bool {
+    COREGISTRATION_SOURCES.read().unwrap().iter()
+        .any(|x| x == source || x == reference_source)
+}
+
+pub fn resample(dataset: &Dataset, reference: &Dataset)
+        -> Result<Dataset, Box<dyn Error>> {
+    // create in-memory dataset on the reference grid
+    let driver = Driver::get("MEM")?;
+    let (x, y) = reference.raster_size();
+    let c_filename = CString::new("")?;
+    let c_dataset = unsafe {
+        let band_count = gdal_sys::GDALGetRasterCount(dataset.c_dataset());
+        let band_type = gdal_sys::GDALGetRasterDataType(
+            gdal_sys::GDALGetRasterBand(dataset.c_dataset(), 1));
+
+        gdal_sys::GDALCreate(driver.c_driver(), c_filename.as_ptr(),
+            x as c_int, y as c_int, band_count, band_type,
+            std::ptr::null_mut())
+    };
+
+    if c_dataset.is_null() {
+        return Err(format!("failed to create resample dataset: {}",
+            last_error_msg()).into());
+    }
+
+    let resampled = unsafe { Dataset::from_c_dataset(c_dataset) };
+    resampled.set_geo_transform(&reference.geo_transform()?)?;
+    resampled.set_projection(&reference.projection())?;
+
+    // reproject with nearest neighbor to preserve mask values
+    let c_src_wkt = CString::new(dataset.projection())?;
+    let c_dst_wkt = CString::new(reference.projection())?;
+    let error = unsafe {
+        gdal_sys::GDALReprojectImage(dataset.c_dataset(),
+            c_src_wkt.as_ptr(), resampled.c_dataset(), c_dst_wkt.as_ptr(),
+            gdal_sys::GDALResampleAlg::GRA_NearestNeighbour, 0.0, 0.0,
+            None, std::ptr::null_mut(), std::ptr::null_mut())
+    };
+
+    if error != gdal_sys::CPLErr::CE_None {
+        return Err(format!("failed to resample dataset: {}",
+            last_error_msg()).into());
+    }
+
+    Ok(resampled)
+}
+
+fn last_error_msg() -> String {
+    unsafe {
+        let c_ptr = gdal_sys::CPLGetLastErrorMsg();
+        let err_msg = CStr::from_ptr(c_ptr).to_string_lossy().into_owned();
+        gdal_sys::CPLErrorReset();
+        err_msg
+    }
+}
+
+fn open_update(path: &PathBuf) -> Result<Dataset, Box<dyn Error>> {
+    // open read-write so metadata is written to the file on close
+    let c_filename = CString::new(path.to_string_lossy().to_string())?;
+    let c_dataset = unsafe {
+        gdal_sys::GDALOpen(c_filename.as_ptr(),
+            gdal_sys::GDALAccess::GA_Update)
+    };
+
+    if c_dataset.is_null() {
+        return Err(format!("failed to open {:?} for update: {}",
+            path, last_error_msg()).into());
+    }
+
+    Ok(unsafe { Dataset::from_c_dataset(c_dataset) })
+}
+
+fn write_quicklook(path: &PathBuf) {
+    // quicklooks are a convenience, never fail the write over them
+    if let Err(e) = crate::quicklook::write(path) {
+        warn!("failed to write quicklook for {:?}: {}", path, e);
+    }
+}
+
+// path of the temporary file written beside a destination before it is
+//   renamed into place
+fn temporary_path(path: &Path) -> PathBuf {
+    let mut filename = path.file_name().unwrap_or_default().to_os_string();
+    filename.push(".tmp");
+    path.with_file_name(filename)
+}
+
+fn write_dataset(path: &PathBuf, dataset: &mut Dataset,
+        cloud_coverage: Option<f64>, creation_options: &[String],
+        geocode: &str,
+        misaligned: Option<PathBuf>, pixel_coverage: f64, platform: &str,
+        source: &str, subdataset: u8, tile: &str, timestamp: i64)
+        -> Result<(), Box<dyn Error>> {
+    // copy to a temporary file renamed into place once closed, so an
+    //   interruption never leaves a partial image at the image path
+    let tmp_path = temporary_path(path);
+    let result = copy_dataset(&tmp_path, dataset, cloud_coverage,
+            creation_options, geocode, misaligned, pixel_coverage,
+            platform, source, subdataset, tile, timestamp)
+        .and_then(|_| sync_path(&tmp_path))
+        .and_then(|_| Ok(std::fs::rename(&tmp_path, path)?))
+        .and_then(|_| match path.parent() {
+            Some(directory) => sync_path(directory),
+            None => Ok(()),
+        });
+
+    if result.is_err() {
+        let _ = std::fs::remove_file(&tmp_path);
+    }
+
+    result
+}
+
+// flush a closed file, or the entries of a directory, to disk so a
+//   rename is never persisted ahead of the data it names
+fn sync_path(path: &Path) -> Result<(), Box<dyn Error>> {
+    File::open(path)?.sync_all()?;
+    Ok(())
+}
+
+fn copy_dataset(path: &PathBuf, dataset: &mut Dataset,
+        cloud_coverage: Option<f64>, creation_options: &[String],
+        geocode: &str,
+        misaligned: Option<PathBuf>, pixel_coverage: f64, platform: &str,
+        source: &str, subdataset: u8, tile: &str, timestamp: i64)
+        -> Result<(), Box<dyn Error>> {
+    // open GeoTiff driver
+    let driver = Driver::get("GTiff")?;
+
+    // intialize copy arguments
+    let path_str = path.to_string_lossy().to_string();
+    let c_filename = CString::new(path_str)?;
+
+    // albums without creation options default to lzw compression
+    let options = match creation_options.is_empty() {
+        true => vec!["COMPRESS=LZW".to_string()],
+        false => creation_options.to_vec(),
+    };
+
+    let mut c_option_ptrs = Vec::new();
+    for option in options {
+        c_option_ptrs.push(CString::new(option)?.into_raw());
+    }
+
+    let mut c_options = c_option_ptrs.clone();
+    c_options.push(std::ptr::null_mut());
+
+    // copy dataset using driver
+    let c_dataset = unsafe {
+        gdal_sys::GDALCreateCopy(driver.c_driver(),
+            c_filename.as_ptr(), dataset.c_dataset(), 0,
+            c_options.as_mut_ptr(), None, std::ptr::null_mut())
+    };
+
+    // clean up c memory to mitigate leaks
+    for c_option_ptr in c_option_ptrs {
+        unsafe {
+            let _ = CString::from_raw(c_option_ptr);
+        }
+    }
+
+    // check for error
+    if c_dataset.is_null() {
+        return Err(format!(
+            "failed to copy dataset: {}", last_error_msg()).into())
+    }
+
+    // the copy is closed, flushing it, when dropped on return
+    let mut dataset_copy = unsafe {
+        Dataset::from_c_dataset(c_dataset)
+    };
+
+    // set image permissions
+    let mut permissions = std::fs::metadata(&path)?.permissions();
+    permissions.set_mode(0o644);
+    std::fs::set_permissions(&path, permissions)?;
+
+    // set dataset metadata attributes
+    dataset_copy.set_metadata_item("GEOCODE", geocode, "STIP")?;
+    dataset_copy.set_metadata_item("PIXEL_COVERAGE",
+        &pixel_coverage.to_string(), "STIP")?;
+    dataset_copy.set_metadata_item("PLATFORM", platform, "STIP")?;
+    dataset_copy.set_metadata_item("SOURCE", source, "STIP")?;
+    dataset_copy.set_metadata_item("SUBDATASET",
+        &subdataset.to_string(), "STIP")?;
+    dataset_copy.set_metadata_item("TILE", tile, "STIP")?;
+    dataset_copy.set_metadata_item("TIMESTAMP",
+        &timestamp.to_string(), "STIP")?;
+
+    if let Some(cloud_coverage) = cloud_coverage {
+        dataset_copy.set_metadata_item(crate::mask::CLOUD_COVERAGE_ITEM,
+            &cloud_coverage.to_string(), "STIP")?;
+    }
+
+    // flag images which failed coregistration
+    if let Some(reference_path) = misaligned {
+        dataset_copy.set_metadata_item("MISALIGNED",
+            &reference_path.to_string_lossy(), "STIP")?;
+    }
+
+    Ok(())
 }
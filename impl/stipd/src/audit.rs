@@ -0,0 +1,191 @@
+use serde_json::{json, Value};
+use tonic::Request;
+use tonic::metadata::MetadataMap;
+
+use std::collections::VecDeque;
+use std::error::Error;
+use std::fmt::Debug;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const AUDIT_LOG: &str = "audit.log";
+// request fields holding credentials, never written to the log
+const CREDENTIAL_FIELDS: &[&str] = &["admin_token"];
+const REDACTED: &str = "\"<redacted>\"";
+
+static AUDIT: Mutex<Option<AuditLog>> = Mutex::new(None);
+
+struct AuditLog {
+    file: File,
+    path: PathBuf,
+    sequence: u64,
+}
+
+#[derive(Clone, Debug)]
+pub struct AuditEntry {
+    pub identity: String,
+    pub operation: String,
+    pub parameters: String,
+    pub sequence: u64,
+    pub timestamp: u64,
+}
+
+pub fn configure(directory: &Path) -> Result<(), Box<dyn Error>> {
+    let path = directory.join(AUDIT_LOG);
+
+    // continue the sequence numbers of existing entries
+    let sequence = match path.exists() {
+        true => BufReader::new(File::open(&path)?).lines().count() as u64,
+        false => 0,
+    };
+
+    // entries are only ever appended
+    let file = OpenOptions::new().append(true).create(true).open(&path)?;
+
+    info!("initailizing audit log [path={:?}, entries={}]", path, sequence);
+    *AUDIT.lock().unwrap() = Some(AuditLog {
+        file: file,
+        path: path,
+        sequence: sequence,
+    });
+
+    Ok(())
+}
+
+// retrieve entries matching the filters, limited to the newest entries
+pub fn read(end_timestamp: Option<u64>, identity: &Option<String>,
+        limit: Option<usize>, operation: &Option<String>,
+        start_timestamp: Option<u64>)
+        -> Result<Vec<AuditEntry>, Box<dyn Error>> {
+    let path = match AUDIT.lock().unwrap().as_ref() {
+        Some(audit) => audit.path.clone(),
+        None => return Err("audit log is not configured".into()),
+    };
+
+    let mut entries = VecDeque::new();
+    for line in BufReader::new(File::open(&path)?).lines() {
+        let value: Value = serde_json::from_str(&line?)?;
+        let entry = AuditEntry {
+            identity: value["identity"].as_str()
+                .unwrap_or_default().to_string(),
+            operation: value["operation"].as_str()
+                .unwrap_or_default().to_string(),
+            parameters: value["parameters"].as_str()
+                .unwrap_or_default().to_string(),
+            sequence: value["sequence"].as_u64().unwrap_or(0),
+            timestamp: value["timestamp"].as_u64().unwrap_or(0),
+        };
+
+        if start_timestamp.map(|x| entry.timestamp < x).unwrap_or(false)
+                || end_timestamp.map(|x| entry.timestamp > x)
+                    .unwrap_or(false)
+                || identity.as_ref().map(|x| &entry.identity != x)
+                    .unwrap_or(false)
+                || operation.as_ref().map(|x| &entry.operation != x)
+                    .unwrap_or(false) {
+            continue;
+        }
+
+        entries.push_back(entry);
+        if limit.map(|x| entries.len() > x).unwrap_or(false) {
+            entries.pop_front();
+        }
+    }
+
+    Ok(entries.into_iter().collect())
+}
+
+// record a mutating rpc, named after its request type
+pub fn record<T: Debug>(request: &Request<T>) {
+    let name = std::any::type_name::<T>().rsplit("::").next()
+        .unwrap_or("Unknown");
+    let operation = name.strip_suffix("Request").unwrap_or(name);
+
+    record_operation(request.metadata(), operation,
+        &redact(&format!("{:?}", request.get_ref())));
+}
+
+pub fn record_operation(metadata: &MetadataMap, operation: &str,
+        parameters: &str) {
+    let mut audit = AUDIT.lock().unwrap();
+    let audit = match audit.as_mut() {
+        Some(audit) => audit,
+        None => return,
+    };
+
+    let timestamp = match SystemTime::now().duration_since(UNIX_EPOCH) {
+        Ok(duration) => duration.as_secs(),
+        Err(_) => 0,
+    };
+
+    let entry = json!({
+        "identity": crate::auth::identity(metadata),
+        "operation": operation,
+        "parameters": parameters,
+        "sequence": audit.sequence,
+        "timestamp": timestamp,
+    });
+
+    // flush each entry so recorded operations survive a crash
+    let result = writeln!(audit.file, "{}", entry)
+        .and_then(|_| audit.file.sync_data());
+    match result {
+        Ok(_) => audit.sequence += 1,
+        Err(e) => warn!("failed to write audit log entry '{}': {}",
+            operation, e),
+    }
+}
+
+// replace string values of credential fields in debug formatted requests
+fn redact(parameters: &str) -> String {
+    let mut redacted = parameters.to_string();
+    for field in CREDENTIAL_FIELDS {
+        let pattern = format!("{}: ", field);
+        let mut offset = 0;
+        while let Some(index) = redacted[offset..].find(&pattern) {
+            let start = offset + index + pattern.len();
+            let value = &redacted[start..];
+            let value = value.strip_prefix("Some(").unwrap_or(value);
+            if !value.starts_with('"') {
+                offset = start;
+                continue;
+            }
+
+            // find the closing quote, skipping escaped characters
+            let value_start = redacted.len() - value.len();
+            let mut escaped = false;
+            let mut value_end = redacted.len();
+            for (i, c) in value.char_indices().skip(1) {
+                match (escaped, c) {
+                    (true, _) => escaped = false,
+                    (false, '\\') => escaped = true,
+                    (false, '"') => {
+                        value_end = value_start + i + 1;
+                        break;
+                    },
+                    _ => {},
+                }
+            }
+
+            redacted.replace_range(value_start..value_end, REDACTED);
+            offset = value_start + REDACTED.len();
+        }
+    }
+
+    redacted
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn redact_credentials() {
+        assert_eq!(super::redact(
+                r#"AlbumHoldRequest { id: "a", admin_token: Some("s\"e,c") }"#),
+            r#"AlbumHoldRequest { id: "a", admin_token: Some("<redacted>") }"#);
+        assert_eq!(super::redact(r#"AlbumHoldRequest { admin_token: None }"#),
+            r#"AlbumHoldRequest { admin_token: None }"#);
+    }
+}
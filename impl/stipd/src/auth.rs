@@ -0,0 +1,248 @@
+use subtle::ConstantTimeEq;
+use tonic::{Code, Request, Status};
+use tonic::metadata::MetadataMap;
+use yaml_rust::{Yaml, YamlLoader};
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::{Arc, RwLock};
+
+// album entry granting a permission on every album and cluster operations
+pub const ALL_ALBUMS: &'static str = "*";
+
+static AUTH: RwLock<Option<Arc<Credentials>>> = RwLock::new(None);
+
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
+pub enum Permission {
+    Read,
+    Write,
+    Admin,
+}
+
+impl fmt::Display for Permission {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Permission::Read => write!(f, "read"),
+            Permission::Write => write!(f, "write"),
+            Permission::Admin => write!(f, "admin"),
+        }
+    }
+}
+
+impl FromStr for Permission {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "read" => Ok(Permission::Read),
+            "write" => Ok(Permission::Write),
+            "admin" => Ok(Permission::Admin),
+            x => Err(format!("unknown permission '{}'", x)),
+        }
+    }
+}
+
+struct Credential {
+    albums: HashMap<String, Permission>,
+    name: String,
+}
+
+impl Credential {
+    fn permits(&self, album: &str, permission: Permission) -> bool {
+        self.albums.get(album).or(self.albums.get(ALL_ALBUMS))
+            .map(|x| *x >= permission).unwrap_or(false)
+    }
+}
+
+struct Credentials {
+    cluster_token: String,
+    tokens: HashMap<String, Credential>,
+}
+
+impl Credentials {
+    // compare against every token so lookups do not leak timing
+    fn get(&self, token: &str) -> Option<&Credential> {
+        let mut found = None;
+        for (key, credential) in self.tokens.iter() {
+            if tokens_match(key, token) {
+                found = Some(credential);
+            }
+        }
+
+        found
+    }
+}
+
+pub fn configure(path: &PathBuf) -> Result<(), Box<dyn Error>> {
+    let docs = YamlLoader::load_from_str(&std::fs::read_to_string(path)?)?;
+    let doc = match docs.get(0) {
+        Some(doc) => doc,
+        None => return Err(format!("credentials file {:?} is empty",
+            path).into()),
+    };
+
+    // nodes present the cluster token to each other
+    let cluster_token = match doc["cluster_token"].as_str() {
+        Some(x) if !x.is_empty() => x.to_string(),
+        Some(_) => return Err("cluster token must not be empty".into()),
+        None => return Err("missing string 'cluster_token'".into()),
+    };
+
+    // parse client credentials
+    let mut tokens = HashMap::new();
+    for credential in doc["credentials"].as_vec().unwrap_or(&Vec::new()) {
+        let name = string(credential, "name")?;
+        let token = string(credential, "token")?;
+        if token == cluster_token || tokens.contains_key(token) {
+            return Err(format!("token of credential '{}' is not unique",
+                name).into());
+        }
+
+        let mut albums = HashMap::new();
+        if let Some(hash) = credential["albums"].as_hash() {
+            for (album, permission) in hash.iter() {
+                match (album.as_str(), permission.as_str()) {
+                    (Some(album), Some(permission)) =>
+                        albums.insert(album.to_string(),
+                            permission.parse::<Permission>()?),
+                    _ => return Err(format!(
+                        "invalid albums of credential '{}'", name).into()),
+                };
+            }
+        }
+
+        tokens.insert(token.to_string(), Credential {
+            albums: albums,
+            name: name.to_string(),
+        });
+    }
+
+    info!("initailizing authentication [credentials={}]", tokens.len());
+    *AUTH.write().unwrap() = Some(Arc::new(Credentials {
+        cluster_token: cluster_token,
+        tokens: tokens,
+    }));
+
+    Ok(())
+}
+
+// ensure the request presents any valid token
+pub fn authenticate<T>(request: &Request<T>) -> Result<(), Status> {
+    check(bearer(request.metadata()).as_deref(), None, Permission::Read)
+}
+
+// verify the token a node presents on transfer connections
+pub fn authenticate_node(token: &str) -> Result<(), Box<dyn Error>> {
+    match AUTH.read().unwrap().as_ref() {
+        Some(auth) if !tokens_match(&auth.cluster_token, token) =>
+            Err("invalid cluster token".into()),
+        _ => Ok(()),
+    }
+}
+
+// ensure the request token holds the permission on the album
+pub fn authorize<T>(request: &Request<T>, album: &str,
+        permission: Permission) -> Result<(), Status> {
+    authorize_metadata(request.metadata(), album, permission)
+}
+
+// authorize with metadata of requests consumed before the album is known
+pub fn authorize_metadata(metadata: &MetadataMap, album: &str,
+        permission: Permission) -> Result<(), Status> {
+    check(bearer(metadata).as_deref(), Some(album), permission)
+}
+
+// token nodes attach to requests sent to other nodes
+pub fn cluster_token() -> Option<String> {
+    AUTH.read().unwrap().as_ref().map(|x| x.cluster_token.clone())
+}
+
+pub fn check(token: Option<&str>, album: Option<&str>,
+        permission: Permission) -> Result<(), Status> {
+    let auth = match AUTH.read().unwrap().clone() {
+        Some(auth) => auth,
+        None => return Ok(()),
+    };
+
+    // nodes hold every permission
+    let token = match token {
+        Some(token) if tokens_match(&auth.cluster_token, token) =>
+            return Ok(()),
+        Some(token) => token,
+        None => return Err(Status::new(Code::Unauthenticated,
+            "missing authorization token")),
+    };
+
+    let credential = match auth.get(token) {
+        Some(credential) => credential,
+        None => return Err(Status::new(Code::Unauthenticated,
+            "invalid authorization token")),
+    };
+
+    match album {
+        Some(album) if !credential.permits(album, permission) => {
+            let resource = match album {
+                ALL_ALBUMS => "the cluster".to_string(),
+                album => format!("album '{}'", album),
+            };
+
+            Err(Status::new(Code::PermissionDenied, format!(
+                "credential '{}' lacks {} permission on {}",
+                credential.name, permission, resource)))
+        },
+        _ => Ok(()),
+    }
+}
+
+// name of the credential presenting the request token
+pub fn identity(metadata: &MetadataMap) -> String {
+    let auth = match AUTH.read().unwrap().clone() {
+        Some(auth) => auth,
+        None => return "anonymous".to_string(),
+    };
+
+    match bearer(metadata) {
+        Some(token) if tokens_match(&auth.cluster_token, &token) =>
+            "cluster".to_string(),
+        Some(token) => auth.get(&token)
+            .map(|x| x.name.clone()).unwrap_or("unknown".to_string()),
+        None => "anonymous".to_string(),
+    }
+}
+
+pub fn is_enabled() -> bool {
+    AUTH.read().unwrap().is_some()
+}
+
+// test whether the request token holds the permission on the album
+pub fn permits<T>(request: &Request<T>, album: &str,
+        permission: Permission) -> bool {
+    authorize(request, album, permission).is_ok()
+}
+
+// compare secrets in time independent of where they differ
+pub fn tokens_match(a: &str, b: &str) -> bool {
+    a.as_bytes().ct_eq(b.as_bytes()).into()
+}
+
+// parse a token from an 'authorization: Bearer <token>' header value
+pub fn parse_bearer(value: &str) -> Option<String> {
+    let mut fields = value.trim().splitn(2, ' ');
+    match (fields.next(), fields.next()) {
+        (Some(scheme), Some(token)) if scheme.eq_ignore_ascii_case("bearer")
+            => Some(token.trim().to_string()),
+        _ => None,
+    }
+}
+
+fn bearer(metadata: &MetadataMap) -> Option<String> {
+    metadata.get("authorization")
+        .and_then(|x| x.to_str().ok()).and_then(parse_bearer)
+}
+
+fn string<'a>(yaml: &'a Yaml, key: &str) -> Result<&'a str, Box<dyn Error>> {
+    yaml[key].as_str().ok_or(format!("missing string '{}'", key).into())
+}
@@ -0,0 +1,405 @@
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use swarm::prelude::Dht;
+use twox_hash::XxHash64;
+
+use std::collections::hash_map::DefaultHasher;
+use std::error::Error;
+use std::fs::File;
+use std::hash::Hasher;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::{Arc, RwLock};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicU8, Ordering};
+use std::time::{Duration, Instant};
+
+static HASH_FUNCTION: AtomicU8 =
+    AtomicU8::new(HashFunction::XxHash64 as u8);
+static REPLICATION_FACTOR: AtomicU8 = AtomicU8::new(1);
+static DECOMMISSIONED: RwLock<Vec<u32>> = RwLock::new(Vec::new());
+static DISK_HIGH_WATERMARK: AtomicU8 = AtomicU8::new(95);
+static LEAVING: AtomicBool = AtomicBool::new(false);
+static PLACEMENT_FALLBACK: AtomicBool = AtomicBool::new(false);
+static RING_GENERATION: AtomicU64 = AtomicU64::new(0);
+
+const DECOMMISSIONED_FILE: &'static str = "decommissioned";
+const TOKENS_FILE: &'static str = "tokens";
+
+const DEFAULT_VIRTUAL_NODE_COUNT: u16 = 8;
+
+// bound on waiting for gossip to discover peers through the seed
+const PEER_DISCOVERY_TIMEOUT: Duration = Duration::from_secs(10);
+const PEER_DISCOVERY_POLL: Duration = Duration::from_millis(100);
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
+const XXHASH64_SEED: u64 = 0;
+
+// hash functions mapping dht keys onto the token ring, persisted in
+//   'cluster.meta' so values must never be renumbered. 'Std' is not
+//   stable across rust releases and only retained for clusters
+//   initialized before stable hashing was introduced
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum HashFunction {
+    Std = 0,
+    Fnv1a = 1,
+    XxHash64 = 2,
+}
+
+impl HashFunction {
+    fn from_u8(value: u8) -> Result<HashFunction, Box<dyn Error>> {
+        match value {
+            0 => Ok(HashFunction::Std),
+            1 => Ok(HashFunction::Fnv1a),
+            2 => Ok(HashFunction::XxHash64),
+            _ => Err(format!("unknown hash function {}", value).into()),
+        }
+    }
+}
+
+impl FromStr for HashFunction {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "std" => Ok(HashFunction::Std),
+            "fnv1a" => Ok(HashFunction::Fnv1a),
+            "xxhash64" => Ok(HashFunction::XxHash64),
+            _ => Err(format!("unknown hash function '{}'", s)),
+        }
+    }
+}
+
+pub struct ClusterConfig {
+    pub hash_function: HashFunction,
+    pub replication_factor: u8,
+    pub virtual_node_count: u16,
+}
+
+impl ClusterConfig {
+    pub fn load(directory: &PathBuf, hash_function: Option<HashFunction>,
+            replication_factor: Option<u8>, virtual_node_count: Option<u16>)
+            -> Result<ClusterConfig, Box<dyn Error>> {
+        if replication_factor == Some(0) {
+            return Err("replication factor must be at least 1".into());
+        }
+
+        let mut path = directory.clone();
+        path.push("cluster");
+        path.set_extension("meta");
+
+        if !path.exists() {
+            // storage written before cluster metadata was persisted
+            // was placed using the standard library hasher
+            let default_hash_function = match contains_albums(directory)? {
+                true => {
+                    warn!("existing albums found without cluster metadata, defaulting to unstable 'std' hash function");
+                    HashFunction::Std
+                },
+                false => HashFunction::XxHash64,
+            };
+
+            // initialize cluster configuration
+            let config = ClusterConfig {
+                hash_function: hash_function
+                    .unwrap_or(default_hash_function),
+                replication_factor: replication_factor.unwrap_or(1),
+                virtual_node_count: virtual_node_count.unwrap_or(0),
+            };
+
+            info!("initializing cluster configuration [hash_function={:?}, replication_factor={}, virtual_node_count={}]",
+                config.hash_function, config.replication_factor,
+                config.virtual_node_count);
+
+            // write metadata file
+            let mut file = File::create(&path)?;
+            file.write_u8(config.hash_function as u8)?;
+            file.write_u16::<BigEndian>(config.virtual_node_count)?;
+            file.write_u8(config.replication_factor)?;
+
+            return Ok(config);
+        }
+
+        // parse metadata file
+        let mut file = File::open(&path)?;
+        let hash_function_value = file.read_u8()?;
+        let virtual_node_count_value = file.read_u16::<BigEndian>()?;
+
+        // metadata written before replication defaults to a single copy
+        let config = ClusterConfig {
+            hash_function: HashFunction::from_u8(hash_function_value)?,
+            replication_factor: file.read_u8().unwrap_or(1),
+            virtual_node_count: virtual_node_count_value,
+        };
+
+        if config.hash_function == HashFunction::Std {
+            warn!("cluster uses the 'std' hash function, data placement may change between stipd builds");
+        }
+
+        // validate against provided configuration
+        if let Some(hash_function) = hash_function {
+            if hash_function != config.hash_function {
+                return Err(format!("hash function {:?} conflicts with persisted cluster configuration {:?}",
+                    hash_function, config.hash_function).into());
+            }
+        }
+
+        if let Some(replication_factor) = replication_factor {
+            if replication_factor != config.replication_factor {
+                return Err(format!("replication factor {} conflicts with persisted cluster configuration {}",
+                    replication_factor, config.replication_factor).into());
+            }
+        }
+
+        if let Some(virtual_node_count) = virtual_node_count {
+            if virtual_node_count != config.virtual_node_count {
+                return Err(format!("virtual node count {} conflicts with persisted cluster configuration {}",
+                    virtual_node_count, config.virtual_node_count).into());
+            }
+        }
+
+        Ok(config)
+    }
+
+    fn generate_tokens(&self, node_id: u32) -> Vec<u64> {
+        (0..self.virtual_node_count).map(|i| {
+            hash_with(self.hash_function,
+                format!("{}-{}", node_id, i).as_bytes())
+        }).collect()
+    }
+
+    pub fn load_tokens(&self, directory: &PathBuf, node_id: u32,
+            tokens: &[u64]) -> Result<Vec<u64>, Box<dyn Error>> {
+        let path = directory.join(TOKENS_FILE);
+        let persisted = match path.exists() {
+            true => Some(read_tokens(&path)?),
+            false => None,
+        };
+
+        let tokens = match (tokens.len(), persisted) {
+            (0, Some(persisted)) => return Ok(persisted),
+            (0, None) => {
+                if self.virtual_node_count != 0
+                        && contains_albums(directory)? {
+                    // storage placed before tokens were persisted used
+                    // tokens derived from the node id
+                    self.generate_tokens(node_id)
+                } else {
+                    // assign random ring positions for each virtual node
+                    let count = match self.virtual_node_count {
+                        0 => DEFAULT_VIRTUAL_NODE_COUNT,
+                        x => x,
+                    };
+
+                    (0..count).map(|_| rand::random::<u64>()).collect()
+                }
+            },
+            (_, persisted) => {
+                if persisted.is_some()
+                        && persisted.as_deref() != Some(tokens) {
+                    warn!("provided tokens replace persisted tokens, data placement will change");
+                }
+
+                tokens.to_vec()
+            },
+        };
+
+        info!("persisting {} dht token(s)", tokens.len());
+        let buf: Vec<String> = tokens.iter().map(|x| x.to_string()).collect();
+        std::fs::write(&path, buf.join("\n"))?;
+        Ok(tokens)
+    }
+
+    pub fn install(&self) {
+        HASH_FUNCTION.store(self.hash_function as u8, Ordering::SeqCst);
+        REPLICATION_FACTOR.store(self.replication_factor, Ordering::SeqCst);
+    }
+}
+
+pub fn assert_disk_available(directory: &PathBuf)
+        -> Result<(), Box<dyn Error>> {
+    let (free_bytes, total_bytes) = crate::admin::disk_usage(directory)?;
+    if total_bytes == 0 {
+        return Ok(());
+    }
+
+    // reject writes once usage reaches the high watermark
+    let usage = 100.0 * (total_bytes - free_bytes) as f64
+        / total_bytes as f64;
+    let watermark = DISK_HIGH_WATERMARK.load(Ordering::SeqCst);
+    match usage >= watermark as f64 {
+        true => Err(format!("disk usage {:.1}% of {:?} exceeds high watermark {}%",
+            usage, directory, watermark).into()),
+        false => Ok(()),
+    }
+}
+
+pub fn configure_placement(disk_high_watermark: u8, fallback: bool)
+        -> Result<(), Box<dyn Error>> {
+    if disk_high_watermark == 0 || disk_high_watermark > 100 {
+        return Err(format!("disk high watermark {} must be within 1-100",
+            disk_high_watermark).into());
+    }
+
+    DISK_HIGH_WATERMARK.store(disk_high_watermark, Ordering::SeqCst);
+    PLACEMENT_FALLBACK.store(fallback, Ordering::SeqCst);
+    Ok(())
+}
+
+pub fn decommission(directory: &PathBuf, node_id: u32, local: bool)
+        -> Result<(), Box<dyn Error>> {
+    {
+        let mut decommissioned = DECOMMISSIONED.write().unwrap();
+        if !decommissioned.contains(&node_id) {
+            decommissioned.push(node_id);
+            ring_changed();
+
+            // persist decommissioned node ids
+            let buf: Vec<String> = decommissioned.iter()
+                .map(|x| x.to_string()).collect();
+            std::fs::write(directory.join(DECOMMISSIONED_FILE),
+                buf.join("\n"))?;
+        }
+    }
+
+    if local {
+        LEAVING.store(true, Ordering::SeqCst);
+    }
+
+    Ok(())
+}
+
+pub fn is_decommissioned(node_id: u32) -> bool {
+    DECOMMISSIONED.read().unwrap().contains(&node_id)
+}
+
+pub fn is_leaving() -> bool {
+    LEAVING.load(Ordering::SeqCst)
+}
+
+pub fn load_decommissioned(directory: &PathBuf, node_id: u32)
+        -> Result<(), Box<dyn Error>> {
+    let path = directory.join(DECOMMISSIONED_FILE);
+    if !path.exists() {
+        return Ok(());
+    }
+
+    let mut decommissioned = DECOMMISSIONED.write().unwrap();
+    for line in std::fs::read_to_string(&path)?.lines() {
+        if !line.trim().is_empty() {
+            decommissioned.push(line.trim().parse::<u32>()?);
+        }
+    }
+
+    if decommissioned.contains(&node_id) {
+        warn!("node {} is decommissioned, rejecting writes", node_id);
+        LEAVING.store(true, Ordering::SeqCst);
+    }
+
+    Ok(())
+}
+
+pub fn hash(key: &[u8]) -> u64 {
+    let hash_function =
+        match HashFunction::from_u8(HASH_FUNCTION.load(Ordering::SeqCst)) {
+            Ok(hash_function) => hash_function,
+            Err(_) => HashFunction::XxHash64,
+        };
+
+    hash_with(hash_function, key)
+}
+
+// compare the hash function advertised by each peer, nodes placing data
+//   with different functions disagree on every replica set
+pub fn verify_hash_function(dht: &Arc<Dht>, node_id: u32,
+        hash_function: HashFunction) -> Result<(), Box<dyn Error>> {
+    let start = Instant::now();
+    while !dht.nodes().iter().any(|x| x.get_id() != node_id)
+            && start.elapsed() < PEER_DISCOVERY_TIMEOUT {
+        std::thread::sleep(PEER_DISCOVERY_POLL);
+    }
+
+    let expected = format!("{:?}", hash_function);
+    let mut conflicts = Vec::new();
+    for node in dht.nodes().iter().filter(|x| x.get_id() != node_id) {
+        match node.get_metadata("hash_function") {
+            Some(value) if value.to_string() != expected => conflicts
+                .push(format!("node {} uses {}", node.get_id(), value)),
+            Some(_) => {},
+            None => warn!("node {} does not advertise a hash function, unable to verify data placement",
+                node.get_id()),
+        }
+    }
+
+    match conflicts.is_empty() {
+        true => Ok(()),
+        false => Err(format!("hash function {:?} conflicts with peers: {}",
+            hash_function, conflicts.join(", ")).into()),
+    }
+}
+
+pub fn placement_fallback() -> bool {
+    PLACEMENT_FALLBACK.load(Ordering::SeqCst)
+}
+
+pub fn replication_factor() -> usize {
+    REPLICATION_FACTOR.load(Ordering::SeqCst) as usize
+}
+
+// incremented whenever replica placement may have changed, so cached
+//   dht resolutions are discarded
+pub fn ring_changed() {
+    RING_GENERATION.fetch_add(1, Ordering::SeqCst);
+}
+
+pub fn ring_generation() -> u64 {
+    RING_GENERATION.load(Ordering::SeqCst)
+}
+
+fn hash_with(hash_function: HashFunction, key: &[u8]) -> u64 {
+    match hash_function {
+        HashFunction::Std => {
+            let mut hasher = DefaultHasher::new();
+            hasher.write(key);
+            hasher.finish()
+        },
+        HashFunction::Fnv1a => {
+            let mut hash = FNV_OFFSET_BASIS;
+            for byte in key {
+                hash ^= *byte as u64;
+                hash = hash.wrapping_mul(FNV_PRIME);
+            }
+
+            hash
+        },
+        HashFunction::XxHash64 => {
+            let mut hasher = XxHash64::with_seed(XXHASH64_SEED);
+            hasher.write(key);
+            hasher.finish()
+        },
+    }
+}
+
+fn read_tokens(path: &PathBuf) -> Result<Vec<u64>, Box<dyn Error>> {
+    let mut tokens = Vec::new();
+    for line in std::fs::read_to_string(path)?.lines() {
+        if !line.trim().is_empty() {
+            tokens.push(line.trim().parse::<u64>()?);
+        }
+    }
+
+    Ok(tokens)
+}
+
+fn contains_albums(directory: &PathBuf) -> Result<bool, Box<dyn Error>> {
+    for entry in std::fs::read_dir(directory)? {
+        let mut path = entry?.path();
+        path.push("album");
+        path.set_extension("meta");
+
+        if path.exists() {
+            return Ok(true);
+        }
+    }
+
+    Ok(false)
+}
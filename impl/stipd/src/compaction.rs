@@ -0,0 +1,477 @@
+use gdal::Dataset;
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+// tiles of a source directory are packed into a container file, located
+//   by an index of 'filename offset length' lines following a header
+//   naming the current container
+const INDEX_FILE: &str = "tiles.idx";
+
+// containers are rewritten once removed tiles outweigh packed tiles
+const REWRITE_DEAD_RATIO: f64 = 1.0;
+
+static INDICES: Mutex<Option<HashMap<PathBuf, Arc<ContainerIndex>>>> =
+    Mutex::new(None);
+
+// serializes modifications of container indices
+static LOCK: Mutex<()> = Mutex::new(());
+
+#[derive(Clone, Default)]
+struct ContainerIndex {
+    container: Option<String>,
+    dead_bytes: u64,
+    entries: HashMap<String, (u64, u64)>,
+}
+
+impl ContainerIndex {
+    fn live_bytes(&self) -> u64 {
+        self.entries.values().map(|(_, len)| len).sum()
+    }
+}
+
+// location of a packed tile as (container, offset, length)
+fn locate(path: &Path) -> Option<(PathBuf, u64, u64)> {
+    let (directory, filename) = split_path(path)?;
+    let index = match load_index(directory) {
+        Ok(index) => index,
+        Err(e) => {
+            warn!("failed to read container index in {:?}: {}",
+                directory, e);
+            return None;
+        },
+    };
+
+    let (offset, len) = index.entries.get(filename)?;
+    let container = directory.join(index.container.as_ref()?);
+    Some((container, *offset, *len))
+}
+
+pub fn exists(path: &Path) -> bool {
+    path.exists() || locate(path).is_some()
+}
+
+pub fn len(path: &Path) -> Result<u64, Box<dyn Error>> {
+    if path.exists() {
+        return Ok(std::fs::metadata(path)?.len());
+    }
+
+    match locate(path) {
+        Some((_, _, len)) => Ok(len),
+        None => Err(format!("path '{}' does not exist",
+            path.to_string_lossy()).into()),
+    }
+}
+
+// open a tile whether it is stored loose or packed in a container
+pub fn open(path: &Path) -> Result<Dataset, Box<dyn Error>> {
+    if path.exists() {
+        return Ok(Dataset::open(path)?);
+    }
+
+    match locate(path) {
+        Some((container, offset, len)) => {
+            let subfile = format!("/vsisubfile/{}_{},{}",
+                offset, len, container.to_string_lossy());
+            Ok(Dataset::open(Path::new(&subfile))?)
+        },
+        None => Err(format!("path '{}' does not exist",
+            path.to_string_lossy()).into()),
+    }
+}
+
+// the encoded bytes of a tile and their length
+pub fn reader(path: &Path)
+        -> Result<(u64, Box<dyn Read + Send>), Box<dyn Error>> {
+    if path.exists() {
+        let file = File::open(path)?;
+        return Ok((file.metadata()?.len(), Box::new(file)));
+    }
+
+    match locate(path) {
+        Some((container, offset, len)) => {
+            let mut file = File::open(&container)?;
+            file.seek(SeekFrom::Start(offset))?;
+            Ok((len, Box::new(file.take(len))))
+        },
+        None => Err(format!("path '{}' does not exist",
+            path.to_string_lossy()).into()),
+    }
+}
+
+// logical paths of the tiles packed within a directory
+pub fn packed_paths(directory: &Path)
+        -> Result<Vec<PathBuf>, Box<dyn Error>> {
+    let index = load_index(directory)?;
+    Ok(index.entries.keys().map(|x| directory.join(x)).collect())
+}
+
+// drop a packed tile, its bytes are reclaimed by a later compaction
+pub fn remove(path: &Path) -> Result<(), Box<dyn Error>> {
+    let (directory, filename) = match split_path(path) {
+        Some(split) => split,
+        None => return Ok(()),
+    };
+
+    let _lock = LOCK.lock().unwrap();
+    let mut index = (*load_index(directory)?).clone();
+    if let Some((_, len)) = index.entries.remove(filename) {
+        index.dead_bytes += len;
+        write_index(directory, index)?;
+    }
+
+    Ok(())
+}
+
+// restore a packed tile to a loose file so it may be modified in place
+pub fn unpack(path: &Path) -> Result<(), Box<dyn Error>> {
+    let (directory, filename) = match split_path(path) {
+        Some(split) => split,
+        None => return Ok(()),
+    };
+
+    let _lock = LOCK.lock().unwrap();
+    let mut index = (*load_index(directory)?).clone();
+    let (offset, len) = match index.entries.get(filename) {
+        Some(entry) => *entry,
+        None => return Ok(()),
+    };
+
+    // copy tile bytes beside the tile before renaming into place
+    let container = directory.join(index.container.as_ref()
+        .ok_or("container index names no container")?);
+    let mut file = File::open(&container)?;
+    file.seek(SeekFrom::Start(offset))?;
+
+    let tmp_path = path.with_extension("tif.tmp");
+    {
+        let mut tmp_file = File::create(&tmp_path)?;
+        std::io::copy(&mut file.take(len), &mut tmp_file)?;
+        tmp_file.sync_all()?;
+    }
+
+    std::fs::rename(&tmp_path, path)?;
+
+    index.entries.remove(filename);
+    index.dead_bytes += len;
+    write_index(directory, index)
+}
+
+// pack up to limit loose tiles unmodified for at least min_age into the
+//   directory container, returning the number of tiles and bytes packed
+pub fn compact(directory: &Path, min_age: Duration, limit: usize)
+        -> Result<(usize, u64), Box<dyn Error>> {
+    let _lock = LOCK.lock().unwrap();
+    let mut index = (*load_index(directory)?).clone();
+
+    // identify cold loose tiles
+    let now = SystemTime::now();
+    let mut tiles = Vec::new();
+    for entry in std::fs::read_dir(directory)? {
+        let path = entry?.path();
+        let filename = match path.file_name().and_then(|x| x.to_str()) {
+            Some(filename) if filename.ends_with(".tif") =>
+                filename.to_string(),
+            _ => continue,
+        };
+
+        let modified = std::fs::metadata(&path)?.modified()?;
+        if now.duration_since(modified).unwrap_or_default() >= min_age {
+            tiles.push((filename, path));
+        }
+
+        if tiles.len() >= limit {
+            break;
+        }
+    }
+
+    // rewrite containers holding mostly removed tiles
+    let rewrite = index.dead_bytes as f64
+        > index.live_bytes() as f64 * REWRITE_DEAD_RATIO;
+    if tiles.is_empty() && !rewrite {
+        return Ok((0, 0));
+    }
+
+    let previous = index.container.clone();
+    let container = match (&index.container, rewrite) {
+        (Some(container), false) => container.clone(),
+        _ => format!("tiles-{}.pack", rand::random::<u32>()),
+    };
+
+    let container_path = directory.join(&container);
+    let mut file = OpenOptions::new().append(true)
+        .create(true).open(&container_path)?;
+    let mut offset = file.metadata()?.len();
+
+    // copy live packed tiles into the new container
+    if rewrite && previous.is_some() {
+        let previous_path = directory.join(previous.as_ref().unwrap());
+        let mut previous_file = File::open(&previous_path)?;
+        let mut entries: Vec<(String, (u64, u64))> =
+            index.entries.drain().collect();
+        entries.sort_by_key(|(_, (offset, _))| *offset);
+
+        for (filename, (previous_offset, len)) in entries {
+            previous_file.seek(SeekFrom::Start(previous_offset))?;
+            std::io::copy(&mut (&mut previous_file).take(len), &mut file)?;
+            index.entries.insert(filename, (offset, len));
+            offset += len;
+        }
+
+        index.dead_bytes = 0;
+    }
+
+    // append cold loose tiles
+    let mut bytes = 0;
+    for (filename, path) in tiles.iter() {
+        let len = std::io::copy(&mut File::open(path)?, &mut file)?;
+
+        // loose tiles replace packed tiles of the same name
+        if let Some((_, replaced_len)) =
+                index.entries.insert(filename.clone(), (offset, len)) {
+            index.dead_bytes += replaced_len;
+        }
+
+        offset += len;
+        bytes += len;
+    }
+
+    file.sync_all()?;
+
+    // publish the index before removing the loose tiles it replaces
+    index.container = Some(container.clone());
+    write_index(directory, index)?;
+
+    for (_, path) in tiles.iter() {
+        std::fs::remove_file(path)?;
+    }
+
+    if let Some(previous) = previous.filter(|x| x != &container) {
+        std::fs::remove_file(directory.join(previous))?;
+    }
+
+    Ok((tiles.len(), bytes))
+}
+
+// drop cached indices of containers beneath a removed directory
+pub fn evict(directory: &Path) {
+    if let Some(indices) = INDICES.lock().unwrap().as_mut() {
+        indices.retain(|path, _| !path.starts_with(directory));
+    }
+}
+
+fn load_index(directory: &Path)
+        -> Result<Arc<ContainerIndex>, Box<dyn Error>> {
+    if let Some(index) = INDICES.lock().unwrap().as_ref()
+            .and_then(|x| x.get(directory)) {
+        return Ok(index.clone());
+    }
+
+    let path = directory.join(INDEX_FILE);
+    let mut index = ContainerIndex::default();
+    if path.exists() {
+        for (i, line) in BufReader::new(File::open(&path)?)
+                .lines().enumerate() {
+            let line = line?;
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            match (i, fields.as_slice()) {
+                (0, [container, dead_bytes]) => {
+                    index.container = Some(container.to_string());
+                    index.dead_bytes = dead_bytes.parse::<u64>()?;
+                },
+                (_, [filename, offset, len]) => {
+                    index.entries.insert(filename.to_string(),
+                        (offset.parse::<u64>()?, len.parse::<u64>()?));
+                },
+                _ => return Err(format!("invalid container index line '{}'",
+                    line).into()),
+            }
+        }
+    }
+
+    let index = Arc::new(index);
+    INDICES.lock().unwrap().get_or_insert_with(HashMap::new)
+        .insert(directory.to_path_buf(), index.clone());
+    Ok(index)
+}
+
+fn write_index(directory: &Path, index: ContainerIndex)
+        -> Result<(), Box<dyn Error>> {
+    // write index to a temporary file and rename over the existing
+    let path = directory.join(INDEX_FILE);
+    let tmp_path = path.with_extension("tmp");
+    {
+        let mut file = File::create(&tmp_path)?;
+        writeln!(file, "{} {}", index.container.as_ref()
+            .map(|x| x.as_str()).unwrap_or("-"), index.dead_bytes)?;
+        for (filename, (offset, len)) in index.entries.iter() {
+            writeln!(file, "{} {} {}", filename, offset, len)?;
+        }
+
+        file.sync_all()?;
+    }
+
+    std::fs::rename(&tmp_path, &path)?;
+    INDICES.lock().unwrap().get_or_insert_with(HashMap::new)
+        .insert(directory.to_path_buf(), Arc::new(index));
+    Ok(())
+}
+
+fn split_path(path: &Path) -> Option<(&Path, &str)> {
+    Some((path.parent()?, path.file_name()?.to_str()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use gdal::{Dataset, Driver};
+    use gdal::spatial_ref::SpatialRef;
+
+    use std::ffi::CString;
+    use std::io::Read;
+    use std::path::{Path, PathBuf};
+    use std::time::Duration;
+
+    fn create_directory() -> PathBuf {
+        let directory = std::env::temp_dir().join(
+            format!("stip-compaction-{}", rand::random::<u64>()));
+        std::fs::create_dir_all(&directory).unwrap();
+        directory
+    }
+
+    // 4x4 pixel GeoTIFF with every pixel set to value
+    fn create_tile(path: &Path, value: f64) {
+        let driver = Driver::get("MEM").unwrap();
+        let c_filename = CString::new("").unwrap();
+        let c_dataset = unsafe {
+            gdal_sys::GDALCreate(driver.c_driver(), c_filename.as_ptr(),
+                4, 4, 1, gdal_sys::GDALDataType::GDT_Byte,
+                std::ptr::null_mut())
+        };
+
+        let dataset = unsafe { Dataset::from_c_dataset(c_dataset) };
+        dataset.set_geo_transform(
+            &[-105.0, 0.01, 0.0, 40.0, 0.0, -0.01]).unwrap();
+        dataset.set_projection(
+            &SpatialRef::from_epsg(4326).unwrap().to_wkt().unwrap()).unwrap();
+        crate::raster::write_band(&dataset, 1, &mut vec![value; 16]).unwrap();
+        crate::raster::write_geotiff(&dataset, path).unwrap();
+    }
+
+    fn read_value(path: &Path) -> f64 {
+        let dataset = super::open(path).unwrap();
+        crate::raster::read_band(&dataset, 1).unwrap().values[0]
+    }
+
+    #[test]
+    fn compact_open() {
+        let directory = create_directory();
+        let (a, b) = (directory.join("a.tif"), directory.join("b.tif"));
+        create_tile(&a, 1.0);
+        create_tile(&b, 2.0);
+        let a_bytes = std::fs::read(&a).unwrap();
+
+        let (count, bytes) = super::compact(&directory,
+            Duration::from_secs(0), 16).unwrap();
+        assert_eq!(count, 2);
+        assert_eq!(bytes, a_bytes.len() as u64
+            + super::len(&b).unwrap());
+
+        // packed tiles read as if they were loose
+        assert!(!a.exists() && super::exists(&a));
+        assert_eq!(read_value(&a), 1.0);
+        assert_eq!(read_value(&b), 2.0);
+        assert_eq!(super::packed_paths(&directory).unwrap().len(), 2);
+
+        let (len, mut reader) = super::reader(&a).unwrap();
+        let mut buf = Vec::new();
+        reader.read_to_end(&mut buf).unwrap();
+        assert_eq!(len, a_bytes.len() as u64);
+        assert_eq!(buf, a_bytes);
+
+        // tiles younger than min_age remain loose
+        let c = directory.join("c.tif");
+        create_tile(&c, 3.0);
+        assert_eq!(super::compact(&directory,
+            Duration::from_secs(3600), 16).unwrap(), (0, 0));
+        assert!(c.exists());
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn compact_limit() {
+        let directory = create_directory();
+        for i in 0..5 {
+            create_tile(&directory.join(format!("{}.tif", i)), 1.0);
+        }
+
+        // batches pack at most limit tiles
+        let zero = Duration::from_secs(0);
+        assert_eq!(super::compact(&directory, zero, 2).unwrap().0, 2);
+        assert_eq!(super::compact(&directory, zero, 2).unwrap().0, 2);
+        assert_eq!(super::compact(&directory, zero, 2).unwrap().0, 1);
+        assert_eq!(super::packed_paths(&directory).unwrap().len(), 5);
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn remove_unpack() {
+        let directory = create_directory();
+        let (a, b) = (directory.join("a.tif"), directory.join("b.tif"));
+        create_tile(&a, 1.0);
+        create_tile(&b, 2.0);
+        super::compact(&directory, Duration::from_secs(0), 16).unwrap();
+
+        // removed tiles are counted as dead bytes
+        let a_len = super::len(&a).unwrap();
+        super::remove(&a).unwrap();
+        assert!(!super::exists(&a));
+        let index = super::load_index(&directory).unwrap();
+        assert_eq!(index.dead_bytes, a_len);
+
+        // unpacked tiles are restored as loose files
+        let b_len = super::len(&b).unwrap();
+        super::unpack(&b).unwrap();
+        assert!(b.exists());
+        assert_eq!(std::fs::metadata(&b).unwrap().len(), b_len);
+        assert_eq!(read_value(&b), 2.0);
+        let index = super::load_index(&directory).unwrap();
+        assert_eq!(index.dead_bytes, a_len + b_len);
+        assert!(index.entries.is_empty());
+
+        // containers holding only dead bytes are rewritten
+        let container = index.container.clone().unwrap();
+        super::compact(&directory, Duration::from_secs(0), 16).unwrap();
+        let index = super::load_index(&directory).unwrap();
+        assert_eq!(index.dead_bytes, 0);
+        assert_ne!(index.container.as_ref(), Some(&container));
+        assert!(!directory.join(&container).exists());
+        assert_eq!(read_value(&b), 2.0);
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn compact_replaced() {
+        let directory = create_directory();
+        let a = directory.join("a.tif");
+        create_tile(&a, 1.0);
+        super::compact(&directory, Duration::from_secs(0), 16).unwrap();
+        let a_len = super::len(&a).unwrap();
+
+        // loose tiles written over packed tiles count the packed as dead
+        create_tile(&a, 2.0);
+        super::compact(&directory, Duration::from_secs(0), 16).unwrap();
+        let index = super::load_index(&directory).unwrap();
+        assert_eq!(index.dead_bytes, a_len);
+        assert_eq!(read_value(&a), 2.0);
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
@@ -0,0 +1,147 @@
+use geocode::Geocode;
+use yaml_rust::{Yaml, YamlLoader};
+
+use crate::cluster::HashFunction;
+
+use std::error::Error;
+use std::net::IpAddr;
+use std::path::PathBuf;
+
+pub struct AlbumDefinition {
+    pub creation_options: Vec<String>,
+    pub dht_key_length: i8,
+    pub geocode: Geocode,
+    pub id: String,
+    pub precision: Option<usize>,
+}
+
+pub struct NodeDefinition {
+    pub directory: Option<PathBuf>,
+    pub gossip_port: u16,
+    pub id: u32,
+    pub ip_address: IpAddr,
+    pub rpc_port: u16,
+    pub tokens: Vec<u64>,
+    pub xfer_port: u16,
+}
+
+pub struct ClusterDefinition {
+    pub albums: Vec<AlbumDefinition>,
+    pub hash_function: Option<HashFunction>,
+    pub nodes: Vec<NodeDefinition>,
+    pub replication_factor: Option<u8>,
+    pub virtual_node_count: Option<u16>,
+}
+
+impl ClusterDefinition {
+    pub fn load(path: &PathBuf) -> Result<ClusterDefinition, Box<dyn Error>> {
+        let docs = YamlLoader::load_from_str(
+            &std::fs::read_to_string(path)?)?;
+        let doc = match docs.get(0) {
+            Some(doc) => doc,
+            None => return Err(format!("cluster config {:?} is empty",
+                path).into()),
+        };
+
+        // parse cluster-wide settings
+        let hash_function = match doc["hash_function"].as_str() {
+            Some(x) => Some(x.parse::<HashFunction>()?),
+            None => None,
+        };
+
+        let replication_factor = doc["replication_factor"].as_i64()
+            .map(|x| x as u8);
+        let virtual_node_count = doc["virtual_nodes"].as_i64()
+            .map(|x| x as u16);
+
+        // parse node definitions
+        let mut nodes = Vec::new();
+        for node in doc["nodes"].as_vec().unwrap_or(&Vec::new()) {
+            let id = integer(node, "id")? as u32;
+            if nodes.iter().any(|x: &NodeDefinition| x.id == id) {
+                return Err(format!("node {} is defined twice", id).into());
+            }
+
+            let mut tokens = Vec::new();
+            for token in node["tokens"].as_vec().unwrap_or(&Vec::new()) {
+                tokens.push(match token {
+                    Yaml::Integer(x) if *x >= 0 => *x as u64,
+                    // integers beyond i64 are parsed as reals
+                    Yaml::Real(x) | Yaml::String(x) => x.parse::<u64>()?,
+                    _ => return Err(format!("invalid token for node {}",
+                        id).into()),
+                });
+            }
+
+            nodes.push(NodeDefinition {
+                directory: node["directory"].as_str().map(PathBuf::from),
+                gossip_port: integer(node, "gossip_port")? as u16,
+                id: id,
+                ip_address: string(node, "ip_address")?.parse::<IpAddr>()?,
+                rpc_port: integer(node, "rpc_port")? as u16,
+                tokens: tokens,
+                xfer_port: integer(node, "xfer_port")? as u16,
+            });
+        }
+
+        // parse album definitions
+        let mut albums = Vec::new();
+        for album in doc["albums"].as_vec().unwrap_or(&Vec::new()) {
+            let geocode = match string(album, "geocode")? {
+                "geohash" => Geocode::Geohash,
+                "quadtile" => Geocode::QuadTile,
+                x => return Err(format!("unsupported geocode '{}'",
+                    x).into()),
+            };
+
+            let mut creation_options = Vec::new();
+            for option in album["creation_options"].as_vec()
+                    .unwrap_or(&Vec::new()) {
+                match option.as_str() {
+                    Some(x) => creation_options.push(x.to_string()),
+                    None => return Err(format!(
+                        "invalid creation option for album '{}'",
+                        string(album, "id")?).into()),
+                }
+            }
+
+            albums.push(AlbumDefinition {
+                creation_options: creation_options,
+                dht_key_length: album["dht_key_length"].as_i64()
+                    .unwrap_or(0) as i8,
+                geocode: geocode,
+                id: string(album, "id")?.to_string(),
+                precision: album["precision"].as_i64().map(|x| x as usize),
+            });
+        }
+
+        Ok(ClusterDefinition {
+            albums: albums,
+            hash_function: hash_function,
+            nodes: nodes,
+            replication_factor: replication_factor,
+            virtual_node_count: virtual_node_count,
+        })
+    }
+
+    pub fn get_node(&self, id: u32) -> Option<&NodeDefinition> {
+        self.nodes.iter().find(|x| x.id == id)
+    }
+
+    // nodes join through the first defined node
+    pub fn seed(&self, id: u32) -> Option<(IpAddr, u16)> {
+        match self.nodes.first() {
+            Some(node) if node.id != id =>
+                Some((node.ip_address, node.gossip_port)),
+            _ => None,
+        }
+    }
+}
+
+fn integer(yaml: &Yaml, key: &str) -> Result<i64, Box<dyn Error>> {
+    yaml[key].as_i64().ok_or(format!("missing integer '{}'", key).into())
+}
+
+fn string<'a>(yaml: &'a Yaml, key: &str) -> Result<&'a str, Box<dyn Error>> {
+    yaml[key].as_str().ok_or(format!("missing string '{}'", key).into())
+}
@@ -0,0 +1,46 @@
+use crate::{Image, StFile};
+
+use std::collections::{BTreeSet, HashSet};
+
+// geocode intervals evaluated by a single coverage report
+pub const MAX_COVERAGE_INTERVALS: usize = 4194304;
+
+// start timestamp of the interval containing the timestamp
+pub fn interval_start(timestamp: i64, start_timestamp: i64,
+        interval_seconds: i64) -> i64 {
+    start_timestamp + ((timestamp - start_timestamp) / interval_seconds)
+        * interval_seconds
+}
+
+// (geocode, interval start) observed by images, truncating geocodes
+// finer than the report precision
+pub fn covered(images: &[(Image, Vec<StFile>)], precision: usize,
+        start_timestamp: i64, interval_seconds: i64)
+        -> BTreeSet<(String, i64)> {
+    images.iter().map(|(image, _)| (image.1.chars().take(precision)
+            .collect(), interval_start(image.5,
+                start_timestamp, interval_seconds)))
+        .collect()
+}
+
+// (geocode, interval start) pairs without observations, a geocode is
+// observed if it or a coarser geocode containing it is covered
+pub fn gaps(geocodes: &BTreeSet<String>, covered: &HashSet<(String, i64)>,
+        start_timestamp: i64, end_timestamp: i64, interval_seconds: i64)
+        -> Vec<(String, i64)> {
+    let mut gaps = Vec::new();
+    for geocode in geocodes.iter() {
+        let mut timestamp = start_timestamp;
+        while timestamp < end_timestamp {
+            let observed = (1..geocode.len() + 1).any(|x| covered
+                .contains(&(geocode[..x].to_string(), timestamp)));
+            if !observed {
+                gaps.push((geocode.clone(), timestamp));
+            }
+
+            timestamp += interval_seconds;
+        }
+    }
+
+    gaps
+}
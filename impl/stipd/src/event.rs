@@ -0,0 +1,150 @@
+use protobuf::EventType;
+use swarm::prelude::Dht;
+
+use crate::task::TaskManager;
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+// events retained for subscribers, discarding the oldest beyond this count
+const EVENT_CAPACITY: usize = 10000;
+const WATCH_INTERVAL: Duration = Duration::from_millis(1000);
+
+static EVENTS: Mutex<(u64, VecDeque<Event>)> =
+    Mutex::new((0, VecDeque::new()));
+
+#[derive(Clone, Debug)]
+pub struct Event {
+    pub attributes: HashMap<String, String>,
+    pub event_type: EventType,
+    pub sequence: u64,
+    pub timestamp: u64,
+}
+
+pub fn publish(event_type: EventType, attributes: &[(&str, String)]) {
+    let timestamp = match SystemTime::now().duration_since(UNIX_EPOCH) {
+        Ok(duration) => duration.as_secs(),
+        Err(_) => 0,
+    };
+
+    let mut events = EVENTS.lock().unwrap();
+    if events.1.len() >= EVENT_CAPACITY {
+        events.1.pop_front();
+        events.0 += 1;
+    }
+
+    let sequence = events.0 + events.1.len() as u64;
+    events.1.push_back(Event {
+        attributes: attributes.iter()
+            .map(|(key, value)| (key.to_string(), value.clone())).collect(),
+        event_type: event_type,
+        sequence: sequence,
+        timestamp: timestamp,
+    });
+}
+
+// sequence number of the next published event
+pub fn sequence() -> u64 {
+    let events = EVENTS.lock().unwrap();
+    events.0 + events.1.len() as u64
+}
+
+// retrieve events starting at the sequence number along with the
+//   sequence number following them
+pub fn since(sequence: u64) -> (Vec<Event>, u64) {
+    let events = EVENTS.lock().unwrap();
+    let skip = sequence.saturating_sub(events.0) as usize;
+
+    (events.1.iter().skip(skip).cloned().collect(),
+        events.0 + events.1.len() as u64)
+}
+
+// publish task and ring membership changes observed by polling
+pub fn watch(dht: Arc<Dht>, task_manager: Arc<RwLock<TaskManager>>) {
+    std::thread::spawn(move || {
+        let (mut nodes, mut tasks) = (None, None);
+        loop {
+            watch_nodes(&dht, &mut nodes);
+            watch_tasks(&task_manager, &mut tasks);
+
+            std::thread::sleep(WATCH_INTERVAL);
+        }
+    });
+}
+
+fn watch_nodes(dht: &Arc<Dht>,
+        previous: &mut Option<HashMap<u32, String>>) {
+    let mut nodes = HashMap::new();
+    for node in dht.nodes() {
+        nodes.insert(node.get_id(), format!("{}:{}", node.get_ip_address(),
+            node.get_metadata("rpc_port").unwrap()));
+    }
+
+    // the first observation establishes the initial membership
+    if let Some(previous) = previous {
+        for (id, rpc_addr) in nodes.iter() {
+            if !previous.contains_key(id) {
+                publish(EventType::NodeJoined, &[("id", id.to_string()),
+                    ("rpc_addr", rpc_addr.clone())]);
+                crate::cluster::ring_changed();
+            }
+        }
+
+        for (id, rpc_addr) in previous.iter() {
+            if !nodes.contains_key(id) {
+                publish(EventType::NodeLeft, &[("id", id.to_string()),
+                    ("rpc_addr", rpc_addr.clone())]);
+                crate::cluster::ring_changed();
+            }
+        }
+    }
+
+    *previous = Some(nodes);
+}
+
+fn watch_tasks(task_manager: &Arc<RwLock<TaskManager>>,
+        previous: &mut Option<HashMap<u64, bool>>) {
+    let mut tasks = HashMap::new();
+    let mut completed = Vec::new();
+    {
+        let task_manager = task_manager.read().unwrap();
+        for (id, task_handle) in task_manager.iter() {
+            // queued tasks have not started
+            let running = task_handle.running();
+            if running && task_handle.queued() {
+                continue;
+            }
+
+            tasks.insert(*id, running);
+            if !running {
+                completed.push((*id, task_handle.cancelled(),
+                    task_handle.completed_count(),
+                    task_handle.skipped_count()));
+            }
+        }
+    }
+
+    // the first observation establishes the initial task states
+    if let Some(previous) = previous {
+        for (id, running) in tasks.iter() {
+            // tasks may start and complete between observations
+            let was_running = previous.get(id);
+            if was_running != Some(&true)
+                    && (*running || was_running.is_none()) {
+                publish(EventType::TaskStarted, &[("id", id.to_string())]);
+            }
+        }
+
+        for (id, cancelled, completed_count, skipped_count) in completed {
+            if previous.get(&id) != Some(&false) {
+                publish(EventType::TaskCompleted, &[("id", id.to_string()),
+                    ("cancelled", cancelled.to_string()),
+                    ("completed_count", completed_count.to_string()),
+                    ("skipped_count", skipped_count.to_string())]);
+            }
+        }
+    }
+
+    *previous = Some(tasks);
+}
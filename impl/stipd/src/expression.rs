@@ -0,0 +1,269 @@
+use std::error::Error;
+use std::iter::Peekable;
+use std::str::{Chars, FromStr};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Function {
+    Abs,
+    Exp,
+    Log,
+    Max,
+    Min,
+    Sqrt,
+}
+
+impl Function {
+    fn arity(&self) -> usize {
+        match self {
+            Function::Max | Function::Min => 2,
+            _ => 1,
+        }
+    }
+
+    fn apply(&self, args: &[f64]) -> f64 {
+        match self {
+            Function::Abs => args[0].abs(),
+            Function::Exp => args[0].exp(),
+            Function::Log => args[0].ln(),
+            Function::Max => args[0].max(args[1]),
+            Function::Min => args[0].min(args[1]),
+            Function::Sqrt => args[0].sqrt(),
+        }
+    }
+}
+
+impl FromStr for Function {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "abs" => Ok(Function::Abs),
+            "exp" => Ok(Function::Exp),
+            "log" => Ok(Function::Log),
+            "max" => Ok(Function::Max),
+            "min" => Ok(Function::Min),
+            "sqrt" => Ok(Function::Sqrt),
+            x => Err(format!("unknown function '{}'", x)),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Operator {
+    Add,
+    Divide,
+    Multiply,
+    Power,
+    Subtract,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Expression {
+    Band(usize),
+    Binary(Operator, Box<Expression>, Box<Expression>),
+    Call(Function, Vec<Expression>),
+    Constant(f64),
+    Negate(Box<Expression>),
+}
+
+impl Expression {
+    // band numbers referenced by the expression, sorted and deduplicated
+    pub fn bands(&self) -> Vec<usize> {
+        let mut bands = Vec::new();
+        self.collect_bands(&mut bands);
+        bands.sort();
+        bands.dedup();
+        bands
+    }
+
+    fn collect_bands(&self, bands: &mut Vec<usize>) {
+        match self {
+            Expression::Band(band) => bands.push(*band),
+            Expression::Binary(_, a, b) => {
+                a.collect_bands(bands);
+                b.collect_bands(bands);
+            },
+            Expression::Call(_, args) =>
+                args.iter().for_each(|x| x.collect_bands(bands)),
+            Expression::Constant(_) => {},
+            Expression::Negate(a) => a.collect_bands(bands),
+        }
+    }
+
+    // values are indexed by band number minus one
+    pub fn evaluate(&self, values: &[f64]) -> f64 {
+        match self {
+            Expression::Band(band) => values[band - 1],
+            Expression::Binary(operator, a, b) => {
+                let (a, b) = (a.evaluate(values), b.evaluate(values));
+                match operator {
+                    Operator::Add => a + b,
+                    Operator::Divide => a / b,
+                    Operator::Multiply => a * b,
+                    Operator::Power => a.powf(b),
+                    Operator::Subtract => a - b,
+                }
+            },
+            Expression::Call(function, args) => {
+                let args: Vec<f64> =
+                    args.iter().map(|x| x.evaluate(values)).collect();
+                function.apply(&args)
+            },
+            Expression::Constant(value) => *value,
+            Expression::Negate(a) => -a.evaluate(values),
+        }
+    }
+}
+
+impl FromStr for Expression {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser { chars: s.chars().peekable() };
+        let expression = parser.expression().map_err(|e| e.to_string())?;
+
+        parser.skip_whitespace();
+        match parser.chars.peek() {
+            Some(c) => Err(format!("unexpected '{}' in expression", c)),
+            None => Ok(expression),
+        }
+    }
+}
+
+// recursive descent parser, lowest precedence first
+struct Parser<'a> {
+    chars: Peekable<Chars<'a>>,
+}
+
+impl<'a> Parser<'a> {
+    fn expression(&mut self) -> Result<Expression, Box<dyn Error>> {
+        let mut expression = self.term()?;
+        loop {
+            let operator = match self.next_operator(&['+', '-']) {
+                Some('+') => Operator::Add,
+                Some(_) => Operator::Subtract,
+                None => return Ok(expression),
+            };
+
+            expression = Expression::Binary(operator,
+                Box::new(expression), Box::new(self.term()?));
+        }
+    }
+
+    fn term(&mut self) -> Result<Expression, Box<dyn Error>> {
+        let mut expression = self.factor()?;
+        loop {
+            let operator = match self.next_operator(&['*', '/']) {
+                Some('*') => Operator::Multiply,
+                Some(_) => Operator::Divide,
+                None => return Ok(expression),
+            };
+
+            expression = Expression::Binary(operator,
+                Box::new(expression), Box::new(self.factor()?));
+        }
+    }
+
+    fn factor(&mut self) -> Result<Expression, Box<dyn Error>> {
+        // exponentiation is right associative
+        let base = self.unary()?;
+        match self.next_operator(&['^']) {
+            Some(_) => Ok(Expression::Binary(Operator::Power,
+                Box::new(base), Box::new(self.factor()?))),
+            None => Ok(base),
+        }
+    }
+
+    fn unary(&mut self) -> Result<Expression, Box<dyn Error>> {
+        match self.next_operator(&['-']) {
+            Some(_) => Ok(Expression::Negate(Box::new(self.unary()?))),
+            None => self.primary(),
+        }
+    }
+
+    fn primary(&mut self) -> Result<Expression, Box<dyn Error>> {
+        self.skip_whitespace();
+        match self.chars.peek().cloned() {
+            Some('(') => {
+                self.chars.next();
+                let expression = self.expression()?;
+                self.expect(')')?;
+                Ok(expression)
+            },
+            Some(c) if c.is_ascii_digit() || c == '.' => {
+                let number =
+                    self.take_while(|x| x.is_ascii_digit() || x == '.');
+                match number.parse::<f64>() {
+                    Ok(value) => Ok(Expression::Constant(value)),
+                    Err(_) => Err(format!("invalid number '{}'",
+                        number).into()),
+                }
+            },
+            Some(c) if c.is_ascii_alphabetic() => {
+                let name = self.take_while(|x| x.is_ascii_alphanumeric())
+                    .to_lowercase();
+
+                // band references are 'b' followed by a band number
+                if name.starts_with('b') && name.len() > 1
+                        && name[1..].chars().all(|x| x.is_ascii_digit()) {
+                    return match name[1..].parse::<usize>()? {
+                        0 => Err("band numbers start at 1".into()),
+                        band => Ok(Expression::Band(band)),
+                    };
+                }
+
+                let function = name.parse::<Function>()?;
+                self.expect('(')?;
+                let mut args = vec![self.expression()?];
+                while self.next_operator(&[',']).is_some() {
+                    args.push(self.expression()?);
+                }
+                self.expect(')')?;
+
+                if args.len() != function.arity() {
+                    return Err(format!("{} takes {} arguments, found {}",
+                        name, function.arity(), args.len()).into());
+                }
+
+                Ok(Expression::Call(function, args))
+            },
+            Some(c) => Err(format!("unexpected '{}' in expression", c).into()),
+            None => Err("unexpected end of expression".into()),
+        }
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), Box<dyn Error>> {
+        match self.next_operator(&[c]) {
+            Some(_) => Ok(()),
+            None => Err(format!("expected '{}' in expression", c).into()),
+        }
+    }
+
+    fn next_operator(&mut self, operators: &[char]) -> Option<char> {
+        self.skip_whitespace();
+        match self.chars.peek() {
+            Some(c) if operators.contains(c) => self.chars.next(),
+            _ => None,
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.chars.peek().map(|x| x.is_whitespace()).unwrap_or(false) {
+            self.chars.next();
+        }
+    }
+
+    fn take_while<F>(&mut self, f: F) -> String where F: Fn(char) -> bool {
+        let mut buf = String::new();
+        while let Some(c) = self.chars.peek().cloned() {
+            if !f(c) {
+                break;
+            }
+
+            buf.push(c);
+            self.chars.next();
+        }
+
+        buf
+    }
+}
@@ -0,0 +1,276 @@
+use chrono::{Datelike, NaiveDateTime};
+use gdal::Dataset;
+
+use crate::{Image, StFile};
+
+use std::error::Error;
+
+pub const DEFAULT_FILL_STRATEGY: &'static str = "window";
+
+const DAY_SECONDS: i64 = 86400;
+
+pub trait FillStrategy: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    // group targets of one platform, geocode, and subdataset, ordered by
+    // timestamp, with donors into records whose first observation is filled
+    fn records(&self, targets: &[(Image, StFile)],
+        donors: &[(Image, StFile)], window_seconds: i64)
+        -> Vec<Vec<(Image, StFile)>>;
+
+    // true if donors may be observed by another platform
+    fn supports_donors(&self) -> bool {
+        true
+    }
+
+    // pixel coverage a filled image must exceed to be written
+    fn baseline(&self, coverages: &[f64]) -> f64;
+
+    // fill the first dataset using the remaining datasets
+    fn fill(&self, record: &[(Image, StFile)], datasets: &[Dataset])
+        -> Result<Dataset, Box<dyn Error>>;
+}
+
+pub fn strategy(name: &str) -> Result<Box<dyn FillStrategy>, String> {
+    match name.to_lowercase().as_str() {
+        "linear" => Ok(Box::new(LinearFill)),
+        "nearest" => Ok(Box::new(NearestFill)),
+        "seasonal" => Ok(Box::new(SeasonalFill)),
+        "window" => Ok(Box::new(WindowFill)),
+        x => Err(format!("unsupported fill strategy '{}'", x)),
+    }
+}
+
+// interpolate between the closest observations before and after
+pub struct LinearFill;
+
+impl FillStrategy for LinearFill {
+    fn name(&self) -> &'static str {
+        "linear"
+    }
+
+    fn records(&self, targets: &[(Image, StFile)],
+            donors: &[(Image, StFile)], window_seconds: i64)
+            -> Vec<Vec<(Image, StFile)>> {
+        temporal_records(targets, donors,
+            |a, b| (a - b).abs() <= window_seconds)
+    }
+
+    fn baseline(&self, coverages: &[f64]) -> f64 {
+        coverages[0]
+    }
+
+    fn fill(&self, record: &[(Image, StFile)], datasets: &[Dataset])
+            -> Result<Dataset, Box<dyn Error>> {
+        temporal_fill(record, datasets, |timestamp, observations| {
+            let mut before: Option<(i64, f64)> = None;
+            let mut after: Option<(i64, f64)> = None;
+            for (t, value) in observations.iter() {
+                if *t == timestamp {
+                    return Some(*value);
+                } else if *t < timestamp
+                        && before.map(|x| *t > x.0).unwrap_or(true) {
+                    before = Some((*t, *value));
+                } else if *t > timestamp
+                        && after.map(|x| *t < x.0).unwrap_or(true) {
+                    after = Some((*t, *value));
+                }
+            }
+
+            // never extrapolate past the closest observations
+            match (before, after) {
+                (Some((t0, v0)), Some((t1, v1))) => Some(v0 + (v1 - v0)
+                    * (timestamp - t0) as f64 / (t1 - t0) as f64),
+                _ => None,
+            }
+        })
+    }
+}
+
+// copy the observation closest in time
+pub struct NearestFill;
+
+impl FillStrategy for NearestFill {
+    fn name(&self) -> &'static str {
+        "nearest"
+    }
+
+    fn records(&self, targets: &[(Image, StFile)],
+            donors: &[(Image, StFile)], window_seconds: i64)
+            -> Vec<Vec<(Image, StFile)>> {
+        temporal_records(targets, donors,
+            |a, b| (a - b).abs() <= window_seconds)
+    }
+
+    fn baseline(&self, coverages: &[f64]) -> f64 {
+        coverages[0]
+    }
+
+    fn fill(&self, record: &[(Image, StFile)], datasets: &[Dataset])
+            -> Result<Dataset, Box<dyn Error>> {
+        temporal_fill(record, datasets, |timestamp, observations| {
+            observations.iter()
+                .min_by_key(|(t, _)| (t - timestamp).abs())
+                .map(|(_, value)| *value)
+        })
+    }
+}
+
+// average observations of other years near the same day of year
+pub struct SeasonalFill;
+
+impl FillStrategy for SeasonalFill {
+    fn name(&self) -> &'static str {
+        "seasonal"
+    }
+
+    fn records(&self, targets: &[(Image, StFile)],
+            donors: &[(Image, StFile)], window_seconds: i64)
+            -> Vec<Vec<(Image, StFile)>> {
+        let window_days = (window_seconds / DAY_SECONDS) as u32;
+        temporal_records(targets, donors, |a, b| {
+            let (a, b) = (NaiveDateTime::from_timestamp(a, 0),
+                NaiveDateTime::from_timestamp(b, 0));
+            if a.year() == b.year() {
+                return false;
+            }
+
+            // day of year distance wraps around the new year
+            let distance = (a.ordinal() as i32 - b.ordinal() as i32)
+                .abs() as u32;
+            distance.min(365u32.saturating_sub(distance)) <= window_days
+        })
+    }
+
+    fn baseline(&self, coverages: &[f64]) -> f64 {
+        coverages[0]
+    }
+
+    fn fill(&self, record: &[(Image, StFile)], datasets: &[Dataset])
+            -> Result<Dataset, Box<dyn Error>> {
+        temporal_fill(record, datasets, |_, observations| {
+            match observations.len() {
+                0 => None,
+                x => Some(observations.iter()
+                    .map(|(_, value)| value).sum::<f64>() / x as f64),
+            }
+        })
+    }
+}
+
+// combine partial images captured within one window into a single image
+pub struct WindowFill;
+
+impl FillStrategy for WindowFill {
+    fn name(&self) -> &'static str {
+        "window"
+    }
+
+    fn records(&self, targets: &[(Image, StFile)],
+            _donors: &[(Image, StFile)], window_seconds: i64)
+            -> Vec<Vec<(Image, StFile)>> {
+        let mut records = Vec::new();
+        let mut images_buf: Vec<(Image, StFile)> = Vec::new();
+        for observation in targets.iter() {
+            let timestamp = images_buf.first().map(|x| (x.0).5);
+            if let Some(timestamp) = timestamp {
+                if (observation.0).5 - timestamp > window_seconds {
+                    if images_buf.len() >= 2 {
+                        records.push(images_buf);
+                        images_buf = Vec::new();
+                    } else {
+                        images_buf.clear();
+                    }
+                }
+            }
+
+            images_buf.push(observation.clone());
+        }
+
+        if images_buf.len() >= 2 {
+            records.push(images_buf);
+        }
+
+        records
+    }
+
+    fn supports_donors(&self) -> bool {
+        false
+    }
+
+    fn baseline(&self, coverages: &[f64]) -> f64 {
+        coverages.iter().cloned().fold(0f64, f64::max)
+    }
+
+    fn fill(&self, _record: &[(Image, StFile)], datasets: &[Dataset])
+            -> Result<Dataset, Box<dyn Error>> {
+        Ok(st_image::fill(datasets)?)
+    }
+}
+
+fn temporal_records<F>(targets: &[(Image, StFile)],
+        donors: &[(Image, StFile)], donor: F)
+        -> Vec<Vec<(Image, StFile)>> where F: Fn(i64, i64) -> bool {
+    let mut records = Vec::new();
+    for target in targets.iter() {
+        let mut record = vec![target.clone()];
+        for observation in donors.iter() {
+            if (observation.1).0 != (target.1).0
+                    && donor((target.0).5, (observation.0).5) {
+                record.push(observation.clone());
+            }
+        }
+
+        if record.len() >= 2 {
+            records.push(record);
+        }
+    }
+
+    records
+}
+
+fn temporal_fill<F>(record: &[(Image, StFile)], datasets: &[Dataset],
+        value: F) -> Result<Dataset, Box<dyn Error>>
+        where F: Fn(i64, &[(i64, f64)]) -> Option<f64> {
+    for (i, dataset) in datasets.iter().enumerate().skip(1) {
+        if dataset.raster_size() != datasets[0].raster_size() {
+            return Err(format!("image '{}' is not aligned with '{}'",
+                (record[i].1).0, (record[0].1).0).into());
+        }
+    }
+
+    // fill nodata pixels of the target from valid donor pixels
+    let filled = crate::raster::copy(&datasets[0])?;
+    let band_count = datasets.iter()
+        .map(crate::raster::band_count).min().unwrap_or(0);
+    let timestamp = (record[0].0).5;
+    for i in 0..band_count {
+        let mut bands = Vec::new();
+        for dataset in datasets.iter() {
+            bands.push(crate::raster::read_band(dataset, i + 1)?);
+        }
+
+        let mut values = bands[0].values.clone();
+        let mut observations = Vec::new();
+        for j in 0..values.len() {
+            if bands[0].is_valid(j) {
+                continue;
+            }
+
+            observations.clear();
+            for (k, band) in bands.iter().enumerate().skip(1) {
+                if band.is_valid(j) {
+                    observations.push(((record[k].0).5, band.values[j]));
+                }
+            }
+
+            if let Some(x) = value(timestamp, &observations) {
+                values[j] = x;
+            }
+        }
+
+        crate::raster::write_band(&filled, i + 1, &mut values)?;
+    }
+
+    Ok(filled)
+}
@@ -0,0 +1,128 @@
+use protobuf::Filter;
+use serde_json::{json, Value};
+
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+pub struct FilterStore {
+    filters: Mutex<BTreeMap<String, Filter>>,
+    path: PathBuf,
+}
+
+impl FilterStore {
+    pub fn new(path: PathBuf) -> Result<FilterStore, Box<dyn Error>> {
+        // load persisted filters
+        let mut filters = BTreeMap::new();
+        if path.exists() {
+            let value: Value =
+                serde_json::from_slice(&std::fs::read(&path)?)?;
+            for entry in value.as_array().ok_or("invalid filter store")? {
+                let name = match entry["name"].as_str() {
+                    Some(name) => name,
+                    None => return Err("invalid filter store entry".into()),
+                };
+
+                let string = |key: &str| entry[key].as_str()
+                    .map(|x| x.to_string());
+                filters.insert(name.to_string(), Filter {
+                    end_timestamp: entry["end_timestamp"].as_i64(),
+                    geocode: string("geocode"),
+                    max_cloud_coverage: entry["max_cloud_coverage"].as_f64(),
+                    min_pixel_coverage: entry["min_pixel_coverage"].as_f64(),
+                    name: None,
+                    platform: string("platform"),
+                    recurse: entry["recurse"].as_bool().unwrap_or(false),
+                    source: string("source"),
+                    start_timestamp: entry["start_timestamp"].as_i64(),
+                });
+            }
+        }
+
+        Ok(FilterStore {
+            filters: Mutex::new(filters),
+            path: path,
+        })
+    }
+
+    pub fn delete(&self, name: &str) -> Result<(), Box<dyn Error>> {
+        let mut filters = self.filters.lock().unwrap();
+        if filters.remove(name).is_none() {
+            return Err(format!("filter '{}' does not exist", name).into());
+        }
+
+        self.write(&filters)
+    }
+
+    pub fn list(&self) -> Vec<(String, Filter)> {
+        self.filters.lock().unwrap().iter()
+            .map(|(name, filter)| (name.clone(), filter.clone()))
+            .collect()
+    }
+
+    // fill fields unset in the filter from the saved filter it names
+    pub fn resolve(&self, filter: &Filter) -> Result<Filter, Box<dyn Error>> {
+        let name = match &filter.name {
+            Some(name) => name,
+            None => return Ok(filter.clone()),
+        };
+
+        let saved = match self.filters.lock().unwrap().get(name) {
+            Some(saved) => saved.clone(),
+            None => return Err(format!("filter '{}' does not exist",
+                name).into()),
+        };
+
+        Ok(Filter {
+            end_timestamp: filter.end_timestamp.or(saved.end_timestamp),
+            geocode: filter.geocode.clone().or(saved.geocode),
+            max_cloud_coverage: filter.max_cloud_coverage
+                .or(saved.max_cloud_coverage),
+            min_pixel_coverage: filter.min_pixel_coverage
+                .or(saved.min_pixel_coverage),
+            name: None,
+            platform: filter.platform.clone().or(saved.platform),
+            recurse: filter.recurse || saved.recurse,
+            source: filter.source.clone().or(saved.source),
+            start_timestamp: filter.start_timestamp
+                .or(saved.start_timestamp),
+        })
+    }
+
+    pub fn save(&self, name: &str, filter: &Filter)
+            -> Result<(), Box<dyn Error>> {
+        if name.is_empty() {
+            return Err("filter name is empty".into());
+        }
+
+        let mut filter = filter.clone();
+        filter.name = None;
+
+        let mut filters = self.filters.lock().unwrap();
+        filters.insert(name.to_string(), filter);
+        self.write(&filters)
+    }
+
+    fn write(&self, filters: &BTreeMap<String, Filter>)
+            -> Result<(), Box<dyn Error>> {
+        let entries: Vec<Value> = filters.iter()
+            .map(|(name, filter)| json!({
+                "name": name,
+                "end_timestamp": filter.end_timestamp,
+                "geocode": filter.geocode,
+                "max_cloud_coverage": filter.max_cloud_coverage,
+                "min_pixel_coverage": filter.min_pixel_coverage,
+                "platform": filter.platform,
+                "recurse": filter.recurse,
+                "source": filter.source,
+                "start_timestamp": filter.start_timestamp,
+            })).collect();
+
+        // write to a temporary file and rename to replace atomically
+        let tmp_path = self.path.with_extension("tmp");
+        std::fs::write(&tmp_path, Value::Array(entries).to_string())?;
+        std::fs::rename(&tmp_path, &self.path)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,537 @@
+use protobuf::{AlbumListRequest, AlbumManagementClient, AlbumStatus, Extent, Filter, Geocode, Image, ImageListRequest, ImageManagementClient, ImageSearchRequest, NodeListRequest, NodeManagementClient, NodeStatusRequest, TaskBroadcastRequest, TaskBroadcastType, TaskCancelRequest, TaskErrorsRequest, TaskListRequest, TaskManagementClient, TaskPauseRequest, TaskResumeRequest};
+use serde_json::{json, Value};
+use swarm::prelude::Dht;
+use tokio::runtime::{Builder, Runtime};
+use tonic::{Request, Status};
+
+use crate::http::{HttpRequest, HttpResponse};
+
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+
+// translates http json requests into grpc requests against the local node
+pub struct GatewayServer {
+    dht: Arc<Dht>,
+    rpc_addr: String,
+    // shared by every connection thread, which block on rpcs through
+    //   its handle
+    runtime: Mutex<Runtime>,
+}
+
+impl GatewayServer {
+    pub fn new(dht: Arc<Dht>, rpc_addr: String)
+            -> Result<GatewayServer, Box<dyn Error>> {
+        let runtime = Builder::new().threaded_scheduler()
+            .enable_all().build()?;
+
+        Ok(GatewayServer {
+            dht: dht,
+            rpc_addr: rpc_addr,
+            runtime: Mutex::new(runtime),
+        })
+    }
+
+    pub fn start(self, address: &str) -> Result<(), Box<dyn Error>> {
+        info!("starting gateway server [address={}]", address);
+        crate::http::start(address, move |request| self.handle(request))
+    }
+
+    fn handle(&self, request: &HttpRequest) -> HttpResponse {
+        let segments: Vec<&str> = request.path.split('/')
+            .filter(|x| !x.is_empty()).collect();
+
+        // rpcs are authorized with the token of the http request
+        let token = request.bearer();
+        let handle = self.runtime.lock().unwrap().handle().clone();
+        let result = handle.block_on(self.route(&request.method,
+            &segments, &request.query, token.as_deref()));
+
+        match result {
+            Ok(response) => response,
+            Err(e) => match e.downcast_ref::<Status>() {
+                Some(status) => HttpResponse::from_status(status),
+                None => {
+                    warn!("failed to handle gateway request '{}': {}",
+                        request.path, e);
+                    HttpResponse::error(500, &e.to_string())
+                },
+            },
+        }
+    }
+
+    async fn route(&self, method: &str, segments: &[&str],
+            params: &HashMap<String, String>, token: Option<&str>)
+            -> Result<HttpResponse, Box<dyn Error>> {
+        match (method, segments) {
+            ("GET", ["v1", "albums"]) => self.albums(token).await,
+            ("GET", ["v1", "albums", album, "images"]) =>
+                self.images(album, params, token).await,
+            ("GET", ["v1", "albums", album, "search"]) =>
+                self.search(album, params, token).await,
+            ("GET", ["v1", "nodes"]) => self.nodes(token).await,
+            ("GET", ["v1", "nodes", "status"]) => self.status(token).await,
+            ("GET", ["v1", "tasks"]) => self.tasks(token).await,
+            ("GET", ["v1", "tasks", id, "errors"]) => match id.parse() {
+                Ok(id) => self.task_errors(id, token).await,
+                Err(_) => Ok(invalid_task_id(id)),
+            },
+            ("POST", ["v1", "tasks", id, action]) => match id.parse() {
+                Ok(id) => self.task_action(id, action, token).await,
+                Err(_) => Ok(invalid_task_id(id)),
+            },
+            (_, ["v1", "tasks", _, _]) => Ok(HttpResponse::error(405,
+                "task errors support GET and task actions support POST")),
+            _ => Ok(HttpResponse::error(404, "resource not found")),
+        }
+    }
+
+    async fn albums(&self, token: Option<&str>)
+            -> Result<HttpResponse, Box<dyn Error>> {
+        let mut client = AlbumManagementClient::new(
+            crate::tls::token_channel(&self.rpc_addr, token).await?);
+        let reply = client.list(Request::new(AlbumListRequest {})).await?;
+
+        let albums: Vec<Value> = reply.get_ref().albums.iter()
+            .map(|album| json!({
+                "creation_options": album.creation_options,
+                "dht_key_length": album.dht_key_length,
+                "frozen": album.frozen,
+                "geocode": match Geocode::from_i32(album.geocode) {
+                    Some(Geocode::Geohash) => "geohash",
+                    Some(Geocode::Quadtile) => "quadtile",
+                    None => "unknown",
+                },
+                "id": album.id,
+                "legal_hold": album.legal_hold,
+                "max_bytes": album.max_bytes,
+                "precision": album.precision,
+                "raw_ttl_days": album.raw_ttl_days,
+                "status": match AlbumStatus::from_i32(album.status) {
+                    Some(AlbumStatus::Open) => "open",
+                    _ => "closed",
+                },
+            })).collect();
+
+        Ok(HttpResponse::json(200, &json!({ "albums": albums })))
+    }
+
+    async fn images(&self, album: &str, params: &HashMap<String, String>,
+            token: Option<&str>) -> Result<HttpResponse, Box<dyn Error>> {
+        let (filter, bbox) = match (filter(params), bbox(params)) {
+            (Ok(filter), Ok(bbox)) => (filter, bbox),
+            (Err(e), _) | (_, Err(e)) =>
+                return Ok(HttpResponse::error(400, &e)),
+        };
+
+        let request = ImageListRequest {
+            album: album.to_string(),
+            albums: Vec::new(),
+            all_albums: None,
+            filter: filter,
+            geometry: None,
+            max_latitude: bbox.map(|x| x.3),
+            max_longitude: bbox.map(|x| x.1),
+            min_latitude: bbox.map(|x| x.2),
+            min_longitude: bbox.map(|x| x.0),
+            statistics: None,
+        };
+
+        // list images stored on every node concurrently, reporting
+        //   unreachable nodes alongside the images of the rest
+        let mut handles = Vec::new();
+        for (node_id, addr) in self.node_addrs() {
+            let (request, token) =
+                (request.clone(), token.map(|x| x.to_string()));
+            handles.push((node_id, tokio::spawn(async move {
+                let mut client = ImageManagementClient::new(
+                    crate::tls::token_channel(&addr, token.as_deref())
+                        .await.map_err(|e| e.to_string())?);
+                let mut stream = client.list(Request::new(request))
+                    .await.map_err(|e| e.message().to_string())?
+                    .into_inner();
+
+                let mut images = Vec::new();
+                while let Some(image) = stream.message().await
+                        .map_err(|e| e.message().to_string())? {
+                    images.push(image);
+                }
+
+                Ok::<_, String>(images)
+            })));
+        }
+
+        let (mut images, mut errors) = (Vec::new(), Vec::new());
+        for (node_id, handle) in handles {
+            match handle.await.map_err(|e| e.to_string())
+                    .and_then(|x| x) {
+                Ok(node_images) => images.extend(node_images.iter()
+                    .map(|x| image_json(node_id, x))),
+                Err(e) => errors.push(node_error(node_id, &e)),
+            }
+        }
+
+        Ok(HttpResponse::json(200, &json!({
+            "errors": errors,
+            "images": images,
+        })))
+    }
+
+    async fn nodes(&self, token: Option<&str>)
+            -> Result<HttpResponse, Box<dyn Error>> {
+        let mut client = NodeManagementClient::new(
+            crate::tls::token_channel(&self.rpc_addr, token).await?);
+        let reply = client.list(Request::new(NodeListRequest {})).await?;
+
+        let nodes: Vec<Value> = reply.get_ref().nodes.iter()
+            .map(|node| json!({
+                "decommissioned": node.decommissioned.unwrap_or(false),
+                "id": node.id,
+                "rpc_addr": node.rpc_addr,
+                "xfer_addr": node.xfer_addr,
+            })).collect();
+
+        Ok(HttpResponse::json(200, &json!({ "nodes": nodes })))
+    }
+
+    async fn search(&self, album: &str, params: &HashMap<String, String>,
+            token: Option<&str>) -> Result<HttpResponse, Box<dyn Error>> {
+        let (filter, bbox) = match (filter(params), bbox(params)) {
+            (Ok(filter), Ok(bbox)) => (filter, bbox),
+            (Err(e), _) | (_, Err(e)) =>
+                return Ok(HttpResponse::error(400, &e)),
+        };
+
+        let request = ImageSearchRequest {
+            album: album.to_string(),
+            filter: filter,
+            full_geocode: Some(true),
+            geometry: None,
+            group_by_interval: params.get("group_by_interval").cloned(),
+            max_latitude: bbox.map(|x| x.3),
+            max_longitude: bbox.map(|x| x.1),
+            min_latitude: bbox.map(|x| x.2),
+            min_longitude: bbox.map(|x| x.0),
+        };
+
+        // search images stored on every node concurrently, reporting
+        //   unreachable nodes alongside the extents of the rest
+        let mut handles = Vec::new();
+        for (node_id, addr) in self.node_addrs() {
+            let (request, token) =
+                (request.clone(), token.map(|x| x.to_string()));
+            handles.push((node_id, tokio::spawn(async move {
+                let mut client = ImageManagementClient::new(
+                    crate::tls::token_channel(&addr, token.as_deref())
+                        .await.map_err(|e| e.to_string())?);
+                let mut stream = client.search(Request::new(request))
+                    .await.map_err(|e| e.message().to_string())?
+                    .into_inner();
+
+                let mut extents = Vec::new();
+                while let Some(extent) = stream.message().await
+                        .map_err(|e| e.message().to_string())? {
+                    extents.push(extent);
+                }
+
+                Ok::<_, String>(extents)
+            })));
+        }
+
+        let (mut extents, mut errors) = (Vec::new(), Vec::new());
+        for (node_id, handle) in handles {
+            match handle.await.map_err(|e| e.to_string())
+                    .and_then(|x| x) {
+                Ok(node_extents) => extents.extend(node_extents),
+                Err(e) => errors.push(node_error(node_id, &e)),
+            }
+        }
+
+        // count replicated images once, summing by geocode prefix as
+        //   the stip cli does
+        let length = request.filter.geocode.as_ref()
+            .map(|x| x.len() + 1).unwrap_or(1);
+        let extents: Vec<Value> = merge_extents(extents, length).iter()
+            .map(extent_json).collect();
+
+        Ok(HttpResponse::json(200, &json!({
+            "errors": errors,
+            "extents": extents,
+        })))
+    }
+
+    async fn status(&self, token: Option<&str>)
+            -> Result<HttpResponse, Box<dyn Error>> {
+        // query nodes concurrently, unreachable nodes are reported
+        //   without status
+        let mut handles = Vec::new();
+        for (node_id, addr) in self.node_addrs() {
+            let token = token.map(|x| x.to_string());
+            let status_addr = addr.clone();
+            handles.push((node_id, addr, tokio::spawn(async move {
+                match crate::tls::token_channel(&status_addr,
+                        token.as_deref()).await {
+                    Ok(channel) => NodeManagementClient::new(channel)
+                        .status(Request::new(NodeStatusRequest {})).await
+                        .map(|x| x.into_inner()).map_err(|e| e.to_string()),
+                    Err(e) => Err(e.to_string()),
+                }
+            })));
+        }
+
+        let mut nodes = Vec::new();
+        for (node_id, addr, handle) in handles {
+            let reply = handle.await.map_err(|e| e.to_string())
+                .and_then(|x| x);
+
+            nodes.push(match reply {
+                Ok(reply) => json!({
+                    "albums": reply.albums.iter().map(|x| json!({
+                        "album": x.album,
+                        "image_count": x.image_count,
+                    })).collect::<Vec<Value>>(),
+                    "disk_free_bytes": reply.disk_free_bytes,
+                    "disk_total_bytes": reply.disk_total_bytes,
+                    "id": node_id,
+                    "queued_task_count": reply.queued_task_count,
+                    "ring_node_count": reply.ring_node_count,
+                    "rpc_addr": addr,
+                    "running_task_count": reply.running_task_count,
+                    "uptime_seconds": reply.uptime_seconds,
+                }),
+                Err(e) => json!({
+                    "error": e,
+                    "id": node_id,
+                    "rpc_addr": addr,
+                }),
+            });
+        }
+
+        Ok(HttpResponse::json(200, &json!({ "nodes": nodes })))
+    }
+
+    async fn task_action(&self, id: u64, action: &str,
+            token: Option<&str>) -> Result<HttpResponse, Box<dyn Error>> {
+        let mut request = task_broadcast(TaskBroadcastType::TaskCancel);
+        match action {
+            "cancel" => request.cancel_request = Some(TaskCancelRequest {
+                id: id,
+            }),
+            "pause" => {
+                request.message_type = TaskBroadcastType::TaskPause as i32;
+                request.pause_request = Some(TaskPauseRequest { id: id });
+            },
+            "resume" => {
+                request.message_type = TaskBroadcastType::TaskResume as i32;
+                request.resume_request = Some(TaskResumeRequest { id: id });
+            },
+            x => return Ok(HttpResponse::error(404,
+                &format!("unsupported task action '{}'", x))),
+        }
+
+        let mut client = TaskManagementClient::new(
+            crate::tls::token_channel(&self.rpc_addr, token).await?);
+        let reply = client.broadcast(Request::new(request)).await?;
+        let reply = reply.get_ref();
+
+        // compile nodes where the action applied
+        let mut node_ids: Vec<u32> = reply.cancel_replies.iter()
+            .filter(|(_, x)| x.cancelled).map(|(id, _)| *id)
+            .chain(reply.pause_replies.iter()
+                .filter(|(_, x)| x.paused).map(|(id, _)| *id))
+            .chain(reply.resume_replies.iter()
+                .filter(|(_, x)| x.resumed).map(|(id, _)| *id))
+            .collect();
+        node_ids.sort();
+
+        Ok(HttpResponse::json(200, &json!({
+            "action": action,
+            "id": id,
+            "nodes": node_ids,
+        })))
+    }
+
+    async fn task_errors(&self, id: u64, token: Option<&str>)
+            -> Result<HttpResponse, Box<dyn Error>> {
+        let mut request = task_broadcast(TaskBroadcastType::TaskErrors);
+        request.errors_request = Some(TaskErrorsRequest { id: id });
+
+        let mut client = TaskManagementClient::new(
+            crate::tls::token_channel(&self.rpc_addr, token).await?);
+        let reply = client.broadcast(Request::new(request)).await?;
+
+        let (mut errors, mut omitted) = (Vec::new(), 0);
+        for (node_id, errors_reply) in reply.get_ref().errors_replies.iter() {
+            for error in errors_reply.errors.iter() {
+                errors.push(json!({
+                    "message": error.message,
+                    "node": node_id,
+                    "record": error.record,
+                }));
+            }
+
+            omitted += errors_reply.omitted.unwrap_or(0);
+        }
+
+        Ok(HttpResponse::json(200, &json!({
+            "errors": errors,
+            "omitted": omitted,
+        })))
+    }
+
+    async fn tasks(&self, token: Option<&str>)
+            -> Result<HttpResponse, Box<dyn Error>> {
+        let mut request = task_broadcast(TaskBroadcastType::TaskList);
+        request.list_request = Some(TaskListRequest {});
+
+        let mut client = TaskManagementClient::new(
+            crate::tls::token_channel(&self.rpc_addr, token).await?);
+        let reply = client.broadcast(Request::new(request)).await?;
+
+        let mut tasks = Vec::new();
+        for (node_id, list_reply) in reply.get_ref().list_replies.iter() {
+            for task in list_reply.tasks.iter() {
+                tasks.push(json!({
+                    "completed_count": task.completed_count,
+                    "id": task.id,
+                    "node": node_id,
+                    "paused": task.paused,
+                    "priority": task.priority,
+                    "queued": task.queued,
+                    "running": task.running,
+                    "skipped_count": task.skipped_count,
+                    "total_count": task.total_count,
+                }));
+            }
+        }
+
+        Ok(HttpResponse::json(200, &json!({ "tasks": tasks })))
+    }
+
+    fn node_addrs(&self) -> Vec<(u32, String)> {
+        let mut addrs: Vec<(u32, String)> = self.dht.nodes().into_iter()
+            .map(|node| (node.get_id(), format!("{}:{}",
+                node.get_ip_address(),
+                node.get_metadata("rpc_port").unwrap())))
+            .collect();
+
+        addrs.sort();
+        addrs
+    }
+}
+
+fn bbox(params: &HashMap<String, String>)
+        -> Result<Option<(f64, f64, f64, f64)>, String> {
+    match params.get("bbox") {
+        Some(bbox) => {
+            let values = bbox.split(',').map(|x| x.trim().parse::<f64>())
+                .collect::<Result<Vec<f64>, _>>()
+                .map_err(|_| format!("invalid bbox '{}'", bbox))?;
+            crate::stac::parse_bbox(&values).map(Some)
+        },
+        None => Ok(None),
+    }
+}
+
+fn extent_json(extent: &Extent) -> Value {
+    json!({
+        "count": extent.count,
+        "geocode": extent.geocode,
+        "platform": extent.platform,
+        "precision": extent.precision,
+        "source": extent.source,
+        "timestamp": extent.timestamp,
+    })
+}
+
+// parse filter fields named as in the stip cli
+fn filter(params: &HashMap<String, String>) -> Result<Filter, String> {
+    fn parse<T: FromStr>(params: &HashMap<String, String>, key: &str)
+            -> Result<Option<T>, String> {
+        params.get(key).map(|x| x.parse::<T>()
+                .map_err(|_| format!("invalid {} '{}'", key, x)))
+            .transpose()
+    }
+
+    Ok(Filter {
+        end_timestamp: parse(params, "end_timestamp")?,
+        geocode: params.get("geocode").cloned(),
+        max_cloud_coverage: parse(params, "max_cloud_coverage")?,
+        min_pixel_coverage: parse(params, "min_pixel_coverage")?,
+        name: params.get("filter").cloned(),
+        platform: params.get("platform").cloned(),
+        recurse: parse(params, "recurse")?.unwrap_or(false),
+        source: params.get("source").cloned(),
+        start_timestamp: parse(params, "start_timestamp")?,
+    })
+}
+
+fn image_json(node_id: u32, image: &Image) -> Value {
+    json!({
+        "album": image.album,
+        "cloud_coverage": image.cloud_coverage,
+        "files": image.files.iter().map(|x| json!({
+            "path": x.path,
+            "pixel_coverage": x.pixel_coverage,
+            "subdataset": x.subdataset,
+        })).collect::<Vec<Value>>(),
+        "geocode": image.geocode,
+        "node": node_id,
+        "platform": image.platform,
+        "source": image.source,
+        "timestamp": image.timestamp,
+    })
+}
+
+fn invalid_task_id(id: &str) -> HttpResponse {
+    HttpResponse::error(400, &format!("invalid task id '{}'", id))
+}
+
+// replicas of a geocode hold the same images, so take the largest node
+//   count of each full geocode before summing them by geocode prefix
+fn merge_extents(extents: Vec<Extent>, length: usize) -> Vec<Extent> {
+    let mut replica_map = BTreeMap::new();
+    for extent in extents.into_iter() {
+        let replica = replica_map.entry((extent.platform.clone(),
+                extent.geocode.clone(), extent.source.clone(),
+                extent.precision, extent.timestamp))
+            .or_insert(extent.clone());
+        replica.count = std::cmp::max(replica.count, extent.count);
+    }
+
+    let mut prefix_map: BTreeMap<_, Extent> = BTreeMap::new();
+    for (_, mut extent) in replica_map.into_iter() {
+        extent.geocode = extent.geocode.chars().take(length).collect();
+        let key = (extent.platform.clone(), extent.geocode.clone(),
+            extent.source.clone(), extent.precision, extent.timestamp);
+        match prefix_map.get_mut(&key) {
+            Some(prefix) => prefix.count += extent.count,
+            None => {
+                prefix_map.insert(key, extent);
+            },
+        }
+    }
+
+    prefix_map.into_iter().map(|(_, extent)| extent).collect()
+}
+
+fn node_error(node_id: u32, error: &str) -> Value {
+    json!({
+        "error": error,
+        "node": node_id,
+    })
+}
+
+fn task_broadcast(message_type: TaskBroadcastType) -> TaskBroadcastRequest {
+    TaskBroadcastRequest {
+        message_type: message_type as i32,
+        clear_request: None,
+        list_request: None,
+        cancel_request: None,
+        pause_request: None,
+        resume_request: None,
+        retry_request: None,
+        errors_request: None,
+    }
+}
@@ -0,0 +1,239 @@
+use serde_json::{json, Value};
+use tonic::{Code, Status};
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::Arc;
+
+const MAX_BODY_LENGTH: usize = 1048576;
+
+pub struct HttpRequest {
+    pub body: Vec<u8>,
+    pub headers: HashMap<String, String>,
+    pub method: String,
+    pub path: String,
+    pub query: HashMap<String, String>,
+}
+
+impl HttpRequest {
+    // base url of the server as addressed by the client
+    pub fn base_url(&self) -> String {
+        format!("{}://{}", scheme(), self.headers.get("host")
+            .map(|x| x.as_str()).unwrap_or("localhost"))
+    }
+
+    // token of an 'authorization: Bearer <token>' header
+    pub fn bearer(&self) -> Option<String> {
+        self.headers.get("authorization")
+            .and_then(|x| crate::auth::parse_bearer(x))
+    }
+}
+
+pub struct HttpResponse {
+    pub body: Vec<u8>,
+    pub content_type: String,
+    pub status: u16,
+}
+
+impl HttpResponse {
+    pub fn error(status: u16, description: &str) -> HttpResponse {
+        HttpResponse::json(status, &json!({
+            "code": reason(status),
+            "description": description,
+        }))
+    }
+
+    // error response with the http status matching an rpc status
+    pub fn from_status(status: &Status) -> HttpResponse {
+        let code = match status.code() {
+            Code::InvalidArgument | Code::FailedPrecondition => 400,
+            Code::Unauthenticated => 401,
+            Code::PermissionDenied => 403,
+            Code::NotFound => 404,
+            Code::Unavailable => 503,
+            _ => 500,
+        };
+
+        HttpResponse::error(code, status.message())
+    }
+
+    pub fn json(status: u16, value: &Value) -> HttpResponse {
+        HttpResponse {
+            body: value.to_string().into_bytes(),
+            content_type: "application/json".to_string(),
+            status: status,
+        }
+    }
+}
+
+// serve each connection on a separate thread, one request per connection
+pub fn start<F>(address: &str, handler: F) -> Result<(), Box<dyn Error>>
+        where F: Fn(&HttpRequest) -> HttpResponse + Send + Sync + 'static {
+    let listener = TcpListener::bind(address)?;
+    let handler = Arc::new(handler);
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let stream = match stream {
+                Ok(stream) => stream,
+                Err(e) => {
+                    warn!("failed to accept http connection: {}", e);
+                    continue;
+                },
+            };
+
+            let handler = handler.clone();
+            std::thread::spawn(move || {
+                if let Err(e) = handle(stream, handler.as_ref()) {
+                    debug!("failed to handle http connection: {}", e);
+                }
+            });
+        }
+    });
+
+    Ok(())
+}
+
+// url scheme of the http servers, which are wrapped with tls when the
+//   node is configured with it
+pub fn scheme() -> &'static str {
+    match crate::tls::is_enabled() {
+        true => "https",
+        false => "http",
+    }
+}
+
+fn handle<F>(mut stream: TcpStream, handler: &F)
+        -> Result<(), Box<dyn Error>>
+        where F: Fn(&HttpRequest) -> HttpResponse {
+    let mut session = match crate::tls::http_session() {
+        Some(session) => session,
+        None => return serve(&mut stream, handler),
+    };
+
+    // wrap connection with tls when configured
+    serve(&mut rustls::Stream::new(&mut session, &mut stream), handler)?;
+    crate::tls::shutdown(&mut session, &mut stream)?;
+    Ok(())
+}
+
+fn serve<T: Read + Write, F>(stream: &mut T, handler: &F)
+        -> Result<(), Box<dyn Error>>
+        where F: Fn(&HttpRequest) -> HttpResponse {
+    // parse request line
+    let mut reader = BufReader::new(&mut *stream);
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+
+    let mut fields = line.split_whitespace();
+    let (method, target) = match (fields.next(), fields.next()) {
+        (Some(method), Some(target)) =>
+            (method.to_uppercase(), target.to_string()),
+        _ => return Err("malformed http request line".into()),
+    };
+
+    // parse headers
+    let mut headers = HashMap::new();
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            break;
+        }
+
+        let header = line.trim_end();
+        if header.is_empty() {
+            break;
+        }
+
+        if let Some(index) = header.find(':') {
+            headers.insert(header[..index].trim().to_lowercase(),
+                header[index + 1..].trim().to_string());
+        }
+    }
+
+    // read body
+    let length = headers.get("content-length")
+        .and_then(|x| x.parse::<usize>().ok()).unwrap_or(0);
+    let response = match length > MAX_BODY_LENGTH {
+        true => HttpResponse::error(413, "request body too large"),
+        false => {
+            let mut body = vec![0u8; length];
+            reader.read_exact(&mut body)?;
+
+            let (path, query) = match target.find('?') {
+                Some(index) => (decode(&target[..index]),
+                    parse_query(&target[index + 1..])),
+                None => (decode(&target), HashMap::new()),
+            };
+
+            handler(&HttpRequest {
+                body: body,
+                headers: headers,
+                method: method,
+                path: path,
+                query: query,
+            })
+        },
+    };
+
+    // write response
+    drop(reader);
+    write!(stream, "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nAccess-Control-Allow-Origin: *\r\nConnection: close\r\n\r\n",
+        response.status, reason(response.status),
+        response.content_type, response.body.len())?;
+    stream.write_all(&response.body)?;
+    stream.flush()?;
+    Ok(())
+}
+
+pub fn parse_query(query: &str) -> HashMap<String, String> {
+    query.split('&').filter(|x| !x.is_empty()).map(|x| {
+        match x.find('=') {
+            Some(index) => (decode(&x[..index]), decode(&x[index + 1..])),
+            None => (decode(x), String::new()),
+        }
+    }).collect()
+}
+
+// percent decode a url component
+fn decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut buf = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'%' if i + 2 < bytes.len() => {
+                match std::str::from_utf8(&bytes[i + 1..i + 3]).ok()
+                        .and_then(|x| u8::from_str_radix(x, 16).ok()) {
+                    Some(byte) => {
+                        buf.push(byte);
+                        i += 3;
+                        continue;
+                    },
+                    None => buf.push(b'%'),
+                }
+            },
+            b'+' => buf.push(b' '),
+            byte => buf.push(byte),
+        }
+
+        i += 1;
+    }
+
+    String::from_utf8_lossy(&buf).to_string()
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        413 => "Payload Too Large",
+        503 => "Service Unavailable",
+        _ => "Internal Server Error",
+    }
+}
@@ -1,10 +1,18 @@
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime};
 use rusqlite::{Connection, ToSql};
 
 use crate::{Extent, Image, StFile};
 use crate::album::Album;
+use crate::raster::Statistics;
 
 use std::error::Error;
+use std::path::PathBuf;
+use std::str::FromStr;
 use std::sync::Mutex;
+use std::sync::atomic::{AtomicI64, AtomicU64, Ordering};
+
+// timestamp, geocode, tile, and source of the last image listed
+pub type ListCursor = (i64, String, String, String);
 
 const CREATE_FILES_TABLE_STMT: &str =
 "CREATE TABLE files (
@@ -24,8 +32,61 @@ const CREATE_IMAGES_TABLE_STMT: &str =
     timestamp       BIGINT NOT NULL
 )";
 
-//const CREATE_INDEX_STMT: &str =
-//"CREATE INDEX idx_images ON images(platform, pixel_coverage)";
+const CREATE_MISALIGNMENTS_TABLE_STMT: &str =
+"CREATE TABLE misalignments (
+    image_id        BIGINT NOT NULL,
+    subdataset      TINYINT NOT NULL,
+    reference       TEXT NOT NULL
+)";
+
+const CREATE_STATISTICS_TABLE_STMT: &str =
+"CREATE TABLE statistics (
+    image_id        BIGINT NOT NULL,
+    subdataset      TINYINT NOT NULL,
+    band            INTEGER NOT NULL,
+    minimum         FLOAT NOT NULL,
+    maximum         FLOAT NOT NULL,
+    mean            FLOAT NOT NULL,
+    stddev          FLOAT NOT NULL,
+    histogram       TEXT NOT NULL
+)";
+
+// indices keep lookups, filters, and joins independent of tile count
+const CREATE_INDEX_STMTS: &[&str] = &[
+    "CREATE INDEX idx_images_geocode ON images(geocode, tile, source)",
+    "CREATE INDEX idx_images_timestamp ON images(timestamp)",
+    "CREATE INDEX idx_files_image ON files(image_id, subdataset)",
+    "CREATE INDEX idx_misalignments_image ON misalignments(image_id, subdataset)",
+    "CREATE INDEX idx_statistics_image ON statistics(image_id, subdataset)",
+];
+
+const DISTINCT_GEOCODES_STMT: &str =
+"SELECT DISTINCT SUBSTR(geocode, 1, ?1) FROM images ORDER BY 1";
+
+const DISTINCT_PLATFORMS_STMT: &str =
+"SELECT DISTINCT platform FROM images ORDER BY platform";
+
+const DISTINCT_SOURCES_STMT: &str =
+"SELECT DISTINCT source FROM images ORDER BY source";
+
+const DISTINCT_SUBDATASETS_STMT: &str =
+"SELECT DISTINCT subdataset FROM files ORDER BY subdataset";
+
+const DELETE_FILES_STMT: &str =
+"DELETE FROM files WHERE image_id = ?1 AND subdataset = ?2";
+
+const DELETE_IMAGES_STMT: &str =
+"DELETE FROM images WHERE id = ?1
+    AND NOT EXISTS (SELECT 1 FROM files WHERE image_id = ?1)";
+
+const DELETE_MISALIGNMENTS_STMT: &str =
+"DELETE FROM misalignments WHERE image_id = ?1 AND subdataset = ?2";
+
+const DELETE_STATISTICS_STMT: &str =
+"DELETE FROM statistics WHERE image_id = ?1 AND subdataset = ?2";
+
+const FILE_COUNT_STMT: &str =
+"SELECT COUNT(*) FROM files WHERE image_id = ?1 AND subdataset = ?2";
 
 const INSERT_FILES_STMT: &str =
 "INSERT INTO files (image_id, pixel_coverage, subdataset)
@@ -36,29 +97,143 @@ const INSERT_IMAGES_STMT: &str =
     id, platform, source, tile, timestamp)
 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)";
 
+const INSERT_MISALIGNMENTS_STMT: &str =
+"INSERT INTO misalignments (image_id, subdataset, reference)
+VALUES (?1, ?2, ?3)";
+
+const INSERT_STATISTICS_STMT: &str =
+"INSERT INTO statistics (image_id, subdataset, band,
+    minimum, maximum, mean, stddev, histogram)
+VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)";
+
+const IMAGE_COUNT_STMT: &str =
+"SELECT COUNT(*) FROM images";
+
 const ID_SELECT_STMT: &str =
 "SELECT id from images WHERE geocode = ?1 AND tile = ?2 AND source = ?3";
 
+const MAX_ID_SELECT_STMT: &str =
+"SELECT MAX(id) FROM images";
+
+const MISALIGNMENT_SELECT_STMT: &str =
+"SELECT reference
+FROM misalignments JOIN images ON images.id = misalignments.image_id
+WHERE images.geocode = ?1 AND images.source = ?2
+    AND images.tile = ?3 AND misalignments.subdataset = ?4";
+
+const REFERENCE_SELECT_STMT: &str =
+"SELECT source, tile, subdataset
+FROM images JOIN files ON images.id = files.image_id
+WHERE geocode = ?1 AND platform = ?2 AND timestamp = ?3 AND source != ?4
+ORDER BY source, tile, subdataset";
+
 const LIST_SELECT_STMT: &str =
 "SELECT cloud_coverage, geocode, pixel_coverage,
     platform, source, subdataset, tile, timestamp
 FROM images JOIN files ON images.id = files.image_id";
 
 const LIST_ORDER_BY_STMT: &str =
-" ORDER BY images.timestamp, images.geocode, images.tile, files.subdataset";
+" ORDER BY images.timestamp, images.geocode, images.tile, images.source, files.subdataset";
 
 const SEARCH_SELECT_STMT: &str =
-"SELECT COUNT(*) as count, SUBSTR(geocode, 0, REPLACE_LENGTH) as geocode_search, platform, LENGTH(geocode) as precision, source
-FROM (SELECT DISTINCT geocode, platform, source, tile
+"SELECT COUNT(*) as count, SUBSTR(geocode, 0, REPLACE_LENGTH) as geocode_search, platform, LENGTH(geocode) as precision, source, REPLACE_INTERVAL as interval_start
+FROM (SELECT DISTINCT geocode, platform, source, tile, timestamp
     FROM images
     JOIN files ON images.id = files.image_id";
 
 const SEARCH_GROUP_BY_STMT: &str =
-" ) GROUP BY geocode_search, platform, precision, source";
+" ) GROUP BY geocode_search, platform, precision, source, interval_start";
+
+const STATISTICS_SELECT_STMT: &str =
+"SELECT band, minimum, maximum, mean, stddev, histogram
+FROM statistics JOIN images ON images.id = statistics.image_id
+WHERE images.geocode = ?1 AND images.source = ?2
+    AND images.tile = ?3 AND statistics.subdataset = ?4
+ORDER BY band";
+
+const UPDATE_IMAGES_STMT: &str =
+"UPDATE images SET cloud_coverage = COALESCE(?1, cloud_coverage),
+    timestamp = COALESCE(?2, timestamp)
+WHERE geocode = ?3 AND tile = ?4 AND source = ?5";
+
+const SNAPSHOT_ATTACH_STMT: &str = "ATTACH DATABASE ?1 AS snapshot";
+const SNAPSHOT_DETACH_STMT: &str = "DETACH DATABASE snapshot";
+const SNAPSHOT_FILES_STMT: &str =
+"INSERT INTO files SELECT * FROM snapshot.files";
+const SNAPSHOT_IMAGES_STMT: &str =
+"INSERT INTO images SELECT * FROM snapshot.images";
+const SNAPSHOT_MISALIGNMENTS_STMT: &str =
+"INSERT INTO misalignments SELECT * FROM snapshot.misalignments";
+const SNAPSHOT_STATISTICS_STMT: &str =
+"INSERT INTO statistics SELECT * FROM snapshot.statistics";
+const SNAPSHOT_TABLE_COUNT_STMT: &str =
+"SELECT COUNT(*) FROM snapshot.sqlite_master WHERE type = 'table' AND name = ?1";
+const SNAPSHOT_WRITE_STMT: &str = "VACUUM INTO ?1";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TimeInterval {
+    Day,
+    Month,
+    Seconds(i64),
+    Week,
+    Year,
+}
+
+impl TimeInterval {
+    // sqlite expression computing the interval start of 'timestamp'
+    fn sql(&self) -> String {
+        let modifiers = match self {
+            TimeInterval::Day => "'start of day'",
+            TimeInterval::Month => "'start of month'",
+            TimeInterval::Seconds(seconds) =>
+                return format!("(timestamp / {}) * {}", seconds, seconds),
+            TimeInterval::Week => "'weekday 0', '-6 days', 'start of day'",
+            TimeInterval::Year => "'start of year'",
+        };
+
+        format!("CAST(STRFTIME('%s', timestamp, 'unixepoch', {}) AS INTEGER)",
+            modifiers)
+    }
+
+    // interval start of a timestamp, weeks begin on monday
+    pub fn start(&self, timestamp: i64) -> i64 {
+        let date = NaiveDateTime::from_timestamp(timestamp, 0).date();
+        let date = match self {
+            TimeInterval::Day => date,
+            TimeInterval::Month =>
+                NaiveDate::from_ymd(date.year(), date.month(), 1),
+            TimeInterval::Seconds(seconds) =>
+                return (timestamp / seconds) * seconds,
+            TimeInterval::Week => date - Duration::days(
+                date.weekday().num_days_from_monday() as i64),
+            TimeInterval::Year => NaiveDate::from_ymd(date.year(), 1, 1),
+        };
+
+        date.and_hms(0, 0, 0).timestamp()
+    }
+}
+
+impl FromStr for TimeInterval {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "day" => Ok(TimeInterval::Day),
+            "month" => Ok(TimeInterval::Month),
+            "week" => Ok(TimeInterval::Week),
+            "year" => Ok(TimeInterval::Year),
+            x => match x.parse::<i64>() {
+                Ok(seconds) if seconds > 0 => Ok(TimeInterval::Seconds(seconds)),
+                _ => Err(format!("unsupported time interval '{}'", x)),
+            },
+        }
+    }
+}
 
 pub struct AlbumIndex {
     conn: Mutex<Connection>,
-    id: i64,
+    id: AtomicI64,
+    image_count: AtomicU64,
 }
 
 impl AlbumIndex {
@@ -67,20 +242,111 @@ impl AlbumIndex {
         let conn = Connection::open_in_memory()?;
         conn.execute(CREATE_FILES_TABLE_STMT, rusqlite::params![])?;
         conn.execute(CREATE_IMAGES_TABLE_STMT, rusqlite::params![])?;
-        //conn.execute(CREATE_INDEX_STMT, rusqlite::params![])?;
+        conn.execute(CREATE_MISALIGNMENTS_TABLE_STMT, rusqlite::params![])?;
+        conn.execute(CREATE_STATISTICS_TABLE_STMT, rusqlite::params![])?;
+        for stmt in CREATE_INDEX_STMTS.iter() {
+            conn.execute(stmt, rusqlite::params![])?;
+        }
 
         Ok(AlbumIndex {
             conn: Mutex::new(conn),
-            id: 1000,
+            id: AtomicI64::new(1000),
+            image_count: AtomicU64::new(0),
         })
     }
 
+    pub fn from_snapshot(path: &PathBuf)
+            -> Result<AlbumIndex, Box<dyn Error>> {
+        let index = AlbumIndex::new()?;
+
+        {
+            // copy snapshot tables into in-memory connection
+            let conn = index.conn.lock().unwrap();
+            let path_str = path.to_string_lossy().to_string();
+            conn.execute(SNAPSHOT_ATTACH_STMT,
+                rusqlite::params![path_str])?;
+            conn.execute(SNAPSHOT_IMAGES_STMT, rusqlite::params![])?;
+            conn.execute(SNAPSHOT_FILES_STMT, rusqlite::params![])?;
+
+            // snapshots written before statistics were cached or
+            //   misalignments were indexed lack their tables
+            for (table, stmt) in &[
+                    ("misalignments", SNAPSHOT_MISALIGNMENTS_STMT),
+                    ("statistics", SNAPSHOT_STATISTICS_STMT)] {
+                let count: i64 = conn.query_row(SNAPSHOT_TABLE_COUNT_STMT,
+                    rusqlite::params![table], |row| row.get(0))?;
+                if count != 0 {
+                    conn.execute(stmt, rusqlite::params![])?;
+                }
+            }
+
+            conn.execute(SNAPSHOT_DETACH_STMT, rusqlite::params![])?;
+
+            // initialize next image id
+            let max_id: Option<i64> = conn.query_row(MAX_ID_SELECT_STMT,
+                rusqlite::params![], |row| row.get(0))?;
+            if let Some(max_id) = max_id {
+                index.id.store(max_id + 1, Ordering::SeqCst);
+            }
+
+            // later writes maintain the count incrementally
+            let count: i64 = conn.query_row(IMAGE_COUNT_STMT,
+                rusqlite::params![], |row| row.get(0))?;
+            index.image_count.store(count as u64, Ordering::SeqCst);
+        }
+
+        Ok(index)
+    }
+
+    // geocode prefixes, platforms, sources, and subdatasets indexed
+    pub fn distinct(&self, precision: usize) -> Result<(Vec<String>,
+            Vec<String>, Vec<String>, Vec<u8>), Box<dyn Error>> {
+        let conn = self.conn.lock().unwrap();
+
+        let mut stmt = conn.prepare(DISTINCT_GEOCODES_STMT)?;
+        let geocodes = stmt.query_map(rusqlite::params![precision as i64],
+            |row| row.get(0))?.collect::<Result<Vec<String>, _>>()?;
+
+        let mut stmt = conn.prepare(DISTINCT_PLATFORMS_STMT)?;
+        let platforms = stmt.query_map(rusqlite::params![],
+            |row| row.get(0))?.collect::<Result<Vec<String>, _>>()?;
+
+        let mut stmt = conn.prepare(DISTINCT_SOURCES_STMT)?;
+        let sources = stmt.query_map(rusqlite::params![],
+            |row| row.get(0))?.collect::<Result<Vec<String>, _>>()?;
+
+        let mut stmt = conn.prepare(DISTINCT_SUBDATASETS_STMT)?;
+        let subdatasets = stmt.query_map(rusqlite::params![],
+            |row| row.get(0))?.collect::<Result<Vec<u8>, _>>()?;
+
+        Ok((geocodes, platforms, sources, subdatasets))
+    }
+
+    pub fn image_count(&self) -> Result<u64, Box<dyn Error>> {
+        Ok(self.image_count.load(Ordering::SeqCst))
+    }
+
     pub fn list(&self, album: &Album, end_timestamp: &Option<i64>,
             geocode: &Option<String>, max_cloud_coverage: &Option<f64>,
             min_pixel_coverage: &Option<f64>, platform: &Option<String>,
             recurse: bool, source: &Option<String>,
             start_timestamp: &Option<i64>)
             -> Result<Vec<(Image, Vec<StFile>)>, Box<dyn Error>> {
+        self.list_page(album, end_timestamp, geocode, max_cloud_coverage,
+                min_pixel_coverage, platform, recurse, source,
+                start_timestamp, &None, None)
+            .map(|(images, _)| images)
+    }
+
+    // list images following the cursor from at most limit file rows,
+    //   returning the cursor of the last image if rows may remain
+    pub fn list_page(&self, album: &Album, end_timestamp: &Option<i64>,
+            geocode: &Option<String>, max_cloud_coverage: &Option<f64>,
+            min_pixel_coverage: &Option<f64>, platform: &Option<String>,
+            recurse: bool, source: &Option<String>,
+            start_timestamp: &Option<i64>, cursor: &Option<ListCursor>,
+            limit: Option<usize>) -> Result<(Vec<(Image, Vec<StFile>)>,
+                Option<ListCursor>), Box<dyn Error>> {
         // lock the sqlite connection
         let conn = self.conn.lock().unwrap();
 
@@ -102,20 +368,37 @@ impl AlbumIndex {
         append_stmt_filter("timestamp", start_timestamp,
             &mut stmt_str, ">=", &mut params);
 
+        // case sensitive GLOB prefixes are served by the geocode index
         let geocode_glob = match geocode {
-            Some(geocode) => Some(format!("{}%", geocode)),
+            Some(geocode) => Some(format!("{}*", geocode)),
             None => None,
         };
 
         match recurse {
             true => append_stmt_filter("geocode", &geocode_glob,
-                &mut stmt_str, "LIKE", &mut params),
+                &mut stmt_str, "GLOB", &mut params),
             false => append_stmt_filter("geocode", geocode,
                 &mut stmt_str, "=", &mut params),
         }
 
+        // resume after the last image of the previous page
+        if let Some(cursor) = cursor {
+            let index = params.len();
+            params.push(&cursor.0);
+            params.push(&cursor.1);
+            params.push(&cursor.2);
+            params.push(&cursor.3);
+
+            stmt_str.push_str(&format!(" {} (images.timestamp, images.geocode, images.tile, images.source) > (?{}, ?{}, ?{}, ?{})",
+                match index { 0 => "WHERE", _ => "AND" },
+                index + 1, index + 2, index + 3, index + 4));
+        }
+
         // append LIST_ORDER_BY_STMT to stmt_str
         stmt_str.push_str(LIST_ORDER_BY_STMT);
+        if let Some(limit) = limit {
+            stmt_str.push_str(&format!(" LIMIT {}", limit));
+        }
 
         // execute query
         let mut stmt = conn.prepare(&stmt_str)?;
@@ -138,7 +421,9 @@ impl AlbumIndex {
 
         // process images
         let mut images: Vec<(Image, Vec<StFile>)> = Vec::new();
+        let mut row_count = 0;
         for (image, file) in images_iter.map(|x| x.unwrap()) {
+            row_count += 1;
             match images.last_mut() {
                 Some((i, f)) => {
                     // if geocode, source, and tile match 
@@ -154,12 +439,23 @@ impl AlbumIndex {
             }
         }
 
-        Ok(images)
+        // a full page may end within an image, defer it to the next page
+        let mut next_cursor = None;
+        if limit.map(|x| row_count >= x).unwrap_or(false) {
+            if images.len() > 1 {
+                images.pop();
+            }
+
+            next_cursor = images.last().map(|(i, _)|
+                (i.5, i.1.clone(), i.4.clone(), i.3.clone()));
+        }
+
+        Ok((images, next_cursor))
     }
 
-    pub fn load(&mut self, cloud_coverage: Option<f64>, geocode: &str,
-            pixel_coverage: f64, platform: &str, source: &str,
-            subdataset: u8, tile: &str, timestamp: i64) 
+    pub fn load(&self, cloud_coverage: Option<f64>, geocode: &str,
+            misaligned: Option<&str>, pixel_coverage: f64, platform: &str,
+            source: &str, subdataset: u8, tile: &str, timestamp: i64)
             -> Result<(), Box<dyn Error>> {
         // load data into sqlite
         let conn = self.conn.lock().unwrap();
@@ -174,25 +470,93 @@ impl AlbumIndex {
         let id = match ids.len() {
             1 => ids[0],
             _ => {
+                let id = self.id.fetch_add(1, Ordering::SeqCst);
                 conn.execute(INSERT_IMAGES_STMT, rusqlite::params![
-                    cloud_coverage, geocode, self.id,
+                    cloud_coverage, geocode, id,
                     platform, source, tile, timestamp
                 ])?;
 
-                self.id += 1;
-                self.id - 1
+                self.image_count.fetch_add(1, Ordering::SeqCst);
+                id
             },
         };
 
+        // check if file has already been indexed
+        let count: i64 = conn.query_row(FILE_COUNT_STMT,
+            rusqlite::params![id, subdataset], |row| row.get(0))?;
+        if count != 0 {
+            return Ok(());
+        }
+
         conn.execute(INSERT_FILES_STMT, rusqlite::params![
                 id, pixel_coverage, subdataset
             ])?;
 
+        // index files which failed coregistration
+        if let Some(reference) = misaligned {
+            conn.execute(INSERT_MISALIGNMENTS_STMT, rusqlite::params![
+                    id, subdataset, reference
+                ])?;
+        }
+
         Ok(())
     }
 
-    pub fn search(&self, end_timestamp: &Option<i64>,
-            geocode: &Option<String>, max_cloud_coverage: &Option<f64>,
+    // reference image of a file which failed coregistration
+    pub fn misalignment(&self, geocode: &str, source: &str, tile: &str,
+            subdataset: u8) -> Result<Option<String>, Box<dyn Error>> {
+        let conn = self.conn.lock().unwrap();
+        match conn.query_row(MISALIGNMENT_SELECT_STMT,
+                rusqlite::params![geocode, source, tile, subdataset],
+                |row| row.get(0)) {
+            Ok(reference) => Ok(Some(reference)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub fn remove(&self, geocode: &str, source: &str, subdataset: u8,
+            tile: &str) -> Result<(), Box<dyn Error>> {
+        let conn = self.conn.lock().unwrap();
+
+        // retrieve indexed image id
+        let id: i64 = match conn.query_row(ID_SELECT_STMT,
+                rusqlite::params![geocode, tile, source], |row| row.get(0)) {
+            Ok(id) => id,
+            Err(rusqlite::Error::QueryReturnedNoRows) => return Ok(()),
+            Err(e) => return Err(e.into()),
+        };
+
+        // remove the file and the image once it holds no files
+        conn.execute(DELETE_MISALIGNMENTS_STMT,
+            rusqlite::params![id, subdataset])?;
+        conn.execute(DELETE_STATISTICS_STMT,
+            rusqlite::params![id, subdataset])?;
+        conn.execute(DELETE_FILES_STMT, rusqlite::params![id, subdataset])?;
+        let count = conn.execute(DELETE_IMAGES_STMT, rusqlite::params![id])?;
+        self.image_count.fetch_sub(count as u64, Ordering::SeqCst);
+        Ok(())
+    }
+
+    // source, tile, and subdataset of files from other sources with
+    //   the same geocode, platform, and timestamp
+    pub fn references(&self, geocode: &str, platform: &str, source: &str,
+            timestamp: i64) -> Result<Vec<(String, String, u8)>,
+            Box<dyn Error>> {
+        let conn = self.conn.lock().unwrap();
+
+        let mut stmt = conn.prepare(REFERENCE_SELECT_STMT)?;
+        let references = stmt.query_map(
+                rusqlite::params![geocode, platform, timestamp, source],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?
+            .collect::<Result<Vec<(String, String, u8)>, _>>()?;
+
+        Ok(references)
+    }
+
+    pub fn search(&self, end_timestamp: &Option<i64>, full_geocode: bool,
+            geocode: &Option<String>, interval: &Option<TimeInterval>,
+            max_cloud_coverage: &Option<f64>,
             min_pixel_coverage: &Option<f64>, platform: &Option<String>,
             recurse: bool, source: &Option<String>,
             start_timestamp: &Option<i64>)
@@ -201,13 +565,20 @@ impl AlbumIndex {
         let conn = self.conn.lock().unwrap();
  
         // initialize the SELECT command and parameters
-        let replace_length = match geocode {
-            Some(geocode) => format!("{}", geocode.len() + 2),
-            None => "2".to_string(),
+        let replace_length = match (full_geocode, geocode) {
+            (true, _) => "LENGTH(geocode) + 1".to_string(),
+            (false, Some(geocode)) => format!("{}", geocode.len() + 2),
+            (false, None) => "2".to_string(),
+        };
+
+        let replace_interval = match interval {
+            Some(interval) => interval.sql(),
+            None => "NULL".to_string(),
         };
 
         let mut stmt_str = SEARCH_SELECT_STMT
-            .replace("REPLACE_LENGTH", &replace_length);
+            .replace("REPLACE_LENGTH", &replace_length)
+            .replace("REPLACE_INTERVAL", &replace_interval);
         let mut params: Vec<&dyn ToSql> = Vec::new();
 
         // append existing filters to stmt_str
@@ -224,14 +595,15 @@ impl AlbumIndex {
         append_stmt_filter("timestamp", start_timestamp,
             &mut stmt_str, ">=", &mut params);
 
+        // case sensitive GLOB prefixes are served by the geocode index
         let geocode_glob = match geocode {
-            Some(geocode) => Some(format!("{}%", geocode)),
+            Some(geocode) => Some(format!("{}*", geocode)),
             None => None,
         };
 
         match recurse {
             true => append_stmt_filter("geocode", &geocode_glob,
-                &mut stmt_str, "LIKE", &mut params),
+                &mut stmt_str, "GLOB", &mut params),
             false => append_stmt_filter("geocode", geocode,
                 &mut stmt_str, "=", &mut params),
         }
@@ -243,7 +615,7 @@ impl AlbumIndex {
         let mut stmt = conn.prepare(&stmt_str)?;
         let extent_iter = stmt.query_map(&params, |row| {
             Ok((row.get(0)?, row.get(1)?, row.get(2)?, 
-                row.get(3)?, row.get(4)?))
+                row.get(3)?, row.get(4)?, row.get(5)?))
         })?;
 
         let extents: Vec<Extent> =
@@ -251,6 +623,96 @@ impl AlbumIndex {
 
         Ok(extents)
     }
+
+    pub fn set_statistics(&self, geocode: &str, source: &str, tile: &str,
+            subdataset: u8, statistics: &[Statistics])
+            -> Result<(), Box<dyn Error>> {
+        let conn = self.conn.lock().unwrap();
+
+        // retrieve indexed image id
+        let id: i64 = match conn.query_row(ID_SELECT_STMT,
+                rusqlite::params![geocode, tile, source], |row| row.get(0)) {
+            Ok(id) => id,
+            Err(rusqlite::Error::QueryReturnedNoRows) => return Err(format!(
+                "image '{}-{}' is not indexed [geocode={}, source={}]",
+                tile, subdataset, geocode, source).into()),
+            Err(e) => return Err(e.into()),
+        };
+
+        // replace existing statistics for the file
+        conn.execute(DELETE_STATISTICS_STMT,
+            rusqlite::params![id, subdataset])?;
+        for band_statistics in statistics.iter() {
+            let histogram = band_statistics.histogram.iter()
+                .map(|x| x.to_string()).collect::<Vec<String>>().join(",");
+            conn.execute(INSERT_STATISTICS_STMT, rusqlite::params![
+                id, subdataset, band_statistics.band as i64,
+                band_statistics.min, band_statistics.max,
+                band_statistics.mean, band_statistics.stddev, histogram
+            ])?;
+        }
+
+        Ok(())
+    }
+
+    pub fn snapshot(&self, path: &PathBuf) -> Result<(), Box<dyn Error>> {
+        // hold the connection until the rename so concurrent snapshots
+        //   do not share the temporary file
+        let conn = self.conn.lock().unwrap();
+
+        // write snapshot to temporary file
+        let tmp_path = path.with_extension("tmp");
+        if tmp_path.exists() {
+            std::fs::remove_file(&tmp_path)?;
+        }
+
+        let tmp_path_str = tmp_path.to_string_lossy().to_string();
+        conn.execute(SNAPSHOT_WRITE_STMT, rusqlite::params![tmp_path_str])?;
+
+        // replace existing snapshot
+        std::fs::rename(&tmp_path, path)?;
+        Ok(())
+    }
+
+    pub fn statistics(&self, geocode: &str, source: &str, tile: &str,
+            subdataset: u8) -> Result<Vec<Statistics>, Box<dyn Error>> {
+        let conn = self.conn.lock().unwrap();
+
+        let mut stmt = conn.prepare(STATISTICS_SELECT_STMT)?;
+        let statistics_iter = stmt.query_map(
+                rusqlite::params![geocode, source, tile, subdataset], |row| {
+            let band: i64 = row.get(0)?;
+            let histogram: String = row.get(5)?;
+            Ok(Statistics {
+                band: band as usize,
+                histogram: histogram.split(',')
+                    .filter_map(|x| x.parse::<u64>().ok()).collect(),
+                max: row.get(2)?,
+                mean: row.get(3)?,
+                min: row.get(1)?,
+                stddev: row.get(4)?,
+            })
+        })?;
+
+        let mut statistics = Vec::new();
+        for band_statistics in statistics_iter {
+            statistics.push(band_statistics?);
+        }
+
+        Ok(statistics)
+    }
+
+    // overwrite provided metadata values of an image
+    pub fn update(&self, geocode: &str, source: &str, tile: &str,
+            cloud_coverage: Option<f64>, timestamp: Option<i64>)
+            -> Result<(), Box<dyn Error>> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(UPDATE_IMAGES_STMT, rusqlite::params![
+            cloud_coverage, timestamp, geocode, tile, source
+        ])?;
+
+        Ok(())
+    }
 }
 
 fn append_stmt_filter<'a, T: ToSql>(feature: &str, filter: &'a Option<T>,
@@ -265,3 +727,61 @@ fn append_stmt_filter<'a, T: ToSql>(feature: &str, filter: &'a Option<T>,
         stmt.push_str(&filter_str);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use geocode::Geocode;
+
+    use crate::album::{AlbumManager, Coregistration};
+
+    #[test]
+    fn list_page_cursor() {
+        let directory = std::env::temp_dir().join(
+            format!("stip-index-{}", rand::random::<u64>()));
+        std::fs::create_dir_all(&directory).unwrap();
+
+        let mut album_manager = AlbumManager::new(directory.clone(),
+            Coregistration::Flag).unwrap();
+        album_manager.create(Vec::new(), 3,
+            Geocode::Geohash, "test", None).unwrap();
+        let album = album_manager.get("test").unwrap().clone();
+        let mut album = album.write().unwrap();
+        album.open(false).unwrap();
+
+        // images of two files each, so pages may end within an image
+        for i in 0..5 {
+            for subdataset in 0..2 {
+                album.load(None, "9xj", None, 1.0, "Sentinel-2", "raw",
+                    subdataset, &format!("T{}", i), 1000 + i).unwrap();
+            }
+        }
+
+        // follow cursors until the final page
+        let list = |start_timestamp: Option<i64>| {
+            let (mut tiles, mut cursor) = (Vec::new(), None);
+            loop {
+                let (images, next_cursor) = album.list_page(&None, &None,
+                    &None, &None, &None, false, &None, &start_timestamp,
+                    &cursor, 3).unwrap();
+                for (image, files) in images {
+                    assert_eq!(files.len(), 2);
+                    tiles.push(image.4);
+                }
+
+                match next_cursor {
+                    Some(next_cursor) => cursor = Some(next_cursor),
+                    None => return tiles,
+                }
+            }
+        };
+
+        // every image is listed once, in order, with all of its files
+        assert_eq!(list(None), vec!["T0", "T1", "T2", "T3", "T4"]);
+
+        // cursors combine with filters
+        assert_eq!(list(Some(1002)), vec!["T2", "T3", "T4"]);
+
+        drop(album);
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
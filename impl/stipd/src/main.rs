@@ -2,35 +2,87 @@
 extern crate log;
 
 use comm::Server as CommServer;
-use protobuf::{ImageManagementServer, AlbumManagementServer, NodeManagementServer, TaskManagementServer};
+use protobuf::{ImageManagementServer, AlbumManagementServer, FilterManagementServer, NodeManagementServer, TaskManagementServer};
+use protobuf::health::HealthServer;
+use protobuf::reflection::ServerReflectionServer;
 use structopt::StructOpt;
 use swarm::prelude::{DhtBuilder, Swarm};
+use tonic::codegen::Service;
 use tonic::transport::Server;
 
+mod accounting;
+use accounting::UsageLedger;
+mod admin;
+use admin::AdminServer;
 mod album;
-use album::AlbumManager;
+use album::{Album, AlbumManager, Coregistration};
+mod audit;
+mod auth;
+mod cluster;
+use cluster::{ClusterConfig, HashFunction};
+mod compaction;
+mod config;
+use config::ClusterDefinition;
+mod coverage;
+mod event;
+mod expression;
+mod fill;
+mod filter;
+use filter::FilterStore;
+mod gateway;
+use gateway::GatewayServer;
+mod http;
 mod index;
+mod mask;
+mod metrics;
+use metrics::MetricsServer;
+mod mosaic;
+mod quicklook;
+use quicklook::QuicklookFormat;
+mod raster;
+mod slowlog;
+mod spatial;
+mod stac;
+use stac::StacServer;
 mod task;
 use task::TaskManager;
+use task::logger::LogFormat;
 mod rpc;
 use rpc::album::AlbumManagementImpl;
+use rpc::filter::FilterManagementImpl;
+use rpc::health::HealthImpl;
 use rpc::image::ImageManagementImpl;
 use rpc::node::NodeManagementImpl;
+use rpc::reflection::ServerReflectionImpl;
 use rpc::task::TaskManagementImpl;
+mod telemetry;
+use telemetry::{Span, SpanContext, SpanKind};
+mod tls;
 mod transfer;
-use transfer::TransferStreamHandler;
+use transfer::{Compression, TransferStreamHandler};
+mod zonal;
 
 use std::net::{IpAddr, SocketAddr, TcpListener};
 use std::path::PathBuf;
 use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
 //use std::thread;
 
+pub const COMPOSITE_SOURCE_PREFIX: &'static str = "composite-";
+pub const DERIVED_SOURCE_PREFIX: &'static str = "derived-";
 pub const FILLED_SOURCE: &'static str = "filled";
+pub const OVERVIEW_SOURCE_PREFIX: &'static str = "overview-";
 pub const RAW_SOURCE: &'static str = "raw";
+pub const REPROJECTED_SOURCE_PREFIX: &'static str = "reprojected-";
 pub const SPLIT_SOURCE: &'static str = "split";
 
+const ADMIN_SOCKET: &'static str = "stipd.sock";
+const FILTER_STORE: &'static str = "filters.json";
+const USAGE_LEDGER: &'static str = "usage.json";
+const SCHEDULE_INTERVAL: Duration = Duration::from_millis(1000);
+
 // count, geocode, platform, precision, source
-pub type Extent = (i64, String, String, u8, String);
+pub type Extent = (i64, String, String, u8, String, Option<i64>);
 
 // cloud_coverage, geocode, platform, source, tile, timestamp
 pub type Image = (Option<f64>, String, String, String, String, i64);
@@ -40,7 +92,9 @@ pub type StFile = (String, f64, u8);
 
 fn main() {
     // initilaize logger
-    env_logger::init();
+    if let Err(e) = task::logger::init() {
+        panic!("failed to initialize logger: {}", e);
+    }
 
     unsafe {
         // disable printing gdal errors to stdout
@@ -48,11 +102,123 @@ fn main() {
             Some(gdal_sys::CPLQuietErrorHandler));
     }
 
+    // report status of a local daemon
+    if std::env::args().nth(1).as_deref() == Some("status") {
+        let status_opt = StatusOpt::from_iter(std::env::args().skip(1));
+        let path = status_opt.admin_socket
+            .unwrap_or(status_opt.directory.join(ADMIN_SOCKET));
+        std::process::exit(admin::status(&path));
+    }
+
     // parse arguments
-    let opt = Opt::from_args();
+    let mut opt = Opt::from_args();
+    task::logger::configure(opt.log_format, opt.node_id);
+
+    // apply declarative cluster configuration
+    let cluster_definition = match &opt.config {
+        Some(path) => match ClusterDefinition::load(path) {
+            Ok(cluster_definition) => Some(cluster_definition),
+            Err(e) => panic!("failed to load cluster config {:?}: {}",
+                path, e),
+        },
+        None => None,
+    };
+
+    if let Some(cluster_definition) = &cluster_definition {
+        apply_definition(&mut opt, cluster_definition);
+    }
+
+    let directory = match &opt.directory {
+        Some(directory) => directory.clone(),
+        None => panic!("no storage directory defined for node {}",
+            opt.node_id),
+    };
+
+    // create storage directory
+    if let Err(e) = std::fs::create_dir_all(&directory) {
+        panic!("failed to create storage directory '{:?}': {}",
+            directory, e);
+    }
+
+    // record mutating rpcs in the storage directory
+    if let Err(e) = audit::configure(&directory) {
+        panic!("failed to open audit log: {}", e);
+    }
+
+    if let Err(e) = slowlog::configure(&directory, opt.slow_op_threshold_ms) {
+        panic!("failed to open slow operation log: {}", e);
+    }
+
+    // initialize cluster configuration
+    let cluster_config = match ClusterConfig::load(&directory,
+            opt.hash_function, opt.replication_factor,
+            opt.virtual_node_count) {
+        Ok(cluster_config) => cluster_config,
+        Err(e) => panic!("initialize cluster configuration failed: {}", e),
+    };
+
+    cluster_config.install();
+
+    if let Err(e) = cluster::configure_placement(opt.disk_high_watermark,
+            opt.placement_fallback) {
+        panic!("invalid placement configuration: {}", e);
+    }
+
+    album::configure_coregistration(opt.coregistration_sources.clone());
+
+    if let Err(e) = quicklook::configure(opt.quicklook_bands.clone(),
+            opt.quicklook_format, opt.quicklook_size) {
+        panic!("invalid quicklook configuration: {}", e);
+    }
+
+    // initialize tls for rpc and transfer connections
+    if let (Some(cert), Some(key)) = (&opt.tls_cert, &opt.tls_key) {
+        if let Err(e) = tls::configure(cert, key, &opt.tls_ca,
+                opt.tls_client_auth, &opt.tls_domain) {
+            panic!("failed to configure tls: {}", e);
+        }
+    } else if opt.tls_cert.is_some() || opt.tls_key.is_some() {
+        panic!("tls requires both a certificate and private key");
+    }
+
+    // initialize token authentication for rpc and transfer connections
+    if let Some(credentials) = &opt.credentials {
+        if let Err(e) = auth::configure(credentials) {
+            panic!("failed to load credentials {:?}: {}", credentials, e);
+        }
+    }
+
+    transfer::configure_compression(opt.transfer_compression);
+    match opt.transfer_rate_limit.checked_mul(1024 * 1024) {
+        Some(rate_limit) => transfer::configure_rate_limit(rate_limit),
+        None => panic!("transfer rate limit {} MiB/s is too large",
+            opt.transfer_rate_limit),
+    }
+
+    // export tracing spans to an otlp collector
+    if let Some(endpoint) = &opt.otlp_endpoint {
+        if let Err(e) = telemetry::configure(endpoint, opt.node_id) {
+            panic!("failed to configure tracing: {}", e);
+        }
+
+        protobuf::set_header_fn(telemetry::inject);
+    }
+
+    if let Err(e) = cluster::load_decommissioned(&directory,
+            opt.node_id) {
+        panic!("failed to load decommissioned nodes: {}", e);
+    }
 
     // initialize topology builder
-    let dht_builder = DhtBuilder::new(opt.tokens);
+    let tokens = match cluster_config.load_tokens(&directory,
+            opt.node_id, &opt.tokens) {
+        Ok(tokens) => tokens,
+        Err(e) => panic!("failed to load dht tokens: {}", e),
+    };
+
+    let tokens_metadata = tokens.iter().map(|x| x.to_string())
+        .collect::<Vec<String>>().join(",");
+    let dht_builder = DhtBuilder::new(tokens);
 
     // initialize swarm
     let seed_address = match opt.seed_ip_addr {
@@ -66,24 +232,193 @@ fn main() {
     // set swarm instance metadata
     swarm.set_metadata("rpc_port", &opt.rpc_port.to_string());
     swarm.set_metadata("xfer_port", &opt.xfer_port.to_string());
+    swarm.set_metadata("xfer_version",
+        &transfer::PROTOCOL_VERSION.to_string());
+    if let Some(stac_port) = opt.stac_port {
+        swarm.set_metadata("stac_port", &stac_port.to_string());
+    }
+
+    // advertise tokens so replica sets can walk the ring
+    swarm.set_metadata("tokens", &tokens_metadata);
+
+    swarm.set_metadata("hash_function",
+        &format!("{:?}", cluster_config.hash_function));
 
     // start swarm
     swarm.start(2, 50, 2000).expect("swarm start");
 
-    // create storage directory
-    if let Err(e) = std::fs::create_dir_all(&opt.directory) {
-        panic!("failed to create storage directory '{:?}': {}",
-            opt.directory, e);
+    // refuse to join a cluster placing data with another hash function
+    if seed_address.is_some() {
+        if let Err(e) = cluster::verify_hash_function(&dht,
+                opt.node_id, cluster_config.hash_function) {
+            panic!("failed to join cluster: {}", e);
+        }
     }
 
+    // resolve transfer protocol versions advertised by peers
+    transfer::configure_peers(dht.clone());
+
+    // initialize usage ledger
+    let ledger = match UsageLedger::new(directory.join(USAGE_LEDGER)) {
+        Ok(ledger) => Arc::new(ledger),
+        Err(e) => panic!("initialize usage ledger failed: {}", e),
+    };
+
+    // initialize saved filters
+    let filter_store = match FilterStore::new(directory.join(FILTER_STORE)) {
+        Ok(filter_store) => Arc::new(filter_store),
+        Err(e) => panic!("initialize filter store failed: {}", e),
+    };
+
     // initialize AlbumManager and TaskManager
-    let album_manager = match AlbumManager::new(opt.directory.clone()) {
+    let album_manager = match AlbumManager::new(
+            directory.clone(), opt.coregistration) {
         Ok(album_manager) => album_manager,
         Err(e) => panic!("initialize AlbumManager failed: {}", e),
     };
 
+    // create albums defined in the cluster config
+    if let Some(cluster_definition) = &cluster_definition {
+        for album in cluster_definition.albums.iter() {
+            if album_manager.get(&album.id).is_some() {
+                continue;
+            }
+
+            if let Err(e) = album_manager.create(
+                    album.creation_options.clone(), album.dht_key_length,
+                    album.geocode.clone(), &album.id, album.precision) {
+                panic!("failed to create album '{}': {}", album.id, e);
+            }
+        }
+    }
+
     let album_manager = Arc::new(RwLock::new(album_manager));
-    let task_manager = Arc::new(RwLock::new(TaskManager::new()));
+
+    // task records are processed on workers shared across tasks
+    task::pool::start(opt.worker_threads);
+
+    let mut task_directory = directory.clone();
+    task_directory.push("tasks");
+    let task_manager = match TaskManager::new(task_directory,
+            ledger.clone(), opt.max_running_tasks,
+            opt.task_retention_count) {
+        Ok(task_manager) => task_manager,
+        Err(e) => panic!("initialize TaskManager failed: {}", e),
+    };
+
+    let task_manager = Arc::new(RwLock::new(task_manager));
+
+    // recover persisted tasks
+    if let Err(e) = task::journal::recover(album_manager.clone(),
+            dht.clone(), task_manager.clone(), opt.load_thread_count) {
+        panic!("failed to recover tasks: {}", e);
+    }
+
+    // start event watcher thread
+    event::watch(dht.clone(), task_manager.clone());
+
+    // start album index snapshot thread
+    let snapshot_album_manager = album_manager.clone();
+    let snapshot_interval = Duration::from_secs(opt.snapshot_interval);
+    std::thread::spawn(move || {
+        loop {
+            std::thread::sleep(snapshot_interval);
+
+            // snapshot all open album indices, releasing the albums
+            //   while each snapshot is written
+            let albums: Vec<(String, Arc<RwLock<Album>>)> = {
+                let album_manager = snapshot_album_manager.read().unwrap();
+                album_manager.iter()
+                    .map(|(id, album)| (id.clone(), album.clone())).collect()
+            };
+
+            for (id, album) in albums {
+                let snapshot = album.read().unwrap().get_snapshot();
+                if let Some((index, path)) = snapshot {
+                    debug!("writing album index snapshot [id={}]", id);
+                    if let Err(e) = index.snapshot(&path) {
+                        warn!("failed to snapshot album '{}' index: {}",
+                            id, e);
+                    }
+                }
+            }
+        }
+    });
+
+    // start album retention garbage collection thread
+    if opt.collect_interval != 0 {
+        task::collect::watch(album_manager.clone(), dht.clone(),
+            task_manager.clone(), Duration::from_secs(opt.collect_interval),
+            opt.load_thread_count);
+    }
+
+    // start album tile compaction thread
+    if opt.compact_interval != 0 {
+        task::compact::watch(album_manager.clone(), task_manager.clone(),
+            Duration::from_secs(opt.compact_interval),
+            Duration::from_secs(opt.compact_min_age), opt.load_thread_count);
+    }
+
+    // start task scheduler thread
+    let scheduler_album_manager = album_manager.clone();
+    let scheduler_dht = dht.clone();
+    let scheduler_task_manager = task_manager.clone();
+    let scheduler_load_thread_count = opt.load_thread_count;
+    std::thread::spawn(move || {
+        loop {
+            std::thread::sleep(SCHEDULE_INTERVAL);
+
+            // start due recurring tasks
+            task::start_recurring(&scheduler_album_manager, &scheduler_dht,
+                &scheduler_task_manager, scheduler_load_thread_count);
+
+            // start queued tasks as running tasks complete
+            let mut task_manager = scheduler_task_manager.write().unwrap();
+            task_manager.schedule();
+
+            // charge task usage to tenants
+            task_manager.account();
+        }
+    });
+
+    // start admin server
+    let admin_socket = opt.admin_socket.clone()
+        .unwrap_or(directory.join(ADMIN_SOCKET));
+    let admin_server = AdminServer::new(album_manager.clone(), dht.clone(),
+        directory.clone(), opt.node_id, task_manager.clone());
+    if let Err(e) = admin_server.start(&admin_socket) {
+        panic!("failed to start admin server: {}", e);
+    }
+
+    // start stac api server
+    if let Some(stac_port) = opt.stac_port {
+        let stac_server = StacServer::new(album_manager.clone(), dht.clone());
+        if let Err(e) = stac_server.start(
+                &format!("{}:{}", opt.ip_addr, stac_port)) {
+            panic!("failed to start stac server: {}", e);
+        }
+    }
+
+    // start http json gateway server
+    if let Some(gateway_port) = opt.gateway_port {
+        let result = GatewayServer::new(dht.clone(),
+                format!("{}:{}", opt.ip_addr, opt.rpc_port))
+            .and_then(|gateway_server| gateway_server.start(
+                &format!("{}:{}", opt.ip_addr, gateway_port)));
+        if let Err(e) = result {
+            panic!("failed to start gateway server: {}", e);
+        }
+    }
+
+    // start prometheus metrics server
+    if let Some(metrics_port) = opt.metrics_port {
+        let metrics_server = MetricsServer::new(directory.clone(),
+            task_manager.clone());
+        if let Err(e) = metrics_server.start(
+                &format!("{}:{}", opt.ip_addr, metrics_port)) {
+            panic!("failed to start metrics server: {}", e);
+        }
+    }
 
     // start transfer server
     debug!("binding xfer server [address={}:{}]",
@@ -92,7 +427,8 @@ fn main() {
     let listener = TcpListener::bind(format!("{}:{}",
         opt.ip_addr, opt.xfer_port)).expect("xfer service bind");
     let transfer_stream_handler =
-        Arc::new(TransferStreamHandler::new(album_manager.clone()));
+        Arc::new(TransferStreamHandler::new(album_manager.clone(),
+            directory.clone(), ledger.clone()));
     let mut server = CommServer::new(listener,
         50, transfer_stream_handler);
 
@@ -102,15 +438,25 @@ fn main() {
     info!("starting grpc server [address=0.0.0.0:{}]", opt.rpc_port);
     let addr = SocketAddr::new("0.0.0.0".parse().unwrap(), opt.rpc_port);
 
-    let album_management = AlbumManagementImpl::new(
+    let album_management = AlbumManagementImpl::new(opt.admin_token.clone(),
         album_manager.clone(), dht.clone(), task_manager.clone());
-    let image_management = ImageManagementImpl::new(
-        album_manager, dht.clone(), task_manager.clone());
-    let node_management = NodeManagementImpl::new(dht.clone());
+    let filter_management =
+        FilterManagementImpl::new(dht.clone(), filter_store.clone());
+    let health = HealthImpl::new(opt.node_id);
+    let image_management = ImageManagementImpl::new(album_manager.clone(),
+        dht.clone(), filter_store, task_manager.clone());
+    let node_management = NodeManagementImpl::new(album_manager,
+        dht.clone(), directory.clone(), ledger, opt.node_id,
+        task_manager.clone());
+    let server_reflection = match ServerReflectionImpl::new() {
+        Ok(server_reflection) => server_reflection,
+        Err(e) => panic!("failed to load service descriptors: {}", e),
+    };
     let task_management = TaskManagementImpl::new(dht, task_manager);
 
     if let Err(e) = start_rpc_server(addr, album_management,
-            image_management, node_management, task_management) {
+            filter_management, health, image_management, node_management,
+            server_reflection, task_management) {
         panic!("failed to start rpc server: {}", e);
     }
 
@@ -118,17 +464,98 @@ fn main() {
     //thread::park();
 }
 
+fn apply_definition(opt: &mut Opt, cluster_definition: &ClusterDefinition) {
+    // node addresses and ports in the config replace flag values
+    let node = match cluster_definition.get_node(opt.node_id) {
+        Some(node) => node,
+        None => panic!("node {} is not defined in cluster config",
+            opt.node_id),
+    };
+
+    opt.ip_addr = node.ip_address;
+    opt.gossip_port = node.gossip_port;
+    opt.rpc_port = node.rpc_port;
+    opt.xfer_port = node.xfer_port;
+
+    if opt.directory.is_none() {
+        opt.directory = node.directory.clone();
+    }
+
+    if opt.tokens.is_empty() {
+        opt.tokens = node.tokens.clone();
+    }
+
+    if opt.seed_ip_addr.is_none() {
+        if let Some((ip_addr, port)) = cluster_definition.seed(opt.node_id) {
+            opt.seed_ip_addr = Some(ip_addr);
+            opt.seed_port = port;
+        }
+    }
+
+    // explicitly provided cluster settings take precedence
+    opt.hash_function = opt.hash_function
+        .or(cluster_definition.hash_function);
+    opt.replication_factor = opt.replication_factor
+        .or(cluster_definition.replication_factor);
+    opt.virtual_node_count = opt.virtual_node_count
+        .or(cluster_definition.virtual_node_count);
+}
+
 #[tokio::main]
 async fn start_rpc_server(addr: SocketAddr, 
         album_management: AlbumManagementImpl,
+        filter_management: FilterManagementImpl,
+        health: HealthImpl,
         image_management: ImageManagementImpl,
         node_management: NodeManagementImpl,
+        server_reflection: ServerReflectionImpl,
         task_management: TaskManagementImpl)
         -> Result<(), Box<dyn std::error::Error>> {
-    Server::builder()
+    let mut server = Server::builder();
+    if let Some(tls_config) = tls::server_config() {
+        server = server.tls_config(tls_config);
+    }
+
+    server
+        .interceptor_fn(|service, request| {
+            // record latencies of each rpc method
+            let method = request.uri().path().to_string();
+            let start = Instant::now();
+
+            // trace rpcs as children of the calling node span
+            let parent = request.headers().get("traceparent")
+                .and_then(|x| x.to_str().ok())
+                .and_then(SpanContext::from_traceparent);
+            let mut span = Span::new(&method, SpanKind::Server, parent);
+            let future = telemetry::instrument(span.context(),
+                service.call(request));
+            async move {
+                let result = future.await;
+
+                // handler errors are returned in trailers-only responses
+                let error = match &result {
+                    Ok(response) => response.headers().get("grpc-status")
+                        .and_then(|x| x.to_str().ok())
+                        .filter(|x| *x != "0")
+                        .map(|x| format!("grpc status {}", x)),
+                    Err(e) => Some(e.to_string()),
+                };
+
+                metrics::observe_rpc(&method, start.elapsed(),
+                    error.is_some());
+                if let Some(error) = &error {
+                    span.set_error(error);
+                }
+
+                result
+            }
+        })
         .add_service(AlbumManagementServer::new(album_management))
+        .add_service(FilterManagementServer::new(filter_management))
+        .add_service(HealthServer::new(health))
         .add_service(ImageManagementServer::new(image_management))
         .add_service(NodeManagementServer::new(node_management))
+        .add_service(ServerReflectionServer::new(server_reflection))
         .add_service(TaskManagementServer::new(task_management))
         .serve(addr).await?;
 
@@ -141,13 +568,81 @@ struct Opt {
     #[structopt(name="NODE_ID", help="Integer node identifier.")]
     node_id: u32,
 
-    #[structopt(short="d", long="directory", help="data storage directory.")]
-    directory: PathBuf,
+    #[structopt(long="admin-token",
+        help="token required to lift album freeze and legal holds.")]
+    admin_token: Option<String>,
+
+    #[structopt(long="admin-socket",
+        help="admin socket path [default: <directory>/stipd.sock].")]
+    admin_socket: Option<PathBuf>,
+
+    #[structopt(long="collect-interval",
+        help="seconds between album retention garbage collections, 0 is disabled.",
+        default_value="3600")]
+    collect_interval: u64,
+
+    #[structopt(long="compact-interval",
+        help="seconds between packing cold album tiles into container files, 0 is disabled.",
+        default_value="0")]
+    compact_interval: u64,
+
+    #[structopt(long="compact-min-age",
+        help="seconds since modification before a tile is packed.",
+        default_value="86400")]
+    compact_min_age: u64,
+
+    #[structopt(long="coregistration",
+        help="handling of images misaligned with other sources [flag, reject, resample].",
+        default_value="flag")]
+    coregistration: Coregistration,
+
+    #[structopt(long="coregistration-sources",
+        help="comma separated mask or QA sources checked for alignment.",
+        use_delimiter=true)]
+    coregistration_sources: Vec<String>,
+
+    #[structopt(long="config",
+        help="declarative cluster config file defining nodes and albums.")]
+    config: Option<PathBuf>,
+
+    #[structopt(long="credentials",
+        help="credentials file enabling token authentication.")]
+    credentials: Option<PathBuf>,
+
+    #[structopt(short="d", long="directory", help="data storage directory.",
+        required_unless="config")]
+    directory: Option<PathBuf>,
+
+    #[structopt(long="disk-high-watermark",
+        help="disk usage percent at which image writes are rejected.",
+        default_value="95")]
+    disk_high_watermark: u8,
+
+    #[structopt(long="gateway-port",
+        help="port serving an http json gateway over rpcs, disabled if omitted.")]
+    gateway_port: Option<u16>,
+
+    #[structopt(long="hash-function",
+        help="dht key hash function [xxhash64, fnv1a, std].")]
+    hash_function: Option<HashFunction>,
 
     #[structopt(short="l", long="load-thread-count",
         help="thread count to load existing data.", default_value="4")]
     load_thread_count: u8,
 
+    #[structopt(long="log-format",
+        help="log line format [json, text].", default_value="text")]
+    log_format: LogFormat,
+
+    #[structopt(long="max-running-tasks",
+        help="maximum concurrently running tasks, 0 is unlimited.",
+        default_value="0")]
+    max_running_tasks: usize,
+
+    #[structopt(long="metrics-port",
+        help="port serving prometheus metrics, disabled if omitted.")]
+    metrics_port: Option<u16>,
+
     #[structopt(short="i", long="ip-address",
         help="gossip ip address.", default_value="127.0.0.1")]
     ip_addr: IpAddr,
@@ -156,6 +651,32 @@ struct Opt {
         help="gossip port.", default_value="15605")]
     gossip_port: u16,
 
+    #[structopt(long="otlp-endpoint",
+        help="http url of an otlp collector receiving traces, disabled if omitted.")]
+    otlp_endpoint: Option<String>,
+
+    #[structopt(long="placement-fallback",
+        help="write to the next ring node when a replica rejects a write.")]
+    placement_fallback: bool,
+
+    #[structopt(long="quicklook-bands",
+        help="comma separated bands rendered in quicklooks [default: 1,2,3].",
+        use_delimiter=true)]
+    quicklook_bands: Vec<usize>,
+
+    #[structopt(long="quicklook-format",
+        help="quicklook image format [png, jpeg].", default_value="png")]
+    quicklook_format: QuicklookFormat,
+
+    #[structopt(long="quicklook-size",
+        help="longest quicklook side in pixels written with each image, 0 is disabled.",
+        default_value="0")]
+    quicklook_size: usize,
+
+    #[structopt(long="replication-factor",
+        help="number of nodes storing each tile [default: 1].")]
+    replication_factor: Option<u8>,
+
     #[structopt(short="r", long="rpc-port",
         help="rpc port.", default_value="15606")]
     rpc_port: u16,
@@ -167,10 +688,80 @@ struct Opt {
         help="seed port.", default_value="15605")]
     seed_port: u16,
 
-    #[structopt(short="t", long="token", help="token list for dht.")]
+    #[structopt(long="slow-op-threshold-ms",
+        help="log list, search, and tile write latencies above this threshold, 0 disables.",
+        default_value="1000")]
+    slow_op_threshold_ms: u64,
+
+    #[structopt(long="snapshot-interval",
+        help="seconds between album index snapshots.",
+        default_value="300")]
+    snapshot_interval: u64,
+
+    #[structopt(long="stac-port",
+        help="port serving a stac api over albums, disabled if omitted.")]
+    stac_port: Option<u16>,
+
+    #[structopt(long="task-retention-count",
+        help="maximum number of completed tasks retained.",
+        default_value="256")]
+    task_retention_count: usize,
+
+    #[structopt(short="t", long="token",
+        help="token list for dht, generated and persisted if omitted.")]
     tokens: Vec<u64>,
 
+    #[structopt(long="virtual-nodes",
+        help="virtual node count when no tokens are provided [default: 8].")]
+    virtual_node_count: Option<u16>,
+
+    #[structopt(long="tls-ca",
+        help="ca certificate used to verify cluster nodes and clients.")]
+    tls_ca: Option<PathBuf>,
+
+    #[structopt(long="tls-cert",
+        help="pem certificate enabling tls for rpc and transfer.")]
+    tls_cert: Option<PathBuf>,
+
+    #[structopt(long="tls-client-auth",
+        help="require clients to present a certificate signed by the ca.")]
+    tls_client_auth: bool,
+
+    #[structopt(long="tls-domain",
+        help="server name within node certificates.", default_value="stipd")]
+    tls_domain: String,
+
+    #[structopt(long="tls-key", help="pem private key for the certificate.")]
+    tls_key: Option<PathBuf>,
+
+    #[structopt(long="transfer-compression",
+        help="codec for outgoing image transfers [none, lz4, zstd].",
+        default_value="none")]
+    transfer_compression: Compression,
+
+    #[structopt(long="transfer-rate-limit",
+        help="outgoing image transfer cap in MiB per second, 0 is unlimited.",
+        default_value="0")]
+    transfer_rate_limit: u64,
+
+    #[structopt(long="worker-threads",
+        help="threads processing task records across all tasks, 0 uses the cpu count.",
+        default_value="0")]
+    worker_threads: usize,
+
     #[structopt(short="x", long="xfer-port",
         help="data transfer port.", default_value="15607")]
     xfer_port: u16,
 }
+
+#[derive(Debug, StructOpt)]
+#[structopt(name = "stipd status",
+    about="Report the status of a local STIP node as JSON.")]
+struct StatusOpt {
+    #[structopt(long="admin-socket",
+        help="admin socket path [default: <directory>/stipd.sock].")]
+    admin_socket: Option<PathBuf>,
+
+    #[structopt(short="d", long="directory", help="data storage directory.")]
+    directory: PathBuf,
+}
@@ -0,0 +1,137 @@
+use gdal::{Dataset, Metadata};
+
+use std::error::Error;
+use std::ffi::CStr;
+use std::os::raw::c_int;
+use std::str::FromStr;
+
+pub const CLOUD_COVERAGE_ITEM: &'static str = "CLOUD_COVERAGE";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CloudMask {
+    QaPixel,
+    Scl,
+}
+
+impl CloudMask {
+    pub fn band_name(&self) -> &'static str {
+        match self {
+            CloudMask::QaPixel => "QA_PIXEL",
+            CloudMask::Scl => "SCL",
+        }
+    }
+
+    // Some(true) for cloud or shadow, None where nothing was observed
+    pub fn classify(&self, value: f64) -> Option<bool> {
+        match self {
+            CloudMask::QaPixel => {
+                let value = value as u16;
+                if value & 1 != 0 {
+                    return None;
+                }
+
+                // dilated cloud, cirrus, cloud, and cloud shadow bits
+                Some(value & 0b11110 != 0)
+            },
+            CloudMask::Scl => match value as u8 {
+                0 => None,
+                3 | 8 | 9 | 10 => Some(true),
+                _ => Some(false),
+            },
+        }
+    }
+
+    pub fn classify_band(&self, mask: &Dataset, band: usize,
+            reference: &Dataset) -> Result<Vec<Option<bool>>, Box<dyn Error>> {
+        // masks are often coarser than the data they cover
+        let resampled = match crate::album::is_aligned(mask, reference)? {
+            true => None,
+            false => Some(crate::album::resample(mask, reference)?),
+        };
+
+        let band = crate::raster::read_band(
+            resampled.as_ref().unwrap_or(mask), band)?;
+        Ok(band.values.iter().map(|x| self.classify(*x)).collect())
+    }
+
+    pub fn find_band(&self, dataset: &Dataset) -> Option<usize> {
+        // gdal describes bands as 'NAME' or 'NAME, description'
+        for i in 0..crate::raster::band_count(dataset) {
+            let description = unsafe {
+                let band = gdal_sys::GDALGetRasterBand(
+                    dataset.c_dataset(), (i + 1) as c_int);
+                CStr::from_ptr(gdal_sys::GDALGetDescription(band as _))
+                    .to_string_lossy().into_owned()
+            };
+
+            let name = description.split(',').next().unwrap_or("").trim();
+            if name.eq_ignore_ascii_case(self.band_name()) {
+                return Some(i + 1);
+            }
+        }
+
+        None
+    }
+}
+
+impl FromStr for CloudMask {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "qa_pixel" => Ok(CloudMask::QaPixel),
+            "scl" => Ok(CloudMask::Scl),
+            x => Err(format!("unsupported cloud mask '{}'", x)),
+        }
+    }
+}
+
+pub fn apply(dataset: &Dataset, classes: &[Option<bool>],
+        skip_band: Option<usize>) -> Result<Option<f64>, Box<dyn Error>> {
+    // write masked pixels as nodata in each data band
+    for i in 1..crate::raster::band_count(dataset) + 1 {
+        if Some(i) == skip_band {
+            continue;
+        }
+
+        let mut band = crate::raster::read_band(dataset, i)?;
+        let nodata = match band.nodata {
+            Some(nodata) => nodata,
+            None => {
+                unsafe {
+                    let c_band = gdal_sys::GDALGetRasterBand(
+                        dataset.c_dataset(), i as c_int);
+                    gdal_sys::GDALSetRasterNoDataValue(c_band, 0.0);
+                }
+
+                0f64
+            },
+        };
+
+        for (value, class) in band.values.iter_mut().zip(classes.iter()) {
+            if *class == Some(true) {
+                *value = nodata;
+            }
+        }
+
+        crate::raster::write_band(dataset, i, &mut band.values)?;
+    }
+
+    // record cloud coverage over observed pixels
+    let observed = classes.iter().filter(|x| x.is_some()).count();
+    if observed == 0 {
+        return Ok(None);
+    }
+
+    let cloudy = classes.iter().filter(|x| **x == Some(true)).count();
+    let cloud_coverage = cloudy as f64 / observed as f64;
+    dataset.set_metadata_item(CLOUD_COVERAGE_ITEM,
+        &cloud_coverage.to_string(), "STIP")?;
+
+    Ok(Some(cloud_coverage))
+}
+
+pub fn cloud_coverage(dataset: &Dataset) -> Option<f64> {
+    dataset.metadata_item(CLOUD_COVERAGE_ITEM, "STIP")
+        .and_then(|x| x.parse::<f64>().ok())
+}
@@ -0,0 +1,242 @@
+use crate::http::{HttpRequest, HttpResponse};
+use crate::task::TaskManager;
+
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt::Write;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, RwLock};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+// upper bounds of the rpc latency histogram buckets in seconds
+const RPC_BUCKETS: [f64; 11] =
+    [0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0];
+
+static DHT_LOOKUP_FAILURES: AtomicU64 = AtomicU64::new(0);
+static TILES_READ: AtomicU64 = AtomicU64::new(0);
+static TILES_WRITTEN: AtomicU64 = AtomicU64::new(0);
+static TRANSFER_FAILED_SENDS: AtomicU64 = AtomicU64::new(0);
+static TRANSFER_RECEIVED_BYTES: AtomicU64 = AtomicU64::new(0);
+static TRANSFER_RETRANSMITS: AtomicU64 = AtomicU64::new(0);
+static TRANSFER_SENT_BYTES: AtomicU64 = AtomicU64::new(0);
+
+static RPC_LATENCIES: Mutex<BTreeMap<String, Histogram>> =
+    Mutex::new(BTreeMap::new());
+
+#[derive(Clone, Copy, Debug)]
+pub enum Counter {
+    DhtLookupFailures,
+    TilesRead,
+    TilesWritten,
+    TransferFailedSends,
+    TransferReceivedBytes,
+    TransferRetransmits,
+    TransferSentBytes,
+}
+
+impl Counter {
+    fn value(&self) -> &'static AtomicU64 {
+        match self {
+            Counter::DhtLookupFailures => &DHT_LOOKUP_FAILURES,
+            Counter::TilesRead => &TILES_READ,
+            Counter::TilesWritten => &TILES_WRITTEN,
+            Counter::TransferFailedSends => &TRANSFER_FAILED_SENDS,
+            Counter::TransferReceivedBytes => &TRANSFER_RECEIVED_BYTES,
+            Counter::TransferRetransmits => &TRANSFER_RETRANSMITS,
+            Counter::TransferSentBytes => &TRANSFER_SENT_BYTES,
+        }
+    }
+}
+
+#[derive(Default)]
+struct Histogram {
+    buckets: [u64; RPC_BUCKETS.len()],
+    count: u64,
+    errors: u64,
+    sum: f64,
+}
+
+pub fn add(counter: Counter, value: u64) {
+    counter.value().fetch_add(value, Ordering::Relaxed);
+}
+
+pub fn get(counter: Counter) -> u64 {
+    counter.value().load(Ordering::Relaxed)
+}
+
+pub fn observe_rpc(method: &str, duration: Duration, failed: bool) {
+    let seconds = duration.as_secs_f64();
+    let mut rpc_latencies = RPC_LATENCIES.lock().unwrap();
+    let histogram = rpc_latencies.entry(method.to_string())
+        .or_insert(Histogram::default());
+
+    // buckets are cumulative
+    for (i, bound) in RPC_BUCKETS.iter().enumerate() {
+        if seconds <= *bound {
+            histogram.buckets[i] += 1;
+        }
+    }
+
+    histogram.count += 1;
+    histogram.sum += seconds;
+    if failed {
+        histogram.errors += 1;
+    }
+}
+
+// call and failure counts of each rpc method served
+pub fn rpc_calls() -> Vec<(String, u64, u64)> {
+    RPC_LATENCIES.lock().unwrap().iter()
+        .map(|(method, histogram)|
+            (method.clone(), histogram.count, histogram.errors))
+        .collect()
+}
+
+// serves node metrics in the prometheus text exposition format
+pub struct MetricsServer {
+    directory: PathBuf,
+    task_manager: Arc<RwLock<TaskManager>>,
+}
+
+impl MetricsServer {
+    pub fn new(directory: PathBuf, task_manager: Arc<RwLock<TaskManager>>)
+            -> MetricsServer {
+        MetricsServer {
+            directory: directory,
+            task_manager: task_manager,
+        }
+    }
+
+    pub fn start(self, address: &str) -> Result<(), Box<dyn Error>> {
+        info!("starting metrics server [address={}]", address);
+        crate::http::start(address, move |request| self.handle(request))
+    }
+
+    fn handle(&self, request: &HttpRequest) -> HttpResponse {
+        match (request.method.as_str(), request.path.as_str()) {
+            ("GET", "/metrics") => match self.render() {
+                Ok(body) => HttpResponse {
+                    body: body.into_bytes(),
+                    content_type: "text/plain; version=0.0.4".to_string(),
+                    status: 200,
+                },
+                Err(e) => HttpResponse::error(500, &e.to_string()),
+            },
+            ("GET", _) => HttpResponse::error(404, "unknown metrics path"),
+            _ => HttpResponse::error(405, "unsupported http method"),
+        }
+    }
+
+    fn render(&self) -> Result<String, Box<dyn Error>> {
+        let mut buf = String::new();
+
+        // counters
+        for (counter, name, help) in &[
+                (Counter::DhtLookupFailures, "stip_dht_lookup_failures_total",
+                    "Failed dht lookups of tile replica nodes."),
+                (Counter::TilesRead, "stip_tiles_read_total",
+                    "Tiles read by other nodes over the transfer protocol."),
+                (Counter::TilesWritten, "stip_tiles_written_total",
+                    "Tiles written to local albums."),
+                (Counter::TransferFailedSends,
+                    "stip_transfer_failed_sends_total",
+                    "Transfer requests other nodes failed or rejected."),
+                (Counter::TransferReceivedBytes,
+                    "stip_transfer_received_bytes_total",
+                    "Bytes received over the transfer protocol."),
+                (Counter::TransferRetransmits,
+                    "stip_transfer_retransmits_total",
+                    "Transfer requests resent after a failure."),
+                (Counter::TransferSentBytes, "stip_transfer_sent_bytes_total",
+                    "Bytes sent over the transfer protocol.")] {
+            write_header(&mut buf, name, help, "counter")?;
+            writeln!(buf, "{} {}", name,
+                counter.value().load(Ordering::Relaxed))?;
+        }
+
+        // rpc latencies
+        write_header(&mut buf, "stip_rpc_duration_seconds",
+            "Latency of rpcs served by this node.", "histogram")?;
+        for (method, histogram) in RPC_LATENCIES.lock().unwrap().iter() {
+            for (bound, count) in RPC_BUCKETS.iter()
+                    .zip(histogram.buckets.iter()) {
+                writeln!(buf, "stip_rpc_duration_seconds_bucket{{method=\"{}\",le=\"{}\"}} {}",
+                    method, bound, count)?;
+            }
+
+            writeln!(buf, "stip_rpc_duration_seconds_bucket{{method=\"{}\",le=\"+Inf\"}} {}",
+                method, histogram.count)?;
+            writeln!(buf, "stip_rpc_duration_seconds_sum{{method=\"{}\"}} {}",
+                method, histogram.sum)?;
+            writeln!(buf, "stip_rpc_duration_seconds_count{{method=\"{}\"}} {}",
+                method, histogram.count)?;
+        }
+
+        write_header(&mut buf, "stip_rpc_errors_total",
+            "Rpcs served by this node which returned an error.", "counter")?;
+        for (method, _, errors) in rpc_calls() {
+            writeln!(buf, "stip_rpc_errors_total{{method=\"{}\"}} {}",
+                method, errors)?;
+        }
+
+        // task progress
+        let (mut queued, mut paused, mut running) = (0, 0, 0);
+        let mut records = Vec::new();
+        {
+            let task_manager = self.task_manager.read().unwrap();
+            for (id, task_handle) in task_manager.iter() {
+                if !task_handle.running() {
+                    continue;
+                } else if task_handle.queued() {
+                    queued += 1;
+                    continue;
+                } else if task_handle.paused() {
+                    paused += 1;
+                } else {
+                    running += 1;
+                }
+
+                records.push((*id, task_handle.completed_count(),
+                    task_handle.skipped_count(), task_handle.total_count()));
+            }
+        }
+
+        write_header(&mut buf, "stip_tasks",
+            "Unfinished tasks by state.", "gauge")?;
+        for (state, count) in &[("paused", paused),
+                ("queued", queued), ("running", running)] {
+            writeln!(buf, "stip_tasks{{state=\"{}\"}} {}", state, count)?;
+        }
+
+        write_header(&mut buf, "stip_task_records",
+            "Records of started tasks by state.", "gauge")?;
+        for (id, completed, skipped, total) in records {
+            writeln!(buf, "stip_task_records{{task_id=\"{}\",state=\"completed\"}} {}",
+                id, completed)?;
+            writeln!(buf, "stip_task_records{{task_id=\"{}\",state=\"skipped\"}} {}",
+                id, skipped)?;
+            writeln!(buf, "stip_task_records{{task_id=\"{}\",state=\"total\"}} {}",
+                id, total)?;
+        }
+
+        // disk usage of the data directory filesystem
+        let (free_bytes, total_bytes) =
+            crate::admin::disk_usage(&self.directory)?;
+        write_header(&mut buf, "stip_disk_free_bytes",
+            "Free bytes on the data directory filesystem.", "gauge")?;
+        writeln!(buf, "stip_disk_free_bytes {}", free_bytes)?;
+        write_header(&mut buf, "stip_disk_total_bytes",
+            "Total bytes on the data directory filesystem.", "gauge")?;
+        writeln!(buf, "stip_disk_total_bytes {}", total_bytes)?;
+
+        Ok(buf)
+    }
+}
+
+fn write_header(buf: &mut String, name: &str, help: &str, metric_type: &str)
+        -> Result<(), Box<dyn Error>> {
+    writeln!(buf, "# HELP {} {}", name, help)?;
+    writeln!(buf, "# TYPE {} {}", name, metric_type)?;
+    Ok(())
+}
@@ -0,0 +1,109 @@
+use gdal::Dataset;
+
+use crate::quicklook::QuicklookFormat;
+use crate::transfer::{LatencyTracker, ReadPrefetcher, ReadRequest};
+
+use std::error::Error;
+use std::ffi::CString;
+use std::os::raw::c_int;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+// concurrent tile reads while gathering a mosaic
+const MOSAIC_READ_WINDOW: usize = 8;
+
+// longest side of mosaic previews in pixels unless otherwise requested
+pub const MOSAIC_PREVIEW_SIZE: usize = 1024;
+
+pub fn mosaic(requests: Vec<ReadRequest>, tracker: Arc<LatencyTracker>)
+        -> Result<Vec<u8>, Box<dyn Error>> {
+    let path = temp_path();
+    let result = read(requests, tracker)
+        .and_then(|datasets| merge(&datasets, &path))
+        .and_then(|_| Ok(std::fs::read(&path)?));
+
+    let _ = std::fs::remove_file(&path);
+    result
+}
+
+// render a stretched quicklook of the merged mosaic
+pub fn preview(requests: Vec<ReadRequest>, tracker: Arc<LatencyTracker>,
+        bands: &[usize], format: QuicklookFormat, size: usize)
+        -> Result<Vec<u8>, Box<dyn Error>> {
+    let path = temp_path();
+    let result = read(requests, tracker)
+        .and_then(|datasets| merge(&datasets, &path))
+        .and_then(|_| crate::quicklook::render(&Dataset::open(&path)?,
+            bands, format, size));
+
+    // rendering may write auxiliary statistics beside the mosaic
+    let _ = std::fs::remove_file(&path);
+    let _ = std::fs::remove_file(
+        format!("{}.aux.xml", path.to_string_lossy()));
+    result
+}
+
+fn read(requests: Vec<ReadRequest>, tracker: Arc<LatencyTracker>)
+        -> Result<Vec<Dataset>, Box<dyn Error>> {
+    // read tiles from replicas, skipping tiles which fail to read
+    let mut datasets = Vec::new();
+    let mut errors = Vec::new();
+    let prefetcher = ReadPrefetcher::new(requests,
+        MOSAIC_READ_WINDOW, tracker);
+    for result in prefetcher {
+        match result {
+            Ok(dataset) => datasets.push(dataset),
+            Err(e) => errors.push(e.to_string()),
+        }
+    }
+
+    if datasets.is_empty() {
+        return match errors.is_empty() {
+            true => Err("no images match the mosaic request".into()),
+            false => Err(format!("failed to read {} mosaic tiles, first error: {}",
+                errors.len(), errors[0]).into()),
+        };
+    }
+
+    if !errors.is_empty() {
+        warn!("skipped {} mosaic tiles, first error: {}",
+            errors.len(), errors[0]);
+    }
+
+    Ok(datasets)
+}
+
+fn merge(datasets: &[Dataset], path: &Path) -> Result<(), Box<dyn Error>> {
+    let c_path = CString::new(path.to_string_lossy().as_ref())?;
+    let c_vrt = CString::new("")?;
+
+    // later datasets are drawn over earlier ones where tiles overlap
+    let mut c_datasets: Vec<gdal_sys::GDALDatasetH> =
+        datasets.iter().map(|x| x.c_dataset()).collect();
+    let mut usage_error: c_int = 0;
+
+    unsafe {
+        let c_vrt_dataset = gdal_sys::GDALBuildVRT(c_vrt.as_ptr(),
+            c_datasets.len() as c_int, c_datasets.as_mut_ptr(),
+            std::ptr::null(), std::ptr::null(), &mut usage_error);
+        if c_vrt_dataset.is_null() {
+            return Err("failed to build mosaic, tiles may have mismatched bands".into());
+        }
+
+        let c_dataset = gdal_sys::GDALTranslate(c_path.as_ptr(),
+            c_vrt_dataset, std::ptr::null(), &mut usage_error);
+        gdal_sys::GDALClose(c_vrt_dataset);
+        if c_dataset.is_null() {
+            return Err("failed to write mosaic".into());
+        }
+
+        gdal_sys::GDALClose(c_dataset);
+    }
+
+    Ok(())
+}
+
+fn temp_path() -> PathBuf {
+    std::env::temp_dir().join(format!(
+        "stip-mosaic-{}.tif", rand::random::<u64>()))
+}
@@ -0,0 +1,183 @@
+use gdal::Dataset;
+
+use std::error::Error;
+use std::ffi::CString;
+use std::os::raw::{c_char, c_int};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::RwLock;
+use std::sync::atomic::{AtomicU8, AtomicUsize, Ordering};
+
+static QUICKLOOK_BANDS: RwLock<Vec<usize>> = RwLock::new(Vec::new());
+static QUICKLOOK_FORMAT: AtomicU8 = AtomicU8::new(QuicklookFormat::Png as u8);
+static QUICKLOOK_SIZE: AtomicUsize = AtomicUsize::new(0);
+
+const PREVIEW_SIZE: usize = 256;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum QuicklookFormat {
+    Jpeg = 0,
+    Png = 1,
+}
+
+impl QuicklookFormat {
+    fn driver(&self) -> &'static str {
+        match self {
+            QuicklookFormat::Jpeg => "JPEG",
+            QuicklookFormat::Png => "PNG",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            QuicklookFormat::Jpeg => "jpg",
+            QuicklookFormat::Png => "png",
+        }
+    }
+}
+
+impl FromStr for QuicklookFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "jpeg" | "jpg" => Ok(QuicklookFormat::Jpeg),
+            "png" => Ok(QuicklookFormat::Png),
+            x => Err(format!("unsupported quicklook format '{}'", x)),
+        }
+    }
+}
+
+pub fn configure(bands: Vec<usize>, format: QuicklookFormat, size: usize)
+        -> Result<(), Box<dyn Error>> {
+    if bands.len() != 0 && bands.len() != 1 && bands.len() != 3 {
+        return Err("quicklooks require one or three bands".into());
+    }
+
+    *QUICKLOOK_BANDS.write().unwrap() = bands;
+    QUICKLOOK_FORMAT.store(format as u8, Ordering::SeqCst);
+    QUICKLOOK_SIZE.store(size, Ordering::SeqCst);
+    Ok(())
+}
+
+pub fn format() -> QuicklookFormat {
+    match QUICKLOOK_FORMAT.load(Ordering::SeqCst) {
+        0 => QuicklookFormat::Jpeg,
+        _ => QuicklookFormat::Png,
+    }
+}
+
+// quicklook of an image, stored alongside it with the format extension
+pub fn path(image_path: &Path, format: QuicklookFormat) -> PathBuf {
+    image_path.with_extension(format.extension())
+}
+
+// remove quicklooks of every format stored alongside an image
+pub fn remove(image_path: &Path) -> Result<(), Box<dyn Error>> {
+    for format in vec![QuicklookFormat::Jpeg, QuicklookFormat::Png] {
+        let path = path(image_path, format);
+        if path.exists() {
+            std::fs::remove_file(&path)?;
+        }
+    }
+
+    Ok(())
+}
+
+pub fn preview(image_path: &Path, bands: &[usize],
+        format: QuicklookFormat, size: Option<usize>)
+        -> Result<Vec<u8>, Box<dyn Error>> {
+    // serve the stored quicklook unless a custom rendering is requested
+    let stored_path = path(image_path, format);
+    if bands.is_empty() && size.is_none() && stored_path.exists() {
+        return Ok(std::fs::read(&stored_path)?);
+    }
+
+    let dataset = crate::compaction::open(image_path)?;
+    render(&dataset, bands, format, size.unwrap_or(PREVIEW_SIZE))
+}
+
+pub fn render(dataset: &Dataset, bands: &[usize], format: QuicklookFormat,
+        size: usize) -> Result<Vec<u8>, Box<dyn Error>> {
+    // default to rgb from the first three bands, otherwise grayscale
+    let band_count = crate::raster::band_count(dataset);
+    let bands = match (bands.is_empty(), band_count >= 3) {
+        (true, true) => vec![1, 2, 3],
+        (true, false) => vec![1],
+        (false, _) => bands.to_vec(),
+    };
+
+    if bands.len() != 1 && bands.len() != 3 {
+        return Err("quicklooks require one or three bands".into());
+    } else if let Some(band) = bands.iter()
+            .find(|x| **x == 0 || **x > band_count) {
+        return Err(format!("band {} does not exist", band).into());
+    }
+
+    // shrink the longest side to size, never enlarging the image
+    let (x, y) = dataset.raster_size();
+    let scale = (size as f64 / x.max(y) as f64).min(1f64);
+    let width = ((x as f64 * scale).round() as usize).max(1);
+    let height = ((y as f64 * scale).round() as usize).max(1);
+
+    // stretch each band between its minimum and maximum
+    let mut args = vec!["-of".to_string(), format.driver().to_string(),
+        "-ot".to_string(), "Byte".to_string(), "-scale".to_string(),
+        "-outsize".to_string(), width.to_string(), height.to_string()];
+    for band in bands.iter() {
+        args.push("-b".to_string());
+        args.push(band.to_string());
+    }
+
+    let c_args = args.into_iter().map(CString::new)
+        .collect::<Result<Vec<CString>, _>>()?;
+    let mut c_arg_ptrs: Vec<*mut c_char> = c_args.iter()
+        .map(|x| x.as_ptr() as *mut c_char).collect();
+    c_arg_ptrs.push(std::ptr::null_mut());
+
+    let path = std::env::temp_dir().join(format!("stip-quicklook-{}.{}",
+        rand::random::<u64>(), format.extension()));
+    let c_path = CString::new(path.to_string_lossy().as_ref())?;
+    let mut usage_error: c_int = 0;
+    unsafe {
+        let c_options = gdal_sys::GDALTranslateOptionsNew(
+            c_arg_ptrs.as_mut_ptr(), std::ptr::null_mut());
+        if c_options.is_null() {
+            return Err("invalid quicklook options".into());
+        }
+
+        let c_dataset = gdal_sys::GDALTranslate(c_path.as_ptr(),
+            dataset.c_dataset(), c_options, &mut usage_error);
+        gdal_sys::GDALTranslateOptionsFree(c_options);
+        if c_dataset.is_null() {
+            return Err("failed to render quicklook".into());
+        }
+
+        gdal_sys::GDALClose(c_dataset);
+    }
+
+    // drivers may write auxiliary metadata beside the image
+    let buf = std::fs::read(&path);
+    std::fs::remove_file(&path)?;
+    let _ = std::fs::remove_file(
+        format!("{}.aux.xml", path.to_string_lossy()));
+    Ok(buf?)
+}
+
+pub fn write(image_path: &Path) -> Result<(), Box<dyn Error>> {
+    // quicklooks are only written at write time when a size is configured
+    let size = QUICKLOOK_SIZE.load(Ordering::SeqCst);
+    if size == 0 {
+        return Ok(());
+    }
+
+    let dataset = Dataset::open(image_path)?;
+    let format = format();
+    let buf = {
+        let bands = QUICKLOOK_BANDS.read().unwrap();
+        render(&dataset, &bands, format, size)?
+    };
+
+    std::fs::write(path(image_path, format), buf)?;
+    Ok(())
+}
@@ -0,0 +1,498 @@
+use gdal::{Dataset, Driver};
+use gdal::spatial_ref::{CoordTransform, SpatialRef};
+
+use std::error::Error;
+use std::ffi::CString;
+use std::os::raw::{c_char, c_int, c_void};
+use std::path::Path;
+
+pub const RESAMPLING_METHODS: &'static [&'static str] =
+    &["average", "bilinear", "cubic", "mode", "near"];
+
+pub struct Band {
+    pub nodata: Option<f64>,
+    pub values: Vec<f64>,
+}
+
+pub struct Statistics {
+    pub band: usize,
+    pub histogram: Vec<u64>,
+    pub max: f64,
+    pub mean: f64,
+    pub min: f64,
+    pub stddev: f64,
+}
+
+impl Band {
+    // true if the pixel holds data in this band
+    pub fn is_valid(&self, index: usize) -> bool {
+        let value = self.values[index];
+        !value.is_nan() && Some(value) != self.nodata
+    }
+}
+
+pub fn band_count(dataset: &Dataset) -> usize {
+    unsafe { gdal_sys::GDALGetRasterCount(dataset.c_dataset()) as usize }
+}
+
+pub fn coverage(values: &[f64]) -> f64 {
+    if values.is_empty() {
+        return 0f64;
+    }
+
+    let count = values.iter().filter(|x| !x.is_nan()).count();
+    count as f64 / values.len() as f64
+}
+
+pub fn copy(dataset: &Dataset) -> Result<Dataset, Box<dyn Error>> {
+    // copy into memory so pixels may be modified
+    let driver = Driver::get("MEM")?;
+    let c_filename = CString::new("")?;
+    let c_dataset = unsafe {
+        gdal_sys::GDALCreateCopy(driver.c_driver(), c_filename.as_ptr(),
+            dataset.c_dataset(), 0, std::ptr::null_mut(), None,
+            std::ptr::null_mut())
+    };
+
+    if c_dataset.is_null() {
+        return Err("failed to copy raster dataset".into());
+    }
+
+    Ok(unsafe { Dataset::from_c_dataset(c_dataset) })
+}
+
+// decode encoded GeoTIFF bytes into an in-memory dataset
+pub fn from_geotiff(mut buf: Vec<u8>) -> Result<Dataset, Box<dyn Error>> {
+    let filename = format!("/vsimem/stip-{}.tif", rand::random::<u64>());
+    let c_filename = CString::new(filename.as_str())?;
+    let c_file = unsafe {
+        gdal_sys::VSIFileFromMemBuffer(c_filename.as_ptr(),
+            buf.as_mut_ptr(), buf.len() as gdal_sys::vsi_l_offset, 0)
+    };
+
+    if c_file.is_null() {
+        return Err("failed to map GeoTIFF buffer".into());
+    }
+
+    // copy out of the buffer before it is released
+    let result = match Dataset::open(Path::new(&filename)) {
+        Ok(dataset) => copy(&dataset),
+        Err(e) => Err(e.into()),
+    };
+    unsafe {
+        gdal_sys::VSIFCloseL(c_file);
+        gdal_sys::VSIUnlink(c_filename.as_ptr());
+    }
+
+    result
+}
+
+// encode a dataset as GeoTIFF bytes
+pub fn to_geotiff(dataset: &Dataset) -> Result<Vec<u8>, Box<dyn Error>> {
+    let path = std::env::temp_dir().join(format!(
+        "stip-{}.tif", rand::random::<u64>()));
+    let result = write_geotiff(dataset, &path)
+        .and_then(|_| Ok(std::fs::read(&path)?));
+    let _ = std::fs::remove_file(&path);
+    result
+}
+
+pub fn write_geotiff(dataset: &Dataset, path: &Path)
+        -> Result<(), Box<dyn Error>> {
+    let driver = Driver::get("GTiff")?;
+    let c_filename = CString::new(path.to_string_lossy().as_ref())?;
+    let c_compress = CString::new("COMPRESS=LZW")?;
+    let mut c_options = vec![c_compress.as_ptr() as *mut c_char,
+        std::ptr::null_mut()];
+
+    let c_dataset = unsafe {
+        gdal_sys::GDALCreateCopy(driver.c_driver(), c_filename.as_ptr(),
+            dataset.c_dataset(), 0, c_options.as_mut_ptr(), None,
+            std::ptr::null_mut())
+    };
+
+    if c_dataset.is_null() {
+        return Err(format!("failed to write GeoTIFF {:?}", path).into());
+    }
+
+    unsafe { gdal_sys::GDALClose(c_dataset) };
+    Ok(())
+}
+
+pub fn create_dataset(reference: &Dataset, band_count: usize)
+        -> Result<Dataset, Box<dyn Error>> {
+    let (x, y) = reference.raster_size();
+    let driver = Driver::get("MEM")?;
+    let c_filename = CString::new("")?;
+    let c_dataset = unsafe {
+        gdal_sys::GDALCreate(driver.c_driver(), c_filename.as_ptr(),
+            x as c_int, y as c_int, band_count as c_int,
+            gdal_sys::GDALDataType::GDT_Float32, std::ptr::null_mut())
+    };
+
+    if c_dataset.is_null() {
+        return Err("failed to create raster dataset".into());
+    }
+
+    let dataset = unsafe { Dataset::from_c_dataset(c_dataset) };
+    dataset.set_geo_transform(&reference.geo_transform()?)?;
+    dataset.set_projection(&reference.projection())?;
+
+    // derived rasters mark missing pixels as nan
+    for i in 0..band_count {
+        unsafe {
+            let band = gdal_sys::GDALGetRasterBand(
+                dataset.c_dataset(), (i + 1) as c_int);
+            gdal_sys::GDALSetRasterNoDataValue(band, std::f64::NAN);
+        }
+    }
+
+    Ok(dataset)
+}
+
+pub fn merge(datasets: &[Dataset]) -> Result<Dataset, Box<dyn Error>> {
+    let c_vrt = CString::new("")?;
+    let c_filename = CString::new("")?;
+    let c_args = vec![CString::new("-of")?, CString::new("MEM")?];
+    let mut c_arg_ptrs: Vec<*mut c_char> = c_args.iter()
+        .map(|x| x.as_ptr() as *mut c_char).collect();
+    c_arg_ptrs.push(std::ptr::null_mut());
+
+    // later datasets are drawn over earlier ones where they overlap
+    let mut c_datasets: Vec<gdal_sys::GDALDatasetH> =
+        datasets.iter().map(|x| x.c_dataset()).collect();
+    let mut usage_error: c_int = 0;
+    let c_dataset = unsafe {
+        let c_vrt_dataset = gdal_sys::GDALBuildVRT(c_vrt.as_ptr(),
+            c_datasets.len() as c_int, c_datasets.as_mut_ptr(),
+            std::ptr::null(), std::ptr::null(), &mut usage_error);
+        if c_vrt_dataset.is_null() {
+            return Err("failed to merge datasets with mismatched bands".into());
+        }
+
+        let c_options = gdal_sys::GDALTranslateOptionsNew(
+            c_arg_ptrs.as_mut_ptr(), std::ptr::null_mut());
+        let c_dataset = gdal_sys::GDALTranslate(c_filename.as_ptr(),
+            c_vrt_dataset, c_options, &mut usage_error);
+        gdal_sys::GDALTranslateOptionsFree(c_options);
+        gdal_sys::GDALClose(c_vrt_dataset);
+        c_dataset
+    };
+
+    if c_dataset.is_null() {
+        return Err("failed to merge datasets".into());
+    }
+
+    Ok(unsafe { Dataset::from_c_dataset(c_dataset) })
+}
+
+pub fn rasterize(reference: &Dataset, geometry: &str)
+        -> Result<Vec<bool>, Box<dyn Error>> {
+    // create in-memory mask on the reference grid
+    let (x, y) = reference.raster_size();
+    let driver = Driver::get("MEM")?;
+    let c_filename = CString::new("")?;
+    let c_dataset = unsafe {
+        gdal_sys::GDALCreate(driver.c_driver(), c_filename.as_ptr(),
+            x as c_int, y as c_int, 1, gdal_sys::GDALDataType::GDT_Byte,
+            std::ptr::null_mut())
+    };
+
+    if c_dataset.is_null() {
+        return Err("failed to create mask dataset".into());
+    }
+
+    let mask = unsafe { Dataset::from_c_dataset(c_dataset) };
+    mask.set_geo_transform(&reference.geo_transform()?)?;
+    mask.set_projection(&reference.projection())?;
+
+    // burn wkt or geojson geometry, given in longitude / latitude, into
+    //   the mask
+    let c_geometry_string = CString::new(geometry.trim())?;
+    let c_wkt = CString::new(reference.projection())?;
+    let error = unsafe {
+        let mut c_geometry = match geometry.trim().starts_with('{') {
+            true => gdal_sys::OGR_G_CreateGeometryFromJson(
+                c_geometry_string.as_ptr()),
+            false => {
+                let mut c_geometry_ptr =
+                    c_geometry_string.as_ptr() as *mut c_char;
+                let mut c_geometry = std::ptr::null_mut();
+                gdal_sys::OGR_G_CreateFromWkt(&mut c_geometry_ptr,
+                    std::ptr::null_mut(), &mut c_geometry);
+                c_geometry
+            },
+        };
+
+        if c_geometry.is_null() {
+            return Err("invalid geometry".into());
+        }
+
+        let c_src = gdal_sys::OSRNewSpatialReference(std::ptr::null());
+        gdal_sys::OSRImportFromEPSG(c_src, 4326);
+        let c_dst = gdal_sys::OSRNewSpatialReference(c_wkt.as_ptr());
+        gdal_sys::OGR_G_AssignSpatialReference(c_geometry, c_src);
+        let transform_error = gdal_sys::OGR_G_TransformTo(c_geometry, c_dst);
+
+        let mut band_list = [1 as c_int];
+        let mut burn_values = [1f64];
+        let error = match transform_error {
+            gdal_sys::OGRErr::OGRERR_NONE =>
+                gdal_sys::GDALRasterizeGeometries(mask.c_dataset(), 1,
+                    band_list.as_mut_ptr(), 1, &mut c_geometry, None,
+                    std::ptr::null_mut(), burn_values.as_mut_ptr(),
+                    std::ptr::null_mut(), None, std::ptr::null_mut()),
+            _ => gdal_sys::CPLErr::CE_Failure,
+        };
+
+        gdal_sys::OGR_G_DestroyGeometry(c_geometry);
+        gdal_sys::OSRRelease(c_src);
+        gdal_sys::OSRRelease(c_dst);
+        error
+    };
+
+    if error != gdal_sys::CPLErr::CE_None {
+        return Err("failed to rasterize geometry".into());
+    }
+
+    let band = read_band(&mask, 1)?;
+    Ok(band.values.iter().map(|x| *x != 0f64).collect())
+}
+
+pub fn read_band(dataset: &Dataset, index: usize)
+        -> Result<Band, Box<dyn Error>> {
+    if index == 0 || index > band_count(dataset) {
+        return Err(format!("band {} does not exist", index).into());
+    }
+
+    let (x, y) = dataset.raster_size();
+    let mut values = vec![0f64; x * y];
+    let mut has_nodata: c_int = 0;
+    let nodata = unsafe {
+        let band = gdal_sys::GDALGetRasterBand(
+            dataset.c_dataset(), index as c_int);
+        raster_io(band, gdal_sys::GDALRWFlag::GF_Read,
+            &mut values, 0, 0, x, y)?;
+        gdal_sys::GDALGetRasterNoDataValue(band, &mut has_nodata)
+    };
+
+    Ok(Band {
+        nodata: if has_nodata != 0 { Some(nodata) } else { None },
+        values: values,
+    })
+}
+
+pub fn write_band(dataset: &Dataset, index: usize, values: &mut Vec<f64>)
+        -> Result<(), Box<dyn Error>> {
+    let (x, y) = dataset.raster_size();
+    unsafe {
+        let band = gdal_sys::GDALGetRasterBand(
+            dataset.c_dataset(), index as c_int);
+        raster_io(band, gdal_sys::GDALRWFlag::GF_Write,
+            values, 0, 0, x, y)
+    }
+}
+
+unsafe fn raster_io(band: gdal_sys::GDALRasterBandH,
+        flag: gdal_sys::GDALRWFlag::Type, buf: &mut Vec<f64>,
+        x_offset: usize, y_offset: usize, x: usize, y: usize)
+        -> Result<(), Box<dyn Error>> {
+    let error = gdal_sys::GDALRasterIO(band, flag, x_offset as c_int,
+        y_offset as c_int, x as c_int, y as c_int, buf.as_mut_ptr() as *mut c_void, x as c_int,
+        y as c_int, gdal_sys::GDALDataType::GDT_Float64, 0, 0);
+
+    match error {
+        gdal_sys::CPLErr::CE_None => Ok(()),
+        _ => Err("failed to transfer raster band data".into()),
+    }
+}
+
+pub fn sample(dataset: &Dataset, index: usize, longitude: f64,
+        latitude: f64, buffer: f64)
+        -> Result<Option<(f64, u32)>, Box<dyn Error>> {
+    if index == 0 || index > band_count(dataset) {
+        return Err(format!("band {} does not exist", index).into());
+    }
+
+    // compute pixel window containing the buffered point
+    let mut xs = [longitude - buffer, longitude + buffer];
+    let mut ys = [latitude - buffer, latitude + buffer];
+    transform_coords(&SpatialRef::from_epsg(4326)?,
+        &SpatialRef::from_wkt(&dataset.projection())?, &mut xs, &mut ys)?;
+
+    let geo_transform = dataset.geo_transform()?;
+    let (width, height) = dataset.raster_size();
+    let to_pixel = |x: f64, y: f64| ((x - geo_transform[0]) / geo_transform[1],
+        (y - geo_transform[3]) / geo_transform[5]);
+
+    let (px1, py1) = to_pixel(xs[0], ys[0]);
+    let (px2, py2) = to_pixel(xs[1], ys[1]);
+    let min_px = px1.min(px2).floor().max(0f64) as usize;
+    let min_py = py1.min(py2).floor().max(0f64) as usize;
+    let max_px = (px1.max(px2).floor() as i64 + 1)
+        .min(width as i64).max(0) as usize;
+    let max_py = (py1.max(py2).floor() as i64 + 1)
+        .min(height as i64).max(0) as usize;
+
+    if min_px >= max_px || min_py >= max_py {
+        return Ok(None);
+    }
+
+    // read window and average pixels holding data
+    let (x, y) = (max_px - min_px, max_py - min_py);
+    let mut values = vec![0f64; x * y];
+    let mut has_nodata: c_int = 0;
+    let nodata = unsafe {
+        let band = gdal_sys::GDALGetRasterBand(
+            dataset.c_dataset(), index as c_int);
+        raster_io(band, gdal_sys::GDALRWFlag::GF_Read,
+            &mut values, min_px, min_py, x, y)?;
+        gdal_sys::GDALGetRasterNoDataValue(band, &mut has_nodata)
+    };
+
+    let band = Band {
+        nodata: if has_nodata != 0 { Some(nodata) } else { None },
+        values: values,
+    };
+
+    let valid: Vec<f64> = (0..band.values.len())
+        .filter(|i| band.is_valid(*i)).map(|i| band.values[i]).collect();
+    match valid.len() {
+        0 => Ok(None),
+        count => Ok(Some((valid.iter().sum::<f64>() / count as f64,
+            count as u32))),
+    }
+}
+
+pub fn statistics(dataset: &Dataset, index: usize, bins: usize)
+        -> Result<Option<Statistics>, Box<dyn Error>> {
+    let band = read_band(dataset, index)?;
+    let values: Vec<f64> = (0..band.values.len())
+        .filter(|i| band.is_valid(*i)).map(|i| band.values[i]).collect();
+    if values.is_empty() || bins == 0 {
+        return Ok(None);
+    }
+
+    // compute summary statistics over pixels holding data
+    let min = values.iter().cloned().fold(std::f64::INFINITY, f64::min);
+    let max = values.iter().cloned().fold(std::f64::NEG_INFINITY, f64::max);
+    let mean = values.iter().sum::<f64>() / values.len() as f64;
+    let variance = values.iter().map(|x| (x - mean).powi(2))
+        .sum::<f64>() / values.len() as f64;
+
+    // bin values evenly between the minimum and maximum
+    let mut histogram = vec![0u64; bins];
+    for value in values.iter() {
+        let bin = match max > min {
+            true => ((value - min) / (max - min) * bins as f64) as usize,
+            false => 0,
+        };
+
+        histogram[bin.min(bins - 1)] += 1;
+    }
+
+    Ok(Some(Statistics {
+        band: index,
+        histogram: histogram,
+        max: max,
+        mean: mean,
+        min: min,
+        stddev: variance.sqrt(),
+    }))
+}
+
+pub fn transform_coords(src: &SpatialRef, dst: &SpatialRef,
+        xs: &mut [f64], ys: &mut [f64]) -> Result<(), Box<dyn Error>> {
+    let transform = CoordTransform::new(src, dst)?;
+
+    let mut zs = vec![0f64; xs.len()];
+    transform.transform_coords(xs, ys, &mut zs)?;
+    Ok(())
+}
+
+pub fn warp(dataset: &Dataset, epsg_code: u32, resolution: Option<f64>,
+        resampling: &str) -> Result<Dataset, Box<dyn Error>> {
+    // compile gdalwarp arguments
+    let mut args = vec!["-of".to_string(), "MEM".to_string(),
+        "-t_srs".to_string(), format!("EPSG:{}", epsg_code),
+        "-r".to_string(), resampling.to_string()];
+    if let Some(resolution) = resolution {
+        args.push("-tr".to_string());
+        args.push(resolution.to_string());
+        args.push(resolution.to_string());
+    }
+
+    gdal_warp(dataset, args).map_err(|_| format!(
+        "failed to warp dataset to EPSG:{}", epsg_code).into())
+}
+
+pub fn align(dataset: &Dataset, reference: &Dataset, resampling: &str)
+        -> Result<Dataset, Box<dyn Error>> {
+    // warp onto the projection, extent, and size of the reference
+    let (x, y) = reference.raster_size();
+    let transform = reference.geo_transform()?;
+    let (min_x, max_y) = (transform[0], transform[3]);
+    let max_x = min_x + transform[1] * x as f64;
+    let min_y = max_y + transform[5] * y as f64;
+
+    let args = vec!["-of".to_string(), "MEM".to_string(),
+        "-t_srs".to_string(), reference.projection(),
+        "-te".to_string(), min_x.to_string(), min_y.to_string(),
+        max_x.to_string(), max_y.to_string(),
+        "-ts".to_string(), x.to_string(), y.to_string(),
+        "-r".to_string(), resampling.to_string()];
+
+    gdal_warp(dataset, args).map_err(|_|
+        "failed to align dataset with reference".into())
+}
+
+pub fn select_bands(dataset: &Dataset, bands: &[usize])
+        -> Result<Dataset, Box<dyn Error>> {
+    // copy bands in the provided order, marking nodata as nan
+    let selected = create_dataset(dataset, bands.len())?;
+    for (i, index) in bands.iter().enumerate() {
+        let band = read_band(dataset, *index)?;
+        let mut values = (0..band.values.len()).map(|j| {
+            match band.is_valid(j) {
+                true => band.values[j],
+                false => std::f64::NAN,
+            }
+        }).collect();
+
+        write_band(&selected, i + 1, &mut values)?;
+    }
+
+    Ok(selected)
+}
+
+fn gdal_warp(dataset: &Dataset, args: Vec<String>)
+        -> Result<Dataset, Box<dyn Error>> {
+    let c_args = args.into_iter().map(CString::new)
+        .collect::<Result<Vec<CString>, _>>()?;
+    let mut c_arg_ptrs: Vec<*mut c_char> = c_args.iter()
+        .map(|x| x.as_ptr() as *mut c_char).collect();
+    c_arg_ptrs.push(std::ptr::null_mut());
+
+    let c_filename = CString::new("")?;
+    let mut c_datasets = vec![dataset.c_dataset()];
+    let mut usage_error: c_int = 0;
+    let c_dataset = unsafe {
+        let c_options = gdal_sys::GDALWarpAppOptionsNew(
+            c_arg_ptrs.as_mut_ptr(), std::ptr::null_mut());
+        if c_options.is_null() {
+            return Err("invalid warp options".into());
+        }
+
+        let c_dataset = gdal_sys::GDALWarp(c_filename.as_ptr(),
+            std::ptr::null_mut(), 1, c_datasets.as_mut_ptr(),
+            c_options, &mut usage_error);
+        gdal_sys::GDALWarpAppOptionsFree(c_options);
+        c_dataset
+    };
+
+    if c_dataset.is_null() {
+        return Err("failed to warp dataset".into());
+    }
+
+    Ok(unsafe { Dataset::from_c_dataset(c_dataset) })
+}
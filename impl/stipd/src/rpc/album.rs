@@ -1,29 +1,46 @@
 use geocode::Geocode;
-use protobuf::{Album, AlbumBroadcastReply, AlbumBroadcastRequest, AlbumBroadcastType, AlbumCloseReply, AlbumCloseRequest, AlbumCreateReply, AlbumCreateRequest, AlbumDeleteReply, AlbumDeleteRequest, AlbumListReply, AlbumListRequest, AlbumManagement, AlbumManagementClient, AlbumOpenReply, AlbumOpenRequest};
+use protobuf::{Album, AlbumBroadcastReply, AlbumBroadcastRequest, AlbumBroadcastType, AlbumCloseReply, AlbumCloseRequest, AlbumCollectReply, AlbumCollectRequest, AlbumCreateReply, AlbumCreateRequest, AlbumDeleteReply, AlbumDeleteRequest, AlbumExportReply, AlbumExportRequest, AlbumHoldReply, AlbumHoldRequest, AlbumImportReply, AlbumImportRequest, AlbumListReply, AlbumListRequest, AlbumManagement, AlbumManagementClient, AlbumOpenReply, AlbumOpenRequest, AlbumReindexReply, AlbumReindexRequest, AlbumRetentionReply, AlbumRetentionRequest};
 use swarm::prelude::Dht;
 use tonic::{Code, Request, Response, Status};
 
 use crate::album::AlbumManager;
-use crate::task::{Task, TaskManager};
+use crate::auth::Permission;
+use crate::task::{RetryPolicy, Task, TaskManager};
+use crate::task::archive::{AlbumManifest, ArchiveExportTask, ArchiveImportTask};
+use crate::task::collect::CollectTask;
+use crate::task::export::Destination;
 use crate::task::open::OpenTask;
+use crate::transfer::LatencyTracker;
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
 use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+const REINDEX_POLL_DURATION: Duration = Duration::from_millis(1000);
+// paths of each index discrepancy returned in reindex replies, the
+//   remainder are only counted
+const REINDEX_SAMPLE_SIZE: usize = 100;
 
 pub struct AlbumManagementImpl {
+    admin_token: Option<String>,
     album_manager: Arc<RwLock<AlbumManager>>,
     dht: Arc<Dht>,
     task_manager: Arc<RwLock<TaskManager>>,
+    tracker: Arc<LatencyTracker>,
 }
 
 impl AlbumManagementImpl {
-    pub fn new(album_manager: Arc<RwLock<AlbumManager>>,
+    pub fn new(admin_token: Option<String>,
+            album_manager: Arc<RwLock<AlbumManager>>,
             dht: Arc<Dht>, task_manager: Arc<RwLock<TaskManager>>)
             -> AlbumManagementImpl {
         AlbumManagementImpl {
+            admin_token: admin_token,
             album_manager: album_manager,
             dht: dht,
             task_manager: task_manager,
+            tracker: Arc::new(LatencyTracker::new()),
         }
     }
 }
@@ -33,23 +50,31 @@ impl AlbumManagement for AlbumManagementImpl {
     async fn broadcast(&self, request: Request<AlbumBroadcastRequest>)
             -> Result<Response<AlbumBroadcastReply>, Status> {
         trace!("AlbumBroadcastRequest: {:?}", request);
+        crate::auth::authorize(&request, broadcast_album(request.get_ref()),
+            Permission::Admin)?;
+        crate::audit::record(&request);
         let request = request.get_ref();
 
         // send broadcast message to each dht node
         let mut create_replies = HashMap::new();
         let mut close_replies = HashMap::new();
+        let mut collect_replies = HashMap::new();
         let mut delete_replies = HashMap::new();
+        let mut hold_replies = HashMap::new();
         let mut open_replies = HashMap::new();
+        let mut reindex_replies = HashMap::new();
+        let mut retention_replies = HashMap::new();
 
         let mut task_id = None;
+        let mut reindex_handles = Vec::new();
         for node in self.dht.nodes() {
             // get rpc address
-            let addr = format!("http://{}:{}", node.get_ip_address(),
+            let addr = format!("{}:{}", node.get_ip_address(),
                 node.get_metadata("rpc_port").unwrap());
 
             // initialize grpc client
-            let mut client = match AlbumManagementClient::connect(
-                    addr.clone()).await {
+            let mut client = match crate::tls::channel(
+                    &addr).await.map(AlbumManagementClient::new) {
                 Ok(client) => client,
                 Err(e) => return Err(Status::new(Code::Unavailable,
                     format!("connection to {} failed: {}", addr, e))),
@@ -87,6 +112,38 @@ impl AlbumManagement for AlbumManagementImpl {
                     delete_replies.insert(node.get_id(),
                         reply.get_ref().to_owned());
                 },
+                AlbumBroadcastType::AlbumCollect => {
+                    // compile new AlbumCollectRequest
+                    let mut collect_request =
+                        request.collect_request.clone().unwrap();
+                    if let Some(task_id) = task_id {
+                        collect_request.task_id = Some(task_id);
+                    }
+
+                    // submit request
+                    let reply = match client.collect(collect_request).await {
+                        Ok(reply) => reply,
+                        Err(e) => return Err(Status::new(Code::Unknown,
+                            format!("collect broadcast failed: {}", e))),
+                    };
+                    collect_replies.insert(node.get_id(),
+                        reply.get_ref().to_owned());
+
+                    // dry runs start no task
+                    if reply.get_ref().task_id != 0 {
+                        task_id = Some(reply.get_ref().task_id);
+                    }
+                },
+                AlbumBroadcastType::AlbumHold => {
+                    let reply = match client.hold(request
+                            .hold_request.clone().unwrap()).await {
+                        Ok(reply) => reply,
+                        Err(e) => return Err(Status::new(Code::Unknown,
+                            format!("hold broadcast failed: {}", e))),
+                    };
+                    hold_replies.insert(node.get_id(),
+                        reply.get_ref().to_owned());
+                },
                 AlbumBroadcastType::AlbumOpen => {
                     // compile new AlbumOpenRequest
                     let mut open_request =
@@ -107,7 +164,44 @@ impl AlbumManagement for AlbumManagementImpl {
                     // process reply
                     task_id = Some(reply.get_ref().task_id);
                 },
+                AlbumBroadcastType::AlbumReindex => {
+                    // nodes reply once reindexed, so share a task id
+                    //   chosen upfront and reindex concurrently
+                    let mut reindex_request =
+                        request.reindex_request.clone().unwrap();
+                    let reindex_task_id = *task_id.get_or_insert(
+                        reindex_request.task_id
+                            .unwrap_or(rand::random::<u64>()));
+                    reindex_request.task_id = Some(reindex_task_id);
+
+                    // submit request
+                    reindex_handles.push((node.get_id(), tokio::spawn(
+                        async move { client.reindex(reindex_request).await })));
+                },
+                AlbumBroadcastType::AlbumRetention => {
+                    let reply = match client.retention(request
+                            .retention_request.clone().unwrap()).await {
+                        Ok(reply) => reply,
+                        Err(e) => return Err(Status::new(Code::Unknown,
+                            format!("retention broadcast failed: {}", e))),
+                    };
+                    retention_replies.insert(node.get_id(),
+                        reply.get_ref().to_owned());
+                },
+            };
+        }
+
+        // collect reindex reports
+        for (node_id, handle) in reindex_handles {
+            let reply = match handle.await {
+                Ok(Ok(reply)) => reply,
+                Ok(Err(e)) => return Err(Status::new(Code::Unknown,
+                    format!("reindex broadcast failed: {}", e))),
+                Err(e) => return Err(Status::new(Code::Unknown,
+                    format!("reindex broadcast failed: {}", e))),
             };
+
+            reindex_replies.insert(node_id, reply.get_ref().to_owned());
         }
 
         // initialize reply
@@ -115,8 +209,12 @@ impl AlbumManagement for AlbumManagementImpl {
             message_type: request.message_type,
             create_replies: create_replies,
             close_replies: close_replies,
+            collect_replies: collect_replies,
             delete_replies: delete_replies,
+            hold_replies: hold_replies,
             open_replies: open_replies,
+            reindex_replies: reindex_replies,
+            retention_replies: retention_replies,
         };
 
         Ok(Response::new(reply))
@@ -125,6 +223,9 @@ impl AlbumManagement for AlbumManagementImpl {
     async fn close(&self, request: Request<AlbumCloseRequest>)
             -> Result<Response<AlbumCloseReply>, Status> {
         trace!("AlbumCloseRequest: {:?}", request);
+        crate::auth::authorize(&request, &request.get_ref().id,
+            Permission::Admin)?;
+        crate::audit::record(&request);
         let request = request.get_ref();
 
         // ensure album exists
@@ -143,9 +244,80 @@ impl AlbumManagement for AlbumManagementImpl {
         Ok(Response::new(reply))
     }
 
+    async fn collect(&self, request: Request<AlbumCollectRequest>)
+            -> Result<Response<AlbumCollectReply>, Status> {
+        trace!("AlbumCollectRequest: {:?}", request);
+        crate::auth::authorize(&request, &request.get_ref().id,
+            Permission::Admin)?;
+        crate::audit::record(&request);
+        let request = request.get_ref();
+
+        // ensure album exists
+        let album = crate::rpc::assert_album_exists(
+            &self.album_manager, &request.id)?;
+
+        // initialize task
+        let task = match CollectTask::new(album, self.dht.clone()) {
+            Ok(task) => Arc::new(task),
+            Err(e) => return Err(Status::new(Code::InvalidArgument,
+                format!("failed to initialize CollectTask: {}", e))),
+        };
+
+        // report collectable files without removing them if requested
+        if request.dry_run.unwrap_or(false) {
+            let records = match task.records().await {
+                Ok(records) => records,
+                Err(e) => return Err(Status::new(Code::Unknown,
+                    format!("failed to evaluate dry run: {}", e))),
+            };
+
+            let byte_count = records.iter()
+                .map(|(_, file)| crate::compaction::len(
+                    std::path::Path::new(&file.0)).unwrap_or(0))
+                .sum();
+
+            return Ok(Response::new(AlbumCollectReply {
+                byte_count: Some(byte_count),
+                file_count: Some(records.len() as u32),
+                task_id: 0,
+            }));
+        }
+
+        // start task
+        let task_handle = match task.start(request.thread_count as u8,
+                None, RetryPolicy::default()) {
+            Ok(task_handle) => task_handle,
+            Err(e) => return Err(Status::new(Code::Unknown,
+                format!("failed to start CollectTask: {}", e))),
+        };
+
+        // register task with TaskHandler
+        let task_id = {
+            let mut task_manager = self.task_manager.write().unwrap();
+            match task_manager.register(task_handle,
+                    request.task_id, request.priority.unwrap_or(0)) {
+                Ok(task_id) => task_id,
+                Err(e) => return Err(Status::new(Code::Unknown,
+                    format!("failed to register CollectTask: {}", e))),
+            }
+        };
+
+        // initialize reply
+        let reply = AlbumCollectReply {
+            byte_count: None,
+            file_count: None,
+            task_id: task_id,
+        };
+
+        Ok(Response::new(reply))
+    }
+
     async fn create(&self, request: Request<AlbumCreateRequest>)
             -> Result<Response<AlbumCreateReply>, Status> {
         trace!("AlbumCreateRequest: {:?}", request);
+        crate::auth::authorize(&request, &request.get_ref().id,
+            Permission::Admin)?;
+        crate::audit::record(&request);
         let request = request.get_ref();
 
         // check if album already exists
@@ -163,7 +335,9 @@ impl AlbumManagement for AlbumManagementImpl {
         {
             let mut album_manager = self.album_manager.write().unwrap();
             if let Err(e) = album_manager.create(
-                    request.dht_key_length as i8, geocode, &request.id) {
+                    request.creation_options.clone(),
+                    request.dht_key_length as i8, geocode, &request.id,
+                    request.precision.map(|x| x as usize)) {
                 return Err(Status::new(Code::Unknown,
                     format!("failed to create album: {}", e)));
             }
@@ -178,6 +352,9 @@ impl AlbumManagement for AlbumManagementImpl {
     async fn delete(&self, request: Request<AlbumDeleteRequest>)
             -> Result<Response<AlbumDeleteReply>, Status> {
         trace!("AlbumDeleteRequest: {:?}", request);
+        crate::auth::authorize(&request, &request.get_ref().id,
+            Permission::Admin)?;
+        crate::audit::record(&request);
         let request = request.get_ref();
 
         // ensure album exists
@@ -199,15 +376,235 @@ impl AlbumManagement for AlbumManagementImpl {
         Ok(Response::new(reply))
     }
 
+    async fn export(&self, request: Request<AlbumExportRequest>)
+            -> Result<Response<AlbumExportReply>, Status> {
+        trace!("AlbumExportRequest: {:?}", request);
+        crate::auth::authorize(&request, &request.get_ref().id,
+            Permission::Read)?;
+        let request = request.get_ref();
+        let filter = &request.filter;
+
+        // ensure album exists
+        let album = crate::rpc::assert_album_exists(
+            &self.album_manager, &request.id)?;
+
+        // parse destination
+        let destination = match request.destination.parse::<Destination>() {
+            Ok(destination) => destination,
+            Err(e) => return Err(Status::new(Code::InvalidArgument, e)),
+        };
+
+        // initialize task
+        let task = match ArchiveExportTask::new(album, destination,
+                self.dht.clone(), filter.end_timestamp,
+                filter.geocode.clone(), filter.max_cloud_coverage,
+                filter.min_pixel_coverage, filter.platform.clone(),
+                filter.recurse, filter.source.clone(),
+                filter.start_timestamp, request.tenant.clone(),
+                self.tracker.clone()) {
+            Ok(task) => Arc::new(task),
+            Err(e) => return Err(Status::new(Code::InvalidArgument,
+                format!("failed to initialize ArchiveExportTask: {}", e))),
+        };
+
+        // start task
+        let mut task_handle = match task.start(request.thread_count as u8,
+                None, RetryPolicy::default()) {
+            Ok(task_handle) => task_handle,
+            Err(e) => return Err(Status::new(Code::Unknown,
+                format!("failed to start ArchiveExportTask: {}", e))),
+        };
+
+        task_handle.set_tenant(request.tenant.clone());
+
+        // register task with TaskHandler
+        let task_id = {
+            let mut task_manager = self.task_manager.write().unwrap();
+            match task_manager.register(task_handle, request.task_id,
+                    request.priority.unwrap_or(0)) {
+                Ok(task_id) => task_id,
+                Err(e) => return Err(Status::new(Code::Unknown,
+                    format!("failed to register ArchiveExportTask: {}", e))),
+            }
+        };
+
+        // initialize reply
+        let reply = AlbumExportReply {
+            task_id: task_id,
+        };
+
+        Ok(Response::new(reply))
+    }
+
+    async fn hold(&self, request: Request<AlbumHoldRequest>)
+            -> Result<Response<AlbumHoldReply>, Status> {
+        trace!("AlbumHoldRequest: {:?}", request);
+        crate::auth::authorize(&request, &request.get_ref().id,
+            Permission::Admin)?;
+        crate::audit::record(&request);
+        let request = request.get_ref();
+
+        // ensure album exists
+        let album = crate::rpc::assert_album_exists(
+            &self.album_manager, &request.id)?;
+
+        // update album holds
+        let (frozen, legal_hold) = {
+            let mut album = album.write().unwrap();
+            let frozen = request.frozen.unwrap_or(album.is_frozen());
+            let legal_hold =
+                request.legal_hold.unwrap_or(album.is_legal_hold());
+
+            // lifting a hold requires the node admin token
+            if (album.is_frozen() && !frozen)
+                    || (album.is_legal_hold() && !legal_hold) {
+                match &self.admin_token {
+                    Some(admin_token) if request.admin_token.as_ref()
+                        .map(|x| crate::auth::tokens_match(x, admin_token))
+                        .unwrap_or(false) => {},
+                    Some(_) => return Err(Status::new(
+                        Code::PermissionDenied,
+                        "invalid admin token".to_string())),
+                    None => return Err(Status::new(Code::PermissionDenied,
+                        "no admin token configured on node".to_string())),
+                }
+            }
+
+            if let Err(e) = album.set_holds(frozen, legal_hold) {
+                return Err(Status::new(Code::Unknown,
+                    format!("failed to set album holds: {}", e)));
+            }
+
+            (frozen, legal_hold)
+        };
+
+        // initialize reply
+        let reply = AlbumHoldReply {
+            frozen: frozen,
+            legal_hold: legal_hold,
+        };
+
+        Ok(Response::new(reply))
+    }
+
+    async fn import(&self, request: Request<AlbumImportRequest>)
+            -> Result<Response<AlbumImportReply>, Status> {
+        trace!("AlbumImportRequest: {:?}", request);
+        crate::auth::authorize(&request, &request.get_ref().id,
+            Permission::Write)?;
+
+        // creating missing albums requires admin permission
+        let exists = self.album_manager.read().unwrap()
+            .get(&request.get_ref().id).is_some();
+        if !exists {
+            crate::auth::authorize(&request, &request.get_ref().id,
+                Permission::Admin)?;
+        }
+
+        crate::audit::record(&request);
+
+        // archives are read from a directory on this node
+        let directory = PathBuf::from(&request.get_ref().directory);
+        if !directory.is_absolute() {
+            return Err(Status::new(Code::InvalidArgument,
+                format!("import directory '{}' must be absolute",
+                    directory.to_string_lossy())));
+        }
+
+        let manifest = match AlbumManifest::read(&directory) {
+            Ok(manifest) => manifest,
+            Err(e) => return Err(Status::new(Code::InvalidArgument,
+                format!("failed to read album archive: {}", e))),
+        };
+
+        // create missing albums on every node using the archive settings
+        if !exists {
+            let create_request = AlbumCreateRequest {
+                creation_options: manifest.creation_options.clone(),
+                dht_key_length: manifest.dht_key_length as i32,
+                geocode: match manifest.geocode {
+                    Geocode::Geohash => protobuf::Geocode::Geohash as i32,
+                    Geocode::QuadTile => protobuf::Geocode::Quadtile as i32,
+                },
+                id: request.get_ref().id.clone(),
+                precision: manifest.precision.map(|x| x as u32),
+            };
+
+            for node in self.dht.nodes() {
+                let addr = format!("{}:{}", node.get_ip_address(),
+                    node.get_metadata("rpc_port").unwrap());
+
+                let mut client = match crate::tls::channel(
+                        &addr).await.map(AlbumManagementClient::new) {
+                    Ok(client) => client,
+                    Err(e) => return Err(Status::new(Code::Unavailable,
+                        format!("connection to {} failed: {}", addr, e))),
+                };
+
+                if let Err(e) = client.create(create_request.clone()).await {
+                    return Err(Status::new(Code::Unknown,
+                        format!("failed to create album on {}: {}",
+                            addr, e)));
+                }
+            }
+        }
+
+        let request = request.get_ref();
+
+        // ensure album exists
+        let album = crate::rpc::assert_album_exists(
+            &self.album_manager, &request.id)?;
+
+        // initialize task
+        let task = match ArchiveImportTask::new(album,
+                self.dht.clone(), directory) {
+            Ok(task) => Arc::new(task),
+            Err(e) => return Err(Status::new(Code::InvalidArgument,
+                format!("failed to initialize ArchiveImportTask: {}", e))),
+        };
+
+        // start task
+        let task_handle = match task.start(request.thread_count as u8,
+                None, RetryPolicy::default()) {
+            Ok(task_handle) => task_handle,
+            Err(e) => return Err(Status::new(Code::Unknown,
+                format!("failed to start ArchiveImportTask: {}", e))),
+        };
+
+        // register task with TaskHandler
+        let task_id = {
+            let mut task_manager = self.task_manager.write().unwrap();
+            match task_manager.register(task_handle, request.task_id,
+                    request.priority.unwrap_or(0)) {
+                Ok(task_id) => task_id,
+                Err(e) => return Err(Status::new(Code::Unknown,
+                    format!("failed to register ArchiveImportTask: {}", e))),
+            }
+        };
+
+        // initialize reply
+        let reply = AlbumImportReply {
+            task_id: task_id,
+        };
+
+        Ok(Response::new(reply))
+    }
+
     async fn list(&self, request: Request<AlbumListRequest>)
             -> Result<Response<AlbumListReply>, Status> {
         trace!("AlbumListRequest: {:?}", request);
+        crate::auth::authenticate(&request)?;
 
         // populate albums from AlbumManager
         let mut albums = Vec::new();
         {
             let album_manager = self.album_manager.read().unwrap();
             for (id, album) in album_manager.iter() {
+                // omit albums the credential may not read
+                if !crate::auth::permits(&request, id, Permission::Read) {
+                    continue;
+                }
+
                 let album = album.read().unwrap();
 
                 // parse album metadata
@@ -226,9 +623,15 @@ impl AlbumManagement for AlbumManagementImpl {
 
                 // add Album protobuf
                 albums.push(Album {
+                    creation_options: album.get_creation_options().clone(),
                     dht_key_length: album.get_dht_key_length() as i32,
+                    frozen: album.is_frozen(),
                     geocode: geocode as i32,
                     id: id.to_string(),
+                    legal_hold: album.is_legal_hold(),
+                    max_bytes: album.get_retention().max_bytes,
+                    precision: album.get_precision().map(|x| x as u32),
+                    raw_ttl_days: album.get_retention().raw_ttl_days,
                     status: status as i32,
                 });
             }
@@ -245,6 +648,9 @@ impl AlbumManagement for AlbumManagementImpl {
     async fn open(&self, request: Request<AlbumOpenRequest>)
             -> Result<Response<AlbumOpenReply>, Status> {
         trace!("AlbumOpenRequest: {:?}", request);
+        crate::auth::authorize(&request, &request.get_ref().id,
+            Permission::Admin)?;
+        crate::audit::record(&request);
         let request = request.get_ref();
 
         // ensure album exists
@@ -254,17 +660,19 @@ impl AlbumManagement for AlbumManagementImpl {
         // open album
         {
             let mut album = album.write().unwrap();
-            if let Err(e) = album.open() {
+            if let Err(e) = album.open(true) {
                 return Err(Status::new(Code::Unknown,
                     format!("failed to open album: {}", e)))
             }
         }
 
         // initialize task
-        let task = Arc::new(OpenTask::new(album));
+        let task = Arc::new(OpenTask::new(album,
+            request.thread_count as u8));
 
         // start task
-        let task_handle = match task.start(request.thread_count as u8) {
+        let task_handle = match task.start(request.thread_count as u8,
+                None, RetryPolicy::default()) {
             Ok(task_handle) => task_handle,
             Err(e) => return Err(Status::new(Code::Unknown,
                 format!("failed to start OpenTask: {}", e))),
@@ -273,7 +681,8 @@ impl AlbumManagement for AlbumManagementImpl {
         // register task with TaskHandler
         let task_id = {
             let mut task_manager = self.task_manager.write().unwrap();
-            match task_manager.register(task_handle, request.task_id) {
+            match task_manager.register(task_handle,
+                    request.task_id, request.priority.unwrap_or(0)) {
                 Ok(task_id) => task_id,
                 Err(e) => return Err(Status::new(Code::Unknown,
                     format!("failed to register OpenTask: {}", e))),
@@ -287,4 +696,171 @@ impl AlbumManagement for AlbumManagementImpl {
 
         Ok(Response::new(reply))
     }
+
+    async fn reindex(&self, request: Request<AlbumReindexRequest>)
+            -> Result<Response<AlbumReindexReply>, Status> {
+        trace!("AlbumReindexRequest: {:?}", request);
+        crate::auth::authorize(&request, &request.get_ref().id,
+            Permission::Admin)?;
+        crate::audit::record(&request);
+        let request = request.get_ref();
+
+        // ensure album exists
+        let album = crate::rpc::assert_album_exists(
+            &self.album_manager, &request.id)?;
+
+        // retain the paths previously indexed, listed before locking the
+        //   album so writes are not blocked while the index is scanned
+        let indexed_paths = match album.read().unwrap().indexed_paths() {
+            Ok(indexed_paths) => indexed_paths,
+            Err(e) => return Err(Status::new(Code::Unknown,
+                format!("failed to list album index: {}", e))),
+        };
+
+        // replace album index
+        if let Err(e) = album.write().unwrap().open(false) {
+            return Err(Status::new(Code::Unknown,
+                format!("failed to reset album index: {}", e)))
+        }
+
+        // initialize task
+        let task = Arc::new(OpenTask::new(album.clone(),
+            request.thread_count as u8));
+
+        // start task
+        let task_handle = match task.start(request.thread_count as u8,
+                None, RetryPolicy::default()) {
+            Ok(task_handle) => task_handle,
+            Err(e) => return Err(Status::new(Code::Unknown,
+                format!("failed to start OpenTask: {}", e))),
+        };
+
+        // register task with TaskHandler
+        let task_id = {
+            let mut task_manager = self.task_manager.write().unwrap();
+            match task_manager.register(task_handle,
+                    request.task_id, request.priority.unwrap_or(0)) {
+                Ok(task_id) => task_id,
+                Err(e) => return Err(Status::new(Code::Unknown,
+                    format!("failed to register OpenTask: {}", e))),
+            }
+        };
+
+        // wait for the reindex to complete
+        while self.task_manager.read().unwrap().get(&task_id)
+                .map(|x| x.running()).unwrap_or(false) {
+            tokio::time::delay_for(REINDEX_POLL_DURATION).await;
+        }
+
+        // compare stored images with those indexed before and after
+        let (stored_paths, reindexed_paths) = {
+            let album = album.read().unwrap();
+            match album.stored_paths().and_then(|stored_paths|
+                    Ok((stored_paths, album.indexed_paths()?))) {
+                Ok((stored_paths, reindexed_paths)) => (stored_paths,
+                    reindexed_paths.unwrap_or_else(HashSet::new)),
+                Err(e) => return Err(Status::new(Code::Unknown,
+                    format!("failed to compare album index: {}", e))),
+            }
+        };
+
+        // images which failed to index are corrupt, and without a prior
+        //   index no images are known to be missing or orphaned
+        let corrupt = stored_paths.difference(&reindexed_paths).cloned();
+        let (missing, orphaned) = match &indexed_paths {
+            Some(indexed_paths) => (
+                indexed_paths.difference(&stored_paths).cloned().collect(),
+                reindexed_paths.difference(indexed_paths).cloned().collect()),
+            None => (Vec::new(), Vec::new()),
+        };
+
+        // initialize reply
+        let (corrupt, corrupt_count) = sample_paths(corrupt.collect());
+        let (missing, missing_count) = sample_paths(missing);
+        let (orphaned, orphaned_count) = sample_paths(orphaned);
+        let reply = AlbumReindexReply {
+            task_id: task_id,
+            corrupt: corrupt,
+            corrupt_count: Some(corrupt_count),
+            missing: missing,
+            missing_count: Some(missing_count),
+            orphaned: orphaned,
+            orphaned_count: Some(orphaned_count),
+        };
+
+        Ok(Response::new(reply))
+    }
+
+    async fn retention(&self, request: Request<AlbumRetentionRequest>)
+            -> Result<Response<AlbumRetentionReply>, Status> {
+        trace!("AlbumRetentionRequest: {:?}", request);
+        crate::auth::authorize(&request, &request.get_ref().id,
+            Permission::Admin)?;
+        crate::audit::record(&request);
+        let request = request.get_ref();
+
+        // ensure album exists
+        let album = crate::rpc::assert_album_exists(
+            &self.album_manager, &request.id)?;
+
+        {
+            // update specified retention rules, 0 values are unset
+            let mut album = album.write().unwrap();
+            let mut retention = album.get_retention().clone();
+            if let Some(max_bytes) = request.max_bytes {
+                retention.max_bytes = Some(max_bytes).filter(|x| *x != 0);
+            }
+
+            if let Some(raw_ttl_days) = request.raw_ttl_days {
+                retention.raw_ttl_days =
+                    Some(raw_ttl_days).filter(|x| *x != 0);
+            }
+
+            if let Err(e) = album.set_retention(retention) {
+                return Err(Status::new(Code::Unknown,
+                    format!("failed to set album retention: {}", e)));
+            }
+        }
+
+        // initialize reply
+        let reply = AlbumRetentionReply {};
+
+        Ok(Response::new(reply))
+    }
+}
+
+// album targeted by the broadcast message
+fn broadcast_album(request: &AlbumBroadcastRequest) -> &str {
+    let id = match AlbumBroadcastType::from_i32(request.message_type) {
+        Some(AlbumBroadcastType::AlbumCreate) =>
+            request.create_request.as_ref().map(|x| &x.id),
+        Some(AlbumBroadcastType::AlbumClose) =>
+            request.close_request.as_ref().map(|x| &x.id),
+        Some(AlbumBroadcastType::AlbumCollect) =>
+            request.collect_request.as_ref().map(|x| &x.id),
+        Some(AlbumBroadcastType::AlbumDelete) =>
+            request.delete_request.as_ref().map(|x| &x.id),
+        Some(AlbumBroadcastType::AlbumHold) =>
+            request.hold_request.as_ref().map(|x| &x.id),
+        Some(AlbumBroadcastType::AlbumOpen) =>
+            request.open_request.as_ref().map(|x| &x.id),
+        Some(AlbumBroadcastType::AlbumReindex) =>
+            request.reindex_request.as_ref().map(|x| &x.id),
+        Some(AlbumBroadcastType::AlbumRetention) =>
+            request.retention_request.as_ref().map(|x| &x.id),
+        None => None,
+    };
+
+    id.map(|x| x.as_str()).unwrap_or(crate::auth::ALL_ALBUMS)
+}
+
+// first sorted paths, bounding reply size, along with the total count
+fn sample_paths(paths: Vec<PathBuf>) -> (Vec<String>, u64) {
+    let mut paths: Vec<String> = paths.iter()
+        .map(|x| x.to_string_lossy().to_string()).collect();
+    paths.sort();
+
+    let count = paths.len() as u64;
+    paths.truncate(REINDEX_SAMPLE_SIZE);
+    (paths, count)
 }
@@ -0,0 +1,124 @@
+use protobuf::{FilterDeleteReply, FilterDeleteRequest, FilterListReply, FilterListRequest, FilterManagement, FilterManagementClient, FilterSaveReply, FilterSaveRequest, SavedFilter};
+use swarm::prelude::Dht;
+use tonic::{Code, Request, Response, Status};
+
+use crate::auth::{Permission, ALL_ALBUMS};
+use crate::filter::FilterStore;
+
+use std::sync::Arc;
+
+pub struct FilterManagementImpl {
+    dht: Arc<Dht>,
+    filter_store: Arc<FilterStore>,
+}
+
+impl FilterManagementImpl {
+    pub fn new(dht: Arc<Dht>, filter_store: Arc<FilterStore>)
+            -> FilterManagementImpl {
+        FilterManagementImpl {
+            dht: dht,
+            filter_store: filter_store,
+        }
+    }
+}
+
+#[tonic::async_trait]
+impl FilterManagement for FilterManagementImpl {
+    async fn delete(&self, request: Request<FilterDeleteRequest>)
+            -> Result<Response<FilterDeleteReply>, Status> {
+        trace!("FilterDeleteRequest: {:?}", request);
+        crate::auth::authorize(&request, ALL_ALBUMS, Permission::Write)?;
+        crate::audit::record(&request);
+        let request = request.get_ref();
+
+        // delete filter locally
+        if request.local.unwrap_or(false) {
+            if let Err(e) = self.filter_store.delete(&request.name) {
+                return Err(Status::new(Code::NotFound,
+                    format!("failed to delete filter: {}", e)));
+            }
+
+            return Ok(Response::new(FilterDeleteReply {}));
+        }
+
+        // delete filter at each dht node
+        let mut local_request = request.clone();
+        local_request.local = Some(true);
+
+        for node in self.dht.nodes() {
+            let addr = format!("{}:{}", node.get_ip_address(),
+                node.get_metadata("rpc_port").unwrap());
+
+            let mut client = match crate::tls::channel(
+                    &addr).await.map(FilterManagementClient::new) {
+                Ok(client) => client,
+                Err(e) => return Err(Status::new(Code::Unavailable,
+                    format!("connection to {} failed: {}", addr, e))),
+            };
+
+            client.delete(Request::new(local_request.clone())).await?;
+        }
+
+        Ok(Response::new(FilterDeleteReply {}))
+    }
+
+    async fn list(&self, request: Request<FilterListRequest>)
+            -> Result<Response<FilterListReply>, Status> {
+        trace!("FilterListRequest: {:?}", request);
+        crate::auth::authenticate(&request)?;
+
+        // initialize reply
+        let reply = FilterListReply {
+            filters: self.filter_store.list().into_iter()
+                .map(|(name, filter)| SavedFilter {
+                    filter: filter,
+                    name: name,
+                }).collect(),
+        };
+
+        Ok(Response::new(reply))
+    }
+
+    async fn save(&self, request: Request<FilterSaveRequest>)
+            -> Result<Response<FilterSaveReply>, Status> {
+        trace!("FilterSaveRequest: {:?}", request);
+        crate::auth::authorize(&request, ALL_ALBUMS, Permission::Write)?;
+        crate::audit::record(&request);
+        let request = request.get_ref();
+
+        // saved filters may extend other saved filters
+        let filter = crate::rpc::resolve_filter(
+            &self.filter_store, &request.filter)?;
+
+        // save filter locally
+        if request.local.unwrap_or(false) {
+            if let Err(e) = self.filter_store.save(&request.name, &filter) {
+                return Err(Status::new(Code::InvalidArgument,
+                    format!("failed to save filter: {}", e)));
+            }
+
+            return Ok(Response::new(FilterSaveReply {}));
+        }
+
+        // save resolved filter at each dht node
+        let mut local_request = request.clone();
+        local_request.filter = filter;
+        local_request.local = Some(true);
+
+        for node in self.dht.nodes() {
+            let addr = format!("{}:{}", node.get_ip_address(),
+                node.get_metadata("rpc_port").unwrap());
+
+            let mut client = match crate::tls::channel(
+                    &addr).await.map(FilterManagementClient::new) {
+                Ok(client) => client,
+                Err(e) => return Err(Status::new(Code::Unavailable,
+                    format!("connection to {} failed: {}", addr, e))),
+            };
+
+            client.save(Request::new(local_request.clone())).await?;
+        }
+
+        Ok(Response::new(FilterSaveReply {}))
+    }
+}
@@ -0,0 +1,86 @@
+use protobuf::health::{Health, HealthCheckRequest, HealthCheckResponse};
+use protobuf::health::health_check_response::ServingStatus;
+use tokio::sync::mpsc::Receiver;
+use tonic::{Code, Request, Response, Status};
+
+use std::time::Duration;
+
+const WATCH_POLL_DURATION: Duration = Duration::from_secs(5);
+const WATCH_REPEAT_POLLS: u32 = 12;
+
+// health checks are used by load balancers and are not authenticated
+pub struct HealthImpl {
+    node_id: u32,
+}
+
+impl HealthImpl {
+    pub fn new(node_id: u32) -> HealthImpl {
+        HealthImpl {
+            node_id: node_id,
+        }
+    }
+}
+
+#[tonic::async_trait]
+impl Health for HealthImpl {
+    async fn check(&self, request: Request<HealthCheckRequest>)
+            -> Result<Response<HealthCheckResponse>, Status> {
+        trace!("HealthCheckRequest: {:?}", request);
+        let request = request.get_ref();
+
+        match status(self.node_id, &request.service) {
+            ServingStatus::ServiceUnknown => Err(Status::new(Code::NotFound,
+                format!("service '{}' does not exist", request.service))),
+            status => Ok(Response::new(HealthCheckResponse {
+                status: status as i32,
+            })),
+        }
+    }
+
+    type WatchStream = Receiver<Result<HealthCheckResponse, Status>>;
+    async fn watch(&self, request: Request<HealthCheckRequest>)
+            -> Result<Response<Self::WatchStream>, Status> {
+        trace!("HealthWatchRequest: {:?}", request);
+        let service = request.into_inner().service;
+        let node_id = self.node_id;
+
+        // send the current status and each subsequent change, repeating
+        //   it periodically so watches of disconnected clients terminate
+        let (mut tx, rx) = tokio::sync::mpsc::channel(4);
+        tokio::spawn(async move {
+            let (mut last_status, mut polls) = (None, 0);
+            loop {
+                let status = status(node_id, &service);
+                if last_status != Some(status)
+                        || polls % WATCH_REPEAT_POLLS == 0 {
+                    let reply = HealthCheckResponse {
+                        status: status as i32,
+                    };
+
+                    if let Err(e) = tx.send(Ok(reply)).await {
+                        debug!("failed to send health status: {}", e);
+                        return;
+                    }
+
+                    last_status = Some(status);
+                }
+
+                polls += 1;
+                tokio::time::delay_for(WATCH_POLL_DURATION).await;
+            }
+        });
+
+        Ok(Response::new(rx))
+    }
+}
+
+fn status(node_id: u32, service: &str) -> ServingStatus {
+    // an empty service name refers to the node as a whole
+    if !service.is_empty() && !super::SERVICES.contains(&service) {
+        ServingStatus::ServiceUnknown
+    } else if crate::cluster::is_decommissioned(node_id) {
+        ServingStatus::NotServing
+    } else {
+        ServingStatus::Serving
+    }
+}
@@ -1,32 +1,86 @@
-use protobuf::{self, ImageBroadcastReply, ImageBroadcastRequest, ImageBroadcastType, ImageCoalesceReply, ImageCoalesceRequest, ImageFillReply, ImageFillRequest, ImageListRequest, ImageManagement, ImageManagementClient, ImageStoreReply, ImageStoreRequest, ImageSearchRequest, ImageSplitReply, ImageSplitRequest, Extent, File, Image, ImageFormat as ProtoImageFormat};
+use gdal::Dataset;
+use gdal::spatial_ref::SpatialRef;
+use geocode::Geocode;
+use protobuf::{self, DryRunReport, ImageBandMathReply, ImageBandMathRequest, ImageBroadcastReply, ImageBroadcastRequest, ImageBroadcastType, ImageCalcReply, ImageCalcRequest, ImageChangeReply, ImageChangeRequest, ImageCoalesceReply, ImageCoalesceRequest, ImageCompositeReply, ImageCompositeRequest, ImageCopyReply, ImageCopyRequest, ImageCoverageReply, ImageCoverageRequest, ImageDeleteReply, ImageDeleteRequest, ImageDistinctReply, ImageDistinctRequest, ImageDownloadChunk, ImageDownloadRequest, ImageExecuteReply, ImageExecuteRequest, ImageExportReply, ImageExportRequest, ImageFillReply, ImageFillRequest, ImageListRequest, ImageManagement, ImageManagementClient, ImageMosaicChunk, ImageMosaicRequest, ImageOverviewReply, ImageOverviewRequest, ImagePipelineReply, ImagePipelineRequest, ImagePreloadReply, ImagePreloadRequest, ImagePreviewReply, ImagePreviewRequest, ImageReadTransform, ImageReprojectReply, ImageReprojectRequest, ImageSample, ImageSampleRequest, ImageStoreReply, ImageStoreRequest, ImageSearchRequest, ImageSplitReply, ImageSplitRequest, ImageStatisticsReply, ImageStatisticsRequest, ImageUpdateReply, ImageUpdateRequest, ImageUploadChunk, ImageUploadHeader, ImageUploadReply, ImageZonalReply, ImageZonalRequest, BandStatistics, CoverageInterval, ZonalStatistics, Extent, File, Image, ImageFormat as ProtoImageFormat};
 use swarm::prelude::Dht;
 use tokio::sync::mpsc::Receiver;
 use tonic::{Code, Request, Response, Status};
 
-use crate::album::AlbumManager;
-use crate::task::{Task, TaskManager};
+use crate::album::{Album, AlbumManager};
+use crate::auth::Permission;
+use crate::expression::Expression;
+use crate::fill::DEFAULT_FILL_STRATEGY;
+use crate::filter::FilterStore;
+use crate::index::{ListCursor, TimeInterval};
+use crate::mask::CloudMask;
+use crate::quicklook::QuicklookFormat;
+use crate::task::{RetryPolicy, Task, TaskManager};
+use crate::task::bandmath::{BandIndex, BandMathTask};
+use crate::task::calc::CalcTask;
+use crate::task::change::{ChangeMethod, ChangeTask};
 use crate::task::coalesce::CoalesceTask;
+use crate::task::composite::{CompositeMethod, CompositeTask};
+use crate::task::delete::DeleteTask;
+use crate::task::execute::ExecuteTask;
+use crate::task::export::{Destination, ExportAssets, ExportTask};
 use crate::task::fill::FillTask;
-use crate::task::store::{StoreEarthExplorerTask, ImageFormat};
+use crate::task::preload::PreloadTask;
+use crate::task::journal::TaskDefinition;
+use crate::task::overview::OverviewTask;
+use crate::task::reproject::ReprojectTask;
+use crate::task::store::{StoreEarthExplorerTask, StorePair, ImageFormat};
+use crate::task::store::generic;
+use crate::task::copy::CopyTask;
 use crate::task::split::SplitTask;
+use crate::task::statistics::StatisticsTask;
+use crate::task::update::UpdateTask;
+use crate::transfer::{LatencyTracker, ReadRequest, ReadTransform, Transaction};
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::io::Write;
+use std::net::SocketAddr;
+use std::path::Path;
 use std::sync::{Arc, RwLock};
 
+// size of GeoTIFF chunks streamed in download and mosaic replies
+const MOSAIC_CHUNK_SIZE: usize = 1024 * 1024;
+
+// default histogram bins of computed tile statistics
+const STATISTICS_BINS: u32 = 64;
+
+// file rows read from an album index per list page
+const LIST_PAGE_SIZE: usize = 256;
+
 pub struct ImageManagementImpl {
     album_manager: Arc<RwLock<AlbumManager>>,
     dht: Arc<Dht>,
+    filter_store: Arc<FilterStore>,
     task_manager: Arc<RwLock<TaskManager>>,
+    tracker: Arc<LatencyTracker>,
 }
 
 impl ImageManagementImpl {
     pub fn new(album_manager: Arc<RwLock<AlbumManager>>,
-            dht: Arc<Dht>,
+            dht: Arc<Dht>, filter_store: Arc<FilterStore>,
             task_manager: Arc<RwLock<TaskManager>>) -> ImageManagementImpl {
         ImageManagementImpl {
             album_manager: album_manager,
             dht: dht,
+            filter_store: filter_store,
             task_manager: task_manager,
+            tracker: Arc::new(LatencyTracker::new()),
+        }
+    }
+
+    fn add_recurring(&self, task_id: Option<u64>,
+            definition: TaskDefinition, schedule: &str)
+            -> Result<u64, Status> {
+        let task_id = task_id.unwrap_or(rand::random::<u64>());
+        let mut task_manager = self.task_manager.write().unwrap();
+        match task_manager.add_recurring(task_id, definition, schedule) {
+            Ok(_) => Ok(task_id),
+            Err(e) => Err(Status::new(Code::InvalidArgument,
+                format!("failed to register recurring task: {}", e))),
         }
     }
 }
@@ -36,22 +90,49 @@ impl ImageManagement for ImageManagementImpl {
     async fn broadcast(&self, request: Request<ImageBroadcastRequest>)
             -> Result<Response<ImageBroadcastReply>, Status> {
         trace!("ImageBroadcastRequest: {:?}", request);
+        let (albums, permission) = broadcast_albums(request.get_ref());
+        for album in albums {
+            crate::auth::authorize(&request, album, permission)?;
+        }
+
+        // copies additionally read from the source album
+        if let Some(copy_request) = &request.get_ref().copy_request {
+            crate::auth::authorize(&request, &copy_request.album,
+                Permission::Read)?;
+        }
+
+        if permission != Permission::Read {
+            crate::audit::record(&request);
+        }
+
         let request = request.get_ref();
 
         // send broadcast message to each dht node
+        let mut band_math_replies = HashMap::new();
+        let mut calc_replies = HashMap::new();
+        let mut change_replies = HashMap::new();
         let mut coalesce_replies = HashMap::new();
+        let mut composite_replies = HashMap::new();
+        let mut copy_replies = HashMap::new();
+        let mut delete_replies = HashMap::new();
+        let mut execute_replies = HashMap::new();
         let mut fill_replies = HashMap::new();
+        let mut pipeline_replies = HashMap::new();
+        let mut preload_replies = HashMap::new();
+        let mut reproject_replies = HashMap::new();
         let mut split_replies = HashMap::new();
+        let mut statistics_replies = HashMap::new();
+        let mut update_replies = HashMap::new();
 
         let mut task_id = None;
         for node in self.dht.nodes() {
             // get rpc address
-            let addr = format!("http://{}:{}", node.get_ip_address(),
+            let addr = format!("{}:{}", node.get_ip_address(),
                 node.get_metadata("rpc_port").unwrap());
 
             // initialize grpc client
-            let mut client = match ImageManagementClient::connect(
-                    addr.clone()).await {
+            let mut client = match crate::tls::channel(
+                    &addr).await.map(ImageManagementClient::new) {
                 Ok(client) => client,
                 Err(e) => return Err(Status::new(Code::Unavailable,
                     format!("connection to {} failed: {}", addr, e))),
@@ -59,6 +140,66 @@ impl ImageManagement for ImageManagementImpl {
 
             // execute message at dht node
             match ImageBroadcastType::from_i32(request.message_type).unwrap() {
+                ImageBroadcastType::BandMath => {
+                    // compile new BandMathRequest
+                    let mut band_math_request =
+                        request.band_math_request.clone().unwrap();
+                    if let Some(task_id) = task_id {
+                        band_math_request.task_id = Some(task_id);
+                    }
+
+                    // submit request
+                    let reply = match client.band_math(band_math_request).await {
+                        Ok(reply) => reply,
+                        Err(e) => return Err(Status::new(Code::Unknown,
+                            format!("band math broadcast failed: {}", e))),
+                    };
+                    band_math_replies.insert(node.get_id(),
+                        reply.get_ref().to_owned());
+
+                    // process reply
+                    task_id = Some(reply.get_ref().task_id);
+                },
+                ImageBroadcastType::Calc => {
+                    // compile new CalcRequest
+                    let mut calc_request =
+                        request.calc_request.clone().unwrap();
+                    if let Some(task_id) = task_id {
+                        calc_request.task_id = Some(task_id);
+                    }
+
+                    // submit request
+                    let reply = match client.calc(calc_request).await {
+                        Ok(reply) => reply,
+                        Err(e) => return Err(Status::new(Code::Unknown,
+                            format!("calc broadcast failed: {}", e))),
+                    };
+                    calc_replies.insert(node.get_id(),
+                        reply.get_ref().to_owned());
+
+                    // process reply
+                    task_id = Some(reply.get_ref().task_id);
+                },
+                ImageBroadcastType::Change => {
+                    // compile new ChangeRequest
+                    let mut change_request =
+                        request.change_request.clone().unwrap();
+                    if let Some(task_id) = task_id {
+                        change_request.task_id = Some(task_id);
+                    }
+
+                    // submit request
+                    let reply = match client.change(change_request).await {
+                        Ok(reply) => reply,
+                        Err(e) => return Err(Status::new(Code::Unknown,
+                            format!("change broadcast failed: {}", e))),
+                    };
+                    change_replies.insert(node.get_id(),
+                        reply.get_ref().to_owned());
+
+                    // process reply
+                    task_id = Some(reply.get_ref().task_id);
+                },
                 ImageBroadcastType::Coalesce => {
                     // compile new CoalesceRequest
                     let mut coalesce_request =
@@ -79,6 +220,86 @@ impl ImageManagement for ImageManagementImpl {
                     // process reply
                     task_id = Some(reply.get_ref().task_id);
                 },
+                ImageBroadcastType::Composite => {
+                    // compile new CompositeRequest
+                    let mut composite_request =
+                        request.composite_request.clone().unwrap();
+                    if let Some(task_id) = task_id {
+                        composite_request.task_id = Some(task_id);
+                    }
+
+                    // submit request
+                    let reply = match client.composite(composite_request).await {
+                        Ok(reply) => reply,
+                        Err(e) => return Err(Status::new(Code::Unknown,
+                            format!("composite broadcast failed: {}", e))),
+                    };
+                    composite_replies.insert(node.get_id(),
+                        reply.get_ref().to_owned());
+
+                    // process reply
+                    task_id = Some(reply.get_ref().task_id);
+                },
+                ImageBroadcastType::Copy => {
+                    // compile new CopyRequest
+                    let mut copy_request =
+                        request.copy_request.clone().unwrap();
+                    if let Some(task_id) = task_id {
+                        copy_request.task_id = Some(task_id);
+                    }
+
+                    // submit request
+                    let reply = match client.copy(copy_request).await {
+                        Ok(reply) => reply,
+                        Err(e) => return Err(Status::new(Code::Unknown,
+                            format!("copy broadcast failed: {}", e))),
+                    };
+                    copy_replies.insert(node.get_id(),
+                        reply.get_ref().to_owned());
+
+                    // process reply
+                    task_id = Some(reply.get_ref().task_id);
+                },
+                ImageBroadcastType::Delete => {
+                    // compile new DeleteRequest
+                    let mut delete_request =
+                        request.delete_request.clone().unwrap();
+                    if let Some(task_id) = task_id {
+                        delete_request.task_id = Some(task_id);
+                    }
+
+                    // submit request
+                    let reply = match client.delete(delete_request).await {
+                        Ok(reply) => reply,
+                        Err(e) => return Err(Status::new(Code::Unknown,
+                            format!("delete broadcast failed: {}", e))),
+                    };
+                    delete_replies.insert(node.get_id(),
+                        reply.get_ref().to_owned());
+
+                    // process reply
+                    task_id = Some(reply.get_ref().task_id);
+                },
+                ImageBroadcastType::Execute => {
+                    // compile new ExecuteRequest
+                    let mut execute_request =
+                        request.execute_request.clone().unwrap();
+                    if let Some(task_id) = task_id {
+                        execute_request.task_id = Some(task_id);
+                    }
+
+                    // submit request
+                    let reply = match client.execute(execute_request).await {
+                        Ok(reply) => reply,
+                        Err(e) => return Err(Status::new(Code::Unknown,
+                            format!("execute broadcast failed: {}", e))),
+                    };
+                    execute_replies.insert(node.get_id(),
+                        reply.get_ref().to_owned());
+
+                    // process reply
+                    task_id = Some(reply.get_ref().task_id);
+                },
                 ImageBroadcastType::Fill => {
                     // compile new FillRequest
                     let mut fill_request =
@@ -99,6 +320,66 @@ impl ImageManagement for ImageManagementImpl {
                     // process reply
                     task_id = Some(reply.get_ref().task_id);
                 },
+                ImageBroadcastType::Pipeline => {
+                    // compile new PipelineRequest
+                    let mut pipeline_request =
+                        request.pipeline_request.clone().unwrap();
+                    if let Some(task_id) = task_id {
+                        pipeline_request.task_id = Some(task_id);
+                    }
+
+                    // submit request
+                    let reply = match client.pipeline(pipeline_request).await {
+                        Ok(reply) => reply,
+                        Err(e) => return Err(Status::new(Code::Unknown,
+                            format!("pipeline broadcast failed: {}", e))),
+                    };
+                    pipeline_replies.insert(node.get_id(),
+                        reply.get_ref().to_owned());
+
+                    // process reply
+                    task_id = Some(reply.get_ref().task_id);
+                },
+                ImageBroadcastType::Preload => {
+                    // compile new PreloadRequest
+                    let mut preload_request =
+                        request.preload_request.clone().unwrap();
+                    if let Some(task_id) = task_id {
+                        preload_request.task_id = Some(task_id);
+                    }
+
+                    // submit request
+                    let reply = match client.preload(preload_request).await {
+                        Ok(reply) => reply,
+                        Err(e) => return Err(Status::new(Code::Unknown,
+                            format!("preload broadcast failed: {}", e))),
+                    };
+                    preload_replies.insert(node.get_id(),
+                        reply.get_ref().to_owned());
+
+                    // process reply
+                    task_id = Some(reply.get_ref().task_id);
+                },
+                ImageBroadcastType::Reproject => {
+                    // compile new ReprojectRequest
+                    let mut reproject_request =
+                        request.reproject_request.clone().unwrap();
+                    if let Some(task_id) = task_id {
+                        reproject_request.task_id = Some(task_id);
+                    }
+
+                    // submit request
+                    let reply = match client.reproject(reproject_request).await {
+                        Ok(reply) => reply,
+                        Err(e) => return Err(Status::new(Code::Unknown,
+                            format!("reproject broadcast failed: {}", e))),
+                    };
+                    reproject_replies.insert(node.get_id(),
+                        reply.get_ref().to_owned());
+
+                    // process reply
+                    task_id = Some(reply.get_ref().task_id);
+                },
                 ImageBroadcastType::Split => {
                     // compile new SplitRequest
                     let mut split_request =
@@ -119,23 +400,78 @@ impl ImageManagement for ImageManagementImpl {
                     // process reply
                     task_id = Some(reply.get_ref().task_id);
                 },
+                ImageBroadcastType::Statistics => {
+                    // compile new StatisticsRequest
+                    let mut statistics_request =
+                        request.statistics_request.clone().unwrap();
+                    if let Some(task_id) = task_id {
+                        statistics_request.task_id = Some(task_id);
+                    }
+
+                    // submit request
+                    let reply = match client.statistics(statistics_request).await {
+                        Ok(reply) => reply,
+                        Err(e) => return Err(Status::new(Code::Unknown,
+                            format!("statistics broadcast failed: {}", e))),
+                    };
+                    statistics_replies.insert(node.get_id(),
+                        reply.get_ref().to_owned());
+
+                    // process reply
+                    task_id = Some(reply.get_ref().task_id);
+                },
+                ImageBroadcastType::Update => {
+                    // compile new UpdateRequest
+                    let mut update_request =
+                        request.update_request.clone().unwrap();
+                    if let Some(task_id) = task_id {
+                        update_request.task_id = Some(task_id);
+                    }
+
+                    // submit request
+                    let reply = match client.update(update_request).await {
+                        Ok(reply) => reply,
+                        Err(e) => return Err(Status::new(Code::Unknown,
+                            format!("update broadcast failed: {}", e))),
+                    };
+                    update_replies.insert(node.get_id(),
+                        reply.get_ref().to_owned());
+
+                    // process reply
+                    task_id = Some(reply.get_ref().task_id);
+                },
             };
         }
 
         // initialize reply
         let reply = ImageBroadcastReply {
             message_type: request.message_type,
+            band_math_replies: band_math_replies,
+            calc_replies: calc_replies,
+            change_replies: change_replies,
             coalesce_replies: coalesce_replies,
+            composite_replies: composite_replies,
+            copy_replies: copy_replies,
+            delete_replies: delete_replies,
+            execute_replies: execute_replies,
             fill_replies: fill_replies,
+            pipeline_replies: pipeline_replies,
+            preload_replies: preload_replies,
+            reproject_replies: reproject_replies,
             split_replies: split_replies,
+            statistics_replies: statistics_replies,
+            update_replies: update_replies,
         };
 
         Ok(Response::new(reply))
     }
 
-    async fn coalesce(&self, request: Request<ImageCoalesceRequest>)
-            -> Result<Response<ImageCoalesceReply>, Status> {
-        trace!("ImageCoalesceRequest: {:?}", request);
+    async fn band_math(&self, request: Request<ImageBandMathRequest>)
+            -> Result<Response<ImageBandMathReply>, Status> {
+        trace!("ImageBandMathRequest: {:?}", request);
+        crate::auth::authorize(&request, &request.get_ref().album,
+            Permission::Write)?;
+        crate::audit::record(&request);
         let request = request.get_ref();
         let filter = &request.filter;
 
@@ -143,42 +479,61 @@ impl ImageManagement for ImageManagementImpl {
         let album = crate::rpc::assert_album_exists(
             &self.album_manager, &request.album)?;
 
-        // initailize task
-        let task = Arc::new(CoalesceTask::new(album, self.dht.clone(),
-            filter.end_timestamp, filter.geocode.clone(),
-            filter.max_cloud_coverage, filter.min_pixel_coverage,
-            filter.platform.clone(), filter.recurse,
-            filter.source.clone(), request.platform.clone(), 
-            filter.start_timestamp, request.window_seconds));
+        // parse band index
+        let index = match request.index.parse::<BandIndex>() {
+            Ok(index) => index,
+            Err(e) => return Err(Status::new(Code::InvalidArgument, e)),
+        };
+
+        // initialize task
+        let bands = request.bands.iter().map(|x| *x as usize).collect();
+        let task = match BandMathTask::new(album, bands,
+                filter.end_timestamp.clone(), filter.geocode.clone(),
+                index, filter.max_cloud_coverage,
+                filter.min_pixel_coverage, filter.platform.clone(),
+                filter.recurse, request.scale.unwrap_or(1f64),
+                filter.source.clone(), filter.start_timestamp.clone(),
+                request.subdataset as u8) {
+            Ok(task) => Arc::new(task),
+            Err(e) => return Err(Status::new(Code::InvalidArgument,
+                format!("failed to initialize BandMathTask: {}", e))),
+        };
 
         // start task
-        let task_handle = match task.start(request.thread_count as u8) {
+        let mut task_handle = match task.start(request.thread_count as u8,
+                None, RetryPolicy::from(&request.retry_policy)) {
             Ok(task_handle) => task_handle,
             Err(e) => return Err(Status::new(Code::Unknown,
-                format!("failed to start CoalesceTask: {}", e))),
+                format!("failed to start BandMathTask: {}", e))),
         };
 
+        task_handle.set_tenant(request.tenant.clone());
+
         // register task with TaskHandler
         let task_id = {
             let mut task_manager = self.task_manager.write().unwrap();
-            match task_manager.register(task_handle, request.task_id) {
+            match task_manager.register(task_handle, request.task_id,
+                    request.priority.unwrap_or(0)) {
                 Ok(task_id) => task_id,
                 Err(e) => return Err(Status::new(Code::Unknown,
-                    format!("failed to register CoalesceTask: {}", e))),
+                    format!("failed to register BandMathTask: {}", e))),
             }
         };
 
         // initialize reply
-        let reply = ImageCoalesceReply {
+        let reply = ImageBandMathReply {
             task_id: task_id,
         };
 
         Ok(Response::new(reply))
     }
 
-    async fn fill(&self, request: Request<ImageFillRequest>)
-            -> Result<Response<ImageFillReply>, Status> {
-        trace!("ImageFillRequest: {:?}", request);
+    async fn calc(&self, request: Request<ImageCalcRequest>)
+            -> Result<Response<ImageCalcReply>, Status> {
+        trace!("ImageCalcRequest: {:?}", request);
+        crate::auth::authorize(&request, &request.get_ref().album,
+            Permission::Write)?;
+        crate::audit::record(&request);
         let request = request.get_ref();
         let filter = &request.filter;
 
@@ -186,41 +541,61 @@ impl ImageManagement for ImageManagementImpl {
         let album = crate::rpc::assert_album_exists(
             &self.album_manager, &request.album)?;
 
+        // parse expression
+        let expression = match request.expression.parse::<Expression>() {
+            Ok(expression) => expression,
+            Err(e) => return Err(Status::new(Code::InvalidArgument,
+                format!("invalid expression '{}': {}",
+                    request.expression, e))),
+        };
+
         // initialize task
-        let task = Arc::new(FillTask::new(album,
-            filter.end_timestamp.clone(), filter.geocode.clone(),
-            filter.platform.clone(), filter.recurse,
-            filter.start_timestamp.clone(), request.window_seconds));
+        let task = match CalcTask::new(album, filter.end_timestamp.clone(),
+                expression, filter.geocode.clone(),
+                filter.max_cloud_coverage, filter.min_pixel_coverage,
+                request.name.clone(), filter.platform.clone(),
+                filter.recurse, filter.source.clone(),
+                filter.start_timestamp.clone(), request.subdataset as u8) {
+            Ok(task) => Arc::new(task),
+            Err(e) => return Err(Status::new(Code::InvalidArgument,
+                format!("failed to initialize CalcTask: {}", e))),
+        };
 
         // start task
-        let task_handle = match task.start(request.thread_count as u8) {
+        let mut task_handle = match task.start(request.thread_count as u8,
+                None, RetryPolicy::from(&request.retry_policy)) {
             Ok(task_handle) => task_handle,
             Err(e) => return Err(Status::new(Code::Unknown,
-                format!("failed to start CoalesceTask: {}", e))),
+                format!("failed to start CalcTask: {}", e))),
         };
 
+        task_handle.set_tenant(request.tenant.clone());
+
         // register task with TaskHandler
         let task_id = {
             let mut task_manager = self.task_manager.write().unwrap();
-            match task_manager.register(task_handle, request.task_id) {
+            match task_manager.register(task_handle, request.task_id,
+                    request.priority.unwrap_or(0)) {
                 Ok(task_id) => task_id,
                 Err(e) => return Err(Status::new(Code::Unknown,
-                    format!("failed to register CoalesceTask: {}", e))),
+                    format!("failed to register CalcTask: {}", e))),
             }
         };
 
         // initialize reply
-        let reply = ImageFillReply {
+        let reply = ImageCalcReply {
             task_id: task_id,
         };
 
         Ok(Response::new(reply))
     }
 
-    type ListStream = Receiver<Result<Image, Status>>;
-    async fn list(&self, request: Request<ImageListRequest>)
-            -> Result<Response<Self::ListStream>, Status> {
-        trace!("ImageListRequest: {:?}", request);
+    async fn change(&self, request: Request<ImageChangeRequest>)
+            -> Result<Response<ImageChangeReply>, Status> {
+        trace!("ImageChangeRequest: {:?}", request);
+        crate::auth::authorize(&request, &request.get_ref().album,
+            Permission::Write)?;
+        crate::audit::record(&request);
         let request = request.get_ref();
         let filter = &request.filter;
 
@@ -228,60 +603,62 @@ impl ImageManagement for ImageManagementImpl {
         let album = crate::rpc::assert_album_exists(
             &self.album_manager, &request.album)?;
 
-        // search for requested images
-        let mut images = Vec::new();
-        {
-            let album = album.read().unwrap();
-            let image_iter = match album.list(&filter.end_timestamp,
-                    &filter.geocode, &filter.max_cloud_coverage,
-                    &filter.min_pixel_coverage, &filter.platform,
-                    filter.recurse, &filter.source,
-                    &filter.start_timestamp) {
-                Ok(image_iter) => image_iter,
-                Err(e) => return Err(Status::new(Code::Unknown,
-                    format!("failed to list images: {}", e))),
-            };
+        // parse change method
+        let method = match request.method.as_ref()
+                .map(|x| x.parse::<ChangeMethod>())
+                .unwrap_or(Ok(ChangeMethod::Difference)) {
+            Ok(method) => method,
+            Err(e) => return Err(Status::new(Code::InvalidArgument, e)),
+        };
 
-            // convert image and files to protobufs
-            for (i, f) in image_iter {
-                let mut files = Vec::new();
-                for file in f {
-                    files.push(File {
-                        path: file.0,
-                        pixel_coverage: file.1,
-                        subdataset: file.2 as i32,
-                    })
-                }
+        // initialize task
+        let task = match ChangeTask::new(
+                (request.after_start, request.after_end), album,
+                request.bands.iter().map(|x| *x as usize).collect(),
+                (request.before_start, request.before_end),
+                filter.geocode.clone(), filter.max_cloud_coverage, method,
+                filter.platform.clone(), filter.recurse,
+                filter.source.clone()) {
+            Ok(task) => Arc::new(task),
+            Err(e) => return Err(Status::new(Code::InvalidArgument,
+                format!("failed to initialize ChangeTask: {}", e))),
+        };
 
-                images.push(Image {
-                    cloud_coverage: i.0,
-                    geocode: i.1,
-                    files: files,
-                    platform: i.2,
-                    source: i.3,
-                    timestamp: i.5,
-                });
-            }
-        }
+        // start task
+        let mut task_handle = match task.start(request.thread_count as u8,
+                None, RetryPolicy::from(&request.retry_policy)) {
+            Ok(task_handle) => task_handle,
+            Err(e) => return Err(Status::new(Code::Unknown,
+                format!("failed to start ChangeTask: {}", e))),
+        };
 
-        // send images though Sender channel
-        let (mut tx, rx) = tokio::sync::mpsc::channel(4);
-        tokio::spawn(async move {
-            for image in images {
-                if let Err(e) = tx.send(Ok(image)).await {
-                    warn!("failed to send image list: {}", e);
-                    break;
-                }
+        task_handle.set_tenant(request.tenant.clone());
+
+        // register task with TaskHandler
+        let task_id = {
+            let mut task_manager = self.task_manager.write().unwrap();
+            match task_manager.register(task_handle, request.task_id,
+                    request.priority.unwrap_or(0)) {
+                Ok(task_id) => task_id,
+                Err(e) => return Err(Status::new(Code::Unknown,
+                    format!("failed to register ChangeTask: {}", e))),
             }
-        });
+        };
 
-        Ok(Response::new(rx))
+        // initialize reply
+        let reply = ImageChangeReply {
+            task_id: task_id,
+        };
+
+        Ok(Response::new(reply))
     }
 
-    type SearchStream = Receiver<Result<Extent, Status>>;
-    async fn search(&self, request: Request<ImageSearchRequest>)
-            -> Result<Response<Self::SearchStream>, Status> {
-        trace!("ImageSearchRequest: {:?}", request);
+    async fn coalesce(&self, request: Request<ImageCoalesceRequest>)
+            -> Result<Response<ImageCoalesceReply>, Status> {
+        trace!("ImageCoalesceRequest: {:?}", request);
+        crate::auth::authorize(&request, &request.get_ref().album,
+            Permission::Write)?;
+        crate::audit::record(&request);
         let request = request.get_ref();
         let filter = &request.filter;
 
@@ -289,45 +666,1651 @@ impl ImageManagement for ImageManagementImpl {
         let album = crate::rpc::assert_album_exists(
             &self.album_manager, &request.album)?;
 
-        // search for requested images
-        let extents: Vec<Extent> = {
-            let album = album.read().unwrap();
-            let extent_iter = match album.search(&filter.end_timestamp,
-                    &filter.geocode, &filter.max_cloud_coverage,
-                    &filter.min_pixel_coverage, &filter.platform,
-                    filter.recurse, &filter.source,
-                    &filter.start_timestamp) {
-                Ok(extent_iter) => extent_iter,
+        // initailize task
+        let task = Arc::new(CoalesceTask::new(album, self.dht.clone(),
+            filter.end_timestamp, filter.geocode.clone(),
+            filter.max_cloud_coverage, filter.min_pixel_coverage,
+            filter.platform.clone(), filter.recurse,
+            filter.source.clone(), request.platform.clone(), 
+            filter.start_timestamp, request.window_seconds));
+
+        // initialize task journal
+        let task_id = request.task_id.unwrap_or(rand::random::<u64>());
+        let journal = {
+            let task_manager = self.task_manager.read().unwrap();
+            match task_manager.create_journal(task_id,
+                    &TaskDefinition::Coalesce(request.clone())) {
+                Ok(journal) => journal,
                 Err(e) => return Err(Status::new(Code::Unknown,
-                    format!("failed to search images: {}", e))),
-            };
-            
-            extent_iter.iter().map(|x| Extent {
+                    format!("failed to create CoalesceTask journal: {}", e))),
+            }
+        };
+
+        // start task
+        let mut task_handle = match task.start(request.thread_count as u8,
+                Some(journal), RetryPolicy::from(&request.retry_policy)) {
+            Ok(task_handle) => task_handle,
+            Err(e) => return Err(Status::new(Code::Unknown,
+                format!("failed to start CoalesceTask: {}", e))),
+        };
+
+        task_handle.set_tenant(request.tenant.clone());
+
+        // register task with TaskHandler
+        let task_id = {
+            let mut task_manager = self.task_manager.write().unwrap();
+            match task_manager.register(task_handle,
+                    Some(task_id), request.priority.unwrap_or(0)) {
+                Ok(task_id) => task_id,
+                Err(e) => return Err(Status::new(Code::Unknown,
+                    format!("failed to register CoalesceTask: {}", e))),
+            }
+        };
+
+        // initialize reply
+        let reply = ImageCoalesceReply {
+            task_id: task_id,
+        };
+
+        Ok(Response::new(reply))
+    }
+
+    async fn composite(&self, request: Request<ImageCompositeRequest>)
+            -> Result<Response<ImageCompositeReply>, Status> {
+        trace!("ImageCompositeRequest: {:?}", request);
+        crate::auth::authorize(&request, &request.get_ref().album,
+            Permission::Write)?;
+        crate::audit::record(&request);
+        let request = request.get_ref();
+        let filter = &request.filter;
+
+        // ensure album exists
+        let album = crate::rpc::assert_album_exists(
+            &self.album_manager, &request.album)?;
+
+        // parse composite method
+        let method = match request.method.parse::<CompositeMethod>() {
+            Ok(method) => method,
+            Err(e) => return Err(Status::new(Code::InvalidArgument, e)),
+        };
+
+        // composites never mix observations across platforms
+        let platform = match &filter.platform {
+            Some(platform) => platform.clone(),
+            None => return Err(Status::new(Code::InvalidArgument,
+                "composite requires a platform filter")),
+        };
+
+        // initialize task
+        let bands = request.bands.iter().map(|x| *x as usize).collect();
+        let task = match CompositeTask::new(album, bands,
+                filter.end_timestamp, filter.geocode.clone(),
+                filter.max_cloud_coverage, method, platform,
+                filter.recurse, filter.source.clone(),
+                filter.start_timestamp, request.window_seconds) {
+            Ok(task) => Arc::new(task),
+            Err(e) => return Err(Status::new(Code::InvalidArgument,
+                format!("failed to initialize CompositeTask: {}", e))),
+        };
+
+        // start task
+        let mut task_handle = match task.start(request.thread_count as u8,
+                None, RetryPolicy::from(&request.retry_policy)) {
+            Ok(task_handle) => task_handle,
+            Err(e) => return Err(Status::new(Code::Unknown,
+                format!("failed to start CompositeTask: {}", e))),
+        };
+
+        task_handle.set_tenant(request.tenant.clone());
+
+        // register task with TaskHandler
+        let task_id = {
+            let mut task_manager = self.task_manager.write().unwrap();
+            match task_manager.register(task_handle, request.task_id,
+                    request.priority.unwrap_or(0)) {
+                Ok(task_id) => task_id,
+                Err(e) => return Err(Status::new(Code::Unknown,
+                    format!("failed to register CompositeTask: {}", e))),
+            }
+        };
+
+        // initialize reply
+        let reply = ImageCompositeReply {
+            task_id: task_id,
+        };
+
+        Ok(Response::new(reply))
+    }
+
+    async fn copy(&self, request: Request<ImageCopyRequest>)
+            -> Result<Response<ImageCopyReply>, Status> {
+        trace!("ImageCopyRequest: {:?}", request);
+        crate::auth::authorize(&request, &request.get_ref().album,
+            Permission::Read)?;
+        crate::auth::authorize(&request, &request.get_ref().destination,
+            Permission::Write)?;
+        crate::audit::record(&request);
+        let request = request.get_ref();
+        let filter = &crate::rpc::resolve_filter(
+            &self.filter_store, &request.filter)?;
+
+        // ensure albums exist
+        let album = crate::rpc::assert_album_exists(
+            &self.album_manager, &request.album)?;
+        let destination = crate::rpc::assert_album_exists(
+            &self.album_manager, &request.destination)?;
+
+        // requests without a precision use the destination album
+        //   target precision, if any, and otherwise retain tile geocodes
+        let precision = request.precision.map(|x| x as usize)
+            .or(destination.read().unwrap().get_precision());
+
+        // initialize task
+        let task = match CopyTask::new(album, destination, self.dht.clone(),
+                filter.end_timestamp, filter.geocode.clone(),
+                filter.max_cloud_coverage, filter.min_pixel_coverage,
+                filter.platform.clone(), precision, filter.recurse,
+                filter.source.clone(), filter.start_timestamp) {
+            Ok(task) => Arc::new(task),
+            Err(e) => return Err(Status::new(Code::InvalidArgument,
+                format!("failed to initialize CopyTask: {}", e))),
+        };
+
+        // start task
+        let task_handle = match task.start(request.thread_count as u8,
+                None, RetryPolicy::default()) {
+            Ok(task_handle) => task_handle,
+            Err(e) => return Err(Status::new(Code::Unknown,
+                format!("failed to start CopyTask: {}", e))),
+        };
+
+        // register task with TaskHandler
+        let task_id = {
+            let mut task_manager = self.task_manager.write().unwrap();
+            match task_manager.register(task_handle, request.task_id,
+                    request.priority.unwrap_or(0)) {
+                Ok(task_id) => task_id,
+                Err(e) => return Err(Status::new(Code::Unknown,
+                    format!("failed to register CopyTask: {}", e))),
+            }
+        };
+
+        // initialize reply
+        let reply = ImageCopyReply {
+            task_id: task_id,
+        };
+
+        Ok(Response::new(reply))
+    }
+
+    async fn coverage(&self, request: Request<ImageCoverageRequest>)
+            -> Result<Response<ImageCoverageReply>, Status> {
+        trace!("ImageCoverageRequest: {:?}", request);
+        crate::auth::authorize(&request, &request.get_ref().album,
+            Permission::Read)?;
+        let request = request.get_ref();
+        let filter = &request.filter;
+
+        // validate report time range and step
+        let (start_timestamp, end_timestamp) =
+                match (filter.start_timestamp, filter.end_timestamp) {
+            (Some(start), Some(end)) if start < end => (start, end),
+            _ => return Err(Status::new(Code::InvalidArgument,
+                "coverage requires a start timestamp preceding the end timestamp".to_string())),
+        };
+
+        if request.interval_seconds <= 0 || request.precision == 0 {
+            return Err(Status::new(Code::InvalidArgument,
+                "interval seconds and precision must be positive".to_string()));
+        }
+
+        let precision = request.precision as usize;
+        let interval_seconds = request.interval_seconds;
+        let album = crate::rpc::assert_album_exists(
+            &self.album_manager, &request.album)?;
+
+        // compute covered intervals over local images
+        if request.local.unwrap_or(false) {
+            let covered = {
+                let album = album.read().unwrap();
+                match album.list(&filter.end_timestamp, &filter.geocode,
+                        &filter.max_cloud_coverage,
+                        &filter.min_pixel_coverage, &filter.platform,
+                        filter.recurse, &filter.source,
+                        &filter.start_timestamp) {
+                    Ok(images) => crate::coverage::covered(&images,
+                        precision, start_timestamp, interval_seconds),
+                    Err(e) => return Err(Status::new(Code::Unknown,
+                        format!("failed to list images: {}", e))),
+                }
+            };
+
+            return Ok(Response::new(ImageCoverageReply {
+                covered: covered.into_iter().map(|(geocode, timestamp)|
+                    CoverageInterval {
+                        end_timestamp: end_timestamp
+                            .min(timestamp + interval_seconds),
+                        geocode: geocode,
+                        start_timestamp: timestamp,
+                    }).collect(),
+                gaps: Vec::new(),
+                geocode_count: 0,
+                interval_count: 0,
+            }));
+        }
+
+        // compute geocodes covering the report extent
+        let geometries = geometries(&request.geometry, request.min_longitude,
+            request.max_longitude, request.min_latitude,
+            request.max_latitude)?;
+        if geometries.is_empty() {
+            return Err(Status::new(Code::InvalidArgument,
+                "coverage requires a geometry or bounds".to_string()));
+        }
+
+        let mut geocodes: Option<BTreeSet<String>> = None;
+        {
+            let album = album.read().unwrap();
+            for geometry in geometries.iter() {
+                let covering = match crate::spatial::covering_geocodes(
+                        album.get_geocode(), geometry, &[precision]) {
+                    Ok(mut covering) => covering.remove(&precision)
+                        .unwrap_or(HashSet::new()),
+                    Err(e) => return Err(Status::new(Code::InvalidArgument,
+                        format!("failed to compute covering geocodes: {}", e))),
+                };
+
+                geocodes = Some(match geocodes {
+                    Some(geocodes) => geocodes.into_iter()
+                        .filter(|x| covering.contains(x)).collect(),
+                    None => covering.into_iter().collect(),
+                });
+            }
+        }
+
+        let geocodes = geocodes.unwrap_or(BTreeSet::new());
+        let interval_count = (end_timestamp - start_timestamp
+            + interval_seconds - 1) / interval_seconds;
+        if geocodes.len() as i64 * interval_count
+                > crate::coverage::MAX_COVERAGE_INTERVALS as i64 {
+            return Err(Status::new(Code::InvalidArgument,
+                format!("coverage report exceeds {} geocode intervals",
+                    crate::coverage::MAX_COVERAGE_INTERVALS)));
+        }
+
+        // gather covered intervals from each node
+        let mut local_request = request.clone();
+        local_request.local = Some(true);
+
+        let mut covered = HashSet::new();
+        for node in self.dht.nodes() {
+            let addr = format!("{}:{}", node.get_ip_address(),
+                node.get_metadata("rpc_port").unwrap());
+
+            let mut client = match crate::tls::channel(
+                    &addr).await.map(ImageManagementClient::new) {
+                Ok(client) => client,
+                Err(e) => return Err(Status::new(Code::Unavailable,
+                    format!("connection to {} failed: {}", addr, e))),
+            };
+
+            let reply = client.coverage(Request::new(local_request.clone()))
+                .await?.into_inner();
+            for interval in reply.covered.into_iter() {
+                covered.insert((interval.geocode, interval.start_timestamp));
+            }
+        }
+
+        // initialize reply
+        let gaps = crate::coverage::gaps(&geocodes, &covered,
+            start_timestamp, end_timestamp, interval_seconds);
+        let reply = ImageCoverageReply {
+            covered: Vec::new(),
+            gaps: gaps.into_iter().map(|(geocode, timestamp)|
+                CoverageInterval {
+                    end_timestamp: end_timestamp
+                        .min(timestamp + interval_seconds),
+                    geocode: geocode,
+                    start_timestamp: timestamp,
+                }).collect(),
+            geocode_count: geocodes.len() as u32,
+            interval_count: interval_count as u32,
+        };
+
+        Ok(Response::new(reply))
+    }
+
+    async fn delete(&self, request: Request<ImageDeleteRequest>)
+            -> Result<Response<ImageDeleteReply>, Status> {
+        trace!("ImageDeleteRequest: {:?}", request);
+        crate::auth::authorize(&request, &request.get_ref().album,
+            Permission::Write)?;
+        crate::audit::record(&request);
+        let request = request.get_ref();
+        let filter = &crate::rpc::resolve_filter(
+            &self.filter_store, &request.filter)?;
+
+        // ensure album exists
+        let album = crate::rpc::assert_album_exists(
+            &self.album_manager, &request.album)?;
+
+        // refuse to delete every image, album deletion covers that
+        if filter.end_timestamp.is_none() && filter.geocode.is_none()
+                && filter.max_cloud_coverage.is_none()
+                && filter.min_pixel_coverage.is_none()
+                && filter.platform.is_none() && filter.source.is_none()
+                && filter.start_timestamp.is_none() {
+            return Err(Status::new(Code::InvalidArgument,
+                "image delete requires at least one filter"));
+        }
+
+        // initialize task
+        let task = match DeleteTask::new(album,
+                filter.end_timestamp.clone(), filter.geocode.clone(),
+                filter.max_cloud_coverage, filter.min_pixel_coverage,
+                filter.platform.clone(), filter.recurse,
+                filter.source.clone(), filter.start_timestamp.clone()) {
+            Ok(task) => Arc::new(task),
+            Err(e) => return Err(Status::new(Code::FailedPrecondition,
+                format!("failed to initialize DeleteTask: {}", e))),
+        };
+
+        // start task
+        let task_handle = match task.start(request.thread_count as u8,
+                None, RetryPolicy::default()) {
+            Ok(task_handle) => task_handle,
+            Err(e) => return Err(Status::new(Code::Unknown,
+                format!("failed to start DeleteTask: {}", e))),
+        };
+
+        // register task with TaskHandler
+        let task_id = {
+            let mut task_manager = self.task_manager.write().unwrap();
+            match task_manager.register(task_handle, request.task_id,
+                    request.priority.unwrap_or(0)) {
+                Ok(task_id) => task_id,
+                Err(e) => return Err(Status::new(Code::Unknown,
+                    format!("failed to register DeleteTask: {}", e))),
+            }
+        };
+
+        // initialize reply
+        let reply = ImageDeleteReply {
+            task_id: task_id,
+        };
+
+        Ok(Response::new(reply))
+    }
+
+    async fn distinct(&self, request: Request<ImageDistinctRequest>)
+            -> Result<Response<ImageDistinctReply>, Status> {
+        trace!("ImageDistinctRequest: {:?}", request);
+        crate::auth::authorize(&request, &request.get_ref().album,
+            Permission::Read)?;
+        let request = request.get_ref();
+        if request.precision == 0 {
+            return Err(Status::new(Code::InvalidArgument,
+                "geocode precision must be positive"));
+        }
+
+        // ensure album exists
+        let album = crate::rpc::assert_album_exists(
+            &self.album_manager, &request.album)?;
+
+        // query distinct values of local images
+        let (geocodes, platforms, sources, subdatasets) = {
+            let album = album.read().unwrap();
+            match album.distinct(request.precision as usize) {
+                Ok(values) => values,
+                Err(e) => return Err(Status::new(Code::Unknown,
+                    format!("failed to query distinct values: {}", e))),
+            }
+        };
+
+        // initialize reply
+        let reply = ImageDistinctReply {
+            geocodes: geocodes,
+            platforms: platforms,
+            sources: sources,
+            subdatasets: subdatasets.into_iter().map(|x| x as u32).collect(),
+        };
+
+        Ok(Response::new(reply))
+    }
+
+    type DownloadStream = Receiver<Result<ImageDownloadChunk, Status>>;
+    async fn download(&self, request: Request<ImageDownloadRequest>)
+            -> Result<Response<Self::DownloadStream>, Status> {
+        trace!("ImageDownloadRequest: {:?}", request);
+        crate::auth::authorize(&request, &request.get_ref().album,
+            Permission::Read)?;
+        let request = request.get_ref();
+        let mosaic = request.mosaic.unwrap_or(false);
+        let transform = read_transform(&request.transform, None)?;
+
+        // gather replicas of matching files from every node
+        let list_request = ImageListRequest {
+            album: request.album.clone(),
+            albums: Vec::new(),
+            all_albums: None,
+            filter: request.filter.clone(),
+            geometry: request.transform.as_ref()
+                .and_then(|x| x.geometry.clone()),
+            max_latitude: None,
+            max_longitude: None,
+            min_latitude: None,
+            min_longitude: None,
+            statistics: None,
+        };
+
+        let mut files = BTreeMap::new();
+        for node in self.dht.nodes() {
+            let addr = format!("{}:{}", node.get_ip_address(),
+                node.get_metadata("rpc_port").unwrap());
+            let xfer_addr = match node.get_metadata("xfer_port")
+                    .and_then(|x| x.parse::<u16>().ok()) {
+                Some(port) => SocketAddr::new(
+                    node.get_ip_address().clone(), port),
+                None => continue,
+            };
+
+            let mut client = match crate::tls::channel(
+                    &addr).await.map(ImageManagementClient::new) {
+                Ok(client) => client,
+                Err(e) => return Err(Status::new(Code::Unavailable,
+                    format!("connection to {} failed: {}", addr, e))),
+            };
+
+            let mut stream = client.list(Request::new(list_request.clone()))
+                .await?.into_inner();
+            while let Some(image) = stream.message().await? {
+                for file in image.files.iter().filter(|x| request.subdataset
+                        .map(|y| y == x.subdataset as u32).unwrap_or(true)) {
+                    // paths mirror the 'platform/geocode/source' layout
+                    let filename = match Path::new(&file.path).file_name()
+                            .and_then(|x| x.to_str()) {
+                        Some(filename) => filename.to_string(),
+                        None => continue,
+                    };
+
+                    let (key, timestamp) = match mosaic {
+                        true => (format!("mosaic-{}.tif", file.subdataset),
+                            image.timestamp),
+                        false => (filename.clone(), 0),
+                    };
+
+                    files.entry(format!("{}/{}/{}/{}", image.platform,
+                            image.geocode, image.source, key))
+                        .or_insert(BTreeMap::new())
+                        .entry((timestamp, filename))
+                        .or_insert(Vec::new())
+                        .push((xfer_addr, file.path.clone()));
+                }
+            }
+        }
+
+        if files.is_empty() {
+            return Err(Status::new(Code::NotFound,
+                "no images match the download request"));
+        }
+
+        // read files and stream GeoTIFFs through Sender channel
+        let (mut tx, rx) = tokio::sync::mpsc::channel(4);
+        let tenant = request.tenant.clone();
+        let tracker = self.tracker.clone();
+        std::thread::spawn(move || {
+            let mut runtime = match tokio::runtime::Builder::new()
+                    .basic_scheduler().enable_all().build() {
+                Ok(runtime) => runtime,
+                Err(e) => {
+                    warn!("failed to initialize download runtime: {}", e);
+                    return;
+                },
+            };
+
+            for (path, tiles) in files.into_iter() {
+                // mosaic tiles in timestamp order so newer tiles draw on top
+                let result = match mosaic {
+                    true => crate::mosaic::mosaic(tiles.into_iter()
+                        .map(|(_, replicas)| ReadRequest {
+                            replicas: replicas,
+                            subgeocode: None,
+                            tenant: tenant.clone(),
+                            transform: transform.clone(),
+                        }).collect(), tracker.clone()),
+                    false => match tiles.into_iter().next() {
+                        Some((_, replicas)) => read_geotiff(&replicas,
+                            &transform, &tenant, &tracker),
+                        None => Err("file has no replicas".into()),
+                    },
+                };
+
+                let sent = runtime.block_on(async {
+                    let buf = match result {
+                        Ok(buf) => buf,
+                        Err(e) => {
+                            let _ = tx.send(Err(Status::new(Code::Unknown,
+                                format!("failed to read '{}': {}", path, e))))
+                                .await;
+                            return false;
+                        },
+                    };
+
+                    for chunk in buf.chunks(MOSAIC_CHUNK_SIZE) {
+                        let chunk = ImageDownloadChunk {
+                            data: chunk.to_vec(),
+                            path: path.clone(),
+                        };
+
+                        if let Err(e) = tx.send(Ok(chunk)).await {
+                            warn!("failed to send download: {}", e);
+                            return false;
+                        }
+                    }
+
+                    true
+                });
+
+                if !sent {
+                    break;
+                }
+            }
+        });
+
+        Ok(Response::new(rx))
+    }
+
+    async fn execute(&self, request: Request<ImageExecuteRequest>)
+            -> Result<Response<ImageExecuteReply>, Status> {
+        trace!("ImageExecuteRequest: {:?}", request);
+        crate::auth::authorize(&request, &request.get_ref().album,
+            Permission::Write)?;
+        crate::audit::record(&request);
+        let request = request.get_ref();
+        let filter = &request.filter;
+
+        // ensure album exists
+        let album = crate::rpc::assert_album_exists(
+            &self.album_manager, &request.album)?;
+
+        // lookup executor registered with this node
+        let address = {
+            let task_manager = self.task_manager.read().unwrap();
+            match task_manager.get_executor(&request.executor) {
+                Some(address) => address.clone(),
+                None => return Err(Status::new(Code::NotFound,
+                    format!("executor '{}' is not registered",
+                        request.executor))),
+            }
+        };
+
+        // initialize task
+        let task = Arc::new(ExecuteTask::new(address, album,
+            self.dht.clone(), filter.end_timestamp.clone(),
+            filter.geocode.clone(), filter.max_cloud_coverage,
+            filter.min_pixel_coverage, request.parameters.clone(),
+            filter.platform.clone(), filter.recurse,
+            filter.source.clone(), filter.start_timestamp.clone()));
+
+        // start task
+        let mut task_handle = match task.start(request.thread_count as u8,
+                None, RetryPolicy::from(&request.retry_policy)) {
+            Ok(task_handle) => task_handle,
+            Err(e) => return Err(Status::new(Code::Unknown,
+                format!("failed to start ExecuteTask: {}", e))),
+        };
+
+        task_handle.set_tenant(request.tenant.clone());
+
+        // register task with TaskHandler
+        let task_id = {
+            let mut task_manager = self.task_manager.write().unwrap();
+            match task_manager.register(task_handle, request.task_id,
+                    request.priority.unwrap_or(0)) {
+                Ok(task_id) => task_id,
+                Err(e) => return Err(Status::new(Code::Unknown,
+                    format!("failed to register ExecuteTask: {}", e))),
+            }
+        };
+
+        // initialize reply
+        let reply = ImageExecuteReply {
+            task_id: task_id,
+        };
+
+        Ok(Response::new(reply))
+    }
+
+    async fn export(&self, request: Request<ImageExportRequest>)
+            -> Result<Response<ImageExportReply>, Status> {
+        trace!("ImageExportRequest: {:?}", request);
+        crate::auth::authorize(&request, &request.get_ref().album,
+            Permission::Read)?;
+        let request = request.get_ref();
+        let filter = &request.filter;
+
+        // ensure album exists
+        let album = crate::rpc::assert_album_exists(
+            &self.album_manager, &request.album)?;
+
+        // parse destination and asset handling
+        let destination = match request.destination.parse::<Destination>() {
+            Ok(destination) => destination,
+            Err(e) => return Err(Status::new(Code::InvalidArgument, e)),
+        };
+
+        let assets = match request.assets.as_ref()
+                .map(|x| x.parse::<ExportAssets>()) {
+            Some(Ok(assets)) => assets,
+            Some(Err(e)) => return Err(Status::new(Code::InvalidArgument, e)),
+            None => ExportAssets::Link,
+        };
+
+        // initialize task
+        let task = match ExportTask::new(album, assets, destination,
+                self.dht.clone(), filter.end_timestamp,
+                filter.geocode.clone(), filter.max_cloud_coverage,
+                filter.min_pixel_coverage, filter.platform.clone(),
+                filter.recurse, filter.source.clone(),
+                filter.start_timestamp, request.tenant.clone(),
+                self.tracker.clone()) {
+            Ok(task) => Arc::new(task),
+            Err(e) => return Err(Status::new(Code::InvalidArgument,
+                format!("failed to initialize ExportTask: {}", e))),
+        };
+
+        // start task
+        let mut task_handle = match task.start(request.thread_count as u8,
+                None, RetryPolicy::from(&request.retry_policy)) {
+            Ok(task_handle) => task_handle,
+            Err(e) => return Err(Status::new(Code::Unknown,
+                format!("failed to start ExportTask: {}", e))),
+        };
+
+        task_handle.set_tenant(request.tenant.clone());
+
+        // register task with TaskHandler
+        let task_id = {
+            let mut task_manager = self.task_manager.write().unwrap();
+            match task_manager.register(task_handle, request.task_id,
+                    request.priority.unwrap_or(0)) {
+                Ok(task_id) => task_id,
+                Err(e) => return Err(Status::new(Code::Unknown,
+                    format!("failed to register ExportTask: {}", e))),
+            }
+        };
+
+        // initialize reply
+        let reply = ImageExportReply {
+            task_id: task_id,
+        };
+
+        Ok(Response::new(reply))
+    }
+
+    async fn fill(&self, request: Request<ImageFillRequest>)
+            -> Result<Response<ImageFillReply>, Status> {
+        trace!("ImageFillRequest: {:?}", request);
+        crate::auth::authorize(&request, &request.get_ref().album,
+            Permission::Write)?;
+        crate::audit::record(&request);
+        let mut request = request.get_ref().clone();
+        request.filter = crate::rpc::resolve_filter(
+            &self.filter_store, &request.filter)?;
+        let request = &request;
+        let filter = &request.filter;
+
+        // ensure album exists
+        let album = crate::rpc::assert_album_exists(
+            &self.album_manager, &request.album)?;
+
+        // parse cloud mask
+        let cloud_mask = match request.cloud_mask.as_ref()
+                .map(|x| x.parse::<CloudMask>()).transpose() {
+            Ok(cloud_mask) => cloud_mask,
+            Err(e) => return Err(Status::new(Code::InvalidArgument, e)),
+        };
+
+        // parse fill strategy
+        let strategy = match crate::fill::strategy(request.strategy.as_ref()
+                .map(|x| x.as_str()).unwrap_or(DEFAULT_FILL_STRATEGY)) {
+            Ok(strategy) => strategy,
+            Err(e) => return Err(Status::new(Code::InvalidArgument, e)),
+        };
+
+        let donor_cloud_mask = match request.donor_cloud_mask.as_ref()
+                .map(|x| x.parse::<CloudMask>()).transpose() {
+            Ok(donor_cloud_mask) => donor_cloud_mask,
+            Err(e) => return Err(Status::new(Code::InvalidArgument, e)),
+        };
+
+        // initialize task
+        let resampling = request.resampling.clone()
+            .unwrap_or("bilinear".to_string());
+        let task = match FillTask::new(album,
+                request.band_map.iter().map(|x| *x as usize).collect(),
+                cloud_mask, donor_cloud_mask, request.donor_platform.clone(),
+                filter.end_timestamp.clone(), filter.geocode.clone(),
+                request.mask_band.map(|x| x as usize),
+                request.mask_subdataset.map(|x| x as u8),
+                filter.platform.clone(), filter.recurse, resampling,
+                filter.start_timestamp.clone(), strategy,
+                request.window_seconds) {
+            Ok(task) => Arc::new(task),
+            Err(e) => return Err(Status::new(Code::InvalidArgument,
+                format!("failed to initialize FillTask: {}", e))),
+        };
+
+        // evaluate task without writing if requested
+        if request.dry_run.unwrap_or(false) {
+            return Ok(Response::new(ImageFillReply {
+                dry_run: Some(dry_run(task.as_ref()).await?),
+                task_id: 0,
+            }));
+        }
+
+        // register recurring task rather than starting immediately
+        if let Some(schedule) = &request.schedule {
+            let mut definition = request.clone();
+            definition.schedule = None;
+            definition.task_id = None;
+
+            let task_id = self.add_recurring(request.task_id,
+                TaskDefinition::Fill(definition), schedule)?;
+            return Ok(Response::new(ImageFillReply {
+                dry_run: None,
+                task_id: task_id,
+            }));
+        }
+
+        // initialize task journal
+        let task_id = request.task_id.unwrap_or(rand::random::<u64>());
+        let journal = {
+            let task_manager = self.task_manager.read().unwrap();
+            match task_manager.create_journal(task_id,
+                    &TaskDefinition::Fill(request.clone())) {
+                Ok(journal) => journal,
+                Err(e) => return Err(Status::new(Code::Unknown,
+                    format!("failed to create CoalesceTask journal: {}", e))),
+            }
+        };
+
+        // start task
+        let mut task_handle = match task.start(request.thread_count as u8,
+                Some(journal), RetryPolicy::from(&request.retry_policy)) {
+            Ok(task_handle) => task_handle,
+            Err(e) => return Err(Status::new(Code::Unknown,
+                format!("failed to start CoalesceTask: {}", e))),
+        };
+
+        task_handle.set_tenant(request.tenant.clone());
+
+        // register task with TaskHandler
+        let task_id = {
+            let mut task_manager = self.task_manager.write().unwrap();
+            match task_manager.register(task_handle,
+                    Some(task_id), request.priority.unwrap_or(0)) {
+                Ok(task_id) => task_id,
+                Err(e) => return Err(Status::new(Code::Unknown,
+                    format!("failed to register CoalesceTask: {}", e))),
+            }
+        };
+
+        // initialize reply
+        let reply = ImageFillReply {
+            dry_run: None,
+            task_id: task_id,
+        };
+
+        Ok(Response::new(reply))
+    }
+
+    type ListStream = Receiver<Result<Image, Status>>;
+    async fn list(&self, request: Request<ImageListRequest>)
+            -> Result<Response<Self::ListStream>, Status> {
+        trace!("ImageListRequest: {:?}", request);
+        let authorize = |album: &str| crate::auth::authorize(&request,
+            album, Permission::Read);
+        let request = request.get_ref();
+        let filter = &crate::rpc::resolve_filter(
+            &self.filter_store, &request.filter)?;
+
+        // compile albums to list
+        let album_ids = match request.all_albums {
+            Some(true) => {
+                let album_manager = self.album_manager.read().unwrap();
+                let mut album_ids: Vec<String> = album_manager.iter()
+                    .map(|(id, _)| id.clone())
+                    .filter(|id| authorize(id).is_ok()).collect();
+                album_ids.sort();
+                album_ids
+            },
+            _ => {
+                let mut album_ids = vec![request.album.clone()];
+                for album_id in request.albums.iter() {
+                    if !album_ids.contains(album_id) {
+                        album_ids.push(album_id.clone());
+                    }
+                }
+
+                album_ids
+            },
+        };
+
+        // ensure albums exist and may be read
+        let mut albums = Vec::new();
+        for album_id in album_ids.iter() {
+            authorize(album_id)?;
+            albums.push((album_id.clone(), crate::rpc::assert_album_exists(
+                &self.album_manager, album_id)?));
+        }
+
+        // compile geometry and bounding box filters
+        let geometries = geometries(&request.geometry, request.min_longitude,
+            request.max_longitude, request.min_latitude,
+            request.max_latitude)?;
+
+        // stream images a page at a time, bounding memory by the page
+        //   size and channel capacity while slow readers apply backpressure
+        let filter = filter.clone();
+        let statistics = request.statistics == Some(true);
+
+        let (mut tx, rx) = tokio::sync::mpsc::channel(4);
+        tokio::spawn(async move {
+            for (album_id, album) in albums.iter() {
+                let mut cursor = None;
+                loop {
+                    let (images, next_cursor) = match list_page(album,
+                            album_id, &filter, &geometries, statistics,
+                            &cursor) {
+                        Ok(page) => page,
+                        Err(e) => {
+                            let _ = tx.send(Err(e)).await;
+                            return;
+                        },
+                    };
+
+                    for image in images {
+                        if let Err(e) = tx.send(Ok(image)).await {
+                            warn!("failed to send image list: {}", e);
+                            return;
+                        }
+                    }
+
+                    match next_cursor {
+                        Some(next_cursor) => cursor = Some(next_cursor),
+                        None => break,
+                    }
+                }
+            }
+        });
+
+        Ok(Response::new(rx))
+    }
+
+    type MosaicStream = Receiver<Result<ImageMosaicChunk, Status>>;
+    async fn mosaic(&self, request: Request<ImageMosaicRequest>)
+            -> Result<Response<Self::MosaicStream>, Status> {
+        trace!("ImageMosaicRequest: {:?}", request);
+        crate::auth::authorize(&request, &request.get_ref().album,
+            Permission::Read)?;
+        let request = request.get_ref();
+
+        // parse optional clip bounds and read transform
+        let bounds = parse_bounds(request.min_longitude,
+            request.max_longitude, request.min_latitude,
+            request.max_latitude)?;
+        let transform = read_transform(&request.transform, bounds)?;
+
+        // parse preview rendering, defaulting to the node quicklook format
+        let preview: Option<(Vec<usize>, QuicklookFormat, usize)> =
+                match &request.preview {
+            Some(preview) => match preview.format.as_ref()
+                    .map(|x| x.parse::<QuicklookFormat>()).transpose() {
+                Ok(format) => Some((
+                    preview.bands.iter().map(|x| *x as usize).collect(),
+                    format.unwrap_or(crate::quicklook::format()),
+                    preview.size.map(|x| x as usize)
+                        .unwrap_or(crate::mosaic::MOSAIC_PREVIEW_SIZE))),
+                Err(e) => return Err(Status::new(Code::InvalidArgument, e)),
+            },
+            None => None,
+        };
+
+        // gather replicas of tiles intersecting the bounds from every node
+        let list_request = ImageListRequest {
+            album: request.album.clone(),
+            albums: Vec::new(),
+            all_albums: None,
+            filter: request.filter.clone(),
+            geometry: request.transform.as_ref()
+                .and_then(|x| x.geometry.clone()),
+            max_latitude: request.max_latitude,
+            max_longitude: request.max_longitude,
+            min_latitude: request.min_latitude,
+            min_longitude: request.min_longitude,
+            statistics: None,
+        };
+
+        let mut tiles = BTreeMap::new();
+        for node in self.dht.nodes() {
+            let addr = format!("{}:{}", node.get_ip_address(),
+                node.get_metadata("rpc_port").unwrap());
+            let xfer_addr = match node.get_metadata("xfer_port")
+                    .and_then(|x| x.parse::<u16>().ok()) {
+                Some(port) => SocketAddr::new(
+                    node.get_ip_address().clone(), port),
+                None => continue,
+            };
+
+            let mut client = match crate::tls::channel(
+                    &addr).await.map(ImageManagementClient::new) {
+                Ok(client) => client,
+                Err(e) => return Err(Status::new(Code::Unavailable,
+                    format!("connection to {} failed: {}", addr, e))),
+            };
+
+            let mut stream = client.list(Request::new(list_request.clone()))
+                .await?.into_inner();
+            while let Some(image) = stream.message().await? {
+                for file in image.files.iter()
+                        .filter(|x| x.subdataset as u32 == request.subdataset) {
+                    // order tiles by timestamp so newer tiles draw on top
+                    tiles.entry((image.timestamp, image.geocode.clone(),
+                            image.platform.clone(), image.source.clone()))
+                        .or_insert(Vec::new())
+                        .push((xfer_addr, file.path.clone()));
+                }
+            }
+        }
+
+        let requests = tiles.into_iter().map(|(_, replicas)| ReadRequest {
+            replicas: replicas,
+            subgeocode: None,
+            tenant: request.tenant.clone(),
+            transform: transform.clone(),
+        }).collect();
+
+        // merge tiles and stream GeoTIFF through Sender channel
+        let (mut tx, rx) = tokio::sync::mpsc::channel(4);
+        let tracker = self.tracker.clone();
+        std::thread::spawn(move || {
+            let result = match preview {
+                Some((bands, format, size)) => crate::mosaic::preview(
+                    requests, tracker, &bands, format, size),
+                None => crate::mosaic::mosaic(requests, tracker),
+            };
+
+            let mut runtime = match tokio::runtime::Builder::new()
+                    .basic_scheduler().enable_all().build() {
+                Ok(runtime) => runtime,
+                Err(e) => {
+                    warn!("failed to initialize mosaic runtime: {}", e);
+                    return;
+                },
+            };
+
+            runtime.block_on(async move {
+                let buf = match result {
+                    Ok(buf) => buf,
+                    Err(e) => {
+                        let _ = tx.send(Err(Status::new(Code::Unknown,
+                            format!("failed to build mosaic: {}", e)))).await;
+                        return;
+                    },
+                };
+
+                for chunk in buf.chunks(MOSAIC_CHUNK_SIZE) {
+                    let chunk = ImageMosaicChunk { data: chunk.to_vec() };
+                    if let Err(e) = tx.send(Ok(chunk)).await {
+                        warn!("failed to send mosaic: {}", e);
+                        break;
+                    }
+                }
+            });
+        });
+
+        Ok(Response::new(rx))
+    }
+
+    type SearchStream = Receiver<Result<Extent, Status>>;
+    async fn overview(&self, request: Request<ImageOverviewRequest>)
+            -> Result<Response<ImageOverviewReply>, Status> {
+        trace!("ImageOverviewRequest: {:?}", request);
+        crate::auth::authorize(&request, &request.get_ref().album,
+            Permission::Write)?;
+        crate::audit::record(&request);
+        let request = request.get_ref();
+        let filter = &request.filter;
+
+        // ensure album exists
+        let album = crate::rpc::assert_album_exists(
+            &self.album_manager, &request.album)?;
+
+        // initialize task
+        let resampling = request.resampling.clone()
+            .unwrap_or("average".to_string());
+        let task = match OverviewTask::new(album, self.dht.clone(),
+                filter.end_timestamp, filter.geocode.clone(),
+                filter.max_cloud_coverage, filter.platform.clone(),
+                request.precision as usize, resampling,
+                filter.source.clone(), filter.start_timestamp,
+                request.tenant.clone(), self.tracker.clone()) {
+            Ok(task) => Arc::new(task),
+            Err(e) => return Err(Status::new(Code::InvalidArgument,
+                format!("failed to initialize OverviewTask: {}", e))),
+        };
+
+        // start task
+        let mut task_handle = match task.start(request.thread_count as u8,
+                None, RetryPolicy::from(&request.retry_policy)) {
+            Ok(task_handle) => task_handle,
+            Err(e) => return Err(Status::new(Code::Unknown,
+                format!("failed to start OverviewTask: {}", e))),
+        };
+
+        task_handle.set_tenant(request.tenant.clone());
+
+        // register task with TaskHandler
+        let task_id = {
+            let mut task_manager = self.task_manager.write().unwrap();
+            match task_manager.register(task_handle, request.task_id,
+                    request.priority.unwrap_or(0)) {
+                Ok(task_id) => task_id,
+                Err(e) => return Err(Status::new(Code::Unknown,
+                    format!("failed to register OverviewTask: {}", e))),
+            }
+        };
+
+        // initialize reply
+        let reply = ImageOverviewReply {
+            task_id: task_id,
+        };
+
+        Ok(Response::new(reply))
+    }
+
+    type SampleStream = Receiver<Result<ImageSample, Status>>;
+    async fn sample(&self, request: Request<ImageSampleRequest>)
+            -> Result<Response<Self::SampleStream>, Status> {
+        trace!("ImageSampleRequest: {:?}", request);
+        crate::auth::authorize(&request, &request.get_ref().album,
+            Permission::Read)?;
+        let request = request.get_ref().clone();
+
+        // ensure album exists
+        let album = crate::rpc::assert_album_exists(
+            &self.album_manager, &request.album)?;
+
+        let buffer = request.buffer.unwrap_or(0f64);
+        if buffer < 0f64 {
+            return Err(Status::new(Code::InvalidArgument,
+                "sample buffer must not be negative"));
+        }
+
+        // search for requested images
+        let filter = &request.filter;
+        let (images, geocode) = {
+            let album = album.read().unwrap();
+            let images = match album.list(&filter.end_timestamp,
+                    &filter.geocode, &filter.max_cloud_coverage,
+                    &filter.min_pixel_coverage, &filter.platform,
+                    filter.recurse, &filter.source,
+                    &filter.start_timestamp) {
+                Ok(images) => images,
+                Err(e) => return Err(Status::new(Code::Unknown,
+                    format!("failed to list images: {}", e))),
+            };
+
+            (images, album.get_geocode().clone())
+        };
+
+        // extract samples and send through Sender channel
+        let (mut tx, rx) = tokio::sync::mpsc::channel(4);
+        std::thread::spawn(move || {
+            let mut runtime = match tokio::runtime::Builder::new()
+                    .basic_scheduler().enable_all().build() {
+                Ok(runtime) => runtime,
+                Err(e) => {
+                    warn!("failed to initialize sample runtime: {}", e);
+                    return;
+                },
+            };
+
+            let samples = extract_samples(&request, buffer, &geocode, images);
+            runtime.block_on(async move {
+                let samples = match samples {
+                    Ok(samples) => samples,
+                    Err(e) => {
+                        let _ = tx.send(Err(Status::new(Code::Unknown,
+                            format!("failed to sample images: {}", e))))
+                            .await;
+                        return;
+                    },
+                };
+
+                for sample in samples {
+                    if let Err(e) = tx.send(Ok(sample)).await {
+                        warn!("failed to send samples: {}", e);
+                        break;
+                    }
+                }
+            });
+        });
+
+        Ok(Response::new(rx))
+    }
+
+    async fn search(&self, request: Request<ImageSearchRequest>)
+            -> Result<Response<Self::SearchStream>, Status> {
+        trace!("ImageSearchRequest: {:?}", request);
+        crate::auth::authorize(&request, &request.get_ref().album,
+            Permission::Read)?;
+        let request = request.get_ref();
+        let filter = &crate::rpc::resolve_filter(
+            &self.filter_store, &request.filter)?;
+
+        // ensure album exists
+        let album = crate::rpc::assert_album_exists(
+            &self.album_manager, &request.album)?;
+
+        // compile geometry and bounding box filters
+        let geometries = geometries(&request.geometry, request.min_longitude,
+            request.max_longitude, request.min_latitude,
+            request.max_latitude)?;
+
+        // parse optional time interval grouping
+        let interval = match request.group_by_interval.as_ref()
+                .map(|x| x.parse::<TimeInterval>()) {
+            Some(Ok(interval)) => Some(interval),
+            Some(Err(e)) => return Err(Status::new(Code::InvalidArgument, e)),
+            None => None,
+        };
+
+        // search for requested images
+        let full_geocode = request.full_geocode.unwrap_or(false);
+        let extents: Vec<Extent> = {
+            let album = album.read().unwrap();
+            let result = match geometries.is_empty() {
+                true => album.search(&filter.end_timestamp, full_geocode,
+                    &filter.geocode, &interval, &filter.max_cloud_coverage,
+                    &filter.min_pixel_coverage, &filter.platform,
+                    filter.recurse, &filter.source,
+                    &filter.start_timestamp),
+                false => search_geometries(&album, filter,
+                    full_geocode, &geometries, &interval),
+            };
+
+            let extent_iter = match result {
+                Ok(extent_iter) => extent_iter,
+                Err(e) => return Err(Status::new(Code::Unknown,
+                    format!("failed to search images: {}", e))),
+            };
+            
+            extent_iter.iter().map(|x| Extent {
                 count: x.0 as u32,
                 geocode: x.1.clone(),
                 platform: x.2.clone(),
                 precision: x.3 as u32,
                 source: x.4.clone(),
+                timestamp: x.5,
             }).collect()
         };
 
-        // send extents though Sender channel
-        let (mut tx, rx) = tokio::sync::mpsc::channel(4);
-        tokio::spawn(async move {
-            for extent in extents {
-                if let Err(e) = tx.send(Ok(extent)).await {
-                    warn!("failed to send extent list: {}", e);
-                    break;
-                }
+        // send extents though Sender channel
+        let (mut tx, rx) = tokio::sync::mpsc::channel(4);
+        tokio::spawn(async move {
+            for extent in extents {
+                if let Err(e) = tx.send(Ok(extent)).await {
+                    warn!("failed to send extent list: {}", e);
+                    break;
+                }
+            }
+        });
+
+        Ok(Response::new(rx))
+    }
+
+    async fn split(&self, request: Request<ImageSplitRequest>)
+            -> Result<Response<ImageSplitReply>, Status> {
+        trace!("ImageSplitRequest: {:?}", request);
+        crate::auth::authorize(&request, &request.get_ref().album,
+            Permission::Write)?;
+        crate::audit::record(&request);
+        let mut request = request.get_ref().clone();
+        request.filter = crate::rpc::resolve_filter(
+            &self.filter_store, &request.filter)?;
+        let request = &request;
+        let filter = &request.filter;
+
+        // ensure album exists
+        let album = crate::rpc::assert_album_exists(
+            &self.album_manager, &request.album)?;
+        let precision = crate::rpc::resolve_precision(
+            &album, request.precision)?;
+
+        // initialize task
+        let task = Arc::new(SplitTask::new(album, self.dht.clone(),
+            filter.end_timestamp.clone(), filter.geocode.clone(),
+            request.geocode_bound.clone(), filter.platform.clone(),
+            precision, filter.recurse,
+            filter.start_timestamp.clone()));
+
+        // evaluate task without writing if requested
+        if request.dry_run.unwrap_or(false) {
+            return Ok(Response::new(ImageSplitReply {
+                dry_run: Some(dry_run(task.as_ref()).await?),
+                task_id: 0,
+            }));
+        }
+
+        // initialize task journal
+        let task_id = request.task_id.unwrap_or(rand::random::<u64>());
+        let journal = {
+            let task_manager = self.task_manager.read().unwrap();
+            match task_manager.create_journal(task_id,
+                    &TaskDefinition::Split(request.clone())) {
+                Ok(journal) => journal,
+                Err(e) => return Err(Status::new(Code::Unknown,
+                    format!("failed to create SplitTask journal: {}", e))),
+            }
+        };
+
+        // start task
+        let mut task_handle = match task.start(request.thread_count as u8,
+                Some(journal), RetryPolicy::from(&request.retry_policy)) {
+            Ok(task_handle) => task_handle,
+            Err(e) => return Err(Status::new(Code::Unknown,
+                format!("failed to start SplitTask: {}", e))),
+        };
+
+        task_handle.set_tenant(request.tenant.clone());
+
+        // register task with TaskHandler
+        let task_id = {
+            let mut task_manager = self.task_manager.write().unwrap();
+            match task_manager.register(task_handle,
+                    Some(task_id), request.priority.unwrap_or(0)) {
+                Ok(task_id) => task_id,
+                Err(e) => return Err(Status::new(Code::Unknown,
+                    format!("failed to register SplitTask: {}", e))),
+            }
+        };
+ 
+        // initialize reply
+        let reply = ImageSplitReply {
+            dry_run: None,
+            task_id: task_id,
+        };
+
+        Ok(Response::new(reply))
+    }
+
+    async fn statistics(&self, request: Request<ImageStatisticsRequest>)
+            -> Result<Response<ImageStatisticsReply>, Status> {
+        trace!("ImageStatisticsRequest: {:?}", request);
+        crate::auth::authorize(&request, &request.get_ref().album,
+            Permission::Write)?;
+        let request = request.get_ref();
+        let filter = &request.filter;
+
+        // ensure album exists
+        let album = crate::rpc::assert_album_exists(
+            &self.album_manager, &request.album)?;
+
+        // initialize task
+        let task = match StatisticsTask::new(album,
+                request.bins.unwrap_or(STATISTICS_BINS) as usize,
+                filter.end_timestamp, filter.geocode.clone(),
+                filter.max_cloud_coverage, filter.min_pixel_coverage,
+                filter.platform.clone(), request.recompute.unwrap_or(false),
+                filter.recurse, filter.source.clone(),
+                filter.start_timestamp) {
+            Ok(task) => Arc::new(task),
+            Err(e) => return Err(Status::new(Code::InvalidArgument,
+                format!("failed to initialize StatisticsTask: {}", e))),
+        };
+
+        // start task
+        let mut task_handle = match task.start(request.thread_count as u8,
+                None, RetryPolicy::from(&request.retry_policy)) {
+            Ok(task_handle) => task_handle,
+            Err(e) => return Err(Status::new(Code::Unknown,
+                format!("failed to start StatisticsTask: {}", e))),
+        };
+
+        task_handle.set_tenant(request.tenant.clone());
+
+        // register task with TaskHandler
+        let task_id = {
+            let mut task_manager = self.task_manager.write().unwrap();
+            match task_manager.register(task_handle, request.task_id,
+                    request.priority.unwrap_or(0)) {
+                Ok(task_id) => task_id,
+                Err(e) => return Err(Status::new(Code::Unknown,
+                    format!("failed to register StatisticsTask: {}", e))),
+            }
+        };
+
+        // initialize reply
+        let reply = ImageStatisticsReply {
+            task_id: task_id,
+        };
+
+        Ok(Response::new(reply))
+    }
+
+    async fn update(&self, request: Request<ImageUpdateRequest>)
+            -> Result<Response<ImageUpdateReply>, Status> {
+        trace!("ImageUpdateRequest: {:?}", request);
+        crate::auth::authorize(&request, &request.get_ref().album,
+            Permission::Write)?;
+        crate::audit::record(&request);
+        let request = request.get_ref();
+        let filter = &crate::rpc::resolve_filter(
+            &self.filter_store, &request.filter)?;
+
+        // ensure album exists
+        let album = crate::rpc::assert_album_exists(
+            &self.album_manager, &request.album)?;
+
+        // initialize task
+        let task = match UpdateTask::new(album, request.cloud_coverage,
+                filter.end_timestamp, filter.geocode.clone(),
+                filter.max_cloud_coverage, filter.min_pixel_coverage,
+                filter.platform.clone(), filter.recurse,
+                filter.source.clone(), filter.start_timestamp,
+                request.timestamp, request.timestamp_offset) {
+            Ok(task) => Arc::new(task),
+            Err(e) => return Err(Status::new(Code::InvalidArgument,
+                format!("failed to initialize UpdateTask: {}", e))),
+        };
+
+        // start task
+        let task_handle = match task.start(request.thread_count as u8,
+                None, RetryPolicy::default()) {
+            Ok(task_handle) => task_handle,
+            Err(e) => return Err(Status::new(Code::Unknown,
+                format!("failed to start UpdateTask: {}", e))),
+        };
+
+        // register task with TaskHandler
+        let task_id = {
+            let mut task_manager = self.task_manager.write().unwrap();
+            match task_manager.register(task_handle, request.task_id,
+                    request.priority.unwrap_or(0)) {
+                Ok(task_id) => task_id,
+                Err(e) => return Err(Status::new(Code::Unknown,
+                    format!("failed to register UpdateTask: {}", e))),
+            }
+        };
+
+        // initialize reply
+        let reply = ImageUpdateReply {
+            task_id: task_id,
+        };
+
+        Ok(Response::new(reply))
+    }
+
+    async fn upload(&self,
+            request: Request<tonic::Streaming<ImageUploadChunk>>)
+            -> Result<Response<ImageUploadReply>, Status> {
+        let metadata = request.metadata().clone();
+        let mut stream = request.into_inner();
+
+        // the first chunk describes the uploaded image
+        let (header, data) = match stream.message().await? {
+            Some(ImageUploadChunk { header: Some(header), data }) =>
+                (header, data),
+            _ => return Err(Status::new(Code::InvalidArgument,
+                "upload must begin with an image header")),
+        };
+
+        trace!("ImageUploadHeader: {:?}", header);
+        crate::auth::authorize_metadata(&metadata, &header.album,
+            Permission::Write)?;
+        crate::audit::record_operation(&metadata, "ImageUpload",
+            &format!("{:?}", header));
+
+        // ensure album exists
+        let album = crate::rpc::assert_album_exists(
+            &self.album_manager, &header.album)?;
+        let precision = crate::rpc::resolve_precision(
+            &album, header.precision)?;
+
+        let cloud_mask = match header.cloud_mask.as_ref()
+                .map(|x| x.parse::<CloudMask>()).transpose() {
+            Ok(cloud_mask) => cloud_mask,
+            Err(e) => return Err(Status::new(Code::InvalidArgument, e)),
+        };
+
+        let subdataset = match header.subdataset {
+            Some(x) if x > std::u8::MAX as u32 => return Err(Status::new(
+                Code::InvalidArgument, format!("invalid subdataset {}", x))),
+            x => x.map(|x| x as u8),
+        };
+
+        // stage image in a temporary file, retaining its extension
+        let filename = match Path::new(&header.filename).file_name()
+                .and_then(|x| x.to_str()) {
+            Some(filename) => filename.to_string(),
+            None => return Err(Status::new(Code::InvalidArgument,
+                format!("invalid filename '{}'", header.filename))),
+        };
+
+        let path = std::env::temp_dir().join(format!(
+            "stip-upload-{}-{}", rand::random::<u64>(), filename));
+        let result: Result<(), Box<dyn std::error::Error + Send + Sync>>
+                = async {
+            let mut file = std::fs::File::create(&path)?;
+            file.write_all(&data)?;
+            while let Some(chunk) = stream.message().await? {
+                file.write_all(&chunk.data)?;
             }
+
+            Ok(())
+        }.await;
+
+        if let Err(e) = result {
+            let _ = std::fs::remove_file(&path);
+            return Err(Status::new(Code::Unknown,
+                format!("failed to receive upload: {}", e)));
+        }
+
+        // split and route tiles off the rpc runtime in one transaction
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        let dht = self.dht.clone();
+        std::thread::spawn(move || {
+            let result = store_upload(&album, cloud_mask, &dht,
+                header, precision, subdataset, &path);
+            let _ = std::fs::remove_file(&path);
+            let _ = tx.send(result.map_err(|e| e.to_string()));
         });
 
-        Ok(Response::new(rx))
+        match rx.await {
+            Ok(Ok(geocodes)) => Ok(Response::new(ImageUploadReply {
+                geocodes: geocodes,
+            })),
+            Ok(Err(e)) => Err(Status::new(Code::Unknown,
+                format!("failed to store upload: {}", e))),
+            Err(e) => Err(Status::new(Code::Unknown,
+                format!("upload worker failed: {}", e))),
+        }
     }
 
-    async fn split(&self, request: Request<ImageSplitRequest>)
-            -> Result<Response<ImageSplitReply>, Status> {
-        trace!("ImageSplitRequest: {:?}", request);
+    async fn zonal(&self, request: Request<ImageZonalRequest>)
+            -> Result<Response<ImageZonalReply>, Status> {
+        trace!("ImageZonalRequest: {:?}", request);
+        crate::auth::authorize(&request, &request.get_ref().album,
+            Permission::Read)?;
+        let request = request.get_ref().clone();
+
+        // parse zones before contacting any node
+        let zones = match crate::zonal::parse_zones(&request.geojson) {
+            Ok(zones) => zones,
+            Err(e) => return Err(Status::new(Code::InvalidArgument,
+                format!("failed to parse geojson: {}", e))),
+        };
+
+        // compute per tile statistics over local images
+        if request.local.unwrap_or(false) {
+            let album = crate::rpc::assert_album_exists(
+                &self.album_manager, &request.album)?;
+
+            let filter = &request.filter;
+            let images = {
+                let album = album.read().unwrap();
+                match album.list(&filter.end_timestamp, &filter.geocode,
+                        &filter.max_cloud_coverage,
+                        &filter.min_pixel_coverage, &filter.platform,
+                        filter.recurse, &filter.source,
+                        &filter.start_timestamp) {
+                    Ok(images) => images,
+                    Err(e) => return Err(Status::new(Code::Unknown,
+                        format!("failed to list images: {}", e))),
+                }
+            };
+
+            let (tx, rx) = tokio::sync::oneshot::channel();
+            std::thread::spawn(move || {
+                let _ = tx.send(local_zonal(&request, &zones, images)
+                    .map_err(|e| e.to_string()));
+            });
+
+            return match rx.await {
+                Ok(Ok(statistics)) => Ok(Response::new(ImageZonalReply {
+                    statistics: statistics,
+                })),
+                Ok(Err(e)) => Err(Status::new(Code::Unknown,
+                    format!("failed to compute zonal statistics: {}", e))),
+                Err(e) => Err(Status::new(Code::Unknown,
+                    format!("zonal statistics worker failed: {}", e))),
+            };
+        }
+
+        // gather per tile statistics from each node
+        let mut local_request = request.clone();
+        local_request.local = Some(true);
+
+        let mut tiles = HashSet::new();
+        let mut aggregates = BTreeMap::new();
+        for node in self.dht.nodes() {
+            let addr = format!("{}:{}", node.get_ip_address(),
+                node.get_metadata("rpc_port").unwrap());
+
+            let mut client = match crate::tls::channel(
+                    &addr).await.map(ImageManagementClient::new) {
+                Ok(client) => client,
+                Err(e) => return Err(Status::new(Code::Unavailable,
+                    format!("connection to {} failed: {}", addr, e))),
+            };
+
+            let reply = client.zonal(Request::new(local_request.clone()))
+                .await?.into_inner();
+
+            // merge statistics, ignoring tiles already read from replicas
+            let mut node_tiles = HashSet::new();
+            for statistics in reply.statistics.into_iter() {
+                let tile = statistics.tile.clone().unwrap_or(String::new());
+                if tiles.contains(&tile) {
+                    continue;
+                }
+
+                node_tiles.insert(tile);
+                let aggregate = aggregates.entry((statistics.zone,
+                        statistics.platform, statistics.timestamp,
+                        statistics.band))
+                    .or_insert((0u64, 0f64));
+                aggregate.0 += statistics.count;
+                aggregate.1 += statistics.sum;
+            }
+
+            tiles.extend(node_tiles);
+        }
+
+        // initialize reply
+        let reply = ImageZonalReply {
+            statistics: aggregates.into_iter().map(|((zone, platform,
+                    timestamp, band), (count, sum))| ZonalStatistics {
+                band: band,
+                count: count,
+                mean: Some(sum / count as f64),
+                platform: platform,
+                sum: sum,
+                tile: None,
+                timestamp: timestamp,
+                zone: zone,
+            }).collect(),
+        };
+
+        Ok(Response::new(reply))
+    }
+
+    async fn pipeline(&self, request: Request<ImagePipelineRequest>)
+            -> Result<Response<ImagePipelineReply>, Status> {
+        trace!("ImagePipelineRequest: {:?}", request);
+        for album in pipeline_albums(request.get_ref()) {
+            crate::auth::authorize(&request, album, Permission::Write)?;
+        }
+
+        crate::audit::record(&request);
+        let mut request = request.get_ref().clone();
+
+        // resolve saved filters referenced by stages
+        for stage in request.stages.iter_mut() {
+            if let Some(fill_request) = stage.fill_request.as_mut() {
+                fill_request.filter = crate::rpc::resolve_filter(
+                    &self.filter_store, &fill_request.filter)?;
+            }
+
+            if let Some(split_request) = stage.split_request.as_mut() {
+                split_request.filter = crate::rpc::resolve_filter(
+                    &self.filter_store, &split_request.filter)?;
+            }
+        }
+
+        let request = &request;
+
+        // initialize pipeline
+        let task_id = request.task_id.unwrap_or(rand::random::<u64>());
+        let pipeline = {
+            let task_manager = self.task_manager.read().unwrap();
+            match task_manager.create_pipeline(task_id, request) {
+                Ok(pipeline) => pipeline,
+                Err(e) => return Err(Status::new(Code::InvalidArgument,
+                    format!("failed to create pipeline: {}", e))),
+            }
+        };
+
+        // start pipeline
+        let task_handle = pipeline.start(self.album_manager.clone(),
+            self.dht.clone(), self.task_manager.clone());
+
+        // register pipeline with TaskHandler
+        let task_id = {
+            let mut task_manager = self.task_manager.write().unwrap();
+            match task_manager.register(task_handle,
+                    Some(task_id), request.priority.unwrap_or(0)) {
+                Ok(task_id) => task_id,
+                Err(e) => return Err(Status::new(Code::Unknown,
+                    format!("failed to register pipeline: {}", e))),
+            }
+        };
+
+        // initialize reply
+        let reply = ImagePipelineReply {
+            task_id: task_id,
+        };
+
+        Ok(Response::new(reply))
+    }
+
+    async fn preload(&self, request: Request<ImagePreloadRequest>)
+            -> Result<Response<ImagePreloadReply>, Status> {
+        trace!("ImagePreloadRequest: {:?}", request);
+        crate::auth::authorize(&request, &request.get_ref().album,
+            Permission::Read)?;
         let request = request.get_ref();
         let filter = &request.filter;
 
@@ -336,31 +2319,172 @@ impl ImageManagement for ImageManagementImpl {
             &self.album_manager, &request.album)?;
 
         // initialize task
-        let task = Arc::new(SplitTask::new(album, self.dht.clone(),
+        let task = Arc::new(PreloadTask::new(album,
             filter.end_timestamp.clone(), filter.geocode.clone(),
-            request.geocode_bound.clone(), filter.platform.clone(),
-            request.precision as usize, filter.recurse,
-            filter.start_timestamp.clone()));
+            filter.max_cloud_coverage, filter.min_pixel_coverage,
+            filter.platform.clone(), filter.recurse,
+            filter.source.clone(), filter.start_timestamp.clone()));
 
         // start task
-        let task_handle = match task.start(request.thread_count as u8) {
+        let task_handle = match task.start(request.thread_count as u8,
+                None, RetryPolicy::default()) {
             Ok(task_handle) => task_handle,
             Err(e) => return Err(Status::new(Code::Unknown,
-                format!("failed to start SplitTask: {}", e))),
+                format!("failed to start PreloadTask: {}", e))),
         };
 
         // register task with TaskHandler
         let task_id = {
             let mut task_manager = self.task_manager.write().unwrap();
-            match task_manager.register(task_handle, request.task_id) {
+            match task_manager.register(task_handle, request.task_id,
+                    request.priority.unwrap_or(0)) {
                 Ok(task_id) => task_id,
                 Err(e) => return Err(Status::new(Code::Unknown,
-                    format!("failed to register SplitTask: {}", e))),
+                    format!("failed to register PreloadTask: {}", e))),
             }
         };
- 
+
         // initialize reply
-        let reply = ImageSplitReply {
+        let reply = ImagePreloadReply {
+            task_id: task_id,
+        };
+
+        Ok(Response::new(reply))
+    }
+
+    async fn preview(&self, request: Request<ImagePreviewRequest>)
+            -> Result<Response<ImagePreviewReply>, Status> {
+        trace!("ImagePreviewRequest: {:?}", request);
+        crate::auth::authorize(&request, &request.get_ref().album,
+            Permission::Read)?;
+        let request = request.get_ref();
+
+        // parse quicklook format, defaulting to the node configuration
+        let format = match request.format.as_ref()
+                .map(|x| x.parse::<QuicklookFormat>()).transpose() {
+            Ok(format) => format.unwrap_or(crate::quicklook::format()),
+            Err(e) => return Err(Status::new(Code::InvalidArgument, e)),
+        };
+
+        // ensure album exists
+        let album = crate::rpc::assert_album_exists(
+            &self.album_manager, &request.album)?;
+
+        let path = {
+            let album = album.read().unwrap();
+            match album.get_image_path(false, &request.geocode,
+                    &request.platform, &request.source,
+                    request.subdataset as u8, &request.tile) {
+                Ok(path) => path,
+                Err(e) => return Err(Status::new(Code::Unknown,
+                    format!("failed to get image path: {}", e))),
+            }
+        };
+
+        // render local images off the rpc runtime
+        if crate::compaction::exists(&path) {
+            let bands: Vec<usize> =
+                request.bands.iter().map(|x| *x as usize).collect();
+            let size = request.size.map(|x| x as usize);
+
+            let (tx, rx) = tokio::sync::oneshot::channel();
+            std::thread::spawn(move || {
+                let _ = tx.send(crate::quicklook::preview(&path,
+                    &bands, format, size).map_err(|e| e.to_string()));
+            });
+
+            return match rx.await {
+                Ok(Ok(data)) => Ok(Response::new(ImagePreviewReply {
+                    data: data,
+                    format: format.extension().to_string(),
+                })),
+                Ok(Err(e)) => Err(Status::new(Code::Unknown,
+                    format!("failed to render preview: {}", e))),
+                Err(e) => Err(Status::new(Code::Unknown,
+                    format!("preview worker failed: {}", e))),
+            };
+        } else if request.local.unwrap_or(false) {
+            return Err(Status::new(Code::NotFound,
+                format!("image '{}' not found", request.tile)));
+        }
+
+        // request preview from the nodes which may hold the image
+        let mut local_request = request.clone();
+        local_request.local = Some(true);
+        for node in self.dht.nodes() {
+            let addr = format!("{}:{}", node.get_ip_address(),
+                node.get_metadata("rpc_port").unwrap());
+
+            let mut client = match crate::tls::channel(
+                    &addr).await.map(ImageManagementClient::new) {
+                Ok(client) => client,
+                Err(e) => {
+                    warn!("connection to {} failed: {}", addr, e);
+                    continue;
+                },
+            };
+
+            match client.preview(Request::new(local_request.clone())).await {
+                Ok(reply) => return Ok(reply),
+                Err(e) if e.code() == Code::NotFound => continue,
+                Err(e) => warn!("preview from {} failed: {}", addr, e),
+            }
+        }
+
+        Err(Status::new(Code::NotFound,
+            format!("image '{}' not found", request.tile)))
+    }
+
+    async fn reproject(&self, request: Request<ImageReprojectRequest>)
+            -> Result<Response<ImageReprojectReply>, Status> {
+        trace!("ImageReprojectRequest: {:?}", request);
+        crate::auth::authorize(&request, &request.get_ref().album,
+            Permission::Write)?;
+        crate::audit::record(&request);
+        let request = request.get_ref();
+        let filter = &request.filter;
+
+        // ensure album exists
+        let album = crate::rpc::assert_album_exists(
+            &self.album_manager, &request.album)?;
+
+        // initialize task
+        let resampling = request.resampling.clone()
+            .unwrap_or("near".to_string());
+        let task = match ReprojectTask::new(album, filter.end_timestamp,
+                request.epsg_code, filter.geocode.clone(),
+                filter.max_cloud_coverage, filter.min_pixel_coverage,
+                filter.platform.clone(), filter.recurse, resampling,
+                request.resolution, filter.source.clone(),
+                filter.start_timestamp) {
+            Ok(task) => Arc::new(task),
+            Err(e) => return Err(Status::new(Code::InvalidArgument,
+                format!("failed to initialize ReprojectTask: {}", e))),
+        };
+
+        // start task
+        let mut task_handle = match task.start(request.thread_count as u8,
+                None, RetryPolicy::from(&request.retry_policy)) {
+            Ok(task_handle) => task_handle,
+            Err(e) => return Err(Status::new(Code::Unknown,
+                format!("failed to start ReprojectTask: {}", e))),
+        };
+
+        task_handle.set_tenant(request.tenant.clone());
+
+        // register task with TaskHandler
+        let task_id = {
+            let mut task_manager = self.task_manager.write().unwrap();
+            match task_manager.register(task_handle, request.task_id,
+                    request.priority.unwrap_or(0)) {
+                Ok(task_id) => task_id,
+                Err(e) => return Err(Status::new(Code::Unknown,
+                    format!("failed to register ReprojectTask: {}", e))),
+            }
+        };
+
+        // initialize reply
+        let reply = ImageReprojectReply {
             task_id: task_id,
         };
 
@@ -370,11 +2494,16 @@ impl ImageManagement for ImageManagementImpl {
     async fn store(&self, request: Request<ImageStoreRequest>)
             -> Result<Response<ImageStoreReply>, Status> {
         trace!("ImageStoreRequest: {:?}", request);
+        crate::auth::authorize(&request, &request.get_ref().album,
+            Permission::Write)?;
+        crate::audit::record(&request);
         let request = request.get_ref();
  
         // ensure album exists
         let album = crate::rpc::assert_album_exists(
             &self.album_manager, &request.album)?;
+        let precision = crate::rpc::resolve_precision(
+            &album, request.precision)?;
 
         // initialize task
         let format = match ProtoImageFormat
@@ -390,21 +2519,89 @@ impl ImageManagement for ImageManagementImpl {
             ProtoImageFormat::Vnp21v001 => ImageFormat::VNP21V001,
         };
 
+        let pair = match &request.pair {
+            Some(pair) => Some(StorePair {
+                album: crate::rpc::assert_album_exists(
+                    &self.album_manager, &pair.album)?,
+                format: match ImageFormat::from_proto(pair.format) {
+                    Some(format) => format,
+                    None => return Err(Status::new(Code::InvalidArgument,
+                        format!("unknown image format {}", pair.format))),
+                },
+                pattern: pair.pattern.clone(),
+                replacement: pair.replacement.clone(),
+            }),
+            None => None,
+        };
+
+        // parse cloud mask
+        let cloud_mask = match request.cloud_mask.as_ref()
+                .map(|x| x.parse::<CloudMask>()).transpose() {
+            Ok(cloud_mask) => cloud_mask,
+            Err(e) => return Err(Status::new(Code::InvalidArgument, e)),
+        };
+
+        if let Some(cloud_mask) = cloud_mask {
+            if !format.supports_cloud_mask(cloud_mask) {
+                return Err(Status::new(Code::InvalidArgument,
+                    format!("{:?} cloud masks are unsupported for {:?}",
+                        cloud_mask, format)));
+            }
+        }
+
         let task = Arc::new(StoreEarthExplorerTask::new(
-            album, self.dht.clone(), format,
-            request.glob.clone(), request.precision as usize));
+            album, cloud_mask, self.dht.clone(), format,
+            request.glob.clone(), pair, precision));
+
+        // evaluate task without writing if requested
+        if request.dry_run.unwrap_or(false) {
+            return Ok(Response::new(ImageStoreReply {
+                dry_run: Some(dry_run(task.as_ref()).await?),
+                task_id: 0,
+            }));
+        }
+
+        // register recurring task rather than starting immediately
+        if let Some(schedule) = &request.schedule {
+            let mut definition = request.clone();
+            definition.schedule = None;
+            definition.task_id = None;
+
+            let task_id = self.add_recurring(request.task_id,
+                TaskDefinition::Store(definition), schedule)?;
+            return Ok(Response::new(ImageStoreReply {
+                dry_run: None,
+                task_id: task_id,
+            }));
+        }
+
+        // initialize task journal
+        let task_id = request.task_id.unwrap_or(rand::random::<u64>());
+        let journal = {
+            let task_manager = self.task_manager.read().unwrap();
+            match task_manager.create_journal(task_id,
+                    &TaskDefinition::Store(request.clone())) {
+                Ok(journal) => journal,
+                Err(e) => return Err(Status::new(Code::Unknown,
+                    format!("failed to create StoreTask journal: {}", e))),
+            }
+        };
 
         // start task
-        let task_handle = match task.start(request.thread_count as u8) {
+        let mut task_handle = match task.start(request.thread_count as u8,
+                Some(journal), RetryPolicy::from(&request.retry_policy)) {
             Ok(task_handle) => task_handle,
             Err(e) => return Err(Status::new(Code::Unknown,
                 format!("failed to start OpenTask: {}", e))),
         };
 
+        task_handle.set_tenant(request.tenant.clone());
+
         // register task with TaskHandler
         let task_id = {
             let mut task_manager = self.task_manager.write().unwrap();
-            match task_manager.register(task_handle, request.task_id) {
+            match task_manager.register(task_handle,
+                    Some(task_id), request.priority.unwrap_or(0)) {
                 Ok(task_id) => task_id,
                 Err(e) => return Err(Status::new(Code::Unknown,
                     format!("failed to register StoreTask: {}", e))),
@@ -413,9 +2610,396 @@ impl ImageManagement for ImageManagementImpl {
 
         // initialize reply
         let reply = ImageStoreReply {
+            dry_run: None,
             task_id: task_id,
         };
 
         Ok(Response::new(reply))
     }
 }
+
+// albums targeted by the broadcast message and the permission it requires
+fn broadcast_albums(request: &ImageBroadcastRequest)
+        -> (Vec<&str>, Permission) {
+    let album = match ImageBroadcastType::from_i32(request.message_type) {
+        Some(ImageBroadcastType::BandMath) =>
+            request.band_math_request.as_ref().map(|x| &x.album),
+        Some(ImageBroadcastType::Calc) =>
+            request.calc_request.as_ref().map(|x| &x.album),
+        Some(ImageBroadcastType::Change) =>
+            request.change_request.as_ref().map(|x| &x.album),
+        Some(ImageBroadcastType::Coalesce) =>
+            request.coalesce_request.as_ref().map(|x| &x.album),
+        Some(ImageBroadcastType::Composite) =>
+            request.composite_request.as_ref().map(|x| &x.album),
+        Some(ImageBroadcastType::Copy) =>
+            request.copy_request.as_ref().map(|x| &x.destination),
+        Some(ImageBroadcastType::Delete) =>
+            request.delete_request.as_ref().map(|x| &x.album),
+        Some(ImageBroadcastType::Execute) =>
+            request.execute_request.as_ref().map(|x| &x.album),
+        Some(ImageBroadcastType::Fill) =>
+            request.fill_request.as_ref().map(|x| &x.album),
+        Some(ImageBroadcastType::Pipeline) => return (request
+            .pipeline_request.as_ref().map(pipeline_albums)
+            .unwrap_or(Vec::new()), Permission::Write),
+        Some(ImageBroadcastType::Preload) => return (request
+            .preload_request.as_ref().map(|x| vec![x.album.as_str()])
+            .unwrap_or(Vec::new()), Permission::Read),
+        Some(ImageBroadcastType::Reproject) =>
+            request.reproject_request.as_ref().map(|x| &x.album),
+        Some(ImageBroadcastType::Split) =>
+            request.split_request.as_ref().map(|x| &x.album),
+        Some(ImageBroadcastType::Statistics) =>
+            request.statistics_request.as_ref().map(|x| &x.album),
+        Some(ImageBroadcastType::Update) =>
+            request.update_request.as_ref().map(|x| &x.album),
+        None => None,
+    };
+
+    (album.map(|x| vec![x.as_str()]).unwrap_or(Vec::new()),
+        Permission::Write)
+}
+
+// read a page of images following the cursor, converted to protobufs
+fn list_page(album: &Arc<RwLock<Album>>, album_id: &str,
+        filter: &protobuf::Filter, geometries: &[String], statistics: bool,
+        cursor: &Option<ListCursor>)
+        -> Result<(Vec<Image>, Option<ListCursor>), Status> {
+    let album = album.read().unwrap();
+    let (image_iter, next_cursor) = match album.list_page(
+            &filter.end_timestamp, &filter.geocode,
+            &filter.max_cloud_coverage, &filter.min_pixel_coverage,
+            &filter.platform, filter.recurse, &filter.source,
+            &filter.start_timestamp, cursor, LIST_PAGE_SIZE) {
+        Ok(page) => page,
+        Err(e) => return Err(Status::new(Code::Unknown,
+            format!("failed to list images: {}", e))),
+    };
+
+    // retain images intersecting each geometry
+    let image_iter = match geometries.is_empty() {
+        true => image_iter,
+        false => match crate::spatial::filter(
+                album.get_geocode(), geometries, image_iter) {
+            Ok(image_iter) => image_iter,
+            Err(e) => return Err(Status::new(Code::InvalidArgument,
+                format!("failed to filter by geometry: {}", e))),
+        },
+    };
+
+    // convert image and files to protobufs
+    let mut images = Vec::new();
+    for (i, f) in image_iter {
+        let mut files = Vec::new();
+        for file in f {
+            // attach cached statistics if requested
+            let statistics = match statistics {
+                true => match album.statistics(&i.1, &i.3, &i.4, file.2) {
+                    Ok(statistics) => statistics,
+                    Err(e) => return Err(Status::new(Code::Unknown,
+                        format!("failed to read statistics: {}", e))),
+                },
+                false => Vec::new(),
+            };
+
+            let misaligned = match album.misalignment(
+                    &i.1, &i.3, &i.4, file.2) {
+                Ok(misaligned) => misaligned,
+                Err(e) => return Err(Status::new(Code::Unknown,
+                    format!("failed to read misalignment: {}", e))),
+            };
+
+            files.push(File {
+                misaligned: misaligned,
+                path: file.0,
+                pixel_coverage: file.1,
+                statistics: statistics.into_iter()
+                    .map(|x| BandStatistics {
+                        band: x.band as u32,
+                        histogram: x.histogram,
+                        max: x.max,
+                        mean: x.mean,
+                        min: x.min,
+                        stddev: x.stddev,
+                    }).collect(),
+                subdataset: file.2 as i32,
+            })
+        }
+
+        images.push(Image {
+            album: Some(album_id.to_string()),
+            cloud_coverage: i.0,
+            geocode: i.1,
+            files: files,
+            platform: i.2,
+            source: i.3,
+            timestamp: i.5,
+        });
+    }
+
+    Ok((images, next_cursor))
+}
+
+// compile extents of images intersecting the geometries, filtering a
+//   page of images at a time so only extent counts are retained
+fn search_geometries(album: &Album, filter: &protobuf::Filter,
+        full_geocode: bool, geometries: &[String],
+        interval: &Option<TimeInterval>)
+        -> Result<Vec<crate::Extent>, Box<dyn std::error::Error>> {
+    let mut counts = BTreeMap::new();
+    let mut cursor = None;
+    loop {
+        let (images, next_cursor) = album.list_page(&filter.end_timestamp,
+            &filter.geocode, &filter.max_cloud_coverage,
+            &filter.min_pixel_coverage, &filter.platform, filter.recurse,
+            &filter.source, &filter.start_timestamp, &cursor,
+            LIST_PAGE_SIZE)?;
+
+        let images = crate::spatial::filter(
+            album.get_geocode(), geometries, images)?;
+        let extents = crate::spatial::extents(
+            full_geocode, &filter.geocode, interval, &images);
+        for (count, geocode, platform, precision, source, interval_start)
+                in extents {
+            *counts.entry((geocode, platform, precision, source,
+                interval_start)).or_insert(0) += count;
+        }
+
+        match next_cursor {
+            Some(next_cursor) => cursor = Some(next_cursor),
+            None => break,
+        }
+    }
+
+    Ok(counts.into_iter().map(|((geocode, platform, precision, source,
+            interval_start), count)| (count, geocode, platform,
+            precision, source, interval_start)).collect())
+}
+
+fn geometries(geometry: &Option<String>, min_longitude: Option<f64>,
+        max_longitude: Option<f64>, min_latitude: Option<f64>,
+        max_latitude: Option<f64>) -> Result<Vec<String>, Status> {
+    let mut geometries: Vec<String> = geometry.iter().cloned().collect();
+    if let Some(bounds) = parse_bounds(min_longitude,
+            max_longitude, min_latitude, max_latitude)? {
+        geometries.push(crate::spatial::bounds_wkt(bounds));
+    }
+
+    Ok(geometries)
+}
+
+// read a file from its replicas as a GeoTIFF, passing stored tiles
+//   through unless transformed
+fn read_geotiff(replicas: &[(SocketAddr, String)],
+        transform: &Option<ReadTransform>, tenant: &Option<String>,
+        tracker: &LatencyTracker)
+        -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    if transform.is_none() {
+        return crate::transfer::read_geotiff(replicas, tenant, tracker);
+    }
+
+    let dataset = crate::transfer::read_image(replicas,
+        &None, transform, tenant, tracker)?;
+    crate::raster::to_geotiff(&dataset)
+}
+
+fn store_upload(album: &Arc<RwLock<Album>>, cloud_mask: Option<CloudMask>,
+        dht: &Arc<Dht>, header: ImageUploadHeader, precision: usize,
+        subdataset: Option<u8>, path: &Path) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let dataset = Dataset::open(path)?;
+    let (platform, subdataset, tile, timestamp) = generic::metadata(
+        &dataset, header.platform, subdataset, header.tile, header.timestamp)?;
+
+    let transaction = Transaction::new();
+    match generic::store(album, cloud_mask, dht, precision,
+            &dataset, &platform, subdataset, &tile, timestamp,
+            Some(&transaction)) {
+        Ok(geocodes) => {
+            transaction.commit()?;
+            Ok(geocodes)
+        },
+        Err(e) => {
+            transaction.abort();
+            Err(e)
+        },
+    }
+}
+
+// albums written by the stages of a pipeline
+fn pipeline_albums(request: &ImagePipelineRequest) -> Vec<&str> {
+    request.stages.iter().filter_map(|stage| stage.coalesce_request
+            .as_ref().map(|x| &x.album)
+        .or(stage.fill_request.as_ref().map(|x| &x.album))
+        .or(stage.split_request.as_ref().map(|x| &x.album))
+        .or(stage.store_request.as_ref().map(|x| &x.album)))
+        .map(|x| x.as_str()).collect()
+}
+
+fn parse_bounds(min_longitude: Option<f64>, max_longitude: Option<f64>,
+        min_latitude: Option<f64>, max_latitude: Option<f64>)
+        -> Result<Option<(f64, f64, f64, f64)>, Status> {
+    match (min_longitude, max_longitude, min_latitude, max_latitude) {
+        (Some(min_x), Some(max_x), Some(min_y), Some(max_y)) => {
+            if min_x > max_x || min_y > max_y {
+                return Err(Status::new(Code::InvalidArgument,
+                    "bounds minimum must not exceed maximum".to_string()));
+            }
+
+            Ok(Some((min_x, max_x, min_y, max_y)))
+        },
+        (None, None, None, None) => Ok(None),
+        _ => Err(Status::new(Code::InvalidArgument,
+            "bounds require minimum and maximum longitude and latitude".to_string())),
+    }
+}
+
+// convert a requested read transform, clipping to the bounds or
+//   otherwise the envelope of the clip geometry
+fn read_transform(transform: &Option<ImageReadTransform>,
+        bounds: Option<(f64, f64, f64, f64)>)
+        -> Result<Option<ReadTransform>, Status> {
+    let transform = match transform {
+        Some(transform) => transform,
+        None => return Ok(bounds.map(|bounds| ReadTransform {
+            bounds: Some(bounds),
+            ..Default::default()
+        })),
+    };
+
+    if transform.bands.len() > std::u8::MAX as usize {
+        return Err(Status::new(Code::InvalidArgument,
+            format!("band subset exceeds {} bands", std::u8::MAX)));
+    }
+
+    let bounds = match (&transform.geometry, bounds) {
+        (Some(geometry), None) => Some(crate::spatial::envelope(geometry)
+            .map_err(|e| Status::new(Code::InvalidArgument,
+                format!("invalid clip geometry: {}", e)))?),
+        (_, bounds) => bounds,
+    };
+
+    let scale = match (transform.scale, transform.offset) {
+        (None, None) => None,
+        (scale, offset) =>
+            Some((scale.unwrap_or(1.0), offset.unwrap_or(0.0))),
+    };
+
+    Ok(Some(ReadTransform {
+        bands: match transform.bands.is_empty() {
+            true => None,
+            false => Some(transform.bands.clone()),
+        },
+        bounds: bounds,
+        data_type: transform.data_type.clone(),
+        geometry: transform.geometry.clone(),
+        scale: scale,
+    }))
+}
+
+fn extract_samples(request: &ImageSampleRequest, buffer: f64,
+        geocode: &Geocode, images: Vec<(crate::Image, Vec<crate::StFile>)>)
+        -> Result<Vec<ImageSample>, Box<dyn std::error::Error>> {
+    // compute buffered corners in the geocode projection
+    let mut xs = [request.longitude - buffer, request.longitude + buffer,
+        request.longitude - buffer, request.longitude + buffer];
+    let mut ys = [request.latitude - buffer, request.latitude - buffer,
+        request.latitude + buffer, request.latitude + buffer];
+    crate::raster::transform_coords(&SpatialRef::from_epsg(4326)?,
+        &SpatialRef::from_epsg(geocode.get_epsg_code())?, &mut xs, &mut ys)?;
+
+    let mut samples = Vec::new();
+    for (image, files) in images {
+        // skip images whose geocode does not contain the sample
+        let mut contains = false;
+        for (x, y) in xs.iter().zip(ys.iter()) {
+            if geocode.encode(*x, *y, image.1.len())? == image.1 {
+                contains = true;
+                break;
+            }
+        }
+
+        if !contains {
+            continue;
+        }
+
+        for file in files {
+            if let Some(subdataset) = request.subdataset {
+                if file.2 as u32 != subdataset {
+                    continue;
+                }
+            }
+
+            let dataset = crate::compaction::open(std::path::Path::new(&file.0))?;
+            if let Some((value, count)) = crate::raster::sample(&dataset,
+                    request.band as usize, request.longitude,
+                    request.latitude, buffer)? {
+                samples.push(ImageSample {
+                    cloud_coverage: image.0,
+                    count: count,
+                    geocode: image.1.clone(),
+                    platform: image.2.clone(),
+                    source: image.3.clone(),
+                    subdataset: file.2 as u32,
+                    timestamp: image.5,
+                    value: value,
+                });
+            }
+        }
+    }
+
+    Ok(samples)
+}
+
+fn local_zonal(request: &ImageZonalRequest, zones: &[String],
+        images: Vec<(crate::Image, Vec<crate::StFile>)>)
+        -> Result<Vec<ZonalStatistics>, Box<dyn std::error::Error>> {
+    let bands: Vec<usize> =
+        request.bands.iter().map(|x| *x as usize).collect();
+
+    let mut statistics = Vec::new();
+    for (image, files) in images {
+        for file in files {
+            if let Some(subdataset) = request.subdataset {
+                if file.2 as u32 != subdataset {
+                    continue;
+                }
+            }
+
+            // tiles are identified by their album relative path
+            let tile = format!("{}/{}/{}/{}-{}", image.2,
+                image.1, image.3, image.4, file.2);
+            let dataset = crate::compaction::open(std::path::Path::new(&file.0))?;
+            for (zone, band, count, sum) in
+                    crate::zonal::zonal(&dataset, zones, &bands)? {
+                statistics.push(ZonalStatistics {
+                    band: band as u32,
+                    count: count,
+                    mean: None,
+                    platform: image.2.clone(),
+                    sum: sum,
+                    tile: Some(tile.clone()),
+                    timestamp: image.5,
+                    zone: zone as u32,
+                });
+            }
+        }
+    }
+
+    Ok(statistics)
+}
+
+async fn dry_run<T, U>(task: &U) -> Result<DryRunReport, Status>
+        where T: 'static + std::fmt::Debug + Send + Sync,
+            U: Task<T> + Sync {
+    match crate::task::dry_run(task).await {
+        Ok(report) => Ok(DryRunReport {
+            destinations: report.destinations,
+            error_count: report.error_count,
+            record_count: report.record_count,
+            tile_count: report.tile_count,
+        }),
+        Err(e) => Err(Status::new(Code::Unknown,
+            format!("failed to evaluate dry run: {}", e))),
+    }
+}
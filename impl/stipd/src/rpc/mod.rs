@@ -1,14 +1,31 @@
 use tonic::{Code, Status};
 
 pub mod album;
+pub mod filter;
+pub mod health;
 pub mod image;
 pub mod node;
+pub mod reflection;
 pub mod task;
 
+use protobuf::Filter;
+
 use crate::album::{Album, AlbumManager};
+use crate::filter::FilterStore;
 
 use std::sync::{Arc, RwLock};
 
+// fully qualified names of services served by each node
+pub const SERVICES: &[&str] = &[
+    "grpc.health.v1.Health",
+    "grpc.reflection.v1alpha.ServerReflection",
+    "stip.AlbumManagement",
+    "stip.FilterManagement",
+    "stip.ImageManagement",
+    "stip.NodeManagement",
+    "stip.TaskManagement",
+];
+
 pub fn assert_album_exists(album_manager: &Arc<RwLock<AlbumManager>>,
         album: &str) -> Result<Arc<RwLock<Album>>, Status> {
     let album_manager = album_manager.read().unwrap();
@@ -19,6 +36,15 @@ pub fn assert_album_exists(album_manager: &Arc<RwLock<AlbumManager>>,
     }
 }
 
+// resolve a request precision, falling back to the album target precision
+pub fn resolve_precision(album: &Arc<RwLock<Album>>, precision: Option<u32>)
+        -> Result<usize, Status> {
+    match album.read().unwrap().resolve_precision(precision) {
+        Ok(precision) => Ok(precision),
+        Err(e) => Err(Status::new(Code::InvalidArgument, e.to_string())),
+    }
+}
+
 pub fn assert_album_not_exists(album_manager: &Arc<RwLock<AlbumManager>>,
         album: &str) -> Result<(), Status> {
     let album_manager = album_manager.read().unwrap();
@@ -28,3 +54,11 @@ pub fn assert_album_not_exists(album_manager: &Arc<RwLock<AlbumManager>>,
         None => Ok(()),
     }
 }
+
+pub fn resolve_filter(filter_store: &FilterStore, filter: &Filter)
+        -> Result<Filter, Status> {
+    match filter_store.resolve(filter) {
+        Ok(filter) => Ok(filter),
+        Err(e) => Err(Status::new(Code::NotFound, format!("{}", e))),
+    }
+}
@@ -1,37 +1,214 @@
-use protobuf::{Node, NodeListReply, NodeListRequest, NodeLocateReply, NodeLocateRequest, NodeManagement};
+use protobuf::{AlbumImageCount, AuditEntry, Event, EventType, Node, NodeAuditReply, NodeAuditRequest, NodeCapacityReply, NodeCapacityRequest, NodeDecommissionReply, NodeDecommissionRequest, NodeEventsRequest, NodeListReply, NodeManagementClient, NodeListRequest, NodeLocateReply, NodeLocateRequest, NodeManagement, NodeStatsReply, NodeStatsRequest, NodeStatusReply, NodeStatusRequest, NodeUsageReply, NodeUsageRequest, RpcCallCount, TenantUsage};
 use swarm::prelude::Dht;
-use tonic::{Request, Response, Status};
+use tokio::sync::mpsc::{Receiver, Sender};
+use tonic::{Code, Request, Response, Status};
+use tonic::metadata::MetadataMap;
 
-use std::collections::hash_map::DefaultHasher;
-use std::hash::Hasher;
-use std::sync::Arc;
+use crate::accounting::UsageLedger;
+use crate::album::AlbumManager;
+use crate::auth::{Permission, ALL_ALBUMS};
+use crate::metrics::Counter;
+use crate::task::{RetryPolicy, Task, TaskManager};
+use crate::task::drain::DrainTask;
+
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+
+const EVENTS_POLL_DURATION: Duration = Duration::from_millis(500);
 
 pub struct NodeManagementImpl {
+    album_manager: Arc<RwLock<AlbumManager>>,
     dht: Arc<Dht>,
+    directory: PathBuf,
+    ledger: Arc<UsageLedger>,
+    node_id: u32,
+    start_time: Instant,
+    task_manager: Arc<RwLock<TaskManager>>,
 }
 
 impl NodeManagementImpl {
-    pub fn new(dht: Arc<Dht>) -> NodeManagementImpl {
+    pub fn new(album_manager: Arc<RwLock<AlbumManager>>, dht: Arc<Dht>,
+            directory: PathBuf, ledger: Arc<UsageLedger>, node_id: u32,
+            task_manager: Arc<RwLock<TaskManager>>) -> NodeManagementImpl {
         NodeManagementImpl {
+            album_manager: album_manager,
             dht: dht,
+            directory: directory,
+            ledger: ledger,
+            node_id: node_id,
+            start_time: Instant::now(),
+            task_manager: task_manager,
         }
     }
 }
 
 #[tonic::async_trait]
 impl NodeManagement for NodeManagementImpl {
+    async fn audit(&self, request: Request<NodeAuditRequest>)
+            -> Result<Response<NodeAuditReply>, Status> {
+        trace!("NodeAuditRequest: {:?}", request);
+        crate::auth::authorize(&request, ALL_ALBUMS, Permission::Admin)?;
+        let request = request.get_ref();
+
+        // read matching entries from the audit log
+        let entries = match crate::audit::read(request.end_timestamp,
+                &request.identity, request.limit.map(|x| x as usize),
+                &request.operation, request.start_timestamp) {
+            Ok(entries) => entries,
+            Err(e) => return Err(Status::new(Code::Unknown,
+                format!("failed to read audit log: {}", e))),
+        };
+
+        // initialize reply
+        let reply = NodeAuditReply {
+            entries: entries.into_iter().map(|x| AuditEntry {
+                identity: x.identity,
+                operation: x.operation,
+                parameters: x.parameters,
+                sequence: x.sequence,
+                timestamp: x.timestamp,
+            }).collect(),
+        };
+
+        Ok(Response::new(reply))
+    }
+
+    async fn capacity(&self, request: Request<NodeCapacityRequest>)
+            -> Result<Response<NodeCapacityReply>, Status> {
+        trace!("NodeCapacityRequest: {:?}", request);
+        crate::auth::authenticate(&request)?;
+
+        // compute storage directory filesystem usage
+        let (disk_free_bytes, disk_total_bytes) =
+                match crate::admin::disk_usage(&self.directory) {
+            Ok(usage) => usage,
+            Err(e) => return Err(Status::new(Code::Unknown,
+                format!("failed to compute disk usage: {}", e))),
+        };
+
+        // count running tasks
+        let running_task_count = {
+            let task_manager = self.task_manager.read().unwrap();
+            task_manager.iter().filter(|(_, x)| x.running()
+                && !x.queued() && !x.paused()).count()
+        };
+
+        let cpu_count = std::thread::available_parallelism()
+            .map(|x| x.get()).unwrap_or(1);
+
+        // initialize reply
+        let reply = NodeCapacityReply {
+            cpu_count: cpu_count as u32,
+            disk_free_bytes: disk_free_bytes,
+            disk_total_bytes: disk_total_bytes,
+            running_task_count: running_task_count as u32,
+        };
+
+        Ok(Response::new(reply))
+    }
+
+    async fn decommission(&self, request: Request<NodeDecommissionRequest>)
+            -> Result<Response<NodeDecommissionReply>, Status> {
+        trace!("NodeDecommissionRequest: {:?}", request);
+        crate::auth::authorize(&request, ALL_ALBUMS, Permission::Admin)?;
+        crate::audit::record(&request);
+        let request = request.get_ref();
+
+        // exclude node from replica sets
+        let local = request.id == self.node_id;
+        if let Err(e) = crate::cluster::decommission(&self.directory,
+                request.id, local) {
+            return Err(Status::new(Code::Unknown,
+                format!("failed to decommission node: {}", e)));
+        }
+
+        if !local {
+            return Ok(Response::new(NodeDecommissionReply {
+                task_id: None,
+            }));
+        }
+
+        // start task draining images to new owners
+        let task = Arc::new(DrainTask::new(self.album_manager.clone(),
+            self.dht.clone(), request.thread_count as u8));
+        let task_handle = match task.start(request.thread_count as u8,
+                None, RetryPolicy::default()) {
+            Ok(task_handle) => task_handle,
+            Err(e) => return Err(Status::new(Code::Unknown,
+                format!("failed to start DrainTask: {}", e))),
+        };
+
+        // register task with TaskHandler
+        let task_id = {
+            let mut task_manager = self.task_manager.write().unwrap();
+            match task_manager.register(task_handle, request.task_id, 0) {
+                Ok(task_id) => task_id,
+                Err(e) => return Err(Status::new(Code::Unknown,
+                    format!("failed to register DrainTask: {}", e))),
+            }
+        };
+
+        // initialize reply
+        let reply = NodeDecommissionReply {
+            task_id: Some(task_id),
+        };
+
+        Ok(Response::new(reply))
+    }
+
+    type EventsStream = Receiver<Result<Event, Status>>;
+    async fn events(&self, request: Request<NodeEventsRequest>)
+            -> Result<Response<Self::EventsStream>, Status> {
+        trace!("NodeEventsRequest: {:?}", request);
+        crate::auth::authenticate(&request)?;
+        let metadata = request.metadata().clone();
+        let request = request.into_inner();
+        let node_id = self.node_id;
+        let (tx, rx) = tokio::sync::mpsc::channel(64);
+
+        if request.local.unwrap_or(false) {
+            // poll for events published on this node
+            let sequence = request.sequence
+                .unwrap_or(crate::event::sequence());
+            tokio::spawn(stream_local(metadata, node_id,
+                request, sequence, tx));
+            return Ok(Response::new(rx));
+        }
+
+        // merge the local event streams of every node
+        let node_request = NodeEventsRequest {
+            event_types: request.event_types.clone(),
+            local: Some(true),
+            sequence: None,
+        };
+
+        for node in self.dht.nodes() {
+            let addr = format!("{}:{}", node.get_ip_address(),
+                node.get_metadata("rpc_port").unwrap());
+            tokio::spawn(stream_remote(addr, metadata.clone(), node_id,
+                node_request.clone(), tx.clone()));
+        }
+
+        Ok(Response::new(rx))
+    }
+
     async fn list(&self, request: Request<NodeListRequest>)
             -> Result<Response<NodeListReply>, Status> {
         trace!("NodeListRequest: {:?}", request);
+        crate::auth::authenticate(&request)?;
 
         // populate cluster nodes from dht
         let mut nodes = Vec::new();
         for node in self.dht.nodes() {
             // add to nodes
             nodes.push(Node {
+                decommissioned: Some(crate::cluster::is_decommissioned(
+                    node.get_id())),
                 id: node.get_id(),
                 rpc_addr: format!("{}:{}", node.get_ip_address(),
                     node.get_metadata("rpc_port").unwrap()),
+                tokens: parse_tokens(node.get_metadata("tokens")),
                 xfer_addr: format!("{}:{}", node.get_ip_address(),
                     node.get_metadata("xfer_port").unwrap()),
             });
@@ -48,20 +225,22 @@ impl NodeManagement for NodeManagementImpl {
     async fn locate(&self, request: Request<NodeLocateRequest>)
             -> Result<Response<NodeLocateReply>, Status> {
         trace!("NodeLocateRequest: {:?}", request);
+        crate::auth::authenticate(&request)?;
         let request = request.get_ref();
 
         // compute geocode hash
-        let mut hasher = DefaultHasher::new();
-        hasher.write(request.geocode.as_bytes());
-        let hash = hasher.finish();
+        let hash = crate::cluster::hash(request.geocode.as_bytes());
 
         // discover hash location
         let node = match self.dht.locate(hash) {
             Some(node) => {
                 Some( Node {
+                    decommissioned: Some(crate::cluster::is_decommissioned(
+                        node.get_id())),
                     id: node.get_id(),
                     rpc_addr: format!("{}:{}", node.get_ip_address(),
                         node.get_metadata("rpc_port").unwrap()),
+                    tokens: parse_tokens(node.get_metadata("tokens")),
                     xfer_addr: format!("{}:{}", node.get_ip_address(),
                     node.get_metadata("xfer_port").unwrap()),
                 })
@@ -76,4 +255,214 @@ impl NodeManagement for NodeManagementImpl {
 
         Ok(Response::new(reply))
     }
+
+    async fn stats(&self, request: Request<NodeStatsRequest>)
+            -> Result<Response<NodeStatsReply>, Status> {
+        trace!("NodeStatsRequest: {:?}", request);
+        crate::auth::authenticate(&request)?;
+
+        // compile rpc counts from served rpc latencies
+        let rpc_calls = crate::metrics::rpc_calls().into_iter()
+            .map(|(method, call_count, error_count)| RpcCallCount {
+                call_count: call_count,
+                error_count: error_count,
+                method: method,
+            }).collect();
+
+        // initialize reply
+        let reply = NodeStatsReply {
+            dht_lookup_failures:
+                crate::metrics::get(Counter::DhtLookupFailures),
+            rpc_calls: rpc_calls,
+            tiles_read: crate::metrics::get(Counter::TilesRead),
+            tiles_written: crate::metrics::get(Counter::TilesWritten),
+            transfer_failed_sends:
+                crate::metrics::get(Counter::TransferFailedSends),
+            transfer_received_bytes:
+                crate::metrics::get(Counter::TransferReceivedBytes),
+            transfer_retransmits:
+                crate::metrics::get(Counter::TransferRetransmits),
+            transfer_sent_bytes:
+                crate::metrics::get(Counter::TransferSentBytes),
+        };
+
+        Ok(Response::new(reply))
+    }
+
+    async fn status(&self, request: Request<NodeStatusRequest>)
+            -> Result<Response<NodeStatusReply>, Status> {
+        trace!("NodeStatusRequest: {:?}", request);
+        crate::auth::authenticate(&request)?;
+
+        // compute storage directory filesystem usage
+        let (disk_free_bytes, disk_total_bytes) =
+                match crate::admin::disk_usage(&self.directory) {
+            Ok(usage) => usage,
+            Err(e) => return Err(Status::new(Code::Unknown,
+                format!("failed to compute disk usage: {}", e))),
+        };
+
+        // count images per album, closed albums are not indexed
+        let mut albums = Vec::new();
+        {
+            let album_manager = self.album_manager.read().unwrap();
+            for (id, album) in album_manager.iter() {
+                let image_count = match album.read().unwrap().image_count() {
+                    Ok(image_count) => image_count,
+                    Err(e) => return Err(Status::new(Code::Unknown,
+                        format!("failed to count album images: {}", e))),
+                };
+
+                albums.push(AlbumImageCount {
+                    album: id.clone(),
+                    image_count: image_count,
+                });
+            }
+        }
+
+        // count queued and running tasks
+        let (queued_task_count, running_task_count) = {
+            let task_manager = self.task_manager.read().unwrap();
+            let queued = task_manager.iter()
+                .filter(|(_, x)| x.running() && x.queued()
+                    && !x.paused()).count();
+            let running = task_manager.iter().filter(|(_, x)| x.running()
+                && !x.queued() && !x.paused()).count();
+            (queued, running)
+        };
+
+        // initialize reply
+        let reply = NodeStatusReply {
+            albums: albums,
+            disk_free_bytes: disk_free_bytes,
+            disk_total_bytes: disk_total_bytes,
+            queued_task_count: queued_task_count as u32,
+            ring_node_count: self.dht.nodes().into_iter().count() as u32,
+            running_task_count: running_task_count as u32,
+            uptime_seconds: self.start_time.elapsed().as_secs(),
+        };
+
+        Ok(Response::new(reply))
+    }
+
+    async fn usage(&self, request: Request<NodeUsageRequest>)
+            -> Result<Response<NodeUsageReply>, Status> {
+        trace!("NodeUsageRequest: {:?}", request);
+        crate::auth::authenticate(&request)?;
+        let request = request.get_ref();
+
+        // compile tenant usage from ledger
+        let usage = self.ledger.report(&request.month, &request.tenant)
+            .into_iter().map(|(month, tenant, usage)| TenantUsage {
+                month: month,
+                tenant: tenant,
+                bytes_ingested: usage.bytes_ingested,
+                bytes_served: usage.bytes_served,
+                compute_ms: usage.compute_ms,
+            }).collect();
+
+        // initialize reply
+        let reply = NodeUsageReply {
+            usage: usage,
+        };
+
+        Ok(Response::new(reply))
+    }
+}
+
+// ensure the requesting token may read the album an event refers to
+fn is_readable(metadata: &MetadataMap, event: &Event) -> bool {
+    match event.attributes.get("album") {
+        Some(album) => crate::auth::authorize_metadata(metadata,
+            album, Permission::Read).is_ok(),
+        None => true,
+    }
+}
+
+async fn stream_local(metadata: MetadataMap, node_id: u32,
+        request: NodeEventsRequest, mut sequence: u64,
+        mut tx: Sender<Result<Event, Status>>) {
+    loop {
+        let (events, next_sequence) = crate::event::since(sequence);
+        sequence = next_sequence;
+
+        for event in events {
+            if !request.event_types.is_empty() && !request.event_types
+                    .contains(&(event.event_type as i32)) {
+                continue;
+            }
+
+            let event = Event {
+                attributes: event.attributes,
+                event_type: event.event_type as i32,
+                node_id: node_id,
+                sequence: event.sequence,
+                timestamp: event.timestamp,
+            };
+
+            if !is_readable(&metadata, &event) {
+                continue;
+            }
+
+            // streams end once a send fails after the client disconnects
+            if let Err(e) = tx.send(Ok(event)).await {
+                debug!("failed to send event: {}", e);
+                return;
+            }
+        }
+
+        tokio::time::delay_for(EVENTS_POLL_DURATION).await;
+    }
+}
+
+async fn stream_remote(addr: String, metadata: MetadataMap, node_id: u32,
+        request: NodeEventsRequest, mut tx: Sender<Result<Event, Status>>) {
+    let mut client = match crate::tls::channel(&addr)
+            .await.map(NodeManagementClient::new) {
+        Ok(client) => client,
+        Err(e) => {
+            warn!("failed to connect to {} for events: {}", addr, e);
+            return;
+        },
+    };
+
+    let mut stream = match client.events(request).await {
+        Ok(reply) => reply.into_inner(),
+        Err(e) => {
+            warn!("failed to open event stream on {}: {}", addr, e);
+            return;
+        },
+    };
+
+    loop {
+        let event = match stream.message().await {
+            Ok(Some(event)) => event,
+            Ok(None) => return,
+            Err(e) => {
+                warn!("event stream on {} failed: {}", addr, e);
+                return;
+            },
+        };
+
+        // every node observes membership, forward only local changes
+        let membership = event.event_type == EventType::NodeJoined as i32
+            || event.event_type == EventType::NodeLeft as i32;
+        if (membership && event.node_id != node_id)
+                || !is_readable(&metadata, &event) {
+            continue;
+        }
+
+        if let Err(e) = tx.send(Ok(event)).await {
+            debug!("failed to send event: {}", e);
+            return;
+        }
+    }
+}
+
+fn parse_tokens<T: AsRef<str>>(tokens: Option<T>) -> Vec<u64> {
+    match tokens {
+        Some(tokens) => tokens.as_ref().split(',')
+            .filter_map(|x| x.parse::<u64>().ok()).collect(),
+        None => Vec::new(),
+    }
 }
@@ -0,0 +1,197 @@
+use prost::Message;
+use prost_types::{DescriptorProto, FileDescriptorProto, FileDescriptorSet};
+use protobuf::reflection::{ErrorResponse, FileDescriptorResponse, ListServiceResponse, ServerReflection, ServerReflectionRequest, ServerReflectionResponse, ServiceResponse};
+use protobuf::reflection::server_reflection_request::MessageRequest;
+use protobuf::reflection::server_reflection_response::MessageResponse;
+use tokio::sync::mpsc::Receiver;
+use tonic::{Code, Request, Response, Status, Streaming};
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::sync::Arc;
+
+// reflection only describes the public api and is not authenticated
+pub struct ServerReflectionImpl {
+    descriptors: Arc<Descriptors>,
+}
+
+impl ServerReflectionImpl {
+    pub fn new() -> Result<ServerReflectionImpl, Box<dyn Error>> {
+        let descriptors = Descriptors::new(protobuf::FILE_DESCRIPTOR_SET)?;
+        Ok(ServerReflectionImpl {
+            descriptors: Arc::new(descriptors),
+        })
+    }
+}
+
+#[tonic::async_trait]
+impl ServerReflection for ServerReflectionImpl {
+    type ServerReflectionInfoStream =
+        Receiver<Result<ServerReflectionResponse, Status>>;
+    async fn server_reflection_info(&self,
+            request: Request<Streaming<ServerReflectionRequest>>)
+            -> Result<Response<Self::ServerReflectionInfoStream>, Status> {
+        let mut stream = request.into_inner();
+        let descriptors = self.descriptors.clone();
+
+        // reply to each request on the stream until the client closes it
+        let (mut tx, rx) = tokio::sync::mpsc::channel(4);
+        tokio::spawn(async move {
+            loop {
+                let request = match stream.message().await {
+                    Ok(Some(request)) => request,
+                    Ok(None) => break,
+                    Err(e) => {
+                        debug!("failed to read reflection request: {}", e);
+                        break;
+                    },
+                };
+
+                trace!("ServerReflectionRequest: {:?}", request);
+                let reply = ServerReflectionResponse {
+                    message_response: Some(descriptors.process(&request)),
+                    original_request: Some(request.clone()),
+                    valid_host: request.host,
+                };
+
+                if let Err(e) = tx.send(Ok(reply)).await {
+                    debug!("failed to send reflection response: {}", e);
+                    break;
+                }
+            }
+        });
+
+        Ok(Response::new(rx))
+    }
+}
+
+struct Descriptors {
+    files: HashMap<String, FileDescriptorProto>,
+    symbols: HashMap<String, String>,
+}
+
+impl Descriptors {
+    fn new(buf: &[u8]) -> Result<Descriptors, Box<dyn Error>> {
+        let descriptor_set = FileDescriptorSet::decode(buf)?;
+
+        // index fully qualified symbols by the name of their file
+        let (mut files, mut symbols) = (HashMap::new(), HashMap::new());
+        for file in descriptor_set.file {
+            let name = file.name().to_string();
+            let prefix = match file.package() {
+                "" => String::new(),
+                package => format!("{}.", package),
+            };
+
+            for service in file.service.iter() {
+                let service_name = format!("{}{}", prefix, service.name());
+                for method in service.method.iter() {
+                    symbols.insert(format!("{}.{}",
+                        service_name, method.name()), name.clone());
+                }
+
+                symbols.insert(service_name, name.clone());
+            }
+
+            for message in file.message_type.iter() {
+                index_message(&mut symbols, &prefix, message, &name);
+            }
+
+            for enumeration in file.enum_type.iter() {
+                symbols.insert(format!("{}{}", prefix, enumeration.name()),
+                    name.clone());
+            }
+
+            files.insert(name, file);
+        }
+
+        Ok(Descriptors {
+            files: files,
+            symbols: symbols,
+        })
+    }
+
+    fn process(&self, request: &ServerReflectionRequest) -> MessageResponse {
+        let result = match &request.message_request {
+            Some(MessageRequest::FileByFilename(filename)) =>
+                self.file_response(filename),
+            Some(MessageRequest::FileContainingSymbol(symbol)) =>
+                match self.symbols.get(symbol.trim_start_matches('.')) {
+                    Some(filename) => self.file_response(filename),
+                    None => Err(Status::new(Code::NotFound,
+                        format!("symbol '{}' does not exist", symbol))),
+                },
+            Some(MessageRequest::ListServices(_)) => {
+                let services = super::SERVICES.iter()
+                    .map(|x| ServiceResponse { name: x.to_string() })
+                    .collect();
+
+                Ok(MessageResponse::ListServicesResponse(
+                    ListServiceResponse { service: services }))
+            },
+            // proto2 extensions are not used by any service
+            Some(_) => Err(Status::new(Code::Unimplemented,
+                "extensions are not supported")),
+            None => Err(Status::new(Code::InvalidArgument,
+                "missing reflection request")),
+        };
+
+        match result {
+            Ok(message_response) => message_response,
+            Err(status) => MessageResponse::ErrorResponse(ErrorResponse {
+                error_code: status.code() as i32,
+                error_message: status.message().to_string(),
+            }),
+        }
+    }
+
+    // encode the file along with each file it transitively imports
+    fn file_response(&self, filename: &str)
+            -> Result<MessageResponse, Status> {
+        let (mut filenames, mut index) = (vec![filename.to_string()], 0);
+        let mut buffers = Vec::new();
+        while index < filenames.len() {
+            let file = match self.files.get(&filenames[index]) {
+                Some(file) => file,
+                None => return Err(Status::new(Code::NotFound,
+                    format!("file '{}' does not exist", filenames[index]))),
+            };
+
+            for dependency in file.dependency.iter() {
+                if !filenames.contains(dependency) {
+                    filenames.push(dependency.clone());
+                }
+            }
+
+            let mut buf = Vec::new();
+            if let Err(e) = file.encode(&mut buf) {
+                return Err(Status::new(Code::Internal, format!(
+                    "failed to encode file '{}': {}", filenames[index], e)));
+            }
+
+            buffers.push(buf);
+            index += 1;
+        }
+
+        Ok(MessageResponse::FileDescriptorResponse(FileDescriptorResponse {
+            file_descriptor_proto: buffers,
+        }))
+    }
+}
+
+fn index_message(symbols: &mut HashMap<String, String>, prefix: &str,
+        message: &DescriptorProto, filename: &str) {
+    let name = format!("{}{}", prefix, message.name());
+    let prefix = format!("{}.", name);
+
+    for nested in message.nested_type.iter() {
+        index_message(symbols, &prefix, nested, filename);
+    }
+
+    for enumeration in message.enum_type.iter() {
+        symbols.insert(format!("{}{}", prefix, enumeration.name()),
+            filename.to_string());
+    }
+
+    symbols.insert(name, filename.to_string());
+}
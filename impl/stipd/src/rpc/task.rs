@@ -1,11 +1,16 @@
-use protobuf::{Task, TaskClearReply, TaskClearRequest, TaskBroadcastReply, TaskBroadcastRequest, TaskBroadcastType, TaskListReply, TaskListRequest, TaskManagement, TaskManagementClient};
+use protobuf::{RecurringTask, Task, TaskCancelReply, TaskCancelRequest, TaskClearReply, TaskClearRequest, TaskError, TaskErrorsReply, TaskErrorsRequest, TaskBroadcastReply, TaskBroadcastRequest, TaskBroadcastType, TaskListReply, TaskListRequest, TaskLogEntry, TaskLogsRequest, TaskManagement, TaskManagementClient, TaskPauseReply, TaskPauseRequest, TaskRegisterExecutorReply, TaskRegisterExecutorRequest, TaskResumeReply, TaskResumeRequest, TaskRetryReply, TaskRetryRequest};
 use swarm::prelude::Dht;
+use tokio::sync::mpsc::Receiver;
 use tonic::{Code, Request, Response, Status};
 
+use crate::auth::{Permission, ALL_ALBUMS};
 use crate::task::TaskManager;
 
 use std::collections::HashMap;
 use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+const LOGS_POLL_DURATION: Duration = Duration::from_millis(1000);
 
 pub struct TaskManagementImpl {
     dht: Arc<Dht>,
@@ -27,20 +32,36 @@ impl TaskManagement for TaskManagementImpl {
     async fn broadcast(&self, request: Request<TaskBroadcastRequest>)
             -> Result<Response<TaskBroadcastReply>, Status> {
         trace!("TaskBroadcastRequest: {:?}", request);
+        match TaskBroadcastType::from_i32(request.get_ref().message_type) {
+            Some(TaskBroadcastType::TaskErrors)
+                | Some(TaskBroadcastType::TaskList) =>
+                crate::auth::authenticate(&request)?,
+            _ => {
+                crate::auth::authorize(&request, ALL_ALBUMS,
+                    Permission::Write)?;
+                crate::audit::record(&request);
+            },
+        }
+
         let request = request.get_ref();
 
         // send broadcast message to each dht node
+        let mut cancel_replies = HashMap::new();
         let mut clear_replies = HashMap::new();
+        let mut errors_replies = HashMap::new();
         let mut list_replies = HashMap::new();
+        let mut pause_replies = HashMap::new();
+        let mut resume_replies = HashMap::new();
+        let mut retry_replies = HashMap::new();
 
         for node in self.dht.nodes() {
             // get rpc address
-            let addr = format!("http://{}:{}", node.get_ip_address(),
+            let addr = format!("{}:{}", node.get_ip_address(),
                 node.get_metadata("rpc_port").unwrap());
 
             // initialize grpc client
-            let mut client = match TaskManagementClient::connect(
-                    addr.clone()).await {
+            let mut client = match crate::tls::channel(
+                    &addr).await.map(TaskManagementClient::new) {
                 Ok(client) => client,
                 Err(e) => return Err(Status::new(Code::Unavailable,
                     format!("connection to {} failed: {}", addr, e))),
@@ -48,6 +69,16 @@ impl TaskManagement for TaskManagementImpl {
 
             // execute message at dht node
             match TaskBroadcastType::from_i32(request.message_type).unwrap() {
+                TaskBroadcastType::TaskCancel => {
+                    let reply = match client.cancel(request
+                            .cancel_request.clone().unwrap()).await {
+                        Ok(reply) => reply,
+                        Err(e) => return Err(Status::new(Code::Unknown,
+                            format!("cancel broadcast failed: {}", e))),
+                    };
+                    cancel_replies.insert(node.get_id(),
+                        reply.get_ref().to_owned());
+                },
                 TaskBroadcastType::TaskClear => {
                     let reply = match client.clear(request
                             .clear_request.clone().unwrap()).await {
@@ -58,6 +89,16 @@ impl TaskManagement for TaskManagementImpl {
                     clear_replies.insert(node.get_id(),
                         reply.get_ref().to_owned());
                 },
+                TaskBroadcastType::TaskErrors => {
+                    let reply = match client.errors(request
+                            .errors_request.clone().unwrap()).await {
+                        Ok(reply) => reply,
+                        Err(e) => return Err(Status::new(Code::Unknown,
+                            format!("errors broadcast failed: {}", e))),
+                    };
+                    errors_replies.insert(node.get_id(),
+                        reply.get_ref().to_owned());
+                },
                 TaskBroadcastType::TaskList => {
                     let reply = match client.list(request
                             .list_request.clone().unwrap()).await {
@@ -68,14 +109,85 @@ impl TaskManagement for TaskManagementImpl {
                     list_replies.insert(node.get_id(),
                         reply.get_ref().to_owned());
                 },
+                TaskBroadcastType::TaskPause => {
+                    let reply = match client.pause(request
+                            .pause_request.clone().unwrap()).await {
+                        Ok(reply) => reply,
+                        Err(e) => return Err(Status::new(Code::Unknown,
+                            format!("pause broadcast failed: {}", e))),
+                    };
+                    pause_replies.insert(node.get_id(),
+                        reply.get_ref().to_owned());
+                },
+                TaskBroadcastType::TaskResume => {
+                    let reply = match client.resume(request
+                            .resume_request.clone().unwrap()).await {
+                        Ok(reply) => reply,
+                        Err(e) => return Err(Status::new(Code::Unknown,
+                            format!("resume broadcast failed: {}", e))),
+                    };
+                    resume_replies.insert(node.get_id(),
+                        reply.get_ref().to_owned());
+                },
+                TaskBroadcastType::TaskRetry => {
+                    let reply = match client.retry(request
+                            .retry_request.clone().unwrap()).await {
+                        Ok(reply) => reply,
+                        Err(e) => return Err(Status::new(Code::Unknown,
+                            format!("retry broadcast failed: {}", e))),
+                    };
+                    retry_replies.insert(node.get_id(),
+                        reply.get_ref().to_owned());
+                },
             };
         }
 
         // initialize reply
         let reply = TaskBroadcastReply {
             message_type: request.message_type,
+            cancel_replies: cancel_replies,
             clear_replies: clear_replies,
+            errors_replies: errors_replies,
             list_replies: list_replies,
+            pause_replies: pause_replies,
+            resume_replies: resume_replies,
+            retry_replies: retry_replies,
+        };
+
+        Ok(Response::new(reply))
+    }
+
+    async fn cancel(&self, request: Request<TaskCancelRequest>)
+            -> Result<Response<TaskCancelReply>, Status> {
+        trace!("TaskCancelRequest: {:?}", request);
+        crate::auth::authorize(&request, ALL_ALBUMS, Permission::Write)?;
+        crate::audit::record(&request);
+        let request = request.get_ref();
+
+        // cancel task or recurring task if it exists on this node
+        let cancelled = {
+            let mut task_manager = self.task_manager.write().unwrap();
+            match task_manager.get(&request.id) {
+                Some(task_handle) => {
+                    info!("cancelling task [id={}]", request.id);
+                    task_handle.cancel();
+                    true
+                },
+                None => {
+                    let removed = task_manager.remove_recurring(&request.id);
+                    if removed {
+                        info!("cancelling recurring task [id={}]",
+                            request.id);
+                    }
+
+                    removed
+                },
+            }
+        };
+
+        // initialize reply
+        let reply = TaskCancelReply {
+            cancelled: cancelled,
         };
 
         Ok(Response::new(reply))
@@ -84,6 +196,8 @@ impl TaskManagement for TaskManagementImpl {
     async fn clear(&self, request: Request<TaskClearRequest>)
             -> Result<Response<TaskClearReply>, Status> {
         trace!("TaskClearRequest: {:?}", request);
+        crate::auth::authorize(&request, ALL_ALBUMS, Permission::Write)?;
+        crate::audit::record(&request);
 
         // clear completed tasks from task_manager
         {
@@ -101,12 +215,42 @@ impl TaskManagement for TaskManagementImpl {
         Ok(Response::new(reply))
     }
 
+    async fn errors(&self, request: Request<TaskErrorsRequest>)
+            -> Result<Response<TaskErrorsReply>, Status> {
+        trace!("TaskErrorsRequest: {:?}", request);
+        crate::auth::authenticate(&request)?;
+        let request = request.get_ref();
+
+        // retrieve record errors if task exists on this node
+        let (errors, omitted) = {
+            let task_manager = self.task_manager.read().unwrap();
+            match task_manager.get(&request.id) {
+                Some(task_handle) => (task_handle.errors().into_iter()
+                    .map(|(record, message)| TaskError {
+                        message: message,
+                        record: record,
+                    }).collect(), task_handle.omitted_errors()),
+                None => (Vec::new(), 0),
+            }
+        };
+
+        // initialize reply
+        let reply = TaskErrorsReply {
+            errors: errors,
+            omitted: Some(omitted),
+        };
+
+        Ok(Response::new(reply))
+    }
+
     async fn list(&self, request: Request<TaskListRequest>)
             -> Result<Response<TaskListReply>, Status> {
         trace!("TaskListRequest: {:?}", request);
+        crate::auth::authenticate(&request)?;
 
         // populate tasks from task_manager
         let mut tasks = Vec::new();
+        let mut recurring_tasks = Vec::new();
         {
             let task_manager = self.task_manager.read().unwrap();
             for (task_id, task_handle) in task_manager.iter() {
@@ -114,18 +258,218 @@ impl TaskManagement for TaskManagementImpl {
                 tasks.push(Task {
                     completed_count: task_handle.completed_count(),
                     id: *task_id,
+                    paused: task_handle.paused(),
+                    priority: task_handle.priority(),
+                    queued: task_handle.queued(),
                     running: task_handle.running(),
                     skipped_count: task_handle.skipped_count(),
                     total_count: task_handle.total_count(),
                 });
             }
+
+            for (id, recurring_task) in task_manager.iter_recurring() {
+                let definition = recurring_task.get_definition();
+                recurring_tasks.push(RecurringTask {
+                    album: definition.get_album().to_string(),
+                    id: *id,
+                    last_task_id: recurring_task.get_last_task_id(),
+                    next_timestamp: recurring_task.get_next_timestamp(),
+                    schedule: recurring_task.get_expression().to_string(),
+                    task_type: definition.get_type().to_string(),
+                });
+            }
         }
 
         // initialize reply
         let reply = TaskListReply {
+            recurring_tasks: recurring_tasks,
             tasks: tasks,
         };
 
         Ok(Response::new(reply))
     }
+
+    type LogsStream = Receiver<Result<TaskLogEntry, Status>>;
+    async fn logs(&self, request: Request<TaskLogsRequest>)
+            -> Result<Response<Self::LogsStream>, Status> {
+        trace!("TaskLogsRequest: {:?}", request);
+        crate::auth::authenticate(&request)?;
+        let request = request.get_ref();
+        let (follow, id) = (request.follow.unwrap_or(false), request.id);
+        let task_manager = self.task_manager.clone();
+
+        // send log entries though Sender channel, polling for new entries
+        //   while following a running task
+        let (mut tx, rx) = tokio::sync::mpsc::channel(4);
+        tokio::spawn(async move {
+            let mut sequence = 0;
+            loop {
+                // tasks not on this node produce no entries
+                let (running, log) = {
+                    let task_manager = task_manager.read().unwrap();
+                    match task_manager.get(&id) {
+                        Some(task_handle) =>
+                            (task_handle.running(), task_handle.log()),
+                        None => break,
+                    }
+                };
+
+                let (entries, next_sequence) = log.since(sequence);
+                sequence = next_sequence;
+
+                for entry in entries {
+                    let entry = TaskLogEntry {
+                        level: entry.level.to_string(),
+                        message: entry.message,
+                        timestamp: entry.timestamp,
+                    };
+
+                    if let Err(e) = tx.send(Ok(entry)).await {
+                        debug!("failed to send task log entry: {}", e);
+                        return;
+                    }
+                }
+
+                if !follow || !running {
+                    break;
+                }
+
+                tokio::time::delay_for(LOGS_POLL_DURATION).await;
+            }
+        });
+
+        Ok(Response::new(rx))
+    }
+
+    async fn pause(&self, request: Request<TaskPauseRequest>)
+            -> Result<Response<TaskPauseReply>, Status> {
+        trace!("TaskPauseRequest: {:?}", request);
+        crate::auth::authorize(&request, ALL_ALBUMS, Permission::Write)?;
+        crate::audit::record(&request);
+        let request = request.get_ref();
+
+        // pause task if it exists on this node
+        let paused = {
+            let task_manager = self.task_manager.read().unwrap();
+            match task_manager.get(&request.id) {
+                Some(task_handle) => {
+                    info!("pausing task [id={}]", request.id);
+                    task_handle.pause();
+                    true
+                },
+                None => false,
+            }
+        };
+
+        // initialize reply
+        let reply = TaskPauseReply {
+            paused: paused,
+        };
+
+        Ok(Response::new(reply))
+    }
+
+    async fn register_executor(&self,
+            request: Request<TaskRegisterExecutorRequest>)
+            -> Result<Response<TaskRegisterExecutorReply>, Status> {
+        trace!("TaskRegisterExecutorRequest: {:?}", request);
+        crate::auth::authorize(&request, ALL_ALBUMS, Permission::Admin)?;
+        crate::audit::record(&request);
+        let request = request.get_ref();
+
+        // register executor, replacing any prior address
+        let replaced = {
+            let mut task_manager = self.task_manager.write().unwrap();
+            task_manager.register_executor(request.name.clone(),
+                request.address.clone())
+        };
+
+        // initialize reply
+        let reply = TaskRegisterExecutorReply {
+            replaced: replaced,
+        };
+
+        Ok(Response::new(reply))
+    }
+
+    async fn resume(&self, request: Request<TaskResumeRequest>)
+            -> Result<Response<TaskResumeReply>, Status> {
+        trace!("TaskResumeRequest: {:?}", request);
+        crate::auth::authorize(&request, ALL_ALBUMS, Permission::Write)?;
+        crate::audit::record(&request);
+        let request = request.get_ref();
+
+        // resume task if it exists on this node
+        let resumed = {
+            let task_manager = self.task_manager.read().unwrap();
+            match task_manager.get(&request.id) {
+                Some(task_handle) => {
+                    info!("resuming task [id={}]", request.id);
+                    task_handle.resume();
+                    true
+                },
+                None => false,
+            }
+        };
+
+        // initialize reply
+        let reply = TaskResumeReply {
+            resumed: resumed,
+        };
+
+        Ok(Response::new(reply))
+    }
+
+    async fn retry(&self, request: Request<TaskRetryRequest>)
+            -> Result<Response<TaskRetryReply>, Status> {
+        trace!("TaskRetryRequest: {:?}", request);
+        crate::auth::authorize(&request, ALL_ALBUMS, Permission::Write)?;
+        crate::audit::record(&request);
+        let request = request.get_ref();
+
+        // retry failed records if task exists on this node
+        let retry_count = {
+            let mut task_manager = self.task_manager.write().unwrap();
+            let task_handle = match task_manager.get(&request.id) {
+                Some(task_handle) if task_handle.skipped_count() != 0 => {
+                    info!("retrying task [id={}]", request.id);
+                    match task_handle.retry(request.thread_count as u8) {
+                        Ok(mut retry_handle) => {
+                            retry_handle.set_tenant(
+                                task_handle.tenant().clone());
+                            Some((retry_handle,
+                                task_handle.priority(),
+                                task_handle.skipped_count()))
+                        },
+                        Err(e) => {
+                            warn!("failed to retry task [id={}]: {}",
+                                request.id, e);
+                            None
+                        },
+                    }
+                },
+                _ => None,
+            };
+
+            match task_handle {
+                Some((task_handle, priority, retry_count)) => {
+                    if let Err(e) = task_manager.register(
+                            task_handle, Some(request.id), priority) {
+                        return Err(Status::new(Code::Unknown,
+                            format!("failed to register task: {}", e)));
+                    }
+
+                    retry_count
+                },
+                None => 0,
+            }
+        };
+
+        // initialize reply
+        let reply = TaskRetryReply {
+            retry_count: retry_count,
+        };
+
+        Ok(Response::new(reply))
+    }
 }
@@ -0,0 +1,61 @@
+use chrono::Utc;
+use serde_json::{json, Value};
+
+use std::error::Error;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Instant;
+
+const SLOW_LOG: &str = "slow.log";
+
+static SLOW_LOG_FILE: Mutex<Option<File>> = Mutex::new(None);
+static THRESHOLD_MS: AtomicU64 = AtomicU64::new(0);
+
+pub fn configure(directory: &Path, threshold_ms: u64)
+        -> Result<(), Box<dyn Error>> {
+    if threshold_ms == 0 {
+        return Ok(());
+    }
+
+    let path = directory.join(SLOW_LOG);
+    let file = OpenOptions::new().append(true).create(true).open(&path)?;
+
+    info!("initailizing slow operation log [path={:?}, threshold_ms={}]",
+        path, threshold_ms);
+    *SLOW_LOG_FILE.lock().unwrap() = Some(file);
+    THRESHOLD_MS.store(threshold_ms, Ordering::SeqCst);
+
+    Ok(())
+}
+
+// record an operation started at the instant if it exceeded the
+//   threshold, parameters are only compiled for slow operations
+pub fn record<F: FnOnce() -> Value>(operation: &str, album: &str,
+        start: Instant, parameters: F) {
+    let threshold_ms = THRESHOLD_MS.load(Ordering::Relaxed);
+    let duration_ms = start.elapsed().as_millis() as u64;
+    if threshold_ms == 0 || duration_ms < threshold_ms {
+        return;
+    }
+
+    let parameters = parameters();
+    warn!("slow {} on album '{}' took {}ms: {}",
+        operation, album, duration_ms, parameters);
+
+    let entry = json!({
+        "album": album,
+        "duration_ms": duration_ms,
+        "operation": operation,
+        "parameters": parameters,
+        "timestamp": Utc::now().to_rfc3339(),
+    });
+
+    if let Some(file) = SLOW_LOG_FILE.lock().unwrap().as_mut() {
+        if let Err(e) = writeln!(file, "{}", entry) {
+            warn!("failed to write slow operation log entry: {}", e);
+        }
+    }
+}
@@ -0,0 +1,266 @@
+use geocode::Geocode;
+use gdal_sys::{OGREnvelope, OGRErr, OGRGeometryH};
+
+use crate::{Extent, Image, StFile};
+use crate::index::TimeInterval;
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::error::Error;
+use std::ffi::CString;
+use std::os::raw::c_char;
+
+const GEOHASH_ALPHABET: &'static str = "0123456789bcdefghjkmnpqrstuvwxyz";
+
+// geocodes enumerated when covering a geometry at a single precision
+const MAX_COVERING_GEOCODES: usize = 1048576;
+
+struct Geometry(OGRGeometryH);
+
+impl Drop for Geometry {
+    fn drop(&mut self) {
+        unsafe { gdal_sys::OGR_G_DestroyGeometry(self.0) };
+    }
+}
+
+impl Geometry {
+    fn from_wkt(wkt: &str) -> Result<Geometry, Box<dyn Error>> {
+        let c_wkt = CString::new(wkt)?;
+        let mut c_wkt_ptr = c_wkt.as_ptr() as *mut c_char;
+        let mut c_geometry = std::ptr::null_mut();
+        let error = unsafe { gdal_sys::OGR_G_CreateFromWkt(&mut c_wkt_ptr,
+            std::ptr::null_mut(), &mut c_geometry) };
+
+        match error == OGRErr::OGRERR_NONE && !c_geometry.is_null() {
+            true => Ok(Geometry(c_geometry)),
+            false => Err("invalid wkt geometry".into()),
+        }
+    }
+
+    fn envelope(&self) -> (f64, f64, f64, f64) {
+        let mut envelope = OGREnvelope {
+            MinX: 0f64, MaxX: 0f64, MinY: 0f64, MaxY: 0f64 };
+        unsafe { gdal_sys::OGR_G_GetEnvelope(self.0, &mut envelope) };
+        (envelope.MinX, envelope.MaxX, envelope.MinY, envelope.MaxY)
+    }
+
+    fn intersects(&self, other: &Geometry) -> bool {
+        unsafe { gdal_sys::OGR_G_Intersects(self.0, other.0) != 0 }
+    }
+
+    fn transform(&self, epsg_code: u32) -> Result<(), Box<dyn Error>> {
+        let error = unsafe {
+            let c_src = gdal_sys::OSRNewSpatialReference(std::ptr::null());
+            gdal_sys::OSRImportFromEPSG(c_src, 4326);
+            let c_dst = gdal_sys::OSRNewSpatialReference(std::ptr::null());
+            gdal_sys::OSRImportFromEPSG(c_dst, epsg_code as i32);
+
+            gdal_sys::OGR_G_AssignSpatialReference(self.0, c_src);
+            let error = gdal_sys::OGR_G_TransformTo(self.0, c_dst);
+
+            gdal_sys::OSRRelease(c_src);
+            gdal_sys::OSRRelease(c_dst);
+            error
+        };
+
+        match error {
+            OGRErr::OGRERR_NONE => Ok(()),
+            _ => Err("failed to transform geometry".into()),
+        }
+    }
+}
+
+// parse wkt or geojson, in longitude / latitude, into the geocode projection
+fn parse(geometry: &str, epsg_code: u32)
+        -> Result<Vec<Geometry>, Box<dyn Error>> {
+    let geometry = geometry.trim();
+    let geometries = match geometry.starts_with('{') {
+        true => {
+            let mut geometries = Vec::new();
+            for zone in crate::zonal::parse_zones(geometry)? {
+                let c_geojson = CString::new(zone)?;
+                let c_geometry = unsafe { gdal_sys::
+                    OGR_G_CreateGeometryFromJson(c_geojson.as_ptr()) };
+                if c_geometry.is_null() {
+                    return Err("invalid geojson geometry".into());
+                }
+
+                geometries.push(Geometry(c_geometry));
+            }
+
+            geometries
+        },
+        false => vec![Geometry::from_wkt(geometry)?],
+    };
+
+    if epsg_code != 4326 {
+        for geometry in geometries.iter() {
+            geometry.transform(epsg_code)?;
+        }
+    }
+
+    Ok(geometries)
+}
+
+// geocodes at each precision whose bounds intersect the geometry
+pub fn covering_geocodes(geocode: &Geocode, geometry: &str,
+        precisions: &[usize])
+        -> Result<HashMap<usize, HashSet<String>>, Box<dyn Error>> {
+    let geometries = parse(geometry, geocode.get_epsg_code())?;
+    let (min_cx, max_cx, min_cy, max_cy) = geometries.iter()
+        .map(|x| x.envelope())
+        .fold((std::f64::MAX, std::f64::MIN, std::f64::MAX, std::f64::MIN),
+            |a, b| (a.0.min(b.0), a.1.max(b.1), a.2.min(b.2), a.3.max(b.3)));
+
+    let mut covering = HashMap::new();
+    for precision in precisions.iter() {
+        let (x_interval, y_interval) = geocode.get_intervals(*precision);
+        let count = ((max_cx - min_cx) / x_interval + 2f64)
+            * ((max_cy - min_cy) / y_interval + 2f64);
+        if count > MAX_COVERING_GEOCODES as f64 {
+            return Err(format!("geometry covers too many geocodes at precision {}", precision).into());
+        }
+
+        // test each geocode window within the geometry envelope
+        let mut geocodes = HashSet::new();
+        for (window_min_cx, window_max_cx, window_min_cy, window_max_cy) in
+                st_image::coordinate::get_windows(min_cx, max_cx,
+                    min_cy, max_cy, x_interval, y_interval) {
+            let window = Geometry::from_wkt(&bounds_wkt((window_min_cx,
+                window_max_cx, window_min_cy, window_max_cy)))?;
+
+            if geometries.iter().any(|x| x.intersects(&window)) {
+                geocodes.insert(geocode.encode(
+                    (window_min_cx + window_max_cx) / 2.0,
+                    (window_min_cy + window_max_cy) / 2.0, *precision)?);
+            }
+        }
+
+        covering.insert(*precision, geocodes);
+    }
+
+    Ok(covering)
+}
+
+// wkt polygon of a bounding box
+// longitude / latitude envelope of a wkt or geojson geometry
+pub fn envelope(geometry: &str)
+        -> Result<(f64, f64, f64, f64), Box<dyn Error>> {
+    let geometries = parse(geometry, 4326)?;
+    Ok(geometries.iter().map(|x| x.envelope())
+        .fold((std::f64::MAX, std::f64::MIN, std::f64::MAX, std::f64::MIN),
+            |a, b| (a.0.min(b.0), a.1.max(b.1), a.2.min(b.2), a.3.max(b.3))))
+}
+
+pub fn bounds_wkt(bounds: (f64, f64, f64, f64)) -> String {
+    let (min_x, max_x, min_y, max_y) = bounds;
+    format!("POLYGON (({} {}, {} {}, {} {}, {} {}, {} {}))", min_x, min_y,
+        max_x, min_y, max_x, max_y, min_x, max_y, min_x, min_y)
+}
+
+// retain images whose geocode bounds intersect every geometry
+pub fn filter(geocode: &Geocode, geometries: &[String],
+        images: Vec<(Image, Vec<StFile>)>)
+        -> Result<Vec<(Image, Vec<StFile>)>, Box<dyn Error>> {
+    let mut precisions: Vec<usize> =
+        images.iter().map(|x| (x.0).1.len()).collect();
+    precisions.sort();
+    precisions.dedup();
+
+    let mut images = images;
+    for geometry in geometries.iter() {
+        let covering = covering_geocodes(geocode, geometry, &precisions)?;
+        images = images.into_iter().filter(|(image, _)| covering
+                .get(&image.1.len())
+                .map(|x| x.contains(&image.1)).unwrap_or(false))
+            .collect();
+    }
+
+    Ok(images)
+}
+
+// group images into extents, matching the album index search
+pub fn extents(full_geocode: bool, geocode: &Option<String>,
+        interval: &Option<TimeInterval>, images: &[(Image, Vec<StFile>)])
+        -> Vec<Extent> {
+    let length = match (full_geocode, geocode) {
+        (true, _) => std::usize::MAX,
+        (false, Some(geocode)) => geocode.len() + 1,
+        (false, None) => 1,
+    };
+
+    let mut extents = BTreeMap::new();
+    for (image, _) in images.iter() {
+        let geocode_search: String = image.1.chars().take(length).collect();
+        let interval_start = interval.map(|x| x.start(image.5));
+        let count = extents.entry((geocode_search, image.2.clone(),
+                image.1.len() as u8, image.3.clone(), interval_start))
+            .or_insert(0i64);
+        *count += 1;
+    }
+
+    extents.into_iter().map(|((geocode, platform, precision, source,
+            interval_start), count)| (count, geocode, platform,
+            precision, source, interval_start)).collect()
+}
+
+// longitude / latitude bounds of a geocode
+pub fn geocode_bounds(geocode: &Geocode, value: &str)
+        -> Result<(f64, f64, f64, f64), Box<dyn Error>> {
+    match geocode {
+        Geocode::Geohash => geohash_bounds(value),
+        Geocode::QuadTile => quadtile_bounds(value),
+    }
+}
+
+fn geohash_bounds(geohash: &str)
+        -> Result<(f64, f64, f64, f64), Box<dyn Error>> {
+    let (mut min_x, mut max_x, mut min_y, mut max_y) =
+        (-180f64, 180f64, -90f64, 90f64);
+
+    // bits alternate between longitude and latitude, longitude first
+    let mut longitude = true;
+    for c in geohash.chars() {
+        let index = match GEOHASH_ALPHABET.find(c) {
+            Some(index) => index,
+            None => return Err(format!("invalid geohash '{}'",
+                geohash).into()),
+        };
+
+        for i in (0..5).rev() {
+            let bit = (index >> i) & 1 == 1;
+            match (longitude, bit) {
+                (true, true) => min_x = (min_x + max_x) / 2.0,
+                (true, false) => max_x = (min_x + max_x) / 2.0,
+                (false, true) => min_y = (min_y + max_y) / 2.0,
+                (false, false) => max_y = (min_y + max_y) / 2.0,
+            }
+
+            longitude = !longitude;
+        }
+    }
+
+    Ok((min_x, max_x, min_y, max_y))
+}
+
+fn quadtile_bounds(quadkey: &str)
+        -> Result<(f64, f64, f64, f64), Box<dyn Error>> {
+    // each digit selects a quadrant of the web mercator tile
+    let (mut x, mut y) = (0u64, 0u64);
+    for c in quadkey.chars() {
+        let digit = match c.to_digit(4) {
+            Some(digit) => digit as u64,
+            None => return Err(format!("invalid quadtile '{}'",
+                quadkey).into()),
+        };
+
+        x = (x << 1) | (digit & 1);
+        y = (y << 1) | (digit >> 1);
+    }
+
+    let tile_count = 2f64.powi(quadkey.len() as i32);
+    let longitude = |x: u64| x as f64 / tile_count * 360.0 - 180.0;
+    let latitude = |y: u64| (std::f64::consts::PI
+        * (1.0 - 2.0 * y as f64 / tile_count)).sinh().atan().to_degrees();
+
+    Ok((longitude(x), longitude(x + 1), latitude(y + 1), latitude(y)))
+}
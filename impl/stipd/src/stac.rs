@@ -0,0 +1,678 @@
+use chrono::{DateTime, NaiveDateTime, SecondsFormat, Utc};
+use geocode::Geocode;
+use protobuf::{Filter, Image, ImageListRequest, ImageManagementClient};
+use serde_json::{json, Map, Value};
+use swarm::prelude::Dht;
+use tokio::runtime::Builder;
+use tonic::Request;
+
+use crate::album::AlbumManager;
+use crate::auth::Permission;
+use crate::http::{HttpRequest, HttpResponse};
+
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+use std::path::Path;
+use std::sync::{Arc, RwLock};
+
+pub const STAC_VERSION: &'static str = "1.0.0";
+
+const CONFORMANCE: [&'static str; 5] = [
+    "https://api.stacspec.org/v1.0.0/core",
+    "https://api.stacspec.org/v1.0.0/collections",
+    "https://api.stacspec.org/v1.0.0/item-search",
+    "https://api.stacspec.org/v1.0.0/ogcapi-features",
+    "http://www.opengis.net/spec/ogcapi-features-1/1.0/conf/geojson",
+];
+
+const EO_EXTENSION: &'static str =
+    "https://stac-extensions.github.io/eo/v1.0.0/schema.json";
+
+const GEOTIFF_TYPE: &'static str = "image/tiff; application=geotiff";
+
+// items returned per page when no limit is requested
+const DEFAULT_LIMIT: usize = 10;
+const MAX_LIMIT: usize = 10000;
+
+pub struct StacServer {
+    album_manager: Arc<RwLock<AlbumManager>>,
+    dht: Arc<Dht>,
+}
+
+// tile replica listed by a node, with the address of its stac server
+struct Item {
+    address: Option<String>,
+    album: String,
+    image: Image,
+    tile: String,
+}
+
+impl Item {
+    fn id(&self) -> String {
+        item_id(&self.image.geocode, &self.image.platform,
+            &self.image.source, &self.tile)
+    }
+}
+
+#[derive(Default)]
+struct Query {
+    bbox: Option<(f64, f64, f64, f64)>,
+    collections: Vec<String>,
+    end_timestamp: Option<i64>,
+    geocode: Option<String>,
+    limit: usize,
+    offset: usize,
+    start_timestamp: Option<i64>,
+}
+
+impl Query {
+    fn from_params(params: &HashMap<String, String>)
+            -> Result<Query, String> {
+        let mut query = Query { limit: DEFAULT_LIMIT, ..Default::default() };
+        if let Some(bbox) = params.get("bbox") {
+            let values = bbox.split(',').map(|x| x.trim().parse::<f64>())
+                .collect::<Result<Vec<f64>, _>>()
+                .map_err(|_| format!("invalid bbox '{}'", bbox))?;
+            query.bbox = Some(parse_bbox(&values)?);
+        }
+
+        if let Some(collections) = params.get("collections") {
+            query.collections = collections.split(',')
+                .filter(|x| !x.is_empty()).map(|x| x.to_string()).collect();
+        }
+
+        if let Some(datetime) = params.get("datetime") {
+            let (start, end) = parse_datetime(datetime)?;
+            query.start_timestamp = start;
+            query.end_timestamp = end;
+        }
+
+        if let Some(limit) = params.get("limit") {
+            query.limit = limit.parse::<usize>()
+                .map_err(|_| format!("invalid limit '{}'", limit))?;
+        }
+
+        if let Some(offset) = params.get("offset") {
+            query.offset = offset.parse::<usize>()
+                .map_err(|_| format!("invalid offset '{}'", offset))?;
+        }
+
+        query.limit = query.limit.min(MAX_LIMIT);
+        Ok(query)
+    }
+
+    fn from_json(body: &[u8]) -> Result<Query, String> {
+        let value: Value = serde_json::from_slice(body)
+            .map_err(|e| format!("invalid search body: {}", e))?;
+
+        // convert json fields to query parameters
+        let mut params = HashMap::new();
+        if let Some(bbox) = value.get("bbox").and_then(|x| x.as_array()) {
+            params.insert("bbox".to_string(), bbox.iter()
+                .map(|x| x.to_string()).collect::<Vec<String>>().join(","));
+        }
+
+        if let Some(collections) = value.get("collections")
+                .and_then(|x| x.as_array()) {
+            params.insert("collections".to_string(), collections.iter()
+                .filter_map(|x| x.as_str()).collect::<Vec<&str>>().join(","));
+        }
+
+        for key in ["datetime", "limit", "offset"].iter() {
+            match value.get(*key) {
+                Some(Value::String(x)) =>
+                    params.insert(key.to_string(), x.clone()),
+                Some(Value::Number(x)) =>
+                    params.insert(key.to_string(), x.to_string()),
+                _ => None,
+            };
+        }
+
+        Query::from_params(&params)
+    }
+
+    // query parameters selecting the page at offset
+    fn page_params(&self, offset: usize) -> String {
+        let mut params = vec![format!("limit={}", self.limit),
+            format!("offset={}", offset)];
+        if let Some((min_x, max_x, min_y, max_y)) = self.bbox {
+            params.push(format!("bbox={},{},{},{}",
+                min_x, min_y, max_x, max_y));
+        }
+
+        if !self.collections.is_empty() {
+            params.push(format!("collections={}",
+                self.collections.join(",")));
+        }
+
+        if self.start_timestamp.is_some() || self.end_timestamp.is_some() {
+            let format = |x: Option<i64>| x.map(|x| rfc3339(x))
+                .unwrap_or("..".to_string());
+            params.push(format!("datetime={}/{}",
+                format(self.start_timestamp), format(self.end_timestamp)));
+        }
+
+        params.join("&")
+    }
+}
+
+impl StacServer {
+    pub fn new(album_manager: Arc<RwLock<AlbumManager>>, dht: Arc<Dht>)
+            -> StacServer {
+        StacServer {
+            album_manager: album_manager,
+            dht: dht,
+        }
+    }
+
+    pub fn start(self, address: &str) -> Result<(), Box<dyn Error>> {
+        info!("starting stac server [address={}]", address);
+        crate::http::start(address, move |request| self.handle(request))
+    }
+
+    fn handle(&self, request: &HttpRequest) -> HttpResponse {
+        let segments: Vec<&str> = request.path.split('/')
+            .filter(|x| !x.is_empty()).collect();
+        let params = &request.query;
+
+        // reject requests without a valid token when authentication is on
+        if let Err(status) = crate::auth::check(request.bearer().as_deref(),
+                None, Permission::Read) {
+            return HttpResponse::from_status(&status);
+        }
+
+        let result = match (request.method.as_str(), segments.as_slice()) {
+            ("GET", []) => Ok(self.landing(request)),
+            ("GET", ["conformance"]) => Ok(HttpResponse::json(200,
+                &json!({ "conformsTo": CONFORMANCE.to_vec() }))),
+            ("GET", ["collections"]) => Ok(self.collections(request)),
+            ("GET", ["collections", id]) => Ok(self.collection(request, id)),
+            ("GET", ["collections", id, "items"]) =>
+                    match Query::from_params(params) {
+                Ok(mut query) => {
+                    query.collections = vec![id.to_string()];
+                    self.items(request, &query, Some(id))
+                },
+                Err(e) => Ok(HttpResponse::error(400, &e)),
+            },
+            ("GET", ["collections", id, "items", item_id]) =>
+                self.item(request, id, item_id),
+            ("GET", ["search"]) => match Query::from_params(params) {
+                Ok(query) => self.items(request, &query, None),
+                Err(e) => Ok(HttpResponse::error(400, &e)),
+            },
+            ("POST", ["search"]) => match Query::from_json(&request.body) {
+                Ok(query) => self.items(request, &query, None),
+                Err(e) => Ok(HttpResponse::error(400, &e)),
+            },
+            ("GET", ["download", album, platform, geocode,
+                    source, tile, subdataset]) => Ok(self.download(request,
+                album, platform, geocode, source, tile, subdataset)),
+            (_, ["search"]) => Ok(HttpResponse::error(405,
+                "search supports GET and POST")),
+            _ => Ok(HttpResponse::error(404, "resource not found")),
+        };
+
+        match result {
+            Ok(response) => response,
+            Err(e) => {
+                warn!("failed to handle stac request '{}': {}",
+                    request.path, e);
+                HttpResponse::error(500, &e.to_string())
+            },
+        }
+    }
+
+    fn landing(&self, request: &HttpRequest) -> HttpResponse {
+        let base_url = request.base_url();
+        let mut links = vec![
+            link("self", &format!("{}/", base_url), "application/json"),
+            link("root", &format!("{}/", base_url), "application/json"),
+            link("conformance", &format!("{}/conformance", base_url),
+                "application/json"),
+            link("data", &format!("{}/collections", base_url),
+                "application/json"),
+            link("search", &format!("{}/search", base_url),
+                "application/geo+json"),
+        ];
+
+        for id in self.album_ids(request) {
+            links.push(link("child", &format!("{}/collections/{}",
+                base_url, id), "application/json"));
+        }
+
+        HttpResponse::json(200, &json!({
+            "conformsTo": CONFORMANCE.to_vec(),
+            "description": "SpatioTemporal Image Partitioner",
+            "id": "stip",
+            "links": links,
+            "stac_version": STAC_VERSION,
+            "type": "Catalog",
+        }))
+    }
+
+    fn collections(&self, request: &HttpRequest) -> HttpResponse {
+        let base_url = request.base_url();
+        let collections: Vec<Value> = self.album_ids(request).iter()
+            .filter_map(|x| self.collection_json(&base_url, x)).collect();
+
+        HttpResponse::json(200, &json!({
+            "collections": collections,
+            "links": vec![
+                link("self", &format!("{}/collections", base_url),
+                    "application/json"),
+                link("root", &format!("{}/", base_url), "application/json"),
+            ],
+        }))
+    }
+
+    fn collection(&self, request: &HttpRequest, id: &str) -> HttpResponse {
+        if let Err(response) = authorize(request, id) {
+            return response;
+        }
+
+        match self.collection_json(&request.base_url(), id) {
+            Some(collection) => HttpResponse::json(200, &collection),
+            None => HttpResponse::error(404,
+                &format!("collection '{}' does not exist", id)),
+        }
+    }
+
+    fn collection_json(&self, base_url: &str, id: &str) -> Option<Value> {
+        let geocode = self.geocode(id)?;
+        Some(collection(id, &geocode, vec![
+            link("self", &format!("{}/collections/{}", base_url, id),
+                "application/json"),
+            link("items", &format!("{}/collections/{}/items",
+                base_url, id), "application/geo+json"),
+            link("parent", &format!("{}/", base_url), "application/json"),
+            link("root", &format!("{}/", base_url), "application/json"),
+        ]))
+    }
+
+    fn items(&self, request: &HttpRequest, query: &Query,
+            collection: Option<&str>) -> Result<HttpResponse, Box<dyn Error>> {
+        // search every album when no collections are requested
+        let mut query_albums = query.collections.clone();
+        if query_albums.is_empty() {
+            query_albums = self.album_ids(request);
+        }
+
+        for album in query_albums.iter() {
+            if self.geocode(album).is_none() {
+                return Ok(HttpResponse::error(404,
+                    &format!("collection '{}' does not exist", album)));
+            } else if let Err(response) = authorize(request, album) {
+                return Ok(response);
+            }
+        }
+
+        let items = self.gather(&query_albums, query)?;
+
+        // page through items
+        let base_url = request.base_url();
+        let features: Vec<Value> = items.iter().skip(query.offset)
+            .take(query.limit).filter_map(|x| self.item_json(&base_url, x))
+            .collect();
+
+        let path = match collection {
+            Some(id) => format!("{}/collections/{}/items", base_url, id),
+            None => format!("{}/search", base_url),
+        };
+
+        let mut links = vec![
+            link("self", &format!("{}?{}", path,
+                query.page_params(query.offset)), "application/geo+json"),
+            link("root", &format!("{}/", base_url), "application/json"),
+        ];
+
+        if query.offset + query.limit < items.len() {
+            links.push(link("next", &format!("{}?{}", path,
+                query.page_params(query.offset + query.limit)),
+                "application/geo+json"));
+        }
+
+        if query.offset > 0 {
+            links.push(link("prev", &format!("{}?{}", path,
+                query.page_params(query.offset.saturating_sub(query.limit))),
+                "application/geo+json"));
+        }
+
+        let mut response = HttpResponse::json(200, &json!({
+            "features": features,
+            "links": links,
+            "numberMatched": items.len(),
+            "numberReturned": features.len(),
+            "type": "FeatureCollection",
+        }));
+
+        response.content_type = "application/geo+json".to_string();
+        Ok(response)
+    }
+
+    fn item(&self, request: &HttpRequest, album: &str, id: &str)
+            -> Result<HttpResponse, Box<dyn Error>> {
+        if self.geocode(album).is_none() {
+            return Ok(HttpResponse::error(404,
+                &format!("collection '{}' does not exist", album)));
+        } else if let Err(response) = authorize(request, album) {
+            return Ok(response);
+        }
+
+        // item identifiers lead with the geocode
+        let query = Query {
+            geocode: id.split('_').next().map(|x| x.to_string()),
+            ..Default::default()
+        };
+
+        let items = self.gather(&[album.to_string()], &query)?;
+        let item = items.iter().filter(|x| x.id() == id)
+            .filter_map(|x| self.item_json(&request.base_url(), x)).next();
+
+        match item {
+            Some(item) => {
+                let mut response = HttpResponse::json(200, &item);
+                response.content_type = "application/geo+json".to_string();
+                Ok(response)
+            },
+            None => Ok(HttpResponse::error(404,
+                &format!("item '{}' does not exist", id))),
+        }
+    }
+
+    fn item_json(&self, base_url: &str, item: &Item) -> Option<Value> {
+        let geocode = self.geocode(&item.album)?;
+        let image = &item.image;
+
+        // assets are downloaded from the node storing the replica
+        let mut assets = Map::new();
+        if let Some(address) = &item.address {
+            for file in image.files.iter() {
+                assets.insert(format!("subdataset-{}", file.subdataset),
+                    asset(&format!("{}://{}/download/{}/{}/{}/{}/{}/{}",
+                        crate::http::scheme(), address, item.album, image.platform, image.geocode,
+                        image.source, item.tile, file.subdataset),
+                    file.pixel_coverage));
+            }
+        }
+
+        let id = item.id();
+        let collection_url = format!("{}/collections/{}",
+            base_url, item.album);
+        let links = vec![
+            link("self", &format!("{}/items/{}", collection_url, id),
+                "application/geo+json"),
+            link("parent", &collection_url, "application/json"),
+            link("collection", &collection_url, "application/json"),
+            link("root", &format!("{}/", base_url), "application/json"),
+        ];
+
+        match self::item(&item.album, &geocode,
+                image, &item.tile, assets, links) {
+            Ok(item) => Some(item),
+            Err(e) => {
+                warn!("failed to compile stac item: {}", e);
+                None
+            },
+        }
+    }
+
+    fn download(&self, request: &HttpRequest, album: &str, platform: &str,
+            geocode: &str, source: &str, tile: &str, subdataset: &str)
+            -> HttpResponse {
+        if let Err(response) = authorize(request, album) {
+            return response;
+        }
+
+        // reject path components escaping the album directory
+        if [platform, geocode, source, tile].iter()
+                .any(|x| x.starts_with('.')) {
+            return HttpResponse::error(400, "invalid image path");
+        }
+
+        let subdataset = match subdataset.parse::<u8>() {
+            Ok(subdataset) => subdataset,
+            Err(_) => return HttpResponse::error(400,
+                &format!("invalid subdataset '{}'", subdataset)),
+        };
+
+        let path = {
+            let album_manager = self.album_manager.read().unwrap();
+            let album = match album_manager.get(album) {
+                Some(album) => album,
+                None => return HttpResponse::error(404,
+                    &format!("collection '{}' does not exist", album)),
+            };
+
+            let album = album.read().unwrap();
+            album.get_image_path(false, geocode,
+                platform, source, subdataset, tile)
+        };
+
+        match path.map(|x| std::fs::read(&x)) {
+            Ok(Ok(body)) => HttpResponse {
+                body: body,
+                content_type: GEOTIFF_TYPE.to_string(),
+                status: 200,
+            },
+            _ => HttpResponse::error(404, "image does not exist"),
+        }
+    }
+
+    // albums the request token may read
+    fn album_ids(&self, request: &HttpRequest) -> Vec<String> {
+        let album_manager = self.album_manager.read().unwrap();
+        let mut ids: Vec<String> = album_manager.iter()
+            .map(|(id, _)| id.clone())
+            .filter(|id| authorize(request, id).is_ok()).collect();
+        ids.sort();
+        ids
+    }
+
+    fn geocode(&self, album: &str) -> Option<Geocode> {
+        let album_manager = self.album_manager.read().unwrap();
+        album_manager.get(album)
+            .map(|x| x.read().unwrap().get_geocode().clone())
+    }
+
+    fn gather(&self, albums: &[String], query: &Query)
+            -> Result<Vec<Item>, Box<dyn Error>> {
+        let mut runtime = Builder::new()
+            .basic_scheduler().enable_all().build()?;
+        runtime.block_on(self.gather_async(albums, query))
+    }
+
+    async fn gather_async(&self, albums: &[String], query: &Query)
+            -> Result<Vec<Item>, Box<dyn Error>> {
+        // list tiles on every node, keeping one replica of each
+        let mut items = BTreeMap::new();
+        for album in albums.iter() {
+            let list_request = ImageListRequest {
+                album: album.clone(),
+                albums: Vec::new(),
+                all_albums: None,
+                filter: Filter {
+                    end_timestamp: query.end_timestamp,
+                    geocode: query.geocode.clone(),
+                    max_cloud_coverage: None,
+                    min_pixel_coverage: None,
+                    name: None,
+                    platform: None,
+                    recurse: false,
+                    source: None,
+                    start_timestamp: query.start_timestamp,
+                },
+                geometry: None,
+                max_latitude: query.bbox.map(|x| x.3),
+                max_longitude: query.bbox.map(|x| x.1),
+                min_latitude: query.bbox.map(|x| x.2),
+                min_longitude: query.bbox.map(|x| x.0),
+                statistics: None,
+            };
+
+            for node in self.dht.nodes() {
+                let addr = format!("{}:{}", node.get_ip_address(),
+                    node.get_metadata("rpc_port").unwrap());
+                let address = node.get_metadata("stac_port").map(|x|
+                    format!("{}:{}", node.get_ip_address(), x));
+
+                let mut client = crate::tls::channel(&addr).await
+                    .map(ImageManagementClient::new)?;
+                let mut stream = client.list(Request::new(
+                    list_request.clone())).await?.into_inner();
+                while let Some(image) = stream.message().await? {
+                    // tiles are stored as 'tile-subdataset.tif'
+                    let tile = match image.files.first()
+                            .and_then(|x| Path::new(&x.path).file_stem())
+                            .and_then(|x| x.to_str())
+                            .and_then(|x| x.rsplitn(2, '-').nth(1)) {
+                        Some(tile) => tile.to_string(),
+                        None => continue,
+                    };
+
+                    let item = Item {
+                        address: address.clone(),
+                        album: album.clone(),
+                        image: image,
+                        tile: tile,
+                    };
+
+                    // prefer replicas on nodes serving downloads
+                    let key = (album.clone(), item.image.timestamp, item.id());
+                    let replace = items.get(&key).map(|x: &Item|
+                        x.address.is_none() && item.address.is_some())
+                        .unwrap_or(true);
+                    if replace {
+                        items.insert(key, item);
+                    }
+                }
+            }
+        }
+
+        Ok(items.into_iter().map(|(_, item)| item).collect())
+    }
+}
+
+pub fn asset(href: &str, pixel_coverage: f64) -> Value {
+    json!({
+        "href": href,
+        "roles": ["data"],
+        "stip:pixel_coverage": pixel_coverage,
+        "type": GEOTIFF_TYPE,
+    })
+}
+
+fn authorize(request: &HttpRequest, album: &str)
+        -> Result<(), HttpResponse> {
+    crate::auth::check(request.bearer().as_deref(), Some(album),
+        Permission::Read).map_err(|e| HttpResponse::from_status(&e))
+}
+
+pub fn collection(id: &str, geocode: &Geocode, links: Vec<Value>) -> Value {
+    json!({
+        "description": format!("stip album '{}'", id),
+        "extent": {
+            "spatial": { "bbox": [[-180.0, -90.0, 180.0, 90.0]] },
+            "temporal": { "interval": [[Value::Null, Value::Null]] },
+        },
+        "id": id,
+        "license": "proprietary",
+        "links": links,
+        "stac_version": STAC_VERSION,
+        "stip:geocode": match geocode {
+            Geocode::Geohash => "geohash",
+            Geocode::QuadTile => "quadtile",
+        },
+        "type": "Collection",
+    })
+}
+
+// stac item of a tile with its geocode bounds as geometry
+pub fn item(album: &str, geocode: &Geocode, image: &Image, tile: &str,
+        assets: Map<String, Value>, links: Vec<Value>)
+        -> Result<Value, Box<dyn Error>> {
+    let (min_x, max_x, min_y, max_y) =
+        crate::spatial::geocode_bounds(geocode, &image.geocode)?;
+
+    let mut properties = json!({
+        "datetime": rfc3339(image.timestamp),
+        "platform": image.platform,
+        "stip:geocode": image.geocode,
+        "stip:source": image.source,
+        "stip:tile": tile,
+    });
+
+    if let Some(cloud_coverage) = image.cloud_coverage {
+        properties["eo:cloud_cover"] = json!(cloud_coverage * 100.0);
+    }
+
+    Ok(json!({
+        "assets": assets,
+        "bbox": [min_x, min_y, max_x, max_y],
+        "collection": album,
+        "geometry": {
+            "coordinates": [[[min_x, min_y], [max_x, min_y],
+                [max_x, max_y], [min_x, max_y], [min_x, min_y]]],
+            "type": "Polygon",
+        },
+        "id": item_id(&image.geocode, &image.platform,
+            &image.source, tile),
+        "links": links,
+        "properties": properties,
+        "stac_extensions": [EO_EXTENSION],
+        "stac_version": STAC_VERSION,
+        "type": "Feature",
+    }))
+}
+
+pub fn item_id(geocode: &str, platform: &str, source: &str, tile: &str)
+        -> String {
+    format!("{}_{}_{}_{}", geocode, platform, source, tile)
+}
+
+pub fn link(rel: &str, href: &str, media_type: &str) -> Value {
+    json!({
+        "href": href,
+        "rel": rel,
+        "type": media_type,
+    })
+}
+
+// convert a stac 'minx,miny,maxx,maxy' bbox to bounds
+pub fn parse_bbox(values: &[f64]) -> Result<(f64, f64, f64, f64), String> {
+    let (min_x, min_y, max_x, max_y) = match values {
+        [min_x, min_y, max_x, max_y] => (*min_x, *min_y, *max_x, *max_y),
+        [min_x, min_y, _, max_x, max_y, _] =>
+            (*min_x, *min_y, *max_x, *max_y),
+        _ => return Err("bbox requires four or six values".to_string()),
+    };
+
+    if min_x > max_x || min_y > max_y {
+        return Err("bbox minimum must not exceed maximum".to_string());
+    }
+
+    Ok((min_x, max_x, min_y, max_y))
+}
+
+// parse an rfc3339 instant or an interval with open '..' ends
+fn parse_datetime(datetime: &str)
+        -> Result<(Option<i64>, Option<i64>), String> {
+    let parse = |x: &str| match x {
+        "" | ".." => Ok(None),
+        x => DateTime::parse_from_rfc3339(x).map(|x| Some(x.timestamp()))
+            .map_err(|_| format!("invalid datetime '{}'", x)),
+    };
+
+    let mut fields = datetime.splitn(2, '/');
+    let start = parse(fields.next().unwrap_or(""))?;
+    match fields.next() {
+        Some(end) => Ok((start, parse(end)?)),
+        None if start.is_some() => Ok((start, start)),
+        None => Err(format!("invalid datetime '{}'", datetime)),
+    }
+}
+
+fn rfc3339(timestamp: i64) -> String {
+    DateTime::<Utc>::from_utc(NaiveDateTime::from_timestamp(timestamp, 0),
+        Utc).to_rfc3339_opts(SecondsFormat::Secs, true)
+}
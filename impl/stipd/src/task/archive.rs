@@ -0,0 +1,346 @@
+use geocode::Geocode;
+use protobuf::{Filter, ImageListRequest};
+use serde_json::{json, Value};
+use swarm::prelude::Dht;
+
+use crate::{Image, StFile};
+use crate::album::Album;
+use crate::task::Task;
+use crate::task::logger::LogContext;
+use crate::task::export::{Destination, ExportRecord};
+use crate::transfer::{ImageWrite, LatencyTracker};
+
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+
+pub const ALBUM_MANIFEST: &str = "album.json";
+pub const IMAGE_MANIFEST: &str = "images.json";
+const ARCHIVE_VERSION: u64 = 1;
+
+pub struct AlbumManifest {
+    pub creation_options: Vec<String>,
+    pub dht_key_length: i8,
+    pub geocode: Geocode,
+    pub precision: Option<usize>,
+}
+
+impl AlbumManifest {
+    pub fn read(directory: &Path) -> Result<AlbumManifest, Box<dyn Error>> {
+        let value = read_json(&directory.join(ALBUM_MANIFEST))?;
+        match value["version"].as_u64() {
+            Some(ARCHIVE_VERSION) => {},
+            x => return Err(format!(
+                "unsupported album archive version {:?}", x).into()),
+        }
+
+        let geocode = match value["geocode"].as_str() {
+            Some("geohash") => Geocode::Geohash,
+            Some("quadtile") => Geocode::QuadTile,
+            x => return Err(format!(
+                "unsupported album archive geocode {:?}", x).into()),
+        };
+
+        let mut creation_options = Vec::new();
+        for option in value["creation_options"].as_array()
+                .unwrap_or(&Vec::new()) {
+            match option.as_str() {
+                Some(x) => creation_options.push(x.to_string()),
+                None => return Err("invalid album archive creation option"
+                    .into()),
+            }
+        }
+
+        Ok(AlbumManifest {
+            creation_options: creation_options,
+            dht_key_length: value["dht_key_length"].as_i64()
+                .unwrap_or(0) as i8,
+            geocode: geocode,
+            precision: value["precision"].as_u64().map(|x| x as usize),
+        })
+    }
+}
+
+pub struct ArchiveExportTask {
+    album: Arc<RwLock<Album>>,
+    destination: Destination,
+    dht: Arc<Dht>,
+    end_timestamp: Option<i64>,
+    geocode: Option<String>,
+    max_cloud_coverage: Option<f64>,
+    min_pixel_coverage: Option<f64>,
+    platform: Option<String>,
+    recurse: bool,
+    source: Option<String>,
+    start_timestamp: Option<i64>,
+    tenant: Option<String>,
+    tracker: Arc<LatencyTracker>,
+}
+
+impl ArchiveExportTask {
+    pub fn new(album: Arc<RwLock<Album>>, destination: Destination,
+            dht: Arc<Dht>, end_timestamp: Option<i64>,
+            geocode: Option<String>, max_cloud_coverage: Option<f64>,
+            min_pixel_coverage: Option<f64>, platform: Option<String>,
+            recurse: bool, source: Option<String>,
+            start_timestamp: Option<i64>, tenant: Option<String>,
+            tracker: Arc<LatencyTracker>)
+            -> Result<ArchiveExportTask, Box<dyn Error>> {
+        {
+            let album = album.read().unwrap();
+            info!("initailizing archive export task [album={}, destination={:?}, end_timestamp={:?}, geocode={:?}, max_cloud_coverage={:?}, min_pixel_coverage={:?}, platform={:?}, recurse={}, source={:?}, start_timestamp={:?}]",
+                album.get_id(), destination, end_timestamp, geocode,
+                max_cloud_coverage, min_pixel_coverage, platform,
+                recurse, source, start_timestamp);
+        }
+
+        Ok(ArchiveExportTask {
+            album: album,
+            destination: destination,
+            dht: dht,
+            end_timestamp: end_timestamp,
+            geocode: geocode,
+            max_cloud_coverage: max_cloud_coverage,
+            min_pixel_coverage: min_pixel_coverage,
+            platform: platform,
+            recurse: recurse,
+            source: source,
+            start_timestamp: start_timestamp,
+            tenant: tenant,
+            tracker: tracker,
+        })
+    }
+}
+
+#[tonic::async_trait]
+impl Task<ExportRecord> for ArchiveExportTask {
+    fn process(&self, record: &ExportRecord) -> Result<(), Box<dyn Error>> {
+        let (image, tile, replicas, _) = record;
+
+        // copy each file from its replicas into the archive
+        for (file, replicas) in image.files.iter().zip(replicas.iter()) {
+            let buf = crate::transfer::read_geotiff(replicas,
+                &self.tenant, &self.tracker)?;
+            self.destination.write(&archive_key(&image.geocode,
+                    &image.platform, &image.source, file.subdataset, tile),
+                |path| Ok(std::fs::write(path, &buf)?))?;
+        }
+
+        Ok(())
+    }
+
+    fn estimate(&self, _record: &ExportRecord)
+            -> Result<Option<Vec<String>>, Box<dyn Error>> {
+        // archives write no tiles
+        Ok(Some(Vec::new()))
+    }
+
+    fn log_context(&self, record: &ExportRecord) -> LogContext {
+        LogContext::new(&self.album, &record.0.geocode)
+    }
+
+    async fn records(&self) -> Result<Vec<ExportRecord>, Box<dyn Error>> {
+        // retrieve album metadata
+        let (album_id, manifest) = {
+            let album = self.album.read().unwrap();
+            let geocode = match album.get_geocode() {
+                Geocode::Geohash => "geohash",
+                Geocode::QuadTile => "quadtile",
+            };
+
+            (album.get_id().to_string(), json!({
+                "creation_options": album.get_creation_options(),
+                "dht_key_length": album.get_dht_key_length(),
+                "geocode": geocode,
+                "id": album.get_id(),
+                "precision": album.get_precision(),
+                "version": ARCHIVE_VERSION,
+            }))
+        };
+
+        // initialize ImageListRequest
+        let request = ImageListRequest {
+            album: album_id,
+            albums: Vec::new(),
+            all_albums: None,
+            filter: Filter {
+                end_timestamp: self.end_timestamp,
+                geocode: self.geocode.clone(),
+                max_cloud_coverage: self.max_cloud_coverage,
+                min_pixel_coverage: self.min_pixel_coverage,
+                name: None,
+                platform: self.platform.clone(),
+                recurse: self.recurse,
+                source: self.source.clone(),
+                start_timestamp: self.start_timestamp,
+            },
+            geometry: None,
+            max_latitude: None,
+            max_longitude: None,
+            min_latitude: None,
+            min_longitude: None,
+            statistics: None,
+        };
+
+        let records = crate::task::export::list_replicas(
+            &self.dht, &request).await?;
+
+        // write manifests describing the archived images
+        let images: Vec<Value> = records.iter()
+            .map(|(image, tile, _, _)| json!({
+                "cloud_coverage": image.cloud_coverage,
+                "files": image.files.iter().map(|file| json!({
+                    "path": archive_key(&image.geocode, &image.platform,
+                        &image.source, file.subdataset, tile),
+                    "pixel_coverage": file.pixel_coverage,
+                    "subdataset": file.subdataset,
+                })).collect::<Vec<Value>>(),
+                "geocode": image.geocode,
+                "platform": image.platform,
+                "source": image.source,
+                "tile": tile,
+                "timestamp": image.timestamp,
+            })).collect();
+
+        self.destination.write_json(ALBUM_MANIFEST, &manifest)?;
+        self.destination.write_json(IMAGE_MANIFEST, &json!(images))?;
+
+        Ok(records)
+    }
+}
+
+pub struct ArchiveImportTask {
+    album: Arc<RwLock<Album>>,
+    dht: Arc<Dht>,
+    directory: PathBuf,
+}
+
+impl ArchiveImportTask {
+    pub fn new(album: Arc<RwLock<Album>>, dht: Arc<Dht>, directory: PathBuf)
+            -> Result<ArchiveImportTask, Box<dyn Error>> {
+        {
+            let album = album.read().unwrap();
+            info!("initailizing archive import task [album={}, directory={:?}]",
+                album.get_id(), directory);
+
+            // tiles are stored under the geocodes of the archive
+            let manifest = AlbumManifest::read(&directory)?;
+            if !is_same_geocode(album.get_geocode(), &manifest.geocode) {
+                return Err(format!("album '{}' geocode differs from the archive",
+                    album.get_id()).into());
+            }
+
+            // fail before reading images if the album is frozen
+            if album.is_frozen() {
+                return Err(format!("album '{}' is frozen",
+                    album.get_id()).into());
+            }
+        }
+
+        Ok(ArchiveImportTask {
+            album: album,
+            dht: dht,
+            directory: directory,
+        })
+    }
+}
+
+#[tonic::async_trait]
+impl Task<(Image, StFile)> for ArchiveImportTask {
+    fn process(&self, record: &(Image, StFile))
+            -> Result<(), Box<dyn Error>> {
+        let (image, file) = record;
+
+        // retrieve album metadata
+        let (album_id, dht_key_length) = {
+            let album = self.album.read().unwrap();
+            (album.get_id().to_string(), album.get_dht_key_length())
+        };
+
+        // redistribute the tile using the cluster dht
+        let dataset = crate::compaction::open(Path::new(&file.0))?;
+        let addrs = crate::task::dht_lookup(
+            &self.dht, dht_key_length, &image.1)?;
+
+        let image_write = ImageWrite::new(&addrs, &album_id, &dataset,
+            &image.1, file.1, &image.2, &image.3, file.2,
+            &image.4, image.5)?;
+
+        for result in crate::transfer::send_images(&[image_write]) {
+            result?;
+        }
+
+        Ok(())
+    }
+
+    fn log_context(&self, record: &(Image, StFile)) -> LogContext {
+        LogContext::new(&self.album, &(record.0).1)
+    }
+
+    async fn records(&self)
+            -> Result<Vec<(Image, StFile)>, Box<dyn Error>> {
+        let value = read_json(&self.directory.join(IMAGE_MANIFEST))?;
+        let images = value.as_array()
+            .ok_or("album archive image manifest is not a list")?;
+
+        let mut records = Vec::new();
+        for image in images.iter() {
+            let image_tuple = (image["cloud_coverage"].as_f64(),
+                string(image, "geocode")?, string(image, "platform")?,
+                string(image, "source")?, string(image, "tile")?,
+                image["timestamp"].as_i64()
+                    .ok_or("album archive image is missing 'timestamp'")?);
+
+            for file in image["files"].as_array()
+                    .unwrap_or(&Vec::new()).iter() {
+                // archive paths are relative to the archive directory
+                let path = string(file, "path")?;
+                if Path::new(&path).is_absolute()
+                        || path.split('/').any(|x| x == "..") {
+                    return Err(format!(
+                        "album archive path '{}' is outside the archive",
+                        path).into());
+                }
+
+                let path = self.directory.join(path);
+                let file_tuple = (path.to_string_lossy().to_string(),
+                    file["pixel_coverage"].as_f64().unwrap_or(0.0),
+                    file["subdataset"].as_u64().unwrap_or(0) as u8);
+
+                records.push((image_tuple.clone(), file_tuple));
+            }
+        }
+
+        Ok(records)
+    }
+}
+
+// archive files use the 'platform/geocode/source/tile-subdataset.tif'
+//   layout of node storage
+fn archive_key(geocode: &str, platform: &str, source: &str,
+        subdataset: i32, tile: &str) -> String {
+    format!("images/{}/{}/{}/{}-{}.tif", platform,
+        geocode, source, tile, subdataset)
+}
+
+fn is_same_geocode(a: &Geocode, b: &Geocode) -> bool {
+    match (a, b) {
+        (Geocode::Geohash, Geocode::Geohash) => true,
+        (Geocode::QuadTile, Geocode::QuadTile) => true,
+        _ => false,
+    }
+}
+
+fn read_json(path: &Path) -> Result<Value, Box<dyn Error>> {
+    let buf = std::fs::read(path).map_err(|e| format!(
+        "failed to read album archive file {:?}: {}", path, e))?;
+    Ok(serde_json::from_slice(&buf)?)
+}
+
+fn string(value: &Value, key: &str) -> Result<String, Box<dyn Error>> {
+    match value[key].as_str() {
+        Some(x) => Ok(x.to_string()),
+        None => Err(format!("album archive image is missing '{}'", key)
+            .into()),
+    }
+}
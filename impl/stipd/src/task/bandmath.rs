@@ -0,0 +1,221 @@
+use crate::{Image, StFile, DERIVED_SOURCE_PREFIX};
+use crate::album::Album;
+use crate::task::Task;
+use crate::task::logger::LogContext;
+
+use std::error::Error;
+use std::str::FromStr;
+use std::sync::{Arc, RwLock};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BandIndex {
+    Evi,
+    NormalizedDifference,
+    Ndvi,
+    Ndwi,
+}
+
+impl BandIndex {
+    pub fn band_count(&self) -> usize {
+        match self {
+            BandIndex::Evi => 3,
+            _ => 2,
+        }
+    }
+
+    // band order of the 10m sentinel-2 subdataset (B4, B3, B2, B8)
+    pub fn default_bands(&self) -> Option<Vec<usize>> {
+        match self {
+            BandIndex::Evi => Some(vec![4, 1, 3]),
+            BandIndex::NormalizedDifference => None,
+            BandIndex::Ndvi => Some(vec![4, 1]),
+            BandIndex::Ndwi => Some(vec![2, 4]),
+        }
+    }
+
+    pub fn compute(&self, values: &[f64], scale: f64) -> f64 {
+        let value = match self {
+            BandIndex::Evi => {
+                // evi constants assume surface reflectance in [0, 1]
+                let (nir, red, blue) = (values[0] / scale,
+                    values[1] / scale, values[2] / scale);
+                2.5 * (nir - red) / (nir + 6.0 * red - 7.5 * blue + 1.0)
+            },
+            _ => (values[0] - values[1]) / (values[0] + values[1]),
+        };
+
+        match value.is_finite() {
+            true => value,
+            false => std::f64::NAN,
+        }
+    }
+
+    pub fn source(&self, bands: &[usize]) -> String {
+        match self {
+            BandIndex::Evi => format!("{}evi", DERIVED_SOURCE_PREFIX),
+            BandIndex::NormalizedDifference => format!("{}nd{}{}",
+                DERIVED_SOURCE_PREFIX, bands[0], bands[1]),
+            BandIndex::Ndvi => format!("{}ndvi", DERIVED_SOURCE_PREFIX),
+            BandIndex::Ndwi => format!("{}ndwi", DERIVED_SOURCE_PREFIX),
+        }
+    }
+}
+
+impl FromStr for BandIndex {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "evi" => Ok(BandIndex::Evi),
+            "nd" => Ok(BandIndex::NormalizedDifference),
+            "ndvi" => Ok(BandIndex::Ndvi),
+            "ndwi" => Ok(BandIndex::Ndwi),
+            x => Err(format!("unsupported band index '{}'", x)),
+        }
+    }
+}
+
+pub struct BandMathTask {
+    album: Arc<RwLock<Album>>,
+    bands: Vec<usize>,
+    end_timestamp: Option<i64>,
+    geocode: Option<String>,
+    index: BandIndex,
+    max_cloud_coverage: Option<f64>,
+    min_pixel_coverage: Option<f64>,
+    platform: Option<String>,
+    recurse: bool,
+    scale: f64,
+    source: Option<String>,
+    start_timestamp: Option<i64>,
+    subdataset: u8,
+}
+
+impl BandMathTask {
+    pub fn new(album: Arc<RwLock<Album>>, bands: Vec<usize>,
+            end_timestamp: Option<i64>, geocode: Option<String>,
+            index: BandIndex, max_cloud_coverage: Option<f64>,
+            min_pixel_coverage: Option<f64>, platform: Option<String>,
+            recurse: bool, scale: f64, source: Option<String>,
+            start_timestamp: Option<i64>, subdataset: u8)
+            -> Result<BandMathTask, Box<dyn Error>> {
+        {
+            let album = album.read().unwrap();
+            info!("initailizing band math task [album={}, bands={:?}, end_timestamp={:?}, geocode={:?}, index={:?}, max_cloud_coverage={:?}, min_pixel_coverage={:?}, platform={:?}, recurse={}, scale={}, source={:?}, start_timestamp={:?}, subdataset={}]",
+                album.get_id(), bands, end_timestamp, geocode, index,
+                max_cloud_coverage, min_pixel_coverage, platform,
+                recurse, scale, source, start_timestamp, subdataset);
+        }
+
+        // fall back to the index defaults when no bands are provided
+        let bands = match (bands.is_empty(), index.default_bands()) {
+            (true, Some(default_bands)) => default_bands,
+            _ => bands,
+        };
+
+        if bands.len() != index.band_count() {
+            return Err(format!("{:?} requires {} bands, found {}",
+                index, index.band_count(), bands.len()).into());
+        }
+
+        if bands.iter().any(|x| *x == 0) {
+            return Err("band numbers start at 1".into());
+        }
+
+        Ok(BandMathTask {
+            album: album,
+            bands: bands,
+            end_timestamp: end_timestamp,
+            geocode: geocode,
+            index: index,
+            max_cloud_coverage: max_cloud_coverage,
+            min_pixel_coverage: min_pixel_coverage,
+            platform: platform,
+            recurse: recurse,
+            scale: scale,
+            source: source,
+            start_timestamp: start_timestamp,
+            subdataset: subdataset,
+        })
+    }
+}
+
+#[tonic::async_trait]
+impl Task<(Image, StFile)> for BandMathTask {
+    fn process(&self, record: &(Image, StFile))
+            -> Result<(), Box<dyn Error>> {
+        let (image, file) = record;
+
+        // read input bands
+        let dataset = crate::compaction::open(std::path::Path::new(&file.0))?;
+        let mut bands = Vec::new();
+        for band in self.bands.iter() {
+            bands.push(crate::raster::read_band(&dataset, *band)?);
+        }
+
+        // compute index for each pixel with data in every band
+        let (x, y) = dataset.raster_size();
+        let mut buf = vec![0f64; bands.len()];
+        let mut values = vec![std::f64::NAN; x * y];
+        for i in 0..values.len() {
+            if !bands.iter().all(|x| x.is_valid(i)) {
+                continue;
+            }
+
+            for (j, band) in bands.iter().enumerate() {
+                buf[j] = band.values[i];
+            }
+
+            values[i] = self.index.compute(&buf, self.scale);
+        }
+
+        let pixel_coverage = crate::raster::coverage(&values);
+        if pixel_coverage == 0f64 {
+            return Ok(());
+        }
+
+        // write derived image alongside the source image
+        let mut derived = crate::raster::create_dataset(&dataset, 1)?;
+        crate::raster::write_band(&derived, 1, &mut values)?;
+
+        let mut album = self.album.write().unwrap();
+        album.write(&mut derived, &image.1, pixel_coverage, &image.2,
+            &self.index.source(&self.bands), file.2, &image.4, image.5)
+    }
+
+    fn estimate(&self, _record: &(Image, StFile))
+            -> Result<Option<Vec<String>>, Box<dyn Error>> {
+        // each record writes at most one derived image locally
+        Ok(Some(vec!["local".to_string()]))
+    }
+
+    fn log_context(&self, record: &(Image, StFile)) -> LogContext {
+        LogContext::new(&self.album, &(record.0).1)
+    }
+
+    async fn records(&self)
+            -> Result<Vec<(Image, StFile)>, Box<dyn Error>> {
+        // search for images using Album
+        let album = self.album.read().unwrap();
+        let images = album.list(&self.end_timestamp, &self.geocode,
+            &self.max_cloud_coverage, &self.min_pixel_coverage,
+            &self.platform, self.recurse, &self.source,
+            &self.start_timestamp)?;
+
+        // derived images are never inputs to band math
+        let mut records = Vec::new();
+        for (image, files) in images.into_iter() {
+            if image.3.starts_with(DERIVED_SOURCE_PREFIX) {
+                continue;
+            }
+
+            for file in files.into_iter() {
+                if file.2 == self.subdataset {
+                    records.push((image.clone(), file));
+                }
+            }
+        }
+
+        Ok(records)
+    }
+}
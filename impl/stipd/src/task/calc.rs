@@ -0,0 +1,151 @@
+use crate::{Image, StFile, DERIVED_SOURCE_PREFIX};
+use crate::album::Album;
+use crate::expression::Expression;
+use crate::task::Task;
+use crate::task::logger::LogContext;
+
+use std::error::Error;
+use std::sync::{Arc, RwLock};
+
+pub struct CalcTask {
+    album: Arc<RwLock<Album>>,
+    end_timestamp: Option<i64>,
+    expression: Expression,
+    geocode: Option<String>,
+    max_cloud_coverage: Option<f64>,
+    min_pixel_coverage: Option<f64>,
+    name: String,
+    platform: Option<String>,
+    recurse: bool,
+    source: Option<String>,
+    start_timestamp: Option<i64>,
+    subdataset: u8,
+}
+
+impl CalcTask {
+    pub fn new(album: Arc<RwLock<Album>>, end_timestamp: Option<i64>,
+            expression: Expression, geocode: Option<String>,
+            max_cloud_coverage: Option<f64>,
+            min_pixel_coverage: Option<f64>, name: String,
+            platform: Option<String>, recurse: bool,
+            source: Option<String>, start_timestamp: Option<i64>,
+            subdataset: u8) -> Result<CalcTask, Box<dyn Error>> {
+        {
+            let album = album.read().unwrap();
+            info!("initailizing calc task [album={}, end_timestamp={:?}, expression={:?}, geocode={:?}, max_cloud_coverage={:?}, min_pixel_coverage={:?}, name={}, platform={:?}, recurse={}, source={:?}, start_timestamp={:?}, subdataset={}]",
+                album.get_id(), end_timestamp, expression, geocode,
+                max_cloud_coverage, min_pixel_coverage, name, platform,
+                recurse, source, start_timestamp, subdataset);
+        }
+
+        // names become source directories
+        if name.is_empty() || !name.chars().all(|x| x.is_ascii_lowercase()
+                || x.is_ascii_digit() || x == '-' || x == '_') {
+            return Err(format!("invalid output name '{}', use lowercase letters, digits, '-', and '_'", name).into());
+        }
+
+        if expression.bands().is_empty() {
+            return Err("expression references no bands".into());
+        }
+
+        Ok(CalcTask {
+            album: album,
+            end_timestamp: end_timestamp,
+            expression: expression,
+            geocode: geocode,
+            max_cloud_coverage: max_cloud_coverage,
+            min_pixel_coverage: min_pixel_coverage,
+            name: name,
+            platform: platform,
+            recurse: recurse,
+            source: source,
+            start_timestamp: start_timestamp,
+            subdataset: subdataset,
+        })
+    }
+}
+
+#[tonic::async_trait]
+impl Task<(Image, StFile)> for CalcTask {
+    fn process(&self, record: &(Image, StFile))
+            -> Result<(), Box<dyn Error>> {
+        let (image, file) = record;
+
+        // read bands referenced by the expression
+        let dataset = crate::compaction::open(std::path::Path::new(&file.0))?;
+        let indices = self.expression.bands();
+        let mut bands = Vec::new();
+        for index in indices.iter() {
+            bands.push(crate::raster::read_band(&dataset, *index)?);
+        }
+
+        // evaluate expression for each pixel with data in every band
+        let (x, y) = dataset.raster_size();
+        let mut buf = vec![0f64; *indices.last().unwrap()];
+        let mut values = vec![std::f64::NAN; x * y];
+        for i in 0..values.len() {
+            if !bands.iter().all(|x| x.is_valid(i)) {
+                continue;
+            }
+
+            for (index, band) in indices.iter().zip(bands.iter()) {
+                buf[index - 1] = band.values[i];
+            }
+
+            let value = self.expression.evaluate(&buf);
+            if value.is_finite() {
+                values[i] = value;
+            }
+        }
+
+        let pixel_coverage = crate::raster::coverage(&values);
+        if pixel_coverage == 0f64 {
+            return Ok(());
+        }
+
+        // write derived image alongside the source image
+        let mut derived = crate::raster::create_dataset(&dataset, 1)?;
+        crate::raster::write_band(&derived, 1, &mut values)?;
+
+        let source = format!("{}{}", DERIVED_SOURCE_PREFIX, self.name);
+        let mut album = self.album.write().unwrap();
+        album.write(&mut derived, &image.1, pixel_coverage, &image.2,
+            &source, file.2, &image.4, image.5)
+    }
+
+    fn estimate(&self, _record: &(Image, StFile))
+            -> Result<Option<Vec<String>>, Box<dyn Error>> {
+        // each record writes at most one derived image locally
+        Ok(Some(vec!["local".to_string()]))
+    }
+
+    fn log_context(&self, record: &(Image, StFile)) -> LogContext {
+        LogContext::new(&self.album, &(record.0).1)
+    }
+
+    async fn records(&self)
+            -> Result<Vec<(Image, StFile)>, Box<dyn Error>> {
+        // search for images using Album
+        let album = self.album.read().unwrap();
+        let images = album.list(&self.end_timestamp, &self.geocode,
+            &self.max_cloud_coverage, &self.min_pixel_coverage,
+            &self.platform, self.recurse, &self.source,
+            &self.start_timestamp)?;
+
+        // derived images are never inputs to calculations
+        let mut records = Vec::new();
+        for (image, files) in images.into_iter() {
+            if image.3.starts_with(DERIVED_SOURCE_PREFIX) {
+                continue;
+            }
+
+            for file in files.into_iter() {
+                if file.2 == self.subdataset {
+                    records.push((image.clone(), file));
+                }
+            }
+        }
+
+        Ok(records)
+    }
+}
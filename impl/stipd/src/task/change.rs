@@ -0,0 +1,248 @@
+use gdal::Dataset;
+
+use crate::{Image, StFile, DERIVED_SOURCE_PREFIX};
+use crate::album::Album;
+use crate::raster::Band;
+use crate::task::Task;
+use crate::task::logger::LogContext;
+use crate::task::composite::CompositeMethod;
+
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::{Arc, RwLock};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ChangeMethod {
+    Difference,
+    Normalized,
+}
+
+impl ChangeMethod {
+    pub fn compute(&self, before: f64, after: f64) -> f64 {
+        let value = match self {
+            ChangeMethod::Difference => after - before,
+            ChangeMethod::Normalized => (after - before) / (after + before),
+        };
+
+        match value.is_finite() {
+            true => value,
+            false => std::f64::NAN,
+        }
+    }
+
+    pub fn source(&self) -> String {
+        match self {
+            ChangeMethod::Difference =>
+                format!("{}difference", DERIVED_SOURCE_PREFIX),
+            ChangeMethod::Normalized =>
+                format!("{}normalized-change", DERIVED_SOURCE_PREFIX),
+        }
+    }
+}
+
+impl FromStr for ChangeMethod {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "difference" => Ok(ChangeMethod::Difference),
+            "normalized" => Ok(ChangeMethod::Normalized),
+            x => Err(format!("unsupported change method '{}'", x)),
+        }
+    }
+}
+
+// observations of one geocode within the before and after windows
+type ChangeRecord = (Vec<(Image, StFile)>, Vec<(Image, StFile)>);
+
+pub struct ChangeTask {
+    after: (i64, i64),
+    album: Arc<RwLock<Album>>,
+    bands: Vec<usize>,
+    before: (i64, i64),
+    geocode: Option<String>,
+    max_cloud_coverage: Option<f64>,
+    method: ChangeMethod,
+    platform: Option<String>,
+    recurse: bool,
+    source: Option<String>,
+}
+
+impl ChangeTask {
+    pub fn new(after: (i64, i64), album: Arc<RwLock<Album>>,
+            bands: Vec<usize>, before: (i64, i64), geocode: Option<String>,
+            max_cloud_coverage: Option<f64>, method: ChangeMethod,
+            platform: Option<String>, recurse: bool, source: Option<String>)
+            -> Result<ChangeTask, Box<dyn Error>> {
+        {
+            let album = album.read().unwrap();
+            info!("initailizing change task [after={:?}, album={}, bands={:?}, before={:?}, geocode={:?}, max_cloud_coverage={:?}, method={:?}, platform={:?}, recurse={}, source={:?}]",
+                after, album.get_id(), bands, before, geocode,
+                max_cloud_coverage, method, platform, recurse, source);
+        }
+
+        if before.0 > before.1 || after.0 > after.1 {
+            return Err("window start must not follow window end".into());
+        }
+
+        if before.1 >= after.0 {
+            return Err("before window must end prior to after window".into());
+        }
+
+        if bands.iter().any(|x| *x == 0) {
+            return Err("band numbers start at 1".into());
+        }
+
+        Ok(ChangeTask {
+            after: after,
+            album: album,
+            bands: bands,
+            before: before,
+            geocode: geocode,
+            max_cloud_coverage: max_cloud_coverage,
+            method: method,
+            platform: platform,
+            recurse: recurse,
+            source: source,
+        })
+    }
+}
+
+#[tonic::async_trait]
+impl Task<ChangeRecord> for ChangeTask {
+    fn process(&self, record: &ChangeRecord)
+            -> Result<(), Box<dyn Error>> {
+        let (before, after) = record;
+
+        // average each window into a single observation
+        let (before_bands, dataset) = read_window(before)?;
+        let (after_bands, _) = read_window(after)?;
+        if before_bands.len() != after_bands.len()
+                || before_bands.iter().zip(after_bands.iter())
+                    .any(|(a, b)| a.len() != b.len()) {
+            return Err(format!("image '{}' is not aligned with '{}'",
+                (after[0].1).0, (before[0].1).0).into());
+        }
+
+        let bands = match self.bands.is_empty() {
+            true => (1..before_bands.len() + 1).collect(),
+            false => self.bands.clone(),
+        };
+
+        // compute change for each requested band
+        let mut change = Vec::new();
+        for band in bands.iter() {
+            if *band > before_bands.len() {
+                return Err(format!("band {} does not exist", band).into());
+            }
+
+            let (before_values, after_values) =
+                (&before_bands[band - 1], &after_bands[band - 1]);
+            change.push(before_values.iter().zip(after_values.iter())
+                .map(|(a, b)| self.method.compute(*a, *b)).collect());
+        }
+
+        let pixel_coverage = crate::raster::coverage(&change[0]);
+        if pixel_coverage == 0f64 {
+            return Ok(());
+        }
+
+        // write change image alongside the source images
+        let mut derived =
+            crate::raster::create_dataset(&dataset, change.len())?;
+        for (i, values) in change.iter_mut().enumerate() {
+            crate::raster::write_band(&derived, i + 1, values)?;
+        }
+
+        let (image, file) = &after[0];
+        let tile = format!("change-{}-{}", self.before.0, self.after.0);
+        let mut album = self.album.write().unwrap();
+        album.write(&mut derived, &image.1, pixel_coverage, &image.2,
+            &self.method.source(), file.2, &tile, self.after.0)
+    }
+
+    fn estimate(&self, _record: &ChangeRecord)
+            -> Result<Option<Vec<String>>, Box<dyn Error>> {
+        // each pair writes at most one change image locally
+        Ok(Some(vec!["local".to_string()]))
+    }
+
+    fn log_context(&self, record: &ChangeRecord) -> LogContext {
+        match record.0.first() {
+            Some((image, _)) => LogContext::new(&self.album, &image.1),
+            None => LogContext::default(),
+        }
+    }
+
+    async fn records(&self) -> Result<Vec<ChangeRecord>, Box<dyn Error>> {
+        // search for images spanning both windows using Album
+        let images = {
+            let album = self.album.read().unwrap();
+            album.list(&Some(self.after.1), &self.geocode,
+                &self.max_cloud_coverage, &None, &self.platform,
+                self.recurse, &self.source, &Some(self.before.0))?
+        };
+
+        // pair observations by geocode, platform, source, and subdataset
+        let mut groups: BTreeMap<(String, String, String, u8),
+            ChangeRecord> = BTreeMap::new();
+        for (image, files) in images.into_iter() {
+            // change images are never inputs to change detection
+            if image.3 == ChangeMethod::Difference.source()
+                    || image.3 == ChangeMethod::Normalized.source() {
+                continue;
+            }
+
+            let before = image.5 <= self.before.1;
+            let after = image.5 >= self.after.0;
+            if !before && !after {
+                continue;
+            }
+
+            for file in files.into_iter() {
+                let group = groups.entry((image.1.clone(), image.2.clone(),
+                        image.3.clone(), file.2))
+                    .or_insert((Vec::new(), Vec::new()));
+                match before {
+                    true => group.0.push((image.clone(), file)),
+                    false => group.1.push((image.clone(), file)),
+                }
+            }
+        }
+
+        Ok(groups.into_iter().map(|(_, x)| x)
+            .filter(|(before, after)| !before.is_empty() && !after.is_empty())
+            .collect())
+    }
+}
+
+fn read_window(observations: &[(Image, StFile)])
+        -> Result<(Vec<Vec<f64>>, Dataset), Box<dyn Error>> {
+    let mut datasets = Vec::new();
+    let mut bands: Vec<Vec<Band>> = Vec::new();
+    for (_, file) in observations.iter() {
+        let dataset = crate::compaction::open(&PathBuf::from(&file.0))?;
+        if let Some(first) = datasets.first() {
+            if dataset.raster_size() != first.raster_size() {
+                return Err(format!("image '{}' is not aligned with '{}'",
+                    file.0, (observations[0].1).0).into());
+            }
+        }
+
+        let mut observation = Vec::new();
+        for i in 0..crate::raster::band_count(&dataset) {
+            observation.push(crate::raster::read_band(&dataset, i + 1)?);
+        }
+
+        bands.push(observation);
+        datasets.push(dataset);
+    }
+
+    let band_count = bands.iter().map(|x| x.len()).min().unwrap_or(0);
+    let (x, y) = datasets[0].raster_size();
+    let values = crate::task::composite::reduce(&bands,
+        band_count, CompositeMethod::Mean, x * y);
+    Ok((values, datasets.remove(0)))
+}
@@ -1,4 +1,3 @@
-use gdal::Dataset;
 use protobuf::{ImageListRequest, Filter, ImageManagementClient};
 use swarm::prelude::Dht;
 use tonic::Request;
@@ -6,6 +5,7 @@ use tonic::Request;
 use crate::{Image, StFile, RAW_SOURCE, SPLIT_SOURCE};
 use crate::album::Album;
 use crate::task::Task;
+use crate::task::logger::LogContext;
 
 use std::collections::{HashMap, HashSet};
 use std::error::Error;
@@ -87,13 +87,13 @@ impl Task<(Image, Vec<StFile>, HashSet<String>)> for CoalesceTask {
                         &image.2, &image.3, file.2, &image.4)?
                 };
 
-                if !path.exists() {
+                if !crate::compaction::exists(&path) {
                     return Err(format!("image path '{}' does not exist",
                         path.to_string_lossy()).into());
                 }
 
                 // open image
-                let dataset = Dataset::open(&path)?;
+                let dataset = crate::compaction::open(&path)?;
 
                 // compute geohash window boundaries for dataset
                 let epsg_code = geocode.get_epsg_code();
@@ -134,20 +134,20 @@ impl Task<(Image, Vec<StFile>, HashSet<String>)> for CoalesceTask {
                     }
 
                     // lookup geocode in dht
-                    let addr = match crate::task::dht_lookup(
+                    let addrs = match crate::task::dht_lookup(
                             &self.dht, dht_key_length, &split_geocode) {
-                        Ok(addr) => addr,
+                        Ok(addrs) => addrs,
                         Err(e) => {
                             warn!("{}", e);
                             continue;
                         },
                     };
 
-                    // send image to new host
-                    if let Err(e) = crate::transfer::send_image(&addr,
+                    // send image to replica hosts
+                    if let Err(e) = crate::transfer::send_image(&addrs,
                             &album_id, &split_dataset, &split_geocode, file.1,
                             &image.2, SPLIT_SOURCE, file.2, &image.4, image.5) {
-                        warn!("failed to write image to node {}: {}", addr, e);
+                        warn!("{}", e);
                     }
                 }
             }
@@ -156,6 +156,10 @@ impl Task<(Image, Vec<StFile>, HashSet<String>)> for CoalesceTask {
         Ok(())
     }
 
+    fn log_context(&self, record: &(Image, Vec<StFile>, HashSet<String>)) -> LogContext {
+        LogContext::new(&self.album, &(record.0).1)
+    }
+
     async fn records(&self) -> Result<Vec<(Image, Vec<StFile>, HashSet<String>)>,
             Box<dyn Error>> {
         // retrieve album metadata
@@ -178,6 +182,7 @@ impl Task<(Image, Vec<StFile>, HashSet<String>)> for CoalesceTask {
             geocode: self.geocode.clone(),
             max_cloud_coverage: self.max_cloud_coverage,
             min_pixel_coverage: self.min_pixel_coverage,
+            name: None,
             platform: self.platform.clone(),
             recurse: self.recurse,
             source: self.source.clone(),
@@ -187,19 +192,27 @@ impl Task<(Image, Vec<StFile>, HashSet<String>)> for CoalesceTask {
         // initialize ImageListRequest
         let request = ImageListRequest {
             album: album_id,
+            albums: Vec::new(),
+            all_albums: None,
             filter: filter,
+            geometry: None,
+            max_latitude: None,
+            max_longitude: None,
+            min_latitude: None,
+            min_longitude: None,
+            statistics: None,
         };
 
         // iterate over dht nodes
         let mut split_records = HashMap::new();
         for node in self.dht.nodes() {
             // get rpc address
-            let addr = format!("http://{}:{}", node.get_ip_address(),
+            let addr = format!("{}:{}", node.get_ip_address(),
                 node.get_metadata("rpc_port").unwrap());
 
             // open ImageManagementClient
-            let mut client = match ImageManagementClient::connect(
-                    addr.clone()).await {
+            let mut client = match crate::tls::channel(
+                    &addr).await.map(ImageManagementClient::new) {
                 Ok(client) => client,
                 Err(e) => return Err(format!(
                     "connection to {} failed: {}", addr, e).into()),
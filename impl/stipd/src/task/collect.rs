@@ -0,0 +1,208 @@
+use protobuf::{Filter, ImageListRequest, ImageManagementClient};
+use swarm::prelude::Dht;
+use tonic::Request;
+
+use crate::{Image, StFile, RAW_SOURCE, SPLIT_SOURCE};
+use crate::album::{Album, AlbumManager};
+use crate::task::{RetryPolicy, Task, TaskManager};
+use crate::task::logger::LogContext;
+
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::path::Path;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const SECONDS_PER_DAY: i64 = 86400;
+
+pub struct CollectTask {
+    album: Arc<RwLock<Album>>,
+    dht: Arc<Dht>,
+}
+
+impl CollectTask {
+    pub fn new(album: Arc<RwLock<Album>>, dht: Arc<Dht>)
+            -> Result<CollectTask, Box<dyn Error>> {
+        {
+            let album = album.read().unwrap();
+            info!("initailizing collect task [album={}, retention={:?}]",
+                album.get_id(), album.get_retention());
+
+            // fail before listing images if the album is under a hold
+            album.assert_modifiable()?;
+        }
+
+        Ok(CollectTask {
+            album: album,
+            dht: dht,
+        })
+    }
+
+    // split tile geocodes across the cluster by platform and timestamp
+    async fn split_geocodes(&self, end_timestamp: i64)
+            -> Result<HashMap<(String, i64), Vec<String>>, Box<dyn Error>> {
+        let album_id = self.album.read().unwrap().get_id().to_string();
+        let list_request = ImageListRequest {
+            album: album_id,
+            albums: Vec::new(),
+            all_albums: None,
+            filter: Filter {
+                end_timestamp: Some(end_timestamp),
+                geocode: None,
+                max_cloud_coverage: None,
+                min_pixel_coverage: None,
+                name: None,
+                platform: None,
+                recurse: false,
+                source: Some(SPLIT_SOURCE.to_string()),
+                start_timestamp: None,
+            },
+            geometry: None,
+            max_latitude: None,
+            max_longitude: None,
+            min_latitude: None,
+            min_longitude: None,
+            statistics: None,
+        };
+
+        // split tiles are distributed away from their raw images
+        let mut geocodes = HashMap::new();
+        for node in self.dht.nodes() {
+            let addr = format!("{}:{}", node.get_ip_address(),
+                node.get_metadata("rpc_port").unwrap());
+
+            let mut client = crate::tls::channel(&addr).await
+                .map(ImageManagementClient::new)?;
+            let mut stream = client.list(Request::new(
+                list_request.clone())).await?.into_inner();
+            while let Some(image) = stream.message().await? {
+                geocodes.entry((image.platform, image.timestamp))
+                    .or_insert(Vec::new()).push(image.geocode);
+            }
+        }
+
+        Ok(geocodes)
+    }
+}
+
+#[tonic::async_trait]
+impl Task<(Image, StFile)> for CollectTask {
+    fn process(&self, record: &(Image, StFile))
+            -> Result<(), Box<dyn Error>> {
+        let (image, file) = record;
+
+        let mut album = self.album.write().unwrap();
+        album.remove(&image.1, &image.2, &image.3, file.2, &image.4)
+    }
+
+    fn log_context(&self, record: &(Image, StFile)) -> LogContext {
+        LogContext::new(&self.album, &(record.0).1)
+    }
+
+    async fn records(&self)
+            -> Result<Vec<(Image, StFile)>, Box<dyn Error>> {
+        // search for images using Album
+        let (images, retention) = {
+            let album = self.album.read().unwrap();
+            (album.list(&None, &None, &None, &None, &None,
+                false, &None, &None)?, album.get_retention().clone())
+        };
+
+        let mut records = Vec::new();
+        let mut collected = HashSet::new();
+
+        // raw images expire once they have been split
+        if let Some(raw_ttl_days) = retention.raw_ttl_days {
+            let now = SystemTime::now().duration_since(UNIX_EPOCH)?;
+            let expiration = now.as_secs() as i64
+                - raw_ttl_days as i64 * SECONDS_PER_DAY;
+            let split_geocodes = self.split_geocodes(expiration).await?;
+
+            for (image, files) in images.iter() {
+                if image.3 != RAW_SOURCE || image.5 >= expiration {
+                    continue;
+                }
+
+                let is_split = split_geocodes
+                    .get(&(image.2.clone(), image.5))
+                    .map(|x| x.iter().any(|y| y.starts_with(&image.1)))
+                    .unwrap_or(false);
+                if !is_split {
+                    continue;
+                }
+
+                for file in files.iter() {
+                    collected.insert(file.0.clone());
+                    records.push((image.clone(), file.clone()));
+                }
+            }
+        }
+
+        // remove the oldest images beyond the byte cap
+        if let Some(max_bytes) = retention.max_bytes {
+            let mut files = Vec::new();
+            let mut total_bytes = 0;
+            for (image, image_files) in images.iter() {
+                for file in image_files.iter() {
+                    if collected.contains(&file.0) {
+                        continue;
+                    }
+
+                    let bytes = crate::compaction::len(Path::new(&file.0))
+                        .unwrap_or(0);
+                    total_bytes += bytes;
+                    files.push((image, file, bytes));
+                }
+            }
+
+            files.sort_by_key(|(image, _, _)| image.5);
+            for (image, file, bytes) in files {
+                if total_bytes <= max_bytes {
+                    break;
+                }
+
+                total_bytes -= bytes;
+                records.push((image.clone(), file.clone()));
+            }
+        }
+
+        Ok(records)
+    }
+}
+
+// periodically start collect tasks on albums with retention rules
+pub fn watch(album_manager: Arc<RwLock<AlbumManager>>, dht: Arc<Dht>,
+        task_manager: Arc<RwLock<TaskManager>>, interval: Duration,
+        thread_count: u8) {
+    std::thread::spawn(move || {
+        loop {
+            std::thread::sleep(interval);
+
+            // collect open albums which are not under a hold
+            let albums: Vec<Arc<RwLock<Album>>> = {
+                let album_manager = album_manager.read().unwrap();
+                album_manager.iter().map(|(_, x)| x.clone())
+                    .filter(|x| {
+                        let album = x.read().unwrap();
+                        album.get_index().is_some()
+                            && !album.get_retention().is_empty()
+                            && album.assert_modifiable().is_ok()
+                    }).collect()
+            };
+
+            for album in albums {
+                let id = album.read().unwrap().get_id().to_string();
+                let result = CollectTask::new(album, dht.clone())
+                    .and_then(|task| Arc::new(task).start(thread_count,
+                        None, RetryPolicy::default()))
+                    .and_then(|task_handle| task_manager.write().unwrap()
+                        .register(task_handle, None, 0));
+
+                if let Err(e) = result {
+                    warn!("failed to start album '{}' collect task: {}",
+                        id, e);
+                }
+            }
+        }
+    });
+}
@@ -0,0 +1,110 @@
+use crate::album::{Album, AlbumManager};
+use crate::task::{RetryPolicy, Task, TaskManager};
+
+use std::error::Error;
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+// tiles packed while holding the album, which is released between batches
+//   so writes and removals are not stalled for an entire directory
+const COMPACT_BATCH_SIZE: usize = 256;
+
+pub struct CompactTask {
+    album: Arc<RwLock<Album>>,
+    min_age: Duration,
+}
+
+impl CompactTask {
+    pub fn new(album: Arc<RwLock<Album>>, min_age: Duration) -> CompactTask {
+        {
+            let album = album.read().unwrap();
+            info!("initailizing compact task [album={}, min_age={:?}]",
+                album.get_id(), min_age);
+        }
+
+        CompactTask {
+            album: album,
+            min_age: min_age,
+        }
+    }
+}
+
+#[tonic::async_trait]
+impl Task<PathBuf> for CompactTask {
+    fn process(&self, record: &PathBuf) -> Result<(), Box<dyn Error>> {
+        let (mut total_count, mut total_bytes) = (0, 0);
+        loop {
+            // hold the album so images are not written or removed mid-pack
+            let (count, bytes) = {
+                let _album = self.album.read().unwrap();
+                crate::compaction::compact(record,
+                    self.min_age, COMPACT_BATCH_SIZE)?
+            };
+
+            total_count += count;
+            total_bytes += bytes;
+            if count < COMPACT_BATCH_SIZE {
+                break;
+            }
+        }
+
+        if total_count != 0 {
+            debug!("packed {} images ({} bytes) in {:?}",
+                total_count, total_bytes, record);
+        }
+
+        Ok(())
+    }
+
+    async fn records(&self) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+        let shards = self.album.read().unwrap().get_shards()?;
+
+        // compact each source directory independently
+        let mut directories = Vec::new();
+        for shard in shards {
+            for entry in std::fs::read_dir(&shard)? {
+                let path = entry?.path();
+                if path.is_dir() {
+                    directories.push(path);
+                }
+            }
+        }
+
+        Ok(directories)
+    }
+}
+
+// periodically start compact tasks on open albums
+pub fn watch(album_manager: Arc<RwLock<AlbumManager>>,
+        task_manager: Arc<RwLock<TaskManager>>, interval: Duration,
+        min_age: Duration, thread_count: u8) {
+    std::thread::spawn(move || {
+        loop {
+            std::thread::sleep(interval);
+
+            // compact open writable albums
+            let albums: Vec<Arc<RwLock<Album>>> = {
+                let album_manager = album_manager.read().unwrap();
+                album_manager.iter().map(|(_, x)| x.clone())
+                    .filter(|x| {
+                        let album = x.read().unwrap();
+                        album.get_index().is_some() && !album.is_frozen()
+                    }).collect()
+            };
+
+            for album in albums {
+                let id = album.read().unwrap().get_id().to_string();
+                let result = Arc::new(CompactTask::new(album, min_age))
+                    .start(thread_count, None, RetryPolicy::default())
+                    .and_then(|task_handle| task_manager.write().unwrap()
+                        .register(task_handle, None, 0));
+
+                if let Err(e) = result {
+                    warn!("failed to start album '{}' compact task: {}",
+                        id, e);
+                }
+            }
+        }
+    });
+}
@@ -0,0 +1,306 @@
+use crate::{Image, StFile, COMPOSITE_SOURCE_PREFIX, DERIVED_SOURCE_PREFIX};
+use crate::album::Album;
+use crate::raster::Band;
+use crate::task::Task;
+use crate::task::logger::LogContext;
+use crate::task::bandmath::BandIndex;
+
+use std::cmp::Ordering as CmpOrdering;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::{Arc, RwLock};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CompositeMethod {
+    MaxNdvi,
+    Mean,
+    Median,
+}
+
+impl CompositeMethod {
+    pub fn source(&self) -> String {
+        match self {
+            CompositeMethod::MaxNdvi =>
+                format!("{}maxndvi", COMPOSITE_SOURCE_PREFIX),
+            CompositeMethod::Mean =>
+                format!("{}mean", COMPOSITE_SOURCE_PREFIX),
+            CompositeMethod::Median =>
+                format!("{}median", COMPOSITE_SOURCE_PREFIX),
+        }
+    }
+}
+
+impl FromStr for CompositeMethod {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "maxndvi" => Ok(CompositeMethod::MaxNdvi),
+            "mean" => Ok(CompositeMethod::Mean),
+            "median" => Ok(CompositeMethod::Median),
+            x => Err(format!("unsupported composite method '{}'", x)),
+        }
+    }
+}
+
+pub struct CompositeTask {
+    album: Arc<RwLock<Album>>,
+    bands: Vec<usize>,
+    end_timestamp: Option<i64>,
+    geocode: Option<String>,
+    max_cloud_coverage: Option<f64>,
+    method: CompositeMethod,
+    platform: String,
+    recurse: bool,
+    source: Option<String>,
+    start_timestamp: Option<i64>,
+    window_seconds: Option<i64>,
+}
+
+impl CompositeTask {
+    pub fn new(album: Arc<RwLock<Album>>, bands: Vec<usize>,
+            end_timestamp: Option<i64>, geocode: Option<String>,
+            max_cloud_coverage: Option<f64>, method: CompositeMethod,
+            platform: String, recurse: bool, source: Option<String>,
+            start_timestamp: Option<i64>, window_seconds: Option<i64>)
+            -> Result<CompositeTask, Box<dyn Error>> {
+        {
+            let album = album.read().unwrap();
+            info!("initailizing composite task [album={}, bands={:?}, end_timestamp={:?}, geocode={:?}, max_cloud_coverage={:?}, method={:?}, platform={}, recurse={}, source={:?}, start_timestamp={:?}, window_seconds={:?}]",
+                album.get_id(), bands, end_timestamp, geocode,
+                max_cloud_coverage, method, platform, recurse, source,
+                start_timestamp, window_seconds);
+        }
+
+        // max ndvi selects observations using the nir and red bands
+        let bands = match (bands.is_empty(), method) {
+            (true, CompositeMethod::MaxNdvi) =>
+                BandIndex::Ndvi.default_bands().unwrap(),
+            _ => bands,
+        };
+
+        if method == CompositeMethod::MaxNdvi && bands.len() != 2 {
+            return Err("maxndvi requires nir and red bands".into());
+        }
+
+        if let Some(window_seconds) = window_seconds {
+            if window_seconds <= 0 {
+                return Err("window seconds must be positive".into());
+            }
+        }
+
+        Ok(CompositeTask {
+            album: album,
+            bands: bands,
+            end_timestamp: end_timestamp,
+            geocode: geocode,
+            max_cloud_coverage: max_cloud_coverage,
+            method: method,
+            platform: platform,
+            recurse: recurse,
+            source: source,
+            start_timestamp: start_timestamp,
+            window_seconds: window_seconds,
+        })
+    }
+
+    fn window_start(&self, timestamp: i64) -> i64 {
+        let start = self.start_timestamp.unwrap_or(0);
+        match self.window_seconds {
+            Some(window_seconds) => start + ((timestamp - start)
+                / window_seconds) * window_seconds,
+            None => start,
+        }
+    }
+}
+
+#[tonic::async_trait]
+impl Task<Vec<(Image, StFile)>> for CompositeTask {
+    fn process(&self, record: &Vec<(Image, StFile)>)
+            -> Result<(), Box<dyn Error>> {
+        // read all bands of each observation
+        let mut datasets = Vec::new();
+        let mut observations = Vec::new();
+        for (_, file) in record.iter() {
+            let dataset = crate::compaction::open(&PathBuf::from(&file.0))?;
+            if let Some(first) = datasets.first() {
+                if dataset.raster_size() != first.raster_size() {
+                    return Err(format!("image '{}' is not aligned with '{}'",
+                        file.0, (record[0].1).0).into());
+                }
+            }
+
+            let mut bands = Vec::new();
+            for i in 0..crate::raster::band_count(&dataset) {
+                bands.push(crate::raster::read_band(&dataset, i + 1)?);
+            }
+
+            observations.push(bands);
+            datasets.push(dataset);
+        }
+
+        let band_count = observations.iter().map(|x| x.len())
+            .min().unwrap_or(0);
+        if band_count == 0 {
+            return Ok(());
+        }
+
+        // compute composite bands
+        let (x, y) = datasets[0].raster_size();
+        let mut composite = match self.method {
+            CompositeMethod::MaxNdvi =>
+                max_ndvi(&observations, band_count, &self.bands, x * y)?,
+            _ => reduce(&observations, band_count, self.method, x * y),
+        };
+
+        let pixel_coverage = crate::raster::coverage(&composite[0]);
+        if pixel_coverage == 0f64 {
+            return Ok(());
+        }
+
+        // write composite image locally
+        let mut dataset =
+            crate::raster::create_dataset(&datasets[0], band_count)?;
+        for (i, values) in composite.iter_mut().enumerate() {
+            crate::raster::write_band(&dataset, i + 1, values)?;
+        }
+
+        let (image, file) = &record[0];
+        let timestamp = self.window_start(image.5);
+        let tile = format!("{}-{}", self.method.source(), timestamp);
+
+        let mut album = self.album.write().unwrap();
+        album.write(&mut dataset, &image.1, pixel_coverage, &image.2,
+            &self.method.source(), file.2, &tile, timestamp)
+    }
+
+    fn estimate(&self, _record: &Vec<(Image, StFile)>)
+            -> Result<Option<Vec<String>>, Box<dyn Error>> {
+        // each group writes at most one composite image locally
+        Ok(Some(vec!["local".to_string()]))
+    }
+
+    fn log_context(&self, record: &Vec<(Image, StFile)>) -> LogContext {
+        match record.first() {
+            Some((image, _)) => LogContext::new(&self.album, &image.1),
+            None => LogContext::default(),
+        }
+    }
+
+    async fn records(&self)
+            -> Result<Vec<Vec<(Image, StFile)>>, Box<dyn Error>> {
+        // search for source images using Album
+        let images = {
+            let album = self.album.read().unwrap();
+            album.list(&self.end_timestamp, &self.geocode,
+                &self.max_cloud_coverage, &None,
+                &Some(self.platform.clone()), self.recurse,
+                &self.source, &self.start_timestamp)?
+        };
+
+        // group observations by geocode, subdataset, and window
+        let mut groups: BTreeMap<(String, u8, i64), Vec<(Image, StFile)>>
+            = BTreeMap::new();
+        for (image, files) in images.into_iter() {
+            // composites are built from original observations only
+            if image.3.starts_with(COMPOSITE_SOURCE_PREFIX)
+                    || image.3.starts_with(DERIVED_SOURCE_PREFIX) {
+                continue;
+            }
+
+            let window_start = self.window_start(image.5);
+            for file in files.into_iter() {
+                groups.entry((image.1.clone(), file.2, window_start))
+                    .or_insert(Vec::new()).push((image.clone(), file));
+            }
+        }
+
+        // order observations within each group by timestamp
+        let mut records = Vec::new();
+        for (_, mut group) in groups.into_iter() {
+            group.sort_by(|a, b| match (a.0).5.cmp(&(b.0).5) {
+                CmpOrdering::Equal => (a.0).4.cmp(&(b.0).4),
+                x => x,
+            });
+
+            records.push(group);
+        }
+
+        Ok(records)
+    }
+}
+
+fn max_ndvi(observations: &[Vec<Band>], band_count: usize,
+        bands: &[usize], len: usize)
+        -> Result<Vec<Vec<f64>>, Box<dyn Error>> {
+    if bands.iter().any(|x| *x == 0 || *x > band_count) {
+        return Err(format!("ndvi bands {:?} do not exist", bands).into());
+    }
+
+    let (nir, red) = (bands[0] - 1, bands[1] - 1);
+    let mut composite = vec![vec![std::f64::NAN; len]; band_count];
+    for i in 0..len {
+        // select observation with the greatest ndvi at this pixel
+        let mut best: Option<(usize, f64)> = None;
+        for (j, observation) in observations.iter().enumerate() {
+            if !observation[nir].is_valid(i)
+                    || !observation[red].is_valid(i) {
+                continue;
+            }
+
+            let ndvi = BandIndex::Ndvi.compute(&[observation[nir].values[i],
+                observation[red].values[i]], 1f64);
+            match best {
+                Some((_, x)) if x >= ndvi || ndvi.is_nan() => {},
+                _ => best = Some((j, ndvi)),
+            }
+        }
+
+        if let Some((j, _)) = best {
+            for k in 0..band_count {
+                if observations[j][k].is_valid(i) {
+                    composite[k][i] = observations[j][k].values[i];
+                }
+            }
+        }
+    }
+
+    Ok(composite)
+}
+
+pub fn reduce(observations: &[Vec<Band>], band_count: usize,
+        method: CompositeMethod, len: usize) -> Vec<Vec<f64>> {
+    let mut composite = vec![vec![std::f64::NAN; len]; band_count];
+    let mut buf = Vec::with_capacity(observations.len());
+    for k in 0..band_count {
+        for i in 0..len {
+            // gather valid observations of this pixel
+            buf.clear();
+            for observation in observations.iter() {
+                if observation[k].is_valid(i) {
+                    buf.push(observation[k].values[i]);
+                }
+            }
+
+            if buf.is_empty() {
+                continue;
+            }
+
+            composite[k][i] = match method {
+                CompositeMethod::Median => {
+                    buf.sort_by(|a, b| a.partial_cmp(b).unwrap());
+                    let mid = buf.len() / 2;
+                    match buf.len() % 2 {
+                        0 => (buf[mid - 1] + buf[mid]) / 2.0,
+                        _ => buf[mid],
+                    }
+                },
+                _ => buf.iter().sum::<f64>() / buf.len() as f64,
+            };
+        }
+    }
+
+    composite
+}
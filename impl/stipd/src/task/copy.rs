@@ -0,0 +1,203 @@
+use geocode::Geocode;
+use swarm::prelude::Dht;
+
+use crate::{Image, StFile, RAW_SOURCE, SPLIT_SOURCE};
+use crate::album::Album;
+use crate::task::Task;
+use crate::task::logger::LogContext;
+use crate::transfer::ImageWrite;
+
+use std::error::Error;
+use std::sync::{Arc, RwLock};
+
+pub struct CopyTask {
+    album: Arc<RwLock<Album>>,
+    destination: Arc<RwLock<Album>>,
+    dht: Arc<Dht>,
+    end_timestamp: Option<i64>,
+    geocode: Option<String>,
+    max_cloud_coverage: Option<f64>,
+    min_pixel_coverage: Option<f64>,
+    platform: Option<String>,
+    precision: Option<usize>,
+    recurse: bool,
+    same_geocode: bool,
+    source: Option<String>,
+    start_timestamp: Option<i64>,
+}
+
+impl CopyTask {
+    pub fn new(album: Arc<RwLock<Album>>, destination: Arc<RwLock<Album>>,
+            dht: Arc<Dht>, end_timestamp: Option<i64>,
+            geocode: Option<String>, max_cloud_coverage: Option<f64>,
+            min_pixel_coverage: Option<f64>, platform: Option<String>,
+            precision: Option<usize>, recurse: bool, source: Option<String>,
+            start_timestamp: Option<i64>)
+            -> Result<CopyTask, Box<dyn Error>> {
+        let same_geocode = {
+            let album = album.read().unwrap();
+            let destination = destination.read().unwrap();
+            info!("initailizing copy task [album={}, destination={}, end_timestamp={:?}, geocode={:?}, max_cloud_coverage={:?}, min_pixel_coverage={:?}, platform={:?}, precision={:?}, recurse={}, source={:?}, start_timestamp={:?}]",
+                album.get_id(), destination.get_id(), end_timestamp,
+                geocode, max_cloud_coverage, min_pixel_coverage, platform,
+                precision, recurse, source, start_timestamp);
+
+            if album.get_id() == destination.get_id() {
+                return Err("source and destination albums must differ"
+                    .into());
+            }
+
+            // fail before listing images if the destination is frozen
+            if destination.is_frozen() {
+                return Err(format!("album '{}' is frozen",
+                    destination.get_id()).into());
+            }
+
+            is_same_geocode(album.get_geocode(), destination.get_geocode())
+        };
+
+        // tiles are re-encoded when geocode algorithms differ
+        if !same_geocode && precision.is_none() {
+            return Err(
+                "copies between geocode algorithms require a precision"
+                .into());
+        }
+
+        Ok(CopyTask {
+            album: album,
+            destination: destination,
+            dht: dht,
+            end_timestamp: end_timestamp,
+            geocode: geocode,
+            max_cloud_coverage: max_cloud_coverage,
+            min_pixel_coverage: min_pixel_coverage,
+            platform: platform,
+            precision: precision,
+            recurse: recurse,
+            same_geocode: same_geocode,
+            source: source,
+            start_timestamp: start_timestamp,
+        })
+    }
+}
+
+#[tonic::async_trait]
+impl Task<(Image, StFile)> for CopyTask {
+    fn process(&self, record: &(Image, StFile))
+            -> Result<(), Box<dyn Error>> {
+        let (image, file) = record;
+
+        // check if path exists
+        let path = {
+            let album = self.album.read().unwrap();
+            album.get_image_path(false, &image.1,
+                &image.2, &image.3, file.2, &image.4)?
+        };
+
+        if !crate::compaction::exists(&path) {
+            return Err(format!("image path '{}' does not exist",
+                path.to_string_lossy()).into());
+        }
+
+        // retrieve destination album metadata
+        let (album_id, dht_key_length, geocode) = {
+            let destination = self.destination.read().unwrap();
+            (destination.get_id().to_string(),
+                destination.get_dht_key_length(),
+                destination.get_geocode().clone())
+        };
+
+        // open image
+        let dataset = crate::compaction::open(&path)?;
+
+        // re-split images coarser than the destination precision
+        let split_precision = match (self.same_geocode, self.precision) {
+            (false, Some(precision)) => Some(precision),
+            (true, Some(precision)) if image.1.len() < precision =>
+                Some(precision),
+            _ => None,
+        };
+
+        let mut image_writes = Vec::new();
+        if let Some(precision) = split_precision {
+            // geocodes of another algorithm never prefix split geocodes
+            let image_geocode = match self.same_geocode {
+                true => image.1.as_str(),
+                false => "",
+            };
+
+            let source = match image.3.as_str() {
+                RAW_SOURCE => SPLIT_SOURCE,
+                source => source,
+            };
+
+            for result in crate::task::split::split_dataset(&dataset,
+                    &geocode, None, image_geocode, precision)? {
+                // halt long records once the task is cancelled
+                crate::task::pool::check_cancelled()?;
+                let (split_geocode, split_dataset) = result?;
+
+                // lookup geocode in dht
+                let addrs = match crate::task::dht_lookup(
+                        &self.dht, dht_key_length, &split_geocode) {
+                    Ok(addrs) => addrs,
+                    Err(e) => {
+                        warn!("{}", e);
+                        continue;
+                    },
+                };
+
+                match ImageWrite::new(&addrs, &album_id, &split_dataset,
+                        &split_geocode, file.1, &image.2, source,
+                        file.2, &image.4, image.5) {
+                    Ok(image_write) => image_writes.push(image_write),
+                    Err(e) => warn!("{}", e),
+                }
+
+                crate::task::split::send_batch(&mut image_writes, false);
+            }
+        } else {
+            // copy the tile unchanged
+            let addrs = crate::task::dht_lookup(
+                &self.dht, dht_key_length, &image.1)?;
+
+            image_writes.push(ImageWrite::new(&addrs, &album_id, &dataset,
+                &image.1, file.1, &image.2, &image.3, file.2,
+                &image.4, image.5)?);
+        }
+
+        crate::task::split::send_batch(&mut image_writes, true);
+        Ok(())
+    }
+
+    fn log_context(&self, record: &(Image, StFile)) -> LogContext {
+        LogContext::new(&self.album, &(record.0).1)
+    }
+
+    async fn records(&self)
+            -> Result<Vec<(Image, StFile)>, Box<dyn Error>> {
+        // search for images using Album
+        let album = self.album.read().unwrap();
+        let images = album.list(&self.end_timestamp, &self.geocode,
+            &self.max_cloud_coverage, &self.min_pixel_coverage,
+            &self.platform, self.recurse, &self.source,
+            &self.start_timestamp)?;
+
+        let mut records = Vec::new();
+        for (image, files) in images.into_iter() {
+            for file in files.into_iter() {
+                records.push((image.clone(), file));
+            }
+        }
+
+        Ok(records)
+    }
+}
+
+fn is_same_geocode(a: &Geocode, b: &Geocode) -> bool {
+    match (a, b) {
+        (Geocode::Geohash, Geocode::Geohash) => true,
+        (Geocode::QuadTile, Geocode::QuadTile) => true,
+        _ => false,
+    }
+}
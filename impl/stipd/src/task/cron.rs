@@ -0,0 +1,269 @@
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use chrono::{DateTime, Datelike, Duration, TimeZone, Timelike, Utc};
+
+use crate::task::journal::TaskDefinition;
+
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufReader, Read, Write};
+use std::path::PathBuf;
+use std::str::FromStr;
+
+// bound search for the next occurrence of sparse expressions
+const MAX_SEARCH_DAYS: i64 = 366 * 5;
+
+pub struct CronExpression {
+    days: Vec<bool>,
+    days_restricted: bool,
+    hours: Vec<bool>,
+    minutes: Vec<bool>,
+    months: Vec<bool>,
+    weekdays: Vec<bool>,
+    weekdays_restricted: bool,
+}
+
+impl CronExpression {
+    pub fn next(&self, after: &DateTime<Utc>) -> Option<DateTime<Utc>> {
+        // start at the minute following 'after'
+        let mut time = Utc.timestamp(after.timestamp()
+            - after.timestamp().rem_euclid(60) + 60, 0);
+        let limit = time + Duration::days(MAX_SEARCH_DAYS);
+
+        while time < limit {
+            if !self.months[time.month() as usize] {
+                let (year, month) = match time.month() {
+                    12 => (time.year() + 1, 1),
+                    x => (time.year(), x + 1),
+                };
+
+                time = Utc.ymd(year, month, 1).and_hms(0, 0, 0);
+            } else if !self.matches_day(&time) {
+                time = (time.date() + Duration::days(1)).and_hms(0, 0, 0);
+            } else if !self.hours[time.hour() as usize] {
+                time = time.date().and_hms(time.hour(), 0, 0)
+                    + Duration::hours(1);
+            } else if !self.minutes[time.minute() as usize] {
+                time = time + Duration::minutes(1);
+            } else {
+                return Some(time);
+            }
+        }
+
+        None
+    }
+
+    fn matches_day(&self, time: &DateTime<Utc>) -> bool {
+        let day = self.days[time.day() as usize];
+        let weekday = self.weekdays
+            [time.weekday().num_days_from_sunday() as usize];
+
+        // restricted day and weekday fields match on either
+        match (self.days_restricted, self.weekdays_restricted) {
+            (true, true) => day || weekday,
+            _ => day && weekday,
+        }
+    }
+}
+
+impl FromStr for CronExpression {
+    type Err = Box<dyn Error>;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let expression = match s.trim() {
+            "@hourly" => "0 * * * *",
+            "@daily" => "0 0 * * *",
+            "@weekly" => "0 0 * * 0",
+            "@monthly" => "0 0 1 * *",
+            x => x,
+        };
+
+        let fields: Vec<&str> = expression.split_whitespace().collect();
+        if fields.len() != 5 {
+            return Err(format!("schedule '{}' must have 5 fields", s).into());
+        }
+
+        // sunday may be written as 0 or 7
+        let mut weekdays = parse_field(fields[4], 0, 7)?;
+        if weekdays[7] {
+            weekdays[0] = true;
+        }
+
+        Ok(CronExpression {
+            days: parse_field(fields[2], 1, 31)?,
+            days_restricted: fields[2] != "*",
+            hours: parse_field(fields[1], 0, 23)?,
+            minutes: parse_field(fields[0], 0, 59)?,
+            months: parse_field(fields[3], 1, 12)?,
+            weekdays: weekdays,
+            weekdays_restricted: fields[4] != "*",
+        })
+    }
+}
+
+pub struct RecurringTask {
+    cron: CronExpression,
+    definition: TaskDefinition,
+    expression: String,
+    last_task_id: Option<u64>,
+    next_timestamp: i64,
+    path: PathBuf,
+}
+
+impl RecurringTask {
+    pub fn create(directory: &PathBuf, recurring_id: u64,
+            definition: TaskDefinition, expression: &str)
+            -> Result<RecurringTask, Box<dyn Error>> {
+        let mut path = directory.clone();
+        path.push(recurring_id.to_string());
+        path.set_extension("recurring");
+
+        // write recurring task file
+        let recurring_task = RecurringTask::new(definition, expression, path)?;
+        recurring_task.persist()?;
+        Ok(recurring_task)
+    }
+
+    pub fn open(path: &PathBuf) -> Result<RecurringTask, Box<dyn Error>> {
+        // read recurring task file
+        let mut reader = BufReader::new(File::open(path)?);
+        let len = reader.read_u32::<BigEndian>()?;
+        let mut buf = vec![0u8; len as usize];
+        reader.read_exact(&mut buf)?;
+
+        let expression = String::from_utf8(buf)?;
+        let definition = TaskDefinition::read(&mut reader)?;
+        let mut recurring_task =
+            RecurringTask::new(definition, &expression, path.clone())?;
+
+        // files written before schedule state end after the definition,
+        //   otherwise occurrences missed while the node was down run once
+        let mut flag = [0u8; 1];
+        if reader.read(&mut flag)? == 1 && flag[0] != 0 {
+            recurring_task.last_task_id = match reader.read_u8()? {
+                0 => None,
+                _ => Some(reader.read_u64::<BigEndian>()?),
+            };
+            recurring_task.next_timestamp = reader.read_i64::<BigEndian>()?;
+        }
+
+        Ok(recurring_task)
+    }
+
+    fn new(definition: TaskDefinition, expression: &str, path: PathBuf)
+            -> Result<RecurringTask, Box<dyn Error>> {
+        let cron = CronExpression::from_str(expression)?;
+        let next_timestamp = match cron.next(&Utc::now()) {
+            Some(time) => time.timestamp(),
+            None => return Err(format!("schedule '{}' never occurs",
+                expression).into()),
+        };
+
+        Ok(RecurringTask {
+            cron: cron,
+            definition: definition,
+            expression: expression.to_string(),
+            last_task_id: None,
+            next_timestamp: next_timestamp,
+            path: path,
+        })
+    }
+
+    // compute the next occurrence, persisted so restarts do not rerun
+    //   the current occurrence
+    pub fn advance(&mut self) -> Result<(), Box<dyn Error>> {
+        self.next_timestamp = match self.cron.next(&Utc::now()) {
+            Some(time) => time.timestamp(),
+            None => std::i64::MAX,
+        };
+
+        self.persist()
+    }
+
+    pub fn get_definition(&self) -> &TaskDefinition {
+        &self.definition
+    }
+
+    pub fn get_expression(&self) -> &str {
+        &self.expression
+    }
+
+    pub fn get_last_task_id(&self) -> Option<u64> {
+        self.last_task_id
+    }
+
+    pub fn get_next_timestamp(&self) -> i64 {
+        self.next_timestamp
+    }
+
+    pub fn set_last_task_id(&mut self, task_id: u64)
+            -> Result<(), Box<dyn Error>> {
+        self.last_task_id = Some(task_id);
+        self.persist()
+    }
+
+    fn persist(&self) -> Result<(), Box<dyn Error>> {
+        // write to a temporary file which replaces the existing file
+        let mut tmp_path = self.path.clone();
+        tmp_path.set_extension("recurring.tmp");
+        {
+            let mut file = File::create(&tmp_path)?;
+            file.write_u32::<BigEndian>(self.expression.len() as u32)?;
+            file.write_all(self.expression.as_bytes())?;
+            self.definition.write(&mut file)?;
+
+            file.write_u8(1)?;
+            match self.last_task_id {
+                Some(task_id) => {
+                    file.write_u8(1)?;
+                    file.write_u64::<BigEndian>(task_id)?;
+                },
+                None => file.write_u8(0)?,
+            }
+
+            file.write_i64::<BigEndian>(self.next_timestamp)?;
+            file.sync_all()?;
+        }
+
+        std::fs::rename(&tmp_path, &self.path)?;
+        Ok(())
+    }
+}
+
+fn parse_field(field: &str, min: u32, max: u32)
+        -> Result<Vec<bool>, Box<dyn Error>> {
+    let mut values = vec![false; max as usize + 1];
+    for part in field.split(',') {
+        // parse optional step
+        let (range, step) = match part.find('/') {
+            Some(index) => (&part[..index],
+                part[index + 1..].parse::<u32>()?),
+            None => (part, 1),
+        };
+
+        if step == 0 {
+            return Err(format!("invalid step in '{}'", part).into());
+        }
+
+        // parse range bounds
+        let (start, end) = match range {
+            "*" => (min, max),
+            x => match x.find('-') {
+                Some(index) => (x[..index].parse::<u32>()?,
+                    x[index + 1..].parse::<u32>()?),
+                None if step != 1 => (x.parse::<u32>()?, max),
+                None => (x.parse::<u32>()?, x.parse::<u32>()?),
+            },
+        };
+
+        if start < min || end > max || start > end {
+            return Err(format!("value '{}' out of range {}-{}",
+                part, min, max).into());
+        }
+
+        for value in (start..=end).step_by(step as usize) {
+            values[value as usize] = true;
+        }
+    }
+
+    Ok(values)
+}
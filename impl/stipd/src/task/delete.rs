@@ -0,0 +1,85 @@
+use crate::{Image, StFile};
+use crate::album::Album;
+use crate::task::Task;
+use crate::task::logger::LogContext;
+
+use std::error::Error;
+use std::sync::{Arc, RwLock};
+
+pub struct DeleteTask {
+    album: Arc<RwLock<Album>>,
+    end_timestamp: Option<i64>,
+    geocode: Option<String>,
+    max_cloud_coverage: Option<f64>,
+    min_pixel_coverage: Option<f64>,
+    platform: Option<String>,
+    recurse: bool,
+    source: Option<String>,
+    start_timestamp: Option<i64>,
+}
+
+impl DeleteTask {
+    pub fn new(album: Arc<RwLock<Album>>, end_timestamp: Option<i64>,
+            geocode: Option<String>, max_cloud_coverage: Option<f64>,
+            min_pixel_coverage: Option<f64>, platform: Option<String>,
+            recurse: bool, source: Option<String>,
+            start_timestamp: Option<i64>)
+            -> Result<DeleteTask, Box<dyn Error>> {
+        {
+            let album = album.read().unwrap();
+            info!("initailizing delete task [album={}, end_timestamp={:?}, geocode={:?}, max_cloud_coverage={:?}, min_pixel_coverage={:?}, platform={:?}, recurse={}, source={:?}, start_timestamp={:?}]",
+                album.get_id(), end_timestamp, geocode, max_cloud_coverage,
+                min_pixel_coverage, platform, recurse, source,
+                start_timestamp);
+
+            // fail before listing images if the album is under a hold
+            album.assert_modifiable()?;
+        }
+
+        Ok(DeleteTask {
+            album: album,
+            end_timestamp: end_timestamp,
+            geocode: geocode,
+            max_cloud_coverage: max_cloud_coverage,
+            min_pixel_coverage: min_pixel_coverage,
+            platform: platform,
+            recurse: recurse,
+            source: source,
+            start_timestamp: start_timestamp,
+        })
+    }
+}
+
+#[tonic::async_trait]
+impl Task<(Image, StFile)> for DeleteTask {
+    fn process(&self, record: &(Image, StFile))
+            -> Result<(), Box<dyn Error>> {
+        let (image, file) = record;
+
+        let mut album = self.album.write().unwrap();
+        album.remove(&image.1, &image.2, &image.3, file.2, &image.4)
+    }
+
+    fn log_context(&self, record: &(Image, StFile)) -> LogContext {
+        LogContext::new(&self.album, &(record.0).1)
+    }
+
+    async fn records(&self)
+            -> Result<Vec<(Image, StFile)>, Box<dyn Error>> {
+        // search for images using Album
+        let album = self.album.read().unwrap();
+        let images = album.list(&self.end_timestamp, &self.geocode,
+            &self.max_cloud_coverage, &self.min_pixel_coverage,
+            &self.platform, self.recurse, &self.source,
+            &self.start_timestamp)?;
+
+        let mut records = Vec::new();
+        for (image, files) in images.into_iter() {
+            for file in files.into_iter() {
+                records.push((image.clone(), file));
+            }
+        }
+
+        Ok(records)
+    }
+}
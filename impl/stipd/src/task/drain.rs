@@ -0,0 +1,99 @@
+use swarm::prelude::Dht;
+
+use crate::{Image, StFile};
+use crate::album::AlbumManager;
+use crate::task::Task;
+use crate::task::logger::LogContext;
+
+use std::error::Error;
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+
+pub struct DrainTask {
+    album_manager: Arc<RwLock<AlbumManager>>,
+    dht: Arc<Dht>,
+    thread_count: u8,
+}
+
+impl DrainTask {
+    pub fn new(album_manager: Arc<RwLock<AlbumManager>>,
+            dht: Arc<Dht>, thread_count: u8) -> DrainTask {
+        info!("initailizing drain task [thread_count={}]", thread_count);
+
+        DrainTask {
+            album_manager: album_manager,
+            dht: dht,
+            thread_count: thread_count,
+        }
+    }
+}
+
+#[tonic::async_trait]
+impl Task<(String, Image, StFile)> for DrainTask {
+    fn process(&self, record: &(String, Image, StFile))
+            -> Result<(), Box<dyn Error>> {
+        let (album_id, image, file) = record;
+
+        // retrieve album metadata
+        let dht_key_length = {
+            let album_manager = self.album_manager.read().unwrap();
+            match album_manager.get(album_id) {
+                Some(album) => album.read().unwrap().get_dht_key_length(),
+                None => return Err(format!("album '{}' does not exist",
+                    album_id).into()),
+            }
+        };
+
+        // check if path exists
+        let path = PathBuf::from(&file.0);
+        if !crate::compaction::exists(&path) {
+            return Err(format!("image path '{}' does not exist",
+                file.0).into());
+        }
+
+        // lookup new owners, which exclude this decommissioned node
+        let addrs = crate::task::dht_lookup(
+            &self.dht, dht_key_length, &image.1)?;
+
+        // send image to replica hosts
+        let dataset = crate::compaction::open(&path)?;
+        crate::transfer::send_image(&addrs, album_id, &dataset,
+            &image.1, file.1, &image.2, &image.3, file.2,
+            &image.4, image.5)
+    }
+
+    fn log_context(&self, record: &(String, Image, StFile)) -> LogContext {
+        LogContext {
+            album: Some(record.0.clone()),
+            geocode: Some((record.1).1.clone()),
+        }
+    }
+
+    async fn records(&self)
+            -> Result<Vec<(String, Image, StFile)>, Box<dyn Error>> {
+        // open album indices to enumerate stored images
+        let album_ids: Vec<String> = self.album_manager.read().unwrap()
+            .iter().map(|(id, _)| id.clone()).collect();
+        for album_id in album_ids.iter() {
+            crate::task::journal::open_album(&self.album_manager,
+                album_id, self.thread_count)?;
+        }
+
+        // list every image file within each album
+        let mut records = Vec::new();
+        let album_manager = self.album_manager.read().unwrap();
+        for (album_id, album) in album_manager.iter() {
+            let album = album.read().unwrap();
+            let images = album.list(&None, &None, &None, &None,
+                &None, true, &None, &None)?;
+
+            for (image, files) in images {
+                for file in files {
+                    records.push((album_id.clone(), image.clone(), file));
+                }
+            }
+        }
+
+        Ok(records)
+    }
+}
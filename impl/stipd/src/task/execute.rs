@@ -0,0 +1,155 @@
+use gdal::Dataset;
+use protobuf::{TaskExecuteInput, TaskExecuteOutput, TaskExecutorClient};
+use swarm::prelude::Dht;
+use tokio::runtime::Builder;
+
+use crate::{Image, StFile};
+use crate::album::Album;
+use crate::task::Task;
+use crate::task::logger::LogContext;
+
+use std::error::Error;
+use std::sync::{Arc, RwLock};
+
+pub struct ExecuteTask {
+    address: String,
+    album: Arc<RwLock<Album>>,
+    dht: Arc<Dht>,
+    end_timestamp: Option<i64>,
+    geocode: Option<String>,
+    max_cloud_coverage: Option<f64>,
+    min_pixel_coverage: Option<f64>,
+    parameters: Option<String>,
+    platform: Option<String>,
+    recurse: bool,
+    source: Option<String>,
+    start_timestamp: Option<i64>,
+}
+
+impl ExecuteTask {
+    pub fn new(address: String, album: Arc<RwLock<Album>>, dht: Arc<Dht>,
+            end_timestamp: Option<i64>, geocode: Option<String>,
+            max_cloud_coverage: Option<f64>,
+            min_pixel_coverage: Option<f64>, parameters: Option<String>,
+            platform: Option<String>, recurse: bool,
+            source: Option<String>, start_timestamp: Option<i64>)
+            -> ExecuteTask {
+        {
+            let album = album.read().unwrap();
+            info!("initailizing execute task [address={}, album={}, end_timestamp={:?}, geocode={:?}, max_cloud_coverage={:?}, min_pixel_coverage={:?}, parameters={:?}, platform={:?}, recurse={}, source={:?}, start_timestamp={:?}]",
+                address, album.get_id(), end_timestamp, geocode,
+                max_cloud_coverage, min_pixel_coverage, parameters,
+                platform, recurse, source, start_timestamp);
+        }
+
+        ExecuteTask {
+            address: address,
+            album: album,
+            dht: dht,
+            end_timestamp: end_timestamp,
+            geocode: geocode,
+            max_cloud_coverage: max_cloud_coverage,
+            min_pixel_coverage: min_pixel_coverage,
+            parameters: parameters,
+            platform: platform,
+            recurse: recurse,
+            source: source,
+            start_timestamp: start_timestamp,
+        }
+    }
+}
+
+#[tonic::async_trait]
+impl Task<(Image, StFile)> for ExecuteTask {
+    fn process(&self, record: &(Image, StFile))
+            -> Result<(), Box<dyn Error>> {
+        let (image, file) = record;
+
+        // retrieve album metadata
+        let (album_id, dht_key_length) = {
+            let album = self.album.read().unwrap();
+            (album.get_id().to_string(), album.get_dht_key_length())
+        };
+
+        // initialize executor input from the stored cell
+        let input = TaskExecuteInput {
+            cloud_coverage: image.0,
+            data: std::fs::read(&file.0)?,
+            geocode: image.1.clone(),
+            parameters: self.parameters.clone(),
+            pixel_coverage: file.1,
+            platform: image.2.clone(),
+            source: image.3.clone(),
+            subdataset: file.2 as u32,
+            tile: image.4.clone(),
+            timestamp: image.5,
+        };
+
+        // stream cell to executor and collect output rasters
+        let mut runtime = Builder::new()
+            .basic_scheduler().enable_all().build()?;
+        let outputs = runtime.block_on(execute(&self.address, input))?;
+
+        for output in outputs.iter() {
+            // write output raster to a temporary file for gdal
+            let path = std::env::temp_dir().join(format!(
+                "stip-execute-{}.tif", rand::random::<u64>()));
+            std::fs::write(&path, &output.data)?;
+
+            let result = match Dataset::open(&path) {
+                Ok(dataset) => crate::task::dht_lookup(&self.dht,
+                        dht_key_length, &output.geocode)
+                    .and_then(|addrs| crate::transfer::send_image(
+                        &addrs, &album_id, &dataset, &output.geocode,
+                        output.pixel_coverage, &image.2, &output.source,
+                        output.subdataset as u8, &output.tile,
+                        output.timestamp)),
+                Err(e) => Err(e.into()),
+            };
+
+            std::fs::remove_file(&path)?;
+            result?;
+        }
+
+        Ok(())
+    }
+
+    fn log_context(&self, record: &(Image, StFile)) -> LogContext {
+        LogContext::new(&self.album, &(record.0).1)
+    }
+
+    async fn records(&self)
+            -> Result<Vec<(Image, StFile)>, Box<dyn Error>> {
+        // search for images using Album
+        let album = self.album.read().unwrap();
+        let images = album.list(&self.end_timestamp, &self.geocode,
+            &self.max_cloud_coverage, &self.min_pixel_coverage,
+            &self.platform, self.recurse, &self.source,
+            &self.start_timestamp)?;
+
+        let mut records = Vec::new();
+        for (image, files) in images.into_iter() {
+            for file in files.into_iter() {
+                records.push((image.clone(), file));
+            }
+        }
+
+        Ok(records)
+    }
+}
+
+async fn execute(address: &str, input: TaskExecuteInput)
+        -> Result<Vec<TaskExecuteOutput>, Box<dyn Error>> {
+    let mut client = TaskExecutorClient::connect(
+        format!("http://{}", address)).await?;
+
+    let reply = client.execute(tokio::stream::iter(vec![input])).await?;
+    let mut stream = reply.into_inner();
+
+    let mut outputs = Vec::new();
+    while let Some(output) = stream.message().await? {
+        outputs.push(output);
+    }
+
+    Ok(outputs)
+}
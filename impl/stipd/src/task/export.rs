@@ -0,0 +1,361 @@
+use protobuf::{File, Filter, Image, ImageListRequest, ImageManagementClient};
+use serde_json::{json, Map, Value};
+use swarm::prelude::Dht;
+use tonic::Request;
+
+use crate::album::Album;
+use crate::task::Task;
+use crate::task::logger::LogContext;
+use crate::transfer::LatencyTracker;
+
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::str::FromStr;
+use std::sync::{Arc, RwLock};
+
+// image, tile, replicas of each file, and stac address storing the tile
+pub type ExportRecord = (Image, String,
+    Vec<Vec<(SocketAddr, String)>>, Option<String>);
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ExportAssets {
+    Copy,
+    Link,
+    None,
+}
+
+impl FromStr for ExportAssets {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "copy" => Ok(ExportAssets::Copy),
+            "link" => Ok(ExportAssets::Link),
+            "none" => Ok(ExportAssets::None),
+            x => Err(format!("unsupported export assets '{}'", x)),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum Destination {
+    Directory(PathBuf),
+    S3(String),
+}
+
+impl Destination {
+    // write a file at the key relative to the destination
+    pub fn write<F>(&self, key: &str, f: F) -> Result<(), Box<dyn Error>>
+            where F: Fn(&Path) -> Result<(), Box<dyn Error>> {
+        match self {
+            Destination::Directory(directory) => {
+                let path = directory.join(key);
+                if let Some(parent) = path.parent() {
+                    std::fs::create_dir_all(parent)?;
+                }
+
+                f(&path)
+            },
+            Destination::S3(prefix) => {
+                // stage locally and upload with the aws cli
+                let path = std::env::temp_dir().join(format!(
+                    "stip-export-{}", rand::random::<u64>()));
+                let result = f(&path).and_then(|_| s3_upload(&path,
+                    &format!("{}/{}", prefix.trim_end_matches('/'), key)));
+                let _ = std::fs::remove_file(&path);
+                result
+            },
+        }
+    }
+
+    pub fn write_json(&self, key: &str, value: &Value)
+            -> Result<(), Box<dyn Error>> {
+        let buf = serde_json::to_vec_pretty(value)?;
+        self.write(key, |path| Ok(std::fs::write(path, &buf)?))
+    }
+}
+
+impl FromStr for Destination {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "" => Err("export destination is empty".to_string()),
+            x if x.starts_with("s3://") => Ok(Destination::S3(x.to_string())),
+            x if Path::new(x).is_absolute() =>
+                Ok(Destination::Directory(PathBuf::from(x))),
+            x => Err(format!("export directory '{}' must be absolute", x)),
+        }
+    }
+}
+
+pub struct ExportTask {
+    album: Arc<RwLock<Album>>,
+    assets: ExportAssets,
+    destination: Destination,
+    dht: Arc<Dht>,
+    end_timestamp: Option<i64>,
+    geocode: Option<String>,
+    max_cloud_coverage: Option<f64>,
+    min_pixel_coverage: Option<f64>,
+    platform: Option<String>,
+    recurse: bool,
+    source: Option<String>,
+    start_timestamp: Option<i64>,
+    tenant: Option<String>,
+    tracker: Arc<LatencyTracker>,
+}
+
+impl ExportTask {
+    pub fn new(album: Arc<RwLock<Album>>, assets: ExportAssets,
+            destination: Destination, dht: Arc<Dht>,
+            end_timestamp: Option<i64>, geocode: Option<String>,
+            max_cloud_coverage: Option<f64>,
+            min_pixel_coverage: Option<f64>, platform: Option<String>,
+            recurse: bool, source: Option<String>,
+            start_timestamp: Option<i64>, tenant: Option<String>,
+            tracker: Arc<LatencyTracker>)
+            -> Result<ExportTask, Box<dyn Error>> {
+        {
+            let album = album.read().unwrap();
+            info!("initailizing export task [album={}, assets={:?}, destination={:?}, end_timestamp={:?}, geocode={:?}, max_cloud_coverage={:?}, min_pixel_coverage={:?}, platform={:?}, recurse={}, source={:?}, start_timestamp={:?}]",
+                album.get_id(), assets, destination, end_timestamp,
+                geocode, max_cloud_coverage, min_pixel_coverage,
+                platform, recurse, source, start_timestamp);
+        }
+
+        Ok(ExportTask {
+            album: album,
+            assets: assets,
+            destination: destination,
+            dht: dht,
+            end_timestamp: end_timestamp,
+            geocode: geocode,
+            max_cloud_coverage: max_cloud_coverage,
+            min_pixel_coverage: min_pixel_coverage,
+            platform: platform,
+            recurse: recurse,
+            source: source,
+            start_timestamp: start_timestamp,
+            tenant: tenant,
+            tracker: tracker,
+        })
+    }
+}
+
+#[tonic::async_trait]
+impl Task<ExportRecord> for ExportTask {
+    fn process(&self, record: &ExportRecord) -> Result<(), Box<dyn Error>> {
+        let (image, tile, replicas, address) = record;
+        let (album_id, geocode) = {
+            let album = self.album.read().unwrap();
+            (album.get_id().to_string(), album.get_geocode().clone())
+        };
+
+        let id = crate::stac::item_id(&image.geocode,
+            &image.platform, &image.source, tile);
+
+        // compile assets, copying files beside the item if requested
+        let mut assets = Map::new();
+        for (file, replicas) in image.files.iter().zip(replicas.iter()) {
+            let href = match (self.assets, address) {
+                (ExportAssets::Copy, _) => {
+                    let buf = crate::transfer::read_geotiff(replicas,
+                        &self.tenant, &self.tracker)?;
+                    self.destination.write(&format!("{}/{}/{}.tif",
+                            album_id, id, file.subdataset),
+                        |path| Ok(std::fs::write(path, &buf)?))?;
+
+                    format!("./{}.tif", file.subdataset)
+                },
+                (ExportAssets::Link, Some(address)) =>
+                    format!("http://{}/download/{}/{}/{}/{}/{}/{}",
+                        address, album_id, image.platform, image.geocode,
+                        image.source, tile, file.subdataset),
+                _ => continue,
+            };
+
+            assets.insert(format!("subdataset-{}", file.subdataset),
+                crate::stac::asset(&href, file.pixel_coverage));
+        }
+
+        // write item beside its assets
+        let item_key = format!("{}/{}/{}.json", album_id, id, id);
+        let links = vec![
+            crate::stac::link("self", &format!("./{}.json", id),
+                "application/geo+json"),
+            crate::stac::link("parent", "../collection.json",
+                "application/json"),
+            crate::stac::link("collection", "../collection.json",
+                "application/json"),
+            crate::stac::link("root", "../../catalog.json",
+                "application/json"),
+        ];
+
+        let item = crate::stac::item(&album_id,
+            &geocode, image, tile, assets, links)?;
+        self.destination.write_json(&item_key, &item)
+    }
+
+    fn estimate(&self, _record: &ExportRecord)
+            -> Result<Option<Vec<String>>, Box<dyn Error>> {
+        // exports write no tiles
+        Ok(Some(Vec::new()))
+    }
+
+    fn log_context(&self, record: &ExportRecord) -> LogContext {
+        LogContext::new(&self.album, &record.0.geocode)
+    }
+
+    async fn records(&self) -> Result<Vec<ExportRecord>, Box<dyn Error>> {
+        // retrieve album metadata
+        let (album_id, geocode) = {
+            let album = self.album.read().unwrap();
+            (album.get_id().to_string(), album.get_geocode().clone())
+        };
+
+        // initialize ImageListRequest
+        let request = ImageListRequest {
+            album: album_id.clone(),
+            albums: Vec::new(),
+            all_albums: None,
+            filter: Filter {
+                end_timestamp: self.end_timestamp,
+                geocode: self.geocode.clone(),
+                max_cloud_coverage: self.max_cloud_coverage,
+                min_pixel_coverage: self.min_pixel_coverage,
+                name: None,
+                platform: self.platform.clone(),
+                recurse: self.recurse,
+                source: self.source.clone(),
+                start_timestamp: self.start_timestamp,
+            },
+            geometry: None,
+            max_latitude: None,
+            max_longitude: None,
+            min_latitude: None,
+            min_longitude: None,
+            statistics: None,
+        };
+
+        let records = list_replicas(&self.dht, &request).await?;
+
+        // write catalog and collection linking every exported item
+        let catalog = json!({
+            "description": format!("stip export of album '{}'", album_id),
+            "id": format!("stip-{}", album_id),
+            "links": vec![
+                crate::stac::link("self", "./catalog.json",
+                    "application/json"),
+                crate::stac::link("root", "./catalog.json",
+                    "application/json"),
+                crate::stac::link("child",
+                    &format!("./{}/collection.json", album_id),
+                    "application/json"),
+            ],
+            "stac_version": crate::stac::STAC_VERSION,
+            "type": "Catalog",
+        });
+
+        let mut links = vec![
+            crate::stac::link("self", "./collection.json",
+                "application/json"),
+            crate::stac::link("parent", "../catalog.json",
+                "application/json"),
+            crate::stac::link("root", "../catalog.json",
+                "application/json"),
+        ];
+
+        for (image, tile, _, _) in records.iter() {
+            let id = crate::stac::item_id(&image.geocode,
+                &image.platform, &image.source, tile);
+            links.push(crate::stac::link("item",
+                &format!("./{}/{}.json", id, id), "application/geo+json"));
+        }
+
+        self.destination.write_json("catalog.json", &catalog)?;
+        self.destination.write_json(&format!("{}/collection.json", album_id),
+            &crate::stac::collection(&album_id, &geocode, links))?;
+
+        Ok(records)
+    }
+}
+
+// list images across the cluster grouping the replicas of each tile file
+pub async fn list_replicas(dht: &Arc<Dht>, request: &ImageListRequest)
+        -> Result<Vec<ExportRecord>, Box<dyn Error>> {
+    let mut tiles: BTreeMap<(String, String, String, String, i64),
+        (Image, BTreeMap<i32, (File, Vec<(SocketAddr, String)>)>,
+            Option<String>)> = BTreeMap::new();
+    for node in dht.nodes() {
+        // get rpc, stac, and transfer addresses
+        let addr = format!("{}:{}", node.get_ip_address(),
+            node.get_metadata("rpc_port").unwrap());
+        let stac_addr = node.get_metadata("stac_port").map(|x|
+            format!("{}:{}", node.get_ip_address(), x));
+        let xfer_addr = SocketAddr::new(node.get_ip_address().clone(),
+            node.get_metadata("xfer_port").unwrap().parse::<u16>()?);
+
+        // open ImageManagementClient
+        let mut client = match crate::tls::channel(
+                &addr).await.map(ImageManagementClient::new) {
+            Ok(client) => client,
+            Err(e) => return Err(format!(
+                "connection to {} failed: {}", addr, e).into()),
+        };
+
+        let mut stream = client.list(Request::new(request.clone()))
+            .await?.into_inner();
+        while let Some(image) = stream.message().await? {
+            // image paths are 'tile-subdataset.tif'
+            let tile = match image.files.first()
+                    .and_then(|x| Path::new(&x.path).file_stem())
+                    .and_then(|x| x.to_str())
+                    .and_then(|x| x.rsplitn(2, '-').nth(1)) {
+                Some(tile) => tile.to_string(),
+                None => continue,
+            };
+
+            let entry = tiles.entry((image.geocode.clone(),
+                    image.platform.clone(), image.source.clone(),
+                    tile, image.timestamp))
+                .or_insert((image.clone(), BTreeMap::new(), None));
+            if entry.2.is_none() {
+                entry.2 = stac_addr.clone();
+            }
+
+            for file in image.files.into_iter() {
+                let path = file.path.clone();
+                entry.1.entry(file.subdataset)
+                    .or_insert((file, Vec::new()))
+                    .1.push((xfer_addr, path));
+            }
+        }
+    }
+
+    let mut records = Vec::new();
+    for ((_, _, _, tile, _), (mut image, files, stac_addr))
+            in tiles.into_iter() {
+        let (files, replicas): (Vec<File>, Vec<_>) = files.into_iter()
+            .map(|(_, x)| x).unzip();
+        image.files = files;
+        records.push((image, tile, replicas, stac_addr));
+    }
+
+    Ok(records)
+}
+
+fn s3_upload(path: &Path, url: &str) -> Result<(), Box<dyn Error>> {
+    // credentials and region are read from the aws cli environment
+    let output = Command::new("aws").arg("s3").arg("cp")
+        .arg("--only-show-errors").arg(path).arg(url).output()?;
+
+    match output.status.success() {
+        true => Ok(()),
+        false => Err(format!("failed to upload {}: {}", url,
+            String::from_utf8_lossy(&output.stderr).trim()).into()),
+    }
+}
@@ -1,45 +1,221 @@
-use gdal::Dataset;
+use gdal::{Dataset, Metadata};
 
 use crate::{Image, StFile, FILLED_SOURCE};
 use crate::album::Album;
+use crate::fill::FillStrategy;
+use crate::mask::CloudMask;
+use crate::raster::RESAMPLING_METHODS;
 use crate::task::Task;
+use crate::task::logger::LogContext;
 
 use std::cmp::Ordering as CmpOrdering;
+use std::collections::BTreeMap;
 use std::error::Error;
 use std::sync::{Arc, RwLock};
 
 pub struct FillTask {
     album: Arc<RwLock<Album>>,
+    band_map: Vec<usize>,
+    cloud_mask: Option<CloudMask>,
+    donor_cloud_mask: Option<CloudMask>,
+    donor_platform: Option<String>,
     end_timestamp: Option<i64>,
     geocode: Option<String>,
+    mask_band: Option<usize>,
+    mask_subdataset: Option<u8>,
     platform: Option<String>,
     recurse: bool,
+    resampling: String,
     start_timestamp: Option<i64>,
+    strategy: Box<dyn FillStrategy>,
     window_seconds: i64,
 }
 
 impl FillTask {
-    pub fn new(album: Arc<RwLock<Album>>, end_timestamp: Option<i64>,
-            geocode: Option<String>, platform: Option<String>,
-            recurse: bool, start_timestamp: Option<i64>,
-            window_seconds: i64) -> FillTask {
+    pub fn new(album: Arc<RwLock<Album>>, band_map: Vec<usize>,
+            cloud_mask: Option<CloudMask>,
+            donor_cloud_mask: Option<CloudMask>,
+            donor_platform: Option<String>, end_timestamp: Option<i64>,
+            geocode: Option<String>, mask_band: Option<usize>,
+            mask_subdataset: Option<u8>, platform: Option<String>,
+            recurse: bool, resampling: String,
+            start_timestamp: Option<i64>, strategy: Box<dyn FillStrategy>,
+            window_seconds: i64) -> Result<FillTask, Box<dyn Error>> {
         {
             let album = album.read().unwrap();
-            info!("initailizing fill task [album={}, end_timestamp={:?}, geocode={:?}, platform={:?}, recurse={}, start_timestamp={:?}, window_seconds={}]",
-                album.get_id(), end_timestamp, geocode, platform,
-                recurse, start_timestamp, window_seconds);
+            info!("initailizing fill task [album={}, band_map={:?}, cloud_mask={:?}, donor_cloud_mask={:?}, donor_platform={:?}, end_timestamp={:?}, geocode={:?}, mask_band={:?}, mask_subdataset={:?}, platform={:?}, recurse={}, resampling={}, start_timestamp={:?}, strategy={}, window_seconds={}]",
+                album.get_id(), band_map, cloud_mask, donor_cloud_mask,
+                donor_platform, end_timestamp, geocode, mask_band,
+                mask_subdataset, platform, recurse, resampling,
+                start_timestamp, strategy.name(), window_seconds);
         }
 
-        FillTask {
+        if donor_platform.is_some() && !strategy.supports_donors() {
+            return Err(format!("{} fill does not support donor platforms",
+                strategy.name()).into());
+        } else if donor_platform.is_some() && platform.is_none() {
+            return Err("donor platform requires a target platform".into());
+        }
+
+        if band_map.iter().any(|x| *x == 0) {
+            return Err("band numbers start at 1".into());
+        }
+
+        if !RESAMPLING_METHODS.contains(&resampling.as_str()) {
+            return Err(format!("unsupported resampling method '{}'",
+                resampling).into());
+        }
+
+        Ok(FillTask {
             album: album,
+            band_map: band_map,
+            cloud_mask: cloud_mask,
+            donor_cloud_mask: donor_cloud_mask,
+            donor_platform: donor_platform,
             end_timestamp: end_timestamp,
             geocode: geocode,
+            mask_band: mask_band,
+            mask_subdataset: mask_subdataset,
             platform: platform,
             recurse: recurse,
+            resampling: resampling,
             start_timestamp: start_timestamp,
+            strategy: strategy,
             window_seconds: window_seconds,
+        })
+    }
+}
+
+impl FillTask {
+    fn group(&self, targets: &[(Image, StFile)],
+            donors: &BTreeMap<(String, u8), Vec<(Image, StFile)>>)
+            -> Vec<Vec<(Image, StFile)>> {
+        let (image, file) = match targets.first() {
+            Some(target) => target,
+            None => return Vec::new(),
+        };
+
+        // fill from the same platform unless a donor platform is set
+        match self.donor_platform {
+            Some(_) => match donors.get(&(image.1.clone(), file.2)) {
+                Some(donors) => self.strategy.records(targets,
+                    donors, self.window_seconds),
+                None => Vec::new(),
+            },
+            None => self.strategy.records(targets,
+                targets, self.window_seconds),
         }
     }
+
+    fn list(&self, platform: &Option<String>)
+            -> Result<Vec<(Image, StFile)>, Box<dyn Error>> {
+        let album = self.album.read().unwrap();
+        let images = album.list(&self.end_timestamp,
+            &self.geocode, &None, &None, platform,
+            self.recurse, &None, &self.start_timestamp)?;
+
+        let mut records = Vec::new();
+        for (image, files) in images.into_iter() {
+            for file in files.into_iter() {
+                records.push((image.clone(), file));
+            }
+        }
+
+        Ok(records)
+    }
+
+    // true if the observation was captured by the donor platform
+    fn is_donor(&self, record: &Vec<(Image, StFile)>, index: usize) -> bool {
+        let platform = &(record[index].0).2;
+        index != 0 && platform != &(record[0].0).2
+            && self.donor_platform.as_ref() == Some(platform)
+    }
+
+    fn mask(&self, record: &Vec<(Image, StFile)>, datasets: &[Dataset])
+            -> Result<(Vec<Dataset>, Option<f64>), Box<dyn Error>> {
+        let mut masked_datasets = Vec::new();
+        let mut clear = Vec::new();
+        let mut cloudy = Vec::new();
+        for (i, ((image, file), dataset)) in
+                record.iter().zip(datasets.iter()).enumerate() {
+            // donors are masked with their own quality band encoding
+            let donor = self.is_donor(record, i);
+            let (cloud_mask, mask_band, mask_subdataset) = match donor {
+                true => (self.donor_cloud_mask, None, None),
+                false => (self.cloud_mask,
+                    self.mask_band, self.mask_subdataset),
+            };
+
+            let cloud_mask = match cloud_mask {
+                Some(cloud_mask) => cloud_mask,
+                None => {
+                    masked_datasets.push(crate::raster::copy(dataset)?);
+                    continue;
+                },
+            };
+
+            // open the image holding the mask band
+            let mask_dataset = match mask_subdataset {
+                Some(subdataset) if subdataset != file.2 => {
+                    let album = self.album.read().unwrap();
+                    let path = album.get_image_path(false, &image.1,
+                        &image.2, &image.3, subdataset, &image.4)?;
+                    Some(crate::compaction::open(&path)?)
+                },
+                _ => None,
+            };
+
+            let mask_source = mask_dataset.as_ref().unwrap_or(dataset);
+            let band = match mask_band
+                    .or(cloud_mask.find_band(mask_source)) {
+                Some(band) => band,
+                None => return Err(format!("{} band not found for '{}'",
+                    cloud_mask.band_name(), file.0).into()),
+            };
+
+            // write clouds as nodata on an in-memory copy
+            let masked = crate::raster::copy(dataset)?;
+            let classes = cloud_mask.classify_band(mask_source,
+                band, &masked)?;
+            let skip_band = match mask_dataset {
+                Some(_) => None,
+                None => Some(band),
+            };
+            crate::mask::apply(&masked, &classes, skip_band)?;
+            masked_datasets.push(masked);
+
+            // track pixels observed clear or cloudy by any target image,
+            // donors are not yet aligned with the target grid
+            if donor {
+                continue;
+            }
+
+            if clear.is_empty() {
+                clear = vec![false; classes.len()];
+                cloudy = vec![false; classes.len()];
+            }
+
+            for (i, class) in classes.iter().enumerate() {
+                match class {
+                    Some(true) => cloudy[i] = true,
+                    Some(false) => clear[i] = true,
+                    None => {},
+                }
+            }
+        }
+
+        // clouds remain where no image observed the pixel clear
+        let observed = clear.iter().zip(cloudy.iter())
+            .filter(|(a, b)| **a || **b).count();
+        let remaining = clear.iter().zip(cloudy.iter())
+            .filter(|(a, b)| !**a && **b).count();
+        let cloud_coverage = match observed {
+            0 => None,
+            _ => Some(remaining as f64 / observed as f64),
+        };
+
+        Ok((masked_datasets, cloud_coverage))
+    }
 }
 
 #[tonic::async_trait]
@@ -67,29 +243,63 @@ impl Task<Vec<(Image, StFile)>> for FillTask {
                     &image.2, &image.3, files.2, &image.4)?
             };
 
-            if !path.exists() {
+            if !crate::compaction::exists(&path) {
                 return Err(format!("image path '{}' does not exist",
                     path.to_string_lossy()).into());
             }
 
             // open image
-            let dataset = Dataset::open(&path)?;
+            let dataset = crate::compaction::open(&path)?;
             datasets.push(dataset);
         }
 
+        // mask clouds so they are filled from other observations
+        let masked = self.cloud_mask.is_some()
+            || self.donor_cloud_mask.is_some();
+        let mut cloud_coverage = None;
+        if masked {
+            let (masked_datasets, remaining) =
+                self.mask(record, &datasets)?;
+            datasets = masked_datasets;
+            cloud_coverage = remaining;
+        }
+
+        // align donors with the target grid and band ordering
+        if self.donor_platform.is_some() {
+            let band_map: Vec<usize> = match self.band_map.is_empty() {
+                true => (1..crate::raster::band_count(&datasets[0]) + 1)
+                    .collect(),
+                false => self.band_map.clone(),
+            };
+
+            for i in 1..datasets.len() {
+                if self.is_donor(record, i) {
+                    let aligned = crate::raster::align(&datasets[i],
+                        &datasets[0], &self.resampling)?;
+                    datasets[i] =
+                        crate::raster::select_bands(&aligned, &band_map)?;
+                }
+            }
+        }
+
         // perform fill
-        let mut dataset = st_image::fill(&datasets)?;
+        let mut dataset = self.strategy.fill(record, &datasets)?;
         let pixel_coverage = st_image::get_coverage(&dataset)?;
+        if let Some(cloud_coverage) = cloud_coverage {
+            dataset.set_metadata_item(crate::mask::CLOUD_COVERAGE_ITEM,
+                &cloud_coverage.to_string(), "STIP")?;
+        }
 
-        // check if pixel coverage is more than previous highest
-        let mut max_pixel_coverage = 0f64;
-        for (_, files) in record.iter() {
-            if files.1 > max_pixel_coverage {
-                max_pixel_coverage = files.1;
-            }
+        // check if pixel coverage improves upon the strategy baseline
+        let mut pixel_coverages = Vec::new();
+        for (i, (_, files)) in record.iter().enumerate() {
+            pixel_coverages.push(match masked || self.is_donor(record, i) {
+                true => st_image::get_coverage(&datasets[i])?,
+                false => files.1,
+            });
         }
 
-        if pixel_coverage > max_pixel_coverage {
+        if pixel_coverage > self.strategy.baseline(&pixel_coverages) {
             let image = &record[0].0;
             let file = &record[0].1;
 
@@ -105,24 +315,23 @@ impl Task<Vec<(Image, StFile)>> for FillTask {
         Ok(())
     }
 
+    fn estimate(&self, _record: &Vec<(Image, StFile)>)
+            -> Result<Option<Vec<String>>, Box<dyn Error>> {
+        // each group writes at most one filled image locally
+        Ok(Some(vec!["local".to_string()]))
+    }
+
+    fn log_context(&self, record: &Vec<(Image, StFile)>) -> LogContext {
+        match record.first() {
+            Some((image, _)) => LogContext::new(&self.album, &image.1),
+            None => LogContext::default(),
+        }
+    }
+
     async fn records(&self)
             -> Result<Vec<Vec<(Image, StFile)>>, Box<dyn Error>> {
         // search for source images using Album
-        let mut src_records: Vec<(Image, StFile)> = {
-            let album = self.album.read().unwrap();
-            let images = album.list(&self.end_timestamp,
-                &self.geocode, &None, &None, &self.platform, 
-                self.recurse, &None, &self.start_timestamp)?;
-
-            let mut src_records = Vec::new();
-            for (image, files) in images.into_iter() {
-                for file in files.into_iter() {
-                    src_records.push((image.clone(), file));
-                }
-            }
-
-            src_records
-        };
+        let mut src_records = self.list(&self.platform)?;
 
         // order by platform, geocode, subdataset, timestamp
         src_records.sort_by(|a, b| {
@@ -144,49 +353,46 @@ impl Task<Vec<(Image, StFile)>> for FillTask {
             (a.0).5.cmp(&(b.0).5)
         });
 
-        // initialize fill image vectors
+        // index donor platform images by geocode and subdataset
+        let mut donors: BTreeMap<(String, u8), Vec<(Image, StFile)>> =
+            BTreeMap::new();
+        if let Some(platform) = &self.donor_platform {
+            let mut donor_records = self.list(&Some(platform.clone()))?;
+            donor_records.sort_by_key(|(image, _)| image.5);
+            for (image, file) in donor_records.into_iter() {
+                donors.entry((image.1.clone(), file.2))
+                    .or_insert(Vec::new()).push((image, file));
+            }
+        }
+
+        // group observations by platform, geocode, and subdataset
         let mut records: Vec<Vec<(Image, StFile)>> = Vec::new();
         let mut images_buf: Vec<(Image, StFile)> = Vec::new();
-
-        let mut platform = String::new();
-        let mut geocode = String::new();
-        let mut subdataset = 255;
-        let mut timestamp = 0i64;
         for (image, file) in src_records.into_iter() {
-            if image.2 != platform || image.1 != geocode
-                    || file.2 != subdataset
-                    || image.5 - timestamp > self.window_seconds {
-                // process images_buf
-                if images_buf.len() >= 2 {
-                    records.push(images_buf);
-                    images_buf = Vec::new();
-                } else {
+            if let Some((buf_image, buf_file)) = images_buf.first() {
+                if image.2 != buf_image.2 || image.1 != buf_image.1
+                        || file.2 != buf_file.2 {
+                    records.append(&mut self.group(&images_buf, &donors));
                     images_buf.clear();
                 }
-
-                // reset geocode and timestamp
-                platform = image.2.clone();
-                geocode = image.1.clone();
-                subdataset = file.2;
-                timestamp = image.5;
             }
 
             images_buf.push((image, file));
         }
- 
-        if images_buf.len() >= 2 {
-            records.push(images_buf);
-        }
 
-        // filter out vectors where full pixel coverage images exist
+        records.append(&mut self.group(&images_buf, &donors));
+
+        // filter out records which already hold full pixel coverage,
+        // unless clouds may still leave gaps in those images
         let records: Vec<Vec<(Image, StFile)>> = records.into_iter()
             .filter(|x| {
-                let mut valid = true;
-                for (_, file) in x.iter() {
-                    valid = valid && file.1 != 1f64;
+                if self.cloud_mask.is_some() {
+                    return true;
                 }
 
-                valid
+                let pixel_coverages: Vec<f64> =
+                    x.iter().map(|(_, file)| file.1).collect();
+                self.strategy.baseline(&pixel_coverages) != 1f64
             }).collect();
 
         Ok(records)
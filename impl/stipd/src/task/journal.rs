@@ -0,0 +1,708 @@
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use num_derive::FromPrimitive;
+use num_traits::FromPrimitive;
+use prost::Message;
+use protobuf::{ImageCoalesceRequest, ImageFillRequest, ImageFormat as ProtoImageFormat, ImageSplitRequest, ImageStoreRequest};
+use swarm::prelude::Dht;
+
+use crate::album::{Album, AlbumManager};
+use crate::fill::DEFAULT_FILL_STRATEGY;
+use crate::mask::CloudMask;
+use crate::task::{RetryPolicy, Task, TaskHandle, TaskManager};
+use crate::task::coalesce::CoalesceTask;
+use crate::task::fill::FillTask;
+use crate::task::open::OpenTask;
+use crate::task::split::SplitTask;
+use crate::task::store::{ImageFormat, StoreEarthExplorerTask, StorePair};
+
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fs::{File, OpenOptions};
+use std::io::{BufReader, BufWriter, ErrorKind, Read, Write};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+
+const OPEN_POLL_DURATION: Duration = Duration::from_millis(500);
+
+// albums whose images are being loaded by open_album, other callers wait
+//   rather than processing against a partially loaded index
+static LOADING: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+#[derive(Clone, Debug)]
+pub enum TaskDefinition {
+    Coalesce(ImageCoalesceRequest),
+    Fill(ImageFillRequest),
+    Split(ImageSplitRequest),
+    Store(ImageStoreRequest),
+}
+
+impl TaskDefinition {
+    fn decode(definition_type: u8, buf: &[u8])
+            -> Result<TaskDefinition, Box<dyn Error>> {
+        match definition_type {
+            0 => Ok(TaskDefinition::Coalesce(
+                ImageCoalesceRequest::decode(buf)?)),
+            1 => Ok(TaskDefinition::Fill(ImageFillRequest::decode(buf)?)),
+            2 => Ok(TaskDefinition::Split(ImageSplitRequest::decode(buf)?)),
+            3 => Ok(TaskDefinition::Store(ImageStoreRequest::decode(buf)?)),
+            _ => Err(format!("unknown task definition type {}",
+                definition_type).into()),
+        }
+    }
+
+    fn encode(&self) -> Result<(u8, Vec<u8>), Box<dyn Error>> {
+        let mut buf = Vec::new();
+        let definition_type = match self {
+            TaskDefinition::Coalesce(request) => {
+                request.encode(&mut buf)?;
+                0
+            },
+            TaskDefinition::Fill(request) => {
+                request.encode(&mut buf)?;
+                1
+            },
+            TaskDefinition::Split(request) => {
+                request.encode(&mut buf)?;
+                2
+            },
+            TaskDefinition::Store(request) => {
+                request.encode(&mut buf)?;
+                3
+            },
+        };
+
+        Ok((definition_type, buf))
+    }
+
+    pub fn read<T: Read>(reader: &mut T)
+            -> Result<TaskDefinition, Box<dyn Error>> {
+        let definition_type = reader.read_u8()?;
+        let len = reader.read_u32::<BigEndian>()?;
+        let mut buf = vec![0u8; len as usize];
+        reader.read_exact(&mut buf)?;
+
+        TaskDefinition::decode(definition_type, &buf)
+    }
+
+    pub fn write<T: Write>(&self, writer: &mut T)
+            -> Result<(), Box<dyn Error>> {
+        let (definition_type, buf) = self.encode()?;
+        writer.write_u8(definition_type)?;
+        writer.write_u32::<BigEndian>(buf.len() as u32)?;
+        writer.write_all(&buf)?;
+        Ok(())
+    }
+
+    pub fn get_album(&self) -> &str {
+        match self {
+            TaskDefinition::Coalesce(request) => &request.album,
+            TaskDefinition::Fill(request) => &request.album,
+            TaskDefinition::Split(request) => &request.album,
+            TaskDefinition::Store(request) => &request.album,
+        }
+    }
+
+    pub fn get_priority(&self) -> u32 {
+        let priority = match self {
+            TaskDefinition::Coalesce(request) => request.priority,
+            TaskDefinition::Fill(request) => request.priority,
+            TaskDefinition::Split(request) => request.priority,
+            TaskDefinition::Store(request) => request.priority,
+        };
+
+        priority.unwrap_or(0)
+    }
+
+    pub fn get_tenant(&self) -> &Option<String> {
+        match self {
+            TaskDefinition::Coalesce(request) => &request.tenant,
+            TaskDefinition::Fill(request) => &request.tenant,
+            TaskDefinition::Split(request) => &request.tenant,
+            TaskDefinition::Store(request) => &request.tenant,
+        }
+    }
+
+    pub fn get_thread_count(&self) -> u8 {
+        let thread_count = match self {
+            TaskDefinition::Coalesce(request) => request.thread_count,
+            TaskDefinition::Fill(request) => request.thread_count,
+            TaskDefinition::Split(request) => request.thread_count,
+            TaskDefinition::Store(request) => request.thread_count,
+        };
+
+        thread_count as u8
+    }
+
+    pub fn get_type(&self) -> &'static str {
+        match self {
+            TaskDefinition::Coalesce(_) => "coalesce",
+            TaskDefinition::Fill(_) => "fill",
+            TaskDefinition::Split(_) => "split",
+            TaskDefinition::Store(_) => "store",
+        }
+    }
+
+    pub fn is_dry_run(&self) -> bool {
+        let dry_run = match self {
+            TaskDefinition::Coalesce(_) => None,
+            TaskDefinition::Fill(request) => request.dry_run,
+            TaskDefinition::Split(request) => request.dry_run,
+            TaskDefinition::Store(request) => request.dry_run,
+        };
+
+        dry_run.unwrap_or(false)
+    }
+
+    pub fn requires_index(&self) -> bool {
+        match self {
+            TaskDefinition::Store(_) => false,
+            _ => true,
+        }
+    }
+
+    pub fn start(&self, album_manager: &Arc<RwLock<AlbumManager>>,
+            dht: &Arc<Dht>, journal: Option<TaskJournal>)
+            -> Result<TaskHandle, Box<dyn Error>> {
+        // retrieve album
+        let album: Arc<RwLock<Album>> = {
+            let album_manager = album_manager.read().unwrap();
+            match album_manager.get(self.get_album()) {
+                Some(album) => album.clone(),
+                None => return Err(format!("album '{}' does not exist",
+                    self.get_album()).into()),
+            }
+        };
+
+        // initialize and start task
+        let mut task_handle = match self {
+            TaskDefinition::Coalesce(request) => {
+                let filter = &request.filter;
+                let task = Arc::new(CoalesceTask::new(album, dht.clone(),
+                    filter.end_timestamp, filter.geocode.clone(),
+                    filter.max_cloud_coverage, filter.min_pixel_coverage,
+                    filter.platform.clone(), filter.recurse,
+                    filter.source.clone(), request.platform.clone(),
+                    filter.start_timestamp, request.window_seconds));
+
+                task.start(request.thread_count as u8, journal,
+                    RetryPolicy::from(&request.retry_policy))
+            },
+            TaskDefinition::Fill(request) => {
+                let filter = &request.filter;
+                let cloud_mask = request.cloud_mask.as_ref()
+                    .map(|x| x.parse::<CloudMask>()).transpose()?;
+                let strategy = crate::fill::strategy(request.strategy
+                    .as_ref().map(|x| x.as_str())
+                    .unwrap_or(DEFAULT_FILL_STRATEGY))?;
+                let donor_cloud_mask = request.donor_cloud_mask.as_ref()
+                    .map(|x| x.parse::<CloudMask>()).transpose()?;
+                let task = Arc::new(FillTask::new(album,
+                    request.band_map.iter().map(|x| *x as usize).collect(),
+                    cloud_mask, donor_cloud_mask,
+                    request.donor_platform.clone(), filter.end_timestamp,
+                    filter.geocode.clone(),
+                    request.mask_band.map(|x| x as usize),
+                    request.mask_subdataset.map(|x| x as u8),
+                    filter.platform.clone(), filter.recurse,
+                    request.resampling.clone()
+                        .unwrap_or("bilinear".to_string()),
+                    filter.start_timestamp, strategy,
+                    request.window_seconds)?);
+
+                task.start(request.thread_count as u8, journal,
+                    RetryPolicy::from(&request.retry_policy))
+            },
+            TaskDefinition::Split(request) => {
+                let filter = &request.filter;
+                let precision = album.read().unwrap()
+                    .resolve_precision(request.precision)?;
+                let task = Arc::new(SplitTask::new(album, dht.clone(),
+                    filter.end_timestamp, filter.geocode.clone(),
+                    request.geocode_bound.clone(), filter.platform.clone(),
+                    precision, filter.recurse,
+                    filter.start_timestamp));
+
+                task.start(request.thread_count as u8, journal,
+                    RetryPolicy::from(&request.retry_policy))
+            },
+            TaskDefinition::Store(request) => {
+                let format = match ProtoImageFormat
+                        ::from_i32(request.format) {
+                    Some(ProtoImageFormat::Generic) => ImageFormat::Generic,
+                    Some(ProtoImageFormat::Gridmet) => ImageFormat::GridMET,
+                    Some(ProtoImageFormat::Mcd43a4) => ImageFormat::MCD43A4,
+                    Some(ProtoImageFormat::Mod11a1) => ImageFormat::MOD11A1,
+                    Some(ProtoImageFormat::Mod11a2) => ImageFormat::MOD11A2,
+                    Some(ProtoImageFormat::Naip) => ImageFormat::NAIP,
+                    Some(ProtoImageFormat::Nlcd) => ImageFormat::NLCD,
+                    Some(ProtoImageFormat::Sentinel2) =>
+                        ImageFormat::Sentinel2,
+                    Some(ProtoImageFormat::Vnp21v001) =>
+                        ImageFormat::VNP21V001,
+                    None => return Err(format!("unknown image format {}",
+                        request.format).into()),
+                };
+
+                let pair = match &request.pair {
+                    Some(pair) => Some(StorePair {
+                        album: album_manager.read().unwrap()
+                            .get(&pair.album).cloned()
+                            .ok_or(format!("album '{}' does not exist",
+                                pair.album))?,
+                        format: ImageFormat::from_proto(pair.format)
+                            .ok_or(format!("unknown image format {}",
+                                pair.format))?,
+                        pattern: pair.pattern.clone(),
+                        replacement: pair.replacement.clone(),
+                    }),
+                    None => None,
+                };
+
+                let cloud_mask = request.cloud_mask.as_ref()
+                    .map(|x| x.parse::<CloudMask>()).transpose()?;
+                let precision = album.read().unwrap()
+                    .resolve_precision(request.precision)?;
+                let task = Arc::new(StoreEarthExplorerTask::new(
+                    album, cloud_mask, dht.clone(), format,
+                    request.glob.clone(), pair, precision));
+
+                task.start(request.thread_count as u8, journal,
+                    RetryPolicy::from(&request.retry_policy))
+            },
+        }?;
+
+        task_handle.set_tenant(self.get_tenant().clone());
+        Ok(task_handle)
+    }
+}
+
+#[derive(FromPrimitive)]
+enum JournalOp {
+    Completed = 0,
+    Skipped = 1,
+    Summary = 2,
+    Error = 3,
+    Retried = 4,
+    OmittedErrors = 5,
+}
+
+pub struct TaskJournal {
+    attempts: HashMap<String, u32>,
+    completed_count: u32,
+    errors: Vec<(String, String)>,
+    file: Mutex<File>,
+    omitted_errors: u32,
+    path: PathBuf,
+    processed: HashSet<String>,
+    skipped_count: u32,
+    summary: Option<(u32, u32, u32, u64)>,
+}
+
+impl TaskJournal {
+    pub fn create(directory: &PathBuf, task_id: u64,
+            definition: &TaskDefinition)
+            -> Result<TaskJournal, Box<dyn Error>> {
+        // write definition file
+        let mut path = directory.clone();
+        path.push(task_id.to_string());
+        path.set_extension("task");
+
+        let mut file = File::create(&path)?;
+        definition.write(&mut file)?;
+
+        // initialize journal file
+        path.set_extension("journal");
+        let file = File::create(&path)?;
+
+        Ok(TaskJournal {
+            attempts: HashMap::new(),
+            completed_count: 0,
+            errors: Vec::new(),
+            file: Mutex::new(file),
+            omitted_errors: 0,
+            path: path,
+            processed: HashSet::new(),
+            skipped_count: 0,
+            summary: None,
+        })
+    }
+
+    pub fn open(path: &PathBuf)
+            -> Result<(TaskDefinition, TaskJournal), Box<dyn Error>> {
+        // read definition file
+        let mut file = File::open(path)?;
+        let definition = TaskDefinition::read(&mut file)?;
+
+        // read journal entries
+        let journal_path = path.with_extension("journal");
+        let mut reader = BufReader::new(File::open(&journal_path)?);
+
+        let mut attempts = HashMap::new();
+        let mut completed_count = 0;
+        let mut errors = Vec::new();
+        let mut omitted_errors = 0;
+        let mut processed = HashSet::new();
+        let mut skipped_count = 0;
+        let mut summary = None;
+        loop {
+            let op_type = match reader.read_u8() {
+                Ok(op_type) => op_type,
+                Err(ref e) if e.kind() == ErrorKind::UnexpectedEof => break,
+                Err(e) => return Err(Box::new(e)),
+            };
+
+            match FromPrimitive::from_u8(op_type) {
+                Some(JournalOp::Completed) | Some(JournalOp::Skipped) => {
+                    // a truncated trailing entry is ignored
+                    let key = match read_key(&mut reader) {
+                        Ok(key) => key,
+                        Err(_) => break,
+                    };
+
+                    match op_type == JournalOp::Completed as u8 {
+                        true => completed_count += 1,
+                        false => skipped_count += 1,
+                    }
+
+                    processed.insert(key);
+                },
+                Some(JournalOp::Summary) => {
+                    summary = Some((reader.read_u32::<BigEndian>()?,
+                        reader.read_u32::<BigEndian>()?,
+                        reader.read_u32::<BigEndian>()?,
+                        reader.read_u64::<BigEndian>()?));
+                },
+                Some(JournalOp::Retried) => {
+                    // a truncated trailing entry is ignored
+                    match read_key(&mut reader).and_then(|key|
+                            Ok((key, reader.read_u32::<BigEndian>()?))) {
+                        Ok((key, attempt)) => {
+                            attempts.insert(key, attempt);
+                        },
+                        Err(_) => break,
+                    }
+                },
+                Some(JournalOp::Error) => {
+                    // a truncated trailing entry is ignored
+                    match read_key(&mut reader)
+                            .and_then(|key| Ok((key, read_key(&mut reader)?))) {
+                        Ok(error) if errors.len() < super::MAX_ERRORS =>
+                            errors.push(error),
+                        Ok(_) => omitted_errors += 1,
+                        Err(_) => break,
+                    }
+                },
+                Some(JournalOp::OmittedErrors) => {
+                    omitted_errors += reader.read_u32::<BigEndian>()?;
+                },
+                None => return Err(format!(
+                    "unknown journal operation {}", op_type).into()),
+            }
+        }
+
+        // open journal for appending
+        let file = OpenOptions::new().append(true).open(&journal_path)?;
+
+        Ok((definition, TaskJournal {
+            attempts: attempts,
+            completed_count: completed_count,
+            errors: errors,
+            file: Mutex::new(file),
+            omitted_errors: omitted_errors,
+            path: journal_path,
+            processed: processed,
+            skipped_count: skipped_count,
+            summary: summary,
+        }))
+    }
+
+    // failed attempts of a record journaled before a restart
+    pub fn attempts(&self, key: &str) -> u32 {
+        *self.attempts.get(key).unwrap_or(&0)
+    }
+
+    pub fn contains(&self, key: &str) -> bool {
+        self.processed.contains(key)
+    }
+
+    pub fn completed_count(&self) -> u32 {
+        self.completed_count
+    }
+
+    pub fn errors(&self) -> &Vec<(String, String)> {
+        &self.errors
+    }
+
+    pub fn finish(&self, completed_count: u32, skipped_count: u32,
+            total_count: u32, end_time: u64, errors: &[(String, String)],
+            omitted_errors: u32) -> Result<(), Box<dyn Error>> {
+        // compact journal to a summary entry followed by retained record
+        //   errors and the count of those omitted
+        let tmp_path = self.path.with_extension("tmp");
+        {
+            let mut file = BufWriter::new(File::create(&tmp_path)?);
+            file.write_u8(JournalOp::Summary as u8)?;
+            file.write_u32::<BigEndian>(completed_count)?;
+            file.write_u32::<BigEndian>(skipped_count)?;
+            file.write_u32::<BigEndian>(total_count)?;
+            file.write_u64::<BigEndian>(end_time)?;
+
+            for (key, message) in errors.iter() {
+                write_error(&mut file, key, message)?;
+            }
+
+            if omitted_errors != 0 {
+                file.write_u8(JournalOp::OmittedErrors as u8)?;
+                file.write_u32::<BigEndian>(omitted_errors)?;
+            }
+
+            file.into_inner()?.sync_all()?;
+        }
+
+        let mut file = self.file.lock().unwrap();
+        std::fs::rename(&tmp_path, &self.path)?;
+        *file = OpenOptions::new().append(true).open(&self.path)?;
+        Ok(())
+    }
+
+    pub fn omitted_errors(&self) -> u32 {
+        self.omitted_errors
+    }
+
+    pub fn record(&self, key: &str, error: &Option<String>)
+            -> Result<(), Box<dyn Error>> {
+        let mut buf = Vec::new();
+        match error {
+            None => buf.write_u8(JournalOp::Completed as u8)?,
+            Some(_) => buf.write_u8(JournalOp::Skipped as u8)?,
+        }
+
+        buf.write_u32::<BigEndian>(key.len() as u32)?;
+        buf.write_all(key.as_bytes())?;
+
+        if let Some(message) = error {
+            write_error(&mut buf, key, message)?;
+        }
+
+        let mut file = self.file.lock().unwrap();
+        file.write_all(&buf)?;
+        Ok(())
+    }
+
+    pub fn record_retry(&self, key: &str, attempt: u32)
+            -> Result<(), Box<dyn Error>> {
+        let mut buf = Vec::new();
+        buf.write_u8(JournalOp::Retried as u8)?;
+        buf.write_u32::<BigEndian>(key.len() as u32)?;
+        buf.write_all(key.as_bytes())?;
+        buf.write_u32::<BigEndian>(attempt)?;
+
+        let mut file = self.file.lock().unwrap();
+        file.write_all(&buf)?;
+        Ok(())
+    }
+
+    pub fn remove(&self) -> Result<(), Box<dyn Error>> {
+        std::fs::remove_file(&self.path)?;
+        std::fs::remove_file(&self.path.with_extension("task"))?;
+        Ok(())
+    }
+
+    pub fn skipped_count(&self) -> u32 {
+        self.skipped_count
+    }
+
+    pub fn summary(&self) -> &Option<(u32, u32, u32, u64)> {
+        &self.summary
+    }
+}
+
+fn read_key<T: Read>(reader: &mut T) -> Result<String, Box<dyn Error>> {
+    let len = reader.read_u32::<BigEndian>()?;
+    let mut buf = vec![0u8; len as usize];
+    reader.read_exact(&mut buf)?;
+    Ok(String::from_utf8(buf)?)
+}
+
+fn write_error<T: Write>(writer: &mut T, key: &str, message: &str)
+        -> Result<(), Box<dyn Error>> {
+    writer.write_u8(JournalOp::Error as u8)?;
+    writer.write_u32::<BigEndian>(key.len() as u32)?;
+    writer.write_all(key.as_bytes())?;
+    writer.write_u32::<BigEndian>(message.len() as u32)?;
+    writer.write_all(message.as_bytes())?;
+    Ok(())
+}
+
+pub fn recover(album_manager: Arc<RwLock<AlbumManager>>, dht: Arc<Dht>,
+        task_manager: Arc<RwLock<TaskManager>>, load_thread_count: u8)
+        -> Result<(), Box<dyn Error>> {
+    let (journals, pipelines) = {
+        let task_manager = task_manager.read().unwrap();
+        (task_manager.journals()?, task_manager.pipelines()?)
+    };
+
+    let mut unfinished = Vec::new();
+    for (task_id, definition, journal) in journals {
+        match journal.summary() {
+            Some((completed_count, skipped_count, total_count, end_time)) => {
+                // register finished task
+                let task_handle = TaskHandle::finished(*completed_count,
+                    *skipped_count, *total_count, *end_time,
+                    journal.errors().clone(), journal.omitted_errors());
+
+                let mut task_manager = task_manager.write().unwrap();
+                task_manager.register(task_handle,
+                    Some(task_id), definition.get_priority())?;
+            },
+            None => unfinished.push((task_id, definition, journal)),
+        }
+    }
+
+    if unfinished.is_empty() && pipelines.is_empty() {
+        return Ok(());
+    }
+
+    // resume unfinished tasks after album indices are loaded
+    std::thread::spawn(move || {
+        for (task_id, definition, journal) in unfinished {
+            info!("resuming task [id={}, album={}]",
+                task_id, definition.get_album());
+
+            let path = journal.path.with_extension("task");
+            if definition.requires_index() {
+                if let Err(e) = open_album(&album_manager,
+                        definition.get_album(), load_thread_count) {
+                    warn!("failed to open album '{}' for task {}: {}",
+                        definition.get_album(), task_id, e);
+                    abandon(&task_manager, task_id, &path, e.to_string());
+                    continue;
+                }
+            }
+
+            let task_handle = match definition.start(&album_manager,
+                    &dht, Some(journal)) {
+                Ok(task_handle) => task_handle,
+                Err(e) => {
+                    warn!("failed to resume task {}: {}", task_id, e);
+                    abandon(&task_manager, task_id, &path, e.to_string());
+                    continue;
+                },
+            };
+
+            let mut task_manager = task_manager.write().unwrap();
+            if let Err(e) = task_manager.register(task_handle,
+                    Some(task_id), definition.get_priority()) {
+                warn!("failed to register task {}: {}", task_id, e);
+            }
+        }
+
+        // resume pipelines once their stages are registered
+        for pipeline in pipelines {
+            info!("resuming pipeline [id={}]", pipeline.get_id());
+            let (pipeline_id, priority) =
+                (pipeline.get_id(), pipeline.get_priority());
+            let task_handle = pipeline.start(album_manager.clone(),
+                dht.clone(), task_manager.clone());
+
+            let mut task_manager = task_manager.write().unwrap();
+            if let Err(e) = task_manager.register(task_handle,
+                    Some(pipeline_id), priority) {
+                warn!("failed to register pipeline {}: {}", pipeline_id, e);
+            }
+        }
+    });
+
+    Ok(())
+}
+
+// finish the journal of a task which failed to resume, so its files are
+//   retained and evicted with other finished tasks rather than resumed
+//   on every restart
+fn abandon(task_manager: &Arc<RwLock<TaskManager>>, task_id: u64,
+        path: &PathBuf, error: String) {
+    let (definition, journal) = match TaskJournal::open(path) {
+        Ok(journal) => journal,
+        Err(e) => {
+            warn!("failed to reopen task journal '{:?}': {}", path, e);
+            return;
+        },
+    };
+
+    let mut errors = journal.errors().clone();
+    let mut omitted_errors = journal.omitted_errors();
+    match errors.len() < super::MAX_ERRORS {
+        true => errors.push(("resume".to_string(), error)),
+        false => omitted_errors += 1,
+    }
+
+    let (completed_count, skipped_count) =
+        (journal.completed_count(), journal.skipped_count());
+    let end_time = super::now();
+    if let Err(e) = journal.finish(completed_count, skipped_count,
+            completed_count + skipped_count, end_time, &errors,
+            omitted_errors) {
+        warn!("failed to finish task journal '{:?}': {}", path, e);
+    }
+
+    let task_handle = TaskHandle::finished(completed_count, skipped_count,
+        completed_count + skipped_count, end_time, errors, omitted_errors);
+
+    let mut task_manager = task_manager.write().unwrap();
+    if let Err(e) = task_manager.register(task_handle,
+            Some(task_id), definition.get_priority()) {
+        warn!("failed to register task {}: {}", task_id, e);
+    }
+}
+
+pub fn open_album(album_manager: &Arc<RwLock<AlbumManager>>, id: &str,
+        thread_count: u8) -> Result<(), Box<dyn Error>> {
+    // retrieve album
+    let album = {
+        let album_manager = album_manager.read().unwrap();
+        match album_manager.get(id) {
+            Some(album) => album.clone(),
+            None => return Err(format!("album '{}' does not exist",
+                id).into()),
+        }
+    };
+
+    // check if album is already open, waiting for other callers loading
+    //   the album to finish
+    loop {
+        let mut loading = LOADING.lock().unwrap();
+        if loading.iter().any(|x| x == id) {
+            drop(loading);
+            std::thread::sleep(OPEN_POLL_DURATION);
+            continue;
+        }
+
+        let mut album = album.write().unwrap();
+        if album.get_index().is_some() {
+            return Ok(());
+        }
+
+        album.open(true)?;
+        loading.push(id.to_string());
+        break;
+    }
+
+    let result = load_album(album, thread_count);
+    LOADING.lock().unwrap().retain(|x| x != id);
+    result
+}
+
+// load album images and wait for completion
+fn load_album(album: Arc<RwLock<Album>>, thread_count: u8)
+        -> Result<(), Box<dyn Error>> {
+    let task = Arc::new(OpenTask::new(album, thread_count));
+    let task_handle =
+        task.start(thread_count, None, RetryPolicy::default())?;
+    task_handle.dequeue();
+    while task_handle.running() {
+        std::thread::sleep(OPEN_POLL_DURATION);
+    }
+
+    Ok(())
+}
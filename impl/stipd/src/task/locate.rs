@@ -0,0 +1,160 @@
+use swarm::prelude::Dht;
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::error::Error;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+
+thread_local! {
+    static LOCATE_CACHE: RefCell<Option<Arc<LocateCache>>> =
+        RefCell::new(None);
+}
+
+// node addresses and sorted ring tokens compiled from dht membership
+struct Ring {
+    addrs: HashMap<u32, SocketAddr>,
+    tokens: Vec<(u64, u32)>,
+}
+
+impl Ring {
+    fn compile(dht: &Dht) -> Result<Ring, Box<dyn Error>> {
+        // compile node addresses and advertised ring tokens
+        let mut addrs = HashMap::new();
+        let mut tokens = Vec::new();
+        for node in dht.nodes() {
+            addrs.insert(node.get_id(), SocketAddr::new(
+                node.get_ip_address().clone(),
+                node.get_metadata("xfer_port").unwrap().parse::<u16>()?));
+
+            if let Some(node_tokens) = node.get_metadata("tokens") {
+                for token in node_tokens.split(',')
+                        .filter(|x| !x.is_empty()) {
+                    tokens.push((token.parse::<u64>()?, node.get_id()));
+                }
+            }
+        }
+
+        tokens.sort();
+
+        Ok(Ring {
+            addrs: addrs,
+            tokens: tokens,
+        })
+    }
+
+    fn replicas(&self, dht: &Dht, key: &str)
+            -> Result<Vec<SocketAddr>, Box<dyn Error>> {
+        // compute geocode hash
+        let hash = crate::cluster::hash(key.as_bytes());
+
+        // discover primary hash location
+        let primary_id = match dht.locate(hash) {
+            Some(node) => node.get_id(),
+            None => return Err(format!("no dht node for hash {}",
+                hash).into()),
+        };
+
+        // walk the token ring collecting distinct successor nodes
+        let replication_factor = crate::cluster::replication_factor();
+        let mut candidate_ids = vec![primary_id];
+        let start = self.tokens.iter().position(|(token, _)| *token > hash)
+            .unwrap_or(0);
+        for i in 0..self.tokens.len() {
+            let (_, node_id) = self.tokens[(start + i) % self.tokens.len()];
+            if !candidate_ids.contains(&node_id) {
+                candidate_ids.push(node_id);
+            }
+        }
+
+        // skip decommissioned nodes so their ownership passes to
+        //   successors, retaining later successors as fallbacks for
+        //   full replicas
+        let count = match crate::cluster::placement_fallback() {
+            true => candidate_ids.len(),
+            false => replication_factor,
+        };
+
+        let replica_ids: Vec<u32> = candidate_ids.into_iter()
+            .filter(|x| !crate::cluster::is_decommissioned(*x))
+            .take(count).collect();
+        if replica_ids.is_empty() {
+            return Err(format!("no active dht node for hash {}",
+                hash).into());
+        }
+
+        if replica_ids.len() < replication_factor {
+            debug!("replica set for hash {} smaller than replication factor {}",
+                hash, replication_factor);
+        }
+
+        replica_ids.iter().map(|node_id| self.addrs.get(node_id).cloned()
+                .ok_or(format!("no address for dht node {}", node_id).into()))
+            .collect()
+    }
+}
+
+// replicas resolved by a single task, valid until the ring changes
+pub struct LocateCache {
+    state: Mutex<LocateState>,
+}
+
+struct LocateState {
+    generation: u64,
+    replicas: HashMap<String, Vec<SocketAddr>>,
+    ring: Option<Ring>,
+}
+
+impl LocateCache {
+    pub fn new() -> LocateCache {
+        LocateCache {
+            state: Mutex::new(LocateState {
+                generation: crate::cluster::ring_generation(),
+                replicas: HashMap::new(),
+                ring: None,
+            }),
+        }
+    }
+
+    // resolve dht keys under a single lock acquisition
+    pub fn lookup(&self, dht: &Dht, keys: &[&str])
+            -> Vec<Result<Vec<SocketAddr>, Box<dyn Error>>> {
+        let mut state = self.state.lock().unwrap();
+
+        // discard resolutions made against a previous ring
+        let generation = crate::cluster::ring_generation();
+        if state.generation != generation {
+            state.generation = generation;
+            state.replicas.clear();
+            state.ring = None;
+        }
+
+        let state = &mut *state;
+        keys.iter().map(|key| {
+            if let Some(addrs) = state.replicas.get(*key) {
+                return Ok(addrs.clone());
+            }
+
+            if state.ring.is_none() {
+                state.ring = Some(Ring::compile(dht)?);
+            }
+
+            let addrs = state.ring.as_ref().unwrap().replicas(dht, key)?;
+            state.replicas.insert(key.to_string(), addrs.clone());
+            Ok(addrs)
+        }).collect()
+    }
+}
+
+// resolve lookups made by the current thread through the task cache
+pub fn attach(cache: &Arc<LocateCache>) {
+    LOCATE_CACHE.with(|x| *x.borrow_mut() = Some(cache.clone()));
+}
+
+pub fn attached() -> Option<Arc<LocateCache>> {
+    LOCATE_CACHE.with(|x| x.borrow().clone())
+}
+
+pub fn detach() {
+    LOCATE_CACHE.with(|x| *x.borrow_mut() = None);
+}
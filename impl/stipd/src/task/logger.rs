@@ -0,0 +1,215 @@
+use chrono::Utc;
+use env_logger::Logger;
+use log::{Level, LevelFilter, Log, Metadata, Record, SetLoggerError};
+use serde_json::{Map, Value};
+
+use crate::album::Album;
+
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::io::Write;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex, RwLock};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+
+// lines retained per task, discarding the oldest beyond this count
+const TASK_LOG_CAPACITY: usize = 10000;
+
+static JSON_FORMAT: AtomicBool = AtomicBool::new(false);
+static NODE_ID: RwLock<Option<u32>> = RwLock::new(None);
+
+thread_local! {
+    static LOG_CONTEXT: RefCell<LogContext> =
+        RefCell::new(LogContext::default());
+    static TASK_LOG: RefCell<Option<Arc<TaskLog>>> = RefCell::new(None);
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LogFormat {
+    Json,
+    Text,
+}
+
+impl FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "json" => Ok(LogFormat::Json),
+            "text" => Ok(LogFormat::Text),
+            _ => Err(format!("unknown log format '{}'", s)),
+        }
+    }
+}
+
+// fields attached to json lines logged by the current thread
+#[derive(Clone, Debug, Default)]
+pub struct LogContext {
+    pub album: Option<String>,
+    pub geocode: Option<String>,
+}
+
+impl LogContext {
+    pub fn new(album: &Arc<RwLock<Album>>, geocode: &str) -> LogContext {
+        LogContext {
+            album: Some(album.read().unwrap().get_id().to_string()),
+            geocode: Some(geocode.to_string()),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct TaskLogEntry {
+    pub level: Level,
+    pub message: String,
+    pub timestamp: u64,
+}
+
+// info and higher lines logged by the threads of a single task
+pub struct TaskLog {
+    entries: Mutex<(u64, VecDeque<TaskLogEntry>)>,
+    task_id: AtomicU64,
+}
+
+impl TaskLog {
+    pub fn new() -> TaskLog {
+        TaskLog {
+            entries: Mutex::new((0, VecDeque::new())),
+            task_id: AtomicU64::new(0),
+        }
+    }
+
+    // identifier assigned when the task is registered, 0 until then
+    pub fn set_task_id(&self, task_id: u64) {
+        self.task_id.store(task_id, Ordering::SeqCst);
+    }
+
+    pub fn task_id(&self) -> u64 {
+        self.task_id.load(Ordering::SeqCst)
+    }
+
+    // retrieve entries starting at the sequence number along with the
+    //   sequence number following them
+    pub fn since(&self, sequence: u64) -> (Vec<TaskLogEntry>, u64) {
+        let entries = self.entries.lock().unwrap();
+        let skip = sequence.saturating_sub(entries.0) as usize;
+
+        (entries.1.iter().skip(skip).cloned().collect(),
+            entries.0 + entries.1.len() as u64)
+    }
+
+    fn push(&self, level: Level, message: String) {
+        let mut entries = self.entries.lock().unwrap();
+        if entries.1.len() >= TASK_LOG_CAPACITY {
+            entries.1.pop_front();
+            entries.0 += 1;
+        }
+
+        entries.1.push_back(TaskLogEntry {
+            level: level,
+            message: message,
+            timestamp: super::now(),
+        });
+    }
+}
+
+// capture lines logged by the current thread in the task log
+pub fn attach(log: &Arc<TaskLog>) {
+    TASK_LOG.with(|x| *x.borrow_mut() = Some(log.clone()));
+}
+
+// stop capturing lines logged by the current thread
+pub fn detach() {
+    TASK_LOG.with(|x| *x.borrow_mut() = None);
+    LOG_CONTEXT.with(|x| *x.borrow_mut() = LogContext::default());
+}
+
+pub fn configure(format: LogFormat, node_id: u32) {
+    JSON_FORMAT.store(format == LogFormat::Json, Ordering::SeqCst);
+    *NODE_ID.write().unwrap() = Some(node_id);
+}
+
+// replace the fields attached to lines logged by the current thread
+pub fn set_context(context: LogContext) {
+    LOG_CONTEXT.with(|x| *x.borrow_mut() = context);
+}
+
+// initialize env_logger, additionally capturing lines logged by tasks
+pub fn init() -> Result<(), SetLoggerError> {
+    let logger = env_logger::Builder::from_default_env().build();
+    let max_level = std::cmp::max(logger.filter(), LevelFilter::Info);
+
+    log::set_boxed_logger(Box::new(TaskLogger {
+        logger: logger,
+    }))?;
+    log::set_max_level(max_level);
+    Ok(())
+}
+
+struct TaskLogger {
+    logger: Logger,
+}
+
+impl Log for TaskLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= Level::Info || self.logger.enabled(metadata)
+    }
+
+    fn log(&self, record: &Record) {
+        match JSON_FORMAT.load(Ordering::SeqCst) {
+            true if self.logger.matches(record) => write_json(record),
+            true => {},
+            false => self.logger.log(record),
+        }
+
+        if record.level() <= Level::Info {
+            let _ = TASK_LOG.try_with(|x| {
+                if let Some(log) = x.borrow().as_ref() {
+                    log.push(record.level(), record.args().to_string());
+                }
+            });
+        }
+    }
+
+    fn flush(&self) {
+        self.logger.flush();
+    }
+}
+
+fn write_json(record: &Record) {
+    let mut line = Map::new();
+    line.insert("timestamp".to_string(),
+        Value::from(Utc::now().to_rfc3339()));
+    line.insert("level".to_string(), Value::from(record.level().as_str()));
+    line.insert("target".to_string(), Value::from(record.target()));
+    line.insert("message".to_string(),
+        Value::from(record.args().to_string()));
+
+    if let Some(node_id) = *NODE_ID.read().unwrap() {
+        line.insert("node_id".to_string(), Value::from(node_id));
+    }
+
+    // attach task and record context of the logging thread
+    let _ = TASK_LOG.try_with(|x| {
+        let task_id = x.borrow().as_ref()
+            .map(|log| log.task_id.load(Ordering::SeqCst));
+        if let Some(task_id) = task_id.filter(|x| *x != 0) {
+            line.insert("task_id".to_string(),
+                Value::from(task_id.to_string()));
+        }
+    });
+
+    let _ = LOG_CONTEXT.try_with(|x| {
+        let context = x.borrow();
+        if let Some(album) = &context.album {
+            line.insert("album".to_string(), Value::from(album.as_str()));
+        }
+
+        if let Some(geocode) = &context.geocode {
+            line.insert("geocode".to_string(),
+                Value::from(geocode.as_str()));
+        }
+    });
+
+    let _ = writeln!(std::io::stderr(), "{}", Value::Object(line));
+}
@@ -1,55 +1,326 @@
+use chrono::Utc;
+use protobuf::ImagePipelineRequest;
 use swarm::prelude::Dht;
 use tokio::runtime::Builder;
 
-use std::collections::hash_map::DefaultHasher;
-use std::collections::HashMap;
+use crate::accounting::UsageLedger;
+use crate::album::AlbumManager;
+use crate::metrics::Counter;
+use crate::telemetry::{Span, SpanContext, SpanKind};
+
+use std::collections::{HashMap, VecDeque};
 use std::collections::hash_map::Iter;
 use std::error::Error;
-use std::hash::Hasher;
 use std::net::SocketAddr;
-use std::sync::Arc;
-use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::panic::AssertUnwindSafe;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, RwLock};
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, AtomicUsize, Ordering};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
+pub mod archive;
+pub mod bandmath;
+pub mod calc;
+pub mod change;
 pub mod coalesce;
+pub mod collect;
+pub mod compact;
+pub mod composite;
+pub mod copy;
+pub mod cron;
+pub mod delete;
+pub mod drain;
+pub mod execute;
+pub mod export;
 pub mod fill;
+pub mod journal;
+pub mod locate;
+pub mod logger;
+pub mod overview;
+pub mod pipeline;
+pub mod pool;
+pub mod preload;
+pub mod reproject;
 pub mod split;
+pub mod statistics;
 pub mod store;
+pub mod update;
 pub mod open;
 
+use cron::RecurringTask;
+use journal::{TaskDefinition, TaskJournal};
+use locate::LocateCache;
+use logger::{LogContext, TaskLog};
+use pipeline::Pipeline;
+
+// record errors retained per task, later errors are only counted
+const MAX_ERRORS: usize = 1000;
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(600);
+const PAUSE_POLL_DURATION: Duration = Duration::from_millis(250);
+
+#[derive(Debug, Default)]
+pub struct DryRunReport {
+    pub destinations: HashMap<String, u32>,
+    pub error_count: u32,
+    pub record_count: u32,
+    pub tile_count: Option<u32>,
+}
+
+type RetryFn = Box<dyn Fn(u8) -> Result<TaskHandle, Box<dyn Error>>
+    + Send + Sync>;
+
+#[derive(Clone, Copy, Debug)]
+pub struct RetryPolicy {
+    pub backoff: Duration,
+    pub max_attempts: u32,
+}
+
+impl RetryPolicy {
+    pub fn new(backoff_ms: u64, max_attempts: u32) -> RetryPolicy {
+        RetryPolicy {
+            backoff: Duration::from_millis(backoff_ms),
+            max_attempts: std::cmp::max(max_attempts, 1),
+        }
+    }
+
+    fn delay(&self, attempt: u32) -> Duration {
+        // exponential backoff doubling after each failed attempt, capped
+        //   so large configured backoffs can not overflow
+        let exponent = std::cmp::min(attempt.saturating_sub(1), 16);
+        match self.backoff.checked_mul(1 << exponent) {
+            Some(delay) => std::cmp::min(delay, MAX_RETRY_DELAY),
+            None => MAX_RETRY_DELAY,
+        }
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> RetryPolicy {
+        RetryPolicy::new(0, 1)
+    }
+}
+
+impl From<&Option<protobuf::RetryPolicy>> for RetryPolicy {
+    fn from(retry_policy: &Option<protobuf::RetryPolicy>) -> RetryPolicy {
+        match retry_policy {
+            Some(retry_policy) => RetryPolicy::new(retry_policy.backoff_ms,
+                retry_policy.max_attempts),
+            None => RetryPolicy::default(),
+        }
+    }
+}
+
 pub struct TaskHandle {
+    cancelled: Arc<AtomicBool>,
     completed_count: Arc<AtomicU32>,
+    composite: bool,
+    compute_ms: Arc<AtomicU64>,
+    end_time: Arc<AtomicU64>,
+    errors: Arc<Mutex<Vec<(String, String)>>>,
+    ingested_bytes: Arc<AtomicU64>,
+    log: Arc<TaskLog>,
+    omitted_errors: Arc<AtomicU32>,
+    paused: Arc<AtomicBool>,
+    priority: u32,
+    queued: Arc<AtomicBool>,
+    requested_share: usize,
+    retry: Option<RetryFn>,
     running: Arc<AtomicBool>,
+    share: Arc<AtomicUsize>,
     skipped_count: Arc<AtomicU32>,
+    tenant: Option<String>,
     total_count: Arc<AtomicU32>,
 }
 
 impl TaskHandle {
+    pub fn finished(completed_count: u32, skipped_count: u32,
+            total_count: u32, end_time: u64, errors: Vec<(String, String)>,
+            omitted_errors: u32) -> TaskHandle {
+        TaskHandle {
+            cancelled: Arc::new(AtomicBool::new(false)),
+            completed_count: Arc::new(AtomicU32::new(completed_count)),
+            composite: false,
+            compute_ms: Arc::new(AtomicU64::new(0)),
+            end_time: Arc::new(AtomicU64::new(end_time)),
+            errors: Arc::new(Mutex::new(errors)),
+            ingested_bytes: Arc::new(AtomicU64::new(0)),
+            log: Arc::new(TaskLog::new()),
+            omitted_errors: Arc::new(AtomicU32::new(omitted_errors)),
+            paused: Arc::new(AtomicBool::new(false)),
+            priority: 0,
+            queued: Arc::new(AtomicBool::new(false)),
+            requested_share: 0,
+            retry: None,
+            running: Arc::new(AtomicBool::new(false)),
+            share: Arc::new(AtomicUsize::new(0)),
+            skipped_count: Arc::new(AtomicU32::new(skipped_count)),
+            tenant: None,
+            total_count: Arc::new(AtomicU32::new(total_count)),
+        }
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    pub fn cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+
     pub fn completed_count(&self) -> u32 {
         self.completed_count.load(Ordering::SeqCst)
     }
 
+    pub fn compute_ms(&self) -> u64 {
+        self.compute_ms.load(Ordering::SeqCst)
+    }
+
+    pub fn dequeue(&self) {
+        self.queued.store(false, Ordering::SeqCst);
+    }
+
+    pub fn end_time(&self) -> u64 {
+        self.end_time.load(Ordering::SeqCst)
+    }
+
+    pub fn errors(&self) -> Vec<(String, String)> {
+        self.errors.lock().unwrap().clone()
+    }
+
+    pub fn ingested_bytes(&self) -> u64 {
+        self.ingested_bytes.load(Ordering::SeqCst)
+    }
+
+    pub fn log(&self) -> Arc<TaskLog> {
+        self.log.clone()
+    }
+
+    // record errors counted beyond those retained
+    pub fn omitted_errors(&self) -> u32 {
+        self.omitted_errors.load(Ordering::SeqCst)
+    }
+
+    pub fn pause(&self) {
+        self.paused.store(true, Ordering::SeqCst);
+    }
+
+    pub fn paused(&self) -> bool {
+        self.paused.load(Ordering::SeqCst)
+    }
+
+    pub fn priority(&self) -> u32 {
+        self.priority
+    }
+
+    pub fn queued(&self) -> bool {
+        self.queued.load(Ordering::SeqCst)
+    }
+
+    pub fn resume(&self) {
+        self.paused.store(false, Ordering::SeqCst);
+    }
+
+    pub fn retry(&self, thread_count: u8)
+            -> Result<TaskHandle, Box<dyn Error>> {
+        if self.running() {
+            return Err("unable to retry a running task".into());
+        }
+
+        match &self.retry {
+            Some(retry) => retry(thread_count),
+            None => Err("task does not support retries".into()),
+        }
+    }
+
     pub fn running(&self) -> bool {
         self.running.load(Ordering::SeqCst)
     }
 
+    pub fn set_tenant(&mut self, tenant: Option<String>) {
+        self.tenant = tenant;
+    }
+
+    // worker pool threads granted to the task
+    pub fn share(&self) -> usize {
+        self.share.load(Ordering::SeqCst)
+    }
+
     pub fn skipped_count(&self) -> u32 {
         self.skipped_count.load(Ordering::SeqCst)
     }
 
+    pub fn tenant(&self) -> &Option<String> {
+        &self.tenant
+    }
+
     pub fn total_count(&self) -> u32 {
         self.total_count.load(Ordering::SeqCst)
     }
 }
 
 pub struct TaskManager {
+    accounted: HashMap<u64, (u64, u64)>,
+    directory: PathBuf,
+    executors: HashMap<String, String>,
+    ledger: Arc<UsageLedger>,
+    max_running_count: usize,
+    recurring_tasks: HashMap<u64, RecurringTask>,
+    retention_count: usize,
+    sequence: u64,
+    sequences: HashMap<u64, u64>,
     tasks: HashMap<u64, TaskHandle>,
 }
 
 impl TaskManager {
-    pub fn new() -> TaskManager {
-        TaskManager {
-            tasks: HashMap::new(),
+    pub fn new(directory: PathBuf, ledger: Arc<UsageLedger>,
+            max_running_count: usize, retention_count: usize)
+            -> Result<TaskManager, Box<dyn Error>> {
+        // create task journal directory
+        std::fs::create_dir_all(&directory)?;
+
+        // load persisted recurring tasks
+        let mut recurring_tasks = HashMap::new();
+        for entry in std::fs::read_dir(&directory)? {
+            let path = entry?.path();
+            if path.extension().and_then(|x| x.to_str())
+                    != Some("recurring") {
+                continue;
+            }
+
+            let recurring_id = match path.file_stem()
+                    .and_then(|x| x.to_str())
+                    .and_then(|x| x.parse::<u64>().ok()) {
+                Some(recurring_id) => recurring_id,
+                None => {
+                    warn!("skipping invalid recurring task file '{:?}'",
+                        path);
+                    continue;
+                },
+            };
+
+            match RecurringTask::open(&path) {
+                Ok(recurring_task) => {
+                    info!("loaded recurring task [id={}, schedule={}]",
+                        recurring_id, recurring_task.get_expression());
+                    recurring_tasks.insert(recurring_id, recurring_task);
+                },
+                Err(e) => warn!("failed to open recurring task '{:?}': {}",
+                    path, e),
+            }
         }
+
+        Ok(TaskManager {
+            accounted: HashMap::new(),
+            directory: directory,
+            executors: HashMap::new(),
+            ledger: ledger,
+            max_running_count: max_running_count,
+            recurring_tasks: recurring_tasks,
+            retention_count: retention_count,
+            sequence: 0,
+            sequences: HashMap::new(),
+            tasks: HashMap::new(),
+        })
     }
 
     pub fn clear(&mut self) -> Result<(), Box<dyn Error>> {
@@ -61,31 +332,379 @@ impl TaskManager {
 
         // remove complete ids
         for complete_id in complete_ids.iter() {
+            self.charge(complete_id);
+            self.tasks.remove(complete_id);
+            self.accounted.remove(complete_id);
+            self.sequences.remove(complete_id);
+            self.remove_journal(complete_id);
+        }
+
+        Ok(())
+    }
+
+    pub fn evict(&mut self) {
+        // retrieve complete ids ordered by end time
+        let mut complete_ids: Vec<(u64, u64)> = self.tasks.iter()
+            .filter(|(_, task_handle)| !task_handle.running())
+            .map(|(id, task_handle)| (task_handle.end_time(), *id))
+            .collect();
+
+        if complete_ids.len() <= self.retention_count {
+            return;
+        }
+
+        complete_ids.sort();
+
+        // remove oldest complete ids
+        let evict_count = complete_ids.len() - self.retention_count;
+        for (_, complete_id) in complete_ids.iter().take(evict_count) {
+            debug!("evicting task [id={}]", complete_id);
+            self.charge(complete_id);
             self.tasks.remove(complete_id);
+            self.accounted.remove(complete_id);
+            self.sequences.remove(complete_id);
+            self.remove_journal(complete_id);
+        }
+    }
+
+    pub fn account(&mut self) {
+        // charge usage accrued since the last accounting pass
+        let task_ids: Vec<u64> = self.tasks.keys().cloned().collect();
+        for task_id in task_ids.iter() {
+            self.charge(task_id);
         }
 
+        if let Err(e) = self.ledger.flush() {
+            warn!("failed to flush usage ledger: {}", e);
+        }
+    }
+
+    pub fn add_recurring(&mut self, recurring_id: u64,
+            definition: TaskDefinition, expression: &str)
+            -> Result<(), Box<dyn Error>> {
+        let recurring_task = RecurringTask::create(&self.directory,
+            recurring_id, definition, expression)?;
+
+        info!("registering recurring task [id={}, schedule={}, next_timestamp={}]",
+            recurring_id, expression, recurring_task.get_next_timestamp());
+        self.recurring_tasks.insert(recurring_id, recurring_task);
         Ok(())
     }
 
+    pub fn create_pipeline(&self, pipeline_id: u64,
+            request: &ImagePipelineRequest)
+            -> Result<Pipeline, Box<dyn Error>> {
+        Pipeline::create(&self.directory, pipeline_id, request)
+    }
+
+    pub fn create_journal(&self, task_id: u64, definition: &TaskDefinition)
+            -> Result<TaskJournal, Box<dyn Error>> {
+        TaskJournal::create(&self.directory, task_id, definition)
+    }
+
+    pub fn get_executor(&self, name: &str) -> Option<&String> {
+        self.executors.get(name)
+    }
+
+    pub fn get(&self, task_id: &u64) -> Option<&TaskHandle> {
+        self.tasks.get(task_id)
+    }
+
     pub fn iter(&self) -> Iter<u64, TaskHandle> {
         self.tasks.iter()
     }
 
-    pub fn register(&mut self, task_handle: TaskHandle,
-            task_id: Option<u64>) -> Result<u64, Box<dyn Error>> {
+    pub fn iter_recurring(&self) -> Iter<u64, RecurringTask> {
+        self.recurring_tasks.iter()
+    }
+
+    pub fn journals(&self)
+            -> Result<Vec<(u64, TaskDefinition, TaskJournal)>, Box<dyn Error>> {
+        let mut journals = Vec::new();
+        for entry in std::fs::read_dir(&self.directory)? {
+            let path = entry?.path();
+            if path.extension().and_then(|x| x.to_str()) != Some("task") {
+                continue;
+            }
+
+            // parse task id from filename
+            let task_id = match path.file_stem()
+                    .and_then(|x| x.to_str())
+                    .and_then(|x| x.parse::<u64>().ok()) {
+                Some(task_id) => task_id,
+                None => {
+                    warn!("skipping invalid task file '{:?}'", path);
+                    continue;
+                },
+            };
+
+            // unreadable journals are never resumed, remove their files
+            match TaskJournal::open(&path) {
+                Ok((definition, journal)) =>
+                    journals.push((task_id, definition, journal)),
+                Err(e) => {
+                    warn!("removing unreadable task journal '{:?}': {}",
+                        path, e);
+                    self.remove_journal(&task_id);
+                },
+            }
+        }
+
+        Ok(journals)
+    }
+
+    pub fn pipelines(&self) -> Result<Vec<Pipeline>, Box<dyn Error>> {
+        let mut pipelines = Vec::new();
+        for entry in std::fs::read_dir(&self.directory)? {
+            let path = entry?.path();
+            if path.extension().and_then(|x| x.to_str())
+                    != Some("pipeline") {
+                continue;
+            }
+
+            match Pipeline::open(&path) {
+                Ok(pipeline) => pipelines.push(pipeline),
+                Err(e) => warn!("failed to open pipeline '{:?}': {}",
+                    path, e),
+            }
+        }
+
+        Ok(pipelines)
+    }
+
+    pub fn register(&mut self, mut task_handle: TaskHandle,
+            task_id: Option<u64>, priority: u32)
+            -> Result<u64, Box<dyn Error>> {
         // initialize task id
         let task_id = match task_id {
             Some(task_id) => task_id,
             None => rand::random::<u64>(),
         };
 
+        // charge usage of any TaskHandle being replaced
+        self.charge(&task_id);
+        self.accounted.remove(&task_id);
+
         // add TaskHandle to map
-        info!("registering task [id={}]", task_id);
+        info!("registering task [id={}, priority={}]", task_id, priority);
+        task_handle.log.set_task_id(task_id);
+        task_handle.priority = priority;
         self.tasks.insert(task_id, task_handle);
 
+        self.sequence += 1;
+        self.sequences.insert(task_id, self.sequence);
+
+        // evict completed tasks exceeding retention
+        self.evict();
+
+        // start queued tasks within concurrency limits
+        self.schedule();
+
         // return task id
         Ok(task_id)
     }
+
+    pub fn register_executor(&mut self, name: String, address: String)
+            -> Option<String> {
+        info!("registering task executor [name={}, address={}]",
+            name, address);
+        self.executors.insert(name, address)
+    }
+
+    pub fn remove_recurring(&mut self, recurring_id: &u64) -> bool {
+        if self.recurring_tasks.remove(recurring_id).is_none() {
+            return false;
+        }
+
+        let mut path = self.directory.clone();
+        path.push(recurring_id.to_string());
+        path.set_extension("recurring");
+
+        if let Err(e) = std::fs::remove_file(&path) {
+            warn!("failed to remove recurring task file '{:?}': {}",
+                path, e);
+        }
+
+        true
+    }
+
+    // retrieve recurring tasks which are due, advancing each to its next
+    //   occurrence so an occurrence is started at most once
+    fn due_recurring(&mut self) -> Vec<(u64, TaskDefinition)> {
+        let now = Utc::now().timestamp();
+        let mut due = Vec::new();
+        for (recurring_id, recurring_task) in self.recurring_tasks.iter_mut()
+                .filter(|(_, x)| x.get_next_timestamp() <= now) {
+            if let Err(e) = recurring_task.advance() {
+                warn!("failed to persist recurring task [id={}]: {}",
+                    recurring_id, e);
+            }
+
+            due.push((*recurring_id,
+                recurring_task.get_definition().clone()));
+        }
+
+        due
+    }
+
+    fn recurring_started(&mut self, recurring_id: &u64, task_id: u64) {
+        // recurring task may have been removed while starting
+        if let Some(recurring_task) =
+                self.recurring_tasks.get_mut(recurring_id) {
+            if let Err(e) = recurring_task.set_last_task_id(task_id) {
+                warn!("failed to persist recurring task [id={}]: {}",
+                    recurring_id, e);
+            }
+        }
+    }
+
+    pub fn schedule(&self) {
+        // count tasks actively processing records
+        let mut running_count = self.tasks.values()
+            .filter(|x| x.running() && !x.queued() && !x.composite
+                && !x.paused() && !x.cancelled())
+            .count();
+
+        // order queued tasks by priority and then submission
+        let mut queued: Vec<(u32, u64, u64)> = self.tasks.iter()
+            .filter(|(_, x)| x.running() && x.queued())
+            .map(|(id, x)| (x.priority(),
+                *self.sequences.get(id).unwrap_or(&0), *id))
+            .collect();
+
+        queued.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+
+        // dequeue tasks until concurrency limit is reached
+        for (priority, _, task_id) in queued {
+            if self.max_running_count != 0
+                    && running_count >= self.max_running_count {
+                break;
+            }
+
+            debug!("scheduling task [id={}, priority={}]",
+                task_id, priority);
+            self.tasks.get(&task_id).unwrap().dequeue();
+            running_count += 1;
+        }
+
+        self.arbitrate();
+    }
+
+    // divide worker pool capacity among tasks processing records, fully
+    //   granting requests below an even share and evenly splitting the
+    //   remaining capacity between larger requests
+    fn arbitrate(&self) {
+        let mut active: Vec<(&u64, &TaskHandle)> = self.tasks.iter()
+            .filter(|(_, x)| x.running() && !x.queued() && !x.composite
+                && !x.paused() && !x.cancelled())
+            .collect();
+
+        active.sort_by_key(|(_, x)| x.requested_share);
+
+        let mut capacity = pool::capacity();
+        let active_count = active.len();
+        for (i, (task_id, task_handle)) in active.into_iter().enumerate() {
+            let even_share = std::cmp::max(capacity / (active_count - i), 1);
+            let share = std::cmp::min(task_handle.requested_share, even_share);
+            capacity = capacity.saturating_sub(share);
+
+            if task_handle.share.swap(share, Ordering::SeqCst) != share {
+                debug!("granting task worker share [id={}, requested={}, share={}]",
+                    task_id, task_handle.requested_share, share);
+            }
+        }
+    }
+
+    fn charge(&mut self, task_id: &u64) {
+        let task_handle = match self.tasks.get(task_id) {
+            Some(task_handle) => task_handle,
+            None => return,
+        };
+
+        // charge counter deltas to the task tenant
+        let (compute_ms, ingested_bytes) =
+            (task_handle.compute_ms(), task_handle.ingested_bytes());
+        let accounted = self.accounted.entry(*task_id).or_insert((0, 0));
+        self.ledger.charge(task_handle.tenant(),
+            ingested_bytes.saturating_sub(accounted.1), 0,
+            compute_ms.saturating_sub(accounted.0));
+
+        *accounted = (compute_ms, ingested_bytes);
+    }
+
+    fn remove_journal(&self, task_id: &u64) {
+        for extension in &["task", "journal"] {
+            let mut path = self.directory.clone();
+            path.push(task_id.to_string());
+            path.set_extension(extension);
+
+            if path.exists() {
+                if let Err(e) = std::fs::remove_file(&path) {
+                    warn!("failed to remove task file '{:?}': {}", path, e);
+                }
+            }
+        }
+    }
+}
+
+// start due recurring tasks, only holding the TaskManager to retrieve and
+//   register tasks so albums may be opened without stalling other requests
+pub fn start_recurring(album_manager: &Arc<RwLock<AlbumManager>>,
+        dht: &Arc<Dht>, task_manager: &Arc<RwLock<TaskManager>>,
+        load_thread_count: u8) {
+    let due = task_manager.write().unwrap().due_recurring();
+    for (recurring_id, definition) in due {
+        // tasks which may open their album start on a separate thread,
+        //   so loading images never stalls the calling scheduler
+        if !definition.requires_index() {
+            start_recurring_task(album_manager, dht, task_manager,
+                load_thread_count, recurring_id, definition);
+            continue;
+        }
+
+        let (album_manager, dht, task_manager) =
+            (album_manager.clone(), dht.clone(), task_manager.clone());
+        std::thread::spawn(move || start_recurring_task(&album_manager,
+            &dht, &task_manager, load_thread_count, recurring_id,
+            definition));
+    }
+}
+
+fn start_recurring_task(album_manager: &Arc<RwLock<AlbumManager>>,
+        dht: &Arc<Dht>, task_manager: &Arc<RwLock<TaskManager>>,
+        load_thread_count: u8, recurring_id: u64,
+        definition: TaskDefinition) {
+    // start a new task instance
+    let task_id = rand::random::<u64>();
+    info!("starting recurring task [id={}, task_id={}]",
+        recurring_id, task_id);
+
+    let result = if definition.requires_index() {
+        journal::open_album(album_manager,
+            definition.get_album(), load_thread_count)
+    } else {
+        Ok(())
+    };
+
+    let result = result
+        .and_then(|_| task_manager.read().unwrap()
+            .create_journal(task_id, &definition))
+        .and_then(|journal| definition.start(album_manager,
+            dht, Some(journal)));
+
+    let mut task_manager = task_manager.write().unwrap();
+    let result = result.and_then(|task_handle| task_manager
+        .register(task_handle, Some(task_id), definition.get_priority()));
+
+    match result {
+        Ok(task_id) => task_manager.recurring_started(&recurring_id,
+            task_id),
+        Err(e) => {
+            warn!("failed to start recurring task [id={}]: {}",
+                recurring_id, e);
+            task_manager.remove_journal(&task_id);
+        },
+    }
 }
 
 #[tonic::async_trait]
@@ -93,137 +712,492 @@ pub trait Task<T: 'static + std::fmt::Debug + Send + Sync> {
     fn process(&self, record: &T) -> Result<(), Box<dyn Error>>;
     async fn records(&self) -> Result<Vec<T>, Box<dyn Error>>;
 
-    fn start(self: Arc<Self>, thread_count: u8) 
+    // destinations of the tiles a record would write, None if unknown
+    fn estimate(&self, _record: &T)
+            -> Result<Option<Vec<String>>, Box<dyn Error>> {
+        Ok(None)
+    }
+
+    // source bytes ingested by processing a record
+    fn ingested_bytes(&self, _record: &T) -> u64 {
+        0
+    }
+
+    // fields attached to json lines logged while processing a record
+    fn log_context(&self, _record: &T) -> LogContext {
+        LogContext::default()
+    }
+
+    fn start(self: Arc<Self>, thread_count: u8,
+            journal: Option<TaskJournal>, retry_policy: RetryPolicy)
+            -> Result<TaskHandle, Box<dyn Error>>
+            where Self: 'static + Send + Sync {
+        self.execute(thread_count, journal, retry_policy,
+            None, Arc::new(TaskLog::new()))
+    }
+
+    fn execute(self: Arc<Self>, thread_count: u8,
+            journal: Option<TaskJournal>, retry_policy: RetryPolicy,
+            records: Option<Vec<T>>, log: Arc<TaskLog>)
             -> Result<TaskHandle, Box<dyn Error>>
             where Self: 'static + Send + Sync {
-        info!("starting task [thread_count={}]", thread_count);
+        info!("starting task [thread_count={}, max_attempts={}]",
+            thread_count, retry_policy.max_attempts);
             
+        // trace the task as a child of the span starting it
+        let mut task_span = Span::new("task", SpanKind::Internal,
+            SpanContext::current());
+        let task_context = task_span.context();
+
         // initialize instance variables
-        let completed_count = Arc::new(AtomicU32::new(0));
+        let journal = journal.map(|journal| Arc::new(journal));
+        let (prior_completed_count, prior_skipped_count, prior_errors,
+                prior_omitted_errors) = match &journal {
+            Some(journal) => (journal.completed_count(),
+                journal.skipped_count(), journal.errors().clone(),
+                journal.omitted_errors()),
+            None => (0, 0, Vec::new(), 0),
+        };
+
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let completed_count =
+            Arc::new(AtomicU32::new(prior_completed_count));
+        let compute_ms = Arc::new(AtomicU64::new(0));
+        let end_time = Arc::new(AtomicU64::new(0));
+        let errors = Arc::new(Mutex::new(prior_errors));
+        let ingested_bytes = Arc::new(AtomicU64::new(0));
+        let omitted_errors = Arc::new(AtomicU32::new(prior_omitted_errors));
+        let paused = Arc::new(AtomicBool::new(false));
+        let queued = Arc::new(AtomicBool::new(true));
         let running = Arc::new(AtomicBool::new(true));
-        let skipped_count = Arc::new(AtomicU32::new(0));
+        let skipped_count = Arc::new(AtomicU32::new(prior_skipped_count));
         let total_count = Arc::new(AtomicU32::new(0));
+        let failed_records = Arc::new(Mutex::new(Vec::new()));
+        let locate_cache = Arc::new(LocateCache::new());
 
-        // initialize record channel
-        let (sender, receiver) = crossbeam_channel::bounded(256);
-
-        // start worker threads
-        let mut join_handles = Vec::new();
-        for _ in 0..thread_count {
-            let completed_count = completed_count.clone();
-            let skipped_count = skipped_count.clone();
-            let receiver = receiver.clone();
-            let self_clone = self.clone();
-
-            let join_handle = std::thread::spawn(move || {
-                // iterate over records
-                loop {
-                    // fetch next record
-                    let record: T = match receiver.recv() {
-                        Ok(record) => record,
-                        Err(_) => break,
-                    };
+        // the thread count requests a share of the worker pool, which the
+        //   TaskManager rebalances against other running tasks
+        let capacity = pool::capacity();
+        let requested_share = std::cmp::max(thread_count as usize, 1);
+        if requested_share > capacity {
+            info!("limiting task to worker pool capacity [thread_count={}, capacity={}]",
+                thread_count, capacity);
+        }
 
-                    // process record
-                    let result = self_clone.process(&record);
+        let share = Arc::new(AtomicUsize::new(
+            std::cmp::min(requested_share, capacity)));
 
-                    // process result
-                    match result {
-                        Ok(_) => completed_count.fetch_add(1,
-                            Ordering::SeqCst),
-                        Err(e) => {
-                            warn!("skipping record '{:?}': {}",
-                                record, e);
-                            skipped_count.fetch_add(1, Ordering::SeqCst)
-                        },
-                    };
-                }
-            });
+        // initialize retry function over failed records
+        let retry_failed_records = failed_records.clone();
+        let retry_log = log.clone();
+        let retry_self = self.clone();
+        let retry: RetryFn = Box::new(move |thread_count| {
+            let records: Vec<T> = retry_failed_records.lock().unwrap()
+                .drain(..).collect();
+            if records.is_empty() {
+                return Err("task has no failed records to retry".into());
+            }
 
-            join_handles.push(join_handle);
-        }
+            retry_self.clone().execute(thread_count, None,
+                retry_policy, Some(records), retry_log.clone())
+        });
 
         // initialize TaskHandle
         let task_handle = TaskHandle {
-            completed_count: completed_count,
-            skipped_count: skipped_count,
+            cancelled: cancelled.clone(),
+            completed_count: completed_count.clone(),
+            composite: false,
+            compute_ms: compute_ms.clone(),
+            end_time: end_time.clone(),
+            errors: errors.clone(),
+            ingested_bytes: ingested_bytes.clone(),
+            log: log.clone(),
+            omitted_errors: omitted_errors.clone(),
+            paused: paused.clone(),
+            priority: 0,
+            queued: queued.clone(),
+            requested_share: requested_share,
+            retry: Some(retry),
+            skipped_count: skipped_count.clone(),
             running: running.clone(),
+            share: share.clone(),
+            tenant: None,
             total_count: total_count.clone(),
         };
 
         // start management thread
         let _ = std::thread::spawn(move || {
+            logger::attach(&log);
+            locate::attach(&locate_cache);
+
             // compute processing records
             let mut runtime = match Builder::new()
                     .basic_scheduler().enable_all().build() {
                 Ok(runtime) => runtime,
                 Err(e) => {
                     warn!("task failed to initialize runtime: {}", e);
-                    running.store(false, Ordering::SeqCst);
+                    finish(&journal, &completed_count, &end_time,
+                        &errors, &omitted_errors, &running,
+                        &skipped_count, &total_count);
                     return;
                 },
             };
 
-            let records = match runtime.block_on(self.records()) {
+            let records = match records {
+                Some(records) => Ok(records),
+                None => runtime.block_on(
+                    crate::telemetry::instrument(task_context, self.records())),
+            };
+
+            let records = match records {
                 Ok(records) => records,
                 Err(e) => {
                     warn!("task failed to compile records: {}", e);
-                    running.store(false, Ordering::SeqCst);
+                    task_span.set_error(&e.to_string());
+                    finish(&journal, &completed_count, &end_time,
+                        &errors, &omitted_errors, &running,
+                        &skipped_count, &total_count);
                     return;
                 },
             };
 
-            total_count.store(records.len() as u32, Ordering::SeqCst);
+            // filter records processed before a restart
+            let records: Vec<T> = match &journal {
+                Some(journal) => records.into_iter()
+                    .filter(|x| !journal.contains(&format!("{:?}", x)))
+                    .collect(),
+                None => records,
+            };
 
-            // add items to pipeline
-            debug!("registering records [count={}]", records.len());
-            for record in records {
-                if let Err(e) = sender.send(record) {
-                    warn!("task failed to send record: {}", e);
-                    break;
+            total_count.store(records.len() as u32 + prior_completed_count
+                + prior_skipped_count, Ordering::SeqCst);
+
+            // resume attempts journaled before a restart
+            let mut pending: VecDeque<(T, u32)> = records.into_iter()
+                .map(|record| {
+                    let attempt = match &journal {
+                        Some(journal) => std::cmp::min(journal
+                            .attempts(&format!("{:?}", record)) + 1,
+                            retry_policy.max_attempts),
+                        None => 1,
+                    };
+
+                    (record, attempt)
+                }).collect();
+
+            // process records on the shared worker pool, limiting the
+            //   jobs in flight to the task share. failed attempts are
+            //   requeued here once their backoff elapses, rather than
+            //   holding a worker while waiting
+            debug!("registering records [count={}]", pending.len());
+            let (retry_sender, retry_receiver) =
+                crossbeam_channel::unbounded();
+            let mut delayed: Vec<(Instant, T, u32)> = Vec::new();
+            let mut throttle = pool::Throttle::new(share);
+            loop {
+                // promote retries whose backoff has elapsed
+                delayed.extend(retry_receiver.try_iter());
+                let now = Instant::now();
+                let mut i = 0;
+                while i < delayed.len() {
+                    if delayed[i].0 <= now {
+                        let (_, record, attempt) = delayed.swap_remove(i);
+                        pending.push_back((record, attempt));
+                    } else {
+                        i += 1;
+                    }
                 }
-            }
- 
-            // drop sender to signal worker threads
-            drop(sender);
-
-            // join worker threads
-            for join_handle in join_handles {
-                if let Err(e) = join_handle.join() {
-                    warn!("task failed to join worker: {:?}", e);
+
+                let (record, attempt) = match pending.pop_front() {
+                    Some(pending) => pending,
+                    None if cancelled.load(Ordering::SeqCst) => break,
+                    None => {
+                        // wait for the next retry or job completion, jobs
+                        //   in flight may requeue their records
+                        match delayed.iter().map(|x| x.0).min() {
+                            Some(due) => std::thread::sleep(std::cmp::min(
+                                due.saturating_duration_since(now),
+                                PAUSE_POLL_DURATION)),
+                            None if throttle.is_idle()
+                                && retry_receiver.is_empty() => break,
+                            None if throttle.is_idle() => {},
+                            None => throttle.complete(),
+                        }
+
+                        continue;
+                    },
+                };
+
+                throttle.wait();
+
+                // wait while task is queued or paused
+                while (queued.load(Ordering::SeqCst)
+                            || paused.load(Ordering::SeqCst))
+                        && !cancelled.load(Ordering::SeqCst) {
+                    std::thread::sleep(PAUSE_POLL_DURATION);
                 }
+
+                // check if task has been cancelled
+                if cancelled.load(Ordering::SeqCst) {
+                    info!("task cancelled, halting record registration");
+                    break;
+                }
+
+                let cancelled = cancelled.clone();
+                let completed_count = completed_count.clone();
+                let compute_ms = compute_ms.clone();
+                let errors = errors.clone();
+                let ingested_bytes = ingested_bytes.clone();
+                let omitted_errors = omitted_errors.clone();
+                let skipped_count = skipped_count.clone();
+                let failed_records = failed_records.clone();
+                let journal = journal.clone();
+                let locate_cache = locate_cache.clone();
+                let log = log.clone();
+                let retry_sender = retry_sender.clone();
+                let self_clone = self.clone();
+
+                throttle.spawn(move || {
+                    // records queued before a cancellation are dropped
+                    if cancelled.load(Ordering::SeqCst) {
+                        return;
+                    }
+
+                    logger::attach(&log);
+                    locate::attach(&locate_cache);
+                    pool::attach_cancel(&cancelled);
+
+                    let log_context = self_clone.log_context(&record);
+                    let mut span = Span::new("task.process",
+                        SpanKind::Internal, task_context).enter();
+                    span.set_attribute("task_id", &log.task_id().to_string());
+                    if let Some(album) = &log_context.album {
+                        span.set_attribute("album", album);
+                    }
+
+                    if let Some(geocode) = &log_context.geocode {
+                        span.set_attribute("geocode", geocode);
+                    }
+
+                    logger::set_context(log_context);
+
+                    // process record attempt, panics fail the record
+                    //   rather than leaving it neither completed nor skipped
+                    let key = format!("{:?}", record);
+                    let start = Instant::now();
+                    let (result, panicked) = match std::panic::catch_unwind(
+                            AssertUnwindSafe(|| self_clone.process(&record))) {
+                        Ok(result) => (result, false),
+                        Err(e) => (Err(format!("record panicked: {}",
+                            pool::panic_message(&e)).into()), true),
+                    };
+
+                    compute_ms.fetch_add(start.elapsed().as_millis() as u64,
+                        Ordering::SeqCst);
+
+                    if let Err(e) = &result {
+                        span.set_error(&e.to_string());
+                    }
+
+                    drop(span);
+
+                    // requeue failed records according to policy
+                    if let Err(e) = &result {
+                        if attempt < retry_policy.max_attempts && !panicked
+                                && !cancelled.load(Ordering::SeqCst) {
+                            debug!("retrying record '{}' [attempt={}]: {}",
+                                key, attempt, e);
+
+                            if let Some(journal) = &journal {
+                                if let Err(e) =
+                                        journal.record_retry(&key, attempt) {
+                                    warn!("failed to journal retry of record '{}': {}",
+                                        key, e);
+                                }
+                            }
+
+                            let due = Instant::now()
+                                + retry_policy.delay(attempt);
+                            let _ = retry_sender.send(
+                                (due, record, attempt + 1));
+                            return;
+                        }
+                    }
+
+                    // process result
+                    let error = match &result {
+                        Ok(_) => {
+                            completed_count.fetch_add(1, Ordering::SeqCst);
+                            ingested_bytes.fetch_add(self_clone
+                                .ingested_bytes(&record), Ordering::SeqCst);
+                            None
+                        },
+                        Err(e) => {
+                            warn!("skipping record '{}': {}", key, e);
+                            skipped_count.fetch_add(1, Ordering::SeqCst);
+                            push_error(&errors, &omitted_errors,
+                                key.clone(), e.to_string());
+                            Some(e.to_string())
+                        },
+                    };
+
+                    // record result in journal
+                    if let Some(journal) = &journal {
+                        if let Err(e) = journal.record(&key, &error) {
+                            warn!("failed to journal record '{:?}': {}",
+                                record, e);
+                        }
+                    }
+
+                    // retain failed records for later retries, bounded
+                    //   like record errors
+                    let mut failed_records = failed_records.lock().unwrap();
+                    if result.is_err() && failed_records.len() < MAX_ERRORS {
+                        failed_records.push(record);
+                    }
+                });
             }
 
+            // wait for queued records to complete
+            throttle.join();
+
+            task_span.set_attribute("task_id", &log.task_id().to_string());
+
             // complete TaskHandle
-            running.store(false, Ordering::SeqCst);
+            finish(&journal, &completed_count, &end_time,
+                &errors, &omitted_errors, &running,
+                &skipped_count, &total_count);
         });
 
         Ok(task_handle)
     }
 }
 
+pub async fn dry_run<T, U>(task: &U) -> Result<DryRunReport, Box<dyn Error>>
+        where T: 'static + std::fmt::Debug + Send + Sync,
+            U: Task<T> + Sync {
+    let records = task.records().await?;
+    let mut report = DryRunReport::default();
+    report.record_count = records.len() as u32;
+
+    // estimate output tiles without processing records
+    for record in records.iter() {
+        match task.estimate(record) {
+            Ok(Some(destinations)) => {
+                report.tile_count = Some(report.tile_count.unwrap_or(0)
+                    + destinations.len() as u32);
+                for destination in destinations {
+                    *report.destinations.entry(destination)
+                        .or_insert(0) += 1;
+                }
+            },
+            Ok(None) => {},
+            Err(e) => {
+                debug!("failed to estimate record '{:?}': {}", record, e);
+                report.error_count += 1;
+            },
+        }
+    }
+
+    Ok(report)
+}
+
 fn dht_lookup(dht: &Arc<Dht>, dht_key_length: i8,
-        geocode: &str) -> Result<SocketAddr, Box<dyn Error>> {
-    // compute dht geocode using dht_key_length
-    let geocode = match dht_key_length {
-        0 => geocode,
+        geocode: &str) -> Result<Vec<SocketAddr>, Box<dyn Error>> {
+    match dht_lookups(dht, dht_key_length, &[geocode]).pop() {
+        Some(result) => result,
+        None => Err(format!("no dht lookup result for '{}'", geocode).into()),
+    }
+}
+
+// resolve geocode replicas against a single ring snapshot, cached by the
+//   task processing on this thread when one is attached
+fn dht_lookups(dht: &Arc<Dht>, dht_key_length: i8, geocodes: &[&str])
+        -> Vec<Result<Vec<SocketAddr>, Box<dyn Error>>> {
+    let mut span = crate::telemetry::span("dht.lookup", SpanKind::Internal);
+    match geocodes {
+        [geocode] => span.set_attribute("geocode", geocode),
+        _ => span.set_attribute("count", &geocodes.len().to_string()),
+    }
+
+    let cache = locate::attached()
+        .unwrap_or_else(|| Arc::new(LocateCache::new()));
+
+    // resolve valid dht keys as one batch
+    let keys: Vec<Result<&str, Box<dyn Error>>> = geocodes.iter()
+        .map(|geocode| dht_key(dht_key_length, geocode)).collect();
+    let valid_keys: Vec<&str> = keys.iter()
+        .filter_map(|x| x.as_ref().ok().cloned()).collect();
+    let mut replicas = cache.lookup(dht, &valid_keys).into_iter();
+
+    let results: Vec<Result<Vec<SocketAddr>, Box<dyn Error>>> =
+        keys.into_iter().map(|key| match key {
+            Ok(_) => replicas.next()
+                .unwrap_or(Err("missing dht lookup result".into())),
+            Err(e) => Err(e),
+        }).collect();
+
+    for result in results.iter() {
+        if let Err(e) = result {
+            crate::metrics::add(Counter::DhtLookupFailures, 1);
+            span.set_error(&e.to_string());
+        }
+    }
+
+    results
+}
+
+// compute dht geocode using dht_key_length
+fn dht_key(dht_key_length: i8, geocode: &str)
+        -> Result<&str, Box<dyn Error>> {
+    match dht_key_length {
+        0 => Ok(geocode),
         x if x > 0 && x < geocode.len() as i8 =>
-            &geocode[x as usize..],
+            Ok(&geocode[x as usize..]),
         x if x < 0 && x > (-1 * geocode.len() as i8) =>
-            &geocode[..(geocode.len() as i8 + x) as usize],
-        _ => return Err(format!("dht key length '{}' invalid for '{}'",
+            Ok(&geocode[..(geocode.len() as i8 + x) as usize]),
+        _ => Err(format!("dht key length '{}' invalid for '{}'",
                 dht_key_length, geocode).into()),
-    };
+    }
+}
 
-    // compute geocode hash
-    let mut hasher = DefaultHasher::new();
-    hasher.write(geocode.as_bytes());
-    let hash = hasher.finish();
+fn finish(journal: &Option<Arc<TaskJournal>>, completed_count: &AtomicU32,
+        end_time: &AtomicU64, errors: &Mutex<Vec<(String, String)>>,
+        omitted_errors: &AtomicU32, running: &AtomicBool,
+        skipped_count: &AtomicU32, total_count: &AtomicU32) {
+    end_time.store(now(), Ordering::SeqCst);
+
+    // compact journal into task summary
+    if let Some(journal) = journal {
+        if let Err(e) = journal.finish(
+                completed_count.load(Ordering::SeqCst),
+                skipped_count.load(Ordering::SeqCst),
+                total_count.load(Ordering::SeqCst),
+                end_time.load(Ordering::SeqCst),
+                &errors.lock().unwrap(),
+                omitted_errors.load(Ordering::SeqCst)) {
+            warn!("failed to finish task journal: {}", e);
+        }
+    }
+
+    running.store(false, Ordering::SeqCst);
+}
+
+// retain the first record errors of a task and count the rest
+fn push_error(errors: &Mutex<Vec<(String, String)>>,
+        omitted_errors: &AtomicU32, key: String, message: String) {
+    let mut errors = errors.lock().unwrap();
+    match errors.len() < MAX_ERRORS {
+        true => errors.push((key, message)),
+        false => {
+            omitted_errors.fetch_add(1, Ordering::SeqCst);
+        },
+    }
+}
 
-    // discover hash location
-    match dht.locate(hash) {
-        Some(node) => Ok(SocketAddr::new(node.get_ip_address().clone(),
-            node.get_metadata("xfer_port").unwrap().parse::<u16>()?)),
-        None => Err(format!("no dht node for hash {}", hash).into()),
+fn now() -> u64 {
+    match SystemTime::now().duration_since(UNIX_EPOCH) {
+        Ok(duration) => duration.as_secs(),
+        Err(_) => 0,
     }
 }
@@ -1,25 +1,34 @@
-use gdal::{Dataset, Metadata};
+use gdal::Metadata;
 
 use crate::album::Album;
 use crate::task::Task;
+use crate::task::pool::Throttle;
 
+use std::collections::HashMap;
 use std::error::Error;
 use std::path::PathBuf;
 use std::sync::{Arc, RwLock};
+use std::sync::atomic::AtomicUsize;
+use std::time::{Duration, Instant, SystemTime};
+
+const SCAN_PROGRESS_INTERVAL: Duration = Duration::from_secs(10);
 
 pub struct OpenTask {
     album: Arc<RwLock<Album>>,
+    thread_count: u8,
 }
 
 impl OpenTask {
-    pub fn new(album: Arc<RwLock<Album>>) -> OpenTask {
+    pub fn new(album: Arc<RwLock<Album>>, thread_count: u8) -> OpenTask {
         {
             let album = album.read().unwrap();
-            info!("initailizing open task [album={}]", album.get_id());
+            info!("initailizing open task [album={}, thread_count={}]",
+                album.get_id(), thread_count);
         }
 
         OpenTask {
             album: album,
+            thread_count: thread_count,
         }
     }
 }
@@ -27,7 +36,7 @@ impl OpenTask {
 #[tonic::async_trait]
 impl Task<PathBuf> for OpenTask {
     fn process(&self, record: &PathBuf) -> Result<(), Box<dyn Error>> {
-        let dataset = Dataset::open(&record)?;
+        let dataset = crate::compaction::open(&record)?;
 
         let cloud_coverage =
                 match dataset.metadata_item("CLOUD_COVERAGE", "STIP") {
@@ -48,17 +57,131 @@ impl Task<PathBuf> for OpenTask {
             .ok_or("image tile metadata not found")?;
         let timestamp = dataset.metadata_item("TIMESTAMP", "STIP")
             .ok_or("image timestamp metadata not found")?.parse::<i64>()?;
+        let misaligned = dataset.metadata_item("MISALIGNED", "STIP");
+
+        // validate metadata against image path
+        let path = {
+            let album = self.album.read().unwrap();
+            album.get_image_path(false, &geocode,
+                &platform, &source, subdataset, &tile)?
+        };
+
+        if &path != record {
+            return Err(format!("image metadata discrepancy: expected path '{}'",
+                path.to_string_lossy()).into());
+        }
 
         let mut album = self.album.write().unwrap();
-        album.load(cloud_coverage, &geocode, pixel_coverage,
-            &platform, &source, subdataset, &tile, timestamp)?;
+        album.load(cloud_coverage, &geocode, misaligned.as_deref(),
+            pixel_coverage, &platform, &source, subdataset, &tile,
+            timestamp)?;
 
         Ok(())
     }
 
     async fn records(&self) -> Result<Vec<PathBuf>, Box<dyn Error>> {
-        // search for paths using Album
-        let album = self.album.read().unwrap();
-        album.get_paths()
+        let (album_id, shards, snapshot_time) = {
+            let album = self.album.read().unwrap();
+            (album.get_id().to_string(), album.get_shards()?,
+                album.get_snapshot_time())
+        };
+
+        // scan shards on the shared worker pool, limited to the task
+        //   thread count
+        let shard_count = shards.len();
+        let thread_count = std::cmp::max(self.thread_count, 1) as usize;
+        let mut throttle =
+            Throttle::new(Arc::new(AtomicUsize::new(thread_count)));
+        let (sender, receiver) = crossbeam_channel::unbounded();
+        for shard in shards {
+            throttle.wait();
+
+            let sender = sender.clone();
+            throttle.spawn(move || {
+                let result = crate::album::get_shard_paths(
+                        &shard, snapshot_time)
+                    .map_err(|e| format!("failed to scan {:?}: {}",
+                        shard, e));
+                let _ = sender.send(result);
+            });
+        }
+
+        drop(sender);
+
+        // collect paths, periodically reporting scan progress
+        let mut paths = Vec::new();
+        let mut scanned = 0;
+        let mut reported = Instant::now();
+        for result in receiver.iter() {
+            paths.extend(result?);
+            scanned += 1;
+
+            if reported.elapsed() >= SCAN_PROGRESS_INTERVAL
+                    || scanned == shard_count {
+                info!("scanning album directories [album={}, scanned={}/{}, images={}]",
+                    album_id, scanned, shard_count, paths.len());
+                reported = Instant::now();
+            }
+        }
+
+        throttle.join();
+
+        // snapshots retain images deleted after they were written
+        if let Some(snapshot_time) = snapshot_time {
+            let count = reconcile(&self.album, snapshot_time)?;
+            if count != 0 {
+                info!("removed images deleted since index snapshot [album={}, images={}]",
+                    album_id, count);
+            }
+        }
+
+        Ok(paths)
     }
 }
+
+// remove index entries of deleted images, only checking directories
+//   modified since the snapshot or since removed
+fn reconcile(album: &Arc<RwLock<Album>>, snapshot_time: SystemTime)
+        -> Result<usize, Box<dyn Error>> {
+    let (index, images) = {
+        let album = album.read().unwrap();
+        let index = match album.get_index() {
+            Some(index) => index.clone(),
+            None => return Ok(0),
+        };
+
+        (index, album.list(&None, &None, &None,
+            &None, &None, true, &None, &None)?)
+    };
+
+    let mut modified_directories = HashMap::new();
+    let mut count = 0;
+    for (image, files) in images {
+        for file in files {
+            let path = PathBuf::from(&file.0);
+            let directory = match path.parent() {
+                Some(directory) => directory.to_path_buf(),
+                None => continue,
+            };
+
+            let modified = match modified_directories.get(&directory) {
+                Some(modified) => *modified,
+                None => {
+                    let modified = std::fs::metadata(&directory)
+                        .and_then(|x| x.modified())
+                        .map(|x| x >= snapshot_time)
+                        .unwrap_or(true);
+                    modified_directories.insert(directory, modified);
+                    modified
+                },
+            };
+
+            if modified && !crate::compaction::exists(&path) {
+                index.remove(&image.1, &image.3, file.2, &image.4)?;
+                count += 1;
+            }
+        }
+    }
+
+    Ok(count)
+}
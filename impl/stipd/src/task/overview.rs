@@ -0,0 +1,280 @@
+use protobuf::{ImageListRequest, Filter, ImageManagementClient};
+use swarm::prelude::Dht;
+use tonic::Request;
+
+use crate::{Image, OVERVIEW_SOURCE_PREFIX};
+use crate::album::Album;
+use crate::raster::RESAMPLING_METHODS;
+use crate::task::Task;
+use crate::task::logger::LogContext;
+use crate::transfer::{LatencyTracker, ReadPrefetcher, ReadRequest};
+
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::net::SocketAddr;
+use std::path::Path;
+use std::sync::{Arc, RwLock};
+
+// concurrent child tile reads per overview
+const OVERVIEW_READ_WINDOW: usize = 8;
+
+// overview image, subdataset, and replicas of each child tile
+type OverviewRecord = (Image, u8, Vec<Vec<(SocketAddr, String)>>);
+
+pub struct OverviewTask {
+    album: Arc<RwLock<Album>>,
+    dht: Arc<Dht>,
+    end_timestamp: Option<i64>,
+    geocode: Option<String>,
+    max_cloud_coverage: Option<f64>,
+    platform: Option<String>,
+    precision: usize,
+    resampling: String,
+    source: Option<String>,
+    start_timestamp: Option<i64>,
+    tenant: Option<String>,
+    tracker: Arc<LatencyTracker>,
+}
+
+impl OverviewTask {
+    pub fn new(album: Arc<RwLock<Album>>, dht: Arc<Dht>,
+            end_timestamp: Option<i64>, geocode: Option<String>,
+            max_cloud_coverage: Option<f64>, platform: Option<String>,
+            precision: usize, resampling: String, source: Option<String>,
+            start_timestamp: Option<i64>, tenant: Option<String>,
+            tracker: Arc<LatencyTracker>)
+            -> Result<OverviewTask, Box<dyn Error>> {
+        {
+            let album = album.read().unwrap();
+            info!("initailizing overview task [album={}, end_timestamp={:?}, geocode={:?}, max_cloud_coverage={:?}, platform={:?}, precision={}, resampling={}, source={:?}, start_timestamp={:?}]",
+                album.get_id(), end_timestamp, geocode, max_cloud_coverage,
+                platform, precision, resampling, source, start_timestamp);
+        }
+
+        if precision == 0 {
+            return Err("overview precision must be positive".into());
+        }
+
+        if let Some(geocode) = &geocode {
+            if geocode.len() > precision {
+                return Err(format!("geocode '{}' is finer than precision {}",
+                    geocode, precision).into());
+            }
+        }
+
+        if !RESAMPLING_METHODS.contains(&resampling.as_str()) {
+            return Err(format!("unsupported resampling method '{}'",
+                resampling).into());
+        }
+
+        Ok(OverviewTask {
+            album: album,
+            dht: dht,
+            end_timestamp: end_timestamp,
+            geocode: geocode,
+            max_cloud_coverage: max_cloud_coverage,
+            platform: platform,
+            precision: precision,
+            resampling: resampling,
+            source: source,
+            start_timestamp: start_timestamp,
+            tenant: tenant,
+            tracker: tracker,
+        })
+    }
+}
+
+#[tonic::async_trait]
+impl Task<OverviewRecord> for OverviewTask {
+    fn process(&self, record: &OverviewRecord)
+            -> Result<(), Box<dyn Error>> {
+        let (image, subdataset, replicas) = record;
+
+        // retrieve album metadata
+        let (album_id, dht_key_length, geocode) = {
+            let album = self.album.read().unwrap();
+            (album.get_id().to_string(), album.get_dht_key_length(),
+                album.get_geocode().clone())
+        };
+
+        // read child tiles from replicas, skipping tiles which fail to read
+        let requests = replicas.iter().map(|x| ReadRequest {
+            replicas: x.clone(),
+            subgeocode: None,
+            tenant: self.tenant.clone(),
+            transform: None,
+        }).collect();
+
+        let mut datasets = Vec::new();
+        for result in ReadPrefetcher::new(requests,
+                OVERVIEW_READ_WINDOW, self.tracker.clone()) {
+            match result {
+                Ok(dataset) => datasets.push(dataset),
+                Err(e) => warn!("failed to read overview tile: {}", e),
+            }
+        }
+
+        if datasets.is_empty() {
+            return Err(format!("no child tiles of '{}' could be read",
+                image.1).into());
+        }
+
+        // downsample so the overview keeps the pixel width of one child
+        let merged = crate::raster::merge(&datasets)?;
+        let epsg_code = geocode.get_epsg_code();
+        let (x_interval, y_interval) =
+            geocode.get_intervals(self.precision);
+        let resolution = x_interval / datasets[0].raster_size().0 as f64;
+
+        let downsampled = crate::raster::warp(&merged, epsg_code,
+            Some(resolution), &self.resampling)?;
+
+        // clip downsampled image to the overview geocode
+        let (min_cx, max_cx, min_cy, max_cy) =
+            st_image::coordinate::get_bounds(&downsampled, epsg_code)?;
+        let window_bounds = st_image::coordinate::get_windows(min_cx,
+            max_cx, min_cy, max_cy, x_interval, y_interval);
+
+        for (min_cx, max_cx, min_cy, max_cy) in window_bounds {
+            let window_geocode = geocode.encode((min_cx + max_cx) / 2.0,
+                (min_cy + max_cy) / 2.0, self.precision)?;
+            if window_geocode != image.1 {
+                continue;
+            }
+
+            let dataset = match st_image::transform::split(&downsampled,
+                    min_cx, max_cx, min_cy, max_cy, epsg_code)? {
+                Some(dataset) => dataset,
+                None => return Ok(()),
+            };
+
+            let pixel_coverage = st_image::get_coverage(&dataset)?;
+            if pixel_coverage == 0f64 {
+                return Ok(());
+            }
+
+            // send overview to replica hosts of its geocode
+            let addrs = crate::task::dht_lookup(
+                &self.dht, dht_key_length, &image.1)?;
+            return crate::transfer::send_image(&addrs, &album_id,
+                &dataset, &image.1, pixel_coverage, &image.2, &image.3,
+                *subdataset, &image.4, image.5);
+        }
+
+        Ok(())
+    }
+
+    fn estimate(&self, record: &OverviewRecord)
+            -> Result<Option<Vec<String>>, Box<dyn Error>> {
+        let dht_key_length = {
+            let album = self.album.read().unwrap();
+            album.get_dht_key_length()
+        };
+
+        let addrs = crate::task::dht_lookup(
+            &self.dht, dht_key_length, &(record.0).1)?;
+        Ok(Some(addrs.iter().take(crate::cluster::replication_factor())
+            .map(|x| x.to_string()).collect()))
+    }
+
+    fn log_context(&self, record: &OverviewRecord) -> LogContext {
+        LogContext::new(&self.album, &(record.0).1)
+    }
+
+    async fn records(&self)
+            -> Result<Vec<OverviewRecord>, Box<dyn Error>> {
+        // retrieve album metadata
+        let album_id = {
+            let album = self.album.read().unwrap();
+            album.get_id().to_string()
+        };
+
+        // initialize ImageListRequest
+        let request = ImageListRequest {
+            album: album_id,
+            albums: Vec::new(),
+            all_albums: None,
+            filter: Filter {
+                end_timestamp: self.end_timestamp,
+                geocode: self.geocode.clone(),
+                max_cloud_coverage: self.max_cloud_coverage,
+                min_pixel_coverage: None,
+                name: None,
+                platform: self.platform.clone(),
+                recurse: true,
+                source: self.source.clone(),
+                start_timestamp: self.start_timestamp,
+            },
+            geometry: None,
+            max_latitude: None,
+            max_longitude: None,
+            min_latitude: None,
+            min_longitude: None,
+            statistics: None,
+        };
+
+        // group replicas of each child tile under its overview geocode
+        let source = format!("{}{}", OVERVIEW_SOURCE_PREFIX, self.precision);
+        let mut groups: BTreeMap<(String, String, i64, String, u8),
+            BTreeMap<String, (String, Vec<(SocketAddr, String)>)>>
+            = BTreeMap::new();
+        for node in self.dht.nodes() {
+            // get rpc and transfer addresses
+            let addr = format!("{}:{}", node.get_ip_address(),
+                node.get_metadata("rpc_port").unwrap());
+            let xfer_addr = SocketAddr::new(node.get_ip_address().clone(),
+                node.get_metadata("xfer_port").unwrap().parse::<u16>()?);
+
+            // open ImageManagementClient
+            let mut client = match crate::tls::channel(
+                    &addr).await.map(ImageManagementClient::new) {
+                Ok(client) => client,
+                Err(e) => return Err(format!(
+                    "connection to {} failed: {}", addr, e).into()),
+            };
+
+            let mut stream = client.list(Request::new(request.clone()))
+                .await?.into_inner();
+            while let Some(image) = stream.message().await? {
+                // overviews are built from finer tiles only
+                if image.geocode.len() <= self.precision
+                        || image.source.starts_with(OVERVIEW_SOURCE_PREFIX) {
+                    continue;
+                }
+
+                let parent = image.geocode[..self.precision].to_string();
+                for file in image.files.iter() {
+                    // image paths are 'tile-subdataset.tif'
+                    let tile = match Path::new(&file.path).file_stem()
+                            .and_then(|x| x.to_str())
+                            .and_then(|x| x.rsplitn(2, '-').nth(1)) {
+                        Some(tile) => tile.to_string(),
+                        None => continue,
+                    };
+
+                    // keep a single source for each child geocode
+                    let children = groups.entry((parent.clone(),
+                            image.platform.clone(), image.timestamp,
+                            tile, file.subdataset as u8))
+                        .or_insert(BTreeMap::new());
+                    let child = children.entry(image.geocode.clone())
+                        .or_insert((image.source.clone(), Vec::new()));
+                    if child.0 == image.source {
+                        child.1.push((xfer_addr, file.path.clone()));
+                    }
+                }
+            }
+        }
+
+        let mut records = Vec::new();
+        for ((geocode, platform, timestamp, tile, subdataset), children)
+                in groups.into_iter() {
+            let image = (None, geocode, platform,
+                source.clone(), tile, timestamp);
+            records.push((image, subdataset, children.into_iter()
+                .map(|(_, (_, replicas))| replicas).collect()));
+        }
+
+        Ok(records)
+    }
+}
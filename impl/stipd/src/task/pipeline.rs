@@ -0,0 +1,321 @@
+use prost::Message;
+use protobuf::{ImagePipelineRequest, TaskListRequest, TaskManagementClient};
+use swarm::prelude::Dht;
+use tokio::runtime::Builder;
+
+use crate::album::AlbumManager;
+use crate::task::{TaskHandle, TaskManager};
+use crate::task::journal::TaskDefinition;
+use crate::task::logger::TaskLog;
+
+use std::error::Error;
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, RwLock};
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, AtomicUsize, Ordering};
+use std::time::Duration;
+
+const PIPELINE_POLL_DURATION: Duration = Duration::from_millis(1000);
+
+pub struct Pipeline {
+    definitions: Vec<TaskDefinition>,
+    id: u64,
+    path: PathBuf,
+    priority: u32,
+}
+
+impl Pipeline {
+    pub fn create(directory: &PathBuf, id: u64,
+            request: &ImagePipelineRequest)
+            -> Result<Pipeline, Box<dyn Error>> {
+        let pipeline = Pipeline::new(directory, id, request)?;
+
+        // write pipeline file
+        let mut buf = Vec::new();
+        request.encode(&mut buf)?;
+
+        let mut file = File::create(&pipeline.path)?;
+        file.write_all(&buf)?;
+
+        Ok(pipeline)
+    }
+
+    pub fn open(path: &PathBuf) -> Result<Pipeline, Box<dyn Error>> {
+        // parse pipeline id from filename
+        let id = match path.file_stem().and_then(|x| x.to_str())
+                .and_then(|x| x.parse::<u64>().ok()) {
+            Some(id) => id,
+            None => return Err(format!("invalid pipeline file '{:?}'",
+                path).into()),
+        };
+
+        // read pipeline file
+        let mut buf = Vec::new();
+        File::open(path)?.read_to_end(&mut buf)?;
+        let request = ImagePipelineRequest::decode(&buf[..])?;
+
+        let directory = path.parent()
+            .map(|x| x.to_path_buf()).unwrap_or(PathBuf::new());
+        Pipeline::new(&directory, id, &request)
+    }
+
+    fn new(directory: &PathBuf, id: u64, request: &ImagePipelineRequest)
+            -> Result<Pipeline, Box<dyn Error>> {
+        // compile stage definitions
+        let mut definitions = Vec::new();
+        for stage in request.stages.iter() {
+            let definition = match (&stage.coalesce_request,
+                    &stage.fill_request, &stage.split_request,
+                    &stage.store_request) {
+                (Some(x), None, None, None) =>
+                    TaskDefinition::Coalesce(x.clone()),
+                (None, Some(x), None, None) =>
+                    TaskDefinition::Fill(x.clone()),
+                (None, None, Some(x), None) =>
+                    TaskDefinition::Split(x.clone()),
+                (None, None, None, Some(x)) =>
+                    TaskDefinition::Store(x.clone()),
+                _ => return Err(format!(
+                    "pipeline stage {} must define exactly one task",
+                    definitions.len()).into()),
+            };
+
+            if definition.is_dry_run() {
+                return Err(format!("pipeline stage {} is a dry run",
+                    definitions.len()).into());
+            }
+
+            definitions.push(definition);
+        }
+
+        if definitions.is_empty() {
+            return Err("pipeline must define at least one stage".into());
+        }
+
+        let mut path = directory.clone();
+        path.push(id.to_string());
+        path.set_extension("pipeline");
+
+        Ok(Pipeline {
+            definitions: definitions,
+            id: id,
+            path: path,
+            priority: request.priority.unwrap_or(0),
+        })
+    }
+
+    pub fn get_id(&self) -> u64 {
+        self.id
+    }
+
+    pub fn get_priority(&self) -> u32 {
+        self.priority
+    }
+
+    pub fn stage_id(&self, index: usize) -> u64 {
+        self.id.wrapping_add(index as u64 + 1)
+    }
+
+    pub fn start(self, album_manager: Arc<RwLock<AlbumManager>>,
+            dht: Arc<Dht>, task_manager: Arc<RwLock<TaskManager>>)
+            -> TaskHandle {
+        info!("starting pipeline [id={}, stage_count={}]",
+            self.id, self.definitions.len());
+
+        // initialize composite TaskHandle over stages
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let completed_count = Arc::new(AtomicU32::new(0));
+        let end_time = Arc::new(AtomicU64::new(0));
+        let errors = Arc::new(Mutex::new(Vec::new()));
+        let log = Arc::new(TaskLog::new());
+        let paused = Arc::new(AtomicBool::new(false));
+        let running = Arc::new(AtomicBool::new(true));
+        let skipped_count = Arc::new(AtomicU32::new(0));
+
+        let task_handle = TaskHandle {
+            cancelled: cancelled.clone(),
+            completed_count: completed_count.clone(),
+            composite: true,
+            compute_ms: Arc::new(AtomicU64::new(0)),
+            end_time: end_time.clone(),
+            errors: errors.clone(),
+            ingested_bytes: Arc::new(AtomicU64::new(0)),
+            log: log.clone(),
+            omitted_errors: Arc::new(AtomicU32::new(0)),
+            paused: paused.clone(),
+            priority: 0,
+            queued: Arc::new(AtomicBool::new(false)),
+            requested_share: 0,
+            retry: None,
+            running: running.clone(),
+            share: Arc::new(AtomicUsize::new(0)),
+            skipped_count: skipped_count.clone(),
+            tenant: None,
+            total_count: Arc::new(AtomicU32::new(
+                self.definitions.len() as u32)),
+        };
+
+        // start pipeline management thread
+        std::thread::spawn(move || {
+            crate::task::logger::attach(&log);
+
+            let stage_count = self.definitions.len();
+            for (index, definition) in self.definitions.iter().enumerate() {
+                let stage_id = self.stage_id(index);
+                if let Err(e) = start_stage(&album_manager, &dht,
+                        &task_manager, definition, stage_id, self.priority) {
+                    warn!("failed to start pipeline {} stage {}: {}",
+                        self.id, index, e);
+                    errors.lock().unwrap().push((format!("stage {}", index),
+                        format!("failed to start: {}", e)));
+                    skipped_count.fetch_add(1, Ordering::SeqCst);
+                    break;
+                }
+
+                // wait for local stage and then the stage on all nodes
+                let succeeded = wait_local(&task_manager,
+                        stage_id, &cancelled, &paused)
+                    && (index + 1 == stage_count
+                        || wait_cluster(&dht, stage_id, &cancelled));
+
+                if cancelled.load(Ordering::SeqCst) {
+                    info!("pipeline cancelled [id={}]", self.id);
+                    break;
+                }
+
+                match succeeded {
+                    true => completed_count.fetch_add(1, Ordering::SeqCst),
+                    false => {
+                        warn!("pipeline {} stage {} failed, halting",
+                            self.id, index);
+                        errors.lock().unwrap().push((
+                            format!("stage {}", index),
+                            format!("task {} failed", stage_id)));
+                        skipped_count.fetch_add(1, Ordering::SeqCst);
+                        break;
+                    },
+                };
+            }
+
+            // remove pipeline file
+            if let Err(e) = std::fs::remove_file(&self.path) {
+                warn!("failed to remove pipeline file '{:?}': {}",
+                    self.path, e);
+            }
+
+            end_time.store(super::now(), Ordering::SeqCst);
+            running.store(false, Ordering::SeqCst);
+        });
+
+        task_handle
+    }
+}
+
+fn start_stage(album_manager: &Arc<RwLock<AlbumManager>>, dht: &Arc<Dht>,
+        task_manager: &Arc<RwLock<TaskManager>>, definition: &TaskDefinition,
+        stage_id: u64, priority: u32) -> Result<(), Box<dyn Error>> {
+    // stages resumed after a restart are already registered
+    if task_manager.read().unwrap().get(&stage_id).is_some() {
+        return Ok(());
+    }
+
+    if definition.requires_index() {
+        super::journal::open_album(album_manager,
+            definition.get_album(), definition.get_thread_count())?;
+    }
+
+    let journal = task_manager.read().unwrap()
+        .create_journal(stage_id, definition)?;
+    let task_handle = definition.start(album_manager, dht, Some(journal))?;
+
+    let mut task_manager = task_manager.write().unwrap();
+    task_manager.register(task_handle, Some(stage_id),
+        std::cmp::max(priority, definition.get_priority()))?;
+    Ok(())
+}
+
+fn wait_cluster(dht: &Arc<Dht>, stage_id: u64,
+        cancelled: &AtomicBool) -> bool {
+    let mut runtime = match Builder::new()
+            .basic_scheduler().enable_all().build() {
+        Ok(runtime) => runtime,
+        Err(e) => {
+            warn!("pipeline failed to initialize runtime: {}", e);
+            return false;
+        },
+    };
+
+    // compile node rpc addresses
+    let mut addrs = Vec::new();
+    for node in dht.nodes() {
+        addrs.push(format!("{}:{}", node.get_ip_address(),
+            node.get_metadata("rpc_port").unwrap()));
+    }
+
+    while !cancelled.load(Ordering::SeqCst) {
+        match runtime.block_on(poll_stage(&addrs, stage_id)) {
+            Ok((true, succeeded)) => return succeeded,
+            Ok((false, _)) => {},
+            Err(e) => warn!("failed to poll pipeline stage {}: {}",
+                stage_id, e),
+        }
+
+        std::thread::sleep(PIPELINE_POLL_DURATION);
+    }
+
+    false
+}
+
+async fn poll_stage(addrs: &[String], stage_id: u64)
+        -> Result<(bool, bool), Box<dyn Error>> {
+    let (mut finished, mut succeeded) = (true, true);
+    for addr in addrs {
+        let mut client =
+            TaskManagementClient::new(crate::tls::channel(addr).await?);
+        let reply = client.list(TaskListRequest {}).await?;
+
+        for task in reply.get_ref().tasks.iter()
+                .filter(|x| x.id == stage_id) {
+            finished &= !task.running;
+            succeeded &= task.completed_count >= task.total_count;
+        }
+    }
+
+    Ok((finished, succeeded))
+}
+
+fn wait_local(task_manager: &Arc<RwLock<TaskManager>>, stage_id: u64,
+        cancelled: &AtomicBool, paused: &AtomicBool) -> bool {
+    let mut stage_paused = false;
+    loop {
+        {
+            let task_manager = task_manager.read().unwrap();
+            let task_handle = match task_manager.get(&stage_id) {
+                Some(task_handle) => task_handle,
+                None => return false,
+            };
+
+            // forward pipeline cancellation and pause transitions
+            if cancelled.load(Ordering::SeqCst) {
+                task_handle.cancel();
+            }
+
+            let pipeline_paused = paused.load(Ordering::SeqCst);
+            match (pipeline_paused, stage_paused) {
+                (true, false) => task_handle.pause(),
+                (false, true) => task_handle.resume(),
+                _ => {},
+            }
+            stage_paused = pipeline_paused;
+
+            if !task_handle.running() {
+                return !task_handle.cancelled()
+                    && task_handle.completed_count()
+                        >= task_handle.total_count();
+            }
+        }
+
+        std::thread::sleep(PIPELINE_POLL_DURATION);
+    }
+}
@@ -0,0 +1,174 @@
+use crossbeam_channel::{Receiver, Sender};
+
+use std::any::Any;
+use std::cell::{Cell, RefCell};
+use std::error::Error;
+use std::panic::AssertUnwindSafe;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+type Job = Box<dyn FnOnce() + Send>;
+
+// workers shared by the records of every task, so node concurrency is
+//   bounded regardless of how many tasks are running
+static POOL: Mutex<Option<Sender<Job>>> = Mutex::new(None);
+static CAPACITY: AtomicUsize = AtomicUsize::new(0);
+
+thread_local! {
+    static CANCELLED: RefCell<Option<Arc<AtomicBool>>> = RefCell::new(None);
+    static WORKER: Cell<bool> = Cell::new(false);
+}
+
+// worker threads divided among running tasks
+pub fn capacity() -> usize {
+    CAPACITY.load(Ordering::SeqCst)
+}
+
+// whether the current thread is processing task records
+pub fn is_worker() -> bool {
+    WORKER.with(|x| x.get())
+}
+
+// observe the cancellation of the task processing on the current thread
+pub fn attach_cancel(cancelled: &Arc<AtomicBool>) {
+    CANCELLED.with(|x| *x.borrow_mut() = Some(cancelled.clone()));
+}
+
+// fail once the task of the current record is cancelled, polled within
+//   records which send many windows
+pub fn check_cancelled() -> Result<(), Box<dyn Error>> {
+    let cancelled = CANCELLED.with(|x| x.borrow().as_ref()
+        .map(|x| x.load(Ordering::SeqCst)).unwrap_or(false));
+    match cancelled {
+        true => Err("task cancelled".into()),
+        false => Ok(()),
+    }
+}
+
+// describe the payload of a caught panic
+pub fn panic_message(payload: &Box<dyn Any + Send>) -> String {
+    match (payload.downcast_ref::<&str>(), payload.downcast_ref::<String>()) {
+        (Some(message), _) => message.to_string(),
+        (_, Some(message)) => message.clone(),
+        _ => "unknown panic".to_string(),
+    }
+}
+
+// start the shared workers once at startup, 0 uses the available
+//   parallelism
+pub fn start(thread_count: usize) {
+    let mut pool = POOL.lock().unwrap();
+    if pool.is_some() {
+        warn!("task worker pool already started");
+        return;
+    }
+
+    let thread_count = match thread_count {
+        0 => std::thread::available_parallelism()
+            .map(|x| x.get()).unwrap_or(4),
+        x => x,
+    };
+
+    info!("initailizing task worker pool [thread_count={}]", thread_count);
+    let (sender, receiver) = crossbeam_channel::unbounded();
+    for _ in 0..thread_count {
+        let receiver = receiver.clone();
+        std::thread::spawn(move || work(receiver));
+    }
+
+    CAPACITY.store(thread_count, Ordering::SeqCst);
+    *pool = Some(sender);
+}
+
+// queue a job behind those of other tasks
+fn spawn(job: Job) {
+    let pool = POOL.lock().unwrap();
+    match pool.as_ref() {
+        Some(sender) => if let Err(e) = sender.send(job) {
+            warn!("failed to queue task job: {}", e);
+        },
+        None => warn!("failed to queue task job: worker pool not started"),
+    }
+}
+
+fn work(receiver: Receiver<Job>) {
+    WORKER.with(|x| x.set(true));
+    for job in receiver.iter() {
+        // contain panics to the job so the worker survives
+        if let Err(e) = std::panic::catch_unwind(AssertUnwindSafe(job)) {
+            warn!("task job panicked: {}", panic_message(&e));
+        }
+
+        // workers are shared, drop the context of the finished job
+        crate::task::locate::detach();
+        crate::task::logger::detach();
+        CANCELLED.with(|x| *x.borrow_mut() = None);
+    }
+}
+
+// limits the jobs a single task has queued or running on the pool to
+//   its share, each job processes one record so tasks interleave fairly
+pub struct Throttle {
+    in_flight: usize,
+    limit: Arc<AtomicUsize>,
+    receiver: Receiver<()>,
+    sender: Sender<()>,
+}
+
+impl Throttle {
+    pub fn new(limit: Arc<AtomicUsize>) -> Throttle {
+        let (sender, receiver) = crossbeam_channel::unbounded();
+        Throttle {
+            in_flight: 0,
+            limit: limit,
+            receiver: receiver,
+            sender: sender,
+        }
+    }
+
+    // block until a queued job completes
+    pub fn complete(&mut self) {
+        if self.receiver.recv().is_ok() {
+            self.in_flight -= 1;
+        }
+    }
+
+    pub fn is_idle(&self) -> bool {
+        self.in_flight == 0
+    }
+
+    // block until all queued jobs have completed
+    pub fn join(mut self) {
+        while self.in_flight > 0 {
+            self.complete();
+        }
+    }
+
+    pub fn spawn<F: 'static + FnOnce() + Send>(&mut self, job: F) {
+        let completion = Completion(self.sender.clone());
+        spawn(Box::new(move || {
+            let _completion = completion;
+            job();
+        }));
+
+        self.in_flight += 1;
+    }
+
+    // block until the task may queue another job, the limit is read on
+    //   each check as shares are rebalanced while tasks run
+    pub fn wait(&mut self) {
+        while self.in_flight
+                >= std::cmp::max(self.limit.load(Ordering::SeqCst), 1) {
+            self.complete();
+        }
+    }
+}
+
+// signals job completion when dropped, including when the job panics
+struct Completion(Sender<()>);
+
+impl Drop for Completion {
+    fn drop(&mut self) {
+        let _ = self.0.send(());
+    }
+}
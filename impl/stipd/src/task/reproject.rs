@@ -0,0 +1,131 @@
+use crate::{Image, StFile, REPROJECTED_SOURCE_PREFIX};
+use crate::album::Album;
+use crate::raster::RESAMPLING_METHODS;
+use crate::task::Task;
+use crate::task::logger::LogContext;
+
+use std::error::Error;
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+
+pub struct ReprojectTask {
+    album: Arc<RwLock<Album>>,
+    end_timestamp: Option<i64>,
+    epsg_code: u32,
+    geocode: Option<String>,
+    max_cloud_coverage: Option<f64>,
+    min_pixel_coverage: Option<f64>,
+    platform: Option<String>,
+    recurse: bool,
+    resampling: String,
+    resolution: Option<f64>,
+    source: Option<String>,
+    start_timestamp: Option<i64>,
+}
+
+impl ReprojectTask {
+    pub fn new(album: Arc<RwLock<Album>>, end_timestamp: Option<i64>,
+            epsg_code: u32, geocode: Option<String>,
+            max_cloud_coverage: Option<f64>,
+            min_pixel_coverage: Option<f64>, platform: Option<String>,
+            recurse: bool, resampling: String, resolution: Option<f64>,
+            source: Option<String>, start_timestamp: Option<i64>)
+            -> Result<ReprojectTask, Box<dyn Error>> {
+        {
+            let album = album.read().unwrap();
+            info!("initailizing reproject task [album={}, end_timestamp={:?}, epsg_code={}, geocode={:?}, max_cloud_coverage={:?}, min_pixel_coverage={:?}, platform={:?}, recurse={}, resampling={}, resolution={:?}, source={:?}, start_timestamp={:?}]",
+                album.get_id(), end_timestamp, epsg_code, geocode,
+                max_cloud_coverage, min_pixel_coverage, platform,
+                recurse, resampling, resolution, source, start_timestamp);
+
+            // fail before listing images if the album is held
+            album.assert_modifiable()?;
+        }
+
+        if !RESAMPLING_METHODS.contains(&resampling.as_str()) {
+            return Err(format!("unsupported resampling method '{}'",
+                resampling).into());
+        }
+
+        if let Some(resolution) = resolution {
+            if resolution <= 0f64 {
+                return Err("resolution must be positive".into());
+            }
+        }
+
+        Ok(ReprojectTask {
+            album: album,
+            end_timestamp: end_timestamp,
+            epsg_code: epsg_code,
+            geocode: geocode,
+            max_cloud_coverage: max_cloud_coverage,
+            min_pixel_coverage: min_pixel_coverage,
+            platform: platform,
+            recurse: recurse,
+            resampling: resampling,
+            resolution: resolution,
+            source: source,
+            start_timestamp: start_timestamp,
+        })
+    }
+}
+
+#[tonic::async_trait]
+impl Task<(Image, StFile)> for ReprojectTask {
+    fn process(&self, record: &(Image, StFile))
+            -> Result<(), Box<dyn Error>> {
+        let (image, file) = record;
+
+        // warp image onto the target projection
+        let dataset = crate::compaction::open(&PathBuf::from(&file.0))?;
+        let mut reprojected = crate::raster::warp(&dataset,
+            self.epsg_code, self.resolution, &self.resampling)?;
+
+        let pixel_coverage = st_image::get_coverage(&reprojected)?;
+        if pixel_coverage == 0f64 {
+            return Ok(());
+        }
+
+        // write reprojected image alongside the source image
+        let source = format!("{}{}",
+            REPROJECTED_SOURCE_PREFIX, self.epsg_code);
+        let mut album = self.album.write().unwrap();
+        album.assert_modifiable()?;
+        album.write(&mut reprojected, &image.1, pixel_coverage, &image.2,
+            &source, file.2, &image.4, image.5)
+    }
+
+    fn estimate(&self, _record: &(Image, StFile))
+            -> Result<Option<Vec<String>>, Box<dyn Error>> {
+        // each record writes one reprojected image locally
+        Ok(Some(vec!["local".to_string()]))
+    }
+
+    fn log_context(&self, record: &(Image, StFile)) -> LogContext {
+        LogContext::new(&self.album, &(record.0).1)
+    }
+
+    async fn records(&self)
+            -> Result<Vec<(Image, StFile)>, Box<dyn Error>> {
+        // search for images using Album
+        let album = self.album.read().unwrap();
+        let images = album.list(&self.end_timestamp, &self.geocode,
+            &self.max_cloud_coverage, &self.min_pixel_coverage,
+            &self.platform, self.recurse, &self.source,
+            &self.start_timestamp)?;
+
+        // previously reprojected images are never rewarped
+        let mut records = Vec::new();
+        for (image, files) in images.into_iter() {
+            if image.3.starts_with(REPROJECTED_SOURCE_PREFIX) {
+                continue;
+            }
+
+            for file in files.into_iter() {
+                records.push((image.clone(), file));
+            }
+        }
+
+        Ok(records)
+    }
+}
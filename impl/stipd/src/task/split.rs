@@ -1,9 +1,12 @@
 use gdal::Dataset;
+use geocode::Geocode;
 use swarm::prelude::Dht;
 
 use crate::{Image, StFile, RAW_SOURCE, SPLIT_SOURCE};
 use crate::album::Album;
 use crate::task::Task;
+use crate::task::logger::LogContext;
+use crate::transfer::ImageWrite;
 
 use std::error::Error;
 use std::sync::{Arc, RwLock};
@@ -68,73 +71,83 @@ impl Task<(Image, Vec<StFile>)> for SplitTask {
                     &image.2, &image.3, file.2, &image.4)?
             };
 
-            if !path.exists() {
+            if !crate::compaction::exists(&path) {
                 return Err(format!("image path '{}' does not exist",
                     path.to_string_lossy()).into());
             }
 
             // open image
-            let dataset = Dataset::open(&path)?;
+            let dataset = crate::compaction::open(&path)?;
 
-            // compute geohash window boundaries for dataset
-            let epsg_code = geocode.get_epsg_code();
-            let (x_interval, y_interval) =
-                geocode.get_intervals(self.precision);
-
-            let (image_min_cx, image_max_cx, image_min_cy, image_max_cy) =
-                st_image::coordinate::get_bounds(&dataset, epsg_code)?;
-
-            let window_bounds = st_image::coordinate::get_windows(
-                image_min_cx, image_max_cx, image_min_cy, image_max_cy,
-                x_interval, y_interval);
-
-            // iterate over window bounds
-            for (min_cx, max_cx, min_cy, max_cy) in window_bounds {
-                let split_geocode = geocode.encode(
-                    (min_cx + max_cx) / 2.0,
-                    (min_cy + max_cy) / 2.0, self.precision)?;
-
-                //  skip if geocode doesn't 'start_with' base image geocode
-                if !split_geocode.starts_with(&image.1) {
-                    continue;
-                }
-
-                // perform dataset split
-                let split_dataset = match st_image::transform::split(&dataset,
-                        min_cx, max_cx, min_cy, max_cy, epsg_code)? {
-                    Some(split_dataset) => split_dataset,
-                    None => continue,
-                };
-
-                // if image has 0.0 coverage -> don't process
-                let pixel_coverage =
-                    st_image::get_coverage(&split_dataset)?;
-                if pixel_coverage == 0f64 {
-                    continue;
-                }
+            // iterate over split datasets, streaming them in batches
+            let mut image_writes = Vec::new();
+            for result in split_dataset(&dataset, &geocode,
+                    self.geocode_bound.as_deref(), &image.1,
+                    self.precision)? {
+                // halt long records once the task is cancelled
+                crate::task::pool::check_cancelled()?;
+                let (split_geocode, split_dataset) = result?;
 
                 // lookup geocode in dht
-                let addr = match crate::task::dht_lookup(
+                let addrs = match crate::task::dht_lookup(
                         &self.dht, dht_key_length, &split_geocode) {
-                    Ok(addr) => addr,
+                    Ok(addrs) => addrs,
                     Err(e) => {
                         warn!("{}", e);
                         continue;
                     },
                 };
 
-                // send image to new host
-                if let Err(e) = crate::transfer::send_image(&addr, &album_id,
-                        &split_dataset, &split_geocode, file.1, &image.2,
-                        SPLIT_SOURCE, file.2, &image.4, image.5) {
-                    warn!("failed to write image to node {}: {}", addr, e);
+                match ImageWrite::new(&addrs, &album_id, &split_dataset,
+                        &split_geocode, file.1, &image.2, SPLIT_SOURCE,
+                        file.2, &image.4, image.5) {
+                    Ok(image_write) => image_writes.push(image_write),
+                    Err(e) => warn!("{}", e),
                 }
+
+                send_batch(&mut image_writes, false);
             }
+
+            send_batch(&mut image_writes, true);
         }
 
         Ok(())
     }
 
+    fn estimate(&self, record: &(Image, Vec<StFile>))
+            -> Result<Option<Vec<String>>, Box<dyn Error>> {
+        let image = &record.0;
+
+        // retrieve album metadata
+        let (dht_key_length, geocode) = {
+            let album = self.album.read().unwrap();
+            (album.get_dht_key_length(), album.get_geocode().clone())
+        };
+
+        let mut destinations = Vec::new();
+        for file in record.1.iter() {
+            let path = {
+                let album = self.album.read().unwrap();
+                album.get_image_path(false, &image.1,
+                    &image.2, &image.3, file.2, &image.4)?
+            };
+
+            // compute geohash windows without splitting the dataset
+            let dataset = crate::compaction::open(&path)?;
+            let split_geocodes = split_geocodes(&dataset, &geocode,
+                self.geocode_bound.as_deref(), &image.1, self.precision)?;
+
+            destinations.append(&mut estimate_destinations(&self.dht,
+                dht_key_length, &split_geocodes)?);
+        }
+
+        Ok(Some(destinations))
+    }
+
+    fn log_context(&self, record: &(Image, Vec<StFile>)) -> LogContext {
+        LogContext::new(&self.album, &(record.0).1)
+    }
+
     async fn records(&self)
             -> Result<Vec<(Image, Vec<StFile>)>, Box<dyn Error>> {
         // search for images using Album
@@ -152,12 +165,280 @@ impl Task<(Image, Vec<StFile>)> for SplitTask {
 
         // filter by result bounding geocode if necessary
         if let Some(geocode) = &self.geocode_bound {
-            records = records.into_iter().filter(|(image, _)| {
-                    image.1.starts_with(geocode)
-                        || geocode.starts_with(&image.1)
-                }).collect();
+            records = records.into_iter()
+                .filter(|(image, _)| within_bound(&image.1, geocode))
+                .collect();
         }
 
         Ok(records)
     }
 }
+
+// windows sent per batch, bounding the splits held in memory while
+//   still pipelining writes to each destination
+const SEND_BATCH_SIZE: usize = 16;
+
+// splits of a dataset computed lazily one window at a time, so peak
+//   memory holds a single split regardless of precision
+pub struct SplitDatasets<'a> {
+    dataset: &'a Dataset,
+    geocode: Geocode,
+    geocode_bound: Option<String>,
+    image_geocode: String,
+    precision: usize,
+    windows: std::vec::IntoIter<(f64, f64, f64, f64)>,
+}
+
+impl<'a> SplitDatasets<'a> {
+    fn split(&self, min_cx: f64, max_cx: f64, min_cy: f64, max_cy: f64)
+            -> Result<Option<(String, Dataset)>, Box<dyn Error>> {
+        let split_geocode = match window_geocode(&self.geocode,
+                self.geocode_bound.as_deref(), &self.image_geocode,
+                self.precision, (min_cx, max_cx, min_cy, max_cy))? {
+            Some(split_geocode) => split_geocode,
+            None => return Ok(None),
+        };
+
+        let mut span = crate::telemetry::span("gdal.split",
+            crate::telemetry::SpanKind::Internal);
+        span.set_attribute("precision", &self.precision.to_string());
+
+        // read the window from the source dataset
+        let split_dataset = match st_image::transform::split(self.dataset,
+                min_cx, max_cx, min_cy, max_cy,
+                self.geocode.get_epsg_code())? {
+            Some(split_dataset) => split_dataset,
+            None => return Ok(None),
+        };
+
+        // if image has 0.0 coverage -> don't process
+        let pixel_coverage = st_image::get_coverage(&split_dataset)?;
+        if pixel_coverage == 0f64 {
+            return Ok(None);
+        }
+
+        Ok(Some((split_geocode, split_dataset)))
+    }
+}
+
+impl<'a> Iterator for SplitDatasets<'a> {
+    type Item = Result<(String, Dataset), Box<dyn Error>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (min_cx, max_cx, min_cy, max_cy) = self.windows.next()?;
+            match self.split(min_cx, max_cx, min_cy, max_cy) {
+                Ok(Some(split)) => return Some(Ok(split)),
+                Ok(None) => continue,
+                Err(e) => return Some(Err(e)),
+            }
+        }
+    }
+}
+
+pub fn split_dataset<'a>(dataset: &'a Dataset, geocode: &Geocode,
+        geocode_bound: Option<&str>, image_geocode: &str, precision: usize)
+        -> Result<SplitDatasets<'a>, Box<dyn Error>> {
+    let window_bounds = get_windows(dataset, geocode, precision)?;
+
+    Ok(SplitDatasets {
+        dataset: dataset,
+        geocode: geocode.clone(),
+        geocode_bound: geocode_bound.map(|x| x.to_string()),
+        image_geocode: image_geocode.to_string(),
+        precision: precision,
+        windows: window_bounds.into_iter(),
+    })
+}
+
+// geocodes split_dataset may produce, computed without reading pixels
+//   so windows without coverage are included
+pub fn split_geocodes(dataset: &Dataset, geocode: &Geocode,
+        geocode_bound: Option<&str>, image_geocode: &str, precision: usize)
+        -> Result<Vec<String>, Box<dyn Error>> {
+    let mut split_geocodes = Vec::new();
+    for window in get_windows(dataset, geocode, precision)? {
+        if let Some(split_geocode) = window_geocode(geocode,
+                geocode_bound, image_geocode, precision, window)? {
+            split_geocodes.push(split_geocode);
+        }
+    }
+
+    Ok(split_geocodes)
+}
+
+// replica hosts of each split geocode, resolved in one batch
+pub fn estimate_destinations(dht: &Arc<Dht>, dht_key_length: i8,
+        split_geocodes: &[String]) -> Result<Vec<String>, Box<dyn Error>> {
+    let split_geocodes: Vec<&str> =
+        split_geocodes.iter().map(|x| x.as_str()).collect();
+
+    let mut destinations = Vec::new();
+    for result in crate::task::dht_lookups(dht,
+            dht_key_length, &split_geocodes) {
+        for addr in result?.iter()
+                .take(crate::cluster::replication_factor()) {
+            destinations.push(addr.to_string());
+        }
+    }
+
+    Ok(destinations)
+}
+
+// compute geohash window boundaries for dataset
+fn get_windows(dataset: &Dataset, geocode: &Geocode, precision: usize)
+        -> Result<Vec<(f64, f64, f64, f64)>, Box<dyn Error>> {
+    let epsg_code = geocode.get_epsg_code();
+    let (x_interval, y_interval) = geocode.get_intervals(precision);
+
+    let (image_min_cx, image_max_cx, image_min_cy, image_max_cy) =
+        st_image::coordinate::get_bounds(dataset, epsg_code)?;
+
+    Ok(st_image::coordinate::get_windows(image_min_cx,
+        image_max_cx, image_min_cy, image_max_cy, x_interval, y_interval))
+}
+
+// geocode of a window, skipped if it doesn't 'start_with' the base
+//   image geocode or falls outside the bounding geocode
+fn window_geocode(geocode: &Geocode, geocode_bound: Option<&str>,
+        image_geocode: &str, precision: usize,
+        (min_cx, max_cx, min_cy, max_cy): (f64, f64, f64, f64))
+        -> Result<Option<String>, Box<dyn Error>> {
+    let split_geocode = geocode.encode((min_cx + max_cx) / 2.0,
+        (min_cy + max_cy) / 2.0, precision)?;
+
+    let within = split_geocode.starts_with(image_geocode)
+        && geocode_bound.map(|x| within_bound(&split_geocode, x))
+            .unwrap_or(true);
+    match within {
+        true => Ok(Some(split_geocode)),
+        false => Ok(None),
+    }
+}
+
+// send queued writes once a batch accumulates, or all when flushing
+pub fn send_batch(image_writes: &mut Vec<ImageWrite>, flush: bool) {
+    if image_writes.is_empty()
+            || (!flush && image_writes.len() < SEND_BATCH_SIZE) {
+        return;
+    }
+
+    // send images to replica hosts, pipelined per destination
+    for result in crate::transfer::send_images(image_writes) {
+        if let Err(e) = result {
+            warn!("{}", e);
+        }
+    }
+
+    image_writes.clear();
+}
+
+fn within_bound(image_geocode: &str, geocode_bound: &str) -> bool {
+    image_geocode.starts_with(geocode_bound)
+        || geocode_bound.starts_with(image_geocode)
+}
+
+#[cfg(test)]
+mod tests {
+    use gdal::{Dataset, Driver};
+    use gdal::spatial_ref::SpatialRef;
+    use geocode::Geocode;
+
+    use std::ffi::CString;
+    use std::os::raw::{c_int, c_void};
+
+    // 20x20 pixel dataset over geohash '9xj' at 0.01 degree resolution
+    fn create_dataset(value: u8) -> Dataset {
+        let driver = Driver::get("MEM").unwrap();
+        let c_filename = CString::new("").unwrap();
+        let c_dataset = unsafe {
+            gdal_sys::GDALCreate(driver.c_driver(), c_filename.as_ptr(),
+                20, 20, 1, gdal_sys::GDALDataType::GDT_Byte,
+                std::ptr::null_mut())
+        };
+
+        let dataset = unsafe { Dataset::from_c_dataset(c_dataset) };
+        dataset.set_geo_transform(
+            &[-105.0, 0.01, 0.0, 40.0, 0.0, -0.01]).unwrap();
+        dataset.set_projection(
+            &SpatialRef::from_epsg(4326).unwrap().to_wkt().unwrap()).unwrap();
+
+        // fill band with value, treating 0 as no data
+        let mut buf = vec![value; 400];
+        unsafe {
+            let band = gdal_sys::GDALGetRasterBand(dataset.c_dataset(), 1);
+            gdal_sys::GDALSetRasterNoDataValue(band, 0.0);
+            gdal_sys::GDALRasterIO(band, gdal_sys::GDALRWFlag::GF_Write,
+                0, 0, 20, 20, buf.as_mut_ptr() as *mut c_void, 20, 20,
+                gdal_sys::GDALDataType::GDT_Byte, 0 as c_int, 0 as c_int);
+        }
+
+        dataset
+    }
+
+    #[test]
+    fn within_bound() {
+        // image geocodes coarser or finer than the bound are retained
+        assert!(super::within_bound("9xj", "9xjq"));
+        assert!(super::within_bound("9xjqb", "9xj"));
+        assert!(super::within_bound("9xj", "9xj"));
+
+        // disjoint geocodes are filtered
+        assert!(!super::within_bound("9xk", "9xj"));
+        assert!(!super::within_bound("9xkq", "9xjq"));
+    }
+
+    #[test]
+    fn split_dataset() {
+        let dataset = create_dataset(1);
+        let split_datasets = super::split_dataset(&dataset,
+            &Geocode::Geohash, None, "9xj", 4).unwrap()
+            .collect::<Result<Vec<_>, _>>().unwrap();
+
+        assert!(!split_datasets.is_empty());
+        for (split_geocode, _) in split_datasets.iter() {
+            assert_eq!(split_geocode.len(), 4);
+            assert!(split_geocode.starts_with("9xj"));
+        }
+    }
+
+    #[test]
+    fn split_dataset_geocode_bound() {
+        // splits of coarser images outside the bound are skipped
+        let dataset = create_dataset(1);
+        let split_datasets = super::split_dataset(&dataset,
+            &Geocode::Geohash, Some("9xj7"), "9xj", 5).unwrap()
+            .collect::<Result<Vec<_>, _>>().unwrap();
+
+        assert!(!split_datasets.is_empty());
+        for (split_geocode, _) in split_datasets.iter() {
+            assert!(split_geocode.starts_with("9xj7"));
+        }
+
+        let split_geocodes = super::split_geocodes(&dataset,
+            &Geocode::Geohash, Some("9xj7"), "9xj", 5).unwrap();
+        assert_eq!(split_geocodes.len(), split_datasets.len());
+    }
+
+    #[test]
+    fn split_dataset_geocode_mismatch() {
+        // windows outside the image geocode are skipped
+        let dataset = create_dataset(1);
+        let split_datasets = super::split_dataset(&dataset,
+            &Geocode::Geohash, None, "9xk", 4).unwrap()
+            .collect::<Result<Vec<_>, _>>().unwrap();
+
+        assert!(split_datasets.is_empty());
+    }
+
+    #[test]
+    fn split_dataset_zero_coverage() {
+        // windows containing only no data pixels are skipped
+        let dataset = create_dataset(0);
+        let split_datasets = super::split_dataset(&dataset,
+            &Geocode::Geohash, None, "9xj", 4).unwrap()
+            .collect::<Result<Vec<_>, _>>().unwrap();
+
+        assert!(split_datasets.is_empty());
+    }
+}
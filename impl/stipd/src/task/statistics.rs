@@ -0,0 +1,119 @@
+use crate::{Image, StFile};
+use crate::album::Album;
+use crate::task::Task;
+use crate::task::logger::LogContext;
+
+use std::error::Error;
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+
+pub struct StatisticsTask {
+    album: Arc<RwLock<Album>>,
+    bins: usize,
+    end_timestamp: Option<i64>,
+    geocode: Option<String>,
+    max_cloud_coverage: Option<f64>,
+    min_pixel_coverage: Option<f64>,
+    platform: Option<String>,
+    recompute: bool,
+    recurse: bool,
+    source: Option<String>,
+    start_timestamp: Option<i64>,
+}
+
+impl StatisticsTask {
+    pub fn new(album: Arc<RwLock<Album>>, bins: usize,
+            end_timestamp: Option<i64>, geocode: Option<String>,
+            max_cloud_coverage: Option<f64>,
+            min_pixel_coverage: Option<f64>, platform: Option<String>,
+            recompute: bool, recurse: bool, source: Option<String>,
+            start_timestamp: Option<i64>)
+            -> Result<StatisticsTask, Box<dyn Error>> {
+        {
+            let album = album.read().unwrap();
+            info!("initailizing statistics task [album={}, bins={}, end_timestamp={:?}, geocode={:?}, max_cloud_coverage={:?}, min_pixel_coverage={:?}, platform={:?}, recompute={}, recurse={}, source={:?}, start_timestamp={:?}]",
+                album.get_id(), bins, end_timestamp, geocode,
+                max_cloud_coverage, min_pixel_coverage, platform,
+                recompute, recurse, source, start_timestamp);
+
+            // fail before listing images if the album is held
+            album.assert_modifiable()?;
+        }
+
+        if bins == 0 {
+            return Err("histogram requires at least one bin".into());
+        }
+
+        Ok(StatisticsTask {
+            album: album,
+            bins: bins,
+            end_timestamp: end_timestamp,
+            geocode: geocode,
+            max_cloud_coverage: max_cloud_coverage,
+            min_pixel_coverage: min_pixel_coverage,
+            platform: platform,
+            recompute: recompute,
+            recurse: recurse,
+            source: source,
+            start_timestamp: start_timestamp,
+        })
+    }
+}
+
+#[tonic::async_trait]
+impl Task<(Image, StFile)> for StatisticsTask {
+    fn process(&self, record: &(Image, StFile))
+            -> Result<(), Box<dyn Error>> {
+        let (image, file) = record;
+
+        // compute statistics for each band
+        let dataset = crate::compaction::open(&PathBuf::from(&file.0))?;
+        let mut statistics = Vec::new();
+        for i in 0..crate::raster::band_count(&dataset) {
+            if let Some(band_statistics) = crate::raster::statistics(
+                    &dataset, i + 1, self.bins)? {
+                statistics.push(band_statistics);
+            }
+        }
+
+        // cache statistics in the album index
+        let mut album = self.album.write().unwrap();
+        album.set_statistics(&image.1, &image.3,
+            &image.4, file.2, &statistics)
+    }
+
+    fn estimate(&self, _record: &(Image, StFile))
+            -> Result<Option<Vec<String>>, Box<dyn Error>> {
+        // statistics are only written to the local index
+        Ok(Some(Vec::new()))
+    }
+
+    fn log_context(&self, record: &(Image, StFile)) -> LogContext {
+        LogContext::new(&self.album, &(record.0).1)
+    }
+
+    async fn records(&self)
+            -> Result<Vec<(Image, StFile)>, Box<dyn Error>> {
+        // search for images using Album
+        let album = self.album.read().unwrap();
+        let images = album.list(&self.end_timestamp, &self.geocode,
+            &self.max_cloud_coverage, &self.min_pixel_coverage,
+            &self.platform, self.recurse, &self.source,
+            &self.start_timestamp)?;
+
+        // skip files with cached statistics unless recomputing
+        let mut records = Vec::new();
+        for (image, files) in images.into_iter() {
+            for file in files.into_iter() {
+                if !self.recompute && !album.statistics(&image.1,
+                        &image.3, &image.4, file.2)?.is_empty() {
+                    continue;
+                }
+
+                records.push((image.clone(), file));
+            }
+        }
+
+        Ok(records)
+    }
+}
@@ -4,60 +4,110 @@ use swarm::prelude::Dht;
 
 use crate::RAW_SOURCE;
 use crate::album::Album;
+use crate::mask::CloudMask;
+use crate::transfer::Transaction;
 
 use std::error::Error;
 use std::path::PathBuf;
 use std::sync::{Arc, RwLock};
 
-pub fn process(album: &Arc<RwLock<Album>>, dht: &Arc<Dht>,
-        precision: usize, record: &PathBuf) -> Result<(), Box<dyn Error>> {
-    // retrieve album metadata
-    let (album_id, dht_key_length, geocode) = {
-        let album = album.read().unwrap();
-        (album.get_id().to_string(), album.get_dht_key_length(),
-            album.get_geocode().clone())
-    };
-
+pub fn process(album: &Arc<RwLock<Album>>, cloud_mask: Option<CloudMask>,
+        dht: &Arc<Dht>, precision: usize, record: &PathBuf,
+        transaction: Option<&Transaction>) -> Result<(), Box<dyn Error>> {
     let dataset = Dataset::open(&record)?;
+    let (platform, subdataset, tile, timestamp) =
+        metadata(&dataset, None, None, None, None)?;
 
-    // parse metadata
-    let platform = match dataset.metadata_item("PLATFORM", "") {
+    store(album, cloud_mask, dht, precision, &dataset, &platform,
+        subdataset, &tile, timestamp, transaction)?;
+    Ok(())
+}
+
+// platform, subdataset, tile, and timestamp of an image, preferring
+// provided values over dataset metadata
+pub fn metadata(dataset: &Dataset, platform: Option<String>,
+        subdataset: Option<u8>, tile: Option<String>,
+        timestamp: Option<i64>)
+        -> Result<(String, u8, String, i64), Box<dyn Error>> {
+    let platform = match platform {
         Some(platform) => platform,
-        None => return Err("platform metadata not found".into()),
+        None => match dataset.metadata_item("PLATFORM", "") {
+            Some(platform) => platform,
+            None => return Err("platform metadata not found".into()),
+        },
     };
 
-    let subdataset = match dataset.metadata_item("SUBDATASET", "") {
-        Some(subdataset) => subdataset.parse::<u8>()?,
-        None => return Err("subdataset metadata not found".into()),
+    let subdataset = match subdataset {
+        Some(subdataset) => subdataset,
+        None => match dataset.metadata_item("SUBDATASET", "") {
+            Some(subdataset) => subdataset.parse::<u8>()?,
+            None => return Err("subdataset metadata not found".into()),
+        },
     };
 
-    let tile = match dataset.metadata_item("TILE", "") {
+    let tile = match tile {
         Some(tile) => tile,
-        None => return Err("tile metadata not found".into()),
+        None => match dataset.metadata_item("TILE", "") {
+            Some(tile) => tile,
+            None => return Err("tile metadata not found".into()),
+        },
     };
 
-    let start_date = match dataset.metadata_item("TIMESTAMP", "") {
-        Some(date) => NaiveDate::parse_from_str(&date, "%Y%m%d")?,
-        None => return Err("start time metadata not found".into()),
+    let timestamp = match timestamp {
+        Some(timestamp) => timestamp,
+        None => match dataset.metadata_item("TIMESTAMP", "") {
+            Some(date) => NaiveDate::parse_from_str(&date, "%Y%m%d")?
+                .and_hms(0, 0, 0).timestamp(),
+            None => return Err("start time metadata not found".into()),
+        },
     };
 
-    let timestamp = start_date.and_hms(0, 0, 0).timestamp();
+    Ok((platform, subdataset, tile, timestamp))
+}
+
+// split a dataset into geocodes and send each to its replica hosts,
+// returning the geocodes written
+pub fn store(album: &Arc<RwLock<Album>>, cloud_mask: Option<CloudMask>,
+        dht: &Arc<Dht>, precision: usize, dataset: &Dataset,
+        platform: &str, subdataset: u8, tile: &str, timestamp: i64,
+        transaction: Option<&Transaction>)
+        -> Result<Vec<String>, Box<dyn Error>> {
+    // retrieve album metadata
+    let (album_id, dht_key_length, geocode) = {
+        let album = album.read().unwrap();
+        (album.get_id().to_string(), album.get_dht_key_length(),
+            album.get_geocode().clone())
+    };
+
+    // locate quality band for cloud masking
+    let mask = match cloud_mask {
+        Some(cloud_mask) => match cloud_mask.find_band(dataset) {
+            Some(band) => Some((cloud_mask, band)),
+            None => return Err(format!("{} band not found",
+                cloud_mask.band_name()).into()),
+        },
+        None => None,
+    };
 
     // compute geohash window boundaries for dataset
     let epsg_code = geocode.get_epsg_code();
     let (x_interval, y_interval) = geocode.get_intervals(precision);
 
     let (image_min_cx, image_max_cx, image_min_cy, image_max_cy) =
-        st_image::coordinate::get_bounds(&dataset, epsg_code)?;
+        st_image::coordinate::get_bounds(dataset, epsg_code)?;
 
     let window_bounds = st_image::coordinate::get_windows(
         image_min_cx, image_max_cx, image_min_cy, image_max_cy,
         x_interval, y_interval);
 
     // iterate over window bounds
+    let mut geocodes = Vec::new();
     for (min_cx, max_cx, min_cy, max_cy) in window_bounds {
+        // halt long records once the task is cancelled
+        crate::task::pool::check_cancelled()?;
+
         // perform dataset split
-        let split_dataset = match st_image::transform::split(&dataset,
+        let split_dataset = match st_image::transform::split(dataset,
                 min_cx, max_cx, min_cy, max_cy, epsg_code)? {
             Some(split_dataset) => split_dataset,
             None => continue,
@@ -66,6 +116,13 @@ pub fn process(album: &Arc<RwLock<Album>>, dht: &Arc<Dht>,
         let split_geocode = geocode.encode((min_cx + max_cx) / 2.0,
             (min_cy + max_cy) / 2.0, precision)?;
 
+        // write clouds and shadows as nodata, retaining the quality band
+        if let Some((cloud_mask, band)) = &mask {
+            let classes = cloud_mask.classify_band(&split_dataset,
+                *band, &split_dataset)?;
+            crate::mask::apply(&split_dataset, &classes, Some(*band))?;
+        }
+
         // if image has 0.0 coverage -> don't process
         let pixel_coverage = 
             st_image::get_coverage(&split_dataset)?;
@@ -74,23 +131,22 @@ pub fn process(album: &Arc<RwLock<Album>>, dht: &Arc<Dht>,
         }
 
         // lookup geocode in dht
-        let addr = match crate::task::dht_lookup(
+        let addrs = match crate::task::dht_lookup(
                 &dht, dht_key_length, &split_geocode) {
-            Ok(addr) => addr,
+            Ok(addrs) => addrs,
             Err(e) => {
                 warn!("{}", e);
                 continue;
             },
         };
 
-        // send image to new host
-        if let Err(e) = crate::transfer::send_image(&addr,
+        // send image to replica hosts
+        super::send_image(transaction, &addrs,
                 &album_id, &split_dataset, &split_geocode,
                 pixel_coverage, &platform,
-                &RAW_SOURCE, subdataset, &tile, timestamp) {
-            warn!("failed to write image to node {}: {}", addr, e);
-        }
+                &RAW_SOURCE, subdataset, &tile, timestamp)?;
+        geocodes.push(split_geocode);
     }
 
-    Ok(())
+    Ok(geocodes)
 }
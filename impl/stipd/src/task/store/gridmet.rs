@@ -8,6 +8,7 @@ use swarm::prelude::Dht;
 
 use crate::RAW_SOURCE;
 use crate::album::Album;
+use crate::transfer::Transaction;
 
 use std::collections::{BTreeMap, HashMap};
 use std::error::Error;
@@ -16,7 +17,8 @@ use std::path::PathBuf;
 use std::sync::{Arc, RwLock};
 
 pub fn process(album: &Arc<RwLock<Album>>, dht: &Arc<Dht>,
-        precision: usize, record: &PathBuf) -> Result<(), Box<dyn Error>> {
+        precision: usize, record: &PathBuf,
+        transaction: Option<&Transaction>) -> Result<(), Box<dyn Error>> {
     // retrieve album metadata
     let (album_id, dht_key_length, geocode) = {
         let album = album.read().unwrap();
@@ -91,7 +93,7 @@ pub fn process(album: &Arc<RwLock<Album>>, dht: &Arc<Dht>,
         };
 
         process_splits(&album_id, &datasets, &dht,
-            dht_key_length, i as u8, &tile, timestamp)?;
+            dht_key_length, i as u8, &tile, timestamp, transaction)?;
     }
 
     Ok(())
@@ -99,7 +101,8 @@ pub fn process(album: &Arc<RwLock<Album>>, dht: &Arc<Dht>,
 
 fn process_splits(album_id: &str, datasets: &HashMap<String, Dataset>,
         dht: &Arc<Dht>, dht_key_length: i8, subdataset: u8, 
-        tile: &str, timestamp: i64) -> Result<(), Box<dyn Error>> {
+        tile: &str, timestamp: i64, transaction: Option<&Transaction>)
+        -> Result<(), Box<dyn Error>> {
     for (geocode, dataset) in datasets.iter() {
         // if image has 0.0 coverage -> don't process
         let pixel_coverage = st_image::get_coverage(&dataset)?;
@@ -108,21 +111,19 @@ fn process_splits(album_id: &str, datasets: &HashMap<String, Dataset>,
         }
 
         // lookup geocode in dht
-        let addr = match crate::task::dht_lookup(
+        let addrs = match crate::task::dht_lookup(
                 &dht, dht_key_length, &geocode) {
-            Ok(addr) => addr,
+            Ok(addrs) => addrs,
             Err(e) => {
                 warn!("{}", e);
                 continue;
             },
         };
 
-        // send image to new host
-        if let Err(e) = crate::transfer::send_image(&addr, album_id,
+        // send image to replica hosts
+        super::send_image(transaction, &addrs, album_id,
                 &dataset, &geocode, pixel_coverage, "gridMET",
-                &RAW_SOURCE, subdataset, &tile, timestamp) {
-            warn!("failed to write image to node {}: {}", addr, e);
-        }
+                &RAW_SOURCE, subdataset, &tile, timestamp)?;
     }
 
     Ok(())
@@ -150,6 +151,9 @@ fn split_subdatasets<T: GdalType>(geocode: Geocode,
 
         // iterate over window bounds
         for (min_cx, max_cx, min_cy, max_cy) in window_bounds {
+            // halt long records once the task is cancelled
+            crate::task::pool::check_cancelled()?;
+
             // perform dataset split
             let split_dataset = match st_image::transform::split(&dataset,
                     min_cx, max_cx, min_cy, max_cy, epsg_code)? {
@@ -1,6 +1,6 @@
 use swarm::prelude::Dht;
 
-mod generic;
+pub mod generic;
 mod gridmet;
 mod modis;
 mod naip;
@@ -8,10 +8,16 @@ mod nlcd;
 mod sentinel2;
 mod viirs;
 
+use gdal::Dataset;
+use protobuf::ImageFormat as ProtoImageFormat;
+
 use crate::album::Album;
+use crate::mask::CloudMask;
 use crate::task::Task;
+use crate::transfer::Transaction;
 
 use std::error::Error;
+use std::net::SocketAddr;
 use std::path::PathBuf;
 use std::sync::{Arc, RwLock};
 
@@ -28,56 +34,182 @@ pub enum ImageFormat {
     VNP21V001,
 }
 
+impl ImageFormat {
+    pub fn supports_cloud_mask(&self, cloud_mask: CloudMask) -> bool {
+        match (self, cloud_mask) {
+            (ImageFormat::Generic, _) => true,
+            (ImageFormat::Sentinel2, CloudMask::Scl) => true,
+            _ => false,
+        }
+    }
+
+    pub fn from_proto(value: i32) -> Option<ImageFormat> {
+        match ProtoImageFormat::from_i32(value) {
+            Some(ProtoImageFormat::Generic) => Some(ImageFormat::Generic),
+            Some(ProtoImageFormat::Gridmet) => Some(ImageFormat::GridMET),
+            Some(ProtoImageFormat::Mcd43a4) => Some(ImageFormat::MCD43A4),
+            Some(ProtoImageFormat::Mod11a1) => Some(ImageFormat::MOD11A1),
+            Some(ProtoImageFormat::Mod11a2) => Some(ImageFormat::MOD11A2),
+            Some(ProtoImageFormat::Naip) => Some(ImageFormat::NAIP),
+            Some(ProtoImageFormat::Nlcd) => Some(ImageFormat::NLCD),
+            Some(ProtoImageFormat::Sentinel2) => Some(ImageFormat::Sentinel2),
+            Some(ProtoImageFormat::Vnp21v001) => Some(ImageFormat::VNP21V001),
+            None => None,
+        }
+    }
+}
+
+pub struct StorePair {
+    pub album: Arc<RwLock<Album>>,
+    pub format: ImageFormat,
+    pub pattern: String,
+    pub replacement: String,
+}
+
 pub struct StoreEarthExplorerTask {
     album: Arc<RwLock<Album>>,
+    cloud_mask: Option<CloudMask>,
     dht: Arc<Dht>,
     format: ImageFormat,
     glob: String,
+    pair: Option<StorePair>,
     precision: usize,
 }
 
 impl StoreEarthExplorerTask {
-    pub fn new(album: Arc<RwLock<Album>>, dht: Arc<Dht>,
-            format: ImageFormat, glob: String, precision: usize)
+    pub fn new(album: Arc<RwLock<Album>>, cloud_mask: Option<CloudMask>,
+            dht: Arc<Dht>, format: ImageFormat, glob: String,
+            pair: Option<StorePair>, precision: usize)
             -> StoreEarthExplorerTask {
         {
             let album = album.read().unwrap();
-            info!("initailizing store task [album={}, format={:?}, glob={}, precision={}]",
-                album.get_id(), format, glob, precision)
+            info!("initailizing store task [album={}, cloud_mask={:?}, format={:?}, glob={}, paired={}, precision={}]",
+                album.get_id(), cloud_mask, format, glob,
+                pair.is_some(), precision)
         }
             
         StoreEarthExplorerTask {
             album: album,
+            cloud_mask: cloud_mask,
             dht: dht,
             format: format,
             glob: glob,
+            pair: pair,
             precision: precision,
         }
     }
+
+    fn estimate_format(&self, album: &Arc<RwLock<Album>>,
+            format: &ImageFormat, record: &PathBuf)
+            -> Result<Option<Vec<String>>, Box<dyn Error>> {
+        // only formats stored as a single raster are estimated, others
+        //   must be unpacked into subdatasets before their extent is known
+        match format {
+            ImageFormat::Generic | ImageFormat::NLCD => (),
+            _ => return Ok(None),
+        }
+
+        // retrieve album metadata
+        let (dht_key_length, geocode) = {
+            let album = album.read().unwrap();
+            (album.get_dht_key_length(), album.get_geocode().clone())
+        };
+
+        let dataset = Dataset::open(record)?;
+        let split_geocodes = crate::task::split::split_geocodes(&dataset,
+            &geocode, None, "", self.precision)?;
+
+        crate::task::split::estimate_destinations(&self.dht,
+            dht_key_length, &split_geocodes).map(Some)
+    }
+
+    fn process_format(&self, album: &Arc<RwLock<Album>>,
+            format: &ImageFormat, record: &PathBuf,
+            transaction: Option<&Transaction>) -> Result<(), Box<dyn Error>> {
+        // paired formats which cannot be masked are loaded unmasked
+        let cloud_mask = self.cloud_mask
+            .filter(|x| format.supports_cloud_mask(*x));
+
+        match format {
+            ImageFormat::Generic => generic::process(album, cloud_mask,
+                &self.dht, self.precision, record, transaction),
+            ImageFormat::GridMET => gridmet::process(album, &self.dht,
+                self.precision, record, transaction),
+            ImageFormat::MCD43A4 => modis::process(album, "MCD43A4",
+                &self.dht, self.precision, record, transaction),
+            ImageFormat::MOD11A1 => modis::process(album, "MOD11A1",
+                &self.dht, self.precision, record, transaction),
+            ImageFormat::MOD11A2 => modis::process(album, "MOD11A2",
+                &self.dht, self.precision, record, transaction),
+            ImageFormat::NAIP => naip::process(album, &self.dht,
+                self.precision, record, transaction),
+            ImageFormat::NLCD => nlcd::process(album, &self.dht,
+                self.precision, record, transaction),
+            ImageFormat::Sentinel2 => sentinel2::process(album, cloud_mask,
+                &self.dht, self.precision, record, transaction),
+            ImageFormat::VNP21V001 => viirs::process(album, &self.dht,
+                self.precision, record, transaction),
+        }
+    }
 }
 
 #[tonic::async_trait]
 impl Task<PathBuf> for StoreEarthExplorerTask {
+    fn estimate(&self, record: &PathBuf)
+            -> Result<Option<Vec<String>>, Box<dyn Error>> {
+        let mut destinations = match self.estimate_format(&self.album,
+                &self.format, record)? {
+            Some(destinations) => destinations,
+            None => return Ok(None),
+        };
+
+        // include destinations of the paired record
+        if let Some(pair) = &self.pair {
+            let pair_record = PathBuf::from(record.to_string_lossy()
+                .replace(&pair.pattern, &pair.replacement));
+            match self.estimate_format(&pair.album,
+                    &pair.format, &pair_record)? {
+                Some(mut pair_destinations) =>
+                    destinations.append(&mut pair_destinations),
+                None => return Ok(None),
+            }
+        }
+
+        Ok(Some(destinations))
+    }
+
+    fn ingested_bytes(&self, record: &PathBuf) -> u64 {
+        std::fs::metadata(record).map(|x| x.len()).unwrap_or(0)
+    }
+
     fn process(&self, record: &PathBuf) -> Result<(), Box<dyn Error>> {
-        match self.format {
-            ImageFormat::Generic => generic::process(
-                &self.album, &self.dht, self.precision, &record),
-            ImageFormat::GridMET => gridmet::process(
-                &self.album, &self.dht, self.precision, &record),
-            ImageFormat::MCD43A4 => modis::process(&self.album,
-                "MCD43A4", &self.dht, self.precision, &record),
-            ImageFormat::MOD11A1 => modis::process(&self.album,
-                "MOD11A1", &self.dht, self.precision, &record),
-            ImageFormat::MOD11A2 => modis::process(&self.album,
-                "MOD11A2", &self.dht, self.precision, &record),
-            ImageFormat::NAIP => naip::process(
-                &self.album, &self.dht, self.precision, &record),
-            ImageFormat::NLCD => nlcd::process(
-                &self.album, &self.dht, self.precision, &record),
-            ImageFormat::Sentinel2 => sentinel2::process(
-                &self.album, &self.dht, self.precision, &record),
-            ImageFormat::VNP21V001 => viirs::process(
-                &self.album, &self.dht, self.precision, &record),
+        let pair = match &self.pair {
+            Some(pair) => pair,
+            None => return self.process_format(&self.album,
+                &self.format, record, None),
+        };
+
+        // locate paired record
+        let pair_record = PathBuf::from(record.to_string_lossy()
+            .replace(&pair.pattern, &pair.replacement));
+        if !pair_record.exists() {
+            return Err(format!("paired record '{:?}' does not exist",
+                pair_record).into());
+        }
+
+        // stage both records within a single transaction
+        let transaction = Transaction::new();
+        let result = self.process_format(&self.album, &self.format,
+                record, Some(&transaction))
+            .and_then(|_| self.process_format(&pair.album, &pair.format,
+                &pair_record, Some(&transaction)));
+
+        match result {
+            Ok(_) => transaction.commit(),
+            Err(e) => {
+                transaction.abort();
+                Err(e)
+            },
         }
     }
 
@@ -91,3 +223,24 @@ impl Task<PathBuf> for StoreEarthExplorerTask {
         Ok(records)
     }
 }
+
+fn send_image(transaction: Option<&Transaction>, addrs: &[SocketAddr],
+        album: &str, dataset: &Dataset, geocode: &str, pixel_coverage: f64,
+        platform: &str, source: &str, subdataset: u8, tile: &str,
+        timestamp: i64) -> Result<(), Box<dyn Error>> {
+    match transaction {
+        Some(transaction) => transaction.prepare_image(addrs, album,
+            dataset, geocode, pixel_coverage, platform, source,
+            subdataset, tile, timestamp),
+        None => {
+            // untransacted writes skip failed images
+            if let Err(e) = crate::transfer::send_image(addrs, album,
+                    dataset, geocode, pixel_coverage, platform,
+                    source, subdataset, tile, timestamp) {
+                warn!("{}", e);
+            }
+
+            Ok(())
+        },
+    }
+}
@@ -7,6 +7,7 @@ use swarm::prelude::Dht;
 
 use crate::RAW_SOURCE;
 use crate::album::Album;
+use crate::transfer::Transaction;
 
 use std::collections::{BTreeMap, HashMap};
 use std::error::Error;
@@ -16,7 +17,8 @@ use std::sync::{Arc, RwLock};
 
 pub fn process(album: &Arc<RwLock<Album>>, dataset_name: &str,
         dht: &Arc<Dht>, precision: usize, 
-        record: &PathBuf) -> Result<(), Box<dyn Error>> {
+        record: &PathBuf,
+        transaction: Option<&Transaction>) -> Result<(), Box<dyn Error>> {
     // retrieve album metadata
     let (album_id, dht_key_length, geocode) = {
         let album = album.read().unwrap();
@@ -92,8 +94,8 @@ pub fn process(album: &Arc<RwLock<Album>>, dataset_name: &str,
             _ => unreachable!(),
         };
 
-        process_splits(&album_id, &datasets, &dataset_name, 
-            &dht, dht_key_length, i as u8, &tile, timestamp)?;
+        process_splits(&album_id, &datasets, &dataset_name, &dht,
+            dht_key_length, i as u8, &tile, timestamp, transaction)?;
     }
 
     Ok(())
@@ -102,7 +104,8 @@ pub fn process(album: &Arc<RwLock<Album>>, dataset_name: &str,
 fn process_splits(album_id: &str,
         datasets: &HashMap<String, Dataset>, dataset_name: &str, 
         dht: &Arc<Dht>, dht_key_length: i8, subdataset: u8, 
-        tile: &str, timestamp: i64) -> Result<(), Box<dyn Error>> {
+        tile: &str, timestamp: i64, transaction: Option<&Transaction>)
+        -> Result<(), Box<dyn Error>> {
     for (geocode, dataset) in datasets.iter() {
         // if image has 0.0 coverage -> don't process
         let pixel_coverage = st_image::get_coverage(&dataset)?;
@@ -111,21 +114,19 @@ fn process_splits(album_id: &str,
         }
 
         // lookup geocode in dht
-        let addr = match crate::task::dht_lookup(
+        let addrs = match crate::task::dht_lookup(
                 &dht, dht_key_length, &geocode) {
-            Ok(addr) => addr,
+            Ok(addrs) => addrs,
             Err(e) => {
                 warn!("{}", e);
                 continue;
             },
         };
 
-        // send image to new host
-        if let Err(e) = crate::transfer::send_image(&addr, album_id,
+        // send image to replica hosts
+        super::send_image(transaction, &addrs, album_id,
                 &dataset, &geocode, pixel_coverage, dataset_name,
-                &RAW_SOURCE, subdataset, &tile, timestamp) {
-            warn!("failed to write image to node {}: {}", addr, e);
-        }
+                &RAW_SOURCE, subdataset, &tile, timestamp)?;
     }
 
     Ok(())
@@ -153,6 +154,9 @@ fn split_subdatasets<T: GdalType>(geocode: Geocode,
 
         // iterate over window bounds
         for (min_cx, max_cx, min_cy, max_cy) in window_bounds {
+            // halt long records once the task is cancelled
+            crate::task::pool::check_cancelled()?;
+
             // perform dataset split
             let split_dataset = match st_image::transform::split(&dataset,
                     min_cx, max_cx, min_cy, max_cy, epsg_code)? {
@@ -4,6 +4,7 @@ use swarm::prelude::Dht;
 
 use crate::RAW_SOURCE;
 use crate::album::Album;
+use crate::transfer::Transaction;
 
 use std::error::Error;
 use std::ffi::OsStr;
@@ -11,7 +12,8 @@ use std::path::PathBuf;
 use std::sync::{Arc, RwLock};
 
 pub fn process(album: &Arc<RwLock<Album>>, dht: &Arc<Dht>,
-        precision: usize, record: &PathBuf) -> Result<(), Box<dyn Error>> {
+        precision: usize, record: &PathBuf,
+        transaction: Option<&Transaction>) -> Result<(), Box<dyn Error>> {
     // retrieve album metadata
     let (album_id, dht_key_length, geocode) = {
         let album = album.read().unwrap();
@@ -54,6 +56,9 @@ pub fn process(album: &Arc<RwLock<Album>>, dht: &Arc<Dht>,
 
     // iterate over window bounds
     for (min_cx, max_cx, min_cy, max_cy) in window_bounds {
+        // halt long records once the task is cancelled
+        crate::task::pool::check_cancelled()?;
+
         // perform dataset split
         let split_dataset = match st_image::transform::split(&dataset,
                 min_cx, max_cx, min_cy, max_cy, epsg_code)? {
@@ -71,21 +76,19 @@ pub fn process(album: &Arc<RwLock<Album>>, dht: &Arc<Dht>,
         }
 
         // lookup geocode in dht
-        let addr = match crate::task::dht_lookup(
+        let addrs = match crate::task::dht_lookup(
                 &dht, dht_key_length, &split_geocode) {
-            Ok(addr) => addr,
+            Ok(addrs) => addrs,
             Err(e) => {
                 warn!("{}", e);
                 continue;
             },
         };
 
-        // send image to new host
-        if let Err(e) = crate::transfer::send_image(&addr, &album_id,
+        // send image to replica hosts
+        super::send_image(transaction, &addrs, &album_id,
                 &split_dataset, &split_geocode, pixel_coverage, "NAIP",
-                &RAW_SOURCE, 0, &tile, timestamp) {
-            warn!("failed to write image to node {}: {}", addr, e);
-        }
+                &RAW_SOURCE, 0, &tile, timestamp)?;
     }
 
     Ok(())
@@ -5,6 +5,8 @@ use zip::ZipArchive;
 
 use crate::RAW_SOURCE;
 use crate::album::Album;
+use crate::mask::CloudMask;
+use crate::transfer::Transaction;
 
 use std::error::Error;
 use std::ffi::OsStr;
@@ -13,8 +15,9 @@ use std::io::BufReader;
 use std::path::PathBuf;
 use std::sync::{Arc, RwLock};
 
-pub fn process(album: &Arc<RwLock<Album>>, dht: &Arc<Dht>,
-        precision: usize, record: &PathBuf) -> Result<(), Box<dyn Error>> {
+pub fn process(album: &Arc<RwLock<Album>>, cloud_mask: Option<CloudMask>,
+        dht: &Arc<Dht>, precision: usize, record: &PathBuf,
+        transaction: Option<&Transaction>) -> Result<(), Box<dyn Error>> {
     // retrieve album metadata
     let (album_id, dht_key_length, geocode) = {
         let album = album.read().unwrap();
@@ -39,7 +42,9 @@ pub fn process(album: &Arc<RwLock<Album>>, dht: &Arc<Dht>,
     for filename in archive.file_names() {
         let path = PathBuf::from(&filename);
 
-        if path.file_name() == Some(OsStr::new("MTD_MSIL1C.xml")) {
+        // level-2a products include the scene classification band
+        if path.file_name() == Some(OsStr::new("MTD_MSIL1C.xml"))
+                || path.file_name() == Some(OsStr::new("MTD_MSIL2A.xml")) {
             zip_metadata_option = Some(filename);
         }
     }
@@ -88,6 +93,27 @@ pub fn process(album: &Arc<RwLock<Album>>, dht: &Arc<Dht>,
         count += 2;
     }
 
+    // locate scene classification band for cloud masking
+    let mask = match cloud_mask {
+        Some(cloud_mask) => {
+            let mut mask = None;
+            for (i, (name, _)) in subdatasets.iter().enumerate() {
+                let dataset = Dataset::open(&PathBuf::from(name))?;
+                if let Some(band) = cloud_mask.find_band(&dataset) {
+                    mask = Some((cloud_mask, dataset, band, i));
+                    break;
+                }
+            }
+
+            match mask {
+                Some(mask) => Some(mask),
+                None => return Err(format!("{} band not found, cloud masks require level-2A products",
+                    cloud_mask.band_name()).into()),
+            }
+        },
+        None => None,
+    };
+
     // process data subsets
     for (i, (name, _)) in subdatasets.iter().enumerate() {
         // open dataset
@@ -107,6 +133,9 @@ pub fn process(album: &Arc<RwLock<Album>>, dht: &Arc<Dht>,
 
         // iterate over window bounds
         for (min_cx, max_cx, min_cy, max_cy) in window_bounds {
+            // halt long records once the task is cancelled
+            crate::task::pool::check_cancelled()?;
+
             // perform dataset split
             let split_dataset = match st_image::transform::split(&dataset,
                     min_cx, max_cx, min_cy, max_cy, epsg_code)? {
@@ -117,6 +146,18 @@ pub fn process(album: &Arc<RwLock<Album>>, dht: &Arc<Dht>,
             let split_geocode = geocode.encode((min_cx + max_cx) / 2.0,
                 (min_cy + max_cy) / 2.0, precision)?;
 
+            // write clouds and shadows as nodata
+            if let Some((cloud_mask, mask_dataset, band, j)) = &mask {
+                if let Some(split_mask) = st_image::transform::split(
+                        mask_dataset, min_cx, max_cx, min_cy, max_cy,
+                        epsg_code)? {
+                    let classes = cloud_mask.classify_band(&split_mask,
+                        *band, &split_dataset)?;
+                    let skip_band = if i == *j { Some(*band) } else { None };
+                    crate::mask::apply(&split_dataset, &classes, skip_band)?;
+                }
+            }
+
             // if image has 0.0 coverage -> don't process
             let pixel_coverage = 
                 st_image::get_coverage(&split_dataset)?;
@@ -125,22 +166,20 @@ pub fn process(album: &Arc<RwLock<Album>>, dht: &Arc<Dht>,
             }
 
             // lookup geocode in dht
-            let addr = match crate::task::dht_lookup(
+            let addrs = match crate::task::dht_lookup(
                     &dht, dht_key_length, &split_geocode) {
-                Ok(addr) => addr,
+                Ok(addrs) => addrs,
                 Err(e) => {
                     warn!("{}", e);
                     continue;
                 },
             };
 
-            // send image to new host
-            if let Err(e) = crate::transfer::send_image(&addr,
+            // send image to replica hosts
+            super::send_image(transaction, &addrs,
                     &album_id, &split_dataset, &split_geocode,
                     pixel_coverage, "Sentinel-2",
-                    &RAW_SOURCE, i as u8, &tile, timestamp) {
-                warn!("failed to write image to node {}: {}", addr, e);
-            }
+                    &RAW_SOURCE, i as u8, &tile, timestamp)?;
         }
     }
 
@@ -0,0 +1,116 @@
+use crate::{Image, StFile};
+use crate::album::Album;
+use crate::task::Task;
+use crate::task::logger::LogContext;
+
+use std::error::Error;
+use std::sync::{Arc, RwLock};
+
+pub struct UpdateTask {
+    album: Arc<RwLock<Album>>,
+    cloud_coverage: Option<f64>,
+    end_timestamp: Option<i64>,
+    geocode: Option<String>,
+    max_cloud_coverage: Option<f64>,
+    min_pixel_coverage: Option<f64>,
+    platform: Option<String>,
+    recurse: bool,
+    source: Option<String>,
+    start_timestamp: Option<i64>,
+    timestamp: Option<i64>,
+    timestamp_offset: Option<i64>,
+}
+
+impl UpdateTask {
+    pub fn new(album: Arc<RwLock<Album>>, cloud_coverage: Option<f64>,
+            end_timestamp: Option<i64>, geocode: Option<String>,
+            max_cloud_coverage: Option<f64>,
+            min_pixel_coverage: Option<f64>, platform: Option<String>,
+            recurse: bool, source: Option<String>,
+            start_timestamp: Option<i64>, timestamp: Option<i64>,
+            timestamp_offset: Option<i64>)
+            -> Result<UpdateTask, Box<dyn Error>> {
+        {
+            let album = album.read().unwrap();
+            info!("initailizing update task [album={}, cloud_coverage={:?}, end_timestamp={:?}, geocode={:?}, max_cloud_coverage={:?}, min_pixel_coverage={:?}, platform={:?}, recurse={}, source={:?}, start_timestamp={:?}, timestamp={:?}, timestamp_offset={:?}]",
+                album.get_id(), cloud_coverage, end_timestamp, geocode,
+                max_cloud_coverage, min_pixel_coverage, platform, recurse,
+                source, start_timestamp, timestamp, timestamp_offset);
+
+            // fail before listing images if the album is held
+            album.assert_modifiable()?;
+        }
+
+        if let Some(cloud_coverage) = cloud_coverage {
+            if cloud_coverage < 0.0 || cloud_coverage > 1.0 {
+                return Err(format!("cloud coverage {} is not within [0, 1]",
+                    cloud_coverage).into());
+            }
+        }
+
+        match (cloud_coverage, timestamp, timestamp_offset) {
+            (_, Some(_), Some(_)) => return Err(
+                "timestamp and timestamp offset are mutually exclusive"
+                .into()),
+            (None, None, None) => return Err(
+                "update requires at least one metadata value".into()),
+            _ => {},
+        }
+
+        Ok(UpdateTask {
+            album: album,
+            cloud_coverage: cloud_coverage,
+            end_timestamp: end_timestamp,
+            geocode: geocode,
+            max_cloud_coverage: max_cloud_coverage,
+            min_pixel_coverage: min_pixel_coverage,
+            platform: platform,
+            recurse: recurse,
+            source: source,
+            start_timestamp: start_timestamp,
+            timestamp: timestamp,
+            timestamp_offset: timestamp_offset,
+        })
+    }
+}
+
+#[tonic::async_trait]
+impl Task<(Image, StFile)> for UpdateTask {
+    fn process(&self, record: &(Image, StFile))
+            -> Result<(), Box<dyn Error>> {
+        let (image, file) = record;
+
+        // offsets correct timestamps relative to the indexed value
+        let timestamp = match self.timestamp_offset {
+            Some(offset) => Some(image.5 + offset),
+            None => self.timestamp,
+        };
+
+        let mut album = self.album.write().unwrap();
+        album.update(&image.1, &image.2, &image.3, file.2, &image.4,
+            self.cloud_coverage, timestamp)
+    }
+
+    fn log_context(&self, record: &(Image, StFile)) -> LogContext {
+        LogContext::new(&self.album, &(record.0).1)
+    }
+
+    async fn records(&self)
+            -> Result<Vec<(Image, StFile)>, Box<dyn Error>> {
+        // search for images using Album
+        let album = self.album.read().unwrap();
+        let images = album.list(&self.end_timestamp, &self.geocode,
+            &self.max_cloud_coverage, &self.min_pixel_coverage,
+            &self.platform, self.recurse, &self.source,
+            &self.start_timestamp)?;
+
+        let mut records = Vec::new();
+        for (image, files) in images.into_iter() {
+            for file in files.into_iter() {
+                records.push((image.clone(), file));
+            }
+        }
+
+        Ok(records)
+    }
+}
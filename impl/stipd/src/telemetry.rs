@@ -0,0 +1,368 @@
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use serde_json::{json, Value};
+use tonic::codegen::http;
+
+use std::cell::Cell;
+use std::error::Error;
+use std::future::Future;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpStream;
+use std::pin::Pin;
+use std::sync::{Mutex, RwLock};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::task::{Context, Poll};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+// spans are buffered and exported in batches, dropping the newest
+//   when the collector falls behind
+const EXPORT_BATCH_SIZE: usize = 512;
+const EXPORT_INTERVAL: Duration = Duration::from_secs(5);
+const EXPORT_TIMEOUT: Duration = Duration::from_secs(10);
+const MAX_QUEUED_SPANS: usize = 8192;
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static QUEUE: Mutex<Vec<Value>> = Mutex::new(Vec::new());
+static RESOURCE: RwLock<Option<Value>> = RwLock::new(None);
+
+thread_local! {
+    static CONTEXT: Cell<Option<SpanContext>> = Cell::new(None);
+}
+
+// ids identifying a span and the trace it belongs to
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SpanContext {
+    pub span_id: u64,
+    pub trace_id: u128,
+}
+
+impl SpanContext {
+    // context of the span entered on the current thread
+    pub fn current() -> Option<SpanContext> {
+        CONTEXT.with(|x| x.get())
+    }
+
+    // parse a w3c 'traceparent' header value
+    pub fn from_traceparent(value: &str) -> Option<SpanContext> {
+        let fields: Vec<&str> = value.trim().split('-').collect();
+        if fields.len() < 4 || fields[1].len() != 32
+                || fields[2].len() != 16 {
+            return None;
+        }
+
+        let trace_id = u128::from_str_radix(fields[1], 16).ok()?;
+        let span_id = u64::from_str_radix(fields[2], 16).ok()?;
+        match trace_id != 0 && span_id != 0 {
+            true => Some(SpanContext {
+                span_id: span_id,
+                trace_id: trace_id,
+            }),
+            false => None,
+        }
+    }
+
+    pub fn to_traceparent(&self) -> String {
+        format!("00-{:032x}-{:016x}-01", self.trace_id, self.span_id)
+    }
+
+    // contexts are prefixed to transfer requests with a presence flag
+    pub fn read<T: Read>(reader: &mut T)
+            -> Result<Option<SpanContext>, Box<dyn Error>> {
+        match reader.read_u8()? {
+            0 => Ok(None),
+            _ => Ok(Some(SpanContext {
+                trace_id: reader.read_u128::<BigEndian>()?,
+                span_id: reader.read_u64::<BigEndian>()?,
+            })),
+        }
+    }
+
+    pub fn write<T: Write>(context: &Option<SpanContext>, writer: &mut T)
+            -> Result<(), Box<dyn Error>> {
+        match context {
+            Some(context) => {
+                writer.write_u8(1)?;
+                writer.write_u128::<BigEndian>(context.trace_id)?;
+                writer.write_u64::<BigEndian>(context.span_id)?;
+            },
+            None => writer.write_u8(0)?,
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum SpanKind {
+    Client = 3,
+    Internal = 1,
+    Server = 2,
+}
+
+struct SpanData {
+    attributes: Vec<(String, String)>,
+    context: SpanContext,
+    error: Option<String>,
+    kind: SpanKind,
+    name: String,
+    parent: Option<SpanContext>,
+    start: SystemTime,
+}
+
+// timed operation exported when dropped, spans are no-ops while
+//   tracing is disabled
+pub struct Span {
+    data: Option<SpanData>,
+    entered: bool,
+    previous: Option<SpanContext>,
+}
+
+impl Span {
+    pub fn new(name: &str, kind: SpanKind, parent: Option<SpanContext>)
+            -> Span {
+        let data = match ENABLED.load(Ordering::Relaxed) {
+            true => Some(SpanData {
+                attributes: Vec::new(),
+                context: SpanContext {
+                    span_id: random_id(),
+                    trace_id: match &parent {
+                        Some(parent) => parent.trace_id,
+                        None => random_id(),
+                    },
+                },
+                error: None,
+                kind: kind,
+                name: name.to_string(),
+                parent: parent,
+                start: SystemTime::now(),
+            }),
+            false => None,
+        };
+
+        Span {
+            data: data,
+            entered: false,
+            previous: None,
+        }
+    }
+
+    pub fn context(&self) -> Option<SpanContext> {
+        self.data.as_ref().map(|x| x.context)
+    }
+
+    // make this the current span of the thread until dropped
+    pub fn enter(mut self) -> Span {
+        if let Some(data) = &self.data {
+            self.previous = CONTEXT.with(|x| x.replace(Some(data.context)));
+            self.entered = true;
+        }
+
+        self
+    }
+
+    pub fn set_attribute(&mut self, key: &str, value: &str) {
+        if let Some(data) = &mut self.data {
+            data.attributes.push((key.to_string(), value.to_string()));
+        }
+    }
+
+    pub fn set_error(&mut self, error: &str) {
+        if let Some(data) = &mut self.data {
+            data.error = Some(error.to_string());
+        }
+    }
+}
+
+impl Drop for Span {
+    fn drop(&mut self) {
+        if self.entered {
+            CONTEXT.with(|x| x.set(self.previous));
+        }
+
+        let data = match self.data.take() {
+            Some(data) => data,
+            None => return,
+        };
+
+        let attributes: Vec<Value> = data.attributes.iter()
+            .map(|(key, value)| json!({
+                "key": key,
+                "value": {"stringValue": value},
+            })).collect();
+
+        let status = match &data.error {
+            Some(error) => json!({"code": 2, "message": error}),
+            None => json!({"code": 0}),
+        };
+
+        let mut span = json!({
+            "attributes": attributes,
+            "endTimeUnixNano": unix_nanos(SystemTime::now()).to_string(),
+            "kind": data.kind as u8,
+            "name": data.name,
+            "spanId": format!("{:016x}", data.context.span_id),
+            "startTimeUnixNano": unix_nanos(data.start).to_string(),
+            "status": status,
+            "traceId": format!("{:032x}", data.context.trace_id),
+        });
+
+        if let Some(parent) = &data.parent {
+            span["parentSpanId"] = json!(format!("{:016x}", parent.span_id));
+        }
+
+        let mut queue = QUEUE.lock().unwrap();
+        if queue.len() < MAX_QUEUED_SPANS {
+            queue.push(span);
+        }
+    }
+}
+
+// future polled within a span context, so spans created by the code it
+//   runs are children even as it moves between runtime threads
+pub struct Instrumented<F> {
+    context: Option<SpanContext>,
+    future: Pin<Box<F>>,
+}
+
+impl<F: Future> Future for Instrumented<F> {
+    type Output = F::Output;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>)
+            -> Poll<Self::Output> {
+        let previous = CONTEXT.with(|x| x.replace(self.context));
+        let result = self.future.as_mut().poll(cx);
+        CONTEXT.with(|x| x.set(previous));
+        result
+    }
+}
+
+pub fn instrument<F: Future>(context: Option<SpanContext>, future: F)
+        -> Instrumented<F> {
+    Instrumented {
+        context: context,
+        future: Box::pin(future),
+    }
+}
+
+// add the current trace context to outgoing rpc headers
+pub fn inject(headers: &mut http::HeaderMap) {
+    let context = match SpanContext::current() {
+        Some(context) => context,
+        None => return,
+    };
+
+    if let Ok(value) =
+            http::HeaderValue::from_str(&context.to_traceparent()) {
+        headers.insert("traceparent", value);
+    }
+}
+
+// child span of the current thread context, entered until dropped
+pub fn span(name: &str, kind: SpanKind) -> Span {
+    Span::new(name, kind, SpanContext::current()).enter()
+}
+
+pub fn configure(endpoint: &str, node_id: u32)
+        -> Result<(), Box<dyn Error>> {
+    let (host, path) = parse_endpoint(endpoint)?;
+
+    *RESOURCE.write().unwrap() = Some(json!({
+        "attributes": [
+            {"key": "service.name", "value": {"stringValue": "stipd"}},
+            {"key": "service.instance.id",
+                "value": {"stringValue": node_id.to_string()}},
+        ],
+    }));
+    ENABLED.store(true, Ordering::SeqCst);
+
+    // periodically export buffered spans
+    std::thread::spawn(move || {
+        loop {
+            std::thread::sleep(EXPORT_INTERVAL);
+
+            loop {
+                let spans: Vec<Value> = {
+                    let mut queue = QUEUE.lock().unwrap();
+                    let len = queue.len().min(EXPORT_BATCH_SIZE);
+                    queue.drain(..len).collect()
+                };
+
+                if spans.is_empty() {
+                    break;
+                }
+
+                if let Err(e) = export(&host, &path, spans) {
+                    warn!("failed to export spans to '{}': {}", host, e);
+                    break;
+                }
+            }
+        }
+    });
+
+    Ok(())
+}
+
+fn export(host: &str, path: &str, spans: Vec<Value>)
+        -> Result<(), Box<dyn Error>> {
+    let body = serde_json::to_vec(&json!({
+        "resourceSpans": [{
+            "resource": RESOURCE.read().unwrap().clone(),
+            "scopeSpans": [{
+                "scope": {"name": "stipd"},
+                "spans": spans,
+            }],
+        }],
+    }))?;
+
+    // post spans using the otlp http json encoding
+    let mut stream = TcpStream::connect(host)?;
+    stream.set_read_timeout(Some(EXPORT_TIMEOUT))?;
+    stream.set_write_timeout(Some(EXPORT_TIMEOUT))?;
+    write!(stream, "POST {} HTTP/1.1\r\nHost: {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        path, host, body.len())?;
+    stream.write_all(&body)?;
+    stream.flush()?;
+
+    let mut status_line = String::new();
+    BufReader::new(stream).read_line(&mut status_line)?;
+    match status_line.split_whitespace().nth(1) {
+        Some(status) if status.starts_with('2') => Ok(()),
+        _ => Err(format!("collector replied '{}'",
+            status_line.trim()).into()),
+    }
+}
+
+// split an 'http://host:port[/path]' endpoint, defaulting to the otlp
+//   traces path
+fn parse_endpoint(endpoint: &str)
+        -> Result<(String, String), Box<dyn Error>> {
+    let address = match endpoint.strip_prefix("http://") {
+        Some(address) => address,
+        None => return Err(format!(
+            "otlp endpoint '{}' must be an http url", endpoint).into()),
+    };
+
+    let (host, path) = match address.find('/') {
+        Some(index) => (&address[..index], &address[index..]),
+        None => (address, "/v1/traces"),
+    };
+
+    match host.contains(':') {
+        true => Ok((host.to_string(), path.to_string())),
+        false => Ok((format!("{}:4318", host), path.to_string())),
+    }
+}
+
+fn random_id<T>() -> T where rand::distributions::Standard:
+        rand::distributions::Distribution<T>, T: Default + PartialEq {
+    // zero ids are invalid in w3c and otlp contexts
+    loop {
+        let id = rand::random::<T>();
+        if id != T::default() {
+            return id;
+        }
+    }
+}
+
+fn unix_nanos(time: SystemTime) -> u128 {
+    time.duration_since(UNIX_EPOCH).map(|x| x.as_nanos()).unwrap_or(0)
+}
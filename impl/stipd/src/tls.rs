@@ -0,0 +1,201 @@
+use rustls::{AllowAnyAuthenticatedClient, ClientConfig, ClientSession, NoClientAuth, RootCertStore, ServerConfig, ServerSession, Session, StreamOwned};
+use protobuf::TokenChannel;
+use rustls::internal::pemfile;
+use tonic::transport::{Certificate, Channel, ClientTlsConfig, Identity, ServerTlsConfig};
+use webpki::DNSNameRef;
+
+use std::error::Error;
+use std::io::{BufReader, Cursor, Read, Write};
+use std::net::{SocketAddr, TcpStream};
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+
+static TLS: RwLock<Option<Arc<TlsConfig>>> = RwLock::new(None);
+
+struct TlsConfig {
+    ca: Vec<u8>,
+    cert: Vec<u8>,
+    client_auth: bool,
+    client_config: Arc<ClientConfig>,
+    domain: String,
+    http_config: Arc<ServerConfig>,
+    key: Vec<u8>,
+    server_config: Arc<ServerConfig>,
+}
+
+pub enum TransferStream {
+    Plain(TcpStream),
+    Tls(StreamOwned<ClientSession, TcpStream>),
+}
+
+impl Read for TransferStream {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        match self {
+            TransferStream::Plain(stream) => stream.read(buf),
+            TransferStream::Tls(stream) => stream.read(buf),
+        }
+    }
+}
+
+impl Write for TransferStream {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match self {
+            TransferStream::Plain(stream) => stream.write(buf),
+            TransferStream::Tls(stream) => stream.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match self {
+            TransferStream::Plain(stream) => stream.flush(),
+            TransferStream::Tls(stream) => stream.flush(),
+        }
+    }
+}
+
+pub fn configure(cert: &PathBuf, key: &PathBuf, ca: &Option<PathBuf>,
+        client_auth: bool, domain: &str) -> Result<(), Box<dyn Error>> {
+    // nodes verify each other against the cluster ca
+    let ca = match ca {
+        Some(ca) => std::fs::read(ca)?,
+        None => return Err("tls requires a ca certificate".into()),
+    };
+
+    let cert = std::fs::read(cert)?;
+    let key = std::fs::read(key)?;
+
+    // parse pem encoded certificates and keys
+    let certs = pemfile::certs(&mut BufReader::new(Cursor::new(&cert)))
+        .map_err(|_| "failed to parse tls certificate")?;
+    let private_key = parse_private_key(&key)?;
+
+    let mut roots = RootCertStore::empty();
+    roots.add_pem_file(&mut BufReader::new(Cursor::new(&ca)))
+        .map_err(|_| "failed to parse tls ca certificate")?;
+
+    // initialize transfer server and client configurations
+    let mut server_config = match client_auth {
+        true => ServerConfig::new(
+            AllowAnyAuthenticatedClient::new(roots.clone())),
+        false => ServerConfig::new(NoClientAuth::new()),
+    };
+    server_config.set_single_cert(certs.clone(), private_key.clone())?;
+
+    // http clients authenticate with bearer tokens rather than certificates
+    let mut http_config = ServerConfig::new(NoClientAuth::new());
+    http_config.set_single_cert(certs.clone(), private_key.clone())?;
+
+    let mut client_config = ClientConfig::new();
+    client_config.root_store = roots;
+    client_config.set_single_client_cert(certs, private_key);
+
+    // validate domain before any connections are attempted
+    DNSNameRef::try_from_ascii_str(domain)
+        .map_err(|_| format!("invalid tls domain '{}'", domain))?;
+
+    *TLS.write().unwrap() = Some(Arc::new(TlsConfig {
+        ca: ca,
+        cert: cert,
+        client_auth: client_auth,
+        client_config: Arc::new(client_config),
+        domain: domain.to_string(),
+        http_config: Arc::new(http_config),
+        key: key,
+        server_config: Arc::new(server_config),
+    }));
+
+    Ok(())
+}
+
+// channel to a cluster node authenticated as this node
+pub async fn channel(addr: &str) -> Result<TokenChannel, Box<dyn Error>> {
+    token_channel(addr, crate::auth::cluster_token().as_deref()).await
+}
+
+pub fn connect(addr: &SocketAddr) -> std::io::Result<TransferStream> {
+    let stream = TcpStream::connect(addr)?;
+    let tls = match TLS.read().unwrap().clone() {
+        Some(tls) => tls,
+        None => return Ok(TransferStream::Plain(stream)),
+    };
+
+    // domain is validated when tls is configured
+    let domain = DNSNameRef::try_from_ascii_str(&tls.domain).unwrap();
+    let session = ClientSession::new(&tls.client_config, domain);
+    Ok(TransferStream::Tls(StreamOwned::new(session, stream)))
+}
+
+// session wrapping connections to the stac, gateway, and metrics servers
+pub fn http_session() -> Option<ServerSession> {
+    let tls = TLS.read().unwrap().clone()?;
+    Some(ServerSession::new(&tls.http_config))
+}
+
+pub fn is_enabled() -> bool {
+    TLS.read().unwrap().is_some()
+}
+
+pub fn server_config() -> Option<ServerTlsConfig> {
+    let tls = TLS.read().unwrap().clone()?;
+    let mut config = ServerTlsConfig::new()
+        .identity(Identity::from_pem(&tls.cert, &tls.key));
+    if tls.client_auth {
+        config = config.client_ca_root(Certificate::from_pem(&tls.ca));
+    }
+
+    Some(config)
+}
+
+pub fn server_session() -> Option<ServerSession> {
+    let tls = TLS.read().unwrap().clone()?;
+    Some(ServerSession::new(&tls.server_config))
+}
+
+pub fn shutdown(session: &mut ServerSession, stream: &mut TcpStream)
+        -> std::io::Result<()> {
+    // send close notify so clients reading to eof see a clean end
+    session.send_close_notify();
+    while session.wants_write() {
+        session.write_tls(stream)?;
+    }
+
+    Ok(())
+}
+
+// channel to a cluster node authenticated with the provided token
+pub async fn token_channel(addr: &str, token: Option<&str>)
+        -> Result<TokenChannel, Box<dyn Error>> {
+    let tls = TLS.read().unwrap().clone();
+    let channel = match tls {
+        Some(tls) => {
+            let config = ClientTlsConfig::new()
+                .ca_certificate(Certificate::from_pem(&tls.ca))
+                .domain_name(tls.domain.clone())
+                .identity(Identity::from_pem(&tls.cert, &tls.key));
+
+            Channel::from_shared(format!("https://{}", addr))?
+                .tls_config(config).connect().await?
+        },
+        None => Channel::from_shared(format!("http://{}", addr))?
+            .connect().await?,
+    };
+
+    TokenChannel::new(channel, token)
+}
+
+fn parse_private_key(key: &[u8])
+        -> Result<rustls::PrivateKey, Box<dyn Error>> {
+    let mut keys = pemfile::pkcs8_private_keys(
+            &mut BufReader::new(Cursor::new(key)))
+        .map_err(|_| "failed to parse tls private key")?;
+    if keys.is_empty() {
+        keys = pemfile::rsa_private_keys(
+                &mut BufReader::new(Cursor::new(key)))
+            .map_err(|_| "failed to parse tls private key")?;
+    }
+
+    match keys.into_iter().next() {
+        Some(key) => Ok(key),
+        None => Err("no private key found in tls key file".into()),
+    }
+}
@@ -1,169 +1,769 @@
-use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use byteorder::{BigEndian, LittleEndian, ReadBytesExt, WriteBytesExt};
 use comm::StreamHandler;
-use gdal::Dataset;
+use gdal::{Dataset, Metadata};
 use geocode::Geocode;
 use num_derive::FromPrimitive;
 use num_traits::FromPrimitive;
+use swarm::prelude::Dht;
+use twox_hash::XxHash64;
 
-use crate::album::AlbumManager;
+use crate::accounting::UsageLedger;
+use crate::album::{AlbumManager, StagedImage};
+use crate::metrics::Counter;
+use crate::task::logger::LogContext;
+use crate::telemetry::{Span, SpanContext, SpanKind};
 
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::error::Error;
-use std::io::{Read, Write};
+use std::ffi::CString;
+use std::hash::Hasher;
+use std::io::{Cursor, Read, Write};
+use std::os::raw::{c_int, c_void};
 use std::net::{TcpStream, SocketAddr};
-use std::path::PathBuf;
-use std::sync::{Arc, RwLock};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex, RwLock};
+use std::sync::atomic::{AtomicU64, AtomicU8, Ordering};
+use std::time::{Duration, Instant};
 
-#[derive(FromPrimitive)]
+static COMPRESSION: AtomicU8 = AtomicU8::new(Compression::None as u8);
+static RATE_LIMIT: AtomicU64 = AtomicU64::new(0);
+static RATE_LIMIT_NEXT: Mutex<Option<Instant>> = Mutex::new(None);
+static POOL: Mutex<Vec<(SocketAddr, Connection)>> = Mutex::new(Vec::new());
+static PEERS: RwLock<Option<Arc<Dht>>> = RwLock::new(None);
+
+// weight of the newest sample in replica latency averages
+const LATENCY_EWMA_ALPHA: f64 = 0.2;
+// latency recorded for replicas which fail to serve a read
+const LATENCY_FAILURE_PENALTY_MS: f64 = 10000.0;
+// header opening versioned transfer streams, the first byte must never
+// collide with an operation type so legacy unframed requests are detected
+const PROTOCOL_MAGIC: [u8; 4] = *b"STXF";
+pub const PROTOCOL_VERSION: u8 = 6;
+// version from which connections carry multiple framed requests
+const PERSISTENT_VERSION: u8 = 2;
+// version from which senders present the cluster token
+const AUTH_VERSION: u8 = 3;
+// version from which requests are prefixed with the sender trace context
+const TRACE_VERSION: u8 = 4;
+// version from which image writes are framed with a checksum
+const CHECKSUM_VERSION: u8 = 5;
+// version from which replies are streamed as frames of at most a chunk,
+//   ended by an empty frame, rather than buffered into one frame
+const STREAM_VERSION: u8 = 6;
+// payloads corrupted in transit are resent to the same node this many
+//   times before falling back to later ring nodes
+const CHECKSUM_RETRY_ATTEMPTS: u32 = 2;
+const CHECKSUM_MISMATCH: &str = "checksum mismatch";
+// idle persistent connections are closed by nodes after this timeout and
+// discarded by senders a while before, to avoid reusing closed streams
+const KEEPALIVE_TIMEOUT: Duration = Duration::from_secs(10);
+const POOL_IDLE_TIMEOUT: Duration = Duration::from_secs(5);
+const POOL_MAX_IDLE: usize = 8;
+// requests written ahead of their replies on a pipelined connection
+const PIPELINE_DEPTH: usize = 64;
+// frames, and the strings and tiles within them, larger than this are
+// rejected so malformed lengths cannot exhaust memory
+const MAX_FRAME_SIZE: u64 = 1024 * 1024 * 1024;
+// requests larger than a chunk are uploaded in resumable pieces
+const TRANSFER_CHUNK_SIZE: usize = 4 * 1024 * 1024;
+const TRANSFER_RETRY_ATTEMPTS: u32 = 5;
+const TRANSFER_RETRY_BACKOFF_MS: u64 = 100;
+// partial uploads idle longer than this are discarded
+const UPLOAD_TIMEOUT: Duration = Duration::from_secs(600);
+// staged transactions idle longer than this are aborted, their
+// coordinator having failed before committing
+const TRANSACTION_TIMEOUT: Duration = Duration::from_secs(1800);
+const ZSTD_LEVEL: i32 = 3;
+// strings shorter than this keep the single length byte understood by
+// earlier releases, longer strings follow it with a u32 length
+const STRING_EXTENDED_LENGTH: u8 = 255;
+
+/// Codecs negotiated at the start of a transfer stream.
+///
+/// Values are sent over the wire and must never be renumbered.
+#[derive(Clone, Copy, Debug, FromPrimitive, PartialEq)]
+pub enum Compression {
+    None = 0,
+    Lz4 = 1,
+    Zstd = 2,
+}
+
+impl Compression {
+    // uncompressed frames are passed through without copying
+    fn compress<'a>(&self, buf: &'a [u8])
+            -> Result<Cow<'a, [u8]>, Box<dyn Error>> {
+        match self {
+            Compression::None => Ok(Cow::Borrowed(buf)),
+            Compression::Lz4 => Ok(Cow::Owned(
+                lz4::block::compress(buf, None, true)?)),
+            Compression::Zstd => Ok(Cow::Owned(
+                zstd::stream::encode_all(buf, ZSTD_LEVEL)?)),
+        }
+    }
+
+    fn decompress(&self, buf: Vec<u8>) -> Result<Vec<u8>, Box<dyn Error>> {
+        match self {
+            Compression::None => Ok(buf),
+            Compression::Lz4 => {
+                // blocks are prefixed with their decompressed length
+                let len = match buf.len() >= 4 {
+                    true => (&buf[..4]).read_u32::<LittleEndian>()?,
+                    false => 0,
+                };
+
+                check_frame_size(len as u64)?;
+                Ok(lz4::block::decompress(&buf, None)?)
+            },
+            Compression::Zstd => {
+                let mut decoded = Vec::new();
+                zstd::stream::read::Decoder::new(&buf[..])?
+                    .take(MAX_FRAME_SIZE + 1).read_to_end(&mut decoded)?;
+                check_frame_size(decoded.len() as u64)?;
+                Ok(decoded)
+            },
+        }
+    }
+}
+
+impl FromStr for Compression {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(Compression::None),
+            "lz4" => Ok(Compression::Lz4),
+            "zstd" => Ok(Compression::Zstd),
+            _ => Err(format!("unknown compression '{}'", s)),
+        }
+    }
+}
+
+#[derive(Debug, FromPrimitive)]
 enum TransferOp {
     ReadImage = 0,
     WriteImage = 1,
+    PrepareImage = 2,
+    CommitTransaction = 3,
+    AbortTransaction = 4,
+    Negotiate = 5,
+    AppendChunk = 6,
+    UploadOffset = 7,
+    CompleteUpload = 8,
+}
+
+struct Connection {
+    compression: Compression,
+    idle_since: Instant,
+    stream: crate::tls::TransferStream,
+    version: u8,
+}
+
+impl Connection {
+    fn open(addr: &SocketAddr) -> Result<Connection, Box<dyn Error>> {
+        let mut stream = crate::tls::connect(addr)?;
+
+        // nodes predating versioned streams read one unframed request
+        let peer_version = peer_version(addr);
+        if is_unframed(peer_version) {
+            return Ok(Connection {
+                compression: Compression::None,
+                idle_since: Instant::now(),
+                stream: stream,
+                version: 0,
+            });
+        }
+
+        // negotiate protocol version and codec before sending requests
+        let offered_version = match peer_version {
+            Some(version) if version != 0 => version.min(PROTOCOL_VERSION),
+            _ => PROTOCOL_VERSION,
+        };
+
+        stream.write_all(&PROTOCOL_MAGIC)?;
+        stream.write_u8(offered_version)?;
+        stream.write_u8(compression() as u8)?;
+
+        let version = stream.read_u8()?;
+        if version == 0 || version > offered_version {
+            return Err(format!("unsupported transfer protocol version {}",
+                version).into());
+        }
+
+        let compression = FromPrimitive::from_u8(stream.read_u8()?)
+            .unwrap_or(Compression::None);
+
+        // authenticate as a cluster node
+        if version >= AUTH_VERSION {
+            write_string(&crate::auth::cluster_token().unwrap_or_default(),
+                &mut stream)?;
+            read_status(&mut stream)?;
+        }
+
+        Ok(Connection {
+            compression: compression,
+            idle_since: Instant::now(),
+            stream: stream,
+            version: version,
+        })
+    }
+
+    fn read_reply(&mut self) -> Result<Vec<u8>, Box<dyn Error>> {
+        // unframed replies end when the node closes the stream
+        if self.version == 0 {
+            let mut reply = Vec::new();
+            (&mut self.stream).take(MAX_FRAME_SIZE + 1)
+                .read_to_end(&mut reply)?;
+            check_frame_size(reply.len() as u64)?;
+            crate::metrics::add(Counter::TransferReceivedBytes,
+                reply.len() as u64);
+            return Ok(reply);
+        }
+
+        let reply = match self.version >= STREAM_VERSION {
+            true => read_chunked(&mut self.stream, self.compression)?,
+            false => self.compression.decompress(
+                read_frame(&mut self.stream)?)?,
+        };
+        crate::metrics::add(Counter::TransferReceivedBytes,
+            reply.len() as u64);
+        Ok(reply)
+    }
+
+    fn write_request(&mut self, request: &[u8])
+            -> Result<(), Box<dyn Error>> {
+        crate::metrics::add(Counter::TransferSentBytes,
+            request.len() as u64);
+        if self.version == 0 {
+            self.stream.write_all(request)?;
+            return Ok(());
+        }
+
+        if self.version < TRACE_VERSION {
+            return write_frame(&self.compression.compress(request)?,
+                &mut self.stream);
+        }
+
+        // continue the sender trace on the receiving node
+        let mut buf = Vec::with_capacity(request.len() + 25);
+        SpanContext::write(&SpanContext::current(), &mut buf)?;
+        buf.extend_from_slice(request);
+        write_frame(&self.compression.compress(&buf)?, &mut self.stream)
+    }
+}
+
+struct Duplex<R, W> {
+    reader: R,
+    writer: W,
+}
+
+impl<R: Read, W> Read for Duplex<R, W> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.reader.read(buf)
+    }
+}
+
+impl<R, W: Write> Write for Duplex<R, W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.writer.write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+// writes a framed reply, streamed in chunks to readers supporting it
+//   and buffered into a single frame for earlier releases
+struct FrameWriter<'a, W: Write> {
+    buf: Vec<u8>,
+    chunked: bool,
+    compression: Compression,
+    writer: &'a mut W,
+}
+
+impl<'a, W: Write> FrameWriter<'a, W> {
+    fn new(compression: Compression, version: u8, writer: &'a mut W)
+            -> FrameWriter<'a, W> {
+        FrameWriter {
+            buf: Vec::new(),
+            chunked: version >= STREAM_VERSION,
+            compression: compression,
+            writer: writer,
+        }
+    }
+
+    fn finish(mut self) -> Result<(), Box<dyn Error>> {
+        // unchunked replies are a single frame, even when empty
+        if !self.chunked || !self.buf.is_empty() {
+            self.write_chunk()?;
+        }
+
+        match self.chunked {
+            true => write_frame(&[], self.writer),
+            false => Ok(()),
+        }
+    }
+
+    fn write_chunk(&mut self) -> Result<(), Box<dyn Error>> {
+        write_frame(&self.compression.compress(&self.buf)?, self.writer)?;
+        self.buf.clear();
+        Ok(())
+    }
+}
+
+impl<'a, W: Write> Write for FrameWriter<'a, W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        if !self.chunked {
+            self.buf.extend_from_slice(buf);
+            return Ok(buf.len());
+        }
+
+        let len = buf.len().min(TRANSFER_CHUNK_SIZE - self.buf.len());
+        self.buf.extend_from_slice(&buf[..len]);
+        if self.buf.len() == TRANSFER_CHUNK_SIZE {
+            self.write_chunk().map_err(|e| std::io::Error::new(
+                std::io::ErrorKind::Other, e.to_string()))?;
+        }
+
+        Ok(len)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+struct StagedTransaction {
+    images: Vec<StagedImage>,
+    sequence: u32,
+    updated: Instant,
 }
 
 pub struct TransferStreamHandler {
     album_manager: Arc<RwLock<AlbumManager>>,
+    directory: PathBuf,
+    ledger: Arc<UsageLedger>,
+    transactions: Mutex<HashMap<u64, StagedTransaction>>,
+    uploads: Mutex<HashMap<u64, (Instant, Vec<u8>)>>,
 }
 
 impl TransferStreamHandler {
-    pub fn new(album_manager: Arc<RwLock<AlbumManager>>)
+    pub fn new(album_manager: Arc<RwLock<AlbumManager>>,
+            directory: PathBuf, ledger: Arc<UsageLedger>)
             -> TransferStreamHandler {
         TransferStreamHandler {
             album_manager: album_manager,
+            directory: directory,
+            ledger: ledger,
+            transactions: Mutex::new(HashMap::new()),
+            uploads: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn abort(&self, transaction_id: u64) {
+        let transaction = self.transactions.lock()
+            .unwrap().remove(&transaction_id);
+        if let Some(transaction) = transaction {
+            remove_staged(transaction);
+        }
+    }
+
+    fn append(&self, upload_id: u64, offset: u64, chunk: &[u8])
+            -> Result<u64, Box<dyn Error>> {
+        let mut uploads = self.uploads.lock().unwrap();
+
+        // drop uploads abandoned by their senders
+        uploads.retain(|_, (updated, _)| updated.elapsed() < UPLOAD_TIMEOUT);
+
+        let (updated, buf) = uploads.entry(upload_id)
+            .or_insert((Instant::now(), Vec::new()));
+        if offset as usize > buf.len() {
+            return Err(format!("upload {} offset {} exceeds received length {}",
+                upload_id, offset, buf.len()).into());
+        }
+
+        // resume from offset, replacing chunks resent by the sender
+        check_frame_size(offset + chunk.len() as u64)?;
+        buf.truncate(offset as usize);
+        buf.extend_from_slice(chunk);
+        *updated = Instant::now();
+
+        Ok(buf.len() as u64)
+    }
+
+    fn commit(&self, transaction_id: u64) -> Result<(), Box<dyn Error>> {
+        let transaction = match self.transactions.lock()
+                .unwrap().remove(&transaction_id) {
+            Some(transaction) => transaction,
+            None => return Ok(()),
+        };
+
+        // group staged images by album
+        let mut albums: HashMap<String, Vec<StagedImage>> = HashMap::new();
+        for staged_image in transaction.images {
+            albums.entry(staged_image.album.clone())
+                .or_insert(Vec::new()).push(staged_image);
+        }
+
+        // move staged images into albums
+        let album_manager = self.album_manager.read().unwrap();
+        for (album_id, staged_images) in albums.iter() {
+            let album = album_manager.get(album_id)
+                .ok_or(format!("album '{}' does not exist", album_id))?;
+
+            let _span = crate::telemetry::span("album.commit",
+                SpanKind::Internal);
+            let mut album = album.write().unwrap();
+            album.commit_transaction(transaction_id, staged_images)?;
+        }
+
+        Ok(())
+    }
+
+    fn expire(&self) {
+        // abort transactions abandoned by a failed coordinator
+        let expired: Vec<StagedTransaction> = {
+            let mut transactions = self.transactions.lock().unwrap();
+            let ids: Vec<u64> = transactions.iter()
+                .filter(|(_, x)| x.updated.elapsed() >= TRANSACTION_TIMEOUT)
+                .map(|(id, _)| *id).collect();
+
+            ids.iter().filter_map(|x| transactions.remove(x)).collect()
+        };
+
+        for transaction in expired {
+            warn!("aborting transaction idle for {:?} with {} staged images",
+                TRANSACTION_TIMEOUT, transaction.images.len());
+            remove_staged(transaction);
+        }
+    }
+
+    fn prepare(&self, transaction_id: u64, album: &str,
+            dataset: &mut Dataset, geocode: &str, pixel_coverage: f64,
+            platform: &str, source: &str, subdataset: u8, tile: &str,
+            timestamp: i64) -> Result<(), Box<dyn Error>> {
+        self.expire();
+
+        let album_manager = self.album_manager.read().unwrap();
+        let album = album_manager.get(album)
+            .ok_or(format!("album '{}' does not exist", album))?;
+
+        // reserve a staged image sequence number
+        let sequence = {
+            let mut transactions = self.transactions.lock().unwrap();
+            let transaction = transactions.entry(transaction_id)
+                .or_insert(StagedTransaction {
+                    images: Vec::new(),
+                    sequence: 0,
+                    updated: Instant::now(),
+                });
+
+            transaction.sequence += 1;
+            transaction.updated = Instant::now();
+            transaction.sequence
+        };
+
+        // stage image within album outside the transactions lock
+        let album = album.read().unwrap();
+        let path = album.stage(dataset, transaction_id,
+            sequence, geocode, pixel_coverage,
+            platform, source, subdataset, tile, timestamp)?;
+
+        let staged_image = StagedImage {
+            album: album.get_id().to_string(),
+            cloud_coverage: crate::mask::cloud_coverage(dataset),
+            geocode: geocode.to_string(),
+            path: path,
+            pixel_coverage: pixel_coverage,
+            platform: platform.to_string(),
+            source: source.to_string(),
+            subdataset: subdataset,
+            tile: tile.to_string(),
+            timestamp: timestamp,
+        };
+
+        // transactions may have completed while the image was written
+        let mut transactions = self.transactions.lock().unwrap();
+        match transactions.get_mut(&transaction_id) {
+            Some(transaction) => {
+                transaction.images.push(staged_image);
+                transaction.updated = Instant::now();
+                Ok(())
+            },
+            None => {
+                let _ = std::fs::remove_file(&staged_image.path);
+                Err(format!("transaction {} completed while staging",
+                    transaction_id).into())
+            },
+        }
+    }
+
+    fn stage<T: Read>(&self, transaction_id: u64, reader: &mut T)
+            -> Result<(), Box<dyn Error>> {
+        // read everything
+        let album = read_string(reader)?;
+        let mut dataset = st_image::serialize::read(reader)?;
+        let geocode = read_string(reader)?;
+        let pixel_coverage = reader.read_f64::<BigEndian>()?;
+        let platform = read_string(reader)?;
+        let source = read_string(reader)?;
+        let subdataset = reader.read_u8()?;
+        let tile = read_string(reader)?;
+        let timestamp = reader.read_i64::<BigEndian>()?;
+        read_cloud_coverage(reader, &dataset)?;
+
+        crate::task::logger::set_context(LogContext {
+            album: Some(album.clone()),
+            geocode: Some(geocode.clone()),
+        });
+
+        // stage image until transaction completes
+        if crate::cluster::is_leaving() {
+            return Err("node is decommissioned".into());
+        }
+
+        crate::cluster::assert_disk_available(&self.directory)?;
+        self.prepare(transaction_id, &album, &mut dataset, &geocode,
+            pixel_coverage, &platform, &source, subdataset, &tile, timestamp)
+    }
+
+    fn write<T: Read>(&self, reader: &mut T) -> Result<(), Box<dyn Error>> {
+        // read everything
+        let album = read_string(reader)?;
+        let mut dataset = st_image::serialize::read(reader)?;
+        let geocode = read_string(reader)?;
+        let pixel_coverage = reader.read_f64::<BigEndian>()?;
+        let platform = read_string(reader)?;
+        let source = read_string(reader)?;
+        let subdataset = reader.read_u8()?;
+        let tile = read_string(reader)?;
+        let timestamp = reader.read_i64::<BigEndian>()?;
+        read_cloud_coverage(reader, &dataset)?;
+
+        crate::task::logger::set_context(LogContext {
+            album: Some(album.clone()),
+            geocode: Some(geocode.clone()),
+        });
+
+        // reject writes while the node is draining
+        if crate::cluster::is_leaving() {
+            return Err(format!("rejecting write of '{}', node is decommissioned",
+                geocode).into());
+        }
+
+        // reject writes when the disk is nearly full
+        if let Err(e) =
+                crate::cluster::assert_disk_available(&self.directory) {
+            return Err(format!("rejecting write of '{}', {}",
+                geocode, e).into());
+        }
+
+        // write image using AlbumManager
+        let album_manager = self.album_manager.read().unwrap();
+        match album_manager.get(&album) {
+            Some(album) => {
+                let _span = crate::telemetry::span("album.write",
+                    SpanKind::Internal);
+                let mut album = album.write().unwrap();
+                album.write(&mut dataset, &geocode, pixel_coverage,
+                    &platform, &source, subdataset, &tile, timestamp)?;
+            },
+            // nack so the sender does not consider the image written
+            None => return Err(format!("album '{}' does not exist",
+                album).into()),
         }
+
+        Ok(())
     }
 }
 
 impl StreamHandler for TransferStreamHandler {
     fn process(&self, stream: &mut TcpStream)
             -> Result<(), Box<dyn Error>> {
-        // read operation type
-        let op_type = stream.read_u8()?;
+        // release workers held by idle persistent connections
+        stream.set_read_timeout(Some(KEEPALIVE_TIMEOUT))?;
+
+        let mut session = match crate::tls::server_session() {
+            Some(session) => session,
+            None => return self.serve(stream),
+        };
+
+        // wrap connection with tls when configured
+        self.serve(&mut rustls::Stream::new(&mut session, stream))?;
+        crate::tls::shutdown(&mut session, stream)?;
+        Ok(())
+    }
+}
+
+impl TransferStreamHandler {
+    // version is the negotiated protocol version, 0 for unframed requests
+    fn handle<T: Read + Write>(&self, op_type: u8, stream: &mut T,
+            version: u8) -> Result<(), Box<dyn Error>> {
+        // clear context of requests previously served by this thread
+        crate::task::logger::set_context(LogContext::default());
+
         match FromPrimitive::from_u8(op_type) {
             Some(TransferOp::ReadImage) => {
-                // read path
-                let path_string = read_string(stream)?;
-                let path = PathBuf::from(&path_string);
-
-                if !path.exists() {
-                    stream.write_u8(1)?;
-                    write_string(&format!("path '{}' does not exist",
-                        path_string), stream)?;
-                    return Ok(());
-                }
-
-                // open dataset
-                let dataset = match Dataset::open(&path) {
-                    Ok(dataset) => dataset,
-                    Err(e) => {
-                        stream.write_u8(1)?;
-                        write_string(&e.to_string(), stream)?;
-                        return Ok(());
+                // read request
+                let path = read_string(stream)?;
+                let subgeocode = match stream.read_u8()? {
+                    0 => None,
+                    _ => {
+                        let geocode_value = stream.read_u8()?;
+                        Some((geocode_value, read_string(stream)?))
                     },
                 };
 
-                // if exists -> split dataset to subgeocode
-                let subgeocode_indicator = stream.read_u8()?;
-                if subgeocode_indicator == 0 {
-                    // no need to split image -> write image
-                    stream.write_u8(0)?;
-                    st_image::serialize::write(&dataset, stream)?;
-                } else {
-                    // read subgeocode metadata
-                    let geocode_value = stream.read_u8()?;
-                    let geocode: Geocode = match geocode_value {
-                        0 => Geocode::Geohash,
-                        1 => Geocode::QuadTile,
-                        _ => {
-                            let err_msg = format!("unknown geocode {}",
-                                geocode_value);
+                let transform = match stream.read_u8()? {
+                    0 => None,
+                    _ => Some(ReadTransform::read(stream)?),
+                };
+
+                let tenant = match stream.read_u8()? {
+                    0 => None,
+                    _ => Some(read_string(stream)?),
+                };
+
+                // newer readers append a flag accepting stored GeoTIFF
+                //   bytes, legacy unframed requests end at the tenant
+                let mut flag = [0u8; 1];
+                let accepts_geotiff = version != 0
+                    && stream.read(&mut flag)? == 1 && flag[0] != 0;
+
+                // stream unmodified tiles without decoding them
+                if accepts_geotiff && subgeocode.is_none()
+                        && transform.is_none() {
+                    match crate::compaction::reader(Path::new(&path)) {
+                        Ok((len, mut reader)) => {
+                            stream.write_u8(2)?;
+                            stream.write_u64::<BigEndian>(len)?;
+                            std::io::copy(&mut reader, stream)?;
+
+                            // charge served bytes to requesting tenant
+                            self.ledger.charge(&tenant, 0, len, 0);
+                            crate::metrics::add(Counter::TilesRead, 1);
+                            crate::metrics::add(Counter::TransferSentBytes,
+                                len);
+                        },
+                        Err(e) => {
                             stream.write_u8(1)?;
-                            write_string(&err_msg, stream)?;
-                            return Err(err_msg.into());
+                            write_string(&format!(
+                                "failed to read path '{}': {}", path, e),
+                                stream)?;
                         },
-                    };
-
-                    let subgeocode = read_string(stream)?;
-
-                    // split image with geocode precision
-                    let precision = subgeocode.len();
-
-                    // compute geohash window boundaries for dataset
-                    let epsg_code = geocode.get_epsg_code();
-                    let (x_interval, y_interval) =
-                        geocode.get_intervals(precision);
-
-                    let (image_min_cx, image_max_cx, 
-                            image_min_cy, image_max_cy) =
-                        st_image::coordinate::get_bounds(
-                            &dataset, epsg_code)?;
-
-                    let window_bounds = 
-                        st_image::coordinate::get_windows(image_min_cx,
-                            image_max_cx, image_min_cy, image_max_cy,
-                            x_interval, y_interval);
-
-                    // iterate over window bounds
-                    for (min_cx, max_cx, min_cy, max_cy) in 
-                            window_bounds {
-                        // perform dataset split
-                        let split_dataset = match 
-                                st_image::transform::split(&dataset,
-                                    min_cx, max_cx, min_cy, max_cy, 
-                                    epsg_code)? {
-                            Some(split_dataset) => split_dataset,
-                            None => continue,
-                        };
-
-                        let split_geocode = geocode.encode(
-                            (min_cx + max_cx) / 2.0,
-                            (min_cy + max_cy) / 2.0, precision)?;
-
-                        // check if this is the desired geocode
-                        if split_geocode.to_lowercase()
-                                != subgeocode.to_lowercase() {
-                            continue;
-                        }
-
-                        // process valid subdataset
-                        stream.write_u8(0)?;
-                        st_image::serialize::write(
-                            &split_dataset, stream)?;
-                        return Ok(())
                     }
 
-                    // failed to split image into subgeocode
-                    stream.write_u8(1)?;
-                    write_string(&format!(
-                        "failed to split image into geocode '{}'",
-                            subgeocode), stream)?;
                     return Ok(());
                 }
+
+                // read dataset and apply requested transform
+                let result = read_dataset(&path, &subgeocode)
+                    .and_then(|dataset| match &transform {
+                        Some(transform) => transform.apply(dataset),
+                        None => Ok(dataset),
+                    });
+
+                match result {
+                    Ok(dataset) => {
+                        let mut buf = Vec::new();
+                        st_image::serialize::write(&dataset, &mut buf)?;
+
+                        stream.write_u8(0)?;
+                        stream.write_all(&buf)?;
+
+                        // charge served bytes to requesting tenant
+                        self.ledger.charge(&tenant, 0, buf.len() as u64, 0);
+                        crate::metrics::add(Counter::TilesRead, 1);
+                        crate::metrics::add(Counter::TransferSentBytes,
+                            buf.len() as u64);
+                    },
+                    Err(e) => {
+                        stream.write_u8(1)?;
+                        write_string(&e.to_string(), stream)?;
+                    },
+                }
             },
             Some(TransferOp::WriteImage) => {
-                // read everything
-                let album = read_string(stream)?;
-                let mut dataset = st_image::serialize::read(stream)?;
-                let geocode = read_string(stream)?;
-                let pixel_coverage = stream.read_f64::<BigEndian>()?;
-                let platform = read_string(stream)?;
-                let source = read_string(stream)?;
-                let subdataset = stream.read_u8()?;
-                let tile = read_string(stream)?;
-                let timestamp = stream.read_i64::<BigEndian>()?;
-
-                // write image using AlbumManager
-                let album_manager = self.album_manager.read().unwrap();
-                match album_manager.get(&album) {
-                    Some(album) => {
-                        let mut album = album.write().unwrap();
-                        album.write(&mut dataset, &geocode,
-                            pixel_coverage, &platform, &source,
-                            subdataset, &tile, timestamp)?;
+                // verify checksum before writing the image
+                let result = match version >= CHECKSUM_VERSION {
+                    true => read_checked(stream).and_then(|buf| {
+                        crate::metrics::add(Counter::TransferReceivedBytes,
+                            buf.len() as u64);
+                        self.write(&mut Cursor::new(buf))
+                    }),
+                    false => self.write(stream),
+                };
+                write_status(result, stream)?;
+            },
+            Some(TransferOp::PrepareImage) => {
+                // verify checksum before staging the image
+                let transaction_id = stream.read_u64::<BigEndian>()?;
+                let result = match version >= CHECKSUM_VERSION {
+                    true => read_checked(stream).and_then(|buf| {
+                        crate::metrics::add(Counter::TransferReceivedBytes,
+                            buf.len() as u64);
+                        self.stage(transaction_id, &mut Cursor::new(buf))
+                    }),
+                    false => self.stage(transaction_id, stream),
+                };
+                write_status(result, stream)?;
+            },
+            Some(TransferOp::CommitTransaction) => {
+                let transaction_id = stream.read_u64::<BigEndian>()?;
+                let result = self.commit(transaction_id);
+                write_status(result, stream)?;
+            },
+            Some(TransferOp::AbortTransaction) => {
+                let transaction_id = stream.read_u64::<BigEndian>()?;
+                self.abort(transaction_id);
+                write_status(Ok(()), stream)?;
+            },
+            Some(TransferOp::AppendChunk) => {
+                let upload_id = stream.read_u64::<BigEndian>()?;
+                let offset = stream.read_u64::<BigEndian>()?;
+                let chunk = read_frame(stream)?;
+
+                // reply with the received length to resume from
+                match self.append(upload_id, offset, &chunk) {
+                    Ok(len) => {
+                        write_status(Ok(()), stream)?;
+                        stream.write_u64::<BigEndian>(len)?;
                     },
-                    None => warn!("album '{}' does not exist", album),
+                    Err(e) => write_status(Err(e), stream)?,
+                }
+            },
+            Some(TransferOp::UploadOffset) => {
+                let upload_id = stream.read_u64::<BigEndian>()?;
+                let len = self.uploads.lock().unwrap().get(&upload_id)
+                    .map(|(_, buf)| buf.len()).unwrap_or(0);
+                stream.write_u64::<BigEndian>(len as u64)?;
+            },
+            Some(TransferOp::CompleteUpload) => {
+                let upload_id = stream.read_u64::<BigEndian>()?;
+                let upload = self.uploads.lock().unwrap().remove(&upload_id);
+
+                // replay the uploaded request
+                match upload {
+                    Some((_, request)) =>
+                        self.replay(request, version, stream)?,
+                    None => write_status(Err(format!(
+                        "upload {} does not exist", upload_id).into()),
+                        stream)?,
                 }
+            },
+            Some(TransferOp::Negotiate) => {
+                // acknowledge codec, falling back to none if unknown
+                let compression = FromPrimitive::from_u8(stream.read_u8()?)
+                    .unwrap_or(Compression::None);
+                stream.write_u8(compression as u8)?;
 
-                // write success
-                stream.write_u8(1)?;
+                // process compressed request and compress the reply
+                let request = compression.decompress(read_frame(stream)?)?;
+                let mut reply = Vec::new();
+                self.replay(request, version, &mut reply)?;
+                write_frame(&compression.compress(&reply)?, stream)?;
             },
             None => return Err(Box::new(std::io::Error::new(
                 std::io::ErrorKind::InvalidInput,
@@ -172,44 +772,1496 @@ impl StreamHandler for TransferStreamHandler {
 
         Ok(())
     }
+
+    fn replay(&self, request: Vec<u8>, version: u8, writer: &mut dyn Write)
+            -> Result<(), Box<dyn Error>> {
+        let mut duplex = Duplex {
+            reader: Cursor::new(request),
+            writer: writer,
+        };
+
+        let op_type = duplex.read_u8()?;
+        self.handle(op_type, &mut duplex, version)
+    }
+
+    fn replay_traced(&self, request: Vec<u8>, version: u8,
+            writer: &mut dyn Write) -> Result<(), Box<dyn Error>> {
+        let mut cursor = Cursor::new(request);
+        let parent = SpanContext::read(&mut cursor)?;
+        let offset = cursor.position() as usize;
+        let mut request = cursor.into_inner();
+        request.drain(..offset);
+
+        // trace the request as a child of the sender span
+        let op_type: Option<TransferOp> = request.first()
+            .and_then(|x| FromPrimitive::from_u8(*x));
+        let name = match op_type {
+            Some(op_type) => format!("transfer.{:?}", op_type),
+            None => "transfer".to_string(),
+        };
+
+        let mut span = Span::new(&name, SpanKind::Server, parent).enter();
+        let result = self.replay(request, version, writer);
+        if let Err(e) = &result {
+            span.set_error(&e.to_string());
+        }
+
+        result
+    }
+
+    fn serve<T: Read + Write>(&self, stream: &mut T)
+            -> Result<(), Box<dyn Error>> {
+        // unframed requests from legacy senders begin with the operation
+        let op_type = stream.read_u8()?;
+        if op_type != PROTOCOL_MAGIC[0] {
+            if crate::auth::is_enabled() {
+                return Err("unauthenticated transfer request".into());
+            }
+
+            return self.handle(op_type, stream, 0);
+        }
+
+        let mut magic = [0u8; 3];
+        stream.read_exact(&mut magic)?;
+        if magic != PROTOCOL_MAGIC[1..] {
+            return Err("invalid transfer protocol header".into());
+        }
+
+        // reply with the protocol version and codec this node will use
+        let version = stream.read_u8()?.min(PROTOCOL_VERSION);
+        let compression = FromPrimitive::from_u8(stream.read_u8()?)
+            .unwrap_or(Compression::None);
+        stream.write_u8(version)?;
+        stream.write_u8(compression as u8)?;
+
+        // verify senders are cluster nodes
+        if version >= AUTH_VERSION {
+            let result = crate::auth::authenticate_node(&read_string(stream)?);
+            let authenticated = result.is_ok();
+            write_status(result, stream)?;
+            if !authenticated {
+                return Err("invalid transfer cluster token".into());
+            }
+        } else if crate::auth::is_enabled() {
+            return Err(format!("transfer protocol version {} is unauthenticated",
+                version).into());
+        }
+
+        // process framed requests, persistent senders may send many
+        let mut first = true;
+        loop {
+            let request = match read_frame(stream) {
+                Ok(request) => request,
+                // sender closed or idled out between requests
+                Err(_) if !first => return Ok(()),
+                Err(e) => return Err(e),
+            };
+
+            let request = compression.decompress(request)?;
+            let mut reply = FrameWriter::new(compression, version, stream);
+            match version >= TRACE_VERSION {
+                true => self.replay_traced(request, version, &mut reply)?,
+                false => self.replay(request, version, &mut reply)?,
+            }
+            reply.finish()?;
+
+            if version < PERSISTENT_VERSION {
+                return Ok(());
+            }
+
+            first = false;
+        }
+    }
 }
 
-pub fn read_string<T: Read>(reader: &mut T)
-        -> Result<String, Box<dyn Error>> {
-    let len = reader.read_u8()?;
-    let mut buf = vec![0u8; len as usize];
-    reader.read_exact(&mut buf)?;
-    Ok(String::from_utf8(buf)?)
+pub struct LatencyTracker {
+    latencies: Mutex<HashMap<SocketAddr, f64>>,
 }
 
-pub fn send_image(addr: &SocketAddr, album: &str, dataset: &Dataset,
-        geocode: &str, pixel_coverage: f64, platform: &str,
-        source: &str, subdataset: u8, tile: &str, timestamp: i64)
-        -> Result<(), Box<dyn Error>> {
-    // open connection
-    let mut stream = TcpStream::connect(addr)?;
-    stream.write_u8(TransferOp::WriteImage as u8)?;
-
-    // write everything
-    write_string(&album, &mut stream)?;
-    st_image::serialize::write(&dataset, &mut stream)?;
-    write_string(&geocode, &mut stream)?;
-    stream.write_f64::<BigEndian>(pixel_coverage)?;
-    write_string(&platform, &mut stream)?;
-    write_string(&source, &mut stream)?;
-    stream.write_u8(subdataset)?;
-    write_string(&tile, &mut stream)?;
-    stream.write_i64::<BigEndian>(timestamp)?;
- 
-    // read success
-    let _ = stream.read_u8()?;
+impl LatencyTracker {
+    pub fn new() -> LatencyTracker {
+        LatencyTracker {
+            latencies: Mutex::new(HashMap::new()),
+        }
+    }
 
-    Ok(())
+    pub fn latency(&self, addr: &SocketAddr) -> Option<f64> {
+        self.latencies.lock().unwrap().get(addr).cloned()
+    }
+
+    pub fn observe(&self, addr: &SocketAddr, latency_ms: f64) {
+        let mut latencies = self.latencies.lock().unwrap();
+        let latency = latencies.entry(*addr).or_insert(latency_ms);
+        *latency = LATENCY_EWMA_ALPHA * latency_ms
+            + (1.0 - LATENCY_EWMA_ALPHA) * *latency;
+    }
+
+    pub fn rank(&self, replicas: &[(SocketAddr, String)])
+            -> Vec<(SocketAddr, String)> {
+        // unobserved replicas sort first so they are sampled
+        let mut replicas = replicas.to_vec();
+        let latencies = self.latencies.lock().unwrap();
+        replicas.sort_by(|a, b| {
+            let a = latencies.get(&a.0).cloned().unwrap_or(0.0);
+            let b = latencies.get(&b.0).cloned().unwrap_or(0.0);
+            a.partial_cmp(&b).unwrap_or(std::cmp::Ordering::Equal)
+        });
+
+        replicas
+    }
 }
 
-pub fn write_string<T: Write>(value: &str, writer: &mut T)
-        -> Result<(), Box<dyn Error>> {
-    writer.write_u8(value.len() as u8)?;
-    writer.write(value.as_bytes())?;
-    Ok(())
+pub struct ReadPrefetcher {
+    in_flight: VecDeque<(crossbeam_channel::Receiver<Result<Vec<u8>, String>>,
+        Option<ReadTransform>)>,
+    requests: VecDeque<ReadRequest>,
+    tracker: Arc<LatencyTracker>,
+    window: usize,
+}
+
+#[derive(Clone, Debug)]
+pub struct ReadRequest {
+    pub replicas: Vec<(SocketAddr, String)>,
+    pub subgeocode: Option<(Geocode, String)>,
+    pub tenant: Option<String>,
+    pub transform: Option<ReadTransform>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct ReadTransform {
+    pub bands: Option<Vec<u32>>,
+    pub bounds: Option<(f64, f64, f64, f64)>,
+    pub data_type: Option<String>,
+    // wkt or geojson clip geometry, masked by the reading node since
+    //   replicas clip only to bounds
+    pub geometry: Option<String>,
+    pub scale: Option<(f64, f64)>,
+}
+
+impl ReadTransform {
+    pub fn apply(&self, dataset: Dataset)
+            -> Result<Dataset, Box<dyn Error>> {
+        // clip dataset to longitude / latitude bounds
+        let dataset = match self.bounds {
+            Some((min_x, max_x, min_y, max_y)) =>
+                    match st_image::transform::split(&dataset,
+                        min_x, max_x, min_y, max_y, 4326)? {
+                Some(dataset) => dataset,
+                None => return Err("image does not intersect clip bounds"
+                    .into()),
+            },
+            None => dataset,
+        };
+
+        if self.bands.is_none() && self.data_type.is_none()
+                && self.scale.is_none() {
+            return Ok(dataset);
+        }
+
+        // validate band subset
+        let band_count = unsafe {
+            gdal_sys::GDALGetRasterCount(dataset.c_dataset())
+        } as u32;
+
+        let bands = match &self.bands {
+            Some(bands) => bands.clone(),
+            None => (1..=band_count).collect(),
+        };
+
+        if bands.is_empty() {
+            return Err("band subset must include at least one band".into());
+        }
+
+        for band in bands.iter() {
+            if *band == 0 || *band > band_count {
+                return Err(format!("band {} out of range 1-{}",
+                    band, band_count).into());
+            }
+        }
+
+        // resolve output data type
+        let data_type = match &self.data_type {
+            Some(name) => {
+                let c_name = CString::new(name.as_str())?;
+                let data_type = unsafe {
+                    gdal_sys::GDALGetDataTypeByName(c_name.as_ptr())
+                };
+
+                if data_type == gdal_sys::GDALDataType::GDT_Unknown {
+                    return Err(format!("unknown data type '{}'",
+                        name).into());
+                }
+
+                data_type
+            },
+            None => unsafe {
+                gdal_sys::GDALGetRasterDataType(gdal_sys::GDALGetRasterBand(
+                    dataset.c_dataset(), bands[0] as c_int))
+            },
+        };
+
+        // create in-memory dataset
+        let driver = gdal::Driver::get("MEM")?;
+        let (x, y) = dataset.raster_size();
+        let c_filename = CString::new("")?;
+        let c_dataset = unsafe {
+            gdal_sys::GDALCreate(driver.c_driver(), c_filename.as_ptr(),
+                x as c_int, y as c_int, bands.len() as c_int,
+                data_type, std::ptr::null_mut())
+        };
+
+        if c_dataset.is_null() {
+            return Err("failed to create transform dataset".into());
+        }
+
+        let transformed = unsafe { Dataset::from_c_dataset(c_dataset) };
+        transformed.set_geo_transform(&dataset.geo_transform()?)?;
+        transformed.set_projection(&dataset.projection())?;
+
+        // copy bands applying scale and offset to valid pixels
+        let mut buf = vec![0f64; x * y];
+        for (index, band) in bands.iter().enumerate() {
+            unsafe {
+                let src_band = gdal_sys::GDALGetRasterBand(
+                    dataset.c_dataset(), *band as c_int);
+                let dst_band = gdal_sys::GDALGetRasterBand(
+                    transformed.c_dataset(), index as c_int + 1);
+
+                raster_io(src_band, gdal_sys::GDALRWFlag::GF_Read,
+                    &mut buf, x, y)?;
+
+                let mut has_no_data: c_int = 0;
+                let no_data = gdal_sys::GDALGetRasterNoDataValue(
+                    src_band, &mut has_no_data);
+
+                if let Some((scale, offset)) = self.scale {
+                    for value in buf.iter_mut() {
+                        if has_no_data == 0 || *value != no_data {
+                            *value = *value * scale + offset;
+                        }
+                    }
+                }
+
+                if has_no_data != 0 {
+                    gdal_sys::GDALSetRasterNoDataValue(dst_band, no_data);
+                }
+
+                raster_io(dst_band, gdal_sys::GDALRWFlag::GF_Write,
+                    &mut buf, x, y)?;
+            }
+        }
+
+        Ok(transformed)
+    }
+
+    // write pixels outside the clip geometry as nodata
+    pub fn mask(&self, dataset: Dataset) -> Result<Dataset, Box<dyn Error>> {
+        let geometry = match &self.geometry {
+            Some(geometry) => geometry,
+            None => return Ok(dataset),
+        };
+
+        let zones = match geometry.trim().starts_with('{') {
+            true => crate::zonal::parse_zones(geometry)?,
+            false => vec![geometry.clone()],
+        };
+
+        let (x, y) = dataset.raster_size();
+        let mut mask = vec![false; x * y];
+        for zone in zones.iter() {
+            let zone_mask = crate::raster::rasterize(&dataset, zone)?;
+            for (value, inside) in mask.iter_mut().zip(zone_mask) {
+                *value |= inside;
+            }
+        }
+
+        if !mask.iter().any(|x| *x) {
+            return Err("image does not intersect clip geometry".into());
+        }
+
+        let masked = crate::raster::copy(&dataset)?;
+        for i in 1..crate::raster::band_count(&masked) + 1 {
+            let mut band = crate::raster::read_band(&masked, i)?;
+            let nodata = match band.nodata {
+                Some(nodata) => nodata,
+                None => {
+                    unsafe {
+                        let c_band = gdal_sys::GDALGetRasterBand(
+                            masked.c_dataset(), i as c_int);
+                        gdal_sys::GDALSetRasterNoDataValue(c_band, 0.0);
+                    }
+
+                    0f64
+                },
+            };
+
+            for (value, inside) in band.values.iter_mut().zip(mask.iter()) {
+                if !inside {
+                    *value = nodata;
+                }
+            }
+
+            crate::raster::write_band(&masked, i, &mut band.values)?;
+        }
+
+        Ok(masked)
+    }
+
+    pub fn read<T: Read>(reader: &mut T)
+            -> Result<ReadTransform, Box<dyn Error>> {
+        let bands = match reader.read_u8()? {
+            0 => None,
+            _ => {
+                let count = reader.read_u8()?;
+                let mut bands = Vec::new();
+                for _ in 0..count {
+                    bands.push(reader.read_u32::<BigEndian>()?);
+                }
+
+                Some(bands)
+            },
+        };
+
+        let bounds = match reader.read_u8()? {
+            0 => None,
+            _ => Some((reader.read_f64::<BigEndian>()?,
+                reader.read_f64::<BigEndian>()?,
+                reader.read_f64::<BigEndian>()?,
+                reader.read_f64::<BigEndian>()?)),
+        };
+
+        let data_type = match reader.read_u8()? {
+            0 => None,
+            _ => Some(read_string(reader)?),
+        };
+
+        let scale = match reader.read_u8()? {
+            0 => None,
+            _ => Some((reader.read_f64::<BigEndian>()?,
+                reader.read_f64::<BigEndian>()?)),
+        };
+
+        Ok(ReadTransform {
+            bands: bands,
+            bounds: bounds,
+            data_type: data_type,
+            geometry: None,
+            scale: scale,
+        })
+    }
+
+    pub fn write<T: Write>(&self, writer: &mut T)
+            -> Result<(), Box<dyn Error>> {
+        match &self.bands {
+            Some(bands) => {
+                // band count is written as a single byte
+                if bands.len() > std::u8::MAX as usize {
+                    return Err(format!("band subset exceeds {} bands",
+                        std::u8::MAX).into());
+                }
+
+                writer.write_u8(1)?;
+                writer.write_u8(bands.len() as u8)?;
+                for band in bands.iter() {
+                    writer.write_u32::<BigEndian>(*band)?;
+                }
+            },
+            None => writer.write_u8(0)?,
+        }
+
+        match self.bounds {
+            Some((min_x, max_x, min_y, max_y)) => {
+                writer.write_u8(1)?;
+                writer.write_f64::<BigEndian>(min_x)?;
+                writer.write_f64::<BigEndian>(max_x)?;
+                writer.write_f64::<BigEndian>(min_y)?;
+                writer.write_f64::<BigEndian>(max_y)?;
+            },
+            None => writer.write_u8(0)?,
+        }
+
+        match &self.data_type {
+            Some(data_type) => {
+                writer.write_u8(1)?;
+                write_string(data_type, writer)?;
+            },
+            None => writer.write_u8(0)?,
+        }
+
+        match self.scale {
+            Some((scale, offset)) => {
+                writer.write_u8(1)?;
+                writer.write_f64::<BigEndian>(scale)?;
+                writer.write_f64::<BigEndian>(offset)?;
+            },
+            None => writer.write_u8(0)?,
+        }
+
+        Ok(())
+    }
+}
+
+impl ReadPrefetcher {
+    pub fn new(requests: Vec<ReadRequest>, window: usize,
+            tracker: Arc<LatencyTracker>) -> ReadPrefetcher {
+        let mut prefetcher = ReadPrefetcher {
+            in_flight: VecDeque::new(),
+            requests: requests.into_iter().collect(),
+            tracker: tracker,
+            window: std::cmp::max(window, 1),
+        };
+
+        prefetcher.fill();
+        prefetcher
+    }
+
+    fn fill(&mut self) {
+        // start reads until window is full
+        while self.in_flight.len() < self.window {
+            let request = match self.requests.pop_front() {
+                Some(request) => request,
+                None => break,
+            };
+
+            let (sender, receiver) = crossbeam_channel::bounded(1);
+            let transform = request.transform.clone();
+            let tracker = self.tracker.clone();
+            std::thread::spawn(move || {
+                let result = read_replicas(&request.replicas,
+                        &request.subgeocode, &request.transform,
+                        &request.tenant, &tracker)
+                    .map_err(|e| e.to_string());
+                let _ = sender.send(result);
+            });
+
+            self.in_flight.push_back((receiver, transform));
+        }
+    }
+}
+
+impl Iterator for ReadPrefetcher {
+    type Item = Result<Dataset, Box<dyn Error>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // retrieve oldest in-flight read
+        let (receiver, transform) = self.in_flight.pop_front()?;
+        self.fill();
+
+        let buf = match receiver.recv() {
+            Ok(Ok(buf)) => buf,
+            Ok(Err(e)) => return Some(Err(e.into())),
+            Err(e) => return Some(Err(Box::new(e))),
+        };
+
+        // decode dataset and mask to the clip geometry
+        let mut cursor = Cursor::new(buf);
+        Some(parse_read_reply(&mut cursor).and_then(|dataset|
+            match &transform {
+                Some(transform) => transform.mask(dataset),
+                None => Ok(dataset),
+            }))
+    }
+}
+
+fn parse_read_reply<T: Read>(reader: &mut T)
+        -> Result<Dataset, Box<dyn Error>> {
+    match reader.read_u8()? {
+        0 => st_image::serialize::read(reader),
+        2 => {
+            // stored GeoTIFF bytes of an unmodified tile
+            let len = reader.read_u64::<BigEndian>()?;
+            check_frame_size(len)?;
+            let mut buf = vec![0u8; len as usize];
+            reader.read_exact(&mut buf)?;
+            crate::raster::from_geotiff(buf)
+        },
+        _ => Err(read_string(reader)?.into()),
+    }
+}
+
+fn read_dataset(path_string: &str, subgeocode: &Option<(u8, String)>)
+        -> Result<Dataset, Box<dyn Error>> {
+    // open dataset, whether stored loose or packed
+    let dataset = crate::compaction::open(Path::new(path_string))?;
+
+    // if exists -> split dataset to subgeocode
+    let (geocode_value, subgeocode) = match subgeocode {
+        Some(subgeocode) => subgeocode,
+        None => return Ok(dataset),
+    };
+
+    let geocode: Geocode = match geocode_value {
+        0 => Geocode::Geohash,
+        1 => Geocode::QuadTile,
+        _ => return Err(format!("unknown geocode {}", geocode_value).into()),
+    };
+
+    // split image with geocode precision
+    let precision = subgeocode.len();
+
+    // compute geohash window boundaries for dataset
+    let epsg_code = geocode.get_epsg_code();
+    let (x_interval, y_interval) = geocode.get_intervals(precision);
+
+    let (image_min_cx, image_max_cx, image_min_cy, image_max_cy) =
+        st_image::coordinate::get_bounds(&dataset, epsg_code)?;
+
+    let window_bounds = st_image::coordinate::get_windows(image_min_cx,
+        image_max_cx, image_min_cy, image_max_cy, x_interval, y_interval);
+
+    // iterate over window bounds
+    for (min_cx, max_cx, min_cy, max_cy) in window_bounds {
+        // perform dataset split
+        let split_dataset = match st_image::transform::split(&dataset,
+                min_cx, max_cx, min_cy, max_cy, epsg_code)? {
+            Some(split_dataset) => split_dataset,
+            None => continue,
+        };
+
+        let split_geocode = geocode.encode((min_cx + max_cx) / 2.0,
+            (min_cy + max_cy) / 2.0, precision)?;
+
+        // check if this is the desired geocode
+        if split_geocode.to_lowercase() == subgeocode.to_lowercase() {
+            return Ok(split_dataset);
+        }
+    }
+
+    // failed to split image into subgeocode
+    Err(format!("failed to split image into geocode '{}'",
+        subgeocode).into())
+}
+
+pub fn read_image(replicas: &[(SocketAddr, String)],
+        subgeocode: &Option<(Geocode, String)>,
+        transform: &Option<ReadTransform>, tenant: &Option<String>,
+        tracker: &LatencyTracker) -> Result<Dataset, Box<dyn Error>> {
+    let buf = read_replicas(replicas, subgeocode,
+        transform, tenant, tracker)?;
+    let mut cursor = Cursor::new(buf);
+    let dataset = parse_read_reply(&mut cursor)?;
+
+    // mask to the clip geometry
+    match transform {
+        Some(transform) => transform.mask(dataset),
+        None => Ok(dataset),
+    }
+}
+
+// read a whole tile as GeoTIFF bytes, passing stored files through and
+//   encoding images from nodes which decode them
+pub fn read_geotiff(replicas: &[(SocketAddr, String)],
+        tenant: &Option<String>, tracker: &LatencyTracker)
+        -> Result<Vec<u8>, Box<dyn Error>> {
+    let mut buf = read_replicas(replicas, &None, &None, tenant, tracker)?;
+    if buf.first() != Some(&2) {
+        let dataset = parse_read_reply(&mut Cursor::new(buf))?;
+        return crate::raster::to_geotiff(&dataset);
+    }
+
+    // strip the status and length preceding the stored bytes
+    let len = (&buf[1..]).read_u64::<BigEndian>()?;
+    if len != buf.len() as u64 - 9 {
+        return Err(format!("expected {} GeoTIFF bytes, read {}",
+            len, buf.len() - 9).into());
+    }
+
+    buf.drain(..9);
+    Ok(buf)
+}
+
+fn read_replicas(replicas: &[(SocketAddr, String)],
+        subgeocode: &Option<(Geocode, String)>,
+        transform: &Option<ReadTransform>, tenant: &Option<String>,
+        tracker: &LatencyTracker) -> Result<Vec<u8>, Box<dyn Error>> {
+    let mut span = crate::telemetry::span("transfer.read", SpanKind::Client);
+
+    // attempt replicas in order of observed latency
+    let mut errors = Vec::new();
+    for (addr, path) in tracker.rank(replicas) {
+        let start = Instant::now();
+        match read_image_bytes(&addr, &path, subgeocode, transform, tenant) {
+            Ok(buf) => {
+                tracker.observe(&addr,
+                    start.elapsed().as_secs_f64() * 1000.0);
+                if buf.first() == Some(&0) || buf.first() == Some(&2) {
+                    return Ok(buf);
+                }
+
+                // replica responded but failed to read the image
+                let mut cursor = Cursor::new(buf);
+                if let Err(e) = parse_read_reply(&mut cursor) {
+                    errors.push(format!("{} ({})", addr, e));
+                }
+            },
+            Err(e) => {
+                tracker.observe(&addr, LATENCY_FAILURE_PENALTY_MS);
+                errors.push(format!("{} ({})", addr, e));
+            },
+        }
+    }
+
+    span.set_error(&errors.join(", "));
+    Err(format!("failed to read image from replicas {}",
+        errors.join(", ")).into())
+}
+
+fn read_image_bytes(addr: &SocketAddr, path: &str,
+        subgeocode: &Option<(Geocode, String)>,
+        transform: &Option<ReadTransform>, tenant: &Option<String>)
+        -> Result<Vec<u8>, Box<dyn Error>> {
+    // write request
+    let mut buf = vec![TransferOp::ReadImage as u8];
+    write_string(path, &mut buf)?;
+    match subgeocode {
+        Some((geocode, subgeocode)) => {
+            buf.write_u8(1)?;
+            match geocode {
+                Geocode::Geohash => buf.write_u8(0)?,
+                Geocode::QuadTile => buf.write_u8(1)?,
+                _ => return Err(format!(
+                    "unsupported geocode: {:?}", geocode).into()),
+            }
+            write_string(subgeocode, &mut buf)?;
+        },
+        None => buf.write_u8(0)?,
+    }
+
+    match transform {
+        Some(transform) => {
+            buf.write_u8(1)?;
+            transform.write(&mut buf)?;
+        },
+        None => buf.write_u8(0)?,
+    }
+
+    match tenant {
+        Some(tenant) => {
+            buf.write_u8(1)?;
+            write_string(tenant, &mut buf)?;
+        },
+        None => buf.write_u8(0)?,
+    }
+
+    // accept stored GeoTIFF bytes, unframed requests end at the tenant
+    if !is_unframed(peer_version(addr)) {
+        buf.write_u8(1)?;
+    }
+
+    // read reply
+    transfer(addr, &buf)
+}
+
+unsafe fn raster_io(band: gdal_sys::GDALRasterBandH,
+        flag: gdal_sys::GDALRWFlag::Type, buf: &mut Vec<f64>,
+        x: usize, y: usize) -> Result<(), Box<dyn Error>> {
+    let error = gdal_sys::GDALRasterIO(band, flag, 0, 0, x as c_int,
+        y as c_int, buf.as_mut_ptr() as *mut c_void, x as c_int,
+        y as c_int, gdal_sys::GDALDataType::GDT_Float64, 0, 0);
+
+    match error {
+        gdal_sys::CPLErr::CE_None => Ok(()),
+        _ => Err("failed to transfer raster band data".into()),
+    }
+}
+
+fn read_cloud_coverage<T: Read>(reader: &mut T, dataset: &Dataset)
+        -> Result<(), Box<dyn Error>> {
+    // images from nodes predating cloud masks end after the timestamp
+    let mut flag = [0u8; 1];
+    if reader.read(&mut flag)? == 0 || flag[0] == 0 {
+        return Ok(());
+    }
+
+    let cloud_coverage = reader.read_f64::<BigEndian>()?;
+    dataset.set_metadata_item(crate::mask::CLOUD_COVERAGE_ITEM,
+        &cloud_coverage.to_string(), "STIP")?;
+    Ok(())
+}
+
+pub fn read_string<T: Read>(reader: &mut T)
+        -> Result<String, Box<dyn Error>> {
+    let len = match reader.read_u8()? {
+        STRING_EXTENDED_LENGTH => reader.read_u32::<BigEndian>()?,
+        len => len as u32,
+    };
+
+    check_frame_size(len as u64)?;
+    let mut buf = vec![0u8; len as usize];
+    reader.read_exact(&mut buf)?;
+    Ok(String::from_utf8(buf)?)
+}
+
+pub struct ImageWrite {
+    addrs: Vec<SocketAddr>,
+    buf: Vec<u8>,
+    geocode: String,
+}
+
+impl ImageWrite {
+    pub fn new(addrs: &[SocketAddr], album: &str, dataset: &Dataset,
+            geocode: &str, pixel_coverage: f64, platform: &str,
+            source: &str, subdataset: u8, tile: &str, timestamp: i64)
+            -> Result<ImageWrite, Box<dyn Error>> {
+        // serialize image once for all replicas
+        let mut image = Vec::new();
+        write_image(&mut image, album, dataset, geocode, pixel_coverage,
+            platform, source, subdataset, tile, timestamp)?;
+
+        let mut buf = vec![TransferOp::WriteImage as u8];
+        write_checked(&image, &mut buf)?;
+
+        Ok(ImageWrite {
+            addrs: addrs.to_vec(),
+            buf: buf,
+            geocode: geocode.to_string(),
+        })
+    }
+
+    // checksummed request, or the bare image for nodes predating checksums
+    fn request(&self, addr: &SocketAddr) -> Cow<[u8]> {
+        if request_version(addr) >= CHECKSUM_VERSION {
+            return Cow::Borrowed(&self.buf);
+        }
+
+        let mut buf = vec![TransferOp::WriteImage as u8];
+        buf.extend_from_slice(unchecked(&self.buf[1..]));
+        Cow::Owned(buf)
+    }
+}
+
+pub fn send_image(addrs: &[SocketAddr], album: &str, dataset: &Dataset,
+        geocode: &str, pixel_coverage: f64, platform: &str,
+        source: &str, subdataset: u8, tile: &str, timestamp: i64)
+        -> Result<(), Box<dyn Error>> {
+    let image_write = ImageWrite::new(addrs, album, dataset, geocode,
+        pixel_coverage, platform, source, subdataset, tile, timestamp)?;
+    write_replicas(&image_write, &[])
+}
+
+pub fn send_images(image_writes: &[ImageWrite])
+        -> Vec<Result<(), Box<dyn Error>>> {
+    let mut span = crate::telemetry::span("transfer.send_images",
+        SpanKind::Internal);
+    span.set_attribute("images", &image_writes.len().to_string());
+
+    // group primary replica writes by destination
+    let replication_factor = crate::cluster::replication_factor();
+    let mut destinations: HashMap<SocketAddr, Vec<usize>> = HashMap::new();
+    for (index, image_write) in image_writes.iter().enumerate() {
+        if image_write.buf.len() > TRANSFER_CHUNK_SIZE {
+            continue;
+        }
+
+        for addr in image_write.addrs.iter().take(replication_factor) {
+            destinations.entry(*addr).or_insert(Vec::new()).push(index);
+        }
+    }
+
+    // pipeline writes over a single connection per destination
+    let mut written = vec![Vec::new(); image_writes.len()];
+    for (addr, indices) in destinations.iter() {
+        let requests: Vec<Cow<[u8]>> = indices.iter()
+            .map(|x| image_writes[*x].request(addr)).collect();
+        let requests: Vec<&[u8]> =
+            requests.iter().map(|x| x.as_ref()).collect();
+        throttle(requests.iter().map(|x| x.len()).sum());
+
+        let replies = match pipeline(addr, &requests) {
+            Ok(replies) => replies,
+            Err(e) => {
+                warn!("pipelined writes to {} failed: {}", addr, e);
+                crate::metrics::add(Counter::TransferRetransmits,
+                    indices.len() as u64);
+                continue;
+            },
+        };
+
+        for (index, reply) in indices.iter().zip(replies) {
+            if read_status(&mut Cursor::new(reply)).is_ok() {
+                written[*index].push(*addr);
+            }
+        }
+    }
+
+    // write individually to fill replicas pipelining missed
+    image_writes.iter().zip(written.iter())
+        .map(|(image_write, written)| {
+            match written.len() >=
+                    replication_factor.min(image_write.addrs.len()) {
+                true => Ok(()),
+                false => write_replicas(image_write, written),
+            }
+        }).collect()
+}
+
+fn write_replicas(image_write: &ImageWrite, written: &[SocketAddr])
+        -> Result<(), Box<dyn Error>> {
+    let mut span = crate::telemetry::span("transfer.write", SpanKind::Client);
+    span.set_attribute("geocode", &image_write.geocode);
+
+    // write image to each replica, falling back to later ring nodes
+    let replication_factor = crate::cluster::replication_factor();
+    let mut failed_addrs = Vec::new();
+    let mut written_count = written.len();
+    for addr in image_write.addrs.iter().filter(|x| !written.contains(*x)) {
+        if written_count >= replication_factor {
+            break;
+        }
+
+        // read ack, or nack with the rejection reason, resending
+        //   payloads the node received corrupted
+        let mut attempt = 0;
+        let result = loop {
+            let result = transfer_with_retry(addr,
+                    &image_write.request(addr), true)
+                .and_then(|reply| read_status(&mut Cursor::new(reply)));
+
+            match result {
+                Err(ref e) if attempt < CHECKSUM_RETRY_ATTEMPTS
+                        && e.to_string().starts_with(CHECKSUM_MISMATCH) => {
+                    warn!("resending image '{}' to node {}: {}",
+                        image_write.geocode, addr, e);
+                    attempt += 1;
+                },
+                result => break result,
+            }
+        };
+
+        match result {
+            Ok(_) => written_count += 1,
+            Err(e) => {
+                crate::metrics::add(Counter::TransferFailedSends, 1);
+                failed_addrs.push(format!("{} ({})", addr, e));
+            },
+        }
+    }
+
+    if written_count >= replication_factor.min(image_write.addrs.len()) {
+        if !failed_addrs.is_empty() {
+            warn!("image '{}' placed on fallback nodes, rejected by {}",
+                image_write.geocode, failed_addrs.join(", "));
+        }
+
+        return Ok(());
+    }
+
+    span.set_error(&failed_addrs.join(", "));
+    Err(format!("failed to write image '{}' to nodes {}",
+        image_write.geocode, failed_addrs.join(", ")).into())
+}
+
+pub struct Transaction {
+    id: u64,
+    participants: Mutex<HashSet<SocketAddr>>,
+}
+
+impl Transaction {
+    pub fn new() -> Transaction {
+        Transaction {
+            id: rand::random::<u64>(),
+            participants: Mutex::new(HashSet::new()),
+        }
+    }
+
+    pub fn abort(&self) {
+        debug!("aborting transaction [id={}]", self.id);
+        let participants = self.participants.lock().unwrap();
+        for addr in participants.iter() {
+            if let Err(e) = send_transaction_op(addr,
+                    TransferOp::AbortTransaction, self.id) {
+                warn!("failed to abort transaction {} on node {}: {}",
+                    self.id, addr, e);
+            }
+        }
+    }
+
+    pub fn commit(&self) -> Result<(), Box<dyn Error>> {
+        debug!("committing transaction [id={}]", self.id);
+        let participants = self.participants.lock().unwrap();
+
+        // commit on participants concurrently
+        let (sender, receiver) = crossbeam_channel::unbounded();
+        for addr in participants.iter() {
+            let (addr, id, sender) = (*addr, self.id, sender.clone());
+            std::thread::spawn(move || {
+                let result = send_transaction_op(&addr,
+                        TransferOp::CommitTransaction, id)
+                    .map_err(|e| e.to_string());
+                let _ = sender.send((addr, result));
+            });
+        }
+
+        drop(sender);
+
+        let mut failed_addrs = Vec::new();
+        for (addr, result) in receiver.iter() {
+            if let Err(e) = result {
+                warn!("failed to commit transaction {} on node {}: {}",
+                    self.id, addr, e);
+                failed_addrs.push(addr.to_string());
+            }
+        }
+
+        match failed_addrs.len() {
+            0 => Ok(()),
+            _ => Err(format!("transaction {} failed to commit on nodes {}",
+                self.id, failed_addrs.join(", ")).into()),
+        }
+    }
+
+    pub fn prepare_image(&self, addrs: &[SocketAddr], album: &str,
+            dataset: &Dataset, geocode: &str, pixel_coverage: f64,
+            platform: &str, source: &str, subdataset: u8, tile: &str,
+            timestamp: i64) -> Result<(), Box<dyn Error>> {
+        // serialize image once for all replicas
+        let mut image = Vec::new();
+        write_image(&mut image, album, dataset, geocode, pixel_coverage,
+            platform, source, subdataset, tile, timestamp)?;
+
+        let mut buf = Vec::new();
+        write_checked(&image, &mut buf)?;
+
+        // stage image on every replica, falling back to later ring nodes
+        let replication_factor = crate::cluster::replication_factor();
+        let mut failed_addrs = Vec::new();
+        let mut staged_count = 0;
+        for addr in addrs {
+            if staged_count >= replication_factor {
+                break;
+            }
+
+            // register participant before staging so aborts reach it
+            self.participants.lock().unwrap().insert(addr.clone());
+
+            match stage_image(addr, self.id, &buf) {
+                Ok(_) => staged_count += 1,
+                Err(e) => failed_addrs.push(format!("{} ({})", addr, e)),
+            }
+        }
+
+        if staged_count >= replication_factor.min(addrs.len()) {
+            if !failed_addrs.is_empty() {
+                warn!("image '{}' staged on fallback nodes, rejected by {}",
+                    geocode, failed_addrs.join(", "));
+            }
+
+            return Ok(());
+        }
+
+        Err(format!("failed to stage image '{}' on nodes {}",
+            geocode, failed_addrs.join(", ")).into())
+    }
+}
+
+fn read_status<T: Read>(reader: &mut T) -> Result<(), Box<dyn Error>> {
+    match reader.read_u8()? {
+        0 => Ok(()),
+        _ => Err(read_string(reader)?.into()),
+    }
+}
+
+fn pipeline(addr: &SocketAddr, requests: &[&[u8]])
+        -> Result<Vec<Vec<u8>>, Box<dyn Error>> {
+    let mut span = crate::telemetry::span("transfer.pipeline",
+        SpanKind::Client);
+    span.set_attribute("addr", &addr.to_string());
+    span.set_attribute("requests", &requests.len().to_string());
+
+    let mut connection = match checkout(addr) {
+        Some(connection) => connection,
+        None => Connection::open(addr)?,
+    };
+
+    // nodes on earlier releases serve one request per connection, the
+    //   negotiated connection serves the first
+    if connection.version < PERSISTENT_VERSION {
+        let mut replies = Vec::new();
+        if let Some((request, requests)) = requests.split_first() {
+            connection.write_request(request)?;
+            replies.push(connection.read_reply()?);
+            for request in requests {
+                replies.push(transfer(addr, request)?);
+            }
+        }
+
+        return Ok(replies);
+    }
+
+    // bound requests in flight so replies never fill socket buffers
+    let mut replies = Vec::new();
+    for (index, request) in requests.iter().enumerate() {
+        if index >= PIPELINE_DEPTH {
+            replies.push(connection.read_reply()?);
+        }
+
+        connection.write_request(request)?;
+    }
+
+    while replies.len() < requests.len() {
+        replies.push(connection.read_reply()?);
+    }
+
+    checkin(addr, connection);
+    Ok(replies)
+}
+
+fn read_checked<T: Read>(reader: &mut T)
+        -> Result<Vec<u8>, Box<dyn Error>> {
+    let buf = read_frame(reader)?;
+    let expected = reader.read_u64::<BigEndian>()?;
+
+    // reject truncated or corrupted payloads
+    let computed = checksum(&buf);
+    if computed != expected {
+        return Err(format!("{}, expected {:016x} computed {:016x}",
+            CHECKSUM_MISMATCH, expected, computed).into());
+    }
+
+    Ok(buf)
+}
+
+// payload of a checksummed frame written by write_checked
+fn unchecked(buf: &[u8]) -> &[u8] {
+    &buf[8..buf.len() - 8]
+}
+
+fn read_frame<T: Read>(reader: &mut T) -> Result<Vec<u8>, Box<dyn Error>> {
+    let len = reader.read_u64::<BigEndian>()?;
+    check_frame_size(len)?;
+    let mut buf = vec![0u8; len as usize];
+    reader.read_exact(&mut buf)?;
+    Ok(buf)
+}
+
+// read a reply streamed as frames of at most a chunk, ended by an empty
+//   frame
+fn read_chunked<T: Read>(reader: &mut T, compression: Compression)
+        -> Result<Vec<u8>, Box<dyn Error>> {
+    let mut reply = Vec::new();
+    loop {
+        let frame = read_frame(reader)?;
+        if frame.is_empty() {
+            return Ok(reply);
+        }
+
+        reply.extend_from_slice(&compression.decompress(frame)?);
+        check_frame_size(reply.len() as u64)?;
+    }
+}
+
+fn remove_staged(transaction: StagedTransaction) {
+    for staged_image in transaction.images {
+        if let Err(e) = std::fs::remove_file(&staged_image.path) {
+            warn!("failed to remove staged image {:?}: {}",
+                staged_image.path, e);
+        }
+    }
+}
+
+fn stage_image(addr: &SocketAddr, transaction_id: u64, buf: &[u8])
+        -> Result<(), Box<dyn Error>> {
+    let mut request = vec![TransferOp::PrepareImage as u8];
+    request.write_u64::<BigEndian>(transaction_id)?;
+    match request_version(addr) >= CHECKSUM_VERSION {
+        true => request.extend_from_slice(buf),
+        false => request.extend_from_slice(unchecked(buf)),
+    }
+
+    let reply = transfer_with_retry(addr, &request, true)?;
+    read_status(&mut Cursor::new(reply))
+}
+
+fn send_transaction_op(addr: &SocketAddr, op: TransferOp,
+        transaction_id: u64) -> Result<(), Box<dyn Error>> {
+    let mut request = vec![op as u8];
+    request.write_u64::<BigEndian>(transaction_id)?;
+
+    let reply = transfer_with_retry(addr, &request, false)?;
+    read_status(&mut Cursor::new(reply))
+}
+
+fn backoff(attempt: u32) {
+    std::thread::sleep(Duration::from_millis(
+        TRANSFER_RETRY_BACKOFF_MS * 2u64.pow(attempt)));
+}
+
+// cap image payloads sent by task records, leaving interactive requests
+//   and transaction control unthrottled
+fn throttle(len: usize) {
+    let rate_limit = RATE_LIMIT.load(Ordering::SeqCst);
+    if rate_limit == 0 || !crate::task::pool::is_worker() {
+        return;
+    }
+
+    // reserve the next send window, sharing the cap across threads
+    let delay = {
+        let mut next = RATE_LIMIT_NEXT.lock().unwrap();
+        let now = Instant::now();
+        let start = match *next {
+            Some(next) if next > now => next,
+            _ => now,
+        };
+
+        *next = Some(start + Duration::from_secs_f64(
+            len as f64 / rate_limit as f64));
+        start - now
+    };
+
+    std::thread::sleep(delay);
+}
+
+fn transfer(addr: &SocketAddr, request: &[u8])
+        -> Result<Vec<u8>, Box<dyn Error>> {
+    // reuse a pooled connection, reopening if it has gone stale
+    if let Some(mut connection) = checkout(addr) {
+        let result = connection.write_request(request)
+            .and_then(|_| connection.read_reply());
+        if let Ok(reply) = result {
+            checkin(addr, connection);
+            return Ok(reply);
+        }
+
+        crate::metrics::add(Counter::TransferRetransmits, 1);
+    }
+
+    let mut connection = Connection::open(addr)?;
+    connection.write_request(request)?;
+    let reply = connection.read_reply()?;
+    checkin(addr, connection);
+    Ok(reply)
+}
+
+fn transfer_chunked(addr: &SocketAddr, request: &[u8])
+        -> Result<Vec<u8>, Box<dyn Error>> {
+    let upload_id = rand::random::<u64>();
+    let (mut offset, mut attempt) = (0, 0);
+    while offset < request.len() {
+        let end = (offset + TRANSFER_CHUNK_SIZE).min(request.len());
+        let mut buf = vec![TransferOp::AppendChunk as u8];
+        buf.write_u64::<BigEndian>(upload_id)?;
+        buf.write_u64::<BigEndian>(offset as u64)?;
+        write_frame(&request[offset..end], &mut buf)?;
+
+        throttle(buf.len());
+        let result = transfer(addr, &buf).and_then(|reply| {
+            let mut cursor = Cursor::new(reply);
+            read_status(&mut cursor)?;
+            Ok(cursor.read_u64::<BigEndian>()? as usize)
+        });
+
+        match result {
+            Ok(len) => {
+                offset = len;
+                attempt = 0;
+            },
+            Err(e) if attempt + 1 < TRANSFER_RETRY_ATTEMPTS => {
+                warn!("upload {} to {} failed at offset {}, retrying: {}",
+                    upload_id, addr, offset, e);
+                crate::metrics::add(Counter::TransferRetransmits, 1);
+                backoff(attempt);
+                attempt += 1;
+
+                // resume from the offset the node has received
+                if let Ok(len) = upload_offset(addr, upload_id) {
+                    offset = len;
+                }
+            },
+            Err(e) => return Err(e),
+        }
+    }
+
+    let mut buf = vec![TransferOp::CompleteUpload as u8];
+    buf.write_u64::<BigEndian>(upload_id)?;
+    transfer(addr, &buf)
+}
+
+fn transfer_with_retry(addr: &SocketAddr, request: &[u8], throttled: bool)
+        -> Result<Vec<u8>, Box<dyn Error>> {
+    if request.len() > TRANSFER_CHUNK_SIZE {
+        return transfer_chunked(addr, request);
+    }
+
+    let mut attempt = 0;
+    loop {
+        if throttled {
+            throttle(request.len());
+        }
+
+        match transfer(addr, request) {
+            Ok(reply) => return Ok(reply),
+            Err(e) if attempt + 1 < TRANSFER_RETRY_ATTEMPTS => {
+                warn!("transfer to {} failed, retrying: {}", addr, e);
+                crate::metrics::add(Counter::TransferRetransmits, 1);
+                backoff(attempt);
+                attempt += 1;
+            },
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+fn upload_offset(addr: &SocketAddr, upload_id: u64)
+        -> Result<usize, Box<dyn Error>> {
+    let mut buf = vec![TransferOp::UploadOffset as u8];
+    buf.write_u64::<BigEndian>(upload_id)?;
+
+    let reply = transfer(addr, &buf)?;
+    Ok(Cursor::new(reply).read_u64::<BigEndian>()? as usize)
+}
+
+// reject lengths read from the stream before allocating for them
+fn check_frame_size(len: u64) -> Result<(), Box<dyn Error>> {
+    match len > MAX_FRAME_SIZE {
+        true => Err(format!("frame of {} bytes exceeds maximum of {} bytes",
+            len, MAX_FRAME_SIZE).into()),
+        false => Ok(()),
+    }
+}
+
+fn checkin(addr: &SocketAddr, mut connection: Connection) {
+    if connection.version < PERSISTENT_VERSION {
+        return;
+    }
+
+    let mut pool = POOL.lock().unwrap();
+    if pool.iter().filter(|(x, _)| x == addr).count() >= POOL_MAX_IDLE {
+        return;
+    }
+
+    connection.idle_since = Instant::now();
+    pool.push((*addr, connection));
+}
+
+fn checkout(addr: &SocketAddr) -> Option<Connection> {
+    let mut pool = POOL.lock().unwrap();
+
+    // drop connections nodes may have already closed
+    pool.retain(|(_, x)| x.idle_since.elapsed() < POOL_IDLE_TIMEOUT);
+
+    let index = pool.iter().position(|(x, _)| x == addr)?;
+    Some(pool.swap_remove(index).1)
+}
+
+// transfer protocol version advertised by the node serving addr, 0 if
+//   the node predates advertising it and None if the node is unknown
+fn peer_version(addr: &SocketAddr) -> Option<u8> {
+    let peers = PEERS.read().unwrap();
+    let node = peers.as_ref()?.nodes().into_iter().find(|node| {
+        node.get_ip_address() == &addr.ip() && node.get_metadata("xfer_port")
+            .and_then(|x| x.parse::<u16>().ok()) == Some(addr.port())
+    })?;
+
+    Some(node.get_metadata("xfer_version")
+        .and_then(|x| x.parse::<u8>().ok()).unwrap_or(0))
+}
+
+// protocol version requests to addr are encoded for, assuming unknown
+//   nodes run this release
+fn request_version(addr: &SocketAddr) -> u8 {
+    match peer_version(addr) {
+        Some(version) => version.min(PROTOCOL_VERSION),
+        None => PROTOCOL_VERSION,
+    }
+}
+
+// unauthenticated clusters fall back to unframed requests for nodes
+//   predating versioned streams, authenticated clusters require them
+fn is_unframed(peer_version: Option<u8>) -> bool {
+    peer_version == Some(0) && !crate::auth::is_enabled()
+}
+
+pub fn configure_peers(dht: Arc<Dht>) {
+    *PEERS.write().unwrap() = Some(dht);
+}
+
+fn checksum(buf: &[u8]) -> u64 {
+    let mut hasher = XxHash64::with_seed(0);
+    hasher.write(buf);
+    hasher.finish()
+}
+
+pub fn compression() -> Compression {
+    FromPrimitive::from_u8(COMPRESSION.load(Ordering::SeqCst))
+        .unwrap_or(Compression::None)
+}
+
+pub fn configure_compression(compression: Compression) {
+    COMPRESSION.store(compression as u8, Ordering::SeqCst);
+}
+
+pub fn configure_rate_limit(bytes_per_second: u64) {
+    RATE_LIMIT.store(bytes_per_second, Ordering::SeqCst);
+}
+
+fn write_checked<T: Write>(buf: &[u8], writer: &mut T)
+        -> Result<(), Box<dyn Error>> {
+    write_frame(buf, writer)?;
+    writer.write_u64::<BigEndian>(checksum(buf))?;
+    Ok(())
+}
+
+fn write_frame<T: Write>(buf: &[u8], writer: &mut T)
+        -> Result<(), Box<dyn Error>> {
+    writer.write_u64::<BigEndian>(buf.len() as u64)?;
+    writer.write_all(buf)?;
+    Ok(())
+}
+
+fn write_image<T: Write>(writer: &mut T, album: &str, dataset: &Dataset,
+        geocode: &str, pixel_coverage: f64, platform: &str,
+        source: &str, subdataset: u8, tile: &str, timestamp: i64)
+        -> Result<(), Box<dyn Error>> {
+    write_string(&album, writer)?;
+    st_image::serialize::write(&dataset, writer)?;
+    write_string(&geocode, writer)?;
+    writer.write_f64::<BigEndian>(pixel_coverage)?;
+    write_string(&platform, writer)?;
+    write_string(&source, writer)?;
+    writer.write_u8(subdataset)?;
+    write_string(&tile, writer)?;
+    writer.write_i64::<BigEndian>(timestamp)?;
+
+    // cloud coverage is carried as dataset metadata
+    match crate::mask::cloud_coverage(dataset) {
+        Some(cloud_coverage) => {
+            writer.write_u8(1)?;
+            writer.write_f64::<BigEndian>(cloud_coverage)?;
+        },
+        None => writer.write_u8(0)?,
+    }
+
+    Ok(())
+}
+
+fn write_status<T: Write>(result: Result<(), Box<dyn Error>>,
+        writer: &mut T) -> Result<(), Box<dyn Error>> {
+    match result {
+        Ok(_) => writer.write_u8(0)?,
+        Err(e) => {
+            writer.write_u8(1)?;
+            write_string(&e.to_string(), writer)?;
+        },
+    }
+
+    Ok(())
+}
+
+pub fn write_string<T: Write>(value: &str, writer: &mut T)
+        -> Result<(), Box<dyn Error>> {
+    match value.len() < STRING_EXTENDED_LENGTH as usize {
+        true => writer.write_u8(value.len() as u8)?,
+        false => {
+            if value.len() > std::u32::MAX as usize {
+                return Err("string exceeds maximum length".into());
+            }
+
+            writer.write_u8(STRING_EXTENDED_LENGTH)?;
+            writer.write_u32::<BigEndian>(value.len() as u32)?;
+        },
+    }
+
+    writer.write_all(value.as_bytes())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use byteorder::{BigEndian, WriteBytesExt};
+
+    use super::{Compression, FrameWriter, ReadTransform};
+
+    use std::io::{Cursor, Write};
+
+    #[test]
+    fn frame_round_trip() {
+        let mut buf = Vec::new();
+        super::write_frame(b"image", &mut buf).unwrap();
+        super::write_frame(&[], &mut buf).unwrap();
+
+        let mut cursor = Cursor::new(buf);
+        assert_eq!(super::read_frame(&mut cursor).unwrap(), b"image");
+        assert!(super::read_frame(&mut cursor).unwrap().is_empty());
+
+        // frames ending early or exceeding the maximum size are rejected
+        assert!(super::read_frame(&mut cursor).is_err());
+
+        let mut buf = Vec::new();
+        buf.write_u64::<BigEndian>(super::MAX_FRAME_SIZE + 1).unwrap();
+        assert!(super::read_frame(&mut Cursor::new(buf)).is_err());
+    }
+
+    #[test]
+    fn compression_round_trip() {
+        let buf = vec![7u8; 4096];
+        for compression in &[Compression::None,
+                Compression::Lz4, Compression::Zstd] {
+            let compressed = compression.compress(&buf).unwrap().to_vec();
+            assert_eq!(compression.decompress(compressed).unwrap(), buf);
+        }
+    }
+
+    #[test]
+    fn chunked_reply_round_trip() {
+        // replies span several chunks and end with a partial chunk
+        let reply: Vec<u8> = (0..super::TRANSFER_CHUNK_SIZE * 2 + 7)
+            .map(|x| x as u8).collect();
+        for compression in &[Compression::None, Compression::Lz4] {
+            let mut buf = Vec::new();
+            let mut writer = FrameWriter::new(*compression,
+                super::STREAM_VERSION, &mut buf);
+            std::io::copy(&mut Cursor::new(&reply), &mut writer).unwrap();
+            writer.finish().unwrap();
+
+            let mut cursor = Cursor::new(buf);
+            let read = super::read_chunked(&mut cursor, *compression);
+            assert_eq!(read.unwrap(), reply);
+            assert_eq!(cursor.position(), cursor.get_ref().len() as u64);
+        }
+
+        // earlier releases read the reply as a single frame
+        let mut buf = Vec::new();
+        let mut writer = FrameWriter::new(Compression::None,
+            super::STREAM_VERSION - 1, &mut buf);
+        writer.write_all(b"image").unwrap();
+        writer.finish().unwrap();
+        assert_eq!(super::read_frame(&mut Cursor::new(buf)).unwrap(),
+            b"image");
+    }
+
+    #[test]
+    fn read_transform_round_trip() {
+        let transform = ReadTransform {
+            bands: Some(vec![1, 3]),
+            bounds: Some((-105.0, -104.5, 39.5, 40.0)),
+            data_type: Some("Float32".to_string()),
+            geometry: Some("POINT (-105 40)".to_string()),
+            scale: Some((0.0001, -0.1)),
+        };
+
+        let mut buf = Vec::new();
+        transform.write(&mut buf).unwrap();
+        let read = ReadTransform::read(&mut Cursor::new(buf)).unwrap();
+        assert_eq!(read.bands, transform.bands);
+        assert_eq!(read.bounds, transform.bounds);
+        assert_eq!(read.data_type, transform.data_type);
+        assert_eq!(read.scale, transform.scale);
+
+        // geometries are masked by the reading node and not sent
+        assert_eq!(read.geometry, None);
+
+        // unset fields are read back unset
+        let mut buf = Vec::new();
+        ReadTransform::default().write(&mut buf).unwrap();
+        let read = ReadTransform::read(&mut Cursor::new(buf)).unwrap();
+        assert!(read.bands.is_none() && read.bounds.is_none()
+            && read.data_type.is_none() && read.scale.is_none());
+
+        // band counts that do not fit the count byte are rejected
+        let transform = ReadTransform {
+            bands: Some((0..256).collect()),
+            ..Default::default()
+        };
+        assert!(transform.write(&mut Vec::new()).is_err());
+    }
 }
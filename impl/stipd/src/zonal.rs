@@ -0,0 +1,70 @@
+use gdal::Dataset;
+use serde_json::Value;
+
+use std::error::Error;
+
+pub fn parse_zones(geojson: &str) -> Result<Vec<String>, Box<dyn Error>> {
+    let value: Value = serde_json::from_str(geojson)?;
+
+    // accept feature collections, features, or bare geometries
+    let geometries = match value["type"].as_str() {
+        Some("FeatureCollection") => match value["features"].as_array() {
+            Some(features) => features.iter()
+                .map(|x| x["geometry"].clone()).collect(),
+            None => return Err("feature collection has no features".into()),
+        },
+        Some("Feature") => vec![value["geometry"].clone()],
+        Some(_) => vec![value],
+        None => return Err("geojson object has no type".into()),
+    };
+
+    let mut zones = Vec::new();
+    for geometry in geometries {
+        if !geometry.is_object() {
+            return Err("geojson feature has no geometry".into());
+        }
+
+        zones.push(geometry.to_string());
+    }
+
+    Ok(zones)
+}
+
+// (zone, band, count, sum) of pixels holding data within each zone
+pub fn zonal(dataset: &Dataset, zones: &[String], bands: &[usize])
+        -> Result<Vec<(usize, usize, u64, f64)>, Box<dyn Error>> {
+    // read requested bands, defaulting to every band
+    let bands = match bands.is_empty() {
+        true => (1..crate::raster::band_count(dataset) + 1).collect(),
+        false => bands.to_vec(),
+    };
+
+    let mut band_values = Vec::new();
+    for band in bands.iter() {
+        band_values.push(crate::raster::read_band(dataset, *band)?);
+    }
+
+    let mut results = Vec::new();
+    for (i, zone) in zones.iter().enumerate() {
+        let mask = crate::raster::rasterize(dataset, zone)?;
+        if !mask.iter().any(|x| *x) {
+            continue;
+        }
+
+        for (band, values) in bands.iter().zip(band_values.iter()) {
+            let (mut count, mut sum) = (0u64, 0f64);
+            for (j, _) in mask.iter().enumerate().filter(|(_, x)| **x) {
+                if values.is_valid(j) {
+                    count += 1;
+                    sum += values.values[j];
+                }
+            }
+
+            if count != 0 {
+                results.push((i, *band, count, sum));
+            }
+        }
+    }
+
+    Ok(results)
+}
@@ -0,0 +1,42 @@
+#!/bin/python3
+
+import os
+import shutil
+import tempfile
+
+from setuptools import setup
+from setuptools.command.build_py import build_py
+
+PACKAGE_DIR = os.path.dirname(os.path.realpath(__file__))
+PROTO = os.path.join(PACKAGE_DIR, '..', 'protobuf', 'proto', 'stip.proto')
+
+def generate_stubs():
+    from grpc_tools import protoc
+
+    # compile the proto beneath a 'stippy' directory so generated
+    #   modules import each other from within the package
+    with tempfile.TemporaryDirectory() as proto_dir:
+        os.makedirs(os.path.join(proto_dir, 'stippy'))
+        proto = os.path.join(proto_dir, 'stippy', 'stip.proto')
+        shutil.copy(PROTO, proto)
+
+        result = protoc.main(['grpc_tools.protoc', '-I' + proto_dir,
+            '--python_out=' + PACKAGE_DIR,
+            '--grpc_python_out=' + PACKAGE_DIR, proto])
+        if result != 0:
+            raise RuntimeError('failed to compile ' + PROTO)
+
+class BuildPy(build_py):
+    def run(self):
+        generate_stubs()
+        build_py.run(self)
+
+setup(
+    name='stippy',
+    version='0.1.0',
+    description='python client for the STIP framework',
+    packages=['stippy'],
+    install_requires=['grpcio', 'protobuf'],
+    setup_requires=['grpcio-tools'],
+    cmdclass={'build_py': BuildPy},
+)
@@ -0,0 +1,162 @@
+import grpc
+
+from concurrent.futures import ThreadPoolExecutor
+
+from stippy import stip_pb2
+from stippy import stip_pb2_grpc
+
+# maximum number of nodes queried concurrently, matching the stip cli
+FAN_OUT_LIMIT = 16
+
+class Client:
+    def __init__(self, host_addr, token=None, tls_ca=None, tls_domain=None):
+        self.host_addr = host_addr
+        self.metadata = []
+        if token is not None:
+            self.metadata.append(('authorization', 'Bearer ' + token))
+
+        self.credentials = None
+        self.options = []
+        if tls_ca is not None:
+            with open(tls_ca, 'rb') as f:
+                self.credentials = grpc.ssl_channel_credentials(
+                    root_certificates=f.read())
+
+            if tls_domain is not None:
+                self.options.append(
+                    ('grpc.ssl_target_name_override', tls_domain))
+
+    def channel(self, addr=None):
+        addr = addr or self.host_addr
+        if self.credentials is None:
+            return grpc.insecure_channel(addr, options=self.options)
+
+        return grpc.secure_channel(addr, self.credentials,
+            options=self.options)
+
+    def list_nodes(self):
+        with self.channel() as channel:
+            stub = stip_pb2_grpc.NodeManagementStub(channel)
+            reply = stub.List(stip_pb2.NodeListRequest(),
+                metadata=self.metadata)
+
+        return sorted(reply.nodes, key=lambda node: node.id)
+
+    def list_node_images(self, album, **kwargs):
+        # query every node concurrently
+        request = stip_pb2.ImageListRequest(album=album,
+            filter=_filter(kwargs), statistics=kwargs.get('statistics'),
+            **_geometry(kwargs))
+
+        def query(node):
+            with self.channel(node.rpcAddr) as channel:
+                stub = stip_pb2_grpc.ImageManagementStub(channel)
+                return [(node, image) for image in
+                    stub.List(request, metadata=self.metadata)]
+
+        images = [x for node_images in self._fan_out(query)
+            for x in node_images]
+
+        # merge node results into a deterministic order
+        images.sort(key=lambda x: (x[1].timestamp, x[1].geocode,
+            x[1].platform, x[1].source, x[0].id))
+        return images
+
+    def list_images(self, album, **kwargs):
+        # flatten images into one record per file
+        records = []
+        for (node, image) in self.list_node_images(album, **kwargs):
+            for file in image.files:
+                records.append({
+                    'album': album,
+                    'node': node.id,
+                    'platform': image.platform,
+                    'geocode': image.geocode,
+                    'source': image.source,
+                    'timestamp': image.timestamp,
+                    'pixel_coverage': file.pixelCoverage,
+                    'cloud_coverage': image.cloudCoverage
+                        if image.HasField('cloudCoverage') else None,
+                    'subdataset': file.subdataset,
+                    'path': file.path,
+                })
+
+        return records
+
+    def search_images(self, album, interval=None, **kwargs):
+        # query every node concurrently
+        request = stip_pb2.ImageSearchRequest(album=album,
+            filter=_filter(kwargs), fullGeocode=True,
+            groupByInterval=interval, **_geometry(kwargs))
+
+        def query(node):
+            with self.channel(node.rpcAddr) as channel:
+                stub = stip_pb2_grpc.ImageManagementStub(channel)
+                return list(stub.Search(request, metadata=self.metadata))
+
+        # replicas of a geocode hold the same images, so merge node counts
+        #   of each full geocode before summing them by geocode prefix
+        replicas = {}
+        for extents in self._fan_out(query):
+            for extent in extents:
+                timestamp = extent.timestamp \
+                    if extent.HasField('timestamp') else None
+                key = (extent.platform, extent.geocode, extent.source,
+                    extent.precision, timestamp)
+                replicas[key] = max(replicas.get(key, 0), extent.count)
+
+        length = len(kwargs.get('geocode') or '') + 1
+        counts = {}
+        for (platform, geocode, source, precision, timestamp), count \
+                in replicas.items():
+            key = (platform, geocode[:length], source, precision, timestamp)
+            counts[key] = counts.get(key, 0) + count
+
+        records = []
+        for key in sorted(counts, key=lambda x: x[:4] + (x[4] or 0,)):
+            (platform, geocode, source, precision, timestamp) = key
+            records.append({
+                'platform': platform,
+                'geocode': geocode,
+                'source': source,
+                'precision': precision,
+                'interval': timestamp,
+                'count': counts[key],
+            })
+
+        return records
+
+    def _fan_out(self, query):
+        nodes = self.list_nodes()
+        with ThreadPoolExecutor(max_workers=FAN_OUT_LIMIT) as executor:
+            return list(executor.map(query, nodes))
+
+def list_node_images(host_addr, album, **kwargs):
+    return Client(host_addr).list_node_images(album, **kwargs)
+
+def _filter(kwargs):
+    return stip_pb2.Filter(
+        endTimestamp=kwargs.get('end_timestamp'),
+        geocode=kwargs.get('geocode'),
+        maxCloudCoverage=kwargs.get('max_cloud_coverage'),
+        minPixelCoverage=kwargs.get('min_pixel_coverage'),
+        name=kwargs.get('filter'),
+        platform=kwargs.get('platform'),
+        recurse=kwargs.get('recurse', False),
+        source=kwargs.get('source'),
+        startTimestamp=kwargs.get('start_timestamp'),
+    )
+
+def _geometry(kwargs):
+    # bounds are ordered as (min_lon, max_lon, min_lat, max_lat) like the cli
+    geometry = {'geometry': kwargs.get('geometry')}
+    bounds = kwargs.get('bounds')
+    if bounds is not None:
+        geometry.update({
+            'minLongitude': bounds[0],
+            'maxLongitude': bounds[1],
+            'minLatitude': bounds[2],
+            'maxLatitude': bounds[3],
+        })
+
+    return geometry
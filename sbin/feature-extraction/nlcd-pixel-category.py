@@ -7,7 +7,7 @@ import sys
 
 # import relative 'stippy' python project
 script_dir = os.path.dirname(os.path.realpath(__file__))
-sys.path.append(script_dir + '/../../../stippy/')
+sys.path.append(script_dir + '/../../impl/stippy/')
 import stippy
 
 def process(image):
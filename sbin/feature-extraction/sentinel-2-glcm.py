@@ -10,7 +10,7 @@ import sys
 
 # import realative 'stippy' python project
 script_dir = os.path.dirname(os.path.realpath(__file__))
-sys.path.append(script_dir + '/../../../stippy/')
+sys.path.append(script_dir + '/../../impl/stippy/')
 import stippy
 
 def process(image):
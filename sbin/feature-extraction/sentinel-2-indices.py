@@ -9,7 +9,7 @@ import sys
 
 # import realative 'stippy' python project
 script_dir = os.path.dirname(os.path.realpath(__file__))
-sys.path.append(script_dir + '/../../../stippy/')
+sys.path.append(script_dir + '/../../impl/stippy/')
 import stippy
 
 BANDS = [(0, 1), (0, 2), (0, 3), (0, 4), (1, 5)]